@@ -0,0 +1,403 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use kicad_sexp::{
+    scan::{Child, Children},
+    schema::Schema,
+    ParseError, Sexp,
+};
+
+use crate::Error;
+
+/// A typed conversion failed because the tree did not have the expected shape.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConvertError {
+    /// Tags of the lists enclosing the error, outermost first.
+    pub path: Vec<String>,
+    pub message: String,
+}
+
+impl ConvertError {
+    pub fn new(message: impl Into<String>) -> Self {
+        ConvertError { path: Vec::new(), message: message.into() }
+    }
+
+    /// Records that the error happened inside a list tagged `tag`.
+    pub fn within(mut self, tag: &str) -> Self {
+        self.path.insert(0, tag.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join(" > "))?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+pub type Result<T> = std::result::Result<T, ConvertError>;
+
+/// Types that can be built from a tagged list.
+pub trait FromSexp: Sized {
+    fn from_sexp(list: &List) -> Result<Self>;
+}
+
+/// Parses `src` and converts its single root list, which must be tagged `tag`.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(tag = %tag)))]
+pub fn parse_document<T: FromSexp>(src: &str, tag: &str) -> std::result::Result<T, Error> {
+    #[cfg(feature = "parallel")]
+    let sexps = kicad_sexp::parallel::parse(src).map_err(Error::Parse)?;
+    #[cfg(not(feature = "parallel"))]
+    let sexps = kicad_sexp::parse(src).map_err(Error::Parse)?;
+    let root = sexps
+        .iter()
+        .find(|sexp| matches!(sexp, Sexp::List(_)))
+        .ok_or_else(|| ConvertError::new(format!("expected a ({} ...) root", tag)))?;
+    let list = List::expect(root, tag)?;
+    #[cfg(feature = "tracing")]
+    let _convert = tracing::debug_span!("convert", children = list.lists().count()).entered();
+    Ok(T::from_sexp(&list)?)
+}
+
+/// Fails with [`Error::Version`] if a `(tag ...)` document's `version` is
+/// newer than `newest`. Older versions are read as far as the model goes.
+pub fn check_version(tag: &str, version: u32, newest: u32) -> std::result::Result<(), Error> {
+    if version > newest {
+        return Err(Error::Version { tag: tag.to_string(), found: version, newest });
+    }
+    Ok(())
+}
+
+/// Parses `src` and checks it against `schema`, failing with
+/// [`Error::Validation`] if anything does not match.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(issues)))]
+pub fn validate_document(src: &str, schema: &Schema) -> std::result::Result<(), Error> {
+    let sexps = kicad_sexp::parse(src).map_err(Error::Parse)?;
+    let issues = schema.validate(&sexps);
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("issues", issues.len());
+    if !issues.is_empty() {
+        return Err(Error::Validation(issues));
+    }
+    Ok(())
+}
+
+/// Parses and converts a list found by [`kicad_sexp::scan`] inside a list
+/// tagged `parent`. Parse error spans are relative to the scanned source.
+pub fn parse_child<T: FromSexp>(child: &Child, parent: &str) -> std::result::Result<T, Error> {
+    let offset = |mut e: ParseError| {
+        e.span = e.span.start + child.span.start..e.span.end + child.span.start;
+        e
+    };
+    let sexps = kicad_sexp::parse(child.text).map_err(|errors| Error::Parse(errors.into_iter().map(offset).collect()))?;
+    let list = List::expect(&sexps[0], child.tag).map_err(|e| e.within(parent))?;
+    Ok(T::from_sexp(&list).map_err(|e| e.within(parent))?)
+}
+
+/// Scans the children of the root list, which must be tagged `tag`.
+pub fn scan_document<'a>(src: &'a str, tag: &str) -> Result<Children<'a>> {
+    let children = kicad_sexp::scan::children(src)
+        .ok_or_else(|| ConvertError::new(format!("expected a ({} ...) root", tag)))?;
+    if children.tag() != tag {
+        return Err(ConvertError::new(format!("expected ({} ...), found ({} ...)", tag, children.tag())));
+    }
+    Ok(children)
+}
+
+/// Like [`parse_document`] for only the root's children tagged one of `tags`.
+/// Everything else is skipped without being parsed or validated.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(root_tag = %root_tag, tags = ?tags, items)))]
+pub fn parse_children<T: FromSexp>(src: &str, root_tag: &str, tags: &[&str]) -> std::result::Result<Vec<T>, Error> {
+    let children = scan_document(src, root_tag)?;
+    let children = children.filter(|child| tags.contains(&child.tag));
+    let items: Vec<T> = children.map(|child| parse_child(&child, root_tag)).collect::<std::result::Result<_, _>>()?;
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("items", items.len());
+    Ok(items)
+}
+
+/// Deserializes a value from its [`FromStr`] text, the counterpart of a
+/// `Serialize` impl that uses `collect_str`.
+#[cfg(feature = "serde")]
+pub fn deserialize_from_str<'de, T, D>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+    D: serde::Deserializer<'de>,
+{
+    let text = <Cow<'de, str> as serde::Deserialize>::deserialize(deserializer)?;
+    text.parse().map_err(serde::de::Error::custom)
+}
+
+/// Returns the text of an atom, unescaping string literals, as
+/// [`Sexp::as_str`] does.
+pub fn atom_str<'a>(sexp: &Sexp<'a>) -> Option<Cow<'a, str>> {
+    sexp.as_str()
+}
+
+fn describe(sexp: &Sexp) -> String {
+    match sexp {
+        Sexp::Invalid => "invalid input".to_string(),
+        Sexp::Symbol(s) => format!("symbol `{}`", s),
+        Sexp::StringLiteral(s) => format!("string \"{}\"", s),
+        Sexp::IntLiteral(s) | Sexp::HexIntLiteral(s) | Sexp::FloatLiteral(s) => format!("number {}", s),
+        Sexp::List(_) => "a list".to_string(),
+    }
+}
+
+/// A view of a list whose first element is a symbol tag.
+#[derive(Clone, Copy, Debug)]
+pub struct List<'s, 'a> {
+    tag: &'a str,
+    args: &'s [Sexp<'a>],
+}
+
+impl<'s, 'a> List<'s, 'a> {
+    /// Returns a view of `sexp` if it is a list starting with a symbol.
+    pub fn new(sexp: &'s Sexp<'a>) -> Option<Self> {
+        match sexp {
+            Sexp::List(items) => match items.split_first() {
+                Some((Sexp::Symbol(tag), args)) => Some(List { tag, args }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Like [`List::new`], but also checks the tag.
+    pub fn expect(sexp: &'s Sexp<'a>, tag: &str) -> Result<Self> {
+        match List::new(sexp) {
+            Some(list) if list.tag == tag => Ok(list),
+            Some(list) => Err(ConvertError::new(format!("expected ({} ...), found ({} ...)", tag, list.tag))),
+            None => Err(ConvertError::new(format!("expected ({} ...), found {}", tag, describe(sexp)))),
+        }
+    }
+
+    pub fn tag(&self) -> &'a str {
+        self.tag
+    }
+
+    /// Notes that the model reads this list, see [`crate::coverage`].
+    fn visit(&self) {
+        crate::coverage::visit(self.tag);
+    }
+
+    /// Everything after the tag.
+    pub fn args(&self) -> &'s [Sexp<'a>] {
+        self.visit();
+        self.args
+    }
+
+    pub fn error(&self, message: impl Into<String>) -> ConvertError {
+        ConvertError::new(message).within(self.tag)
+    }
+
+    /// Returns the positional argument at `index`.
+    pub fn arg(&self, index: usize) -> Result<&'s Sexp<'a>> {
+        self.visit();
+        self.args.get(index).ok_or_else(|| self.error(format!("missing argument {}", index + 1)))
+    }
+
+    /// Returns the text of the atom at `index`.
+    pub fn string(&self, index: usize) -> Result<String> {
+        let arg = self.arg(index)?;
+        arg.as_str()
+            .map(Cow::into_owned)
+            .ok_or_else(|| self.error(format!("expected a string, found {}", describe(arg))))
+    }
+
+    /// Returns the symbol at `index`.
+    pub fn symbol(&self, index: usize) -> Result<&'a str> {
+        let arg = self.arg(index)?;
+        arg.as_symbol().ok_or_else(|| self.error(format!("expected a symbol, found {}", describe(arg))))
+    }
+
+    /// Parses the numeric atom at `index`.
+    pub fn number<T: FromStr>(&self, index: usize) -> Result<T> {
+        let arg = self.arg(index)?;
+        match arg {
+            Sexp::IntLiteral(s) | Sexp::FloatLiteral(s) | Sexp::Symbol(s) => s.parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| self.error(format!("expected a number, found {}", describe(arg))))
+    }
+
+    /// Like [`List::number`], but returns `None` if there is no argument at `index`.
+    pub fn opt_number<T: FromStr>(&self, index: usize) -> Result<Option<T>> {
+        self.visit();
+        match self.args.get(index) {
+            Some(Sexp::List(_)) | None => Ok(None),
+            Some(_) => self.number(index).map(Some),
+        }
+    }
+
+    /// Reads a hex literal such as `0x5F3C` or a grouped layer mask, see
+    /// [`Sexp::as_hex`].
+    pub fn hex(&self, index: usize) -> Result<u128> {
+        let arg = self.arg(index)?;
+        arg.as_hex().ok_or_else(|| self.error(format!("expected a hex number, found {}", describe(arg))))
+    }
+
+    /// Iterates over the tagged child lists.
+    pub fn lists(&self) -> impl Iterator<Item = List<'s, 'a>> + use<'s, 'a> {
+        self.visit();
+        self.args.iter().filter_map(List::new)
+    }
+
+    /// Returns the first child list tagged `tag`.
+    pub fn child(&self, tag: &str) -> Option<List<'s, 'a>> {
+        self.lists().find(|list| list.tag == tag).inspect(List::visit)
+    }
+
+    /// Iterates over all child lists tagged `tag`.
+    pub fn children<'t>(&self, tag: &'t str) -> impl Iterator<Item = List<'s, 'a>> + use<'s, 'a, 't> {
+        self.lists().filter(move |list| list.tag == tag).inspect(List::visit)
+    }
+
+    /// Converts the first child tagged `tag`, if present.
+    pub fn parse<T: FromSexp>(&self, tag: &str) -> Result<Option<T>> {
+        self.child(tag)
+            .map(|child| T::from_sexp(&child).map_err(|e| e.within(self.tag)))
+            .transpose()
+    }
+
+    /// Converts the first child tagged `tag`, failing if it is missing.
+    pub fn parse_required<T: FromSexp>(&self, tag: &str) -> Result<T> {
+        self.parse(tag)?.ok_or_else(|| self.error(format!("missing ({} ...)", tag)))
+    }
+
+    /// Converts every child tagged `tag`.
+    pub fn parse_all<T: FromSexp>(&self, tag: &str) -> Result<Vec<T>> {
+        self.children(tag)
+            .map(|child| T::from_sexp(&child).map_err(|e| e.within(self.tag)))
+            .collect()
+    }
+
+    /// Returns the first argument of the child tagged `tag` as text.
+    pub fn child_string(&self, tag: &str) -> Result<Option<String>> {
+        self.child(tag)
+            .map(|child| child.string(0).map_err(|e| e.within(self.tag)))
+            .transpose()
+    }
+
+    /// Returns the first argument of the child tagged `tag` as a number.
+    pub fn child_number<T: FromStr>(&self, tag: &str) -> Result<Option<T>> {
+        self.child(tag)
+            .map(|child| child.number(0).map_err(|e| e.within(self.tag)))
+            .transpose()
+    }
+
+    /// Reads a boolean flag written either as `(name yes|no)`, `(name)` or
+    /// as the bare symbol `name` used by older formats.
+    pub fn opt_flag(&self, name: &str) -> Result<Option<bool>> {
+        self.visit();
+        if self.args.iter().any(|arg| arg.as_symbol() == Some(name)) {
+            return Ok(Some(true));
+        }
+        let Some(child) = self.child(name) else {
+            return Ok(None);
+        };
+        match child.args.first() {
+            None => Ok(Some(true)),
+            Some(Sexp::Symbol("yes" | "true")) => Ok(Some(true)),
+            Some(Sexp::Symbol("no" | "false")) => Ok(Some(false)),
+            Some(other) => Err(child.error(format!("expected yes or no, found {}", describe(other))).within(self.tag)),
+        }
+    }
+
+    /// Like [`List::opt_flag`], defaulting to false.
+    pub fn flag(&self, name: &str) -> Result<bool> {
+        Ok(self.opt_flag(name)?.unwrap_or(false))
+    }
+
+    /// Returns true if the bare symbol `name` appears among the arguments.
+    pub fn has_symbol(&self, name: &str) -> bool {
+        self.visit();
+        self.args.iter().any(|arg| arg.as_symbol() == Some(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Vec<Sexp<'_>> {
+        kicad_sexp::parse(src).unwrap()
+    }
+
+    #[test]
+    fn atoms() {
+        let sexps = parse("(at 1.5 -2 90) (name \"a \\\"b\\\"\\n\") (layer F.Cu)");
+        let at = List::expect(&sexps[0], "at").unwrap();
+        let name = List::new(&sexps[1]).unwrap();
+        let layer = List::new(&sexps[2]).unwrap();
+
+        assert_eq!(at.number::<f64>(0), Ok(1.5));
+        assert_eq!(at.number::<i32>(1), Ok(-2));
+        assert_eq!(at.opt_number::<f64>(3), Ok(None));
+        assert_eq!(name.string(0).unwrap(), "a \"b\"\n");
+        assert_eq!(layer.symbol(0), Ok("F.Cu"));
+        assert_eq!(layer.number::<f64>(0).unwrap_err().to_string(), "layer: expected a number, found symbol `F.Cu`");
+        assert_eq!(List::expect(&sexps[0], "xy").unwrap_err().message, "expected (xy ...), found (at ...)");
+
+        let sexps = parse("(tstamp 0x5F3C 12)");
+        let tstamp = List::new(&sexps[0]).unwrap();
+        assert_eq!(tstamp.hex(0), Ok(0x5f3c));
+        assert_eq!(tstamp.hex(1).unwrap_err().to_string(), "tstamp: expected a hex number, found number 12");
+    }
+
+    #[test]
+    fn flags() {
+        let sexps = parse("(effects (font (bold yes) (italic no)) (hide) mirror (justify (x)))");
+        let effects = List::new(&sexps[0]).unwrap();
+        let font = effects.child("font").unwrap();
+
+        assert_eq!(font.flag("bold"), Ok(true));
+        assert_eq!(font.opt_flag("italic"), Ok(Some(false)));
+        assert_eq!(font.opt_flag("missing"), Ok(None));
+        assert_eq!(effects.flag("hide"), Ok(true));
+        assert_eq!(effects.flag("mirror"), Ok(true));
+        assert!(effects.flag("justify").is_err());
+    }
+
+    struct At(f64, f64);
+
+    impl FromSexp for At {
+        fn from_sexp(list: &List) -> Result<Self> {
+            Ok(At(list.number(0)?, list.number(1)?))
+        }
+    }
+
+    #[test]
+    fn nested_errors() {
+        let sexps = parse("(symbol (at 1 2) (at 3 x))");
+        let symbol = List::new(&sexps[0]).unwrap();
+
+        assert_eq!(symbol.parse::<At>("at").unwrap().map(|at| at.0 + at.1), Some(3.0));
+        assert_eq!(
+            symbol.parse_all::<At>("at").err().unwrap().to_string(),
+            "symbol > at: expected a number, found symbol `x`"
+        );
+        assert_eq!(symbol.parse_required::<At>("xy").err().unwrap().to_string(), "symbol: missing (xy ...)");
+    }
+
+    #[test]
+    fn documents() {
+        let empty_sch_file = include_str!("../../reference-files/empty/empty.kicad_sch");
+
+        struct Root(u32);
+        impl FromSexp for Root {
+            fn from_sexp(list: &List) -> Result<Self> {
+                Ok(Root(list.child_number("version")?.unwrap_or_default()))
+            }
+        }
+
+        assert_eq!(parse_document::<Root>(empty_sch_file, "kicad_sch").unwrap().0, 20250114);
+        assert!(matches!(parse_document::<Root>(empty_sch_file, "kicad_pcb"), Err(Error::Convert(_))));
+        assert!(matches!(parse_document::<Root>("(kicad_sch", "kicad_sch"), Err(Error::Parse(_))));
+    }
+}
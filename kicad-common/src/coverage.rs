@@ -0,0 +1,108 @@
+//! Finds the lists a typed model skips, to guide format coverage work.
+//!
+//! While [`record`] runs a conversion, every [`List`](crate::List) the model
+//! reads from (rather than only checking the tag of) is noted. Lists are
+//! identified by the address of their tag, so the conversion and the tree
+//! passed to [`Visited::unhandled`] must be parsed from the same source
+//! buffer.
+
+use std::{cell::RefCell, collections::HashSet};
+
+use kicad_sexp::Sexp;
+
+thread_local! {
+    static VISITED: RefCell<Option<HashSet<usize>>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn visit(tag: &str) {
+    VISITED.with_borrow_mut(|visited| {
+        if let Some(visited) = visited {
+            visited.insert(tag.as_ptr() as usize);
+        }
+    });
+}
+
+/// The lists read during a [`record`] call.
+#[derive(Clone, Debug, Default)]
+pub struct Visited(HashSet<usize>);
+
+/// Runs `convert`, noting the lists it reads on this thread.
+pub fn record<T>(convert: impl FnOnce() -> T) -> (T, Visited) {
+    let outer = VISITED.replace(Some(HashSet::new()));
+    let value = convert();
+    let visited = VISITED.replace(outer).unwrap_or_default();
+    (value, Visited(visited))
+}
+
+fn list_tag<'s, 'a>(sexp: &'s Sexp<'a>) -> Option<(&'a str, &'s [Sexp<'a>])> {
+    match sexp {
+        Sexp::List(items) => match items.split_first() {
+            Some((Sexp::Symbol(tag), args)) => Some((tag, args)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Visited {
+    /// Tag paths of the outermost lists in `sexps` that were not read, one
+    /// per occurrence in document order, e.g. `kicad_pcb > setup > stackup`.
+    pub fn unhandled(&self, sexps: &[Sexp]) -> Vec<String> {
+        let mut paths = Vec::new();
+        let mut path = Vec::new();
+        for sexp in sexps {
+            self.walk(sexp, &mut path, &mut paths);
+        }
+        paths
+    }
+
+    fn walk<'a>(&self, sexp: &Sexp<'a>, path: &mut Vec<&'a str>, paths: &mut Vec<String>) {
+        let Some((tag, args)) = list_tag(sexp) else {
+            return;
+        };
+        path.push(tag);
+        if self.0.contains(&(tag.as_ptr() as usize)) {
+            for arg in args {
+                self.walk(arg, path, paths);
+            }
+        } else {
+            paths.push(path.join(" > "));
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromSexp, List, convert::Result};
+
+    struct Setup(Option<f64>);
+
+    impl FromSexp for Setup {
+        fn from_sexp(list: &List) -> Result<Self> {
+            for item in list.lists() {
+                if item.tag() == "grid" {
+                    item.number::<f64>(0)?;
+                }
+            }
+            Ok(Setup(list.child("general").map(|g| g.child_number("thickness")).transpose()?.flatten()))
+        }
+    }
+
+    #[test]
+    fn unhandled() {
+        let src = "(setup (general (thickness 1.6) (legacy_teardrops no)) (grid 1) (grid 2) \
+            (stackup (layer x)) (hide))";
+        let (setup, visited) = record(|| crate::convert::parse_document::<Setup>(src, "setup").unwrap());
+        assert_eq!(setup.0, Some(1.6));
+        assert_eq!(
+            visited.unhandled(&kicad_sexp::parse(src).unwrap()),
+            ["setup > general > legacy_teardrops", "setup > stackup", "setup > hide"]
+        );
+
+        // Nothing is noted outside of `record`.
+        crate::convert::parse_document::<Setup>(src, "setup").unwrap();
+        assert!(VISITED.with_borrow(Option::is_none));
+    }
+}
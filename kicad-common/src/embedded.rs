@@ -0,0 +1,604 @@
+//! Files KiCad 9 embeds in boards, schematics, footprints and symbols, as in
+//! `(embedded_files (file (name "a.pdf") (type datasheet) (data |KLUv...|)
+//! (checksum "...")))`.
+//!
+//! The stored data is the zstd-compressed file in base64, and the checksum
+//! is KiCad's MurmurHash3 of the uncompressed contents. Compression is left
+//! to a [`Codec`]: [`Stored`] reads and writes uncompressed frames, while
+//! the compressed files KiCad writes need a full zstd decoder, such as
+//! [`Zstd`] with the `zstd` feature.
+
+use std::{borrow::Cow, fmt, io};
+
+use kicad_sexp::{
+    cst::{Document, Node},
+    schema::Issue,
+    SexpOwned,
+};
+
+use crate::convert::{FromSexp, List, Result};
+
+/// What an embedded file is used for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileKind {
+    Datasheet,
+    Font,
+    Model,
+    Worksheet,
+    #[default]
+    Other,
+}
+
+impl FileKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileKind::Datasheet => "datasheet",
+            FileKind::Font => "font",
+            FileKind::Model => "model",
+            FileKind::Worksheet => "worksheet",
+            FileKind::Other => "other",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmbeddedFile {
+    pub name: String,
+    pub kind: FileKind,
+    /// The compressed contents in base64, without the `|` markers and line
+    /// breaks of the file.
+    pub data: String,
+    pub checksum: Option<String>,
+}
+
+/// Compresses and decompresses the contents of embedded files, which KiCad
+/// stores as zstd frames.
+pub trait Codec {
+    fn compress(&self, contents: &[u8]) -> io::Result<Vec<u8>>;
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// Writes zstd frames of uncompressed blocks, which any zstd decoder reads,
+/// and reads frames without compressed blocks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stored;
+
+/// Reads any zstd frame with [`ruzstd`], checking content checksums, and
+/// writes frames like [`Stored`].
+#[cfg(feature = "zstd")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Zstd;
+
+/// How an embedded file's contents compare to its checksum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Integrity {
+    Valid,
+    Mismatch { stored: String, actual: String },
+    /// The file has no checksum to compare with.
+    Unchecked { actual: String },
+    /// The data could not be decoded.
+    Unreadable(String),
+}
+
+impl fmt::Display for Integrity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integrity::Valid => write!(f, "checksum matches"),
+            Integrity::Mismatch { stored, actual } => {
+                write!(f, "stored checksum {} does not match the contents, {}", stored, actual)
+            },
+            Integrity::Unchecked { .. } => write!(f, "no checksum stored"),
+            Integrity::Unreadable(message) => write!(f, "data cannot be read: {}", message),
+        }
+    }
+}
+
+impl FromSexp for EmbeddedFile {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let kind = match list.child("type") {
+            Some(kind) => match kind.symbol(0)? {
+                "datasheet" => FileKind::Datasheet,
+                "font" => FileKind::Font,
+                "model" => FileKind::Model,
+                "worksheet" => FileKind::Worksheet,
+                _ => FileKind::Other,
+            },
+            None => FileKind::Other,
+        };
+        let data = match list.child("data") {
+            Some(data) => join_data(data.args().iter().filter_map(|arg| arg.as_str())),
+            None => String::new(),
+        };
+        Ok(EmbeddedFile {
+            name: list.child_string("name")?.ok_or_else(|| list.error("missing (name ...)"))?,
+            kind,
+            data,
+            checksum: list.child_string("checksum")?,
+        })
+    }
+}
+
+/// Characters per line of data, as KiCad writes it.
+const LINE: usize = 76;
+
+fn join_data<'a>(tokens: impl Iterator<Item = Cow<'a, str>>) -> String {
+    tokens.map(|token| token.trim_matches('|').to_string()).collect()
+}
+
+impl EmbeddedFile {
+    /// A file holding `contents`, compressed by `codec` and with its
+    /// checksum calculated.
+    pub fn new(name: impl Into<String>, kind: FileKind, contents: &[u8], codec: &dyn Codec) -> io::Result<Self> {
+        Ok(EmbeddedFile {
+            name: name.into(),
+            kind,
+            data: encode_base64(&codec.compress(contents)?),
+            checksum: Some(checksum(contents)),
+        })
+    }
+
+    /// The decompressed contents, without checking them.
+    pub fn contents(&self, codec: &dyn Codec) -> io::Result<Vec<u8>> {
+        codec.decompress(&decode_base64(&self.data)?)
+    }
+
+    /// The contents and how they compare to the stored checksum.
+    pub fn extract(&self, codec: &dyn Codec) -> io::Result<(Vec<u8>, Integrity)> {
+        let contents = self.contents(codec)?;
+        let actual = checksum(&contents);
+        let integrity = match &self.checksum {
+            Some(stored) if stored.eq_ignore_ascii_case(&actual) => Integrity::Valid,
+            Some(stored) => Integrity::Mismatch { stored: stored.clone(), actual },
+            None => Integrity::Unchecked { actual },
+        };
+        Ok((contents, integrity))
+    }
+
+    pub fn integrity(&self, codec: &dyn Codec) -> Integrity {
+        self.extract(codec).map_or_else(|e| Integrity::Unreadable(e.to_string()), |(_, integrity)| integrity)
+    }
+
+    /// Recalculates the stored checksum from the contents.
+    pub fn update_checksum(&mut self, codec: &dyn Codec) -> io::Result<()> {
+        self.checksum = Some(checksum(&self.contents(codec)?));
+        Ok(())
+    }
+
+    /// The `(file ...)` list, with the data split into lines as KiCad
+    /// writes it.
+    pub fn to_sexp(&self) -> SexpOwned {
+        let tagged = |tag: &str, args: Vec<SexpOwned>| {
+            SexpOwned::List(std::iter::once(SexpOwned::symbol(tag)).chain(args).collect())
+        };
+        let lines = self.data.as_bytes().chunks(LINE).map(|line| String::from_utf8_lossy(line).into_owned());
+        let mut lines: Vec<String> = lines.collect();
+        if let Some(first) = lines.first_mut() {
+            first.insert(0, '|');
+        }
+        if let Some(last) = lines.last_mut() {
+            last.push('|');
+        }
+        let mut items = vec![
+            tagged("name", vec![SexpOwned::string(&self.name)]),
+            tagged("type", vec![SexpOwned::symbol(self.kind.as_str())]),
+            tagged("data", lines.into_iter().map(SexpOwned::Symbol).collect()),
+        ];
+        if let Some(checksum) = &self.checksum {
+            items.push(tagged("checksum", vec![SexpOwned::string(checksum)]));
+        }
+        tagged("file", items)
+    }
+}
+
+/// A warning for each file whose contents do not match its checksum or
+/// cannot be read, at `parent`, the path of the list holding the files.
+pub fn verify(files: &[EmbeddedFile], codec: &dyn Codec, parent: &[&str]) -> Vec<Issue> {
+    let path: Vec<String> = parent.iter().chain(&["embedded_files", "file"]).map(|tag| tag.to_string()).collect();
+    files
+        .iter()
+        .filter_map(|file| match file.integrity(codec) {
+            Integrity::Valid | Integrity::Unchecked { .. } => None,
+            integrity => Some(Issue {
+                path: path.clone(),
+                message: format!("embedded file {:?}: {}", file.name, integrity),
+            }),
+        })
+        .collect()
+}
+
+/// Rewrites the checksum of every embedded file in `doc` from its
+/// contents, adding checksums that are missing. Files whose data cannot be
+/// read are left alone. Returns how many checksums changed.
+pub fn update_checksums(doc: &mut Document, codec: &dyn Codec) -> io::Result<usize> {
+    let mut changed = 0;
+    for node in &mut doc.nodes {
+        update_node(node, codec, &mut changed)?;
+    }
+    Ok(changed)
+}
+
+fn update_node(node: &mut Node, codec: &dyn Codec, changed: &mut usize) -> io::Result<()> {
+    if node.tag() != Some("embedded_files") {
+        for item in node.items_mut().into_iter().flatten() {
+            update_node(item, codec, changed)?;
+        }
+        return Ok(());
+    }
+    for file in node.items_mut().into_iter().flatten().filter(|item| item.tag() == Some("file")) {
+        let Some(data) = file.child("data") else {
+            continue;
+        };
+        let data = join_data(data.items().iter().skip(1).filter_map(Node::value));
+        let Ok(contents) = decode_base64(&data).and_then(|data| codec.decompress(&data)) else {
+            continue;
+        };
+        let actual = checksum(&contents);
+        match file.child_mut("checksum").and_then(|node| node.items_mut()).and_then(|items| items.get_mut(1)) {
+            Some(stored) if stored.value().is_some_and(|value| value == actual) => {},
+            Some(stored) => {
+                stored.set_string(&actual);
+                *changed += 1;
+            },
+            None => {
+                let Some(items) = file.items_mut() else { continue };
+                let at = items.iter().position(|item| item.tag() == Some("data")).map_or(items.len(), |i| i + 1);
+                let mut node = Node::tagged("checksum", vec![Node::string(&actual)]);
+                node.leading = items[at - 1].leading.clone();
+                items.insert(at, node);
+                *changed += 1;
+            },
+        }
+    }
+    Ok(())
+}
+
+/// KiCad's checksum of embedded contents: MurmurHash3 x64 128 with seed
+/// `0xABBA2345`, written as four 32-bit words in upper case hex, low word
+/// of each half first.
+pub fn checksum(contents: &[u8]) -> String {
+    let (h1, h2) = murmur3_x64_128(contents, 0xABBA_2345);
+    format!("{:08X}{:08X}{:08X}{:08X}", h1 as u32, (h1 >> 32) as u32, h2 as u32, (h2 >> 32) as u32)
+}
+
+fn murmur3_x64_128(data: &[u8], seed: u32) -> (u64, u64) {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+    let fmix = |mut k: u64| {
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        k ^ (k >> 33)
+    };
+    let mix1 = |k: u64| k.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    let mix2 = |k: u64| k.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+    let (mut h1, mut h2) = (u64::from(seed), u64::from(seed));
+
+    let mut blocks = data.chunks_exact(16);
+    for block in blocks.by_ref() {
+        let k1 = u64::from_le_bytes(block[..8].try_into().unwrap_or_default());
+        let k2 = u64::from_le_bytes(block[8..].try_into().unwrap_or_default());
+        h1 ^= mix1(k1);
+        h1 = h1.rotate_left(27).wrapping_add(h2).wrapping_mul(5).wrapping_add(0x52dc_e729);
+        h2 ^= mix2(k2);
+        h2 = h2.rotate_left(31).wrapping_add(h1).wrapping_mul(5).wrapping_add(0x3849_5ab5);
+    }
+    let tail = blocks.remainder();
+    let word = |bytes: &[u8]| bytes.iter().rev().fold(0u64, |k, &b| (k << 8) | u64::from(b));
+    if tail.len() > 8 {
+        h2 ^= mix2(word(&tail[8..]));
+    }
+    if !tail.is_empty() {
+        h1 ^= mix1(word(&tail[..tail.len().min(8)]));
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix(h1);
+    h2 = fmix(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    (h1, h2)
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (u32::from(b) << (16 - 8 * i)));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(BASE64[(n >> (18 - 6 * i)) as usize & 63] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+/// Decodes base64, ignoring whitespace.
+fn decode_base64(text: &str) -> io::Result<Vec<u8>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let text: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !text.len().is_multiple_of(4) {
+        return Err(invalid("base64 data is not a whole number of groups"));
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for (group, chunk) in text.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&b| b == b'=').count();
+        if padding > 2 || (padding > 0 && (group + 1) * 4 != text.len()) {
+            return Err(invalid("misplaced base64 padding"));
+        }
+        let mut n = 0u32;
+        for &b in &chunk[..4 - padding] {
+            let value = BASE64.iter().position(|&c| c == b).ok_or_else(|| invalid("invalid base64 character"))?;
+            n = (n << 6) | value as u32;
+        }
+        n <<= 6 * padding;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(out)
+}
+
+/// Splits the first `n` bytes off `rest`.
+fn take<'d>(rest: &mut &'d [u8], n: usize) -> io::Result<&'d [u8]> {
+    let (head, tail) = rest
+        .split_at_checked(n)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "zstd frame is truncated"))?;
+    *rest = tail;
+    Ok(head)
+}
+
+const ZSTD_MAGIC: u32 = 0xFD2F_B528;
+/// Largest block a zstd frame may hold.
+const ZSTD_BLOCK: usize = 128 * 1024;
+
+impl Codec for Stored {
+    fn compress(&self, contents: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(contents.len() + 16);
+        out.extend_from_slice(&ZSTD_MAGIC.to_le_bytes());
+        // Single segment with an 8 byte content size.
+        out.push(0xE0);
+        out.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+        let mut blocks = contents.chunks(ZSTD_BLOCK).peekable();
+        if blocks.peek().is_none() {
+            out.extend_from_slice(&[1, 0, 0]);
+        }
+        while let Some(block) = blocks.next() {
+            let header = ((block.len() as u32) << 3) | u32::from(blocks.peek().is_none());
+            out.extend_from_slice(&header.to_le_bytes()[..3]);
+            out.extend_from_slice(block);
+        }
+        Ok(out)
+    }
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut out = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            let magic = u32::from_le_bytes(take(&mut rest, 4)?.try_into().unwrap_or_default());
+            if magic & 0xFFFF_FFF0 == 0x184D_2A50 {
+                let size = u32::from_le_bytes(take(&mut rest, 4)?.try_into().unwrap_or_default());
+                take(&mut rest, size as usize)?;
+                continue;
+            }
+            if magic != ZSTD_MAGIC {
+                return Err(invalid("not a zstd frame"));
+            }
+            let descriptor = take(&mut rest, 1)?[0];
+            let single_segment = descriptor & 0x20 != 0;
+            let window = usize::from(!single_segment);
+            let dictionary = [0, 1, 2, 4][usize::from(descriptor & 3)];
+            let content_size = match descriptor >> 6 {
+                0 => usize::from(single_segment),
+                flag => 1 << flag,
+            };
+            take(&mut rest, window + dictionary + content_size)?;
+            loop {
+                let header = take(&mut rest, 3)?;
+                let header = u32::from(header[0]) | (u32::from(header[1]) << 8) | (u32::from(header[2]) << 16);
+                let size = (header >> 3) as usize;
+                match (header >> 1) & 3 {
+                    0 => out.extend_from_slice(take(&mut rest, size)?),
+                    1 => out.extend(std::iter::repeat_n(take(&mut rest, 1)?[0], size)),
+                    2 => return Err(invalid("compressed zstd blocks need a full zstd decoder, see the zstd feature")),
+                    _ => return Err(invalid("reserved zstd block type")),
+                }
+                if header & 1 != 0 {
+                    break;
+                }
+            }
+            if descriptor & 4 != 0 {
+                take(&mut rest, 4)?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Codec for Zstd {
+    fn compress(&self, contents: &[u8]) -> io::Result<Vec<u8>> {
+        Stored.compress(contents)
+    }
+
+    /// Decodes every frame in `data`, skipping skippable frames.
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        use ruzstd::decoding::{
+            errors::{FrameDecoderError, ReadFrameHeaderError},
+            BlockDecodingStrategy, FrameDecoder,
+        };
+
+        let invalid = |e: FrameDecoderError| io::Error::new(io::ErrorKind::InvalidData, format!("zstd: {}", e));
+        let mut decoder = FrameDecoder::new();
+        let mut out = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            match decoder.reset(&mut rest) {
+                Ok(()) => {},
+                // The header of a skippable frame has been read; its length is that of the rest.
+                Err(FrameDecoderError::ReadFrameHeaderError(ReadFrameHeaderError::SkipFrame { length, .. })) => {
+                    rest = rest.get(length as usize..).ok_or_else(|| invalid(FrameDecoderError::FailedToSkipFrame))?;
+                    continue;
+                },
+                Err(e) => return Err(invalid(e)),
+            }
+            while !decoder.is_finished() {
+                decoder.decode_blocks(&mut rest, BlockDecodingStrategy::UptoBytes(1 << 20)).map_err(invalid)?;
+                decoder.collect_to_writer(&mut out)?;
+            }
+            decoder.collect_to_writer(&mut out)?;
+            let checksums = (decoder.get_checksum_from_data(), decoder.get_calculated_checksum());
+            if let (Some(stored), Some(actual)) = checksums
+                && stored != actual
+            {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "zstd: content checksum does not match"));
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn murmur3() {
+        let hex = |(h1, h2): (u64, u64)| format!("{:016x}{:016x}", h1, h2);
+        assert_eq!(hex(murmur3_x64_128(b"", 0)), "0".repeat(32));
+        assert_eq!(hex(murmur3_x64_128(b"hello", 0)), "cbd8a7b341bd9b025b1e906a48ae1d19");
+        assert_eq!(
+            hex(murmur3_x64_128(b"The quick brown fox jumps over the lazy dog", 0)),
+            "e34bbc7bbc071b6c7a433ca9c49a9347"
+        );
+        assert_eq!(checksum(b"").len(), 32);
+    }
+
+    #[test]
+    fn base64() {
+        for (bytes, text) in [(&b""[..], ""), (b"f", "Zg=="), (b"fo", "Zm8="), (b"foo", "Zm9v"), (b"foob", "Zm9vYg==")] {
+            assert_eq!(encode_base64(bytes), text);
+            assert_eq!(decode_base64(text).unwrap(), bytes);
+        }
+        assert_eq!(decode_base64("Zm9v\n\tYg==").unwrap(), b"foob");
+        for text in ["Zm9", "Zm=v", "Zg==Zg==", "Z!==", "Z==="] {
+            assert!(decode_base64(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn stored_frames() {
+        let contents: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        for contents in [&[][..], b"abc", &contents] {
+            let frame = Stored.compress(contents).unwrap();
+            assert_eq!(Stored.decompress(&frame).unwrap(), contents);
+        }
+        // An RLE block of five 'x' after a skippable frame.
+        let frame = [&[0x50, 0x2A, 0x4D, 0x18, 1, 0, 0, 0, 9][..], &[0x28, 0xB5, 0x2F, 0xFD, 0x20, 5, 0x2B, 0, 0, b'x']];
+        assert_eq!(Stored.decompress(&frame.concat()).unwrap(), b"xxxxx");
+        let compressed = [0x28, 0xB5, 0x2F, 0xFD, 0x20, 5, 0x2D, 0, 0];
+        assert!(Stored.decompress(&compressed).unwrap_err().to_string().contains("full zstd decoder"));
+        assert!(Stored.decompress(&[0x28, 0xB5]).is_err());
+    }
+
+    /// Frames written by the reference zstd encoder at several levels, one
+    /// spanning several blocks and carrying a content checksum.
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_frames() {
+        let src = include_str!("../../reference-files/embedded/embedded.kicad_pcb");
+        let sexps = kicad_sexp::parse(src).unwrap();
+        let root = List::new(&sexps[0]).unwrap();
+        let files: Vec<EmbeddedFile> = root.child("embedded_files").unwrap().parse_all("file").unwrap();
+        let expected = [
+            include_str!("../../reference-files/demo/demo.kicad_wks").to_string(),
+            include_str!("../../reference-files/demo/demo.kicad_sch").to_string(),
+            include_str!("../../reference-files/demo/demo.kicad_sym").to_string(),
+            include_str!("../../reference-files/demo/demo.kicad_pcb").repeat(8),
+        ];
+        assert_eq!(files.len(), expected.len());
+        for (file, expected) in files.iter().zip(expected) {
+            assert_eq!(file.extract(&Zstd).unwrap(), (expected.into_bytes(), Integrity::Valid), "{}", file.name);
+            assert!(matches!(file.integrity(&Stored), Integrity::Unreadable(_)));
+        }
+        assert!(verify(&files, &Zstd, &["kicad_pcb"]).is_empty());
+
+        // Damaged frames fail without panicking.
+        let frame = decode_base64(&files[1].data).unwrap();
+        for end in 1..frame.len() {
+            assert!(Zstd.decompress(&frame[..end]).is_err(), "{}", end);
+        }
+        for i in 4..frame.len() {
+            for bit in [0x01, 0x80] {
+                let mut damaged = frame.clone();
+                damaged[i] ^= bit;
+                let _ = Zstd.decompress(&damaged);
+            }
+        }
+        let mut damaged = decode_base64(&files[3].data).unwrap();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert!(Zstd.decompress(&damaged).unwrap_err().to_string().contains("checksum does not match"));
+        let stored = Stored.compress(b"stored").unwrap();
+        assert_eq!(Zstd.decompress(&stored).unwrap(), b"stored");
+    }
+
+    #[test]
+    fn files() {
+        let contents = b"%PDF-1.4 not really".repeat(10);
+        let file = EmbeddedFile::new("R.pdf", FileKind::Datasheet, &contents, &Stored).unwrap();
+        assert_eq!(file.extract(&Stored).unwrap(), (contents.clone(), Integrity::Valid));
+
+        let text = kicad_sexp::writer::to_string(&[file.to_sexp().as_sexp()]);
+        assert!(text.contains("(data |KLUv/") && text.contains("=|\n"), "{}", text);
+        let sexps = kicad_sexp::parse(&text).unwrap();
+        let parsed = EmbeddedFile::from_sexp(&List::new(&sexps[0]).unwrap()).unwrap();
+        assert_eq!(parsed, file);
+
+        let mut broken = file.clone();
+        broken.checksum = Some("00000000000000000000000000000000".into());
+        let issues = verify(&[file.clone(), broken.clone()], &Stored, &["kicad_pcb"]);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.starts_with("embedded file \"R.pdf\": stored checksum 0000"), "{}", issues[0].message);
+        broken.update_checksum(&Stored).unwrap();
+        assert_eq!(broken, file);
+
+        let unreadable = EmbeddedFile { data: "@@".into(), ..file.clone() };
+        assert!(matches!(unreadable.integrity(&Stored), Integrity::Unreadable(_)));
+        let unchecked = EmbeddedFile { checksum: None, ..file };
+        assert!(matches!(unchecked.integrity(&Stored), Integrity::Unchecked { .. }));
+    }
+
+    #[test]
+    fn document_checksums() {
+        let file = EmbeddedFile::new("a.txt", FileKind::Other, b"hello", &Stored).unwrap();
+        let checksum = file.checksum.clone().unwrap();
+        let src = format!(
+            "(kicad_pcb (version 20241229)\n\t(embedded_files\n\t\t(file\n\t\t\t(name \"a.txt\")\n\t\t\t(type other)\n\
+             \t\t\t(data |{data}|)\n\t\t\t(checksum \"FFFF\")\n\t\t)\n\t)\n\t(footprint \"R\"\n\t\t(embedded_files\n\
+             \t\t\t(file\n\t\t\t\t(name \"b.txt\")\n\t\t\t\t(data\n\t\t\t\t\t|{data}|\n\t\t\t\t)\n\t\t\t)\n\t\t)\n\t)\n)\n",
+            data = file.data
+        );
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(update_checksums(&mut doc, &Stored).unwrap(), 2);
+        let updated = doc.to_string();
+        assert_eq!(
+            updated,
+            src.replacen("FFFF", &checksum, 1)
+                .replacen("\t\t\t\t)\n\t\t\t)", &format!("\t\t\t\t)\n\t\t\t\t(checksum \"{}\")\n\t\t\t)", checksum), 1)
+        );
+        let mut doc = kicad_sexp::cst::parse(&updated).unwrap();
+        assert_eq!(update_checksums(&mut doc, &Stored).unwrap(), 0);
+
+        let sexps = kicad_sexp::parse(&updated).unwrap();
+        let root = List::new(&sexps[0]).unwrap();
+        let files: Vec<EmbeddedFile> = root.child("embedded_files").unwrap().parse_all("file").unwrap();
+        assert_eq!((files.len(), files[0].kind, files[0].checksum.as_deref()), (1, FileKind::Other, Some(&*checksum)));
+    }
+}
@@ -0,0 +1,100 @@
+//! Substitution of `${NAME}` variables, as in the paths of library tables
+//! and drawing sheets.
+
+use std::collections::BTreeMap;
+
+use crate::Error;
+
+/// The variable KiCad sets to the directory of the open project.
+pub const PROJECT_DIR: &str = "KIPRJMOD";
+
+/// Values for the variables in a path. Names set with [`EnvVars::with`]
+/// take precedence over the process environment.
+#[derive(Clone, Debug, Default)]
+pub struct EnvVars {
+    vars: BTreeMap<String, String>,
+    process: bool,
+}
+
+impl EnvVars {
+    /// No variables at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Falls back to the process environment, where KiCad's own variables
+    /// such as `KICAD8_SYMBOL_DIR` live.
+    pub fn from_process() -> Self {
+        EnvVars { vars: BTreeMap::new(), process: true }
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets [`PROJECT_DIR`].
+    pub fn with_project_dir(self, dir: impl AsRef<std::path::Path>) -> Self {
+        let dir = dir.as_ref().to_string_lossy().into_owned();
+        self.with(PROJECT_DIR, dir)
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        match self.vars.get(name) {
+            Some(value) => Some(value.clone()),
+            None if self.process => std::env::var(name).ok(),
+            None => None,
+        }
+    }
+
+    /// Replaces each `${NAME}` or `$(NAME)` in `text` with its value. A `$`
+    /// without a closed name is kept as is; an unset name fails with
+    /// [`Error::UndefinedVariable`].
+    pub fn expand(&self, text: &str) -> Result<String, Error> {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let close = match rest[1..].chars().next() {
+                Some('{') => '}',
+                Some('(') => ')',
+                _ => {
+                    out.push('$');
+                    rest = &rest[1..];
+                    continue;
+                },
+            };
+            let Some(end) = rest.find(close) else {
+                break;
+            };
+            let name = &rest[2..end];
+            out.push_str(&self.get(name).ok_or_else(|| Error::UndefinedVariable(name.to_string()))?);
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand() {
+        let env = EnvVars::new().with("KICAD8_SYMBOL_DIR", "/usr/share/kicad/symbols").with_project_dir("/work/demo");
+
+        let device = env.expand("${KICAD8_SYMBOL_DIR}/Device.kicad_sym").unwrap();
+        assert_eq!(device, "/usr/share/kicad/symbols/Device.kicad_sym");
+        assert_eq!(env.expand("$(KIPRJMOD)/lib/${KIPRJMOD}").unwrap(), "/work/demo/lib//work/demo");
+        assert_eq!(env.expand("price $5 ${unclosed").unwrap(), "price $5 ${unclosed");
+        assert_eq!(env.expand("no variables").unwrap(), "no variables");
+        let error = env.expand("${KICAD9_FOOTPRINT_DIR}/x.pretty").unwrap_err();
+        assert_eq!(error.to_string(), "undefined variable `KICAD9_FOOTPRINT_DIR`");
+
+        let process = EnvVars::from_process().with("PATH", "mine");
+        assert_eq!(process.get("PATH").as_deref(), Some("mine"));
+        assert_eq!(EnvVars::new().get("PATH"), None);
+    }
+}
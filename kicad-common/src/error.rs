@@ -0,0 +1,137 @@
+use std::{
+    fmt, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use kicad_sexp::{schema::Issue, ParseError};
+
+use crate::ConvertError;
+
+/// Error returned when loading a typed document.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The text is not a well-formed s-expression.
+    #[error("parse failed{}", Joined(.0))]
+    Parse(Vec<ParseError>),
+    /// The tree does not have the shape of the expected document.
+    #[error(transparent)]
+    Convert(#[from] ConvertError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The file was written by a newer KiCad than this crate reads.
+    #[error("({tag} ...) version {found} is newer than the supported {newest}")]
+    Version { tag: String, found: u32, newest: u32 },
+    /// The document does not match its schema.
+    #[error("validation failed{}", Joined(.0))]
+    Validation(Vec<Issue>),
+    /// A JSON document such as a `.kicad_pro` is malformed or has the wrong
+    /// shape; `line` and `column` count from 1.
+    #[error("{message} at line {line}, column {column}")]
+    Json { message: String, line: usize, column: usize },
+    /// A `${NAME}` in a path names a variable that is not set.
+    #[error("undefined variable `{0}`")]
+    UndefinedVariable(String),
+    /// The file is not a document this build reads, or reading it needs a
+    /// feature that is off.
+    #[error("{0}")]
+    Unsupported(String),
+    /// Loading `file` failed.
+    #[error("{}: {source}", file.display())]
+    File { file: PathBuf, source: Box<Error> },
+}
+
+struct Joined<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for Joined<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in self.0 {
+            write!(f, "; {}", item)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    /// Attaches the file being loaded, unless the error already names one.
+    pub fn in_file(self, file: impl Into<PathBuf>) -> Self {
+        match self {
+            Error::File { .. } => self,
+            e => Error::File { file: file.into(), source: Box::new(e) },
+        }
+    }
+
+    /// The error without its file context.
+    pub fn kind(&self) -> &Error {
+        match self {
+            Error::File { source, .. } => source.kind(),
+            e => e,
+        }
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            Error::File { file, .. } => Some(file),
+            _ => None,
+        }
+    }
+
+    /// Tags of the lists enclosing a conversion or validation error,
+    /// outermost first.
+    pub fn path(&self) -> Option<&[String]> {
+        match self.kind() {
+            Error::Convert(e) => Some(&e.path),
+            Error::Validation(issues) => issues.first().map(|issue| &issue.path[..]),
+            _ => None,
+        }
+    }
+
+    /// Byte range of the first parse error.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self.kind() {
+            Error::Parse(errors) => errors.first().map(|e| e.span.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use kicad_sexp::schema::Schema;
+
+    use super::*;
+    use crate::convert::{check_version, validate_document};
+
+    #[test]
+    fn context() {
+        let error = Error::Parse(kicad_sexp::parse("(a))").unwrap_err()).in_file("a.kicad_pcb");
+        assert_eq!(error.to_string(), "a.kicad_pcb: parse failed; unexpected ')' at 3..4");
+        assert_eq!(error.file(), Some(Path::new("a.kicad_pcb")));
+        assert_eq!(error.span(), Some(3..4));
+        assert!(matches!(error.kind(), Error::Parse(_)));
+        assert!(error.source().is_some());
+        assert_eq!(error.in_file("b").file(), Some(Path::new("a.kicad_pcb")));
+
+        let error = Error::from(ConvertError::new("missing (at ...)").within("pad").within("footprint"));
+        assert_eq!(error.path(), Some(&["footprint".to_string(), "pad".to_string()][..]));
+        assert_eq!(error.span(), None);
+
+        assert!(check_version("kicad_pcb", 20241229, 20241229).is_ok());
+        let error = check_version("kicad_pcb", 20250901, 20241229).unwrap_err();
+        assert_eq!(error.to_string(), "(kicad_pcb ...) version 20250901 is newer than the supported 20241229");
+    }
+
+    #[test]
+    fn validation() {
+        let schema = Schema::parse("(schema a (list a (children b)) (list b (args number)))").unwrap();
+        assert!(validate_document("(a (b 1))", &schema).is_ok());
+
+        let error = validate_document("(a (b x))", &schema).unwrap_err();
+        assert!(matches!(&error, Error::Validation(issues) if issues.len() == 1));
+        assert_eq!(error.path(), Some(&["a".to_string(), "b".to_string()][..]));
+        assert!(error.to_string().starts_with("validation failed; a > b: "));
+    }
+}
@@ -0,0 +1,235 @@
+//! Exact counterparts of the [`geometry`] types, in [`Decimal`] millimeters
+//! with KiCad's nanometer resolution.
+//!
+//! This falls short of models backed by decimals: the typed models keep
+//! `f64` coordinates with or without the `decimal` feature. Features have to
+//! be additive, and changing the type of public fields under one would break
+//! every crate in a build that is written against `f64` as soon as another
+//! crate turns the feature on. The geometry code, such as zone filling,
+//! routing and footprint transforms, is float math as well.
+//!
+//! Reading and writing through the models is still lossless. KiCad stores
+//! 32-bit nanometers, so coordinates stay within ±2147.483647 mm, where an
+//! `f64` is within a fraction of a nanometer of every six-decimal value and
+//! [`format_float`](kicad_sexp::number::format_float) writes it back as
+//! read. Precision is lost to arithmetic instead: sums of offsets and
+//! rotations pick up float error, which shows when endpoints are compared.
+//! Do that arithmetic in these types, converting at the edges with `From`.
+
+use std::ops::{Add, Neg, Sub};
+
+use kicad_sexp::number::Decimal;
+
+use crate::{
+    convert::{FromSexp, List, Result},
+    geometry,
+};
+
+#[cfg(feature = "serde")]
+mod decimal {
+    use kicad_sexp::number::Decimal;
+
+    /// Writes the decimal as a string, so no float is involved.
+    pub fn serialize<S: serde::Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        crate::convert::deserialize_from_str(deserializer)
+    }
+}
+
+/// An exact [`geometry::Point`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    #[cfg_attr(feature = "serde", serde(with = "decimal"))]
+    pub x: Decimal,
+    #[cfg_attr(feature = "serde", serde(with = "decimal"))]
+    pub y: Decimal,
+}
+
+impl Point {
+    pub const fn new(x: Decimal, y: Decimal) -> Self {
+        Point { x, y }
+    }
+
+    /// Rotates like [`geometry::Point::rotated`]. Quarter turns are exact,
+    /// other angles are rounded to the nanometer as KiCad does.
+    pub fn rotated(self, angle: f64) -> Self {
+        match angle.rem_euclid(360.0) {
+            0.0 => self,
+            90.0 => Point { x: self.y, y: -self.x },
+            180.0 => -self,
+            270.0 => Point { x: -self.y, y: self.x },
+            _ => geometry::Point::from(self).rotated(angle).into(),
+        }
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point { x: -self.x, y: -self.y }
+    }
+}
+
+impl From<geometry::Point> for Point {
+    /// Rounds to the nearest nanometer.
+    fn from(point: geometry::Point) -> Self {
+        Point { x: Decimal::from_f64(point.x), y: Decimal::from_f64(point.y) }
+    }
+}
+
+impl From<Point> for geometry::Point {
+    fn from(point: Point) -> Self {
+        geometry::Point { x: point.x.to_f64(), y: point.y.to_f64() }
+    }
+}
+
+impl FromSexp for Point {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Point { x: list.number(0)?, y: list.number(1)? })
+    }
+}
+
+/// An exact [`geometry::Size`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Size {
+    #[cfg_attr(feature = "serde", serde(with = "decimal"))]
+    pub width: Decimal,
+    #[cfg_attr(feature = "serde", serde(with = "decimal"))]
+    pub height: Decimal,
+}
+
+impl Size {
+    pub const fn new(width: Decimal, height: Decimal) -> Self {
+        Size { width, height }
+    }
+}
+
+impl From<geometry::Size> for Size {
+    /// Rounds to the nearest nanometer.
+    fn from(size: geometry::Size) -> Self {
+        Size { width: Decimal::from_f64(size.width), height: Decimal::from_f64(size.height) }
+    }
+}
+
+impl From<Size> for geometry::Size {
+    fn from(size: Size) -> Self {
+        geometry::Size { width: size.width.to_f64(), height: size.height.to_f64() }
+    }
+}
+
+impl FromSexp for Size {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Size { width: list.number(0)?, height: list.number(1)? })
+    }
+}
+
+/// An exact [`geometry::Position`]. The angle stays in `f64` degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    #[cfg_attr(feature = "serde", serde(with = "decimal"))]
+    pub x: Decimal,
+    #[cfg_attr(feature = "serde", serde(with = "decimal"))]
+    pub y: Decimal,
+    pub angle: f64,
+}
+
+impl Position {
+    pub const fn new(x: Decimal, y: Decimal, angle: f64) -> Self {
+        Position { x, y, angle }
+    }
+
+    pub const fn point(&self) -> Point {
+        Point { x: self.x, y: self.y }
+    }
+
+    /// Maps a point relative to this position, such as a pad in a footprint,
+    /// to the coordinates the position is in.
+    pub fn to_outer(&self, point: Point) -> Point {
+        self.point() + point.rotated(self.angle)
+    }
+}
+
+impl From<geometry::Position> for Position {
+    /// Rounds to the nearest nanometer.
+    fn from(position: geometry::Position) -> Self {
+        Position { x: Decimal::from_f64(position.x), y: Decimal::from_f64(position.y), angle: position.angle }
+    }
+}
+
+impl From<Position> for geometry::Position {
+    fn from(position: Position) -> Self {
+        geometry::Position { x: position.x.to_f64(), y: position.y.to_f64(), angle: position.angle }
+    }
+}
+
+impl FromSexp for Position {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Position {
+            x: list.number(0)?,
+            y: list.number(1)?,
+            angle: list.opt_number(2)?.unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mm(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn conversions() {
+        let sexps = kicad_sexp::parse("(pad (at 12.7 -3.81 90) (size 1.6 0.000001))").unwrap();
+        let pad = List::new(&sexps[0]).unwrap();
+        let at: Position = pad.parse("at").unwrap().unwrap();
+        let size: Size = pad.parse("size").unwrap().unwrap();
+        assert_eq!(at, Position::new(mm("12.7"), mm("-3.81"), 90.0));
+        assert_eq!(size.height, Decimal::from_millionths(1));
+
+        let float: geometry::Position = pad.parse("at").unwrap().unwrap();
+        assert_eq!(Position::from(float), at);
+        assert_eq!(geometry::Size::from(size), geometry::Size::new(1.6, 0.000001));
+        let error = kicad_sexp::parse("(xy 0.0000001 0)").unwrap();
+        assert!(Point::from_sexp(&List::new(&error[0]).unwrap()).is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let step = Point::new(mm("0.1"), mm("0.2"));
+        let sum = (0..3).fold(Point::default(), |sum, _| sum + step);
+        assert_eq!(sum, Point::new(mm("0.3"), mm("0.6")));
+        assert_eq!(sum - step - step - step, Point::default());
+
+        let at = Position::new(mm("10"), mm("5"), 90.0);
+        assert_eq!(at.to_outer(Point::new(mm("1.27"), mm("0"))), Point::new(mm("10"), mm("3.73")));
+        assert_eq!(step.rotated(-90.0), Point::new(mm("-0.2"), mm("0.1")));
+        assert_eq!(step.rotated(180.0), -step);
+        assert_eq!(Point::new(mm("1"), mm("0")).rotated(45.0), Point::new(mm("0.707107"), mm("-0.707107")));
+    }
+}
@@ -0,0 +1,287 @@
+//! Handlers for lists the file format does not define, such as a
+//! company-internal `(x_panel_info ...)`, so downstream crates can read,
+//! check and write them without forking the models.
+//!
+//! Register [`Extension`] types in a [`Registry`] and parse documents inside
+//! [`Registry::scope`]. The typed models then keep matching lists in their
+//! `extensions` fields instead of skipping them:
+//!
+//! ```ignore
+//! let mut registry = Registry::new();
+//! registry.register::<PanelInfo>();
+//! let board = registry.scope(|| Board::parse(src))?;
+//! let panel = board.extensions.iter().find_map(ExtensionNode::get::<PanelInfo>);
+//! ```
+
+use std::{any::Any, cell::RefCell, collections::HashMap, fmt};
+
+use kicad_sexp::{
+    schema::{Issue, Schema},
+    Sexp, SexpOwned,
+};
+
+use crate::{
+    convert::{FromSexp, List, Result},
+    ConvertError,
+};
+
+/// A typed extension list.
+pub trait Extension: FromSexp + Clone + PartialEq + fmt::Debug + Send + Sync + 'static {
+    /// Tag of the list, e.g. `x_panel_info`.
+    const TAG: &'static str;
+
+    /// Writes the value back as a list tagged [`Extension::TAG`].
+    fn to_sexp(&self) -> SexpOwned;
+
+    /// Problems with a value that parsed, reported by [`Registry::validate`].
+    fn validate(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+trait DynExtension: Any + fmt::Debug + Send + Sync {
+    fn tag(&self) -> &'static str;
+    fn to_sexp(&self) -> SexpOwned;
+    fn validate(&self) -> Vec<String>;
+    fn clone_box(&self) -> Box<dyn DynExtension>;
+    fn eq_dyn(&self, other: &dyn DynExtension) -> bool;
+}
+
+impl<T: Extension> DynExtension for T {
+    fn tag(&self) -> &'static str {
+        T::TAG
+    }
+
+    fn to_sexp(&self) -> SexpOwned {
+        Extension::to_sexp(self)
+    }
+
+    fn validate(&self) -> Vec<String> {
+        Extension::validate(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn DynExtension> {
+        Box::new(self.clone())
+    }
+
+    fn eq_dyn(&self, other: &dyn DynExtension) -> bool {
+        (other as &dyn Any).downcast_ref::<T>() == Some(self)
+    }
+}
+
+/// An extension list kept by a typed model, of any registered type.
+#[derive(Debug)]
+pub struct ExtensionNode(Box<dyn DynExtension>);
+
+impl ExtensionNode {
+    pub fn new<T: Extension>(value: T) -> Self {
+        ExtensionNode(Box::new(value))
+    }
+
+    pub fn tag(&self) -> &'static str {
+        self.0.tag()
+    }
+
+    /// The value, if it is a `T`.
+    pub fn get<T: Extension>(&self) -> Option<&T> {
+        (&*self.0 as &dyn Any).downcast_ref()
+    }
+
+    pub fn to_sexp(&self) -> SexpOwned {
+        self.0.to_sexp()
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        self.0.validate()
+    }
+}
+
+impl Clone for ExtensionNode {
+    fn clone(&self) -> Self {
+        ExtensionNode(self.0.clone_box())
+    }
+}
+
+impl PartialEq for ExtensionNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_dyn(&*other.0)
+    }
+}
+
+type Handler = fn(&List) -> Result<ExtensionNode>;
+
+fn handle<T: Extension>(list: &List) -> Result<ExtensionNode> {
+    T::from_sexp(list).map(ExtensionNode::new)
+}
+
+/// The registered extension types, by tag.
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    handlers: HashMap<&'static str, Handler>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Registry>> = const { RefCell::new(None) };
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Registers `T`, replacing any handler for the same tag.
+    pub fn register<T: Extension>(&mut self) -> &mut Self {
+        self.handlers.insert(T::TAG, handle::<T>);
+        self
+    }
+
+    pub fn handles(&self, tag: &str) -> bool {
+        self.handlers.contains_key(tag)
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.handlers.keys().copied()
+    }
+
+    /// Runs `parse` with this registry active on the current thread.
+    pub fn scope<T>(&self, parse: impl FnOnce() -> T) -> T {
+        let outer = CURRENT.replace(Some(self.clone()));
+        let value = parse();
+        CURRENT.set(outer);
+        value
+    }
+
+    /// Lets `schema` accept the registered lists, which it cannot check.
+    pub fn allow_in(&self, schema: &mut Schema) {
+        for tag in self.tags() {
+            schema.allow_extension(tag);
+        }
+    }
+
+    /// Parses every registered list in `sexps` and collects conversion
+    /// errors and [`Extension::validate`] messages, in the form of schema
+    /// issues.
+    pub fn validate(&self, sexps: &[Sexp]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let mut path = Vec::new();
+        for sexp in sexps {
+            self.walk(sexp, &mut path, &mut issues);
+        }
+        issues
+    }
+
+    fn walk(&self, sexp: &Sexp, path: &mut Vec<String>, issues: &mut Vec<Issue>) {
+        let Some(list) = List::new(sexp) else {
+            return;
+        };
+        path.push(list.tag().to_string());
+        match self.handlers.get(list.tag()) {
+            Some(handler) => match handler(&list) {
+                Ok(node) => {
+                    issues.extend(node.validate().into_iter().map(|message| Issue { path: path.clone(), message }));
+                },
+                Err(ConvertError { path: inner, message }) => {
+                    let mut full = path[..path.len() - 1].to_vec();
+                    full.extend(inner);
+                    issues.push(Issue { path: full, message });
+                },
+            },
+            None => {
+                for item in list.args() {
+                    self.walk(item, path, issues);
+                }
+            },
+        }
+        path.pop();
+    }
+}
+
+/// Converts `list` with the handler registered for its tag in the active
+/// [`Registry::scope`], if any. Models call this for lists they do not know.
+pub fn parse_extension(list: &List) -> Option<Result<ExtensionNode>> {
+    let handler = CURRENT.with_borrow(|registry| registry.as_ref()?.handlers.get(list.tag()).copied())?;
+    Some(handler(list))
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_sexp::SexpBuilder;
+
+    use super::*;
+    use crate::convert::parse_document;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct PanelInfo {
+        columns: u32,
+        rows: u32,
+    }
+
+    impl FromSexp for PanelInfo {
+        fn from_sexp(list: &List) -> Result<Self> {
+            Ok(PanelInfo { columns: list.number(0)?, rows: list.number(1)? })
+        }
+    }
+
+    impl Extension for PanelInfo {
+        const TAG: &'static str = "x_panel_info";
+
+        fn to_sexp(&self) -> SexpOwned {
+            SexpBuilder::new(Self::TAG).int(self.columns.into()).int(self.rows.into()).build()
+        }
+
+        fn validate(&self) -> Vec<String> {
+            if self.rows == 0 { vec!["a panel needs at least one row".to_string()] } else { Vec::new() }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Root(Vec<ExtensionNode>);
+
+    impl FromSexp for Root {
+        fn from_sexp(list: &List) -> Result<Self> {
+            let mut extensions = Vec::new();
+            for item in list.lists() {
+                if let Some(node) = parse_extension(&item) {
+                    extensions.push(node.map_err(|e| e.within(list.tag()))?);
+                }
+            }
+            Ok(Root(extensions))
+        }
+    }
+
+    const SRC: &str = "(kicad_pcb (version 20241229) (x_panel_info 3 2) (x_other))";
+
+    #[test]
+    fn extensions() {
+        let mut registry = Registry::new();
+        registry.register::<PanelInfo>();
+
+        assert!(parse_document::<Root>(SRC, "kicad_pcb").unwrap().0.is_empty());
+        let root = registry.scope(|| parse_document::<Root>(SRC, "kicad_pcb")).unwrap();
+        assert_eq!(root.0.len(), 1);
+        assert_eq!(root.0[0].get::<PanelInfo>(), Some(&PanelInfo { columns: 3, rows: 2 }));
+        assert_eq!(root.0[0].clone(), ExtensionNode::new(PanelInfo { columns: 3, rows: 2 }));
+        assert_eq!(root.0[0].to_sexp().to_string(), "(x_panel_info 3 2)");
+
+        let error = registry.scope(|| parse_document::<Root>("(kicad_pcb (x_panel_info 3))", "kicad_pcb"));
+        assert_eq!(error.unwrap_err().to_string(), "kicad_pcb > x_panel_info: missing argument 2");
+
+        let sexps = kicad_sexp::parse("(kicad_pcb (x_panel_info 3 0) (footprint (x_panel_info x 1)))").unwrap();
+        let issues: Vec<_> = registry.validate(&sexps).iter().map(Issue::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "kicad_pcb > x_panel_info: a panel needs at least one row",
+                "kicad_pcb > footprint > x_panel_info: expected a number, found symbol `x`",
+            ]
+        );
+
+        let mut schema =
+            Schema::parse("(schema kicad_pcb (list kicad_pcb (children version)) (list version (args int)))").unwrap();
+        let sexps = kicad_sexp::parse(SRC).unwrap();
+        assert_eq!(schema.validate(&sexps).len(), 2);
+        registry.allow_in(&mut schema);
+        let issues: Vec<_> = schema.validate(&sexps).iter().map(Issue::to_string).collect();
+        assert_eq!(issues, ["kicad_pcb: unexpected (x_other ...)"]);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::convert::{FromSexp, List, Result};
+
+/// A coordinate pair in millimeters, as in `(xy x y)`, `(start x y)` or `(size w h)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+
+    /// Rotates around the origin by `angle` degrees, counter-clockwise as
+    /// seen on screen with KiCad's downward Y axis.
+    pub fn rotated(self, angle: f64) -> Self {
+        if angle == 0.0 {
+            return self;
+        }
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Point { x: self.x * cos + self.y * sin, y: -self.x * sin + self.y * cos }
+    }
+}
+
+impl std::ops::Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl FromSexp for Point {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Point { x: list.number(0)?, y: list.number(1)? })
+    }
+}
+
+/// Width and height in millimeters, as in `(size w h)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Size {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Size {
+    pub const fn new(width: f64, height: f64) -> Self {
+        Size { width, height }
+    }
+}
+
+impl FromSexp for Size {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Size { width: list.number(0)?, height: list.number(1)? })
+    }
+}
+
+/// A location with an optional rotation in degrees, as in `(at x y angle)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+    pub angle: f64,
+}
+
+impl Position {
+    pub const fn new(x: f64, y: f64, angle: f64) -> Self {
+        Position { x, y, angle }
+    }
+
+    pub const fn point(&self) -> Point {
+        Point { x: self.x, y: self.y }
+    }
+}
+
+impl FromSexp for Position {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Position {
+            x: list.number(0)?,
+            y: list.number(1)?,
+            angle: list.opt_number(2)?.unwrap_or_default(),
+        })
+    }
+}
+
+/// Reads the `(xy x y)` children of a `(pts ...)` list.
+pub fn points(pts: &List) -> Result<Vec<Point>> {
+    pts.parse_all("xy")
+}
+
+/// Reads the points of the `(pts ...)` child of `list`, empty if it has none.
+pub fn child_points(list: &List) -> Result<Vec<Point>> {
+    match list.child("pts") {
+        Some(pts) => points(&pts).map_err(|e| e.within(list.tag())),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let sexps = kicad_sexp::parse("(wire (pts (xy 1 2) (xy 3.5 -4)) (at 1 2) (at 1 2 90))").unwrap();
+        let wire = List::new(&sexps[0]).unwrap();
+        let at: Vec<Position> = wire.parse_all("at").unwrap();
+
+        assert_eq!(child_points(&wire).unwrap(), [Point::new(1.0, 2.0), Point::new(3.5, -4.0)]);
+        assert_eq!(at, [Position::new(1.0, 2.0, 0.0), Position::new(1.0, 2.0, 90.0)]);
+    }
+
+    #[test]
+    fn rotation() {
+        let p = Point::new(1.0, 0.0).rotated(90.0);
+        assert!((p.x - 0.0).abs() < 1e-9 && (p.y + 1.0).abs() < 1e-9);
+        assert_eq!(Point::new(2.0, 3.0).rotated(0.0) + Point::new(1.0, 1.0), Point::new(3.0, 4.0));
+    }
+}
@@ -0,0 +1,107 @@
+use kicad_sexp::SexpBuilder;
+
+use crate::convert::{FromSexp, List, Result};
+
+/// An RGBA color; `a` is the opacity from 0 to 1. All zeros means "use the default".
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: f64,
+}
+
+impl Color {
+    pub fn is_default(&self) -> bool {
+        *self == Color::default()
+    }
+
+    pub fn to_sexp(&self) -> SexpBuilder {
+        SexpBuilder::new("color").int(self.r.into()).int(self.g.into()).int(self.b.into()).float(self.a)
+    }
+}
+
+impl FromSexp for Color {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Color {
+            r: list.number(0)?,
+            g: list.number(1)?,
+            b: list.number(2)?,
+            a: list.opt_number(3)?.unwrap_or(1.0),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StrokeKind {
+    #[default]
+    Default,
+    Solid,
+    Dash,
+    Dot,
+    DashDot,
+    DashDotDot,
+}
+
+impl StrokeKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "default" => StrokeKind::Default,
+            "solid" => StrokeKind::Solid,
+            "dash" => StrokeKind::Dash,
+            "dot" => StrokeKind::Dot,
+            "dash_dot" => StrokeKind::DashDot,
+            "dash_dot_dot" => StrokeKind::DashDotDot,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrokeKind::Default => "default",
+            StrokeKind::Solid => "solid",
+            StrokeKind::Dash => "dash",
+            StrokeKind::Dot => "dot",
+            StrokeKind::DashDot => "dash_dot",
+            StrokeKind::DashDotDot => "dash_dot_dot",
+        }
+    }
+}
+
+/// Line style, as in `(stroke (width 0.1) (type dash) (color 0 0 0 1))`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stroke {
+    pub width: f64,
+    pub kind: StrokeKind,
+    pub color: Option<Color>,
+}
+
+impl Stroke {
+    /// The stroke as KiCad writes it; a default color is left out.
+    pub fn to_sexp(&self) -> SexpBuilder {
+        SexpBuilder::new("stroke")
+            .child(SexpBuilder::new("width").float(self.width))
+            .child(SexpBuilder::new("type").symbol(self.kind.name()))
+            .child_opt(self.color.filter(|color| !color.is_default()).map(|color| color.to_sexp()))
+    }
+}
+
+impl FromSexp for Stroke {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let kind = match list.child("type") {
+            Some(kind) => {
+                let name = kind.symbol(0).map_err(|e| e.within(list.tag()))?;
+                StrokeKind::from_name(name).ok_or_else(|| list.error(format!("unknown stroke type `{}`", name)))?
+            },
+            None => StrokeKind::Default,
+        };
+        Ok(Stroke {
+            width: list.child_number("width")?.unwrap_or_default(),
+            kind,
+            color: list.parse("color")?,
+        })
+    }
+}
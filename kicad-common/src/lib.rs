@@ -0,0 +1,31 @@
+//! Types and conversion helpers shared by the typed KiCad document crates.
+
+pub mod convert;
+pub mod coverage;
+pub mod embedded;
+pub mod env;
+mod error;
+#[cfg(feature = "decimal")]
+pub mod exact;
+pub mod extension;
+pub mod geometry;
+pub mod graphics;
+pub mod lib_table;
+pub mod metadata;
+pub mod netlist;
+pub mod page;
+pub mod property;
+pub mod reference;
+pub mod text;
+pub mod uuid;
+
+pub use convert::{ConvertError, FromSexp, List};
+pub use env::EnvVars;
+pub use error::Error;
+pub use geometry::{Point, Position, Size};
+pub use graphics::{Color, Stroke, StrokeKind};
+pub use lib_table::{LibTable, LibTableKind, Library};
+pub use page::{Paper, PaperSize, TitleBlock};
+pub use property::Property;
+pub use text::{Effects, Font, HAlign, Justify, VAlign};
+pub use uuid::{Uuid, UuidGenerator};
@@ -0,0 +1,236 @@
+//! Library tables, `fp-lib-table` and `sym-lib-table`: the nicknames that
+//! `Library:Item` references such as `Device:R` use, and where each
+//! library lives.
+
+use std::path::PathBuf;
+
+use crate::{
+    convert::{check_version, FromSexp, List, Result},
+    env::EnvVars,
+    ConvertError, Error,
+};
+
+/// Which libraries a table lists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LibTableKind {
+    /// `.pretty` footprint directories.
+    Footprint,
+    /// `.kicad_sym` symbol files.
+    Symbol,
+}
+
+impl LibTableKind {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            LibTableKind::Footprint => "fp_lib_table",
+            LibTableKind::Symbol => "sym_lib_table",
+        }
+    }
+
+    /// The name KiCad gives the table file, in a project directory or in
+    /// its configuration directory for the global table.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            LibTableKind::Footprint => "fp-lib-table",
+            LibTableKind::Symbol => "sym-lib-table",
+        }
+    }
+}
+
+/// A `(lib ...)` entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Library {
+    /// The nickname references use.
+    pub name: String,
+    /// The `type`: `KiCad` for the native formats, otherwise the importer,
+    /// as in `Legacy` or `Eagle`.
+    pub format: String,
+    /// Location of the library, possibly with `${NAME}` variables.
+    pub uri: String,
+    pub options: String,
+    pub description: String,
+    pub disabled: bool,
+    /// Left out of the library browser but still resolved.
+    pub hidden: bool,
+}
+
+impl Library {
+    /// The library's file or directory, with the variables in its URI
+    /// replaced.
+    pub fn path(&self, env: &EnvVars) -> std::result::Result<PathBuf, Error> {
+        env.expand(&self.uri).map(PathBuf::from)
+    }
+}
+
+impl FromSexp for Library {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Library {
+            name: list.child_string("name")?.ok_or_else(|| list.error("missing (name ...)"))?,
+            format: list.child_string("type")?.unwrap_or_default(),
+            uri: list.child_string("uri")?.ok_or_else(|| list.error("missing (uri ...)"))?,
+            options: list.child_string("options")?.unwrap_or_default(),
+            description: list.child_string("descr")?.unwrap_or_default(),
+            disabled: list.flag("disabled")?,
+            hidden: list.flag("hidden")?,
+        })
+    }
+}
+
+/// Splits a `Library:Item` reference. References without a nickname, as
+/// in symbols inside a schematic's own cache, have `None` for it.
+pub fn split_lib_id(lib_id: &str) -> (Option<&str>, &str) {
+    match lib_id.split_once(':') {
+        Some((library, item)) => (Some(library), item),
+        None => (None, lib_id),
+    }
+}
+
+/// Where a `Library:Item` reference points.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolved<'t> {
+    pub library: &'t Library,
+    /// The library's file or directory.
+    pub path: PathBuf,
+    /// The item within the library.
+    pub item: String,
+}
+
+impl Resolved<'_> {
+    /// The item's `.kicad_mod` file, for a footprint library.
+    pub fn footprint_path(&self) -> PathBuf {
+        self.path.join(format!("{}.kicad_mod", self.item))
+    }
+}
+
+/// A parsed `fp-lib-table` or `sym-lib-table`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LibTable {
+    pub kind: LibTableKind,
+    /// 0 for tables from before KiCad 7, which have no `(version ...)`.
+    pub version: u32,
+    pub libs: Vec<Library>,
+}
+
+impl LibTable {
+    /// Newest file format version the model is written against.
+    pub const NEWEST_VERSION: u32 = 7;
+
+    pub fn new(kind: LibTableKind) -> Self {
+        LibTable { kind, version: Self::NEWEST_VERSION, libs: Vec::new() }
+    }
+
+    /// Parses either kind of table, telling them apart by the root tag.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(libs)))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let sexps = kicad_sexp::parse(src).map_err(Error::Parse)?;
+        let root = || ConvertError::new("expected a (fp_lib_table ...) or (sym_lib_table ...) root");
+        let list = sexps.iter().find_map(List::new).ok_or_else(root)?;
+        let kind = match list.tag() {
+            "fp_lib_table" => LibTableKind::Footprint,
+            "sym_lib_table" => LibTableKind::Symbol,
+            _ => return Err(root().into()),
+        };
+        let table = LibTable {
+            kind,
+            version: list.child_number("version")?.unwrap_or_default(),
+            libs: list.parse_all("lib")?,
+        };
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("libs", table.libs.len());
+        Ok(table)
+    }
+
+    /// Fails with [`Error::Version`] for tables from a newer KiCad.
+    pub fn check_version(&self) -> std::result::Result<(), Error> {
+        check_version(self.kind.tag(), self.version, Self::NEWEST_VERSION)
+    }
+
+    /// The library with nickname `name`, disabled or not.
+    pub fn lib(&self, name: &str) -> Option<&Library> {
+        self.libs.iter().find(|lib| lib.name == name)
+    }
+
+    /// Adds the libraries of `fallback` whose nicknames this table does not
+    /// have, the way KiCad puts a project's table in front of the global one.
+    pub fn extend_from(&mut self, fallback: &LibTable) {
+        for lib in &fallback.libs {
+            if self.lib(&lib.name).is_none() {
+                self.libs.push(lib.clone());
+            }
+        }
+    }
+
+    /// Looks up the library of a `Library:Item` reference. `None` if the
+    /// reference has no nickname or the table has no enabled library for
+    /// it; an error if the library's URI uses an unset variable.
+    pub fn resolve(&self, lib_id: &str, env: &EnvVars) -> std::result::Result<Option<Resolved<'_>>, Error> {
+        let (Some(name), item) = split_lib_id(lib_id) else {
+            return Ok(None);
+        };
+        let Some(library) = self.lib(name).filter(|lib| !lib.disabled) else {
+            return Ok(None);
+        };
+        Ok(Some(Resolved { library, path: library.path(env)?, item: item.to_string() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FP_TABLE: &str = include_str!("../../reference-files/demo/fp-lib-table");
+    const SYM_TABLE: &str = include_str!("../../reference-files/demo/sym-lib-table");
+
+    #[test]
+    fn tables() {
+        let footprints = LibTable::parse(FP_TABLE).unwrap();
+        assert_eq!((footprints.kind, footprints.version), (LibTableKind::Footprint, 7));
+        assert!(footprints.check_version().is_ok());
+        let names: Vec<_> = footprints.libs.iter().map(|lib| lib.name.as_str()).collect();
+        assert_eq!(names, ["demo", "Resistor_SMD", "Old"]);
+        let demo = footprints.lib("demo").unwrap();
+        assert_eq!((demo.format.as_str(), demo.description.as_str()), ("KiCad", "Project footprints"));
+        assert!(footprints.lib("Old").unwrap().disabled);
+
+        let symbols = LibTable::parse(SYM_TABLE).unwrap();
+        assert_eq!(symbols.kind, LibTableKind::Symbol);
+        assert_eq!(symbols.kind.file_name(), "sym-lib-table");
+        assert!(symbols.lib("Connector").unwrap().hidden);
+
+        let old = LibTable::parse("(sym_lib_table (lib (name Device)(type Legacy)(uri /lib/device.lib)))").unwrap();
+        assert_eq!((old.version, old.libs[0].format.as_str()), (0, "Legacy"));
+        let error = LibTable::parse("(sym_lib_table (lib (name x)))").unwrap_err();
+        assert_eq!(error.to_string(), "sym_lib_table > lib: missing (uri ...)");
+        assert!(LibTable::parse("(kicad_sym)").is_err());
+        assert!(LibTable::parse("(fp_lib_table (version 8))").unwrap().check_version().is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let env = EnvVars::new()
+            .with_project_dir("/work/demo")
+            .with("KICAD8_FOOTPRINT_DIR", "/usr/share/kicad/footprints");
+        let mut footprints = LibTable::parse(FP_TABLE).unwrap();
+
+        let resolved = footprints.resolve("demo:R_0603_1608Metric", &env).unwrap().unwrap();
+        assert_eq!(resolved.library.name, "demo");
+        assert_eq!(resolved.footprint_path(), PathBuf::from("/work/demo/demo.pretty/R_0603_1608Metric.kicad_mod"));
+        let resolved = footprints.resolve("Resistor_SMD:R_0805_2012Metric", &env).unwrap().unwrap();
+        assert_eq!(resolved.path, PathBuf::from("/usr/share/kicad/footprints/Resistor_SMD.pretty"));
+        assert_eq!(footprints.resolve("Old:R", &env).unwrap(), None);
+        assert_eq!(footprints.resolve("Missing:R", &env).unwrap(), None);
+        assert_eq!(footprints.resolve("R", &env).unwrap(), None);
+        assert!(footprints.resolve("Resistor_SMD:R", &EnvVars::new()).is_err());
+        assert_eq!(split_lib_id("Device:R"), (Some("Device"), "R"));
+
+        let mut global = LibTable::new(LibTableKind::Footprint);
+        global.libs.push(Library { name: "demo".into(), uri: "/elsewhere".into(), ..Library::default() });
+        global.libs.push(Library { name: "Capacitor_SMD".into(), uri: "/caps.pretty".into(), ..Library::default() });
+        footprints.extend_from(&global);
+        assert_eq!(footprints.libs.len(), 4);
+        assert_eq!(footprints.lib("demo").unwrap().uri, "${KIPRJMOD}/demo.pretty");
+    }
+}
@@ -0,0 +1,220 @@
+//! Data that other tools attach to the items of a document, kept in a
+//! sidecar file next to it and keyed by item UUID. KiCad refuses lists it
+//! does not know inside its own files and shows every extra property as a
+//! field, so a sidecar is the one place such data survives both KiCad and
+//! this crate untouched.
+//!
+//! Each tool writes under its own namespace, by convention a reverse domain
+//! name like `com.example.panelizer`, so tools do not overwrite each
+//! other's keys:
+//!
+//! ```no_run
+//! use kicad_common::metadata::Metadata;
+//!
+//! let path = Metadata::sidecar_path("demo.kicad_pcb".as_ref());
+//! let mut metadata = Metadata::load(&path)?;
+//! let uuid = "7dfecbba-c7b6-4b01-9f5f-e26c00ad6670".parse()?;
+//! metadata.set(uuid, "com.example.panelizer", "tab", "left");
+//! let board = std::fs::read_to_string("demo.kicad_pcb")?;
+//! metadata.prune(&kicad_sexp::parse(&board).map_err(kicad_common::Error::Parse)?);
+//! std::fs::write(&path, metadata.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use kicad_sexp::{Sexp, SexpBuilder};
+
+use crate::{
+    convert::{check_version, FromSexp, List, Result},
+    uuid::Uuid,
+    ConvertError, Error,
+};
+
+/// The values of one item, by namespace and then by key.
+pub type Fields = BTreeMap<String, BTreeMap<String, String>>;
+
+/// A parsed `(kicad_metadata ...)` sidecar.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    pub version: u32,
+    pub items: BTreeMap<Uuid, Fields>,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Metadata { version: Self::NEWEST_VERSION, items: BTreeMap::new() }
+    }
+}
+
+impl Metadata {
+    /// Newest sidecar format version.
+    pub const NEWEST_VERSION: u32 = 1;
+
+    /// The sidecar of the document at `path`: `demo.kicad_pcb` keeps its
+    /// metadata in `demo.kicad_pcb.meta`.
+    pub fn sidecar_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".meta");
+        PathBuf::from(name)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = src.len())))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        crate::convert::parse_document(src, "kicad_metadata")
+    }
+
+    /// Reads the sidecar at `path`; an empty one if there is no file yet.
+    pub fn load(path: &Path) -> std::result::Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(src) => Self::parse(&src),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Metadata::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Fails with [`Error::Version`] for sidecars from a newer version.
+    pub fn check_version(&self) -> std::result::Result<(), Error> {
+        check_version("kicad_metadata", self.version, Self::NEWEST_VERSION)
+    }
+
+    pub fn get(&self, uuid: Uuid, namespace: &str, key: &str) -> Option<&str> {
+        self.items.get(&uuid)?.get(namespace)?.get(key).map(String::as_str)
+    }
+
+    /// The keys and values `namespace` attached to `uuid`.
+    pub fn namespace(&self, uuid: Uuid, namespace: &str) -> Option<&BTreeMap<String, String>> {
+        self.items.get(&uuid)?.get(namespace)
+    }
+
+    /// Sets a value, returning the one it replaces.
+    pub fn set(&mut self, uuid: Uuid, namespace: &str, key: &str, value: &str) -> Option<String> {
+        let fields = self.items.entry(uuid).or_default().entry(namespace.to_string()).or_default();
+        fields.insert(key.to_string(), value.to_string())
+    }
+
+    /// Removes a value, and the item once it has none left.
+    pub fn remove(&mut self, uuid: Uuid, namespace: &str, key: &str) -> Option<String> {
+        let fields = self.items.get_mut(&uuid)?;
+        let values = fields.get_mut(namespace)?;
+        let value = values.remove(key);
+        if values.is_empty() {
+            fields.remove(namespace);
+        }
+        if fields.is_empty() {
+            self.items.remove(&uuid);
+        }
+        value
+    }
+
+    /// Drops the items that are no longer in the document `sexps`, the way
+    /// KiCad forgets deleted items. Returns how many were dropped.
+    pub fn prune(&mut self, sexps: &[Sexp]) -> usize {
+        let mut uuids = HashSet::new();
+        for sexp in sexps {
+            collect_uuids(sexp, &mut uuids);
+        }
+        let before = self.items.len();
+        self.items.retain(|uuid, _| uuids.contains(uuid));
+        before - self.items.len()
+    }
+
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let mut root = SexpBuilder::new("kicad_metadata").child(SexpBuilder::new("version").int(self.version.into()));
+        for (uuid, fields) in &self.items {
+            let mut item = SexpBuilder::new("item").string(&uuid.to_string());
+            for (namespace, values) in fields {
+                for (key, value) in values {
+                    item = item.child(SexpBuilder::new("field").string(namespace).string(key).string(value));
+                }
+            }
+            root = root.child(item);
+        }
+        root
+    }
+}
+
+/// Writes the sidecar, indented like the files KiCad writes.
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_sexp().build())
+    }
+}
+
+impl FromSexp for Metadata {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mut items: BTreeMap<Uuid, Fields> = BTreeMap::new();
+        for item in list.children("item") {
+            let uuid = Uuid::from_atom(item.arg(0)?).map_err(|e| e.within("item").within(list.tag()))?;
+            let fields = items.entry(uuid).or_default();
+            for field in item.children("field") {
+                let [namespace, key, value] = [0, 1, 2].map(|i| field.string(i));
+                let (namespace, key, value) = (namespace?, key?, value?);
+                if namespace.is_empty() {
+                    return Err(ConvertError::new("a field needs a namespace").within("item").within(list.tag()));
+                }
+                fields.entry(namespace).or_default().insert(key, value);
+            }
+        }
+        Ok(Metadata { version: list.child_number("version")?.unwrap_or_default(), items })
+    }
+}
+
+fn collect_uuids(sexp: &Sexp, uuids: &mut HashSet<Uuid>) {
+    let Sexp::List(items) = sexp else {
+        return;
+    };
+    if let [Sexp::Symbol("uuid" | "tstamp"), atom] = items.as_slice()
+        && let Ok(uuid) = Uuid::from_atom(atom)
+    {
+        uuids.insert(uuid);
+    }
+    for item in items {
+        collect_uuids(item, uuids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn round_trip() {
+        let item: Uuid = "2e6bd89c-0e36-4146-b154-a712e9fecee2".parse().unwrap();
+        let gone: Uuid = "5F3C1A2B".parse().unwrap();
+        let mut metadata = Metadata::default();
+        assert_eq!(metadata.set(item, "com.example.panelizer", "tab", "left"), None);
+        assert_eq!(metadata.set(item, "com.example.panelizer", "tab", "right"), Some("left".to_string()));
+        metadata.set(item, "org.example.bom", "note", "fit \"last\"\nby hand");
+        metadata.set(gone, "org.example.bom", "note", "deleted");
+
+        let written = metadata.to_string();
+        let first = "(kicad_metadata\n\t(version 1)\n\t(item \"00000000-0000-0000-0000-00005f3c1a2b\"";
+        assert!(written.starts_with(first));
+        let read = Metadata::parse(&written).unwrap();
+        assert_eq!(read, metadata);
+        assert!(read.check_version().is_ok());
+        assert_eq!(read.get(item, "com.example.panelizer", "tab"), Some("right"));
+        assert_eq!(read.get(item, "org.example.bom", "note"), Some("fit \"last\"\nby hand"));
+        assert_eq!(read.namespace(item, "org.example.bom").map(BTreeMap::len), Some(1));
+        assert_eq!(read.get(item, "com.example.other", "tab"), None);
+
+        assert_eq!(metadata.prune(&kicad_sexp::parse(BOARD).unwrap()), 1);
+        assert_eq!(metadata.items.keys().collect::<Vec<_>>(), [&item]);
+        assert_eq!(metadata.remove(item, "org.example.bom", "note").as_deref(), Some("fit \"last\"\nby hand"));
+        metadata.remove(item, "com.example.panelizer", "tab");
+        assert!(metadata.items.is_empty());
+
+        assert_eq!(Metadata::sidecar_path("demo/demo.kicad_pcb".as_ref()), PathBuf::from("demo/demo.kicad_pcb.meta"));
+        let error = Metadata::parse("(kicad_metadata (item \"5F3C1A2B\" (field \"\" \"tab\" \"left\")))").unwrap_err();
+        assert_eq!(error.to_string(), "kicad_metadata > item: a field needs a namespace");
+        assert!(Metadata::parse("(kicad_metadata (version 2))").unwrap().check_version().is_err());
+    }
+}
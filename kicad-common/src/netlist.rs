@@ -0,0 +1,346 @@
+//! Netlists: the parts of a design and the pins each net connects, as
+//! KiCad exports them (`.net`, `(export (version "E") ...)`) or as a
+//! generator describes a circuit. skidl writes the KiCad format too.
+//!
+//! With the `json` feature a netlist can also be read from JSON with the
+//! fields of [`Netlist`], all optional but the references, names and pins:
+//!
+//! ```json
+//! {
+//!   "components": [
+//!     {"reference": "R1", "value": "10k", "lib": "Device", "part": "R",
+//!      "footprint": "Resistor_SMD:R_0603_1608Metric"}
+//!   ],
+//!   "nets": [
+//!     {"name": "VIN", "class": "Power", "nodes": [{"reference": "R1", "pin": "1"}]}
+//!   ]
+//! }
+//! ```
+//!
+//! ```no_run
+//! use kicad_common::netlist::Netlist;
+//!
+//! let netlist = Netlist::parse(&std::fs::read_to_string("demo.net")?)?;
+//! for net in &netlist.nets {
+//!     let pins: Vec<String> = net.nodes.iter().map(|node| format!("{}.{}", node.reference, node.pin)).collect();
+//!     println!("{}: {}", net.name, pins.join(" "));
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{collections::BTreeMap, fmt};
+
+use kicad_sexp::SexpBuilder;
+
+use crate::{
+    convert::{FromSexp, List, Result},
+    Error,
+};
+
+/// A netlist export, or a circuit description.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Netlist {
+    /// The format version, `E` for the one KiCad 6 and later write.
+    pub version: String,
+    /// The schematic the netlist was exported from.
+    pub source: Option<String>,
+    pub tool: Option<String>,
+    pub components: Vec<Component>,
+    pub nets: Vec<Net>,
+}
+
+/// A `(comp ...)`: one part, with all its units.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Component {
+    pub reference: String,
+    pub value: String,
+    /// The `Library:Footprint` of the part.
+    pub footprint: Option<String>,
+    /// Nickname of the symbol library the part comes from.
+    pub lib: Option<String>,
+    /// The symbol's name in that library.
+    pub part: Option<String>,
+    pub description: Option<String>,
+    /// The other fields, such as `MPN`.
+    pub fields: BTreeMap<String, String>,
+    /// The sheet path of the part by sheet names, `/` on the root sheet.
+    pub sheet: Option<String>,
+    /// The sheet path by UUIDs ending in the symbol's UUID, which links the
+    /// part's footprint on a board to its symbol.
+    pub path: Option<String>,
+}
+
+impl Component {
+    /// The `Library:Symbol` of the part, when the netlist names both.
+    pub fn lib_id(&self) -> Option<String> {
+        Some(format!("{}:{}", self.lib.as_deref()?, self.part.as_deref()?))
+    }
+}
+
+/// A `(net ...)` and the pins it connects.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Net {
+    /// Net number, from 1; 0 for descriptions that leave it to the reader.
+    pub code: u32,
+    pub name: String,
+    /// Net class, such as `Default` or `Power`.
+    pub class: Option<String>,
+    pub nodes: Vec<NetNode>,
+}
+
+/// A `(node ...)`: a pin on a net.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct NetNode {
+    pub reference: String,
+    /// Pin number.
+    pub pin: String,
+    /// Pin name, such as `GPIO0`.
+    pub function: Option<String>,
+    /// Electrical type, such as `passive` or `power_in`.
+    pub pin_type: Option<String>,
+}
+
+impl Netlist {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = src.len())))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        crate::convert::parse_document(src, "export")
+    }
+
+    /// Reads the JSON form described in the [module docs](self).
+    #[cfg(feature = "json")]
+    pub fn parse_json(src: &str) -> std::result::Result<Self, Error> {
+        serde_json::from_str(src).map_err(|e| {
+            let (line, column) = (e.line(), e.column());
+            let message = e.to_string();
+            let suffix = format!(" at line {} column {}", line, column);
+            let message = message.strip_suffix(&suffix).unwrap_or(&message).to_string();
+            Error::Json { message, line, column }
+        })
+    }
+
+    pub fn component(&self, reference: &str) -> Option<&Component> {
+        self.components.iter().find(|component| component.reference == reference)
+    }
+
+    pub fn net(&self, name: &str) -> Option<&Net> {
+        self.nets.iter().find(|net| net.name == name)
+    }
+
+    /// The net pin `pin` of the part `reference` is on.
+    pub fn net_of(&self, reference: &str, pin: &str) -> Option<&Net> {
+        self.nets.iter().find(|net| net.nodes.iter().any(|node| node.reference == reference && node.pin == pin))
+    }
+
+    /// The netlist in KiCad's export format; [`Display`](fmt::Display)
+    /// writes the whole file.
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let text = |tag: &str, value: Option<&str>| value.map(|value| SexpBuilder::new(tag).string(value));
+        let design = SexpBuilder::new("design")
+            .child_opt(text("source", self.source.as_deref()))
+            .child_opt(text("tool", self.tool.as_deref()));
+        let mut components = SexpBuilder::new("components");
+        for component in &self.components {
+            let libsource = (component.lib.is_some() || component.part.is_some()).then(|| {
+                SexpBuilder::new("libsource")
+                    .child_opt(text("lib", component.lib.as_deref()))
+                    .child_opt(text("part", component.part.as_deref()))
+                    .child_opt(text("description", component.description.as_deref()))
+            });
+            let fields = (!component.fields.is_empty()).then(|| {
+                let field = |(key, value): (&String, &String)| {
+                    SexpBuilder::new("field").child(SexpBuilder::new("name").string(key)).string(value)
+                };
+                component.fields.iter().map(field).fold(SexpBuilder::new("fields"), SexpBuilder::child)
+            });
+            // The path splits into the sheet's UUIDs and the symbol's.
+            let (sheet, uuid) = match component.path.as_deref().and_then(|path| path.rsplit_once('/')) {
+                Some((sheet, uuid)) => (Some(format!("{}/", sheet)), Some(uuid)),
+                None => (None, None),
+            };
+            let sheetpath = (component.sheet.is_some() || sheet.is_some()).then(|| {
+                SexpBuilder::new("sheetpath")
+                    .child_opt(text("names", component.sheet.as_deref()))
+                    .child_opt(text("tstamps", sheet.as_deref()))
+            });
+            components = components.child(
+                SexpBuilder::new("comp")
+                    .child(SexpBuilder::new("ref").string(&component.reference))
+                    .child(SexpBuilder::new("value").string(&component.value))
+                    .child_opt(text("footprint", component.footprint.as_deref()))
+                    .child_opt(fields)
+                    .child_opt(libsource)
+                    .child_opt(sheetpath)
+                    .child_opt(text("tstamps", uuid)),
+            );
+        }
+        let mut nets = SexpBuilder::new("nets");
+        for net in &self.nets {
+            let mut list = SexpBuilder::new("net")
+                .child(SexpBuilder::new("code").string(&net.code.to_string()))
+                .child(SexpBuilder::new("name").string(&net.name))
+                .child_opt(text("class", net.class.as_deref()));
+            for node in &net.nodes {
+                list = list.child(
+                    SexpBuilder::new("node")
+                        .child(SexpBuilder::new("ref").string(&node.reference))
+                        .child(SexpBuilder::new("pin").string(&node.pin))
+                        .child_opt(text("pinfunction", node.function.as_deref()))
+                        .child_opt(text("pintype", node.pin_type.as_deref())),
+                );
+            }
+            nets = nets.child(list);
+        }
+        let version = if self.version.is_empty() { "E" } else { &self.version };
+        SexpBuilder::new("export")
+            .child(SexpBuilder::new("version").string(version))
+            .child(design)
+            .child(components)
+            .child(nets)
+    }
+}
+
+impl fmt::Display for Netlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_sexp().build())
+    }
+}
+
+impl FromSexp for Netlist {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let design = list.child("design");
+        let field = |tag: &str| design.as_ref().map(|design| design.child_string(tag)).transpose();
+        let within = |e: crate::ConvertError| e.within(list.tag());
+        let components = list.child("components").map(|components| components.parse_all("comp"));
+        let nets = list.child("nets").map(|nets| nets.parse_all("net"));
+        let (components, nets) = (components.transpose().map_err(within)?, nets.transpose().map_err(within)?);
+        Ok(Netlist {
+            version: list.child_string("version")?.unwrap_or_default(),
+            source: field("source")?.flatten(),
+            tool: field("tool")?.flatten(),
+            components: components.unwrap_or_default(),
+            nets: nets.unwrap_or_default(),
+        })
+    }
+}
+
+impl FromSexp for Component {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let libsource = list.child("libsource");
+        let source = |tag: &str| libsource.as_ref().map(|libsource| libsource.child_string(tag)).transpose();
+        let sheetpath = list.child("sheetpath");
+        let sheet_path = |tag: &str| {
+            let path = sheetpath.as_ref().map(|path| path.child_string(tag));
+            path.transpose().map(Option::flatten)
+        };
+        // KiCad 7 and later write the UUIDs of all units; KiCad 6 one tstamp.
+        let uuid = list.child_string("tstamps")?.or(list.child_string("tstamp")?);
+        let uuid = uuid.and_then(|uuids| uuids.split_whitespace().next().map(str::to_string));
+        let mut fields = BTreeMap::new();
+        for field in list.child("fields").iter().flat_map(|fields| fields.children("field")) {
+            let name = field.child_string("name")?.ok_or_else(|| field.error("missing (name ...)"))?;
+            fields.insert(name, field.string(1).unwrap_or_default());
+        }
+        Ok(Component {
+            reference: list.child_string("ref")?.ok_or_else(|| list.error("missing (ref ...)"))?,
+            value: list.child_string("value")?.unwrap_or_default(),
+            footprint: list.child_string("footprint")?.filter(|footprint| !footprint.is_empty()),
+            lib: source("lib")?.flatten(),
+            part: source("part")?.flatten(),
+            description: source("description")?.flatten(),
+            fields,
+            sheet: sheet_path("names")?,
+            path: match (sheet_path("tstamps")?, uuid) {
+                (Some(sheet), Some(uuid)) => Some(format!("{}/{}", sheet.trim_end_matches('/'), uuid)),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl FromSexp for Net {
+    fn from_sexp(list: &List) -> Result<Self> {
+        // KiCad quotes the code.
+        let code = match list.child_string("code")? {
+            Some(code) => code.parse().map_err(|_| list.error(format!("expected a net code, found `{}`", code)))?,
+            None => 0,
+        };
+        Ok(Net {
+            code,
+            name: list.child_string("name")?.ok_or_else(|| list.error("missing (name ...)"))?,
+            class: list.child_string("class")?,
+            nodes: list.parse_all("node")?,
+        })
+    }
+}
+
+impl FromSexp for NetNode {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(NetNode {
+            reference: list.child_string("ref")?.ok_or_else(|| list.error("missing (ref ...)"))?,
+            pin: list.child_string("pin")?.ok_or_else(|| list.error("missing (pin ...)"))?,
+            function: list.child_string("pinfunction")?,
+            pin_type: list.child_string("pintype")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.net");
+
+    #[test]
+    fn demo() {
+        let netlist = Netlist::parse(DEMO).unwrap();
+        assert_eq!((netlist.version.as_str(), netlist.source.as_deref()), ("E", Some("demo.kicad_sch")));
+        let references: Vec<_> = netlist.components.iter().map(|component| component.reference.as_str()).collect();
+        assert_eq!(references, ["J1", "R1", "R2"]);
+        let r1 = netlist.component("R1").unwrap();
+        assert_eq!((r1.value.as_str(), r1.lib_id().as_deref()), ("10k", Some("Device:R")));
+        assert_eq!(r1.footprint.as_deref(), Some("Resistor_SMD:R_0603_1608Metric"));
+        assert_eq!(r1.sheet.as_deref(), Some("/"));
+
+        let vout = netlist.net("VOUT").unwrap();
+        assert_eq!((vout.code, vout.class.as_deref(), vout.nodes.len()), (2, Some("Default"), 3));
+        assert_eq!(vout.nodes[2].pin_type.as_deref(), Some("passive"));
+        assert_eq!(netlist.net_of("R2", "2").map(|net| net.name.as_str()), Some("GND"));
+        assert_eq!(netlist.net_of("R2", "3"), None);
+        let mut fields = netlist.clone();
+        fields.components[0].fields.insert("MPN".to_string(), "PH1-03".to_string());
+        assert_eq!(Netlist::parse(&fields.to_string()).unwrap(), fields);
+
+        let sheetpath = "(sheetpath (names \"/Power/\") (tstamps \"/5f3c1a2b/\"))";
+        let comp = format!("(comp (ref \"R1\") {} (tstamps \"7dfecbba\"))", sheetpath);
+        let netlist = Netlist::parse(&format!("(export (version \"E\") (components {}))", comp)).unwrap();
+        let r1 = &netlist.components[0];
+        assert_eq!((r1.sheet.as_deref(), r1.path.as_deref()), (Some("/Power/"), Some("/5f3c1a2b/7dfecbba")));
+        assert_eq!(Netlist::parse(DEMO).unwrap().components[0].path, None);
+        assert_eq!(Netlist::parse(&netlist.to_string()).unwrap(), netlist);
+
+        let error = Netlist::parse("(export (nets (net (code \"1\"))))").unwrap_err();
+        assert_eq!(error.to_string(), "export > nets > net: missing (name ...)");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        let src = r#"{
+            "components": [{"reference": "R1", "value": "10k", "lib": "Device", "part": "R"}],
+            "nets": [{"name": "VIN", "nodes": [{"reference": "R1", "pin": "1"}]}]
+        }"#;
+        let netlist = Netlist::parse_json(src).unwrap();
+        assert_eq!(netlist.component("R1").unwrap().lib_id().as_deref(), Some("Device:R"));
+        assert_eq!(netlist.net_of("R1", "1").unwrap().name, "VIN");
+        let error = Netlist::parse_json("{\"nets\": 1}").unwrap_err();
+        assert!(matches!(error, Error::Json { line: 1, .. }), "{}", error);
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+
+use kicad_sexp::{SexpBuilder, SexpOwned};
+
+use crate::convert::{FromSexp, List, Result};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PaperSize {
+    A5,
+    #[default]
+    A4,
+    A3,
+    A2,
+    A1,
+    A0,
+    A,
+    B,
+    C,
+    D,
+    E,
+    Gerber,
+    USLetter,
+    USLegal,
+    USLedger,
+    /// Custom size in millimeters.
+    User { width: f64, height: f64 },
+}
+
+impl PaperSize {
+    /// Landscape width and height in millimeters.
+    pub fn dimensions(&self) -> (f64, f64) {
+        match *self {
+            PaperSize::A5 => (210.0, 148.0),
+            PaperSize::A4 => (297.0, 210.0),
+            PaperSize::A3 => (420.0, 297.0),
+            PaperSize::A2 => (594.0, 420.0),
+            PaperSize::A1 => (841.0, 594.0),
+            PaperSize::A0 => (1189.0, 841.0),
+            PaperSize::A => (279.4, 215.9),
+            PaperSize::B => (431.8, 279.4),
+            PaperSize::C => (558.8, 431.8),
+            PaperSize::D => (863.6, 558.8),
+            PaperSize::E => (1117.6, 863.6),
+            PaperSize::Gerber => (812.8, 812.8),
+            PaperSize::USLetter => (279.4, 215.9),
+            PaperSize::USLegal => (355.6, 215.9),
+            PaperSize::USLedger => (431.8, 279.4),
+            PaperSize::User { width, height } => (width, height),
+        }
+    }
+}
+
+/// Page setup, as in `(paper "A4" portrait)` or `(paper "User" 100 80)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Paper {
+    pub size: PaperSize,
+    pub portrait: bool,
+}
+
+impl Paper {
+    /// The `(paper ...)` list as KiCad writes it.
+    pub fn to_sexp(&self) -> SexpOwned {
+        let name = match self.size {
+            PaperSize::A5 => "A5",
+            PaperSize::A4 => "A4",
+            PaperSize::A3 => "A3",
+            PaperSize::A2 => "A2",
+            PaperSize::A1 => "A1",
+            PaperSize::A0 => "A0",
+            PaperSize::A => "A",
+            PaperSize::B => "B",
+            PaperSize::C => "C",
+            PaperSize::D => "D",
+            PaperSize::E => "E",
+            PaperSize::Gerber => "GERBER",
+            PaperSize::USLetter => "USLetter",
+            PaperSize::USLegal => "USLegal",
+            PaperSize::USLedger => "USLedger",
+            PaperSize::User { width, height } => {
+                return SexpBuilder::new("paper").string("User").float(width).float(height).build();
+            },
+        };
+        let paper = SexpBuilder::new("paper").string(name);
+        if self.portrait { paper.symbol("portrait") } else { paper }.build()
+    }
+
+    /// Width and height in millimeters, taking orientation into account.
+    pub fn dimensions(&self) -> (f64, f64) {
+        let (width, height) = self.size.dimensions();
+        match self.size {
+            PaperSize::User { .. } => (width, height),
+            _ if self.portrait => (height, width),
+            _ => (width, height),
+        }
+    }
+}
+
+impl FromSexp for Paper {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let name = list.string(0)?;
+        let size = match name.as_str() {
+            "A5" => PaperSize::A5,
+            "A4" => PaperSize::A4,
+            "A3" => PaperSize::A3,
+            "A2" => PaperSize::A2,
+            "A1" => PaperSize::A1,
+            "A0" => PaperSize::A0,
+            "A" => PaperSize::A,
+            "B" => PaperSize::B,
+            "C" => PaperSize::C,
+            "D" => PaperSize::D,
+            "E" => PaperSize::E,
+            "GERBER" => PaperSize::Gerber,
+            "USLetter" => PaperSize::USLetter,
+            "USLegal" => PaperSize::USLegal,
+            "USLedger" => PaperSize::USLedger,
+            "User" => PaperSize::User { width: list.number(1)?, height: list.number(2)? },
+            other => return Err(list.error(format!("unknown paper size `{}`", other))),
+        };
+        Ok(Paper { size, portrait: list.has_symbol("portrait") })
+    }
+}
+
+/// The drawing sheet title block.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TitleBlock {
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub rev: Option<String>,
+    pub company: Option<String>,
+    /// Numbered comments, `(comment 1 "...")` through `(comment 9 "...")`.
+    pub comments: BTreeMap<u8, String>,
+}
+
+impl TitleBlock {
+    /// The `(title_block ...)` list as KiCad writes it, leaving out unset
+    /// fields.
+    pub fn to_sexp(&self) -> SexpOwned {
+        let field = |tag: &str, value: &Option<String>| value.as_deref().map(|v| SexpBuilder::new(tag).string(v));
+        let block = SexpBuilder::new("title_block")
+            .child_opt(field("title", &self.title))
+            .child_opt(field("date", &self.date))
+            .child_opt(field("rev", &self.rev))
+            .child_opt(field("company", &self.company));
+        let comment = |(n, text): (&u8, &String)| SexpBuilder::new("comment").int((*n).into()).string(text);
+        self.comments.iter().fold(block, |block, entry| block.child(comment(entry))).build()
+    }
+}
+
+impl FromSexp for TitleBlock {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let comments = list
+            .children("comment")
+            .map(|comment| Ok((comment.number(0)?, comment.string(1)?)))
+            .collect::<Result<_>>()
+            .map_err(|e| e.within(list.tag()))?;
+        Ok(TitleBlock {
+            title: list.child_string("title")?,
+            date: list.child_string("date")?,
+            rev: list.child_string("rev")?,
+            company: list.child_string("company")?,
+            comments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert<T: FromSexp>(src: &str) -> T {
+        let sexps = kicad_sexp::parse(src).unwrap();
+        T::from_sexp(&List::new(&sexps[0]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn paper() {
+        assert_eq!(convert::<Paper>("(paper \"A4\")").dimensions(), (297.0, 210.0));
+        assert_eq!(convert::<Paper>("(paper \"A5\" portrait)").dimensions(), (148.0, 210.0));
+        assert_eq!(
+            convert::<Paper>("(paper \"User\" 100 80.5)").size,
+            PaperSize::User { width: 100.0, height: 80.5 }
+        );
+    }
+
+    #[test]
+    fn title_block() {
+        let title_block: TitleBlock =
+            convert("(title_block (title \"Demo\") (rev \"1.2\") (comment 1 \"first\") (comment 4 \"fourth\"))");
+
+        assert_eq!(title_block.title.as_deref(), Some("Demo"));
+        assert_eq!(title_block.rev.as_deref(), Some("1.2"));
+        assert_eq!(title_block.date, None);
+        assert_eq!(title_block.comments[&4], "fourth");
+        assert_eq!(title_block.comments.len(), 2);
+    }
+
+    #[test]
+    fn round_trip() {
+        for src in ["(paper \"A4\")", "(paper \"A5\" portrait)", "(paper \"User\" 100 80.5)", "(paper \"GERBER\")"] {
+            assert_eq!(convert::<Paper>(src).to_sexp().to_string(), src);
+        }
+        let src = "(title_block\n\t(title \"Demo\")\n\t(rev \"1.2\")\n\t(comment 1 \"first\")\n)";
+        assert_eq!(convert::<TitleBlock>(src).to_sexp().to_string(), src);
+    }
+}
@@ -0,0 +1,82 @@
+use kicad_sexp::SexpBuilder;
+
+use crate::{
+    convert::{FromSexp, List, Result},
+    geometry::Position,
+    text::Effects,
+    uuid::{item_uuid, Uuid},
+};
+
+/// A named field, as in `(property "Reference" "R1" (at ...) (effects ...))`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Property {
+    pub key: String,
+    pub value: String,
+    pub at: Option<Position>,
+    pub effects: Option<Effects>,
+    /// Hidden either directly or through its effects.
+    pub hide: bool,
+    pub show_name: bool,
+    pub do_not_autoplace: bool,
+    pub uuid: Option<Uuid>,
+}
+
+impl Property {
+    /// The property as a symbol library writes it, hidden through its
+    /// effects.
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let yes = |tag: &str, on: bool| on.then(|| SexpBuilder::new(tag).symbol("yes"));
+        let effects = match (&self.effects, self.hide) {
+            (Some(effects), hide) => Some(Effects { hide, ..effects.clone() }),
+            (None, true) => Some(Effects { hide: true, ..Default::default() }),
+            (None, false) => None,
+        };
+        SexpBuilder::new("property")
+            .string(&self.key)
+            .string(&self.value)
+            .child_opt(self.at.map(|at| SexpBuilder::new("at").float(at.x).float(at.y).float(at.angle)))
+            .child_opt(yes("show_name", self.show_name))
+            .child_opt(yes("do_not_autoplace", self.do_not_autoplace))
+            .child_opt(self.uuid.map(|uuid| SexpBuilder::new("uuid").string(&uuid.to_string())))
+            .child_opt(effects.map(|effects| effects.to_sexp()))
+    }
+}
+
+impl FromSexp for Property {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let effects: Option<Effects> = list.parse("effects")?;
+        Ok(Property {
+            key: list.string(0)?,
+            value: list.string(1)?,
+            at: list.parse("at")?,
+            hide: list.flag("hide")? || effects.as_ref().is_some_and(|effects| effects.hide),
+            effects,
+            show_name: list.flag("show_name")?,
+            do_not_autoplace: list.flag("do_not_autoplace")?,
+            uuid: item_uuid(list)?,
+        })
+    }
+}
+
+/// A field the project adds to every symbol, from the `field_names` of its
+/// schematic settings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FieldTemplate {
+    pub name: String,
+    /// Whether the field is shown on the sheet.
+    pub visible: bool,
+    /// Whether the value is a link.
+    pub url: bool,
+}
+
+/// The fields KiCad gives every symbol. `Description` is only mandatory
+/// from KiCad 8 on.
+pub const MANDATORY_FIELDS: [&str; 5] = ["Reference", "Value", "Footprint", "Datasheet", "Description"];
+
+/// Looks up the value of the property named `key`.
+pub fn property<'p>(properties: &'p [Property], key: &str) -> Option<&'p str> {
+    properties.iter().find(|property| property.key == key).map(|property| property.value.as_str())
+}
@@ -0,0 +1,15 @@
+//! Reference designators such as `R10` or `U3A`.
+
+use std::cmp::Ordering;
+
+/// Compares references by prefix, then by number, so `R2` sorts before
+/// `R10`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    fn split(reference: &str) -> (&str, Option<u64>, &str) {
+        let digits = reference.find(|c: char| c.is_ascii_digit()).unwrap_or(reference.len());
+        let (prefix, rest) = reference.split_at(digits);
+        let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        (prefix, rest[..end].parse().ok(), &rest[end..])
+    }
+    split(a).cmp(&split(b)).then_with(|| a.cmp(b))
+}
@@ -0,0 +1,186 @@
+use kicad_sexp::SexpBuilder;
+
+use crate::{
+    convert::{FromSexp, List, Result},
+    graphics::Color,
+};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Font {
+    /// Outline font name; `None` is the KiCad stroke font.
+    pub face: Option<String>,
+    pub height: f64,
+    pub width: f64,
+    pub thickness: Option<f64>,
+    pub bold: bool,
+    pub italic: bool,
+    pub line_spacing: Option<f64>,
+    pub color: Option<Color>,
+}
+
+impl Font {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let yes = |tag: &str, on: bool| on.then(|| SexpBuilder::new(tag).symbol("yes"));
+        SexpBuilder::new("font")
+            .child_opt(self.face.as_deref().map(|face| SexpBuilder::new("face").string(face)))
+            .child(SexpBuilder::new("size").float(self.height).float(self.width))
+            .child_opt(self.thickness.map(|thickness| SexpBuilder::new("thickness").float(thickness)))
+            .child_opt(yes("bold", self.bold))
+            .child_opt(yes("italic", self.italic))
+            .child_opt(self.line_spacing.map(|spacing| SexpBuilder::new("line_spacing").float(spacing)))
+            .child_opt(self.color.map(|color| color.to_sexp()))
+    }
+}
+
+impl FromSexp for Font {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let (height, width) = match list.child("size") {
+            Some(size) => (
+                size.number(0).map_err(|e| e.within(list.tag()))?,
+                size.number(1).map_err(|e| e.within(list.tag()))?,
+            ),
+            None => (1.27, 1.27),
+        };
+        Ok(Font {
+            face: list.child_string("face")?,
+            height,
+            width,
+            thickness: list.child_number("thickness")?,
+            bold: list.flag("bold")?,
+            italic: list.flag("italic")?,
+            line_spacing: list.child_number("line_spacing")?,
+            color: list.parse("color")?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VAlign {
+    Top,
+    #[default]
+    Center,
+    Bottom,
+}
+
+/// Text alignment, as in `(justify left bottom mirror)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Justify {
+    pub horizontal: HAlign,
+    pub vertical: VAlign,
+    pub mirror: bool,
+}
+
+impl Justify {
+    /// The `(justify ...)` list; `None` for centered, unmirrored text.
+    pub fn to_sexp(&self) -> Option<SexpBuilder> {
+        let mut justify = SexpBuilder::new("justify");
+        match self.horizontal {
+            HAlign::Left => justify = justify.symbol("left"),
+            HAlign::Center => {},
+            HAlign::Right => justify = justify.symbol("right"),
+        }
+        match self.vertical {
+            VAlign::Top => justify = justify.symbol("top"),
+            VAlign::Center => {},
+            VAlign::Bottom => justify = justify.symbol("bottom"),
+        }
+        if self.mirror {
+            justify = justify.symbol("mirror");
+        }
+        (*self != Justify::default()).then_some(justify)
+    }
+}
+
+impl FromSexp for Justify {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mut justify = Justify::default();
+        for index in 0..list.args().len() {
+            match list.symbol(index)? {
+                "left" => justify.horizontal = HAlign::Left,
+                "right" => justify.horizontal = HAlign::Right,
+                "top" => justify.vertical = VAlign::Top,
+                "bottom" => justify.vertical = VAlign::Bottom,
+                "mirror" => justify.mirror = true,
+                other => return Err(list.error(format!("unknown justification `{}`", other))),
+            }
+        }
+        Ok(justify)
+    }
+}
+
+/// Text appearance, as in `(effects (font ...) (justify ...) (hide yes))`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Effects {
+    pub font: Font,
+    pub justify: Justify,
+    pub hide: bool,
+    pub href: Option<String>,
+}
+
+impl Effects {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        SexpBuilder::new("effects")
+            .child(self.font.to_sexp())
+            .child_opt(self.justify.to_sexp())
+            .child_opt(self.hide.then(|| SexpBuilder::new("hide").symbol("yes")))
+            .child_opt(self.href.as_deref().map(|href| SexpBuilder::new("href").string(href)))
+    }
+}
+
+impl FromSexp for Effects {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Effects {
+            font: list.parse("font")?.unwrap_or_default(),
+            justify: list.parse("justify")?.unwrap_or_default(),
+            hide: list.flag("hide")?,
+            href: list.child_string("href")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects() {
+        let sexps = kicad_sexp::parse(
+            "(effects (font (face \"Arial\") (size 2 1.5) (thickness 0.3) (bold yes)) (justify left bottom) (hide yes))",
+        )
+        .unwrap();
+        let effects = Effects::from_sexp(&List::new(&sexps[0]).unwrap()).unwrap();
+
+        assert_eq!(effects.font.face.as_deref(), Some("Arial"));
+        assert_eq!((effects.font.height, effects.font.width), (2.0, 1.5));
+        assert_eq!(effects.font.thickness, Some(0.3));
+        assert!(effects.font.bold && !effects.font.italic);
+        assert_eq!(effects.justify, Justify { horizontal: HAlign::Left, vertical: VAlign::Bottom, mirror: false });
+        assert!(effects.hide);
+
+        let written = effects.to_sexp().build();
+        let written = written.as_sexp();
+        assert_eq!(Effects::from_sexp(&List::new(&written).unwrap()).unwrap(), effects);
+    }
+
+    #[test]
+    fn legacy_hide() {
+        let sexps = kicad_sexp::parse("(effects (font (size 1.27 1.27)) (justify mirror) hide)").unwrap();
+        let effects = Effects::from_sexp(&List::new(&sexps[0]).unwrap()).unwrap();
+
+        assert!(effects.hide && effects.justify.mirror);
+        assert_eq!(effects.justify.horizontal, HAlign::Center);
+    }
+}
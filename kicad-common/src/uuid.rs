@@ -0,0 +1,269 @@
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    fmt,
+    hash::{BuildHasher, Hasher},
+    str::FromStr,
+};
+
+use kicad_sexp::Sexp;
+
+use crate::convert::{atom_str, ConvertError, FromSexp, List, Result};
+
+/// A KiCad item identifier.
+///
+/// Legacy 32-bit timestamps (`(tstamp 5F3C1A2B)`) are widened the same way
+/// KiCad does when it upgrades old files.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(u128);
+
+impl Uuid {
+    pub const NIL: Uuid = Uuid(0);
+
+    pub const fn from_u128(value: u128) -> Self {
+        Uuid(value)
+    }
+
+    pub const fn as_u128(&self) -> u128 {
+        self.0
+    }
+
+    /// The name-based version 5 UUID of `name` in `namespace`, as specified
+    /// in RFC 9562.
+    pub fn new_v5(namespace: Uuid, name: &[u8]) -> Self {
+        let mut sha1 = sha1_smol::Sha1::new();
+        sha1.update(&namespace.0.to_be_bytes());
+        sha1.update(name);
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&sha1.digest().bytes()[..16]);
+        Uuid::with_version(u128::from_be_bytes(bytes), 5)
+    }
+
+    /// Sets the version and RFC 9562 variant bits of `bits`.
+    const fn with_version(bits: u128, version: u8) -> Self {
+        let bits = bits & !(0xf << 76) | (version as u128) << 76;
+        Uuid(bits & !(0b11 << 62) | 0b10 << 62)
+    }
+
+    /// The version field: 4 for the random UUIDs KiCad makes, 5 for
+    /// [`Uuid::new_v5`].
+    pub const fn version(&self) -> u8 {
+        (self.0 >> 76) as u8 & 0xf
+    }
+
+    /// Converts an atom holding a UUID, quoted or not.
+    pub fn from_atom(sexp: &Sexp) -> Result<Self> {
+        let text = atom_str(sexp).ok_or_else(|| ConvertError::new("expected a UUID"))?;
+        text.parse().map_err(|_| ConvertError::new(format!("invalid UUID `{}`", text)))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseUuidError;
+
+impl fmt::Display for ParseUuidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid UUID")
+    }
+}
+
+impl std::error::Error for ParseUuidError {}
+
+impl FromStr for Uuid {
+    type Err = ParseUuidError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let hex = |s: &str| u128::from_str_radix(s, 16).map_err(|_| ParseUuidError);
+        if s.len() <= 8 && !s.is_empty() && !s.starts_with('+') {
+            return hex(s).map(Uuid);
+        }
+        let groups: Vec<&str> = s.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] || groups.iter().any(|group| !group.bytes().all(|b| b.is_ascii_hexdigit())) {
+            return Err(ParseUuidError);
+        }
+        hex(&groups.concat()).map(Uuid)
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            (v >> 96) as u32,
+            (v >> 80) as u16,
+            (v >> 64) as u16,
+            (v >> 48) as u16,
+            v & 0xffff_ffff_ffff
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Uuid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Uuid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        crate::convert::deserialize_from_str(deserializer)
+    }
+}
+
+impl fmt::Debug for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Uuid({})", self)
+    }
+}
+
+/// Namespace that [`UuidGenerator::deterministic`] seeds are hashed in.
+const SEED_NAMESPACE: Uuid = Uuid(0x4b1c_ad0e_5f2d_4c6a_9e3b_71d8_02a5_f6c4);
+
+/// Hands out UUIDs for newly created items.
+///
+/// KiCad itself makes random UUIDs, which is what [`UuidGenerator::random`]
+/// does. A [`UuidGenerator::deterministic`] generator instead derives each
+/// UUID from a seed and a description of the item, so a generator run
+/// twice on the same input writes the same file.
+#[derive(Clone, Debug)]
+pub struct UuidGenerator {
+    mode: Mode,
+    /// Everything handed out or reserved, so no UUID is used twice.
+    used: HashSet<Uuid>,
+}
+
+#[derive(Clone, Debug)]
+enum Mode {
+    Random(RandomState),
+    Deterministic(Uuid),
+}
+
+impl UuidGenerator {
+    /// Random version 4 UUIDs. Good enough to keep items apart, but not
+    /// for anything secret.
+    pub fn random() -> Self {
+        UuidGenerator { mode: Mode::Random(RandomState::new()), used: HashSet::new() }
+    }
+
+    /// Version 5 UUIDs of item descriptions, namespaced by `seed`.
+    pub fn deterministic(seed: &str) -> Self {
+        let namespace = Uuid::new_v5(SEED_NAMESPACE, seed.as_bytes());
+        UuidGenerator { mode: Mode::Deterministic(namespace), used: HashSet::new() }
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        matches!(self.mode, Mode::Deterministic(_))
+    }
+
+    /// Marks UUIDs already in the document as taken.
+    pub fn reserve(&mut self, uuids: impl IntoIterator<Item = Uuid>) {
+        self.used.extend(uuids);
+    }
+
+    /// A new UUID for an item described by `content`, e.g. its kind and
+    /// position. In deterministic mode, items with the same description get
+    /// distinct UUIDs in the order they are generated.
+    pub fn generate(&mut self, content: impl AsRef<[u8]>) -> Uuid {
+        let content = content.as_ref();
+        for attempt in 0u32.. {
+            let uuid = match &self.mode {
+                Mode::Random(state) => {
+                    let mut hasher = state.build_hasher();
+                    hasher.write_usize(self.used.len());
+                    hasher.write_u32(attempt);
+                    let high = hasher.finish();
+                    hasher.write_u8(0);
+                    Uuid::with_version(u128::from(high) << 64 | u128::from(hasher.finish()), 4)
+                },
+                Mode::Deterministic(namespace) if attempt == 0 => Uuid::new_v5(*namespace, content),
+                Mode::Deterministic(namespace) => {
+                    Uuid::new_v5(*namespace, &[content, b"#", attempt.to_string().as_bytes()].concat())
+                },
+            };
+            if self.used.insert(uuid) {
+                return uuid;
+            }
+        }
+        unreachable!("ran out of attempts")
+    }
+}
+
+impl Default for UuidGenerator {
+    fn default() -> Self {
+        UuidGenerator::random()
+    }
+}
+
+/// Reads `(uuid ...)`.
+impl FromSexp for Uuid {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Uuid::from_atom(list.arg(0)?).map_err(|e| e.within(list.tag()))
+    }
+}
+
+/// Reads the `(uuid ...)` child of `list`, falling back to a legacy `(tstamp ...)`.
+pub fn item_uuid(list: &List) -> Result<Option<Uuid>> {
+    match list.parse("uuid")? {
+        Some(uuid) => Ok(Some(uuid)),
+        None => list.parse("tstamp"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        let uuid: Uuid = "7dfecbba-c7b6-4b01-9f5f-e26c00ad6670".parse().unwrap();
+
+        assert_eq!(uuid.as_u128(), 0x7dfecbba_c7b6_4b01_9f5f_e26c00ad6670);
+        assert_eq!(uuid.to_string(), "7dfecbba-c7b6-4b01-9f5f-e26c00ad6670");
+        assert_eq!("5F3C1A2B".parse::<Uuid>().unwrap().to_string(), "00000000-0000-0000-0000-00005f3c1a2b");
+        assert_eq!("7dfecbba-c7b6-4b01-9f5f".parse::<Uuid>(), Err(ParseUuidError));
+        assert_eq!("7dfecbba-c7b6-4b01-9f5f-e26c00ad667g".parse::<Uuid>(), Err(ParseUuidError));
+    }
+
+    #[test]
+    fn generate() {
+        let url = Uuid::from_u128(0x6ba7b811_9dad_11d1_80b4_00c04fd430c8);
+        assert_eq!(Uuid::new_v5(url, b"python.org").to_string(), "7af94e2b-4dd9-50f0-9c9a-8a48519bdef0");
+
+        let run = || {
+            let mut uuids = UuidGenerator::deterministic("board-v1");
+            ["via 1 2", "via 1 2", "track 0 0 1 1"].map(|item| uuids.generate(item))
+        };
+        let uuids = run();
+        assert_eq!(uuids, run());
+        assert!(uuids[0] != uuids[1] && uuids.iter().all(|uuid| uuid.version() == 5));
+        assert_ne!(UuidGenerator::deterministic("board-v2").generate("via 1 2"), uuids[0]);
+
+        let mut reserved = UuidGenerator::deterministic("board-v1");
+        reserved.reserve([uuids[0]]);
+        assert_eq!(reserved.generate("via 1 2"), uuids[1]);
+
+        let mut random = UuidGenerator::random();
+        let (a, b) = (random.generate("via 1 2"), random.generate("via 1 2"));
+        assert!(a != b && a.version() == 4 && (a.as_u128() >> 62) & 0b11 == 0b10);
+        assert!(!random.is_deterministic());
+    }
+
+    #[test]
+    fn item_uuids() {
+        let sexps = kicad_sexp::parse(concat!(
+            "(a (uuid 7dfecbba-c7b6-4b01-9f5f-e26c00ad6670)) (b (uuid \"7dfecbba-c7b6-4b01-9f5f-e26c00ad6670\")) ",
+            "(c (tstamp 12345678)) (d) (e (tstamp 5E123456))",
+        ))
+        .unwrap();
+        let uuids: Vec<_> = sexps.iter().map(|sexp| item_uuid(&List::new(sexp).unwrap()).unwrap()).collect();
+
+        assert_eq!(uuids[0], uuids[1]);
+        assert_eq!(uuids[2], Some(Uuid::from_u128(0x12345678)));
+        assert_eq!(uuids[3], None);
+        assert_eq!(uuids[4], Some(Uuid::from_u128(0x5e123456)));
+    }
+}
@@ -0,0 +1,242 @@
+use kicad_common::ConvertError;
+
+use crate::{BinaryOp, Condition, Expr, UnaryOp, Unit};
+
+/// What an expression evaluates to. Comparisons give numbers, 1 for true
+/// and 0 for false, as in KiCad.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    /// Lengths in millimeters, angles in degrees.
+    Number(f64),
+    String(String),
+    /// A property the item does not have; equal to nothing.
+    Undefined,
+}
+
+impl Outcome {
+    pub fn bool(value: bool) -> Self {
+        Outcome::Number(if value { 1.0 } else { 0.0 })
+    }
+
+    pub fn is_true(&self) -> bool {
+        match self {
+            Outcome::Number(number) => *number != 0.0,
+            Outcome::String(text) => !text.is_empty(),
+            Outcome::Undefined => false,
+        }
+    }
+
+    /// The number, or the string read as one, as in `'0.2mm'`.
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            Outcome::Number(number) => Some(*number),
+            Outcome::String(text) => {
+                let value: crate::Value = text.trim().parse().ok()?;
+                value.millimeters().or(Some(value.number))
+            },
+            Outcome::Undefined => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Outcome::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Equality as KiCad has it: strings ignore case, and `*` and `?` in
+    /// the right-hand string are wildcards.
+    fn equals(&self, other: &Outcome) -> bool {
+        match (self, other) {
+            (Outcome::Undefined, _) | (_, Outcome::Undefined) => false,
+            (Outcome::String(a), Outcome::String(b)) => wildcard_match(b, a),
+            (a, b) => match (a.number(), b.number()) {
+                (Some(a), Some(b)) => (a - b).abs() < 1e-9,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// An item a rule expression's `A` or `B` stands for, such as a footprint
+/// or a track.
+pub trait Item {
+    /// A property such as `Type` or `NetClass`; `None` if the item has no
+    /// such property, which evaluates to [`Outcome::Undefined`].
+    fn property(&self, name: &str) -> Option<Outcome>;
+
+    /// A function such as `hasComponentClass('Power')`; `None` if the item
+    /// has no such function, which is an error.
+    fn call(&self, name: &str, args: &[Outcome]) -> Option<Outcome>;
+}
+
+impl Expr {
+    /// Evaluates the expression with `A` standing for `a` and `B` for `b`.
+    /// Fails on unknown functions, on objects other than `A` and `B`, and
+    /// on arithmetic with strings.
+    pub fn eval(&self, a: &dyn Item, b: Option<&dyn Item>) -> Result<Outcome, ConvertError> {
+        let item = |object: &str| match object {
+            "A" => Ok(a),
+            "B" => b.ok_or_else(|| ConvertError::new("no item B to evaluate against".to_string())),
+            _ => Err(ConvertError::new(format!("unknown object `{}`", object))),
+        };
+        Ok(match self {
+            Expr::Number(value) => Outcome::Number(match value.unit {
+                Unit::Deg => value.number,
+                _ => value.millimeters().unwrap_or(value.number),
+            }),
+            Expr::String(text) => Outcome::String(text.clone()),
+            Expr::Ident(name) => return Err(ConvertError::new(format!("unexpected name `{}`", name))),
+            Expr::Property { object, name } => item(object)?.property(name).unwrap_or(Outcome::Undefined),
+            Expr::Call { object, name, args } => {
+                let args = args.iter().map(|arg| arg.eval(a, b)).collect::<Result<Vec<_>, _>>()?;
+                item(object)?
+                    .call(name, &args)
+                    .ok_or_else(|| ConvertError::new(format!("unknown function {}.{}()", object, name)))?
+            },
+            Expr::Unary { op: UnaryOp::Not, expr } => Outcome::bool(!expr.eval(a, b)?.is_true()),
+            Expr::Unary { op: UnaryOp::Neg, expr } => Outcome::Number(-arithmetic(&expr.eval(a, b)?, self)?),
+            Expr::Binary { op: BinaryOp::And, lhs, rhs } => {
+                Outcome::bool(lhs.eval(a, b)?.is_true() && rhs.eval(a, b)?.is_true())
+            },
+            Expr::Binary { op: BinaryOp::Or, lhs, rhs } => {
+                Outcome::bool(lhs.eval(a, b)?.is_true() || rhs.eval(a, b)?.is_true())
+            },
+            Expr::Binary { op, lhs, rhs } => {
+                let (lhs, rhs) = (lhs.eval(a, b)?, rhs.eval(a, b)?);
+                let compare = |test: fn(f64, f64) -> bool| match (lhs.number(), rhs.number()) {
+                    (Some(l), Some(r)) => Outcome::bool(test(l, r)),
+                    _ => Outcome::bool(false),
+                };
+                match op {
+                    BinaryOp::Eq => Outcome::bool(lhs.equals(&rhs)),
+                    BinaryOp::Ne => Outcome::bool(!lhs.equals(&rhs)),
+                    BinaryOp::Lt => compare(|l, r| l < r),
+                    BinaryOp::Le => compare(|l, r| l <= r),
+                    BinaryOp::Gt => compare(|l, r| l > r),
+                    BinaryOp::Ge => compare(|l, r| l >= r),
+                    BinaryOp::Add => Outcome::Number(arithmetic(&lhs, self)? + arithmetic(&rhs, self)?),
+                    BinaryOp::Sub => Outcome::Number(arithmetic(&lhs, self)? - arithmetic(&rhs, self)?),
+                    BinaryOp::Mul => Outcome::Number(arithmetic(&lhs, self)? * arithmetic(&rhs, self)?),
+                    BinaryOp::Div => Outcome::Number(arithmetic(&lhs, self)? / arithmetic(&rhs, self)?),
+                    BinaryOp::And | BinaryOp::Or => unreachable!("handled above"),
+                }
+            },
+        })
+    }
+}
+
+impl Condition {
+    /// Whether the condition holds for `a` and `b`, as [`Expr::eval`]
+    /// evaluates it.
+    pub fn matches(&self, a: &dyn Item, b: Option<&dyn Item>) -> Result<bool, ConvertError> {
+        Ok(self.expr.eval(a, b)?.is_true())
+    }
+}
+
+fn arithmetic(operand: &Outcome, expr: &Expr) -> Result<f64, ConvertError> {
+    operand.number().ok_or_else(|| ConvertError::new(format!("`{}` needs numbers", expr)))
+}
+
+/// Matches `text` against `pattern` ignoring case, with `*` for any run of
+/// characters and `?` for any one.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                },
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Track {
+        width: f64,
+        net: &'static str,
+    }
+
+    impl Item for Track {
+        fn property(&self, name: &str) -> Option<Outcome> {
+            match name {
+                "Type" => Some(Outcome::String("Track".to_string())),
+                "Width" => Some(Outcome::Number(self.width)),
+                "NetName" => Some(Outcome::String(self.net.to_string())),
+                _ => None,
+            }
+        }
+
+        fn call(&self, name: &str, args: &[Outcome]) -> Option<Outcome> {
+            match name {
+                "inDiffPair" => {
+                    let pattern = args.first()?.as_str()?;
+                    Some(Outcome::bool(wildcard_match(pattern, self.net)))
+                },
+                _ => None,
+            }
+        }
+    }
+
+    fn eval(text: &str, a: &Track, b: Option<&Track>) -> Result<Outcome, ConvertError> {
+        Expr::parse(text).unwrap().eval(a, b.map(|b| b as &dyn Item))
+    }
+
+    #[test]
+    fn evaluate() {
+        let power = Track { width: 0.5, net: "/VBUS" };
+        let signal = Track { width: 0.2, net: "/USB_D+" };
+        let holds = |text: &str| eval(text, &power, Some(&signal)).unwrap().is_true();
+
+        assert!(holds("A.Type == 'track' && A.NetName == '/V*'"));
+        assert!(holds("A.Width >= 19mil && B.Width < 0.25mm"));
+        assert!(holds("A.Width - B.Width == 0.3"));
+        assert!(holds("!(B.NetName == 'GND') || A.Width > 1"));
+        assert!(holds("B.inDiffPair('/USB_D?')"));
+        assert!(!holds("A.Missing == ''"));
+        assert!(holds("A.Missing != 'x'"));
+        assert!(!holds("A.NetName == '/VBUS_*'"));
+
+        assert!(eval("A.Width > 0.1", &power, None).unwrap().is_true());
+        assert!(eval("B.Width > 0.1", &power, None).is_err());
+        let error = eval("A.isPlated()", &power, None).unwrap_err();
+        assert_eq!(error.to_string(), "unknown function A.isPlated()");
+        assert!(eval("A.NetName + 1", &power, None).is_err());
+        assert!(eval("C.Width == 1", &power, None).is_err());
+
+        let condition = Condition::parse("A.Width > 0.3mm").unwrap();
+        assert!(condition.matches(&power, None).unwrap() && !condition.matches(&signal, None).unwrap());
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("/USB*", "/usb_d+"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(wildcard_match("U?", "U1"));
+        assert!(!wildcard_match("U?", "U12"));
+        assert!(!wildcard_match("a*c", "abcd"));
+    }
+}
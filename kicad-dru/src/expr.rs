@@ -0,0 +1,337 @@
+use std::fmt;
+
+use kicad_common::ConvertError;
+
+use crate::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnaryOp {
+    /// `!`
+    Not,
+    /// `-`
+    Neg,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+        }
+    }
+
+    /// Binding strength, higher binds tighter.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq | BinaryOp::Ne => 3,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 4,
+            BinaryOp::Add | BinaryOp::Sub => 5,
+            BinaryOp::Mul | BinaryOp::Div => 6,
+        }
+    }
+}
+
+/// A parsed rule expression, as in `A.NetClass == 'HV' && B.isPlated()`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr {
+    Number(Value),
+    /// A quoted string, without the quotes.
+    String(String),
+    /// A bare name, usually the item `A` or `B`.
+    Ident(String),
+    /// A property of an item, as in `A.Type`.
+    Property { object: String, name: String },
+    /// A method call on an item, as in `A.intersectsCourtyard('U1')`.
+    Call { object: String, name: String, args: Vec<Expr> },
+    Unary { op: UnaryOp, expr: Box<Expr> },
+    Binary { op: BinaryOp, lhs: Box<Expr>, rhs: Box<Expr> },
+}
+
+impl Expr {
+    /// Parses `text`. Errors name the column, counted in characters from 1.
+    pub fn parse(text: &str) -> Result<Self, ConvertError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens: &tokens, pos: 0, len: text.chars().count() };
+        let expr = parser.expr(0)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some((token, column)) => Err(error(column, format!("unexpected {}", token))),
+        }
+    }
+
+    /// The objects, such as `A` and `B`, whose properties the expression reads.
+    pub fn objects(&self) -> Vec<&str> {
+        let mut objects = Vec::new();
+        self.walk(&mut |expr| {
+            if let Expr::Property { object, .. } | Expr::Call { object, .. } = expr
+                && !objects.contains(&object.as_str())
+            {
+                objects.push(object.as_str());
+            }
+        });
+        objects
+    }
+
+    /// Calls `f` on this expression and everything inside it, outermost first.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        f(self);
+        match self {
+            Expr::Call { args, .. } => args.iter().for_each(|arg| arg.walk(f)),
+            Expr::Unary { expr, .. } => expr.walk(f),
+            Expr::Binary { lhs, rhs, .. } => {
+                lhs.walk(f);
+                rhs.walk(f);
+            },
+            Expr::Number(_) | Expr::String(_) | Expr::Ident(_) | Expr::Property { .. } => {},
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Writes the expression back with single quoted strings and
+    /// parentheses only where precedence needs them.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn operand(f: &mut fmt::Formatter<'_>, expr: &Expr, min: u8) -> fmt::Result {
+            match expr {
+                Expr::Binary { op, .. } if op.precedence() < min => write!(f, "({})", expr),
+                _ => write!(f, "{}", expr),
+            }
+        }
+
+        match self {
+            Expr::Number(value) => write!(f, "{}", value),
+            Expr::String(text) => write!(f, "'{}'", text),
+            Expr::Ident(name) => f.write_str(name),
+            Expr::Property { object, name } => write!(f, "{}.{}", object, name),
+            Expr::Call { object, name, args } => {
+                write!(f, "{}.{}(", object, name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                f.write_str(")")
+            },
+            Expr::Unary { op, expr } => {
+                f.write_str(match op {
+                    UnaryOp::Not => "!",
+                    UnaryOp::Neg => "-",
+                })?;
+                operand(f, expr, u8::MAX)
+            },
+            Expr::Binary { op, lhs, rhs } => {
+                operand(f, lhs, op.precedence())?;
+                write!(f, " {} ", op.symbol())?;
+                operand(f, rhs, op.precedence() + 1)
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Value),
+    String(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "number `{}`", value),
+            Token::String(text) => write!(f, "string '{}'", text),
+            Token::Ident(name) => write!(f, "`{}`", name),
+            Token::Op(op) => write!(f, "`{}`", op),
+        }
+    }
+}
+
+fn error(column: usize, message: String) -> ConvertError {
+    ConvertError::new(format!("{} at column {}", message, column))
+}
+
+const OPERATORS: [&str; 17] =
+    ["||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/", "(", ")", ",", "."];
+
+/// Splits `text` into tokens with their columns.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ConvertError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (c, column) = (chars[i], i + 1);
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&end| end == c)
+                .ok_or_else(|| error(column, "unterminated string".to_string()))?;
+            tokens.push((Token::String(chars[i + 1..i + 1 + end].iter().collect()), column));
+            i += end + 2;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) {
+            let len = chars[i..].iter().take_while(|c| c.is_ascii_alphanumeric() || **c == '.').count();
+            let literal: String = chars[i..i + len].iter().collect();
+            let value = literal.parse().map_err(|_| error(column, format!("invalid number `{}`", literal)))?;
+            tokens.push((Token::Number(value), column));
+            i += len;
+        } else if c.is_alphabetic() || c == '_' {
+            let len = chars[i..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+            tokens.push((Token::Ident(chars[i..i + len].iter().collect()), column));
+            i += len;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| error(column, format!("unexpected `{}`", c)))?;
+            tokens.push((Token::Op(op), column));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'t> {
+    tokens: &'t [(Token, usize)],
+    pos: usize,
+    /// Length of the text in characters, for errors at the end.
+    len: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<(&Token, usize)> {
+        self.tokens.get(self.pos).map(|(token, column)| (token, *column))
+    }
+
+    fn next(&mut self) -> Result<(Token, usize), ConvertError> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| error(self.len + 1, "unexpected end".into()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        let found = matches!(self.peek(), Some((Token::Op(found), _)) if *found == op);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), ConvertError> {
+        match self.next()? {
+            (Token::Op(found), _) if found == op => Ok(()),
+            (token, column) => Err(error(column, format!("expected `{}`, found {}", op, token))),
+        }
+    }
+
+    fn binary_op(&self) -> Option<BinaryOp> {
+        let Some((Token::Op(op), _)) = self.peek() else {
+            return None;
+        };
+        Some(match *op {
+            "||" => BinaryOp::Or,
+            "&&" => BinaryOp::And,
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">" => BinaryOp::Gt,
+            ">=" => BinaryOp::Ge,
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            _ => return None,
+        })
+    }
+
+    /// Parses operators binding tighter than `min` by precedence climbing.
+    fn expr(&mut self, min: u8) -> Result<Expr, ConvertError> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.binary_op().filter(|op| op.precedence() > min) {
+            self.pos += 1;
+            let rhs = self.expr(op.precedence())?;
+            lhs = Expr::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) };
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ConvertError> {
+        let op = if self.eat("!") {
+            UnaryOp::Not
+        } else if self.eat("-") {
+            UnaryOp::Neg
+        } else {
+            return self.primary();
+        };
+        Ok(Expr::Unary { op, expr: Box::new(self.unary()?) })
+    }
+
+    fn primary(&mut self) -> Result<Expr, ConvertError> {
+        match self.next()? {
+            (Token::Number(value), _) => Ok(Expr::Number(value)),
+            (Token::String(text), _) => Ok(Expr::String(text)),
+            (Token::Op("("), _) => {
+                let expr = self.expr(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            (Token::Ident(object), _) if self.eat(".") => {
+                let name = match self.next()? {
+                    (Token::Ident(name), _) => name,
+                    (token, column) => return Err(error(column, format!("expected a property, found {}", token))),
+                };
+                if !self.eat("(") {
+                    return Ok(Expr::Property { object, name });
+                }
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.expr(0)?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Expr::Call { object, name, args })
+            },
+            (Token::Ident(name), _) => Ok(Expr::Ident(name)),
+            (token, column) => Err(error(column, format!("unexpected {}", token))),
+        }
+    }
+}
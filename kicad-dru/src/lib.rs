@@ -0,0 +1,123 @@
+//! Typed model of KiCad custom design rule (`.kicad_dru`) files.
+
+mod eval;
+mod expr;
+mod rules;
+mod value;
+
+pub use kicad_common::Error;
+
+pub use eval::{wildcard_match, Item, Outcome};
+pub use expr::{BinaryOp, Expr, UnaryOp};
+pub use rules::{Condition, Constraint, ConstraintKind, LayerFilter, Rule, Rules, Severity};
+pub use value::{ParseValueError, Unit, Value};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_dru");
+
+    fn mm(number: f64) -> Option<Value> {
+        Some(Value::new(number, Unit::Mm))
+    }
+
+    fn string(text: &str) -> Box<Expr> {
+        Box::new(Expr::String(text.to_string()))
+    }
+
+    fn property(object: &str, name: &str) -> Box<Expr> {
+        Box::new(Expr::Property { object: object.to_string(), name: name.to_string() })
+    }
+
+    #[test]
+    fn rules() {
+        let rules = Rules::parse(DEMO).unwrap();
+
+        assert_eq!(rules.version, 1);
+        assert!(rules.check_version().is_ok());
+        let names: Vec<_> = rules.rules.iter().map(|rule| rule.name.as_str()).collect();
+        assert_eq!(names, [
+            "Copper to edge",
+            "HV",
+            "Power track width",
+            "No vias under J1",
+            "Plated holes",
+            "Solid ground pads",
+            "Signal pair skew",
+        ]);
+
+        let edge = &rules.rules[0];
+        assert_eq!((edge.condition.as_ref(), edge.layer.as_ref()), (None, Some(&LayerFilter::Outer)));
+        let width = rules.rule("Power track width").unwrap().constraint(ConstraintKind::TrackWidth).unwrap();
+        assert_eq!((width.min, width.opt, width.max), (mm(0.4), mm(0.5), mm(2.0)));
+
+        let vias = rules.rule("No vias under J1").unwrap();
+        assert_eq!(vias.constraints[0].options, ["via", "micro_via"]);
+        assert_eq!(vias.severity, Some(Severity::Warning));
+        let courtyard = Expr::Call { object: "A".into(), name: "intersectsCourtyard".into(), args: vec![*string("J1")] };
+        assert_eq!(vias.condition.as_ref().unwrap().expr, courtyard);
+
+        let holes = rules.rule("Plated holes").unwrap();
+        assert_eq!(holes.layer, Some(LayerFilter::Layer("F.Cu".into())));
+        let annular = holes.constraint(ConstraintKind::AnnularWidth).unwrap().min.unwrap();
+        assert!((annular.millimeters().unwrap() - 0.1524).abs() < 1e-9);
+
+        let zones = rules.rule("Solid ground pads").unwrap();
+        assert_eq!(zones.constraints[0].options, ["solid"]);
+        assert!(rules.rules[6].constraints[0].within_diff_pairs);
+
+        let clearance: Vec<_> = rules.with_constraint(ConstraintKind::Clearance).map(|(rule, _)| &rule.name).collect();
+        assert_eq!(clearance, ["HV"]);
+    }
+
+    #[test]
+    fn conditions() {
+        let hv = Rules::parse(DEMO).unwrap().rules.remove(1).condition.unwrap();
+        let expected = Expr::Binary {
+            op: BinaryOp::And,
+            lhs: Box::new(Expr::Binary { op: BinaryOp::Eq, lhs: property("A", "NetClass"), rhs: string("Power") }),
+            rhs: Box::new(Expr::Binary { op: BinaryOp::Ne, lhs: property("B", "NetClass"), rhs: string("Power") }),
+        };
+        assert_eq!(hv.expr, expected);
+        assert_eq!(hv.expr.to_string(), hv.text);
+        assert_eq!(hv.expr.objects(), ["A", "B"]);
+
+        let expr = Expr::parse("!(A.Type == \"Via\" || A.Type == 'Pad') && A.Width * 2 >= 0.5mm + 10mil").unwrap();
+        assert_eq!(expr.to_string(), "!(A.Type == 'Via' || A.Type == 'Pad') && A.Width * 2 >= 0.5mm + 10mil");
+        let Expr::Binary { rhs, .. } = &expr else { panic!("expected a binary expression") };
+        assert!(matches!(**rhs, Expr::Binary { op: BinaryOp::Ge, .. }));
+        assert_eq!(Expr::parse("1 - 2 - 3").unwrap().to_string(), "1 - 2 - 3");
+        assert_eq!(Expr::parse("1 - (2 - 3)").unwrap().to_string(), "1 - (2 - 3)");
+
+        let error = |text: &str| Expr::parse(text).unwrap_err().to_string();
+        assert_eq!(error("A.Type == 'Pad"), "unterminated string at column 11");
+        assert_eq!(error("A.Type =="), "unexpected end at column 10");
+        assert_eq!(error("A.isPlated(1 2)"), "expected `,`, found number `2` at column 14");
+        assert_eq!(error("A.Width > 1furlong"), "invalid number `1furlong` at column 11");
+    }
+
+    #[test]
+    fn errors() {
+        let rules = Rules::parse("(version 1)\n(rule x (constraint assertion \"A.Size > 1mm\"))").unwrap();
+        let assertion = rules.rules[0].constraints[0].assertion.as_ref().unwrap();
+        assert_eq!(assertion.expr.to_string(), "A.Size > 1mm");
+
+        let error = Rules::parse("(version 1)\n(rule x (constraint clearance (min 1parsec)))").unwrap_err();
+        assert_eq!(error.to_string(), "rule > constraint > min: expected a number, found symbol `1parsec`");
+        let error = Rules::parse("(rule x (condition \"A.Type ==\"))").unwrap_err();
+        assert_eq!(error.to_string(), "rule > condition: unexpected end at column 10");
+        let error = Rules::parse("(rule x (constraint wiggle))").unwrap_err();
+        assert_eq!(error.to_string(), "rule > constraint: unknown constraint `wiggle`");
+        assert!(Rules::parse("(kicad_pcb)").is_err());
+        assert!(Rules::parse("(version 2)").unwrap().check_version().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let rules = Rules::parse(DEMO).unwrap();
+        let json = serde_json::to_string(&rules).unwrap();
+        assert_eq!(serde_json::from_str::<Rules>(&json).unwrap(), rules);
+    }
+}
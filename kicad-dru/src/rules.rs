@@ -0,0 +1,299 @@
+use kicad_common::{
+    convert::{check_version, FromSexp, List, Result},
+    ConvertError, Error,
+};
+use kicad_sexp::Sexp;
+
+use crate::{Expr, Value};
+
+/// What a constraint limits, the first argument of `(constraint ...)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConstraintKind {
+    AnnularWidth,
+    Assertion,
+    BridgedMask,
+    Clearance,
+    ConnectionWidth,
+    CourtyardClearance,
+    Creepage,
+    DiffPairGap,
+    DiffPairUncoupled,
+    Disallow,
+    EdgeClearance,
+    HoleClearance,
+    HoleSize,
+    HoleToHole,
+    Length,
+    MinResolvedSpokes,
+    PhysicalClearance,
+    PhysicalHoleClearance,
+    SilkClearance,
+    Skew,
+    SolderMaskExpansion,
+    SolderPasteAbsMargin,
+    SolderPasteRelMargin,
+    TextHeight,
+    TextThickness,
+    ThermalReliefGap,
+    ThermalSpokeWidth,
+    TrackAngle,
+    TrackSegmentLength,
+    TrackWidth,
+    ViaCount,
+    ViaDiameter,
+    ZoneConnection,
+}
+
+impl ConstraintKind {
+    const NAMES: [(ConstraintKind, &'static str); 33] = [
+        (ConstraintKind::AnnularWidth, "annular_width"),
+        (ConstraintKind::Assertion, "assertion"),
+        (ConstraintKind::BridgedMask, "bridged_mask"),
+        (ConstraintKind::Clearance, "clearance"),
+        (ConstraintKind::ConnectionWidth, "connection_width"),
+        (ConstraintKind::CourtyardClearance, "courtyard_clearance"),
+        (ConstraintKind::Creepage, "creepage"),
+        (ConstraintKind::DiffPairGap, "diff_pair_gap"),
+        (ConstraintKind::DiffPairUncoupled, "diff_pair_uncoupled"),
+        (ConstraintKind::Disallow, "disallow"),
+        (ConstraintKind::EdgeClearance, "edge_clearance"),
+        (ConstraintKind::HoleClearance, "hole_clearance"),
+        (ConstraintKind::HoleSize, "hole_size"),
+        (ConstraintKind::HoleToHole, "hole_to_hole"),
+        (ConstraintKind::Length, "length"),
+        (ConstraintKind::MinResolvedSpokes, "min_resolved_spokes"),
+        (ConstraintKind::PhysicalClearance, "physical_clearance"),
+        (ConstraintKind::PhysicalHoleClearance, "physical_hole_clearance"),
+        (ConstraintKind::SilkClearance, "silk_clearance"),
+        (ConstraintKind::Skew, "skew"),
+        (ConstraintKind::SolderMaskExpansion, "solder_mask_expansion"),
+        (ConstraintKind::SolderPasteAbsMargin, "solder_paste_abs_margin"),
+        (ConstraintKind::SolderPasteRelMargin, "solder_paste_rel_margin"),
+        (ConstraintKind::TextHeight, "text_height"),
+        (ConstraintKind::TextThickness, "text_thickness"),
+        (ConstraintKind::ThermalReliefGap, "thermal_relief_gap"),
+        (ConstraintKind::ThermalSpokeWidth, "thermal_spoke_width"),
+        (ConstraintKind::TrackAngle, "track_angle"),
+        (ConstraintKind::TrackSegmentLength, "track_segment_length"),
+        (ConstraintKind::TrackWidth, "track_width"),
+        (ConstraintKind::ViaCount, "via_count"),
+        (ConstraintKind::ViaDiameter, "via_diameter"),
+        (ConstraintKind::ZoneConnection, "zone_connection"),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().find(|(_, n)| *n == name).map(|(kind, _)| *kind)
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES.iter().find(|(kind, _)| kind == self).map(|(_, name)| *name).unwrap()
+    }
+}
+
+/// A condition or assertion: the text as written and its parsed form.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Condition {
+    pub text: String,
+    pub expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> std::result::Result<Self, ConvertError> {
+        Ok(Condition { text: text.to_string(), expr: Expr::parse(text)? })
+    }
+
+    fn from_list(list: &List) -> Result<Self> {
+        Condition::parse(&list.string(0)?).map_err(|e| e.within(list.tag()))
+    }
+}
+
+/// A `(constraint kind ...)` of a rule.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Constraint {
+    pub kind: ConstraintKind,
+    pub min: Option<Value>,
+    pub opt: Option<Value>,
+    pub max: Option<Value>,
+    /// The bare symbols after the kind: item types for `disallow`, as in
+    /// `track` or `via`, or the connection style for `zone_connection`.
+    pub options: Vec<String>,
+    /// The expression an `assertion` checks.
+    pub assertion: Option<Condition>,
+    /// `skew` is measured within each differential pair.
+    pub within_diff_pairs: bool,
+}
+
+impl FromSexp for Constraint {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let name = list.symbol(0)?;
+        let kind =
+            ConstraintKind::from_name(name).ok_or_else(|| list.error(format!("unknown constraint `{}`", name)))?;
+        let mut constraint = Constraint {
+            kind,
+            min: list.child_number("min")?,
+            opt: list.child_number("opt")?,
+            max: list.child_number("max")?,
+            options: Vec::new(),
+            assertion: None,
+            within_diff_pairs: list.child("within_diff_pairs").is_some(),
+        };
+        for arg in &list.args()[1..] {
+            match arg {
+                Sexp::Symbol(option) => constraint.options.push(option.to_string()),
+                Sexp::StringLiteral(_) if kind == ConstraintKind::Assertion => {
+                    let text = arg.as_str().unwrap_or_default();
+                    constraint.assertion = Some(Condition::parse(&text).map_err(|e| e.within(list.tag()))?);
+                },
+                _ => {},
+            }
+        }
+        Ok(constraint)
+    }
+}
+
+/// The copper layers a rule applies to, from `(layer ...)`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LayerFilter {
+    /// `outer`: the front and back copper layers.
+    Outer,
+    /// `inner`: every copper layer but the outer ones.
+    Inner,
+    /// A layer by name, as in `"F.Cu"`.
+    Layer(String),
+}
+
+impl FromSexp for LayerFilter {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(match list.arg(0)? {
+            Sexp::Symbol("outer") => LayerFilter::Outer,
+            Sexp::Symbol("inner") => LayerFilter::Inner,
+            _ => LayerFilter::Layer(list.string(0)?),
+        })
+    }
+}
+
+/// How violations of a rule are reported, from `(severity ...)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+    Ignore,
+    /// Violations are excluded from the report.
+    Exclusion,
+}
+
+impl Severity {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "error" => Severity::Error,
+            "warning" => Severity::Warning,
+            "ignore" => Severity::Ignore,
+            "exclusion" => Severity::Exclusion,
+            _ => return None,
+        })
+    }
+}
+
+/// A `(rule name ...)`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+    pub name: String,
+    /// The items the rule applies to; `None` applies it to everything.
+    pub condition: Option<Condition>,
+    pub constraints: Vec<Constraint>,
+    pub layer: Option<LayerFilter>,
+    /// `None` leaves the severity to the constraint's DRC check.
+    pub severity: Option<Severity>,
+}
+
+impl Rule {
+    pub fn constraint(&self, kind: ConstraintKind) -> Option<&Constraint> {
+        self.constraints.iter().find(|constraint| constraint.kind == kind)
+    }
+}
+
+impl FromSexp for Rule {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let severity = match list.child("severity") {
+            Some(child) => {
+                let name = child.symbol(0).map_err(|e| e.within(list.tag()))?;
+                Some(Severity::from_name(name).ok_or_else(|| list.error(format!("unknown severity `{}`", name)))?)
+            },
+            None => None,
+        };
+        let condition = list.child("condition").map(|child| Condition::from_list(&child)).transpose();
+        Ok(Rule {
+            name: list.string(0)?,
+            condition: condition.map_err(|e| e.within(list.tag()))?,
+            constraints: list.parse_all("constraint")?,
+            layer: list.parse("layer")?,
+            severity,
+        })
+    }
+}
+
+/// A `.kicad_dru` file of custom design rules. Unlike the other documents
+/// it has no root list, just a `(version ...)` followed by the rules.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rules {
+    pub version: u32,
+    /// In file order. KiCad gives later rules priority over earlier ones.
+    pub rules: Vec<Rule>,
+}
+
+/// Blanks out `#` comment lines, keeping offsets for error spans.
+fn strip_comments(src: &str) -> String {
+    src.split_inclusive('\n')
+        .map(|line| {
+            if line.trim_start().starts_with('#') {
+                line.chars().map(|c| if c == '\n' { c } else { ' ' }).collect()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+impl Rules {
+    /// Newest file format version the model is written against.
+    pub const NEWEST_VERSION: u32 = 1;
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(rules)))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let src = strip_comments(src);
+        let sexps = kicad_sexp::parse(&src).map_err(Error::Parse)?;
+        let mut rules = Rules::default();
+        for list in sexps.iter().filter_map(List::new) {
+            match list.tag() {
+                "version" => rules.version = list.number(0)?,
+                "rule" => rules.rules.push(Rule::from_sexp(&list)?),
+                tag => return Err(ConvertError::new(format!("unexpected ({} ...)", tag)).into()),
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("rules", rules.rules.len());
+        Ok(rules)
+    }
+
+    /// Fails with [`Error::Version`] for files from a newer KiCad.
+    pub fn check_version(&self) -> std::result::Result<(), Error> {
+        check_version("kicad_dru", self.version, Self::NEWEST_VERSION)
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    /// The rules with a constraint of `kind`, highest priority first.
+    pub fn with_constraint(&self, kind: ConstraintKind) -> impl Iterator<Item = (&Rule, &Constraint)> {
+        self.rules.iter().rev().filter_map(move |rule| rule.constraint(kind).map(|constraint| (rule, constraint)))
+    }
+}
@@ -0,0 +1,97 @@
+use std::{fmt, str::FromStr};
+
+use kicad_sexp::number::format_float;
+
+/// The unit suffix of a [`Value`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Unit {
+    /// No suffix: millimeters for lengths, a plain number otherwise.
+    #[default]
+    None,
+    Mm,
+    Um,
+    Mil,
+    Inch,
+    Deg,
+}
+
+impl Unit {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "" => Unit::None,
+            "mm" => Unit::Mm,
+            "um" => Unit::Um,
+            "mil" | "mils" | "thou" => Unit::Mil,
+            "in" => Unit::Inch,
+            "deg" => Unit::Deg,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Unit::None => "",
+            Unit::Mm => "mm",
+            Unit::Um => "um",
+            Unit::Mil => "mil",
+            Unit::Inch => "in",
+            Unit::Deg => "deg",
+        }
+    }
+}
+
+/// A number with an optional unit, as in `0.2mm`, `10mil` or `45deg`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Value {
+    pub number: f64,
+    pub unit: Unit,
+}
+
+impl Value {
+    pub const fn new(number: f64, unit: Unit) -> Self {
+        Value { number, unit }
+    }
+
+    /// The value as a length in millimeters, `None` for angles.
+    pub fn millimeters(&self) -> Option<f64> {
+        match self.unit {
+            Unit::None | Unit::Mm => Some(self.number),
+            Unit::Um => Some(self.number / 1000.0),
+            Unit::Mil => Some(self.number * 0.0254),
+            Unit::Inch => Some(self.number * 25.4),
+            Unit::Deg => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseValueError;
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid value")
+    }
+}
+
+impl std::error::Error for ParseValueError {}
+
+impl FromStr for Value {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        Ok(Value {
+            number: number.parse().map_err(|_| ParseValueError)?,
+            unit: Unit::from_name(unit).ok_or(ParseValueError)?,
+        })
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", format_float(self.number), self.unit.name())
+    }
+}
@@ -0,0 +1,111 @@
+//! Reports the lists the typed models skip across a corpus of board,
+//! schematic and symbol library files, most frequent first, with the
+//! format versions they were seen in.
+//!
+//! Usage: `cargo run -p kicad-file --example coverage -- <file or directory>...`
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use kicad_file::{
+    common::coverage,
+    pcb, sch,
+    sexp::{self, Sexp},
+    sym,
+};
+
+#[derive(Default)]
+struct Gap {
+    count: usize,
+    files: BTreeSet<PathBuf>,
+    versions: BTreeSet<u32>,
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            eprintln!("{}: cannot read directory", path.display());
+            return;
+        };
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+        entries.sort();
+        for entry in entries {
+            collect(&entry, files);
+        }
+    } else if matches!(path.extension().and_then(|e| e.to_str()), Some("kicad_pcb" | "kicad_sch" | "kicad_sym")) {
+        files.push(path.to_path_buf());
+    }
+}
+
+fn version(sexps: &[Sexp]) -> Option<u32> {
+    let Some(Sexp::List(root)) = sexps.first() else {
+        return None;
+    };
+    root.iter().find_map(|item| match item {
+        Sexp::List(items) => match items.as_slice() {
+            [Sexp::Symbol("version"), Sexp::IntLiteral(v)] => v.parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn main() {
+    let mut files = Vec::new();
+    for arg in env::args().skip(1) {
+        collect(Path::new(&arg), &mut files);
+    }
+    if files.is_empty() {
+        eprintln!("usage: coverage <file or directory>...");
+        process::exit(2);
+    }
+
+    let mut gaps: BTreeMap<String, Gap> = BTreeMap::new();
+    let mut failed = 0;
+    for file in &files {
+        let src = match fs::read(file).map(|bytes| sexp::input::normalize(&bytes).map(|s| s.into_owned())) {
+            Ok(Ok(src)) => src,
+            _ => {
+                eprintln!("{}: cannot read file", file.display());
+                failed += 1;
+                continue;
+            },
+        };
+        let (result, visited) = coverage::record(|| match file.extension().and_then(|e| e.to_str()) {
+            Some("kicad_pcb") => pcb::Board::parse(&src).map(drop),
+            Some("kicad_sym") => sym::SymbolLib::parse(&src).map(drop),
+            _ => sch::Schematic::parse(&src).map(drop),
+        });
+        if let Err(e) = result {
+            eprintln!("{}: {}", file.display(), e);
+            failed += 1;
+        }
+        let Ok(sexps) = sexp::parse(&src) else {
+            continue;
+        };
+        let version = version(&sexps);
+        for path in visited.unhandled(&sexps) {
+            let gap = gaps.entry(path).or_default();
+            gap.count += 1;
+            gap.files.insert(file.clone());
+            gap.versions.extend(version);
+        }
+    }
+
+    let mut gaps: Vec<_> = gaps.into_iter().collect();
+    gaps.sort_by(|(a, x), (b, y)| y.count.cmp(&x.count).then_with(|| a.cmp(b)));
+    println!("{:>8} {:>6}  {:<19}  list", "count", "files", "versions");
+    for (path, gap) in &gaps {
+        let versions = match (gap.versions.first(), gap.versions.last()) {
+            (Some(first), Some(last)) if first != last => format!("{}..{}", first, last),
+            (Some(first), _) => first.to_string(),
+            _ => "?".to_string(),
+        };
+        println!("{:>8} {:>6}  {:<19}  {}", gap.count, gap.files.len(), versions, path);
+    }
+    println!("{} files, {} failed to convert, {} kinds of list skipped", files.len(), failed, gaps.len());
+}
@@ -0,0 +1,292 @@
+//! KiCad's project backups: zip archives of the project's own files, kept
+//! in the `<name>-backups` directory next to the project and named after
+//! the time they were made, e.g. `demo-2026-10-14_120000.zip`.
+//!
+//! Sources are read-only, so creating a backup returns the archive and
+//! [`plan_backup`] returns the old backups to delete; writing and deleting
+//! the files is up to the caller. The retention limits are the user's
+//! [`BackupSettings`], which KiCad keeps in `kicad_common.json` rather than
+//! in the project file.
+//!
+//! ```no_run
+//! use std::time::{SystemTime, UNIX_EPOCH};
+//!
+//! use kicad_file::{
+//!     backup::{backups, create_backup, plan_backup},
+//!     project::{BackupSettings, Project},
+//!     sexp::{source::FsSource, zip::DateTime},
+//! };
+//!
+//! let source = FsSource::new();
+//! let project = Project::open(&source, "demo/demo.kicad_pro")?;
+//! let now = DateTime::from_unix(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+//! let plan = plan_backup(&BackupSettings::default(), &backups(&source, &project)?, now);
+//! if plan.due {
+//!     for old in &plan.remove {
+//!         std::fs::remove_file(old)?;
+//!     }
+//!     let (path, archive) = create_backup(&source, &project, now)?;
+//!     std::fs::create_dir_all(path.parent().unwrap())?;
+//!     std::fs::write(path, archive)?;
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use kicad_sexp::{
+    source::{Fingerprint, Source, ZipSource},
+    zip::{DateTime, ZipWriter},
+};
+
+use crate::project::{BackupSettings, Project};
+
+/// Extensions of the files KiCad puts in a backup.
+const EXTENSIONS: [&str; 8] =
+    ["kicad_pro", "kicad_prl", "kicad_sch", "kicad_sym", "kicad_pcb", "kicad_mod", "kicad_dru", "kicad_wks"];
+const FILE_NAMES: [&str; 2] = ["fp-lib-table", "sym-lib-table"];
+
+/// A backup archive of a project.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// When the backup was made, from its name.
+    pub time: DateTime,
+    /// Size of the archive in bytes.
+    pub size: u64,
+}
+
+/// The directory the backups of `project` are kept in.
+pub fn backup_dir(project: &Project) -> PathBuf {
+    project.dir().join(format!("{}-backups", project.name()))
+}
+
+/// The file name of a backup of project `name` made at `time`.
+pub fn backup_name(name: &str, time: DateTime) -> String {
+    format!(
+        "{}-{:04}-{:02}-{:02}_{:02}{:02}{:02}.zip",
+        name, time.year, time.month, time.day, time.hour, time.minute, time.second
+    )
+}
+
+/// The time in a backup file name of project `name`.
+fn parse_name(name: &str, file_name: &str) -> Option<DateTime> {
+    let stamp = file_name.strip_prefix(name)?.strip_prefix('-')?.strip_suffix(".zip")?;
+    let bytes = stamp.as_bytes();
+    if bytes.len() != 17 || (bytes[4], bytes[7], bytes[10]) != (b'-', b'-', b'_') {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| {
+        let digits = stamp.get(range)?;
+        digits.bytes().all(|b| b.is_ascii_digit()).then(|| digits.parse::<u16>().ok())?
+    };
+    Some(DateTime {
+        year: field(0..4)?,
+        month: field(5..7)? as u8,
+        day: field(8..10)? as u8,
+        hour: field(11..13)? as u8,
+        minute: field(13..15)? as u8,
+        second: field(15..17)? as u8,
+    })
+}
+
+/// The backups of `project`, oldest first. Files in the backup directory
+/// that are not named like a backup are left out.
+pub fn backups(source: &dyn Source, project: &Project) -> io::Result<Vec<Backup>> {
+    let dir = backup_dir(project);
+    if !source.exists(&dir) {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for path in source.read_dir(&dir)? {
+        let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let Some(time) = parse_name(project.name(), file_name) else { continue };
+        let size = match source.fingerprint(&path)? {
+            Some(Fingerprint::Modified { len, .. }) => len,
+            _ => source.read(&path)?.len() as u64,
+        };
+        backups.push(Backup { path, time, size });
+    }
+    backups.sort_by_key(|backup| backup.time);
+    Ok(backups)
+}
+
+fn is_project_file(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    FILE_NAMES.contains(&file_name) || extension.is_some_and(|extension| EXTENSIONS.contains(&extension))
+}
+
+/// Collects the project files under `dir`, leaving out `skip` and hidden
+/// directories.
+fn collect(source: &dyn Source, dir: &Path, skip: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for path in source.read_dir(dir)? {
+        let hidden = path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.'));
+        if path == skip || hidden {
+            continue;
+        }
+        if is_project_file(&path) {
+            files.push(path);
+        } else if source.read_dir(&path).is_ok() {
+            collect(source, &path, skip, files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Archives the KiCad files of `project`'s directory and its subdirectories,
+/// as KiCad backs up a project: documents, libraries, library tables and
+/// settings, leaving out outputs such as netlists and Gerbers. Returns the
+/// path the backup goes to and its contents, each file dated `time`.
+pub fn create_backup(source: &dyn Source, project: &Project, time: DateTime) -> io::Result<(PathBuf, Vec<u8>)> {
+    let dir = backup_dir(project);
+    let mut files = Vec::new();
+    collect(source, project.dir(), &dir, &mut files)?;
+    let mut writer = ZipWriter::new();
+    for path in files {
+        let name = path.strip_prefix(project.dir()).unwrap_or(&path);
+        let name: Vec<_> = name.components().map(|part| part.as_os_str().to_string_lossy()).collect();
+        writer.add(&name.join("/"), time, &source.read(&path)?)?;
+    }
+    Ok((dir.join(backup_name(project.name(), time)), writer.finish()?))
+}
+
+/// The files of a backup archive as a source, placed under `dir`, so the
+/// backed up project can be opened with [`Project::open`] or restored.
+pub fn open_backup(archive: Vec<u8>, dir: &Path) -> io::Result<ZipSource> {
+    Ok(ZipSource::new(archive)?.at(dir))
+}
+
+/// What [`plan_backup`] decided.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackupPlan {
+    /// A new backup should be made now.
+    pub due: bool,
+    /// Old backups to delete before, so the limits hold with the new one.
+    pub remove: Vec<PathBuf>,
+}
+
+/// Applies KiCad's backup policy to the existing `backups`, oldest first as
+/// [`backups`] returns them: a backup is due if backups are enabled and the
+/// last one is at least `min_interval` old. The oldest backups then go
+/// until the total count, the count of each day and the total size are
+/// within the limits.
+pub fn plan_backup(settings: &BackupSettings, backups: &[Backup], now: DateTime) -> BackupPlan {
+    let recent = backups.last().is_some_and(|last| now.to_unix() < last.time.to_unix() + settings.min_interval);
+    if !settings.enabled || recent {
+        return BackupPlan::default();
+    }
+    let mut kept: Vec<&Backup> = backups.iter().collect();
+    let mut remove = Vec::new();
+
+    let limit = settings.limit_total_files as usize;
+    if limit > 0 && kept.len() >= limit {
+        remove.extend(kept.drain(..=kept.len() - limit).map(|backup| backup.path.clone()));
+    }
+
+    let day = |time: DateTime| (time.year, time.month, time.day);
+    let daily = settings.limit_daily_files as usize;
+    if daily > 0 {
+        let mut days: Vec<_> = kept.iter().map(|backup| day(backup.time)).collect();
+        days.dedup();
+        for date in days {
+            let allowed = if date == day(now) { daily - 1 } else { daily };
+            let count = kept.iter().filter(|backup| day(backup.time) == date).count();
+            for _ in allowed..count {
+                let oldest = kept.iter().position(|backup| day(backup.time) == date).expect("counted above");
+                remove.push(kept.remove(oldest).path.clone());
+            }
+        }
+    }
+
+    if settings.limit_total_size > 0 {
+        let mut total: u64 = kept.iter().map(|backup| backup.size).sum();
+        while total > settings.limit_total_size && !kept.is_empty() {
+            let oldest = kept.remove(0);
+            total -= oldest.size;
+            remove.push(oldest.path.clone());
+        }
+    }
+    BackupPlan { due: true, remove }
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_sexp::{source::FsSource, zip::ZipArchive};
+
+    use super::*;
+
+    fn source() -> FsSource {
+        FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"))
+    }
+
+    fn at(day: u8, hour: u8) -> DateTime {
+        DateTime { year: 2026, month: 10, day, hour, minute: 0, second: 0 }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(backup_name("demo", at(14, 9)), "demo-2026-10-14_090000.zip");
+        assert_eq!(parse_name("demo", "demo-2026-10-14_090000.zip"), Some(at(14, 9)));
+        for other in ["demo-2026-10-14_0900.zip", "demo-2026-10-14_09000x.zip", "other-2026-10-14_090000.zip"] {
+            assert_eq!(parse_name("demo", other), None, "{}", other);
+        }
+    }
+
+    #[test]
+    fn create_and_open() {
+        let source = source();
+        let project = Project::open(&source, "demo/demo.kicad_pro").unwrap();
+        let existing = backups(&source, &project).unwrap();
+        let path = PathBuf::from("demo/demo-backups/demo-2026-10-14_120000.zip");
+        assert_eq!(existing, [Backup { path: path.clone(), time: at(14, 12), size: 15634 }]);
+
+        let (new, archive) = create_backup(&source, &project, at(14, 13)).unwrap();
+        assert_eq!(new, Path::new("demo/demo-backups/demo-2026-10-14_130000.zip"));
+        let names = |data: Vec<u8>| -> Vec<String> {
+            ZipArchive::new(data).unwrap().entries().iter().map(|entry| entry.name.clone()).collect()
+        };
+        // The same files as the backup KiCad made, without the netlist.
+        assert_eq!(names(archive.clone()), names(source.read(&path).unwrap()));
+
+        let restored = open_backup(archive, Path::new("restored")).unwrap();
+        let copy = Project::open(&restored, "restored/demo.kicad_pro").unwrap();
+        assert_eq!(copy.settings, project.settings);
+        assert_eq!(copy.footprint_libs, project.footprint_libs);
+        let file = Path::new("demo.pretty/R_0603_1608Metric.kicad_mod");
+        let original = source.read(&project.dir().join(file)).unwrap();
+        assert_eq!(restored.read(&Path::new("restored").join(file)).unwrap(), original);
+        let old = open_backup(source.read(&path).unwrap(), Path::new("old")).unwrap();
+        assert!(old.exists(Path::new("old/power.kicad_sch")));
+    }
+
+    #[test]
+    fn retention() {
+        let backup = |day: u8, hour: u8, size: u64| Backup {
+            path: PathBuf::from(backup_name("demo", at(day, hour))),
+            time: at(day, hour),
+            size,
+        };
+        let paths = |plan: &BackupPlan| -> Vec<String> {
+            plan.remove.iter().map(|path| path.to_string_lossy()[13..18].to_string()).collect()
+        };
+        let existing = [backup(12, 8, 10), backup(12, 9, 10), backup(13, 8, 10), backup(14, 8, 10), backup(14, 9, 10)];
+        let settings =
+            BackupSettings { limit_total_files: 4, limit_daily_files: 2, limit_total_size: 15, ..Default::default() };
+
+        let plan = plan_backup(&settings, &existing, at(14, 10));
+        assert!(plan.due);
+        // Two over the count, then one of today's to make room, then too large.
+        assert_eq!(paths(&plan), ["12_08", "12_09", "14_08", "13_08"]);
+
+        assert_eq!(plan_backup(&settings, &existing, DateTime { minute: 4, ..at(14, 9) }), BackupPlan::default());
+        assert!(plan_backup(&settings, &existing, DateTime { minute: 5, ..at(14, 9) }).due);
+        let off = BackupSettings { enabled: false, ..Default::default() };
+        assert!(!plan_backup(&off, &[], at(14, 10)).due);
+        let unlimited = BackupSettings { limit_total_files: 0, limit_daily_files: 0, limit_total_size: 0, ..settings };
+        assert_eq!(plan_backup(&unlimited, &existing, at(15, 0)), BackupPlan { due: true, remove: Vec::new() });
+    }
+}
@@ -0,0 +1,190 @@
+//! Running an operation over many files or projects at once, as CI checks
+//! do: a bounded number of worker threads, every failure collected instead
+//! of stopping at the first, and a callback as each input finishes.
+//!
+//! ```no_run
+//! use kicad_file::{batch::Batch, sexp::source::FsSource};
+//!
+//! let paths = vec!["a.kicad_pcb".into(), "b.kicad_sch".into()];
+//! let batch = Batch::new().jobs(4).on_progress(|p| eprintln!("{}/{}", p.done, p.total));
+//! let report = batch.load(&FsSource::new(), paths);
+//! for (_, error) in report.failures() {
+//!     eprintln!("{}", error);
+//! }
+//! ```
+
+use std::{
+    fmt,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use kicad_common::Error;
+use kicad_sexp::source::Source;
+
+use crate::KicadFile;
+
+/// Where a batch is, passed to the progress callback after each input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// Position of the input that just finished.
+    pub index: usize,
+    pub ok: bool,
+    /// Inputs finished so far, this one included.
+    pub done: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+type Callback = Box<dyn Fn(Progress) + Send + Sync>;
+
+/// Settings for running an operation over many inputs.
+pub struct Batch {
+    jobs: usize,
+    progress: Option<Callback>,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Batch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batch").field("jobs", &self.jobs).field("progress", &self.progress.is_some()).finish()
+    }
+}
+
+impl Batch {
+    /// One worker per available CPU.
+    pub fn new() -> Self {
+        let jobs = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        Batch { jobs, progress: None }
+    }
+
+    /// Runs at most `jobs` inputs at a time; 0 counts as 1.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Calls `f` after each input, from the worker that ran it.
+    pub fn on_progress(mut self, f: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Runs `op` on every input. The report lists the outcomes in input
+    /// order whatever order they finished in. A panic in `op` is passed on
+    /// once the other workers are done.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(inputs = inputs.len(), jobs = self.jobs))
+    )]
+    pub fn run<I, T, E, F>(&self, inputs: Vec<I>, op: F) -> Report<I, T, E>
+    where
+        I: Sync,
+        T: Send,
+        E: Send,
+        F: Fn(&I) -> Result<T, E> + Sync,
+    {
+        let total = inputs.len();
+        let next = AtomicUsize::new(0);
+        let (done, failed) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let results = Mutex::new((0..total).map(|_| None).collect::<Vec<Option<Result<T, E>>>>());
+        let worker = || {
+            #[cfg(feature = "tracing")]
+            let parent = tracing::Span::current();
+            loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(index) else {
+                    break;
+                };
+                #[cfg(feature = "tracing")]
+                let _span = tracing::debug_span!(parent: &parent, "input", index).entered();
+                let result = op(input);
+                let ok = result.is_ok();
+                let failed =
+                    if ok { failed.load(Ordering::Relaxed) } else { failed.fetch_add(1, Ordering::Relaxed) + 1 };
+                results.lock().unwrap()[index] = Some(result);
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(progress) = &self.progress {
+                    progress(Progress { index, ok, done, failed, total });
+                }
+            }
+        };
+        thread::scope(|scope| {
+            for _ in 1..self.jobs.min(total) {
+                scope.spawn(worker);
+            }
+            worker();
+        });
+        let results = results.into_inner().unwrap();
+        let outcomes = inputs.into_iter().zip(results).map(|(input, result)| (input, result.unwrap())).collect();
+        Report { outcomes }
+    }
+
+    /// Loads every path with [`KicadFile::load_from`].
+    pub fn load(&self, source: &dyn Source, paths: Vec<PathBuf>) -> Report<PathBuf, KicadFile, Error> {
+        self.run(paths, |path| KicadFile::load_from(source, path))
+    }
+
+    /// Loads every path and passes the document to `op`, e.g. a lint or an
+    /// export; load errors and errors from `op` end up in the same report.
+    pub fn load_with<T, F>(&self, source: &dyn Source, paths: Vec<PathBuf>, op: F) -> Report<PathBuf, T, Error>
+    where
+        T: Send,
+        F: Fn(&std::path::Path, KicadFile) -> Result<T, Error> + Sync,
+    {
+        self.run(paths, |path| op(path, KicadFile::load_from(source, path)?).map_err(|e| e.in_file(path)))
+    }
+}
+
+/// The outcome of every input of a batch, in input order.
+#[derive(Debug)]
+pub struct Report<I, T, E> {
+    pub outcomes: Vec<(I, Result<T, E>)>,
+}
+
+impl<I, T, E> Report<I, T, E> {
+    pub fn is_ok(&self) -> bool {
+        self.outcomes.iter().all(|(_, result)| result.is_ok())
+    }
+
+    pub fn successes(&self) -> impl Iterator<Item = (&I, &T)> {
+        self.outcomes.iter().filter_map(|(input, result)| result.as_ref().ok().map(|value| (input, value)))
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (&I, &E)> {
+        self.outcomes.iter().filter_map(|(input, result)| result.as_ref().err().map(|error| (input, error)))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failures().count()
+    }
+
+    /// The values if every input succeeded, otherwise all the errors.
+    pub fn into_result(self) -> Result<Vec<T>, Vec<(I, E)>> {
+        if self.is_ok() {
+            return Ok(self.outcomes.into_iter().filter_map(|(_, result)| result.ok()).collect());
+        }
+        Err(self.outcomes.into_iter().filter_map(|(input, result)| result.err().map(|error| (input, error))).collect())
+    }
+}
+
+impl<I, T, E: fmt::Display> fmt::Display for Report<I, T, E> {
+    /// A summary line followed by one line per error.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} failed", self.failed(), self.outcomes.len())?;
+        for (_, error) in self.failures() {
+            write!(f, "\n{}", error)?;
+        }
+        Ok(())
+    }
+}
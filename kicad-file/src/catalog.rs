@@ -0,0 +1,244 @@
+//! A catalog of many designs at once, for organizations with hundreds of
+//! KiCad repositories: which boards use a footprint, which designs contain
+//! a part number, how many nets the boards have.
+//!
+//! [`Catalog::scan`] walks directory trees for `.kicad_pro` files and
+//! indexes each project's board and schematic hierarchy on the threads of a
+//! [`Batch`]. Backup directories and hidden ones are skipped. With
+//! the `serde` feature the catalog can be stored, e.g. as JSON, and queried
+//! later without reading the designs again.
+//!
+//! ```no_run
+//! use kicad_file::{batch::Batch, catalog::Catalog, sexp::source::FsSource};
+//!
+//! let (catalog, failures) = Catalog::scan(&FsSource::new(), &["repos".into()], "MPN", &Batch::new());
+//! for (path, error) in &failures {
+//!     eprintln!("{}: {}", path.display(), error);
+//! }
+//! for design in catalog.using_footprint("Package_QFP:LQFP-48_7x7mm_P0.5mm") {
+//!     println!("{}", design.name);
+//! }
+//! println!("{:?}", catalog.net_histogram(50));
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::{Path, PathBuf},
+};
+
+use kicad_common::Error;
+use kicad_pcb::Board;
+use kicad_sch::Hierarchy;
+use kicad_sexp::source::Source;
+
+use crate::batch::Batch;
+
+/// What the catalog knows about one project.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Design {
+    /// Path of the `.kicad_pro` file.
+    pub project: PathBuf,
+    /// The project name, the file name without `.kicad_pro`.
+    pub name: String,
+    /// The board's footprints by library id, with how many are placed.
+    pub footprints: BTreeMap<String, usize>,
+    /// The part numbers in the part number field of the schematic's
+    /// symbols, or of the board's footprints if there is no schematic.
+    pub part_numbers: BTreeSet<String>,
+    /// The board's nets, the unconnected net 0 left out; `None` without a
+    /// board.
+    pub nets: Option<usize>,
+    /// Parts in the schematic, or footprints on the board if there is no
+    /// schematic; multi-unit parts count once.
+    pub parts: usize,
+}
+
+impl Design {
+    /// Indexes the project at `path`, reading `<name>.kicad_pcb` and the
+    /// hierarchy below `<name>.kicad_sch` where they exist. `field` names
+    /// the part number field.
+    pub fn load(source: &dyn Source, path: &Path, field: &str) -> Result<Self, Error> {
+        let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+        let mut design = Design { project: path.to_path_buf(), name, ..Default::default() };
+        let board_path = path.with_extension("kicad_pcb");
+        let board = if source.exists(&board_path) {
+            let src = source.read_to_string(&board_path).map_err(|e| Error::from(e).in_file(&board_path))?;
+            Some(Board::parse(&src).map_err(|e| e.in_file(&board_path))?)
+        } else {
+            None
+        };
+        if let Some(board) = &board {
+            for footprint in &board.footprints {
+                *design.footprints.entry(footprint.lib_id.clone()).or_default() += 1;
+            }
+            design.nets = Some(board.nets.iter().filter(|net| net.number != 0).count());
+        }
+        let schematic = path.with_extension("kicad_sch");
+        if source.exists(&schematic) {
+            let hierarchy = Hierarchy::load(source, schematic)?;
+            let parts = hierarchy.parts();
+            design.parts = parts.len();
+            let numbers = parts.iter().filter_map(|part| part.units[0].symbol.property(field));
+            design.part_numbers = numbers.filter(|number| !number.is_empty()).map(str::to_string).collect();
+        } else if let Some(board) = &board {
+            design.parts = board.footprints.len();
+            let numbers = board.footprints.iter().filter_map(|footprint| footprint.property(field));
+            design.part_numbers = numbers.filter(|number| !number.is_empty()).map(str::to_string).collect();
+        }
+        Ok(design)
+    }
+}
+
+/// The designs of many projects, by project path.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Catalog {
+    /// Sorted by project path.
+    pub designs: Vec<Design>,
+}
+
+impl Catalog {
+    /// Indexes every project below `roots`, with `field` as the part number
+    /// field. Projects that fail to load, and directories that fail to
+    /// list, are returned with their errors and left out.
+    pub fn scan(source: &dyn Source, roots: &[PathBuf], field: &str, batch: &Batch) -> (Self, Vec<(PathBuf, Error)>) {
+        let mut projects = Vec::new();
+        let mut failures = Vec::new();
+        for root in roots {
+            if let Err(error) = find_projects(source, root, &mut projects) {
+                failures.push((root.clone(), Error::from(error).in_file(root)));
+            }
+        }
+        let report = batch.run(projects, |path| Design::load(source, path, field));
+        let mut catalog = Catalog::default();
+        for (path, result) in report.outcomes {
+            match result {
+                Ok(design) => catalog.insert(design),
+                Err(error) => failures.push((path, error)),
+            }
+        }
+        (catalog, failures)
+    }
+
+    /// Adds `design`, replacing the one of the same project.
+    pub fn insert(&mut self, design: Design) {
+        match self.designs.binary_search_by(|other| other.project.cmp(&design.project)) {
+            Ok(i) => self.designs[i] = design,
+            Err(i) => self.designs.insert(i, design),
+        }
+    }
+
+    /// Drops the design of the project at `path`.
+    pub fn remove(&mut self, path: &Path) -> Option<Design> {
+        let i = self.designs.binary_search_by(|design| design.project.as_path().cmp(path)).ok()?;
+        Some(self.designs.remove(i))
+    }
+
+    /// The designs whose board has the footprint `lib_id`: a full
+    /// `Library:Footprint` id, or a footprint name alone to match it in any
+    /// library.
+    pub fn using_footprint<'c>(&'c self, lib_id: &'c str) -> impl Iterator<Item = &'c Design> {
+        self.designs.iter().filter(move |design| {
+            design.footprints.keys().any(|id| {
+                if lib_id.contains(':') { id == lib_id } else { id.rsplit(':').next() == Some(lib_id) }
+            })
+        })
+    }
+
+    /// The designs containing the part number `number`, ignoring case.
+    pub fn with_part_number<'c>(&'c self, number: &'c str) -> impl Iterator<Item = &'c Design> {
+        self.designs.iter().filter(move |design| design.part_numbers.iter().any(|n| n.eq_ignore_ascii_case(number)))
+    }
+
+    /// How many designs place each footprint, by library id.
+    pub fn footprint_usage(&self) -> BTreeMap<&str, usize> {
+        let mut usage = BTreeMap::new();
+        for id in self.designs.iter().flat_map(|design| design.footprints.keys()) {
+            *usage.entry(id.as_str()).or_default() += 1;
+        }
+        usage
+    }
+
+    /// How many boards have each number of nets, in buckets of `width`
+    /// keyed by their lowest count; designs without a board are left out.
+    /// A `width` of 0 counts as 1.
+    pub fn net_histogram(&self, width: usize) -> BTreeMap<usize, usize> {
+        let width = width.max(1);
+        let mut histogram = BTreeMap::new();
+        for nets in self.designs.iter().filter_map(|design| design.nets) {
+            *histogram.entry(nets / width * width).or_default() += 1;
+        }
+        histogram
+    }
+}
+
+/// Adds the `.kicad_pro` files below `dir` to `projects`, depth first.
+/// Entries below it that cannot be listed are files.
+fn find_projects(source: &dyn Source, dir: &Path, projects: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in source.read_dir(dir)? {
+        let name = entry.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if entry.extension().is_some_and(|extension| extension == "kicad_pro") {
+            projects.push(entry);
+        } else if !name.starts_with('.') && !name.ends_with("-backups") {
+            let _ = find_projects(source, &entry, projects);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_sexp::source::{FsSource, MemorySource};
+
+    use super::*;
+
+    #[test]
+    fn reference_files() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let (catalog, failures) = Catalog::scan(&source, &["".into()], "MPN", &Batch::new().jobs(2));
+        assert!(failures.is_empty(), "{:?}", failures);
+        let names: Vec<_> = catalog.designs.iter().map(|design| design.name.as_str()).collect();
+        assert_eq!(names, ["demo", "empty"]);
+
+        let demo = &catalog.designs[0];
+        assert_eq!(demo.project, Path::new("demo/demo.kicad_pro"));
+        assert_eq!(demo.footprints.get("Resistor_SMD:R_0603_1608Metric"), Some(&2));
+        assert_eq!(demo.nets, Some(3));
+        assert!(demo.part_numbers.contains("RC0603FR-0710KL"));
+
+        let using: Vec<_> = catalog.using_footprint("R_0603_1608Metric").map(|design| &design.name).collect();
+        assert_eq!(using, ["demo"]);
+        assert_eq!(catalog.using_footprint("Other:R_0603_1608Metric").count(), 0);
+        assert_eq!(catalog.with_part_number("rc0603fr-0710kl").count(), 1);
+        assert_eq!(catalog.footprint_usage()["Resistor_SMD:R_0603_1608Metric"], 1);
+        assert_eq!(catalog.net_histogram(10), [(0, 2)].into());
+        assert_eq!(catalog.net_histogram(0), [(0, 1), (3, 1)].into());
+    }
+
+    #[test]
+    fn failures_and_updates() {
+        let board = "(kicad_pcb (version 20240108) (generator \"x\") (net 0 \"\") (net 1 \"GND\") \
+                     (footprint \"Lib:U\" (layer \"F.Cu\") (property \"MPN\" \"NE555\" (at 0 0) (layer \"F.Fab\"))))";
+        let source = MemorySource::new()
+            .with("a/a.kicad_pro", "{}")
+            .with("a/a.kicad_pcb", board)
+            .with("b/b.kicad_pro", "{}")
+            .with("b/b.kicad_pcb", "(kicad_pcb (version 1) (segment))");
+        let (mut catalog, failures) = Catalog::scan(&source, &["".into(), "missing".into()], "MPN", &Batch::new());
+        let failed: Vec<_> = failures.iter().map(|(path, _)| path.to_str().unwrap()).collect();
+        assert_eq!(failed, ["missing", "b/b.kicad_pro"], "{:?}", failures);
+        assert_eq!(failures[1].1.file(), Some(Path::new("b/b.kicad_pcb")));
+
+        assert_eq!(catalog.designs.len(), 1);
+        let a = &catalog.designs[0];
+        assert_eq!((a.nets, a.parts, a.part_numbers.len()), (Some(1), 1, 1));
+        assert_eq!(catalog.with_part_number("NE555").count(), 1);
+
+        catalog.insert(Design { project: "a/a.kicad_pro".into(), name: "a".into(), ..Default::default() });
+        assert_eq!(catalog.designs.len(), 1);
+        assert_eq!(catalog.with_part_number("NE555").count(), 0);
+        assert!(catalog.remove(Path::new("a/a.kicad_pro")).is_some() && catalog.designs.is_empty());
+    }
+}
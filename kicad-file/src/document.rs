@@ -0,0 +1,196 @@
+//! Loading any KiCad file into the matching typed document, for tools such
+//! as formatters and linters that do not care which kind they get.
+
+use std::path::Path;
+
+use kicad_common::{netlist::Netlist, Error, LibTable, LibTableKind};
+use kicad_sexp::source::{FsSource, Source};
+
+#[cfg(feature = "dru")]
+use kicad_dru::Rules;
+#[cfg(feature = "pcb")]
+use kicad_pcb::{Board, Footprint};
+#[cfg(feature = "project")]
+use kicad_pro::ProjectSettings;
+#[cfg(feature = "sch")]
+use kicad_sch::Schematic;
+#[cfg(feature = "sym")]
+use kicad_sym::SymbolLib;
+#[cfg(feature = "wks")]
+use kicad_wks::DrawingSheet;
+
+/// The kinds of file [`KicadFile`] tells apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    Schematic,
+    Board,
+    Footprint,
+    SymbolLib,
+    DrawingSheet,
+    Project,
+    DesignRules,
+    Netlist,
+    FootprintLibTable,
+    SymbolLibTable,
+}
+
+impl FileKind {
+    /// The kind a file name or extension stands for.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.file_name()?.to_str()? {
+            "fp-lib-table" => return Some(FileKind::FootprintLibTable),
+            "sym-lib-table" => return Some(FileKind::SymbolLibTable),
+            _ => {},
+        }
+        Some(match path.extension()?.to_str()? {
+            "kicad_sch" => FileKind::Schematic,
+            "kicad_pcb" => FileKind::Board,
+            "kicad_mod" => FileKind::Footprint,
+            "kicad_sym" => FileKind::SymbolLib,
+            "kicad_wks" => FileKind::DrawingSheet,
+            "kicad_pro" => FileKind::Project,
+            "kicad_dru" => FileKind::DesignRules,
+            "net" => FileKind::Netlist,
+            _ => return None,
+        })
+    }
+
+    /// The kind of document `src` holds, from its root tag, or for
+    /// projects its opening brace. Design rules have no root, so they are
+    /// known by a leading `(version ...)` or `(rule ...)`.
+    pub fn sniff(src: &str) -> Option<Self> {
+        let text = src
+            .lines()
+            .map(str::trim_start)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+        if text.starts_with('{') {
+            return Some(FileKind::Project);
+        }
+        let tag = text.strip_prefix('(')?.trim_start();
+        let tag = &tag[..tag.find(|c: char| c.is_whitespace() || c == '(' || c == ')').unwrap_or(tag.len())];
+        Some(match tag {
+            "kicad_sch" => FileKind::Schematic,
+            "kicad_pcb" => FileKind::Board,
+            "footprint" | "module" => FileKind::Footprint,
+            "kicad_symbol_lib" => FileKind::SymbolLib,
+            "kicad_wks" => FileKind::DrawingSheet,
+            "version" | "rule" => FileKind::DesignRules,
+            "export" => FileKind::Netlist,
+            "fp_lib_table" => FileKind::FootprintLibTable,
+            "sym_lib_table" => FileKind::SymbolLibTable,
+            _ => return None,
+        })
+    }
+
+    /// Sniffs the contents, falling back to the file name for contents
+    /// that give nothing away, such as an empty project.
+    pub fn detect(path: &Path, src: &str) -> Option<Self> {
+        Self::sniff(src).or_else(|| Self::from_path(path))
+    }
+
+    /// The cargo feature of this crate that reads the kind.
+    pub fn feature(&self) -> Option<&'static str> {
+        match self {
+            FileKind::Schematic => Some("sch"),
+            FileKind::Board | FileKind::Footprint => Some("pcb"),
+            FileKind::SymbolLib => Some("sym"),
+            FileKind::DrawingSheet => Some("wks"),
+            FileKind::Project => Some("project"),
+            FileKind::DesignRules => Some("dru"),
+            FileKind::Netlist | FileKind::FootprintLibTable | FileKind::SymbolLibTable => None,
+        }
+    }
+}
+
+/// A typed document of any kind. Variants follow the enabled features;
+/// the typed models are boxed to keep the enum small.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum KicadFile {
+    #[cfg(feature = "sch")]
+    Schematic(Box<Schematic>),
+    #[cfg(feature = "pcb")]
+    Board(Box<Board>),
+    #[cfg(feature = "pcb")]
+    Footprint(Box<Footprint>),
+    #[cfg(feature = "sym")]
+    SymbolLib(Box<SymbolLib>),
+    #[cfg(feature = "wks")]
+    DrawingSheet(Box<DrawingSheet>),
+    /// The settings of a `.kicad_pro`, without the documents next to it;
+    /// see [`crate::project::Project`] for those.
+    #[cfg(feature = "project")]
+    Project(Box<ProjectSettings>),
+    #[cfg(feature = "dru")]
+    DesignRules(Box<Rules>),
+    /// A netlist export.
+    Netlist(Netlist),
+    LibTable(LibTable),
+}
+
+impl KicadFile {
+    /// Loads the file at `path` from the filesystem.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::load_from(&FsSource::new(), path.as_ref())
+    }
+
+    /// Loads `path` from `source`, naming the file in errors.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(path = %path.display())))]
+    pub fn load_from(source: &dyn Source, path: &Path) -> Result<Self, Error> {
+        let src = source.read_to_string(path).map_err(|e| Error::from(e).in_file(path))?;
+        let kind = FileKind::detect(path, &src)
+            .ok_or_else(|| Error::Unsupported("not a KiCad document".to_string()).in_file(path))?;
+        Self::parse(&src, kind).map_err(|e| e.in_file(path))
+    }
+
+    /// Parses `src` as a document of `kind`.
+    pub fn parse(src: &str, kind: FileKind) -> Result<Self, Error> {
+        Ok(match kind {
+            #[cfg(feature = "sch")]
+            FileKind::Schematic => KicadFile::Schematic(Box::new(Schematic::parse(src)?)),
+            #[cfg(feature = "pcb")]
+            FileKind::Board => KicadFile::Board(Box::new(Board::parse(src)?)),
+            #[cfg(feature = "pcb")]
+            FileKind::Footprint => KicadFile::Footprint(Box::new(Footprint::parse(src)?)),
+            #[cfg(feature = "sym")]
+            FileKind::SymbolLib => KicadFile::SymbolLib(Box::new(SymbolLib::parse(src)?)),
+            #[cfg(feature = "wks")]
+            FileKind::DrawingSheet => KicadFile::DrawingSheet(Box::new(DrawingSheet::parse(src)?)),
+            #[cfg(feature = "project")]
+            FileKind::Project => KicadFile::Project(Box::new(ProjectSettings::parse(src)?)),
+            #[cfg(feature = "dru")]
+            FileKind::DesignRules => KicadFile::DesignRules(Box::new(Rules::parse(src)?)),
+            FileKind::Netlist => KicadFile::Netlist(Netlist::parse(src)?),
+            FileKind::FootprintLibTable | FileKind::SymbolLibTable => KicadFile::LibTable(LibTable::parse(src)?),
+            #[allow(unreachable_patterns)]
+            kind => {
+                let feature = kind.feature().unwrap_or_default();
+                return Err(Error::Unsupported(format!("reading {:?} files needs the `{}` feature", kind, feature)));
+            },
+        })
+    }
+
+    pub fn kind(&self) -> FileKind {
+        match self {
+            #[cfg(feature = "sch")]
+            KicadFile::Schematic(_) => FileKind::Schematic,
+            #[cfg(feature = "pcb")]
+            KicadFile::Board(_) => FileKind::Board,
+            #[cfg(feature = "pcb")]
+            KicadFile::Footprint(_) => FileKind::Footprint,
+            #[cfg(feature = "sym")]
+            KicadFile::SymbolLib(_) => FileKind::SymbolLib,
+            #[cfg(feature = "wks")]
+            KicadFile::DrawingSheet(_) => FileKind::DrawingSheet,
+            #[cfg(feature = "project")]
+            KicadFile::Project(_) => FileKind::Project,
+            #[cfg(feature = "dru")]
+            KicadFile::DesignRules(_) => FileKind::DesignRules,
+            KicadFile::Netlist(_) => FileKind::Netlist,
+            KicadFile::LibTable(table) => match table.kind {
+                LibTableKind::Footprint => FileKind::FootprintLibTable,
+                LibTableKind::Symbol => FileKind::SymbolLibTable,
+            },
+        }
+    }
+}
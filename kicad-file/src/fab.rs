@@ -0,0 +1,173 @@
+//! Upload-ready fab packages: a board's Gerber, drill and placement files,
+//! its drill report and BOM, and a `README.txt` manifest saying what each
+//! file is, zipped into one archive.
+//!
+//! Layers are named the way KiCad names them, or with Protel extensions
+//! through [`GerberOptions::protel_extensions`], which most fabs also
+//! recognize. The paste layers are the stencil.
+//!
+//! ```no_run
+//! use kicad_file::{
+//!     fab::FabPackage,
+//!     pcb::Board,
+//!     sexp::zip::DateTime,
+//! };
+//!
+//! let board = Board::parse(&std::fs::read_to_string("demo.kicad_pcb")?)?;
+//! let mut package = FabPackage::new(&board, "demo");
+//! package.gerber.protel_extensions = true;
+//! std::fs::write("demo-fab.zip", package.to_zip(DateTime::from_unix(1_760_400_000))?)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{fmt::Write, io};
+
+use kicad_pcb::{
+    drill::{drill_files, drill_report, DrillOptions},
+    gerber::{gerber_files, GerberOptions},
+    placement::{position_file, PlacementOptions},
+    Board, Layer,
+};
+use kicad_sexp::zip::{DateTime, ZipWriter};
+
+/// The files of one board for its fab and assembly house.
+#[derive(Clone, Debug)]
+pub struct FabPackage<'a> {
+    pub board: &'a Board,
+    /// What the files are named after, usually the board's file name.
+    pub name: String,
+    pub gerber: GerberOptions,
+    pub drill: DrillOptions,
+    pub placement: PlacementOptions,
+    /// The BOM as CSV, such as `kicad_sch::Bom::to_csv`
+    /// writes it; left out when `None`.
+    pub bom: Option<String>,
+}
+
+impl<'a> FabPackage<'a> {
+    pub fn new(board: &'a Board, name: &str) -> Self {
+        FabPackage {
+            board,
+            name: name.to_string(),
+            gerber: GerberOptions::default(),
+            drill: DrillOptions::default(),
+            placement: PlacementOptions::default(),
+            bom: None,
+        }
+    }
+
+    /// Adds the BOM of the board's schematic.
+    #[cfg(feature = "sch")]
+    pub fn with_bom(mut self, bom: &kicad_sch::Bom) -> Self {
+        self.bom = Some(bom.to_csv());
+        self
+    }
+
+    /// The files by name, the manifest last.
+    pub fn files(&self) -> Vec<(String, Vec<u8>)> {
+        let mut files = Vec::new();
+        let mut manifest = Vec::new();
+        for file in gerber_files(self.board, &self.name, &self.gerber) {
+            manifest.push((file.name.clone(), layer_role(self.board, file.layer)));
+            files.push((file.name, file.contents.into_bytes()));
+        }
+        let drills = drill_files(self.board, &self.name, &self.drill);
+        for file in &drills {
+            let role = match (file.span.plated, file.span.is_through()) {
+                (false, _) => "Drill, non-plated holes".to_string(),
+                (true, true) => "Drill, plated through holes".to_string(),
+                (true, false) => format!("Drill, plated holes from {} to {}", file.span.front, file.span.back),
+            };
+            manifest.push((file.name.clone(), role));
+            files.push((file.name.clone(), file.contents.clone().into_bytes()));
+        }
+        let report = format!("{}-drl.rpt", self.name);
+        manifest.push((report.clone(), "Drill report".to_string()));
+        files.push((report, drill_report(&self.name, &drills).into_bytes()));
+        let placement = format!("{}-all-pos.csv", self.name);
+        manifest.push((placement.clone(), "Component placement, both sides".to_string()));
+        files.push((placement, position_file(self.board, &self.placement).to_csv().into_bytes()));
+        if let Some(bom) = &self.bom {
+            let name = format!("{}-bom.csv", self.name);
+            manifest.push((name.clone(), "Bill of materials".to_string()));
+            files.push((name, bom.clone().into_bytes()));
+        }
+        files.push(("README.txt".to_string(), self.manifest(&manifest).into_bytes()));
+        files
+    }
+
+    fn manifest(&self, files: &[(String, String)]) -> String {
+        let board = self.board;
+        let mut out = format!("{} fab package\n\n", self.name);
+        let finish = board.setup.copper_finish.as_deref().map(|finish| format!(", {} finish", finish));
+        let _ = writeln!(
+            out,
+            "Board: {} copper layers, {} mm thick{}",
+            board.copper_layers().len(),
+            board.thickness,
+            finish.unwrap_or_default()
+        );
+        out.push_str("Units: millimeters. Gerber files are RS-274X with X2 attributes.\n\nFiles:\n");
+        let width = files.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, role) in files {
+            let _ = writeln!(out, "  {:width$}  {}", name, role, width = width);
+        }
+        out
+    }
+
+    /// The files zipped, with `modified` as their time.
+    pub fn to_zip(&self, modified: DateTime) -> io::Result<Vec<u8>> {
+        let mut zip = ZipWriter::new();
+        for (name, data) in self.files() {
+            zip.add(&name, modified, &data)?;
+        }
+        zip.finish()
+    }
+}
+
+/// What a fab layer's file is for.
+fn layer_role(board: &Board, layer: Layer) -> String {
+    let side = if layer.is_back() { "Bottom" } else { "Top" };
+    match layer {
+        Layer::FCu | Layer::BCu | Layer::InCu(_) => {
+            let index = board.copper_layers().iter().position(|&l| l == layer).map_or(0, |i| i + 1);
+            let side = if matches!(layer, Layer::InCu(_)) { "Inner" } else { side };
+            format!("{} copper, layer {}", side, index)
+        },
+        Layer::FMask | Layer::BMask => format!("{} solder mask", side),
+        Layer::FSilkS | Layer::BSilkS => format!("{} silkscreen", side),
+        Layer::FPaste | Layer::BPaste => format!("{} paste, for the stencil", side),
+        Layer::EdgeCuts => "Board outline".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_sexp::zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn demo() {
+        let board = Board::parse(include_str!("../../reference-files/demo/demo.kicad_pcb")).unwrap();
+        let mut package = FabPackage::new(&board, "demo");
+        package.bom = Some("Reference,Quantity\nR1,1\n".to_string());
+        let archive = ZipArchive::new(package.to_zip(DateTime::from_unix(1_760_400_000)).unwrap()).unwrap();
+        let names: Vec<_> = archive.entries().iter().map(|entry| entry.name.as_str()).collect();
+        for name in ["demo-F_Cu.gbr", "demo-Edge_Cuts.gbr", "demo-PTH.drl", "demo-drl.rpt", "demo-all-pos.csv"] {
+            assert!(names.contains(&name), "{} not in {:?}", name, names);
+        }
+        assert_eq!(names[names.len() - 2..], ["demo-bom.csv", "README.txt"]);
+
+        let readme = String::from_utf8(archive.read_file("README.txt").unwrap()).unwrap();
+        assert!(readme.starts_with("demo fab package\n\nBoard: 2 copper layers, 1.6 mm thick"));
+        assert!(readme.contains("  demo-F_Cu.gbr       Top copper, layer 1\n"), "{}", readme);
+        assert!(readme.contains("  demo-B_Paste.gbr    Bottom paste, for the stencil\n"));
+        assert!(readme.contains("  demo-PTH.drl        Drill, plated through holes\n"));
+
+        package.gerber.protel_extensions = true;
+        let files = package.files();
+        assert!(files.iter().any(|(name, _)| name == "demo-F_Cu.gtl"));
+    }
+}
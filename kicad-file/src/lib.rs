@@ -0,0 +1,277 @@
+//! All the KiCad file crates behind one dependency, with a cargo feature
+//! per document type so tools only compile the models they use:
+//!
+//! ```toml
+//! kicad-file = { version = "0.1", default-features = false, features = ["sch"] }
+//! ```
+//!
+//! The s-expression layer and the shared types are always available.
+
+pub use kicad_common as common;
+pub use kicad_sexp as sexp;
+
+#[cfg(feature = "backup")]
+pub mod backup;
+pub mod batch;
+#[cfg(feature = "catalog")]
+pub mod catalog;
+pub mod document;
+#[cfg(feature = "fab")]
+pub mod fab;
+#[cfg(feature = "project")]
+pub mod project;
+
+pub use document::{FileKind, KicadFile};
+
+#[cfg(feature = "dru")]
+pub use kicad_dru as dru;
+#[cfg(feature = "pcb")]
+pub use kicad_pcb as pcb;
+#[cfg(feature = "sch")]
+pub use kicad_sch as sch;
+#[cfg(feature = "sym")]
+pub use kicad_sym as sym;
+#[cfg(feature = "wks")]
+pub use kicad_wks as wks;
+
+#[cfg(all(test, feature = "project", feature = "sch", feature = "pcb", feature = "wks", feature = "dru"))]
+mod tests {
+    use std::path::Path;
+
+    use kicad_sexp::source::{FsSource, MemorySource, Source};
+
+    use super::*;
+    use crate::project::Project;
+
+    #[test]
+    fn project() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let mut project = Project::open(&source, "demo/demo.kicad_pro").unwrap();
+
+        assert_eq!(project.name(), "demo");
+        assert_eq!(project.document_path("kicad_sch"), Path::new("demo/demo.kicad_sch"));
+        assert_eq!(project.schematic.as_ref().unwrap().title_block.as_ref().unwrap().rev.as_deref(), Some("1.2"));
+        assert_eq!(project.drawing_sheet.as_ref().unwrap().items.len(), 24);
+        let board = project.board.as_ref().unwrap();
+        let classes: Vec<_> = board.nets[1..].iter().map(|net| project.net_class(&net.name).name).collect();
+        assert_eq!(classes, ["Power", "Default", "Power"]);
+        assert_eq!(project.design_rules.as_ref().unwrap().rules.len(), 7);
+        let footprints = project.footprint_libs.as_ref().unwrap();
+        let resolved = footprints.resolve("demo:R_0603_1608Metric", &project.env()).unwrap().unwrap();
+        assert!(source.exists(&resolved.footprint_path()));
+        let symbols = project.symbol_libs.as_ref().unwrap();
+        assert_eq!(symbols.resolve("demo:R", &project.env()).unwrap().unwrap().path, Path::new("demo/demo.kicad_sym"));
+        let names: Vec<_> = project.field_templates().iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, ["MPN", "Supplier"]);
+        assert!(project.apply_field_templates().is_empty());
+        assert!(project.schematic.unwrap().symbols.iter().all(|symbol| symbol.property("Supplier").is_some()));
+
+        let empty = Project::open(&source, "empty/empty.kicad_pro").unwrap();
+        assert!(empty.schematic.is_some() && empty.board.is_some());
+        assert!(empty.footprint_libs.is_none() && empty.symbol_libs.is_none());
+        assert_eq!((empty.drawing_sheet_path(), empty.drawing_sheet), (None, None));
+    }
+
+    #[test]
+    fn project_errors() {
+        let settings = "{\"schematic\": {\"page_layout_descr_file\": \"${KIPRJMOD}/frame.kicad_wks\"}}";
+        let source = MemorySource::new()
+            .with("a/a.kicad_pro", "{}")
+            .with("a/a.kicad_pcb", "(kicad_pcb (version 1) (segment))");
+        let error = Project::open(&source, "a/a.kicad_pro").unwrap_err();
+        assert_eq!(error.file(), Some(Path::new("a/a.kicad_pcb")));
+        assert!(matches!(error.kind(), common::Error::Convert(_)));
+
+        let source = MemorySource::new().with("b.kicad_pro", settings);
+        let project = Project::open(&source, "b.kicad_pro").unwrap();
+        assert_eq!(project.drawing_sheet_path(), Some("frame.kicad_wks".into()));
+        assert!(project.schematic.is_none() && project.drawing_sheet.is_none());
+
+        let error = Project::open(&source, "missing.kicad_pro").unwrap_err();
+        assert!(matches!(error.kind(), common::Error::Io(_)));
+        assert_eq!(error.file(), Some(Path::new("missing.kicad_pro")));
+    }
+
+    #[test]
+    fn load() {
+        let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files/demo"));
+        let kinds = [
+            ("demo.kicad_sch", FileKind::Schematic),
+            ("demo.kicad_pcb", FileKind::Board),
+            ("demo.pretty/R_0603_1608Metric.kicad_mod", FileKind::Footprint),
+            ("demo.kicad_sym", FileKind::SymbolLib),
+            ("demo.kicad_wks", FileKind::DrawingSheet),
+            ("demo.kicad_pro", FileKind::Project),
+            ("demo.kicad_dru", FileKind::DesignRules),
+            ("demo.net", FileKind::Netlist),
+            ("fp-lib-table", FileKind::FootprintLibTable),
+            ("sym-lib-table", FileKind::SymbolLibTable),
+        ];
+        for (file, kind) in kinds {
+            let path = dir.join(file);
+            assert_eq!(FileKind::from_path(&path), Some(kind), "{}", file);
+            assert_eq!(KicadFile::load(&path).unwrap().kind(), kind, "{}", file);
+        }
+        let KicadFile::Netlist(netlist) = KicadFile::load(dir.join("demo.net")).unwrap() else {
+            panic!("expected a netlist");
+        };
+        assert_eq!(netlist.nets.len(), 3);
+    }
+
+    #[test]
+    fn load_sniffing() {
+        let source = MemorySource::new()
+            .with("renamed.txt", "(kicad_pcb (version 20241229) (generator \"pcbnew\"))")
+            .with("empty.kicad_pro", "")
+            .with("notes.txt", "hello")
+            .with("broken.kicad_sch", "(kicad_sch (version x))");
+        let board = KicadFile::load_from(&source, Path::new("renamed.txt")).unwrap();
+        assert!(matches!(board, KicadFile::Board(_)));
+        assert_eq!(FileKind::sniff("# comment\n(version 1)\n(rule x)"), Some(FileKind::DesignRules));
+        assert_eq!(FileKind::sniff("(module R (layer F.Cu))"), Some(FileKind::Footprint));
+        assert_eq!(FileKind::detect(Path::new("empty.kicad_pro"), ""), Some(FileKind::Project));
+
+        let error = KicadFile::load_from(&source, Path::new("notes.txt")).unwrap_err();
+        assert_eq!(error.to_string(), "notes.txt: not a KiCad document");
+        let error = KicadFile::load_from(&source, Path::new("broken.kicad_sch")).unwrap_err();
+        assert_eq!(error.file(), Some(Path::new("broken.kicad_sch")));
+        assert!(matches!(error.kind(), common::Error::Convert(_)));
+        assert!(KicadFile::parse("(kicad_pcb)", FileKind::Netlist).is_err());
+    }
+
+    #[test]
+    fn batch() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        };
+
+        use crate::batch::Batch;
+
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let batch = Batch::new().jobs(3).on_progress(move |progress| log.lock().unwrap().push(progress));
+        let report = batch.run((0..20).collect(), |&n: &u32| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            running.fetch_sub(1, Ordering::SeqCst);
+            if n % 7 == 3 { Err(format!("{} failed", n)) } else { Ok(n * 2) }
+        });
+        assert!(most.load(Ordering::SeqCst) <= 3);
+        assert_eq!(report.outcomes.iter().map(|(n, _)| *n).collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+        assert_eq!(report.outcomes[4].1, Ok(8));
+        assert_eq!(report.to_string(), "3 of 20 failed\n3 failed\n10 failed\n17 failed");
+        let seen = seen.lock().unwrap();
+        assert_eq!((seen.len(), seen.iter().map(|p| p.done).max()), (20, Some(20)));
+        assert_eq!(seen.iter().filter(|p| !p.ok).count(), 3);
+        assert_eq!(seen.iter().map(|p| p.failed).max(), Some(3));
+        assert!(report.into_result().is_err());
+
+        let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files/demo"));
+        let paths = vec![dir.join("demo.kicad_pcb"), dir.join("demo.kicad_sch"), dir.join("missing.kicad_pcb")];
+        let report = Batch::new().load(&FsSource::new(), paths.clone());
+        assert_eq!(report.successes().count(), 2);
+        let (path, error) = report.failures().next().unwrap();
+        assert_eq!((path, error.file()), (&paths[2], Some(paths[2].as_path())));
+
+        let kinds = Batch::new().jobs(1).load_with(&FsSource::new(), paths[..2].to_vec(), |_, file| Ok(file.kind()));
+        assert_eq!(kinds.into_result().unwrap(), [FileKind::Board, FileKind::Schematic]);
+        assert!(Batch::new().run(Vec::<u32>::new(), |_| Ok::<_, ()>(())).is_ok());
+    }
+
+    /// Integer fields recorded on a span.
+    #[cfg(feature = "tracing")]
+    type Fields = Vec<(&'static str, u64)>;
+
+    /// Keeps the name and counts of every span opened.
+    #[cfg(feature = "tracing")]
+    #[derive(Default)]
+    struct Spans {
+        spans: std::sync::Mutex<Vec<(&'static tracing::Metadata<'static>, Fields)>>,
+        entered: std::sync::Mutex<Vec<tracing::span::Id>>,
+    }
+
+    #[cfg(feature = "tracing")]
+    struct Counts<'a>(&'a mut Fields);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for Counts<'_> {
+        fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+            self.0.push((field.name(), value));
+        }
+
+        fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for Spans {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut spans = self.spans.lock().unwrap();
+            let mut counts = Vec::new();
+            span.record(&mut Counts(&mut counts));
+            spans.push((span.metadata(), counts));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut Counts(&mut spans[span.into_u64() as usize - 1].1));
+        }
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, _: &tracing::Event<'_>) {}
+
+        fn enter(&self, span: &tracing::span::Id) {
+            self.entered.lock().unwrap().push(span.clone());
+        }
+
+        fn exit(&self, _: &tracing::span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> tracing_core::span::Current {
+            match self.entered.lock().unwrap().last() {
+                Some(span) => {
+                    let metadata = self.spans.lock().unwrap()[span.into_u64() as usize - 1].0;
+                    tracing_core::span::Current::new(span.clone(), metadata)
+                },
+                None => tracing_core::span::Current::none(),
+            }
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing() {
+        let spans = std::sync::Arc::new(Spans::default());
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let project = tracing::subscriber::with_default(spans.clone(), || {
+            Project::open(&source, "demo/demo.kicad_pro").unwrap()
+        });
+
+        let spans = spans.spans.lock().unwrap();
+        let names: Vec<_> =
+            spans.iter().map(|(metadata, _)| format!("{}::{}", metadata.target(), metadata.name())).collect();
+        assert_eq!(names[..6], [
+            "kicad_file::project::open",
+            "kicad_pro::settings::parse",
+            "kicad_sch::schematic::parse",
+            "kicad_common::convert::parse_document",
+            "kicad_sexp::parse",
+            "kicad_common::convert::convert",
+        ]);
+        let board = project.board.as_ref().unwrap();
+        let counts = [board.footprints.len(), board.tracks.len(), board.zones.len()].map(|count| count as u64);
+        let board_span = names.iter().position(|name| name == "kicad_pcb::board::parse").unwrap();
+        assert_eq!(spans[board_span].1, [("footprints", counts[0]), ("tracks", counts[1]), ("zones", counts[2])]);
+        assert_eq!(spans.iter().filter(|(metadata, _)| metadata.name() == "convert").count(), 3);
+    }
+}
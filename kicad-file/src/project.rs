@@ -0,0 +1,152 @@
+//! Opening a KiCad project: the `.kicad_pro` settings together with the
+//! documents saved next to it under the same name.
+
+use std::path::{Path, PathBuf};
+
+use kicad_common::{property::FieldTemplate, EnvVars, LibTable, LibTableKind};
+#[cfg(feature = "sch")]
+use kicad_sexp::schema::Issue;
+use kicad_sexp::source::Source;
+
+pub use kicad_pro::*;
+
+#[cfg(feature = "dru")]
+use kicad_dru::Rules;
+#[cfg(feature = "pcb")]
+use kicad_pcb::Board;
+#[cfg(feature = "sch")]
+use kicad_sch::Schematic;
+#[cfg(feature = "wks")]
+use kicad_wks::DrawingSheet;
+
+/// A loaded project. Documents the project has no file for are `None`.
+#[derive(Clone, Debug)]
+pub struct Project {
+    /// Path of the `.kicad_pro` file.
+    pub path: PathBuf,
+    pub settings: ProjectSettings,
+    /// The project's own `fp-lib-table`, in front of the global one.
+    pub footprint_libs: Option<LibTable>,
+    /// The project's own `sym-lib-table`.
+    pub symbol_libs: Option<LibTable>,
+    /// The root sheet, `<name>.kicad_sch`.
+    #[cfg(feature = "sch")]
+    pub schematic: Option<Schematic>,
+    /// `<name>.kicad_pcb`.
+    #[cfg(feature = "pcb")]
+    pub board: Option<Board>,
+    /// Custom design rules, `<name>.kicad_dru`.
+    #[cfg(feature = "dru")]
+    pub design_rules: Option<Rules>,
+    /// The custom drawing sheet set in the schematic settings.
+    #[cfg(feature = "wks")]
+    pub drawing_sheet: Option<DrawingSheet>,
+}
+
+/// Loads `path` with `parse` if it exists, naming the file in errors.
+fn load<T>(source: &dyn Source, path: &Path, parse: fn(&str) -> Result<T, Error>) -> Result<Option<T>, Error> {
+    if !source.exists(path) {
+        return Ok(None);
+    }
+    let src = source.read_to_string(path).map_err(|e| Error::from(e).in_file(path))?;
+    parse(&src).map(Some).map_err(|e| e.in_file(path))
+}
+
+impl Project {
+    /// Loads the project file at `path` and the documents that belong to it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(path)))]
+    pub fn open(source: &dyn Source, path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("path", tracing::field::display(path.display()));
+        let src = source.read_to_string(&path).map_err(|e| Error::from(e).in_file(&path))?;
+        let settings = ProjectSettings::parse(&src).map_err(|e| e.in_file(&path))?;
+        let mut project = Project {
+            path,
+            settings,
+            footprint_libs: None,
+            symbol_libs: None,
+            #[cfg(feature = "sch")]
+            schematic: None,
+            #[cfg(feature = "pcb")]
+            board: None,
+            #[cfg(feature = "dru")]
+            design_rules: None,
+            #[cfg(feature = "wks")]
+            drawing_sheet: None,
+        };
+        #[cfg(feature = "sch")]
+        {
+            project.schematic = load(source, &project.document_path("kicad_sch"), Schematic::parse)?;
+        }
+        #[cfg(feature = "pcb")]
+        {
+            project.board = load(source, &project.document_path("kicad_pcb"), Board::parse)?;
+        }
+        #[cfg(feature = "dru")]
+        {
+            project.design_rules = load(source, &project.document_path("kicad_dru"), Rules::parse)?;
+        }
+        #[cfg(feature = "wks")]
+        if let Some(sheet) = project.drawing_sheet_path() {
+            project.drawing_sheet = load(source, &sheet, DrawingSheet::parse)?;
+        }
+        project.footprint_libs = load(source, &project.lib_table_path(LibTableKind::Footprint), LibTable::parse)?;
+        project.symbol_libs = load(source, &project.lib_table_path(LibTableKind::Symbol), LibTable::parse)?;
+        Ok(project)
+    }
+
+    /// The project name, the file name without `.kicad_pro`.
+    pub fn name(&self) -> &str {
+        self.path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default()
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+
+    /// Path of the project's document with `extension`, e.g. `kicad_pcb`.
+    pub fn document_path(&self, extension: &str) -> PathBuf {
+        self.path.with_extension(extension)
+    }
+
+    /// Path of the project's library table of `kind`.
+    pub fn lib_table_path(&self, kind: LibTableKind) -> PathBuf {
+        self.dir().join(kind.file_name())
+    }
+
+    /// The process environment with `KIPRJMOD` set to the project directory,
+    /// for resolving library paths.
+    pub fn env(&self) -> EnvVars {
+        EnvVars::from_process().with_project_dir(self.dir())
+    }
+
+    /// Path of the custom drawing sheet, resolved against the project
+    /// directory; `None` for KiCad's built-in one.
+    pub fn drawing_sheet_path(&self) -> Option<PathBuf> {
+        let file = self.settings.schematic.page_layout_descr_file.as_str();
+        if file.is_empty() {
+            return None;
+        }
+        let file = file.strip_prefix("${KIPRJMOD}").map(|rest| rest.trim_start_matches(['/', '\\'])).unwrap_or(file);
+        Some(self.dir().join(file))
+    }
+
+    /// The fields the project adds to every symbol.
+    pub fn field_templates(&self) -> &[FieldTemplate] {
+        &self.settings.schematic.drawing.field_names
+    }
+
+    /// Applies [`Project::field_templates`] to the root sheet's symbols, see
+    /// [`Schematic::apply_field_templates`].
+    #[cfg(feature = "sch")]
+    pub fn apply_field_templates(&mut self) -> Vec<Issue> {
+        let templates = &self.settings.schematic.drawing.field_names;
+        self.schematic.as_mut().map_or_else(Vec::new, |schematic| schematic.apply_field_templates(templates))
+    }
+
+    /// The effective net class of `net`, see [`NetSettings::class_for`].
+    pub fn net_class(&self, net: &str) -> NetClass {
+        self.settings.net_settings.class_for(net)
+    }
+}
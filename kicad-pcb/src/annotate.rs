@@ -0,0 +1,193 @@
+//! Geographic reannotation, as in KiCad's board editor: footprints are
+//! renumbered by where they sit on the board, the front first and then the
+//! back, each reference prefix counting on its own.
+//!
+//! The changes carry the sheet paths of the footprints, which link them to
+//! their schematic symbols, so the schematic can be back-annotated with
+//! `kicad_sch::Hierarchy::back_annotate`:
+//!
+//! ```no_run
+//! use kicad_pcb::{
+//!     annotate::{reannotate, ReannotateOptions},
+//!     selection::{write_properties, Selection},
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let changes = reannotate(&mut board, &ReannotateOptions::default());
+//! let renamed: Selection = changes.iter().filter_map(|change| change.uuid).collect();
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_properties(&board, &renamed, &mut doc)?;
+//! std::fs::write("demo.kicad_pcb", doc.to_string())?;
+//! // The schematic side: `hierarchy.back_annotate(&links)`.
+//! let links: Vec<(String, String)> =
+//!     changes.iter().filter_map(|change| Some((change.path.clone()?, change.new.clone()))).collect();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeMap;
+
+use kicad_common::Uuid;
+
+use crate::{Board, Footprint, Side, TextKind};
+
+/// The order in which [`reannotate`] numbers the footprints of a side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// Row by row from the top, each row from the left.
+    #[default]
+    Rows,
+    /// Column by column from the left, each column from the top.
+    Columns,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReannotateOptions {
+    pub order: Order,
+    /// Positions are rounded to this grid before sorting, so parts a little
+    /// off a row or column still count as on it.
+    pub grid: f64,
+    /// The first number of each prefix on the front.
+    pub start: u32,
+    /// The first number of each prefix on the back; `None` continues from
+    /// the front.
+    pub back_start: Option<u32>,
+}
+
+impl Default for ReannotateOptions {
+    fn default() -> Self {
+        ReannotateOptions { order: Order::Rows, grid: 1.27, start: 1, back_start: None }
+    }
+}
+
+/// A footprint whose reference [`reannotate`] changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reannotation {
+    pub uuid: Option<Uuid>,
+    /// Sheet path of the linked schematic symbol; `None` for footprints
+    /// that are only on the board.
+    pub path: Option<String>,
+    pub old: String,
+    pub new: String,
+}
+
+/// The prefix of a reference that [`reannotate`] renumbers: `R` of `R12`
+/// or of the unannotated `R?`. Power symbols' `#` references and ones
+/// without a number, like `REF**`, are left alone.
+fn prefix(reference: &str) -> Option<&str> {
+    let prefix = reference.trim_end_matches(|c: char| c.is_ascii_digit() || c == '?');
+    let number = &reference[prefix.len()..];
+    let numbered = !number.is_empty() && (number == "?" || number.bytes().all(|b| b.is_ascii_digit()));
+    (numbered && !prefix.is_empty() && !prefix.starts_with('#')).then_some(prefix)
+}
+
+fn set_reference(footprint: &mut Footprint, reference: &str) {
+    match footprint.properties.iter_mut().find(|field| field.key == "Reference") {
+        Some(field) => field.value = reference.to_string(),
+        None => {
+            let text = footprint.texts.iter_mut().find(|text| text.kind == Some(TextKind::Reference));
+            if let Some(text) = text {
+                text.text = reference.to_string();
+            }
+        },
+    }
+}
+
+/// Renumbers the references of the footprints of `board` by position:
+/// those on the front in `options.order`, then those on the back in the
+/// same order as seen from the back, which mirrors left and right. Returns
+/// the footprints whose reference changed; write them back with
+/// [`write_properties`](crate::selection::write_properties).
+pub fn reannotate(board: &mut Board, options: &ReannotateOptions) -> Vec<Reannotation> {
+    let grid = |v: f64| if options.grid > 0.0 { (v / options.grid).round() as i64 } else { (v * 1e6).round() as i64 };
+    let key = |footprint: &Footprint| {
+        let (x, y) = (grid(footprint.at.x), grid(footprint.at.y));
+        let x = if footprint.side() == Side::Bottom { -x } else { x };
+        match options.order {
+            Order::Rows => (y, x),
+            Order::Columns => (x, y),
+        }
+    };
+    let mut changes = Vec::new();
+    let mut next: BTreeMap<String, u32> = BTreeMap::new();
+    for side in [Side::Top, Side::Bottom] {
+        let start = match side {
+            Side::Top => options.start,
+            Side::Bottom => match options.back_start {
+                Some(start) => {
+                    next.clear();
+                    start
+                },
+                None => options.start,
+            },
+        };
+        let mut order: Vec<usize> = (0..board.footprints.len())
+            .filter(|&i| board.footprints[i].side() == side)
+            .filter(|&i| board.footprints[i].reference().and_then(prefix).is_some())
+            .collect();
+        order.sort_by_key(|&i| key(&board.footprints[i]));
+        for i in order {
+            let footprint = &mut board.footprints[i];
+            let old = footprint.reference().unwrap_or_default().to_string();
+            let prefix = prefix(&old).unwrap_or_default().to_string();
+            let number = next.entry(prefix.clone()).or_insert(start);
+            let new = format!("{}{}", prefix, number);
+            *number += 1;
+            if new != old {
+                set_reference(footprint, &new);
+                changes.push(Reannotation { uuid: footprint.uuid, path: footprint.path.clone(), old, new });
+            }
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::{write_properties, Selection};
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn references(board: &Board) -> Vec<&str> {
+        board.footprints.iter().map(|footprint| footprint.reference().unwrap_or_default()).collect()
+    }
+
+    #[test]
+    fn geographic() {
+        let mut board = Board::parse(DEMO).unwrap();
+        assert_eq!(references(&board), ["J1", "R1", "R2", "C1", "H1", "H2"]);
+        assert!(reannotate(&mut board, &ReannotateOptions::default()).is_empty());
+
+        // R1 moves below R2, and the back numbers from 101.
+        board.footprints[1].at.y = 112.0;
+        let options = ReannotateOptions { back_start: Some(101), ..Default::default() };
+        let changes = reannotate(&mut board, &options);
+        assert_eq!(references(&board), ["J1", "R2", "R1", "C101", "H1", "H2"]);
+        let names: Vec<_> = changes.iter().map(|change| (change.old.as_str(), change.new.as_str())).collect();
+        assert_eq!(names, [("R2", "R1"), ("R1", "R2"), ("C1", "C101")]);
+        let c1 = "/2a3c5ebe-b43c-4735-aace-eff978af37c6/ff294785-b81a-4633-8a53-0b1dfb5f0ff1";
+        assert_eq!(changes[2].path.as_deref(), Some(c1));
+
+        let mut doc = kicad_sexp::cst::parse(DEMO).unwrap();
+        let renamed: Selection = changes.iter().filter_map(|change| change.uuid).collect();
+        write_properties(&board, &renamed, &mut doc).unwrap();
+        let written = Board::parse(&doc.to_string()).unwrap();
+        assert_eq!(references(&written), references(&board));
+
+        // Columns: H1 at x 104 comes before R1 and R2 at x 110.
+        let mut board = Board::parse(DEMO).unwrap();
+        board.footprints[4].properties[0].value = "H?".into();
+        board.footprints[5].at.x = 100.0;
+        reannotate(&mut board, &ReannotateOptions { order: Order::Columns, ..Default::default() });
+        assert_eq!(references(&board), ["J1", "R1", "R2", "C1", "H2", "H1"]);
+    }
+
+    #[test]
+    fn prefixes() {
+        assert_eq!(prefix("R12"), Some("R"));
+        assert_eq!(prefix("LED?"), Some("LED"));
+        assert_eq!([prefix("#PWR01"), prefix("REF**"), prefix("12"), prefix("R1?"), prefix("")], [None; 5]);
+    }
+}
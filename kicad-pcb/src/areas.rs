@@ -0,0 +1,246 @@
+//! Named placement areas, or rooms: a region of the board and the
+//! footprints that belong in it, a sheet, a component class or a list of
+//! references.
+//!
+//! Areas come from KiCad 9 placement rule areas, [`PlacementArea::rule_areas`],
+//! or from any polygon, such as one drawn on a user layer. [`pack`] moves
+//! the footprints of an area into it in rows, and [`check_areas`] reports
+//! footprints that have left their area, for CI.
+//!
+//! ```no_run
+//! use kicad_pcb::{
+//!     areas::{check_areas, pack, PlacementArea},
+//!     selection::write_moves,
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let areas = PlacementArea::rule_areas(&board);
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! for area in &areas {
+//!     let packing = pack(&mut board, area, 0.5);
+//!     write_moves(&packing.moves, &mut doc)?;
+//!     for reference in &packing.unplaced {
+//!         eprintln!("{} does not fit in {}", reference, area.name);
+//!     }
+//! }
+//! for issue in check_areas(&board, &areas) {
+//!     eprintln!("{}", issue);
+//! }
+//! std::fs::write("demo.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{Point, Uuid};
+use kicad_dru::wildcard_match;
+use kicad_sexp::schema::Issue;
+
+use crate::{
+    bounds::{polygon_inside, Bounds},
+    index::Item,
+    selection::{bounds, move_each},
+    Board, Footprint, PlacementSource,
+};
+
+/// The footprints that belong in a placement area.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Members {
+    /// Those of a sheet or component class, as for a rule area.
+    Source(PlacementSource),
+    /// Footprints by reference, with `*` and `?` wildcards.
+    References(Vec<String>),
+}
+
+/// A named region of the board and the footprints that belong in it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlacementArea {
+    pub name: String,
+    pub outline: Vec<Point>,
+    pub members: Members,
+}
+
+impl PlacementArea {
+    /// The enabled placement rule areas of `board`, named by the zone's
+    /// name or else its sheet, class or group.
+    pub fn rule_areas(board: &Board) -> Vec<Self> {
+        let placements = board.zones.iter().filter_map(|zone| {
+            let placement = zone.placement.as_ref().filter(|placement| placement.enabled)?;
+            Some((zone, placement))
+        });
+        let areas = placements.map(|(zone, placement)| {
+            let name = zone.name.clone().unwrap_or_else(|| match &placement.source {
+                PlacementSource::Sheet(name) | PlacementSource::ComponentClass(name) | PlacementSource::Group(name) => {
+                    name.clone()
+                },
+            });
+            PlacementArea { name, outline: zone.outline.clone(), members: Members::Source(placement.source.clone()) }
+        });
+        areas.collect()
+    }
+
+    /// The footprints of `board` that belong in the area. Groups are not
+    /// part of the board model, so group areas have none.
+    pub fn footprints<'b>(&self, board: &'b Board) -> Vec<&'b Footprint> {
+        let member = |footprint: &&Footprint| match &self.members {
+            Members::Source(PlacementSource::Sheet(sheet)) => footprint.sheetname.as_ref() == Some(sheet),
+            Members::Source(PlacementSource::ComponentClass(class)) => footprint.has_component_class(class),
+            Members::Source(PlacementSource::Group(_)) => false,
+            Members::References(patterns) => {
+                let reference = footprint.reference().unwrap_or_default();
+                patterns.iter().any(|pattern| wildcard_match(pattern, reference))
+            },
+        };
+        board.footprints.iter().filter(member).collect()
+    }
+
+    /// Whether the bounds of `footprint` lie within the outline.
+    pub fn contains(&self, footprint: &Footprint) -> bool {
+        let bounds = bounds(Item::Footprint(footprint));
+        bounds.is_some_and(|bounds| polygon_inside(&bounds.corners(), &self.outline))
+    }
+}
+
+/// What [`pack`] did.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Packing {
+    /// The offset of each footprint moved, for
+    /// [`write_moves`](crate::selection::write_moves).
+    pub moves: Vec<(Uuid, Point)>,
+    /// References of the footprints left where they were: those that did
+    /// not fit, and those without a UUID.
+    pub unplaced: Vec<String>,
+}
+
+/// Moves the footprints of `area` into it, in rows from its top left
+/// corner, tallest first, `spacing` millimeters apart and from the
+/// outline. The rows fill the outline's bounds, skipping places outside
+/// the outline; other footprints on the board are not avoided.
+pub fn pack(board: &mut Board, area: &PlacementArea, spacing: f64) -> Packing {
+    let mut packing = Packing::default();
+    let Some(region) = Bounds::of(area.outline.iter().copied()) else {
+        return packing;
+    };
+    let mut members: Vec<(Option<Uuid>, String, Bounds)> = area
+        .footprints(board)
+        .into_iter()
+        .filter_map(|footprint| {
+            let bounds = bounds(Item::Footprint(footprint))?;
+            Some((footprint.uuid, footprint.reference().unwrap_or_default().to_string(), bounds))
+        })
+        .collect();
+    let height = |bounds: &Bounds| bounds.max.y - bounds.min.y;
+    members.sort_by(|(_, a, a_bounds), (_, b, b_bounds)| height(b_bounds).total_cmp(&height(a_bounds)).then(a.cmp(b)));
+
+    let step = spacing.max(0.1);
+    let (mut x, mut y, mut row) = (region.min.x + spacing, region.min.y + spacing, 0.0_f64);
+    let mut moves = Vec::new();
+    for (uuid, reference, bounds) in members {
+        let Some(uuid) = uuid else {
+            packing.unplaced.push(reference);
+            continue;
+        };
+        let (width, height) = (bounds.max.x - bounds.min.x, height(&bounds));
+        let placed = loop {
+            if x + width > region.max.x - spacing {
+                (x, y, row) = (region.min.x + spacing, y + (row + spacing).max(step), 0.0);
+            }
+            if y + height > region.max.y - spacing || width > region.max.x - region.min.x - 2.0 * spacing {
+                break None;
+            }
+            let min = Point::new(x, y);
+            let candidate = Bounds { min, max: Point::new(x + width, y + height) };
+            x += step;
+            if polygon_inside(&candidate.corners(), &area.outline) {
+                x = min.x + width + spacing;
+                row = row.max(height);
+                break Some(min);
+            }
+        };
+        match placed {
+            Some(min) => moves.push((uuid, Point::new(min.x - bounds.min.x, min.y - bounds.min.y))),
+            None => packing.unplaced.push(reference),
+        }
+    }
+    packing.moves = move_each(board, moves);
+    packing
+}
+
+/// An issue for each footprint of an area whose bounds are not within it.
+pub fn check_areas(board: &Board, areas: &[PlacementArea]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for area in areas {
+        for footprint in area.footprints(board).into_iter().filter(|footprint| !area.contains(footprint)) {
+            let message = format!(
+                "{} is outside its placement area {}",
+                footprint.reference().unwrap_or_default(),
+                area.name
+            );
+            issues.push(Issue { path: vec!["kicad_pcb".into(), "footprint".into()], message });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn rectangle(min: Point, max: Point) -> Vec<Point> {
+        vec![min, Point::new(max.x, min.y), max, Point::new(min.x, max.y)]
+    }
+
+    #[test]
+    fn pack_and_check() {
+        let mut board = Board::parse(DEMO).unwrap();
+        let area = PlacementArea {
+            name: "Input".to_string(),
+            outline: rectangle(Point::new(130.0, 100.0), Point::new(140.0, 110.0)),
+            members: Members::References(vec!["R?".to_string(), "C1".to_string()]),
+        };
+        let issues = check_areas(&board, std::slice::from_ref(&area));
+        let messages: Vec<String> = issues.into_iter().map(|issue| issue.message).collect();
+        assert_eq!(messages, [
+            "R1 is outside its placement area Input",
+            "R2 is outside its placement area Input",
+            "C1 is outside its placement area Input"
+        ]);
+
+        let packing = pack(&mut board, &area, 0.5);
+        assert_eq!(packing.moves.len(), 3);
+        assert!(packing.unplaced.is_empty());
+        assert!(check_areas(&board, std::slice::from_ref(&area)).is_empty());
+        let r1 = board.footprint("R1").unwrap();
+        let r2 = board.footprint("R2").unwrap();
+        let (r1, r2) = (bounds(Item::Footprint(r1)).unwrap(), bounds(Item::Footprint(r2)).unwrap());
+        assert!(r1.max.x + 0.5 <= r2.min.x + 1e-9 || r2.max.x + 0.5 <= r1.min.x + 1e-9);
+
+        let small = PlacementArea { outline: rectangle(Point::new(130.0, 100.0), Point::new(134.0, 104.0)), ..area };
+        // The first footprint is already in place, so it is not moved.
+        let packing = pack(&mut board, &small, 0.5);
+        assert!(packing.moves.is_empty());
+        assert_eq!(packing.unplaced.len(), 2);
+    }
+
+    #[test]
+    fn rule_areas() {
+        let zone = "(zone (net 0) (net_name \"\") (layers \"F.Cu\") (name \"Power\") (hatch edge 0.5) \
+                    (placement (enabled yes) (sheetname \"/Power/\")) \
+                    (polygon (pts (xy 100 100) (xy 120 100) (xy 120 120) (xy 100 120))))";
+        let src = DEMO.replacen("(gr_rect", &format!("{}\n\t(gr_rect", zone), 1);
+        let mut board = Board::parse(&src).unwrap();
+        board.footprints[1].sheetname = Some("/Power/".to_string());
+        let areas = PlacementArea::rule_areas(&board);
+        assert_eq!(areas.len(), 1);
+        assert_eq!(areas[0].name, "Power");
+        let members: Vec<_> = areas[0].footprints(&board).iter().map(|f| f.reference().unwrap()).collect();
+        assert_eq!(members, ["R1"]);
+        assert!(check_areas(&board, &areas).is_empty());
+        board.footprints[1].at.x = 125.0;
+        assert_eq!(check_areas(&board, &areas)[0].message, "R1 is outside its placement area Power");
+    }
+}
@@ -0,0 +1,726 @@
+//! Assembly drawings: one view per board side with the fab layer outline,
+//! reference and polarity mark of each part, and a table of the parts.
+//!
+//! Drawings are in board millimeters. The bottom side is mirrored left to
+//! right, as seen when the board is turned over. They are written as SVG or
+//! as a one-page PDF with the same layout, whose text uses the standard
+//! Helvetica fonts and is centered by an estimated width.
+//!
+//! ```no_run
+//! use kicad_pcb::{assembly::assembly_drawing, Board, Side};
+//!
+//! let board = Board::parse(&std::fs::read_to_string("demo.kicad_pcb")?)?;
+//! for (side, name) in [(Side::Top, "assembly-top"), (Side::Bottom, "assembly-bottom")] {
+//!     let drawing = assembly_drawing(&board, side);
+//!     std::fs::write(format!("{}.svg", name), drawing.to_svg())?;
+//!     std::fs::write(format!("{}.pdf", name), drawing.to_pdf())?;
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt::Write;
+
+use kicad_common::{reference::natural_cmp, Point};
+use kicad_sexp::number::format_float;
+
+use crate::{
+    bounds::{circle_center, distance, footprint_shape_points, map_points, shape_points, Bounds},
+    Board, Footprint, Layer, ShapeKind, Side, TextKind,
+};
+
+/// Footprint names of polarized two-pin parts, which get a pin 1 mark.
+const POLARIZED: [&str; 4] = ["CP_", "C_Elec", "D_", "LED_"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssemblyPart {
+    pub reference: String,
+    pub value: String,
+    pub lib_id: String,
+    pub dnp: bool,
+    /// Fab layer drawings, or the courtyard if there are none, in drawing
+    /// coordinates.
+    pub outline: Vec<ShapeKind>,
+    /// Center of the reference text.
+    pub label: Point,
+    /// Reference text angle in degrees, turned to read from the bottom or
+    /// the right.
+    pub label_angle: f64,
+    pub label_size: f64,
+    /// Center of pad 1 of parts with more than two pads or a polarized
+    /// footprint.
+    pub pin1: Option<Point>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssemblyDrawing {
+    pub side: Side,
+    /// `Edge.Cuts` drawings, in drawing coordinates.
+    pub edges: Vec<ShapeKind>,
+    /// Parts on `side`, sorted by reference.
+    pub parts: Vec<AssemblyPart>,
+}
+
+fn fab_layer(side: Side) -> Layer {
+    match side {
+        Side::Top => Layer::FFab,
+        Side::Bottom => Layer::BFab,
+    }
+}
+
+/// Reading angle in (-90, 90].
+fn readable(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(360.0);
+    if angle > 90.0 && angle <= 270.0 { angle - 180.0 } else if angle > 270.0 { angle - 360.0 } else { angle }
+}
+
+fn is_polarized(footprint: &Footprint) -> bool {
+    let name = footprint.lib_id.rsplit(':').next().unwrap_or_default();
+    POLARIZED.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Rects turned with a footprint are no longer axis aligned, so they are
+/// kept as polygons.
+fn to_board(footprint: &Footprint, kind: &ShapeKind) -> ShapeKind {
+    match kind {
+        ShapeKind::Rect { start, end } => {
+            let corners = Bounds::of([*start, *end]).map(|bounds| bounds.corners()).unwrap_or_default();
+            ShapeKind::Polygon(corners.iter().map(|p| footprint.to_board(*p)).collect())
+        },
+        kind => map_points(kind, |p| footprint.to_board(p)),
+    }
+}
+
+/// Collects the assembly drawing of `side` of `board`. Board-only
+/// footprints and those excluded from the BOM are left out; DNP parts are
+/// kept and marked.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(footprints = board.footprints.len())))]
+pub fn assembly_drawing(board: &Board, side: Side) -> AssemblyDrawing {
+    let mut edges = Vec::new();
+    for shape in board.shapes.iter().filter(|shape| shape.layer == Layer::EdgeCuts) {
+        edges.push(shape.kind.clone());
+    }
+    for footprint in &board.footprints {
+        for shape in footprint.shapes.iter().filter(|shape| shape.layer == Layer::EdgeCuts) {
+            edges.push(to_board(footprint, &shape.kind));
+        }
+    }
+
+    let mut parts = Vec::new();
+    let footprints = board.footprints.iter().filter(|footprint| footprint.side() == side);
+    for footprint in footprints.filter(|footprint| !footprint.attributes.board_only) {
+        if footprint.attributes.exclude_from_bom {
+            continue;
+        }
+        let fab = fab_layer(side);
+        let mut shapes: Vec<_> = footprint.shapes.iter().filter(|shape| shape.layer == fab).collect();
+        if shapes.is_empty() {
+            shapes = footprint.courtyard(side).collect();
+        }
+        let outline: Vec<ShapeKind> = shapes.iter().map(|shape| to_board(footprint, &shape.kind)).collect();
+        let points = shapes.iter().flat_map(|shape| footprint_shape_points(footprint, &shape.kind));
+        let pads = footprint.pads.iter().map(|pad| footprint.pad_position(pad));
+        let bounds = Bounds::of(points).or_else(|| Bounds::of(pads)).unwrap_or(Bounds {
+            min: footprint.at.point(),
+            max: footprint.at.point(),
+        });
+
+        let reference = footprint.reference().unwrap_or_default().to_string();
+        let fab_text = footprint.texts.iter().find(|text| {
+            let reference = matches!(text.text.as_str(), "${REFERENCE}" | "%R");
+            text.kind == Some(TextKind::User) && text.layer == fab && reference
+        });
+        let (label, angle, size) = match fab_text {
+            Some(text) => {
+                let size = if text.effects.font.height > 0.0 { text.effects.font.height } else { 1.0 };
+                (footprint.to_board(text.at.point()), text.at.angle, size)
+            },
+            None => {
+                let center = Point::new((bounds.min.x + bounds.max.x) / 2.0, (bounds.min.y + bounds.max.y) / 2.0);
+                let tall = bounds.max.y - bounds.min.y > bounds.max.x - bounds.min.x;
+                (center, if tall { 90.0 } else { 0.0 }, 1.0)
+            },
+        };
+        let across = match readable(angle).abs() > 45.0 {
+            true => bounds.max.y - bounds.min.y,
+            false => bounds.max.x - bounds.min.x,
+        };
+        let chars = reference.chars().count().max(1) as f64;
+        let label_size = if across > 0.0 { size.min(across / (0.8 * chars)) } else { size };
+
+        let mut numbers: Vec<&str> = footprint.pads.iter().map(|pad| pad.number.as_str()).collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+        numbers.retain(|number| !number.is_empty());
+        let marked = numbers.len() > 2 || is_polarized(footprint);
+        let pin1 = ["1", "A1"].into_iter().find_map(|number| footprint.pad(number));
+
+        parts.push(AssemblyPart {
+            reference,
+            value: footprint.value().unwrap_or_default().to_string(),
+            lib_id: footprint.lib_id.clone(),
+            dnp: footprint.attributes.dnp,
+            outline,
+            label,
+            label_angle: angle,
+            label_size,
+            pin1: pin1.filter(|_| marked).map(|pad| footprint.pad_position(pad)),
+        });
+    }
+    parts.sort_by(|a, b| natural_cmp(&a.reference, &b.reference));
+
+    if side == Side::Bottom {
+        let points = edges.iter().chain(parts.iter().flat_map(|part| &part.outline)).flat_map(shape_points);
+        let axis = Bounds::of(points).map_or(0.0, |bounds| bounds.min.x + bounds.max.x);
+        let mirror = |p: Point| Point::new(axis - p.x, p.y);
+        edges = edges.iter().map(|edge| map_points(edge, mirror)).collect();
+        for part in &mut parts {
+            part.outline = part.outline.iter().map(|shape| map_points(shape, mirror)).collect();
+            part.label = mirror(part.label);
+            part.label_angle = 180.0 - part.label_angle;
+            part.pin1 = part.pin1.map(mirror);
+        }
+    }
+    for part in &mut parts {
+        part.label_angle = readable(part.label_angle);
+    }
+
+    AssemblyDrawing { side, edges, parts }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn n(value: f64) -> String {
+    format_float(value)
+}
+
+fn text(svg: &mut String, class: &str, at: Point, angle: f64, size: f64, value: &str) {
+    let _ = write!(svg, "<text class=\"{}\" x=\"{}\" y=\"{}\" font-size=\"{}\"", class, n(at.x), n(at.y), n(size));
+    if angle != 0.0 {
+        let _ = write!(svg, " transform=\"rotate({} {} {})\"", n(-angle), n(at.x), n(at.y));
+    }
+    let _ = writeln!(svg, ">{}</text>", escape(value));
+}
+
+fn shape(svg: &mut String, class: &str, kind: &ShapeKind) {
+    let points = |points: &[Point]| {
+        let points: Vec<String> = points.iter().map(|p| format!("{},{}", n(p.x), n(p.y))).collect();
+        points.join(" ")
+    };
+    let _ = match kind {
+        ShapeKind::Line { start, end } => writeln!(
+            svg,
+            "<line class=\"{}\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
+            class,
+            n(start.x),
+            n(start.y),
+            n(end.x),
+            n(end.y)
+        ),
+        ShapeKind::Rect { start, end } => writeln!(
+            svg,
+            "<rect class=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+            class,
+            n(start.x.min(end.x)),
+            n(start.y.min(end.y)),
+            n((end.x - start.x).abs()),
+            n((end.y - start.y).abs())
+        ),
+        ShapeKind::Circle { center, end } => writeln!(
+            svg,
+            "<circle class=\"{}\" cx=\"{}\" cy=\"{}\" r=\"{}\"/>",
+            class,
+            n(center.x),
+            n(center.y),
+            n(distance(*center, *end))
+        ),
+        ShapeKind::Arc { start, mid, end } => match circle_center(*start, *mid, *end) {
+            Some(center) => {
+                let cross = |o: Point, p: Point, q: Point| (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x);
+                // The arc is the long way round if it bulges to the side of the chord its center is on.
+                let large = cross(*start, *end, *mid) * cross(*start, *end, center) > 0.0;
+                let sweep = cross(*start, *mid, *end) > 0.0;
+                let r = n(distance(center, *start));
+                writeln!(
+                    svg,
+                    "<path class=\"{}\" d=\"M{} {}A{} {} 0 {} {} {} {}\"/>",
+                    class,
+                    n(start.x),
+                    n(start.y),
+                    r,
+                    r,
+                    large as u8,
+                    sweep as u8,
+                    n(end.x),
+                    n(end.y)
+                )
+            },
+            None => writeln!(svg, "<polyline class=\"{}\" points=\"{}\"/>", class, points(&[*start, *end])),
+        },
+        ShapeKind::Polygon(polygon) => writeln!(svg, "<polygon class=\"{}\" points=\"{}\"/>", class, points(polygon)),
+        ShapeKind::Curve(curve) => match curve.as_slice() {
+            [p0, p1, p2, p3] => writeln!(
+                svg,
+                "<path class=\"{}\" d=\"M{} {}C{} {} {} {} {} {}\"/>",
+                class,
+                n(p0.x),
+                n(p0.y),
+                n(p1.x),
+                n(p1.y),
+                n(p2.x),
+                n(p2.y),
+                n(p3.x),
+                n(p3.y)
+            ),
+            curve => writeln!(svg, "<polyline class=\"{}\" points=\"{}\"/>", class, points(curve)),
+        },
+    };
+}
+
+/// A column of the part table.
+type Cell = fn(&AssemblyPart) -> &str;
+
+/// Space around the board and between the board and the table.
+const MARGIN: f64 = 5.0;
+const ROW: f64 = 2.5;
+const FONT: f64 = 1.6;
+/// Rough stroke font advance per character, relative to its height.
+const ADVANCE: f64 = 0.7;
+/// Radius of the pin 1 dot.
+const DOT: f64 = 0.3;
+
+const COLUMNS: [(&str, Cell); 4] = [
+    ("Reference", |part| &part.reference),
+    ("Value", |part| &part.value),
+    ("Footprint", |part| part.lib_id.rsplit(':').next().unwrap_or_default()),
+    ("", |part| if part.dnp { "DNP" } else { "" }),
+];
+
+/// Where the board and the part table go on the page.
+struct Frame {
+    /// The board drawings.
+    bounds: Bounds,
+    widths: Vec<f64>,
+    /// Top left corner of the table.
+    table: Point,
+    /// Corners of the page.
+    min: Point,
+    max: Point,
+}
+
+/// The output format of a drawing. Classes are those of the SVG style
+/// sheet.
+trait Canvas {
+    fn shape(&mut self, class: &str, kind: &ShapeKind);
+    fn text(&mut self, class: &str, at: Point, angle: f64, size: f64, value: &str);
+}
+
+impl Canvas for String {
+    fn shape(&mut self, class: &str, kind: &ShapeKind) {
+        shape(self, class, kind);
+    }
+
+    fn text(&mut self, class: &str, at: Point, angle: f64, size: f64, value: &str) {
+        text(self, class, at, angle, size, value);
+    }
+}
+
+/// Millimeters to PDF points.
+const PT: f64 = 72.0 / 25.4;
+
+/// The content stream of a PDF page, drawn in drawing millimeters.
+struct Pdf {
+    content: Vec<u8>,
+    /// Top left corner of the page.
+    origin: Point,
+}
+
+impl Pdf {
+    fn op(&mut self, op: &str) {
+        self.content.extend_from_slice(op.as_bytes());
+        self.content.push(b'\n');
+    }
+
+    /// `p` in page points, with y pointing up.
+    fn xy(&self, p: Point) -> String {
+        format!("{} {}", n((p.x - self.origin.x) * PT), n((self.origin.y - p.y) * PT))
+    }
+
+    /// Starts a path at `start` and adds the Bézier pieces of an arc.
+    fn arc(&mut self, center: Point, start: Point, sweep: f64) {
+        let mut path = format!("{} m", self.xy(start));
+        for [a, b, c] in arc_beziers(center, start, sweep) {
+            path += &format!(" {} {} {} c", self.xy(a), self.xy(b), self.xy(c));
+        }
+        self.op(&path);
+    }
+
+    fn polyline(&mut self, points: &[Point], closed: bool) {
+        let mut path = String::new();
+        for (i, p) in points.iter().enumerate() {
+            path += &format!("{}{} {}", if i == 0 { "" } else { " " }, self.xy(*p), if i == 0 { "m" } else { "l" });
+        }
+        if closed {
+            path += " h";
+        }
+        self.op(&path);
+    }
+}
+
+/// Cubic Bézier pieces of at most a quarter turn, as control points and end
+/// point, of the arc around `center` from `start`, turning by `sweep`
+/// radians.
+fn arc_beziers(center: Point, start: Point, sweep: f64) -> Vec<[Point; 3]> {
+    let r = distance(center, start);
+    let first = (start.y - center.y).atan2(start.x - center.x);
+    let pieces = (sweep.abs() / std::f64::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+    let step = sweep / pieces as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan() * r;
+    let at = |angle: f64| Point::new(center.x + r * angle.cos(), center.y + r * angle.sin());
+    (0..pieces)
+        .map(|i| {
+            let (a, b) = (first + step * i as f64, first + step * (i + 1) as f64);
+            let (p, q) = (at(a), at(b));
+            let c1 = Point::new(p.x - k * a.sin(), p.y + k * a.cos());
+            let c2 = Point::new(q.x + k * b.sin(), q.y - k * b.cos());
+            [c1, c2, q]
+        })
+        .collect()
+}
+
+/// `value` as a PDF string in WinAnsi encoding; characters outside Latin-1
+/// become `?`.
+fn pdf_string(value: &str) -> Vec<u8> {
+    let mut s = vec![b'('];
+    for c in value.chars() {
+        match u8::try_from(c as u32).unwrap_or(b'?') {
+            byte @ (b'(' | b')' | b'\\') => s.extend([b'\\', byte]),
+            byte => s.push(byte),
+        }
+    }
+    s.push(b')');
+    s
+}
+
+impl Canvas for Pdf {
+    fn shape(&mut self, class: &str, kind: &ShapeKind) {
+        let (gray, width, dash) = match class {
+            "edge" => (0.0, 0.15, None),
+            "fab" => (0.25, 0.1, None),
+            "dnp" => (0.627, 0.1, Some((0.4, 0.3))),
+            _ => (0.0, 0.1, None),
+        };
+        match class {
+            "pin1" => self.op("0.75 0 0 rg"),
+            _ => {
+                let dash = dash.map(|(on, off): (f64, f64)| format!("{} {}", n(on * PT), n(off * PT)));
+                let dash = dash.unwrap_or_default();
+                self.op(&format!("{} G {} w [{}] 0 d", n(gray), n(width * PT), dash));
+            },
+        }
+        match kind {
+            ShapeKind::Line { start, end } => self.polyline(&[*start, *end], false),
+            ShapeKind::Rect { start, end } => {
+                let corners = Bounds::of([*start, *end]).map(|bounds| bounds.corners()).unwrap_or_default();
+                self.polyline(&corners, true);
+            },
+            ShapeKind::Circle { center, end } => {
+                let start = Point::new(center.x + distance(*center, *end), center.y);
+                self.arc(*center, start, std::f64::consts::TAU);
+                self.op("h");
+            },
+            ShapeKind::Arc { start, mid, end } => match circle_center(*start, *mid, *end) {
+                Some(center) => {
+                    let angle = |p: Point| (p.y - center.y).atan2(p.x - center.x);
+                    let turn = |p: Point| (angle(p) - angle(*start)).rem_euclid(std::f64::consts::TAU);
+                    let sweep = turn(*end);
+                    let sweep = if turn(*mid) > sweep { sweep - std::f64::consts::TAU } else { sweep };
+                    self.arc(center, *start, sweep);
+                },
+                None => self.polyline(&[*start, *end], false),
+            },
+            ShapeKind::Polygon(polygon) => self.polyline(polygon, true),
+            ShapeKind::Curve(curve) => match curve.as_slice() {
+                [p0, p1, p2, p3] => {
+                    let path = format!("{} m {} {} {} c", self.xy(*p0), self.xy(*p1), self.xy(*p2), self.xy(*p3));
+                    self.op(&path);
+                },
+                curve => self.polyline(curve, false),
+            },
+        }
+        self.op(if class == "pin1" { "f" } else { "S" });
+    }
+
+    fn text(&mut self, class: &str, at: Point, angle: f64, size: f64, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        let font = if class == "head" { "F2" } else { "F1" };
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let matrix = format!("{} {} {} {} {}", n(cos), n(sin), n(-sin), n(cos), self.xy(at));
+        let mut op = format!("0 g BT /{} {} Tf {} Tm", font, n(size * PT), matrix);
+        if class == "ref" {
+            // Centered on `at`, as the SVG's middle anchor and central baseline.
+            let width = value.chars().count() as f64 * size * ADVANCE;
+            op += &format!(" {} {} Td", n(-width / 2.0 * PT), n(-0.35 * size * PT));
+        }
+        self.content.extend_from_slice(op.as_bytes());
+        self.content.push(b' ');
+        self.content.extend(pdf_string(value));
+        self.op(" Tj ET");
+    }
+}
+
+impl AssemblyDrawing {
+    fn frame(&self) -> Frame {
+        let points = self.edges.iter().chain(self.parts.iter().flat_map(|part| &part.outline)).flat_map(shape_points);
+        let bounds = Bounds::of(points).unwrap_or(Bounds { min: Point::default(), max: Point::default() });
+        let widths: Vec<f64> = COLUMNS
+            .iter()
+            .map(|(title, cell)| {
+                let chars = self.parts.iter().map(|part| cell(part).chars().count()).chain([title.len()]).max();
+                chars.unwrap_or_default() as f64 * FONT * ADVANCE + FONT
+            })
+            .collect();
+        let table = Point::new(bounds.max.x + MARGIN, bounds.min.y);
+        let table_end = table.y + ROW * (self.parts.len() + 1) as f64;
+        let min = Point::new(bounds.min.x - MARGIN, bounds.min.y - MARGIN - ROW);
+        let max = Point::new(table.x + widths.iter().sum::<f64>() + MARGIN, (bounds.max.y).max(table_end) + MARGIN);
+        Frame { bounds, widths, table, min, max }
+    }
+
+    /// Draws the title, board, parts and table.
+    fn draw(&self, canvas: &mut impl Canvas, frame: &Frame) {
+        let Frame { bounds, widths, table, .. } = frame;
+        let title = match self.side {
+            Side::Top => "Top side",
+            Side::Bottom => "Bottom side (mirrored)",
+        };
+        canvas.text("head", Point::new(bounds.min.x, bounds.min.y - MARGIN / 2.0), 0.0, 2.0, title);
+
+        for edge in &self.edges {
+            canvas.shape("edge", edge);
+        }
+        for part in &self.parts {
+            let class = if part.dnp { "dnp" } else { "fab" };
+            for kind in &part.outline {
+                canvas.shape(class, kind);
+            }
+            if let (true, Some(bounds)) = (part.dnp, Bounds::of(part.outline.iter().flat_map(shape_points))) {
+                let [a, b, c, d] = bounds.corners();
+                canvas.shape(class, &ShapeKind::Line { start: a, end: c });
+                canvas.shape(class, &ShapeKind::Line { start: b, end: d });
+            }
+            if let Some(pin1) = part.pin1 {
+                canvas.shape("pin1", &ShapeKind::Circle { center: pin1, end: Point::new(pin1.x + DOT, pin1.y) });
+            }
+            canvas.text("ref", part.label, part.label_angle, part.label_size, &part.reference);
+        }
+
+        let rows = std::iter::once(None).chain(self.parts.iter().map(Some));
+        for (i, part) in rows.enumerate() {
+            let y = table.y + ROW * (i + 1) as f64 - (ROW - FONT) / 2.0;
+            let mut x = table.x;
+            for ((title, cell), width) in COLUMNS.iter().zip(widths) {
+                match part {
+                    Some(part) => canvas.text("", Point::new(x, y), 0.0, FONT, cell(part)),
+                    None => canvas.text("head", Point::new(x, y), 0.0, FONT, title),
+                }
+                x += width;
+            }
+        }
+        let rule = table.y + ROW;
+        let end = Point::new(table.x + widths.iter().sum::<f64>(), rule);
+        canvas.shape("rule", &ShapeKind::Line { start: Point::new(table.x, rule), end });
+    }
+
+    /// Renders the drawing as SVG, with the part table to the right of the
+    /// board. DNP parts are dashed and crossed out.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(parts = self.parts.len())))]
+    pub fn to_svg(&self) -> String {
+        let frame = self.frame();
+        let Frame { min, max, .. } = frame;
+        let (width, height) = (max.x - min.x, max.y - min.y);
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"{x} {y} {w} {h}\">",
+            x = n(min.x),
+            y = n(min.y),
+            w = n(width),
+            h = n(height)
+        );
+        svg.push_str(
+            "<style>\
+             .edge{stroke:#000;stroke-width:0.15;fill:none}\
+             .fab{stroke:#404040;stroke-width:0.1;fill:none}\
+             .dnp{stroke:#a0a0a0;stroke-width:0.1;fill:none;stroke-dasharray:0.4 0.3}\
+             .pin1{fill:#c00000}\
+             .rule{stroke:#000;stroke-width:0.1}\
+             text{font-family:sans-serif;fill:#000}\
+             .ref{text-anchor:middle;dominant-baseline:central}\
+             .head{font-weight:bold}\
+             </style>\n",
+        );
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#fff\"/>",
+            n(min.x),
+            n(min.y),
+            n(width),
+            n(height)
+        );
+        self.draw(&mut svg, &frame);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Renders the drawing as a one-page PDF laid out as [`to_svg`](Self::to_svg)
+    /// does, with text in the standard Helvetica fonts.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(parts = self.parts.len())))]
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let frame = self.frame();
+        let mut page = Pdf { content: Vec::new(), origin: Point::new(frame.min.x, frame.max.y) };
+        page.op("1 J 1 j");
+        self.draw(&mut page, &frame);
+        let size = Point::new((frame.max.x - frame.min.x) * PT, (frame.max.y - frame.min.y) * PT);
+
+        let font = |name: &str| {
+            format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", name)
+        };
+        let objects: [Vec<u8>; 6] = [
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 4 0 R /F2 5 0 R >> >> \
+                 /Contents 6 0 R >>",
+                n(size.x),
+                n(size.y)
+            )
+            .into_bytes(),
+            font("Helvetica").into_bytes(),
+            font("Helvetica-Bold").into_bytes(),
+            [format!("<< /Length {} >>\nstream\n", page.content.len()).as_bytes(), &page.content, b"\nendstream"]
+                .concat(),
+        ];
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend(object);
+            pdf.extend(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            pdf.extend(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        let trailer = format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref);
+        pdf.extend(trailer.as_bytes());
+        pdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn top() {
+        let board = Board::parse(DEMO).unwrap();
+        let drawing = assembly_drawing(&board, Side::Top);
+        let references: Vec<_> = drawing.parts.iter().map(|part| part.reference.as_str()).collect();
+        assert_eq!(references, ["J1", "R1", "R2"]);
+        assert_eq!(drawing.edges, [ShapeKind::Rect { start: Point::new(100.0, 100.0), end: Point::new(130.0, 120.0) }]);
+
+        let j1 = &drawing.parts[0];
+        assert_eq!(j1.pin1, Some(Point::new(104.0, 105.0)));
+        assert_eq!((j1.label, j1.label_angle, j1.label_size), (Point::new(104.0, 107.54), 90.0, 1.0));
+        let r1 = &drawing.parts[1];
+        assert_eq!((r1.pin1, r1.label, r1.label_angle), (None, Point::new(110.0, 105.0), 0.0));
+        assert!(r1.label_size < 1.0);
+
+        let svg = drawing.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("<circle class=\"pin1\" cx=\"104\" cy=\"105\" r=\"0.3\"/>"));
+        assert!(svg.contains(
+            "<text class=\"ref\" x=\"104\" y=\"107.54\" font-size=\"1\" transform=\"rotate(-90 104 107.54)\">J1</text>"
+        ));
+        assert!(svg.contains("<rect class=\"edge\" x=\"100\" y=\"100\" width=\"30\" height=\"20\"/>"));
+        assert!(svg.contains(">PinHeader_1x03_P2.54mm_Vertical</text>"));
+        assert!(!svg.contains(">H1<") && !svg.contains(">C1<"));
+    }
+
+    #[test]
+    fn bottom() {
+        let board = Board::parse(DEMO).unwrap();
+        let drawing = assembly_drawing(&board, Side::Bottom);
+        assert_eq!(drawing.parts.len(), 1);
+        let c1 = &drawing.parts[0];
+        assert_eq!(c1.reference, "C1");
+        // Mirrored about the middle of the 100..130 outline, and turned to read upright.
+        assert_eq!((c1.label, c1.label_angle), (Point::new(110.0, 107.5), 0.0));
+        assert_eq!(drawing.edges, [ShapeKind::Rect { start: Point::new(130.0, 100.0), end: Point::new(100.0, 120.0) }]);
+        let svg = drawing.to_svg();
+        assert!(svg.contains(">Bottom side (mirrored)</text>"));
+        assert!(svg.contains("<rect class=\"edge\" x=\"100\" y=\"100\" width=\"30\" height=\"20\"/>"));
+    }
+
+    #[test]
+    fn pdf() {
+        let board = Board::parse(DEMO).unwrap();
+        let pdf = assembly_drawing(&board, Side::Top).to_pdf();
+        let text = String::from_utf8(pdf.clone()).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n1 0 obj\n") && text.ends_with("%%EOF\n"));
+        // The cross-reference table points at each object.
+        let xref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert!(text[xref..].starts_with("xref\n0 7\n"));
+        for (i, line) in text[xref..].lines().skip(3).take(6).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(text[offset..].starts_with(&format!("{} 0 obj\n", i + 1)), "{}", line);
+        }
+        let stream = text.split_once("stream\n").unwrap().1;
+        let length: usize = text.split("/Length ").nth(1).unwrap().split(' ').next().unwrap().parse().unwrap();
+        assert!(stream[length..].starts_with("\nendstream"));
+        assert!(stream.contains("(J1) Tj ET") && stream.contains("(PinHeader_1x03_P2.54mm_Vertical) Tj ET"));
+        assert!(stream.contains("0.75 0 0 rg\n"));
+        assert!(stream.contains("0 g BT /F2 5.669291 Tf 1 0 0 1 14.173228 "), "{}", stream);
+        assert!(!stream.contains("() Tj"));
+
+        assert_eq!(pdf_string("R(1)\\µ→"), b"(R\\(1\\)\\\\\xb5?)");
+        let quarter = arc_beziers(Point::new(0.0, 0.0), Point::new(1.0, 0.0), std::f64::consts::PI);
+        assert_eq!(quarter.len(), 2);
+        assert!(distance(quarter[0][2], Point::new(0.0, 1.0)) < 1e-9);
+        assert!(distance(quarter[1][2], Point::new(-1.0, 0.0)) < 1e-9);
+    }
+
+    #[test]
+    fn dnp_and_polarity() {
+        let board = Board::parse(
+            "(kicad_pcb (version 20241229) (generator \"pcbnew\")
+                (footprint \"LED_SMD:LED_0603_1608Metric\" (layer \"F.Cu\") (at 10 10 90)
+                    (property \"Reference\" \"D1\" (at 0 0) (layer \"F.SilkS\"))
+                    (attr smd dnp)
+                    (fp_arc (start -1 0) (mid 0 -1) (end 1 0) (layer \"F.Fab\") (stroke (width 0.1) (type solid)))
+                    (pad \"1\" smd rect (at -0.8 0 90) (size 0.8 0.8) (layers \"F.Cu\"))
+                    (pad \"2\" smd rect (at 0.8 0 90) (size 0.8 0.8) (layers \"F.Cu\"))))",
+        )
+        .unwrap();
+        let drawing = assembly_drawing(&board, Side::Top);
+        let d1 = &drawing.parts[0];
+        assert!(d1.dnp);
+        assert_eq!(d1.pin1.map(|p| ((p.x * 1e6).round() / 1e6, (p.y * 1e6).round() / 1e6)), Some((10.0, 10.8)));
+        // No fab reference text: centered in the outline, running along its longer side.
+        assert_eq!(d1.label_angle, 90.0);
+
+        let svg = drawing.to_svg();
+        assert_eq!(svg.matches("<line class=\"dnp\"").count(), 2);
+        assert!(svg.contains("<path class=\"dnp\" d=\"M"));
+        assert!(svg.contains(">DNP</text>"));
+    }
+}
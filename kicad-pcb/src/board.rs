@@ -0,0 +1,358 @@
+use kicad_common::{
+    convert::{check_version, parse_document, FromSexp, List, Result},
+    embedded::{self, Codec, EmbeddedFile},
+    extension::{parse_extension, ExtensionNode},
+    Error, Paper, Point, TitleBlock, Uuid, UuidGenerator,
+};
+use kicad_sexp::{number::format_float, schema::Issue};
+
+use crate::{
+    layer::{layer_table, LayerDef},
+    zone::PlacementSource,
+    Footprint, Layer, Shape, Text, Track, Via, ViaKind, ViaProtection, Zone,
+};
+
+/// A net declaration, as in `(net 1 "VIN")`. Net 0 is the unconnected net.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Net {
+    pub number: u32,
+    pub name: String,
+}
+
+impl FromSexp for Net {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Net { number: list.number(0)?, name: list.string(1)? })
+    }
+}
+
+/// One layer of the physical board stackup.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StackupLayer {
+    /// Board layer name, or e.g. `dielectric 1` for dielectrics.
+    pub name: String,
+    /// Free-form description such as `copper`, `core` or `Top Solder Mask`.
+    pub kind: String,
+    pub thickness: Option<f64>,
+    pub material: Option<String>,
+    pub epsilon_r: Option<f64>,
+}
+
+impl StackupLayer {
+    /// Whether the layer is a coverlay, the film a flex circuit has in
+    /// place of solder mask: a mask layer of polyimide, or any layer whose
+    /// material is a coverlay. KiCad has no layer type of its own for them.
+    pub fn is_coverlay(&self) -> bool {
+        let mask = self.name.ends_with(".Mask") || self.kind.ends_with("Solder Mask");
+        self.material_is(&["coverlay"]) || (mask && self.material_is(&["polyimide", "kapton"]))
+    }
+
+    /// Whether the layer is a flexible dielectric, of polyimide.
+    pub fn is_flexible(&self) -> bool {
+        self.name.starts_with("dielectric") && self.material_is(&["polyimide", "kapton", "flex"])
+    }
+
+    fn material_is(&self, names: &[&str]) -> bool {
+        let material = self.material.as_deref().unwrap_or_default().to_lowercase();
+        names.iter().any(|name| material.contains(name))
+    }
+}
+
+impl FromSexp for StackupLayer {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(StackupLayer {
+            name: list.string(0)?,
+            kind: list.child_string("type")?.unwrap_or_default(),
+            thickness: list.child_number("thickness")?,
+            material: list.child_string("material")?,
+            epsilon_r: list.child_number("epsilon_r")?,
+        })
+    }
+}
+
+/// The subset of the board `(setup ...)` section used by tooling.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Setup {
+    pub stackup: Vec<StackupLayer>,
+    pub copper_finish: Option<String>,
+    pub pad_to_mask_clearance: f64,
+    /// Drill/place file origin.
+    pub aux_axis_origin: Option<Point>,
+    pub grid_origin: Option<Point>,
+    /// The defaults for vias without settings of their own.
+    pub via_protection: ViaProtection,
+}
+
+impl Setup {
+    /// Whether the stackup has flexible dielectrics or coverlays, as flex
+    /// and rigid-flex boards do.
+    pub fn is_flex(&self) -> bool {
+        self.stackup.iter().any(|layer| layer.is_flexible() || layer.is_coverlay())
+    }
+}
+
+impl FromSexp for Setup {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let (stackup, copper_finish) = match list.child("stackup") {
+            Some(stackup) => (
+                stackup.parse_all("layer").map_err(|e| e.within(list.tag()))?,
+                stackup.child_string("copper_finish").map_err(|e| e.within(list.tag()))?,
+            ),
+            None => (Vec::new(), None),
+        };
+        Ok(Setup {
+            stackup,
+            copper_finish,
+            pad_to_mask_clearance: list.child_number("pad_to_mask_clearance")?.unwrap_or_default(),
+            aux_axis_origin: list.parse("aux_axis_origin")?,
+            grid_origin: list.parse("grid_origin")?,
+            via_protection: ViaProtection::from_parent(list)?,
+        })
+    }
+}
+
+/// A `.kicad_pcb` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Board {
+    /// File format version, a date like `20241229`.
+    pub version: u32,
+    pub generator: String,
+    pub generator_version: Option<String>,
+    /// Board thickness in millimeters.
+    pub thickness: f64,
+    pub paper: Paper,
+    pub title_block: Option<TitleBlock>,
+    pub layers: Vec<LayerDef>,
+    pub setup: Setup,
+    pub nets: Vec<Net>,
+    pub footprints: Vec<Footprint>,
+    pub shapes: Vec<Shape>,
+    pub texts: Vec<Text>,
+    /// Segments and arcs.
+    pub tracks: Vec<Track>,
+    pub vias: Vec<Via>,
+    pub zones: Vec<Zone>,
+    pub embedded_files: Vec<EmbeddedFile>,
+    /// Lists handled by an [`Extension`](kicad_common::extension::Extension)
+    /// registered for the parse.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub extensions: Vec<ExtensionNode>,
+}
+
+impl Board {
+    /// Newest file format version the model is written against, KiCad 9.
+    pub const NEWEST_VERSION: u32 = 20241229;
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(footprints, tracks, zones)))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let board: Board = parse_document(src, "kicad_pcb")?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current()
+            .record("footprints", board.footprints.len())
+            .record("tracks", board.tracks.len())
+            .record("zones", board.zones.len());
+        Ok(board)
+    }
+
+    /// Fails with [`Error::Version`] for files from a newer KiCad.
+    pub fn check_version(&self) -> std::result::Result<(), Error> {
+        check_version("kicad_pcb", self.version, Self::NEWEST_VERSION)
+    }
+
+    pub fn layer(&self, layer: Layer) -> Option<&LayerDef> {
+        self.layers.iter().find(|def| def.layer == layer)
+    }
+
+    /// Enabled copper layers, front to back.
+    pub fn copper_layers(&self) -> Vec<Layer> {
+        let mut copper: Vec<_> = self.layers.iter().map(|def| def.layer).filter(Layer::is_copper).collect();
+        copper.sort();
+        copper
+    }
+
+    /// Checks each via's layer pair against the copper layers: through vias
+    /// must join the outer layers, blind and buried vias must not, and micro
+    /// vias must join neighbouring layers. Wrap the issues in
+    /// [`Error::Validation`] to fail on them.
+    pub fn check_vias(&self) -> Vec<Issue> {
+        let copper = self.copper_layers();
+        let outer = (copper.first().copied(), copper.last().copied());
+        let position = |layer| copper.iter().position(|&l| l == layer);
+        let mut issues = Vec::new();
+        for via in &self.vias {
+            let at = format!("({}, {})", format_float(via.at.x), format_float(via.at.y));
+            let message = match via.span() {
+                None => format!("via at {} does not list two layers", at),
+                Some((front, back)) if front == back => format!("via at {} starts and ends on {}", at, front),
+                Some((front, back)) => match (position(front), position(back)) {
+                    (None, _) => format!("via at {} is on {}, which is not a copper layer of the board", at, front),
+                    (_, None) => format!("via at {} is on {}, which is not a copper layer of the board", at, back),
+                    (Some(i), Some(j)) => {
+                        let through = (Some(front), Some(back)) == outer;
+                        match via.kind {
+                            ViaKind::Through if !through => {
+                                format!("through via at {} spans {} to {}, not the outer layers", at, front, back)
+                            },
+                            ViaKind::BlindBuried if through => {
+                                format!("blind via at {} spans {} to {}, the whole board", at, front, back)
+                            },
+                            ViaKind::Micro if j - i != 1 => {
+                                format!("micro via at {} joins {} and {}, which are not neighbours", at, front, back)
+                            },
+                            _ => continue,
+                        }
+                    },
+                },
+            };
+            issues.push(Issue { path: vec!["kicad_pcb".into(), "via".into()], message });
+        }
+        issues
+    }
+
+    /// Checks the files embedded in the board and its footprints against
+    /// their checksums, see [`embedded::verify`].
+    pub fn check_embedded_files(&self, codec: &dyn Codec) -> Vec<Issue> {
+        let mut issues = embedded::verify(&self.embedded_files, codec, &["kicad_pcb"]);
+        for footprint in &self.footprints {
+            issues.extend(embedded::verify(&footprint.embedded_files, codec, &["kicad_pcb", "footprint"]));
+        }
+        issues
+    }
+
+    pub fn net(&self, number: u32) -> Option<&Net> {
+        self.nets.iter().find(|net| net.number == number)
+    }
+
+    pub fn net_by_name(&self, name: &str) -> Option<&Net> {
+        self.nets.iter().find(|net| net.name == name)
+    }
+
+    /// Finds a footprint by its reference designator.
+    pub fn footprint(&self, reference: &str) -> Option<&Footprint> {
+        self.footprints.iter().find(|footprint| footprint.reference() == Some(reference))
+    }
+
+    /// The footprints a KiCad 9 placement rule area keeps together: those
+    /// of its sheet, or those with its component class. Empty for other
+    /// zones and for disabled placements.
+    pub fn rule_area_footprints(&self, zone: &Zone) -> Vec<&Footprint> {
+        let Some(placement) = zone.placement.as_ref().filter(|placement| placement.enabled) else {
+            return Vec::new();
+        };
+        let member = |footprint: &&Footprint| match &placement.source {
+            PlacementSource::Sheet(sheet) => footprint.sheetname.as_ref() == Some(sheet),
+            PlacementSource::ComponentClass(class) => footprint.has_component_class(class),
+            PlacementSource::Group(_) => false,
+        };
+        self.footprints.iter().filter(member).collect()
+    }
+
+    /// The placement rule areas `footprint` is a member of.
+    pub fn rule_areas(&self, footprint: &Footprint) -> Vec<&Zone> {
+        let member = |zone: &&Zone| {
+            self.rule_area_footprints(zone).iter().any(|member| std::ptr::eq(*member, footprint))
+        };
+        self.zones.iter().filter(member).collect()
+    }
+
+    /// The UUIDs of every item that has one.
+    pub fn uuids(&self) -> Vec<Uuid> {
+        let mut uuids = Vec::new();
+        for footprint in &self.footprints {
+            uuids.extend(footprint.uuid);
+            uuids.extend(footprint.properties.iter().filter_map(|field| field.uuid));
+            uuids.extend(footprint.pads.iter().filter_map(|pad| pad.uuid));
+            uuids.extend(footprint.shapes.iter().filter_map(|shape| shape.uuid));
+            uuids.extend(footprint.texts.iter().filter_map(|text| text.uuid));
+        }
+        uuids.extend(self.shapes.iter().filter_map(|shape| shape.uuid));
+        uuids.extend(self.texts.iter().filter_map(|text| text.uuid));
+        uuids.extend(self.tracks.iter().map(|track| track.uuid));
+        uuids.extend(self.vias.iter().map(|via| via.uuid));
+        uuids.extend(self.zones.iter().filter_map(|zone| zone.uuid));
+        uuids
+    }
+
+    /// Gives every item without a UUID a new one from `generator`, after
+    /// reserving the ones already used. Items are described by their kind,
+    /// placement and parent, so with a deterministic generator the same
+    /// board gets the same UUIDs on every run.
+    pub fn assign_missing_uuids(&mut self, generator: &mut UuidGenerator) {
+        generator.reserve(self.uuids());
+        for footprint in &mut self.footprints {
+            let description = format!("footprint {} {:?}", footprint.lib_id, footprint.at);
+            let parent = *footprint.uuid.get_or_insert_with(|| generator.generate(description));
+            for field in footprint.properties.iter_mut().filter(|field| field.uuid.is_none()) {
+                field.uuid = Some(generator.generate(format!("{} property {}", parent, field.key)));
+            }
+            for pad in footprint.pads.iter_mut().filter(|pad| pad.uuid.is_none()) {
+                pad.uuid = Some(generator.generate(format!("{} pad {} {:?}", parent, pad.number, pad.at)));
+            }
+            for shape in footprint.shapes.iter_mut().filter(|shape| shape.uuid.is_none()) {
+                shape.uuid = Some(generator.generate(format!("{} fp_shape {} {:?}", parent, shape.layer, shape.kind)));
+            }
+            for text in footprint.texts.iter_mut().filter(|text| text.uuid.is_none()) {
+                text.uuid = Some(generator.generate(format!("{} fp_text {:?} {:?}", parent, text.text, text.at)));
+            }
+        }
+        for shape in self.shapes.iter_mut().filter(|shape| shape.uuid.is_none()) {
+            shape.uuid = Some(generator.generate(format!("shape {} {:?}", shape.layer, shape.kind)));
+        }
+        for text in self.texts.iter_mut().filter(|text| text.uuid.is_none()) {
+            text.uuid = Some(generator.generate(format!("text {:?} {:?}", text.text, text.at)));
+        }
+        for zone in self.zones.iter_mut().filter(|zone| zone.uuid.is_none()) {
+            zone.uuid = Some(generator.generate(format!("zone {:?} {:?}", zone.net_name, zone.outline)));
+        }
+    }
+
+    /// Adds a top-level item, like `(segment ...)` or `(net ...)`, to the
+    /// board. Unknown items are kept as extensions or skipped.
+    pub(crate) fn add_item(&mut self, item: &List) -> Result<()> {
+        match item.tag() {
+            "layers" => self.layers = layer_table(item)?,
+            "net" => self.nets.push(Net::from_sexp(item)?),
+            "footprint" | "module" => self.footprints.push(Footprint::from_sexp(item)?),
+            "gr_text" => self.texts.push(Text::from_sexp(item)?),
+            "segment" | "arc" => self.tracks.push(Track::from_sexp(item)?),
+            "via" => self.vias.push(Via::from_sexp(item)?),
+            "zone" => self.zones.push(Zone::from_sexp(item)?),
+            "embedded_files" => self.embedded_files = item.parse_all("file")?,
+            tag if Shape::is_shape_tag(tag) => self.shapes.push(Shape::from_sexp(item)?),
+            _ => {
+                if let Some(node) = parse_extension(item) {
+                    self.extensions.push(node?);
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+impl FromSexp for Board {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let thickness = match list.child("general") {
+            Some(general) => general.child_number("thickness").map_err(|e| e.within(list.tag()))?,
+            None => None,
+        };
+        let mut board = Board {
+            version: list.child_number("version")?.ok_or_else(|| list.error("missing (version ...)"))?,
+            generator: list.child_string("generator")?.unwrap_or_default(),
+            generator_version: list.child_string("generator_version")?,
+            thickness: thickness.unwrap_or(1.6),
+            paper: list.parse("paper")?.unwrap_or_default(),
+            title_block: list.parse("title_block")?,
+            setup: list.parse("setup")?.unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for item in list.lists() {
+            board.add_item(&item).map_err(|e| e.within(list.tag()))?;
+        }
+
+        Ok(board)
+    }
+}
@@ -0,0 +1,215 @@
+//! Geometry helpers shared by selections and the render and export
+//! modules, each of which uses only some of them.
+#![cfg_attr(not(all(feature = "render", feature = "export")), allow(dead_code))]
+
+use kicad_common::Point;
+
+use crate::{Footprint, ShapeKind};
+
+/// Axis-aligned bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn of(points: impl IntoIterator<Item = Point>) -> Option<Bounds> {
+        points.into_iter().fold(None, |bounds, p| {
+            Some(match bounds {
+                None => Bounds { min: p, max: p },
+                Some(Bounds { min, max }) => Bounds {
+                    min: Point::new(min.x.min(p.x), min.y.min(p.y)),
+                    max: Point::new(max.x.max(p.x), max.y.max(p.y)),
+                },
+            })
+        })
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x) && (self.min.y..=self.max.y).contains(&p.y)
+    }
+
+    pub fn corners(&self) -> [Point; 4] {
+        [self.min, Point::new(self.max.x, self.min.y), self.max, Point::new(self.min.x, self.max.y)]
+    }
+
+    /// Returns true if the bounds and `polygon` share any area.
+    pub fn overlaps(&self, polygon: &[Point]) -> bool {
+        let corners = self.corners();
+        corners.iter().any(|corner| polygon_contains(polygon, *corner))
+            || polygon.iter().any(|p| self.contains(*p))
+            || edges(polygon).any(|(a, b)| edges(&corners).any(|(c, d)| segments_cross(a, b, c, d)))
+    }
+}
+
+/// Center of the circle through three points, `None` if they are collinear.
+pub(crate) fn circle_center(a: Point, b: Point, c: Point) -> Option<Point> {
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if d.abs() < 1e-12 {
+        return None;
+    }
+    let (a2, b2, c2) = (a.x * a.x + a.y * a.y, b.x * b.x + b.y * b.y, c.x * c.x + c.y * c.y);
+    Some(Point::new(
+        (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+        (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+    ))
+}
+
+/// Points whose bounds contain the shape. Arcs use their three points, which
+/// can miss a bulge past an axis extreme.
+pub(crate) fn shape_points(kind: &ShapeKind) -> Vec<Point> {
+    match kind {
+        ShapeKind::Line { start, end } => vec![*start, *end],
+        ShapeKind::Rect { start, end } => vec![*start, Point::new(end.x, start.y), *end, Point::new(start.x, end.y)],
+        ShapeKind::Circle { center, end } => {
+            let r = distance(*center, *end);
+            vec![Point::new(center.x - r, center.y - r), Point::new(center.x + r, center.y + r)]
+        },
+        ShapeKind::Arc { start, mid, end } => vec![*start, *mid, *end],
+        ShapeKind::Polygon(points) | ShapeKind::Curve(points) => points.clone(),
+    }
+}
+
+/// Like [`shape_points`] for a footprint-relative shape, in board coordinates.
+/// Rotation happens before the bounds are taken, so rotated circles stay tight.
+pub(crate) fn footprint_shape_points(footprint: &Footprint, kind: &ShapeKind) -> Vec<Point> {
+    match kind {
+        ShapeKind::Circle { center, end } => {
+            let r = distance(*center, *end);
+            let c = footprint.to_board(*center);
+            vec![Point::new(c.x - r, c.y - r), Point::new(c.x + r, c.y + r)]
+        },
+        kind => shape_points(kind).into_iter().map(|p| footprint.to_board(p)).collect(),
+    }
+}
+
+pub(crate) fn distance(a: Point, b: Point) -> f64 {
+    ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt()
+}
+
+fn edges(polygon: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    polygon.iter().zip(polygon.iter().cycle().skip(1)).map(|(a, b)| (*a, *b))
+}
+
+pub(crate) fn polygon_contains(polygon: &[Point], p: Point) -> bool {
+    let mut inside = false;
+    for (a, b) in edges(polygon) {
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+pub(crate) fn segments_cross(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let cross = |o: Point, p: Point, q: Point| (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x);
+    let (d1, d2) = (cross(c, d, a), cross(c, d, b));
+    let (d3, d4) = (cross(a, b, c), cross(a, b, d));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Returns true if the polygon `inner` lies within `outer`.
+pub(crate) fn polygon_inside(inner: &[Point], outer: &[Point]) -> bool {
+    inner.iter().all(|p| polygon_contains(outer, *p))
+        && !edges(inner).any(|(a, b)| edges(outer).any(|(c, d)| segments_cross(a, b, c, d)))
+}
+
+/// The parts of the segment from `a` to `b` inside `polygon`, in order from
+/// `a`. Ends inside the polygon are kept exactly; the others are snapped to
+/// KiCad's nanometer grid.
+pub(crate) fn clip_segment(a: Point, b: Point, polygon: &[Point]) -> Vec<(Point, Point)> {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let mut cuts = vec![0.0, 1.0];
+    for (c, d) in edges(polygon) {
+        let (ex, ey) = (d.x - c.x, d.y - c.y);
+        let denom = dx * ey - dy * ex;
+        if denom.abs() < 1e-12 {
+            continue;
+        }
+        let t = ((c.x - a.x) * ey - (c.y - a.y) * ex) / denom;
+        let u = ((c.x - a.x) * dy - (c.y - a.y) * dx) / denom;
+        if (0.0..=1.0).contains(&u) && t > 0.0 && t < 1.0 {
+            cuts.push(t);
+        }
+    }
+    cuts.sort_by(f64::total_cmp);
+    let at = |t: f64| match t {
+        0.0 => a,
+        1.0 => b,
+        _ => snap(Point::new(a.x + dx * t, a.y + dy * t)),
+    };
+    let mut parts: Vec<(f64, f64)> = Vec::new();
+    for pair in cuts.windows(2) {
+        let middle = Point::new(a.x + dx * (pair[0] + pair[1]) / 2.0, a.y + dy * (pair[0] + pair[1]) / 2.0);
+        if pair[1] - pair[0] < 1e-12 || !polygon_contains(polygon, middle) {
+            continue;
+        }
+        match parts.last_mut() {
+            Some(last) if last.1 == pair[0] => last.1 = pair[1],
+            _ => parts.push((pair[0], pair[1])),
+        }
+    }
+    parts.into_iter().map(|(start, end)| (at(start), at(end))).collect()
+}
+
+/// `p` rounded to KiCad's nanometer grid.
+pub(crate) fn snap(p: Point) -> Point {
+    let round = |v: f64| (v * 1e6).round() / 1e6;
+    Point::new(round(p.x), round(p.y))
+}
+
+/// `kind` with `f` applied to all of its points.
+pub(crate) fn map_points(kind: &ShapeKind, f: impl Fn(Point) -> Point) -> ShapeKind {
+    match kind {
+        ShapeKind::Line { start, end } => ShapeKind::Line { start: f(*start), end: f(*end) },
+        ShapeKind::Rect { start, end } => ShapeKind::Rect { start: f(*start), end: f(*end) },
+        ShapeKind::Circle { center, end } => ShapeKind::Circle { center: f(*center), end: f(*end) },
+        ShapeKind::Arc { start, mid, end } => ShapeKind::Arc { start: f(*start), mid: f(*mid), end: f(*end) },
+        ShapeKind::Polygon(points) => ShapeKind::Polygon(points.iter().copied().map(f).collect()),
+        ShapeKind::Curve(points) => ShapeKind::Curve(points.iter().copied().map(f).collect()),
+    }
+}
+
+/// Points along a cubic Bézier curve; other point counts are taken as a
+/// polyline.
+pub(crate) fn bezier_points(points: &[Point]) -> Vec<Point> {
+    let &[p0, p1, p2, p3] = points else {
+        return points.to_vec();
+    };
+    (0..=16)
+        .map(|i| {
+            let t = i as f64 / 16.0;
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            Point::new(a * p0.x + b * p1.x + c * p2.x + d * p3.x, a * p0.y + b * p1.y + c * p2.y + d * p3.y)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Vec<Point> {
+        vec![Point::new(x0, y0), Point::new(x1, y0), Point::new(x1, y1), Point::new(x0, y1)]
+    }
+
+    #[test]
+    fn overlap() {
+        let bounds = Bounds { min: Point::new(0.0, 0.0), max: Point::new(2.0, 2.0) };
+
+        assert!(bounds.overlaps(&rect(1.0, 1.0, 5.0, 5.0)));
+        assert!(bounds.overlaps(&rect(-1.0, -1.0, 3.0, 3.0)));
+        assert!(bounds.overlaps(&rect(0.5, -1.0, 1.5, 3.0)));
+        assert!(!bounds.overlaps(&rect(3.0, 0.0, 4.0, 2.0)));
+    }
+
+    #[test]
+    fn circles() {
+        let center = circle_center(Point::new(1.0, 0.0), Point::new(0.0, 1.0), Point::new(-1.0, 0.0)).unwrap();
+
+        assert!(distance(center, Point::default()) < 1e-12);
+        assert_eq!(circle_center(Point::new(0.0, 0.0), Point::new(1.0, 1.0), Point::new(2.0, 2.0)), None);
+    }
+}
@@ -0,0 +1,269 @@
+//! New boards from scratch, for tools that generate them such as keyboard
+//! and panel generators.
+//!
+//! The builder writes the header of a board the way KiCad saves a new,
+//! empty one: version, paper, title block, layer table, setup and nets.
+//! Content is added to the written file, or to the [`Board`] from
+//! [`BoardBuilder::build`], e.g. with the functions of [`crate::layout`].
+//!
+//! ```
+//! use kicad_common::{Paper, PaperSize, TitleBlock};
+//! use kicad_pcb::{BoardBuilder, Layer, LayerDef};
+//!
+//! let board = BoardBuilder::new()
+//!     .paper(Paper { size: PaperSize::A3, portrait: false })
+//!     .title_block(TitleBlock { title: Some("Panel".to_string()), ..Default::default() })
+//!     .copper_layers(4)
+//!     .add_layer(LayerDef::new(Layer::User(5)))
+//!     .net("GND")
+//!     .build()?;
+//! assert_eq!(board.copper_layers().len(), 4);
+//! assert_eq!(board.net_by_name("GND").map(|net| net.number), Some(1));
+//! # Ok::<(), kicad_pcb::Error>(())
+//! ```
+
+use std::fmt;
+
+use kicad_common::{Error, FromSexp, List, Paper, TitleBlock};
+use kicad_sexp::{SexpBuilder, SexpOwned};
+
+use crate::{Board, Layer, LayerDef, LayerKind};
+
+/// The setup section of a new board in KiCad 9.
+const SETUP: &str = "(setup
+    (pad_to_mask_clearance 0)
+    (allow_soldermask_bridges_in_footprints no)
+    (tenting front back)
+    (pcbplotparams
+        (layerselection 0x00000000_00000000_55555555_5755f5ff)
+        (plot_on_all_layers_selection 0x00000000_00000000_00000000_00000000)
+        (disableapertmacros no) (usegerberextensions no) (usegerberattributes yes)
+        (usegerberadvancedattributes yes) (creategerberjobfile yes)
+        (dashed_line_dash_ratio 12.000000) (dashed_line_gap_ratio 3.000000) (svgprecision 4)
+        (plotframeref no) (mode 1) (useauxorigin no)
+        (hpglpennumber 1) (hpglpenspeed 20) (hpglpendiameter 15.000000)
+        (pdf_front_fp_property_popups yes) (pdf_back_fp_property_popups yes) (pdf_metadata yes)
+        (pdf_single_document no) (dxfpolygonmode yes) (dxfimperialunits yes) (dxfusepcbnewfont yes)
+        (psnegative no) (psa4output no) (plot_black_and_white yes) (sketchpadsonfab no)
+        (plotpadnumbers no) (hidednponfab no) (sketchdnponfab yes) (crossoutdnponfab yes)
+        (subtractmaskfromsilk no) (outputformat 1) (mirror no) (drillshape 1) (scaleselection 1)
+        (outputdirectory \"\")))";
+
+/// The layers of a new two-layer board, in KiCad's table order.
+const LAYERS: [Layer; 24] = [
+    Layer::FCu,
+    Layer::BCu,
+    Layer::FAdhes,
+    Layer::BAdhes,
+    Layer::FPaste,
+    Layer::BPaste,
+    Layer::FSilkS,
+    Layer::BSilkS,
+    Layer::FMask,
+    Layer::BMask,
+    Layer::DwgsUser,
+    Layer::CmtsUser,
+    Layer::Eco1User,
+    Layer::Eco2User,
+    Layer::EdgeCuts,
+    Layer::Margin,
+    Layer::FCrtYd,
+    Layer::BCrtYd,
+    Layer::FFab,
+    Layer::BFab,
+    Layer::User(1),
+    Layer::User(2),
+    Layer::User(3),
+    Layer::User(4),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoardBuilder {
+    generator: String,
+    generator_version: Option<String>,
+    paper: Paper,
+    title_block: Option<TitleBlock>,
+    thickness: f64,
+    /// Copper layers first, front to back, then the others as added.
+    layers: Vec<LayerDef>,
+    /// Net names from net 1 on; net 0 is always the unconnected net.
+    nets: Vec<String>,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBuilder {
+    /// A 1.6 mm two-layer board on A4 paper with KiCad's default layers,
+    /// as pcbnew 9.0 creates it.
+    pub fn new() -> Self {
+        BoardBuilder {
+            generator: "pcbnew".to_string(),
+            generator_version: Some("9.0".to_string()),
+            paper: Paper::default(),
+            title_block: None,
+            thickness: 1.6,
+            layers: LAYERS.into_iter().map(LayerDef::new).collect(),
+            nets: Vec::new(),
+        }
+    }
+
+    /// The program named as the file's writer.
+    pub fn generator(mut self, name: &str, version: Option<&str>) -> Self {
+        self.generator = name.to_string();
+        self.generator_version = version.map(str::to_string);
+        self
+    }
+
+    pub fn paper(mut self, paper: Paper) -> Self {
+        self.paper = paper;
+        self
+    }
+
+    pub fn title_block(mut self, title_block: TitleBlock) -> Self {
+        self.title_block = Some(title_block);
+        self
+    }
+
+    /// Board thickness in millimeters.
+    pub fn thickness(mut self, thickness: f64) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    /// Adds or removes inner copper layers to have `count` copper layers.
+    ///
+    /// # Panics
+    ///
+    /// If `count` is odd or not between 2 and 32, which KiCad does not
+    /// allow.
+    pub fn copper_layers(mut self, count: u8) -> Self {
+        assert!(count.is_multiple_of(2) && (2..=32).contains(&count), "a board has 2 to 32 copper layers, an even number");
+        self.layers.retain(|def| !matches!(def.layer, Layer::InCu(_)));
+        let back = self.layers.iter().position(|def| def.layer == Layer::BCu).unwrap_or(self.layers.len());
+        let inner = (1..count - 1).map(|n| LayerDef::new(Layer::InCu(n)));
+        self.layers.splice(back..back, inner);
+        self
+    }
+
+    /// Adds `def` to the layer table, replacing the entry for the same
+    /// layer. New non-copper layers go at the end; a new inner copper layer
+    /// goes between its neighbours.
+    pub fn add_layer(mut self, def: LayerDef) -> Self {
+        if let Some(existing) = self.layers.iter_mut().find(|existing| existing.layer == def.layer) {
+            *existing = def;
+            return self;
+        }
+        let at = match def.layer.is_copper() {
+            true => self.layers.iter().position(|other| !other.layer.is_copper() || other.layer > def.layer),
+            false => None,
+        };
+        self.layers.insert(at.unwrap_or(self.layers.len()), def);
+        self
+    }
+
+    /// Adds a net, numbered after the ones added before.
+    pub fn net(mut self, name: &str) -> Self {
+        self.nets.push(name.to_string());
+        self
+    }
+
+    /// The board file as KiCad writes it; [`Display`](fmt::Display) formats
+    /// the whole file.
+    pub fn to_sexp(&self) -> SexpOwned {
+        let layers = self.layers.iter().fold(SexpBuilder::new("layers"), |layers, def| {
+            let kind = match def.kind {
+                LayerKind::Signal => "signal",
+                LayerKind::Power => "power",
+                LayerKind::Mixed => "mixed",
+                LayerKind::Jumper => "jumper",
+                LayerKind::User => "user",
+            };
+            let entry = SexpOwned::List(
+                [SexpOwned::int(def.ordinal.into()), SexpOwned::string(&def.layer.to_string()), SexpOwned::symbol(kind)]
+                    .into_iter()
+                    .chain(def.user_name.as_deref().map(SexpOwned::string))
+                    .collect(),
+            );
+            layers.item(entry)
+        });
+        let setup = kicad_sexp::parse(SETUP).expect("the setup template parses").remove(0).into_owned();
+        let board = SexpBuilder::new("kicad_pcb")
+            .child(SexpBuilder::new("version").int(Board::NEWEST_VERSION.into()))
+            .child(SexpBuilder::new("generator").string(&self.generator))
+            .child_opt(self.generator_version.as_deref().map(|v| SexpBuilder::new("generator_version").string(v)))
+            .child(
+                SexpBuilder::new("general")
+                    .child(SexpBuilder::new("thickness").float(self.thickness))
+                    .child(SexpBuilder::new("legacy_teardrops").symbol("no")),
+            )
+            .item(self.paper.to_sexp());
+        let board = match &self.title_block {
+            Some(title_block) => board.item(title_block.to_sexp()),
+            None => board,
+        };
+        let board = board.child(layers).item(setup).child(SexpBuilder::new("net").int(0).string(""));
+        let board = self.nets.iter().enumerate().fold(board, |board, (i, name)| {
+            board.child(SexpBuilder::new("net").int(i as i64 + 1).string(name))
+        });
+        board.child(SexpBuilder::new("embedded_fonts").symbol("no")).build()
+    }
+
+    /// The board as the typed model. Fails if something given to the
+    /// builder, like a net or layer name, does not read back.
+    pub fn build(&self) -> Result<Board, Error> {
+        let sexp = self.to_sexp();
+        let sexp = sexp.as_sexp();
+        Ok(Board::from_sexp(&List::expect(&sexp, "kicad_pcb")?)?)
+    }
+}
+
+impl fmt::Display for BoardBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_sexp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::PaperSize;
+
+    use super::*;
+
+    #[test]
+    fn empty_board() {
+        assert_eq!(BoardBuilder::new().to_string(), include_str!("../../reference-files/empty/empty.kicad_pcb"));
+        assert_eq!(BoardBuilder::new().build().unwrap(), Board::parse(&BoardBuilder::new().to_string()).unwrap());
+    }
+
+    #[test]
+    fn four_layers() {
+        let title_block = TitleBlock { title: Some("Keyboard".to_string()), ..Default::default() };
+        let builder = BoardBuilder::new()
+            .paper(Paper { size: PaperSize::User { width: 200.0, height: 150.0 }, portrait: false })
+            .title_block(title_block.clone())
+            .thickness(1.2)
+            .copper_layers(4)
+            .add_layer(LayerDef { kind: LayerKind::Power, ..LayerDef::new(Layer::InCu(2)) })
+            .add_layer(LayerDef::new(Layer::User(5)))
+            .net("GND")
+            .net("VCC");
+        let text = builder.to_string();
+        assert!(text.contains("\t(paper \"User\" 200 150)\n\t(title_block\n\t\t(title \"Keyboard\")\n\t)\n"));
+        assert!(text.contains("(0 \"F.Cu\" signal)\n\t\t(4 \"In1.Cu\" signal)\n\t\t(6 \"In2.Cu\" power)\n\t\t(2 \"B.Cu\""));
+        assert!(text.contains("(45 \"User.4\" user)\n\t\t(47 \"User.5\" user)\n\t)"));
+
+        let board = Board::parse(&text).unwrap();
+        assert_eq!(board, builder.build().unwrap());
+        assert_eq!((board.thickness, &board.title_block), (1.2, &Some(title_block)));
+        assert_eq!(board.copper_layers(), [Layer::FCu, Layer::InCu(1), Layer::InCu(2), Layer::BCu]);
+        let nets: Vec<_> = board.nets.iter().map(|net| (net.number, net.name.as_str())).collect();
+        assert_eq!(nets, [(0, ""), (1, "GND"), (2, "VCC")]);
+
+        let two = builder.copper_layers(2).build().unwrap();
+        assert_eq!(two.copper_layers(), [Layer::FCu, Layer::BCu]);
+    }
+}
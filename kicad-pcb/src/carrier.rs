@@ -0,0 +1,253 @@
+//! Modules soldered onto carrier boards: footprints with castellated pads,
+//! or of a module library, and maps of the pads along their edges.
+//!
+//! A [`ConnectionMap`] lists the pads of a module footprint that reach the
+//! edge of its outline, the footprint's `Edge.Cuts` drawings or else its
+//! fabrication or courtyard drawings, with the edge each is on and how far
+//! along it. The map can be written as CSV for a carrier board generator,
+//! or [checked](ConnectionMap::check) against the footprint a carrier board
+//! lands the module on.
+//!
+//! ```no_run
+//! use kicad_pcb::{carrier::ConnectionMap, Board};
+//!
+//! let board = Board::parse(&std::fs::read_to_string("module.kicad_pcb")?)?;
+//! let module = board.footprint("U1").ok_or("no U1")?;
+//! let map = ConnectionMap::new(module, 0.1).ok_or("U1 has no outline")?;
+//! std::fs::write("module.csv", map.to_csv())?;
+//! let carrier = Board::parse(&std::fs::read_to_string("carrier.kicad_pcb")?)?;
+//! for issue in map.check(carrier.footprint("M1").ok_or("no M1")?, 0.01) {
+//!     eprintln!("{}", issue);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt::Write;
+
+use kicad_common::{Point, Size};
+use kicad_sexp::{number::format_float, schema::Issue};
+
+use crate::{
+    bounds::{distance, shape_points, Bounds},
+    Board, Footprint, Layer, LayerClass, Pad, PadKind, PadProperty,
+};
+
+/// A side of a footprint's outline, in the footprint's own orientation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Edge {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+impl Edge {
+    pub fn name(self) -> &'static str {
+        match self {
+            Edge::Top => "top",
+            Edge::Right => "right",
+            Edge::Bottom => "bottom",
+            Edge::Left => "left",
+        }
+    }
+}
+
+/// A pad at the edge of a module.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EdgePad {
+    pub number: String,
+    pub pinfunction: Option<String>,
+    pub edge: Edge,
+    /// Distance along the edge from its left or top end, in millimeters.
+    pub offset: f64,
+    /// Center relative to the footprint, unturned.
+    pub position: Point,
+    pub castellated: bool,
+}
+
+/// The pads along the edges of a module footprint.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectionMap {
+    pub lib_id: String,
+    /// Top left corner of the outline, relative to the footprint.
+    pub origin: Point,
+    pub size: Size,
+    /// By edge, clockwise from the top, then along it.
+    pub pads: Vec<EdgePad>,
+}
+
+impl ConnectionMap {
+    /// The map of `footprint`'s copper pads that reach within `tolerance`
+    /// millimeters of its outline, or cross it; castellated pads count
+    /// wherever they are. `None` for footprints without an outline.
+    pub fn new(footprint: &Footprint, tolerance: f64) -> Option<Self> {
+        let outline = outline(footprint)?;
+        let mut pads: Vec<EdgePad> = footprint
+            .pads
+            .iter()
+            .filter(|pad| !pad.number.is_empty() && pad.kind != PadKind::NpThruHole)
+            .filter_map(|pad| edge_pad(footprint, pad, outline, tolerance))
+            .collect();
+        pads.sort_by(|a, b| a.edge.cmp(&b.edge).then(a.offset.total_cmp(&b.offset)));
+        let size = Size::new(outline.max.x - outline.min.x, outline.max.y - outline.min.y);
+        Some(ConnectionMap { lib_id: footprint.lib_id.clone(), origin: outline.min, size, pads })
+    }
+
+    /// One line per pad: number, function, edge, offset along it, position
+    /// and whether it is castellated.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("Pad,Function,Edge,Offset,PosX,PosY,Castellated\n");
+        let quoted = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+        for pad in &self.pads {
+            let _ = writeln!(
+                out,
+                "{},{},{},{:.6},{:.6},{:.6},{}",
+                quoted(&pad.number),
+                quoted(pad.pinfunction.as_deref().unwrap_or_default()),
+                pad.edge.name(),
+                pad.offset,
+                pad.position.x,
+                pad.position.y,
+                if pad.castellated { "yes" } else { "no" },
+            );
+        }
+        out
+    }
+
+    /// An issue for each pad of the map that `footprint`, the module's
+    /// landing pattern on a carrier board, lacks or has more than
+    /// `tolerance` millimeters away from the module's.
+    pub fn check(&self, footprint: &Footprint, tolerance: f64) -> Vec<Issue> {
+        let reference = footprint.reference().unwrap_or_default();
+        let mut issues = Vec::new();
+        for pad in &self.pads {
+            let message = match footprint.pad(&pad.number) {
+                None => format!("{} has no pad {} for the module's {} edge", reference, pad.number, pad.edge.name()),
+                Some(landing) if distance(landing.at.point(), pad.position) > tolerance => format!(
+                    "pad {} of {} is at ({}, {}), {} mm from the module's ({}, {})",
+                    pad.number,
+                    reference,
+                    format_float(landing.at.x),
+                    format_float(landing.at.y),
+                    format_float((distance(landing.at.point(), pad.position) * 1000.0).round() / 1000.0),
+                    format_float(pad.position.x),
+                    format_float(pad.position.y)
+                ),
+                Some(_) => continue,
+            };
+            issues.push(Issue { path: vec!["kicad_pcb".into(), "footprint".into(), "pad".into()], message });
+        }
+        issues
+    }
+}
+
+/// Whether any pad of `footprint` is castellated.
+pub fn is_castellated(footprint: &Footprint) -> bool {
+    footprint.pads.iter().any(|pad| pad.property == Some(PadProperty::Castellated))
+}
+
+/// Modules are recognized by castellated pads, or by a library whose name
+/// ends in `Module`, as KiCad's `RF_Module` and `Module` do.
+pub fn is_module(footprint: &Footprint) -> bool {
+    let library = footprint.lib_id.split_once(':').map_or("", |(library, _)| library);
+    is_castellated(footprint) || library.ends_with("Module")
+}
+
+/// The module footprints of `board`.
+pub fn modules(board: &Board) -> Vec<&Footprint> {
+    board.footprints.iter().filter(|footprint| is_module(footprint)).collect()
+}
+
+/// Bounds of the footprint's `Edge.Cuts` drawings, or else of its
+/// fabrication or courtyard drawings, relative to the footprint.
+fn outline(footprint: &Footprint) -> Option<Bounds> {
+    let classes = [None, Some(LayerClass::Fab), Some(LayerClass::CrtYd)];
+    classes.into_iter().find_map(|class| {
+        let on = |layer: Layer| match class {
+            None => layer == Layer::EdgeCuts,
+            Some(class) => layer.class() == Some(class) && layer.is_back() == footprint.is_back(),
+        };
+        let shapes = footprint.shapes.iter().filter(|shape| on(shape.layer));
+        Bounds::of(shapes.flat_map(|shape| shape_points(&shape.kind)))
+    })
+}
+
+fn edge_pad(footprint: &Footprint, pad: &Pad, outline: Bounds, tolerance: f64) -> Option<EdgePad> {
+    // The pad's own turn, which the footprint's is part of.
+    let turned = (pad.at.angle - footprint.at.angle).rem_euclid(180.0);
+    let (width, height) = match (turned - 90.0).abs() < 45.0 {
+        true => (pad.size.height, pad.size.width),
+        false => (pad.size.width, pad.size.height),
+    };
+    let at = pad.at.point();
+    let gaps = [
+        (Edge::Top, at.y - height / 2.0 - outline.min.y),
+        (Edge::Right, outline.max.x - at.x - width / 2.0),
+        (Edge::Bottom, outline.max.y - at.y - height / 2.0),
+        (Edge::Left, at.x - width / 2.0 - outline.min.x),
+    ];
+    let (edge, gap) = gaps.into_iter().min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+    let castellated = pad.property == Some(PadProperty::Castellated);
+    if gap > tolerance && !castellated {
+        return None;
+    }
+    let offset = match edge {
+        Edge::Top | Edge::Bottom => at.x - outline.min.x,
+        Edge::Left | Edge::Right => at.y - outline.min.y,
+    };
+    Some(EdgePad {
+        number: pad.number.clone(),
+        pinfunction: pad.pinfunction.clone(),
+        edge,
+        offset,
+        position: at,
+        castellated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODULE: &str = r#"(footprint "RF_Module:Test" (layer "F.Cu")
+        (property "Reference" "U1" (at 0 -1) (layer "F.SilkS"))
+        (fp_rect (start 0 0) (end 10 6) (stroke (width 0.1) (type solid)) (fill no) (layer "F.Fab"))
+        (pad "1" smd rect (at 0.5 1) (size 1.5 0.8) (property pad_prop_castellated) (layers "F.Cu" "F.Mask"))
+        (pad "2" smd rect (at 0.5 3) (size 1.5 0.8) (property pad_prop_castellated) (layers "F.Cu" "F.Mask")
+            (pinfunction "TX"))
+        (pad "3" smd rect (at 4 5.5 90) (size 1.5 0.8) (layers "F.Cu" "F.Mask"))
+        (pad "4" smd rect (at 9.5 2) (size 1.5 0.8) (layers "F.Cu" "F.Mask"))
+        (pad "5" smd rect (at 5 3) (size 2 2) (layers "F.Cu" "F.Mask")))"#;
+
+    #[test]
+    fn connection_map() {
+        let module = Footprint::parse(MODULE).unwrap();
+        assert!(is_castellated(&module) && is_module(&module));
+        assert_eq!(module.pad("1").unwrap().property, Some(PadProperty::Castellated));
+        assert!(module.pad("1").unwrap().to_sexp().build().to_string().contains("(property pad_prop_castellated)"));
+
+        let map = ConnectionMap::new(&module, 0.1).unwrap();
+        assert_eq!((map.origin, map.size), (Point::default(), Size::new(10.0, 6.0)));
+        let pads: Vec<_> = map.pads.iter().map(|pad| (pad.number.as_str(), pad.edge, pad.offset)).collect();
+        let expected = [("4", Edge::Right, 2.0), ("3", Edge::Bottom, 4.0), ("1", Edge::Left, 1.0)];
+        assert_eq!(pads[..3], expected);
+        assert_eq!(pads[3], ("2", Edge::Left, 3.0));
+        assert!(map.to_csv().ends_with("\"2\",\"TX\",left,3.000000,0.500000,3.000000,yes\n"), "{}", map.to_csv());
+
+        let mut landing = module.clone();
+        landing.pads.retain(|pad| pad.number != "4");
+        landing.pads[0].at.y = 1.3;
+        let messages: Vec<String> = map.check(&landing, 0.01).into_iter().map(|issue| issue.message).collect();
+        assert_eq!(messages, [
+            "U1 has no pad 4 for the module's right edge",
+            "pad 1 of U1 is at (0.5, 1.3), 0.3 mm from the module's (0.5, 1)"
+        ]);
+        assert!(map.check(&module, 0.01).is_empty());
+
+        let resistor = Footprint { lib_id: "Resistor_SMD:R_0603".to_string(), ..Footprint::default() };
+        assert!(!is_module(&resistor) && ConnectionMap::new(&resistor, 0.1).is_none());
+    }
+}
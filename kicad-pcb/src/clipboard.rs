@@ -0,0 +1,87 @@
+//! The text the board and footprint editors put on the clipboard. The board
+//! editor copies a `(kicad_pcb ...)` file of the selected items, with the
+//! layers, setup and nets of the board; the footprint editor copies a lone
+//! `(footprint ...)`.
+//!
+//! [`parse`] reads either, or root-less items, into the typed model, and
+//! [`copy`] writes a selection the way the board editor does, so it can be
+//! pasted into a running KiCad.
+//!
+//! ```no_run
+//! use kicad_pcb::{clipboard, index::Item, selection::Selection, Board};
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let board = Board::parse(&src)?;
+//! let footprints = Selection::query(&board, |item| matches!(item, Item::Footprint(..)));
+//! let text = clipboard::copy(&footprints, &kicad_sexp::cst::parse(&src)?)?;
+//! assert_eq!(clipboard::parse(&text)?.footprints.len(), footprints.len());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{
+    convert::{FromSexp, List},
+    ConvertError, Error,
+};
+use kicad_sexp::cst::Document;
+
+use crate::{
+    selection::{write_subset, Selection},
+    Board,
+};
+
+/// Parses clipboard text: a whole board, a footprint or root-less items.
+/// The items of anything but a board go into an otherwise empty board,
+/// whose header fields, like `version`, keep their defaults.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = src.len())))]
+pub fn parse(src: &str) -> Result<Board, Error> {
+    let sexps = kicad_sexp::parse(src).map_err(Error::Parse)?;
+    let mut board = Board::default();
+    for sexp in &sexps {
+        let list = List::new(sexp).ok_or_else(|| ConvertError::new("expected a list on the clipboard"))?;
+        match list.tag() {
+            "kicad_pcb" if sexps.len() == 1 => return Ok(Board::from_sexp(&list)?),
+            _ => board.add_item(&list)?,
+        }
+    }
+    Ok(board)
+}
+
+/// The selected items of the board `doc` as clipboard text: the board file
+/// without the items that are not selected.
+pub fn copy(selection: &Selection, doc: &Document<'_>) -> Result<String, Error> {
+    let mut doc = doc.clone();
+    write_subset(selection, &mut doc)?;
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Item;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+    const FOOTPRINT: &str = include_str!("../../reference-files/demo/demo.pretty/R_0603_1608Metric.kicad_mod");
+
+    #[test]
+    fn copy_and_paste() {
+        let board = Board::parse(DEMO).unwrap();
+        let selection = Selection::query(&board, |item| matches!(item, Item::Footprint(..) | Item::Track(..)));
+        let text = copy(&selection, &kicad_sexp::cst::parse(DEMO).unwrap()).unwrap();
+        let pasted = parse(&text).unwrap();
+        assert_eq!((pasted.footprints, pasted.tracks), (board.footprints.clone(), board.tracks.clone()));
+        assert!(pasted.vias.is_empty() && pasted.zones.is_empty());
+        assert_eq!((pasted.version, pasted.layers, pasted.nets), (board.version, board.layers, board.nets));
+
+        let pasted = parse(FOOTPRINT).unwrap();
+        assert_eq!(pasted.footprints, [crate::Footprint::parse(FOOTPRINT).unwrap()]);
+        assert_eq!(pasted.version, 0);
+        let track = concat!(
+            "(segment (start 0 0) (end 1 0) (width 0.2) (layer \"F.Cu\") (net 1)",
+            " (uuid \"00000000-0000-0000-0000-000000000001\"))\n",
+            "(via (at 1 0) (size 0.6) (drill 0.3) (layers \"F.Cu\" \"B.Cu\") (net 1)",
+            " (uuid \"00000000-0000-0000-0000-000000000002\"))",
+        );
+        let pasted = parse(track).unwrap();
+        assert_eq!((pasted.tracks.len(), pasted.vias.len()), (1, 1));
+    }
+}
@@ -0,0 +1,347 @@
+//! Relative placement constraints, for checking placement intent in CI:
+//! "C3 within 2 mm of U1 pad 5", "the decouplers on the same side as U1".
+//!
+//! Constraints live in a sidecar file next to the board, in the style of a
+//! `.kicad_dru` file: a `(version 1)` followed by the constraints, each
+//! naming the footprints it applies to, with `*` and `?` wildcards, and how
+//! they relate to another footprint.
+//!
+//! ```text
+//! (version 1)
+//! (constraint "decoupling"
+//!     (footprints "C3" "C4")
+//!     (near "U1" (pad "5") (max 2)))
+//! (constraint (footprints "C*") (same_side "U1"))
+//! ```
+//!
+//! Or they live on the footprints themselves, as a field whose value is
+//! one or more relations, such as `(near "U1" (pad "5") (max 2))` on C3;
+//! [`PlacementConstraints::from_properties`] collects them.
+//!
+//! ```no_run
+//! use kicad_pcb::{constraints::PlacementConstraints, Board};
+//!
+//! let board = Board::parse(&std::fs::read_to_string("demo.kicad_pcb")?)?;
+//! let mut constraints = PlacementConstraints::parse(&std::fs::read_to_string("demo.placement")?)?;
+//! constraints.constraints.extend(PlacementConstraints::from_properties(&board, "Placement")?.constraints);
+//! for issue in constraints.check(&board) {
+//!     eprintln!("{}", issue);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    ConvertError, Error,
+};
+use kicad_dru::wildcard_match;
+use kicad_sexp::{number::format_float, schema::Issue, SexpBuilder};
+
+use crate::{bounds::distance, Board, Footprint, Side};
+
+/// How the constrained footprints relate to another one.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Relation {
+    /// Within `max` millimeters of the footprint `reference`, or of its pad
+    /// `pad`, measured between pad centers: the nearest pad of the
+    /// constrained footprint to the pad, or to the nearest pad of the
+    /// other footprint. Footprints without pads count their origin.
+    Near { reference: String, pad: Option<String>, max: f64 },
+    /// On the same side of the board as the footprint `reference`.
+    SameSide { reference: String },
+}
+
+impl Relation {
+    fn reference(&self) -> &str {
+        match self {
+            Relation::Near { reference, .. } | Relation::SameSide { reference } => reference,
+        }
+    }
+
+    fn to_sexp(&self) -> SexpBuilder {
+        match self {
+            Relation::Near { reference, pad, max } => SexpBuilder::new("near")
+                .string(reference)
+                .child_opt(pad.as_deref().map(|pad| SexpBuilder::new("pad").string(pad)))
+                .child(SexpBuilder::new("max").float(*max)),
+            Relation::SameSide { reference } => SexpBuilder::new("same_side").string(reference),
+        }
+    }
+
+    /// `(near ...)` or `(same_side ...)`; `None` for other lists.
+    fn from_list(list: &List) -> Result<Option<Self>> {
+        Ok(match list.tag() {
+            "near" => Some(Relation::Near {
+                reference: list.string(0)?,
+                pad: list.child_string("pad")?,
+                max: list.child_number("max")?.ok_or_else(|| list.error("missing (max ...)"))?,
+            }),
+            "same_side" => Some(Relation::SameSide { reference: list.string(0)? }),
+            _ => None,
+        })
+    }
+}
+
+/// One `(constraint ...)`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlacementConstraint {
+    pub name: Option<String>,
+    /// References of the constrained footprints, with `*` and `?`
+    /// wildcards.
+    pub footprints: Vec<String>,
+    pub relation: Relation,
+}
+
+impl PlacementConstraint {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let footprints = self.footprints.iter().fold(SexpBuilder::new("footprints"), |list, f| list.string(f));
+        let constraint = SexpBuilder::new("constraint");
+        let constraint = match &self.name {
+            Some(name) => constraint.string(name),
+            None => constraint,
+        };
+        constraint.child(footprints).child(self.relation.to_sexp())
+    }
+
+    /// The constraint's name, or else what it says, for messages.
+    fn label(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let relation = match &self.relation {
+            Relation::Near { reference, pad: Some(pad), .. } => format!("near {} pad {}", reference, pad),
+            Relation::Near { reference, pad: None, .. } => format!("near {}", reference),
+            Relation::SameSide { reference } => format!("same side as {}", reference),
+        };
+        format!("{} {}", self.footprints.join(" "), relation)
+    }
+}
+
+impl FromSexp for PlacementConstraint {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let footprints = match list.child("footprints") {
+            Some(footprints) => (0..footprints.args().len()).map(|i| footprints.string(i)).collect::<Result<_>>(),
+            None => return Err(list.error("missing (footprints ...)")),
+        };
+        let mut relations = Vec::new();
+        for child in list.lists() {
+            relations.extend(Relation::from_list(&child).map_err(|e| e.within(list.tag()))?);
+        }
+        let relation = match <[Relation; 1]>::try_from(relations) {
+            Ok([relation]) => relation,
+            Err(_) => return Err(list.error("expected one (near ...) or (same_side ...)")),
+        };
+        let name = list.args().first().and_then(|arg| arg.as_str()).map(|name| name.into_owned());
+        Ok(PlacementConstraint { name, footprints: footprints.map_err(|e| e.within(list.tag()))?, relation })
+    }
+}
+
+/// A sidecar file of placement constraints.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlacementConstraints {
+    pub version: u32,
+    /// In file order.
+    pub constraints: Vec<PlacementConstraint>,
+}
+
+impl PlacementConstraints {
+    /// Newest file format version the model is written against.
+    pub const NEWEST_VERSION: u32 = 1;
+
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let sexps = kicad_sexp::parse(src).map_err(Error::Parse)?;
+        let mut constraints = PlacementConstraints::default();
+        for list in sexps.iter().filter_map(List::new) {
+            match list.tag() {
+                "version" => constraints.version = list.number(0)?,
+                "constraint" => constraints.constraints.push(PlacementConstraint::from_sexp(&list)?),
+                tag => return Err(ConvertError::new(format!("unexpected ({} ...)", tag)).into()),
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// The constraints written on the board's footprints in the field
+    /// `field`, each applying to the footprint it is on.
+    pub fn from_properties(board: &Board, field: &str) -> std::result::Result<Self, Error> {
+        let mut constraints = PlacementConstraints { version: Self::NEWEST_VERSION, constraints: Vec::new() };
+        for footprint in &board.footprints {
+            let (Some(value), Some(reference)) = (footprint.property(field), footprint.reference()) else {
+                continue;
+            };
+            let in_field = |message: String| ConvertError::new(format!("{} of {}: {}", field, reference, message));
+            let sexps = kicad_sexp::parse(value).map_err(|errors| in_field(errors[0].to_string()))?;
+            for list in sexps.iter().filter_map(List::new) {
+                let relation = Relation::from_list(&list).map_err(|e| in_field(e.to_string()))?;
+                let relation = relation.ok_or_else(|| in_field(format!("unknown relation ({} ...)", list.tag())))?;
+                constraints.constraints.push(PlacementConstraint {
+                    name: None,
+                    footprints: vec![reference.to_string()],
+                    relation,
+                });
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// An issue for each constraint the board breaks, and for each that
+    /// names a footprint or pad the board does not have.
+    pub fn check(&self, board: &Board) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        for constraint in &self.constraints {
+            let mut issue = |message: String| {
+                let path = vec!["constraint".to_string()];
+                issues.push(Issue { path, message: format!("{}: {}", constraint.label(), message) });
+            };
+            let reference = constraint.relation.reference();
+            let Some(other) = board.footprint(reference) else {
+                issue(format!("no footprint {} on the board", reference));
+                continue;
+            };
+            let subjects: Vec<&Footprint> = board
+                .footprints
+                .iter()
+                .filter(|footprint| {
+                    let name = footprint.reference().unwrap_or_default();
+                    name != reference && constraint.footprints.iter().any(|pattern| wildcard_match(pattern, name))
+                })
+                .collect();
+            if subjects.is_empty() {
+                issue("matches no footprint".to_string());
+            }
+            match &constraint.relation {
+                Relation::Near { pad, max, .. } => {
+                    let targets = match pad {
+                        Some(number) => {
+                            let pads = other.pads.iter().filter(|pad| &pad.number == number);
+                            let targets: Vec<_> = pads.map(|pad| other.pad_position(pad)).collect();
+                            if targets.is_empty() {
+                                issue(format!("{} has no pad {}", reference, number));
+                                continue;
+                            }
+                            targets
+                        },
+                        None => anchors(other),
+                    };
+                    for subject in subjects {
+                        let gap = anchors(subject)
+                            .into_iter()
+                            .flat_map(|a| targets.iter().map(move |&b| distance(a, b)))
+                            .fold(f64::INFINITY, f64::min);
+                        if gap > *max + 1e-9 {
+                            let to = pad.as_ref().map(|pad| format!(" pad {}", pad)).unwrap_or_default();
+                            issue(format!(
+                                "{} is {} mm from {}{}, more than {} mm",
+                                subject.reference().unwrap_or_default(),
+                                format_float((gap * 1000.0).round() / 1000.0),
+                                reference,
+                                to,
+                                format_float(*max)
+                            ));
+                        }
+                    }
+                },
+                Relation::SameSide { .. } => {
+                    for subject in subjects.into_iter().filter(|subject| subject.side() != other.side()) {
+                        issue(format!(
+                            "{} is on the {} side, {} on the {}",
+                            subject.reference().unwrap_or_default(),
+                            side_name(subject.side()),
+                            reference,
+                            side_name(other.side())
+                        ));
+                    }
+                },
+            }
+        }
+        issues
+    }
+}
+
+impl fmt::Display for PlacementConstraints {
+    /// The sidecar file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", SexpBuilder::new("version").int(self.version.into()).build())?;
+        for constraint in &self.constraints {
+            writeln!(f, "{}", constraint.to_sexp().build())?;
+        }
+        Ok(())
+    }
+}
+
+/// The points a footprint is measured from: its pad centers, or its origin.
+fn anchors(footprint: &Footprint) -> Vec<kicad_common::Point> {
+    match footprint.pads.is_empty() {
+        true => vec![footprint.at.point()],
+        false => footprint.pads.iter().map(|pad| footprint.pad_position(pad)).collect(),
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Top => "top",
+        Side::Bottom => "bottom",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn sidecar() {
+        let board = Board::parse(DEMO).unwrap();
+        let src = "(version 1)\n\
+                   (constraint \"input\" (footprints \"R1\") (near \"J1\" (pad \"1\") (max 6)))\n\
+                   (constraint (footprints \"R?\") (near \"J1\" (pad \"1\") (max 6)))\n\
+                   (constraint \"decoupling\" (footprints \"C*\") (same_side \"R1\"))\n\
+                   (constraint (footprints \"H9\") (same_side \"R1\"))\n\
+                   (constraint (footprints \"R1\") (near \"J1\" (pad \"7\") (max 1)))\n";
+        let constraints = PlacementConstraints::parse(src).unwrap();
+        assert_eq!(constraints.constraints.len(), 5);
+        assert_eq!(
+            constraints.constraints[0].relation,
+            Relation::Near { reference: "J1".to_string(), pad: Some("1".to_string()), max: 6.0 }
+        );
+        let messages: Vec<String> = constraints.check(&board).into_iter().map(|issue| issue.message).collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert_eq!(messages[0], "R? near J1 pad 1: R2 is 7.196 mm from J1 pad 1, more than 6 mm");
+        assert_eq!(messages[1], "decoupling: C1 is on the bottom side, R1 on the top");
+        assert_eq!(messages[2], "H9 same side as R1: matches no footprint");
+        assert_eq!(messages[3], "R1 near J1 pad 7: J1 has no pad 7");
+
+        let written = constraints.to_string();
+        assert!(written.starts_with("(version 1)\n(constraint \"input\"\n"), "{}", written);
+        assert_eq!(PlacementConstraints::parse(&written).unwrap(), constraints);
+
+        assert!(PlacementConstraints::parse("(constraint (footprints \"R1\"))").is_err());
+        assert!(PlacementConstraints::parse("(constraint (near \"J1\" (max 1)))").is_err());
+    }
+
+    #[test]
+    fn properties() {
+        let mut board = Board::parse(DEMO).unwrap();
+        let r2 = board.footprints.iter_mut().find(|footprint| footprint.reference() == Some("R2")).unwrap();
+        let mut field = r2.properties[0].clone();
+        (field.key, field.value) = ("Placement".to_string(), "(near \"R1\" (max 6)) (same_side \"C1\")".to_string());
+        r2.properties.push(field);
+        let constraints = PlacementConstraints::from_properties(&board, "Placement").unwrap();
+        assert_eq!(constraints.constraints.len(), 2);
+        assert_eq!(constraints.constraints[0].footprints, ["R2"]);
+        let messages: Vec<String> = constraints.check(&board).into_iter().map(|issue| issue.message).collect();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert_eq!(messages[0], "R2 same side as C1: R2 is on the top side, C1 on the bottom");
+
+        let r2 = board.footprints.iter_mut().find(|footprint| footprint.reference() == Some("R2")).unwrap();
+        r2.properties.last_mut().unwrap().value = "(inside \"R1\")".to_string();
+        let error = PlacementConstraints::from_properties(&board, "Placement").unwrap_err();
+        assert_eq!(error.to_string(), "Placement of R2: unknown relation (inside ...)");
+    }
+}
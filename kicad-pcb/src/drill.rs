@@ -0,0 +1,393 @@
+//! The holes of a board grouped the way drill files are split: plated holes
+//! through the whole board in one set, the vias of each blind, buried or
+//! micro via span in a set of their own, and non-plated holes apart.
+//!
+//! Positions are in board coordinates; [`drill_files`] writes the spans as
+//! Excellon files relative to an origin, as KiCad's drill file generator
+//! does, and [`drill_report`] counts their holes the way KiCad's drill
+//! report does.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use kicad_common::{Point, Size};
+
+use crate::{
+    placement::{Origin, Units},
+    Board, Layer, PadKind, ViaKind,
+};
+
+/// What made a hole.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HoleKind {
+    Via(ViaKind),
+    Pad,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hole {
+    pub kind: HoleKind,
+    pub at: Point,
+    /// Both dimensions are the diameter for round holes; oval holes are
+    /// slots along the longer one.
+    pub size: Size,
+    /// Rotation of oval holes in degrees, counter-clockwise.
+    pub angle: f64,
+}
+
+/// The holes drilled between two copper layers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrillSpan {
+    pub front: Layer,
+    pub back: Layer,
+    pub plated: bool,
+    /// In board order: pads by footprint, then vias.
+    pub holes: Vec<Hole>,
+}
+
+impl DrillSpan {
+    pub fn is_through(&self) -> bool {
+        (self.front, self.back) == (Layer::FCu, Layer::BCu)
+    }
+
+    /// The part KiCad adds to the board name for the span's drill file:
+    /// `PTH`, `NPTH`, or the span's layers as in `front-in1` or `in2-back`.
+    pub fn file_suffix(&self) -> String {
+        let name = |layer: Layer| match layer {
+            Layer::FCu => "front".to_string(),
+            Layer::BCu => "back".to_string(),
+            Layer::InCu(n) => format!("in{}", n),
+            other => other.to_string(),
+        };
+        match (self.plated, self.is_through()) {
+            (false, _) => "NPTH".to_string(),
+            (true, true) => "PTH".to_string(),
+            (true, false) => format!("{}-{}", name(self.front), name(self.back)),
+        }
+    }
+
+    pub fn count(&self, kind: HoleKind) -> usize {
+        self.holes.iter().filter(|hole| hole.kind == kind).count()
+    }
+
+    /// The distinct hole diameters, smallest first, as drill tools.
+    pub fn tools(&self) -> Vec<f64> {
+        let mut tools: Vec<f64> = self.holes.iter().map(Hole::diameter).collect();
+        tools.sort_by(f64::total_cmp);
+        tools.dedup();
+        tools
+    }
+
+    /// How many holes each of [`DrillSpan::tools`] drills, and how many of
+    /// those are slots.
+    pub fn tool_counts(&self) -> Vec<ToolCount> {
+        self.tools()
+            .into_iter()
+            .map(|diameter| {
+                let holes: Vec<&Hole> = self.holes.iter().filter(|hole| hole.diameter() == diameter).collect();
+                let slots = holes.iter().filter(|hole| hole.is_slot()).count();
+                ToolCount { diameter, holes: holes.len(), slots }
+            })
+            .collect()
+    }
+
+    /// The span as an Excellon drill file in KiCad's decimal format, with
+    /// positions relative to `origin` and Y pointing up. The file function
+    /// numbers the span's layers by their place in `copper_layers`.
+    pub fn to_excellon(&self, origin: Point, units: Units, copper_layers: &[Layer]) -> String {
+        let index = |layer: Layer| copper_layers.iter().position(|&l| l == layer).map_or(0, |i| i + 1);
+        let (from, to) = (index(self.front), index(self.back));
+        let outer = self.front == Layer::FCu || self.back == Layer::BCu;
+        let function = match (self.plated, self.is_through()) {
+            (false, _) => format!("NonPlated,{},{},NPTH", from, to),
+            (true, true) => format!("Plated,{},{},PTH", from, to),
+            (true, false) => format!("Plated,{},{},{}", from, to, if outer { "Blind" } else { "Buried" }),
+        };
+        let (unit, name) = match units {
+            Units::Millimeters => ("metric", "METRIC"),
+            Units::Inches => ("inch", "INCH"),
+        };
+        let mut out = String::from("M48\n");
+        let _ = writeln!(out, "; FORMAT={{-:-/ absolute / {} / decimal}}", unit);
+        let _ = writeln!(out, "; #@! TF.FileFunction,{}", function);
+        let _ = writeln!(out, "FMAT,2\n{}", name);
+        let tools = self.tools();
+        for (i, diameter) in tools.iter().enumerate() {
+            let _ = writeln!(out, "T{}C{:.*}", i + 1, precision(units), units.convert(*diameter));
+        }
+        out.push_str("%\nG90\nG05\n");
+        let xy = |p: Point| {
+            let (x, y) = (units.convert(p.x - origin.x), units.convert(origin.y - p.y));
+            format!("X{}Y{}", coordinate(x), coordinate(y))
+        };
+        for (i, diameter) in tools.iter().enumerate() {
+            let _ = writeln!(out, "T{}", i + 1);
+            for hole in self.holes.iter().filter(|hole| hole.diameter() == *diameter) {
+                match hole.slot() {
+                    Some((start, end)) => {
+                        let _ = writeln!(out, "{}G85{}", xy(start), xy(end));
+                    },
+                    None => {
+                        let _ = writeln!(out, "{}", xy(hole.at));
+                    },
+                }
+            }
+        }
+        out.push_str("M30\n");
+        out
+    }
+}
+
+impl Hole {
+    pub fn diameter(&self) -> f64 {
+        self.size.width.min(self.size.height)
+    }
+
+    pub fn is_slot(&self) -> bool {
+        self.size.width != self.size.height
+    }
+
+    /// The centers of the two ends of an oval hole.
+    fn slot(&self) -> Option<(Point, Point)> {
+        if !self.is_slot() {
+            return None;
+        }
+        let half = (self.size.width - self.size.height).abs() / 2.0;
+        let along = match self.size.width > self.size.height {
+            true => Point::new(half, 0.0),
+            false => Point::new(0.0, half),
+        };
+        let along = along.rotated(self.angle);
+        Some((self.at + Point::new(-along.x, -along.y), self.at + along))
+    }
+}
+
+/// Holes of one diameter in a span.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToolCount {
+    pub diameter: f64,
+    pub holes: usize,
+    /// How many of the holes are oval.
+    pub slots: usize,
+}
+
+/// Digits after the decimal point of tool sizes.
+fn precision(units: Units) -> usize {
+    match units {
+        Units::Millimeters => 3,
+        Units::Inches => 4,
+    }
+}
+
+/// A coordinate with a decimal point, which Excellon readers need to not
+/// apply the implied decimals of integer coordinates.
+fn coordinate(value: f64) -> String {
+    let value = format!("{:.6}", value);
+    let value = value.trim_end_matches('0');
+    let value = match value.strip_suffix('.') {
+        Some(integer) => format!("{}.0", integer),
+        None => value.to_string(),
+    };
+    match value.as_str() {
+        "-0.0" => "0.0".to_string(),
+        _ => value,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrillOptions {
+    pub units: Units,
+    pub origin: Origin,
+}
+
+/// One drill file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrillFile {
+    /// `name` given to [`drill_files`] with KiCad's suffix for the span,
+    /// e.g. `board-PTH.drl` or `board-front-in1.drl`.
+    pub name: String,
+    pub span: DrillSpan,
+    pub contents: String,
+}
+
+/// The Excellon files of `board`, one per span of [`drill_spans`], named
+/// after `name` as KiCad names them.
+pub fn drill_files(board: &Board, name: &str, options: &DrillOptions) -> Vec<DrillFile> {
+    let origin = match options.origin {
+        Origin::Absolute => Point::default(),
+        Origin::AuxAxis => board.setup.aux_axis_origin.unwrap_or_default(),
+    };
+    let copper_layers = board.copper_layers();
+    drill_spans(board)
+        .into_iter()
+        .map(|span| DrillFile {
+            name: format!("{}-{}.drl", name, span.file_suffix()),
+            contents: span.to_excellon(origin, options.units, &copper_layers),
+            span,
+        })
+        .collect()
+}
+
+/// A report of the holes in `files` by tool, as in KiCad's drill report:
+/// for each file, the holes of each diameter and the total count.
+pub fn drill_report(board_name: &str, files: &[DrillFile]) -> String {
+    let mut out = format!("Drill report for {}\n", board_name);
+    for file in files {
+        let span = &file.span;
+        let kind = match (span.plated, span.is_through()) {
+            (false, _) => "unplated through holes".to_string(),
+            (true, true) => "plated through holes".to_string(),
+            (true, false) => format!("holes from {} to {}", span.front, span.back),
+        };
+        let _ = write!(out, "\nDrill file '{}' contains\n    {}:\n    {}\n", file.name, kind, "=".repeat(61));
+        for (i, tool) in span.tool_counts().iter().enumerate() {
+            let (mm, inches) = (tool.diameter, tool.diameter / 25.4);
+            let _ = write!(out, "    T{}  {:.2}mm  {:.3}\"  ({} holes)", i + 1, mm, inches, tool.holes);
+            match tool.slots {
+                0 => out.push('\n'),
+                slots => {
+                    let _ = writeln!(out, "  (with {} slots)", slots);
+                },
+            }
+        }
+        let plated = if span.plated { "plated" } else { "unplated" };
+        let vias = span.holes.iter().filter(|hole| matches!(hole.kind, HoleKind::Via(_))).count();
+        let vias = if span.plated { format!(", vias {}", vias) } else { String::new() };
+        let _ = writeln!(out, "\n    Total {} holes count {}{}", plated, span.holes.len(), vias);
+    }
+    out
+}
+
+/// The board's holes by span: the plated through holes first, then the
+/// other plated spans front to back, then the non-plated holes. Spans
+/// without holes are left out. Vias that do not list two layers are taken
+/// as through vias; [`Board::check_vias`] reports them.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(spans)))]
+pub fn drill_spans(board: &Board) -> Vec<DrillSpan> {
+    let through = (Layer::FCu, Layer::BCu);
+    let mut plated: BTreeMap<(bool, Layer, Layer), Vec<Hole>> = BTreeMap::new();
+    let mut unplated = Vec::new();
+    for footprint in &board.footprints {
+        for pad in &footprint.pads {
+            let Some(drill) = pad.drill.filter(|drill| drill.size.width > 0.0) else {
+                continue;
+            };
+            let hole = Hole {
+                kind: HoleKind::Pad,
+                at: footprint.pad_position(pad) + drill.offset.rotated(pad.at.angle),
+                size: drill.size,
+                angle: pad.at.angle,
+            };
+            match pad.kind {
+                PadKind::ThruHole => plated.entry((false, through.0, through.1)).or_default().push(hole),
+                PadKind::NpThruHole => unplated.push(hole),
+                PadKind::Smd | PadKind::Connect => {},
+            }
+        }
+    }
+    for via in &board.vias {
+        let (front, back) = via.span().unwrap_or(through);
+        // The through span sorts first.
+        let key = ((front, back) != through, front, back);
+        plated.entry(key).or_default().push(Hole {
+            kind: HoleKind::Via(via.kind),
+            at: via.at,
+            size: Size::new(via.drill, via.drill),
+            angle: 0.0,
+        });
+    }
+    let mut spans: Vec<DrillSpan> = plated
+        .into_iter()
+        .map(|((_, front, back), holes)| DrillSpan { front, back, plated: true, holes })
+        .collect();
+    if !unplated.is_empty() {
+        spans.push(DrillSpan { front: through.0, back: through.1, plated: false, holes: unplated });
+    }
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("spans", spans.len());
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn demo_board() {
+        let board = Board::parse(DEMO).unwrap();
+        let spans = drill_spans(&board);
+        let suffixes: Vec<_> = spans.iter().map(DrillSpan::file_suffix).collect();
+        assert_eq!(suffixes, ["PTH", "NPTH"]);
+        assert_eq!((spans[0].count(HoleKind::Pad), spans[0].count(HoleKind::Via(ViaKind::Through))), (3, 2));
+        assert_eq!(spans[0].tools(), [0.3, 1.0]);
+        assert_eq!(spans[0].holes[3].at, Point::new(115.0, 102.0));
+        assert_eq!((spans[1].holes.len(), spans[1].tools()), (2, vec![3.2]));
+    }
+
+    #[test]
+    fn demo_files() {
+        let board = Board::parse(DEMO).unwrap();
+        let files = drill_files(&board, "demo", &DrillOptions { origin: Origin::AuxAxis, ..Default::default() });
+        let names: Vec<_> = files.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["demo-PTH.drl", "demo-NPTH.drl"]);
+
+        let pth = &files[0].contents;
+        assert!(pth.starts_with("M48\n; FORMAT={-:-/ absolute / metric / decimal}\n"));
+        assert!(pth.contains("; #@! TF.FileFunction,Plated,1,2,PTH\nFMAT,2\nMETRIC\n"));
+        assert!(pth.contains("\nT1C0.300\nT2C1.000\n%\nG90\nG05\nT1\n"));
+        // The via at (115, 102) from the origin at (100, 120).
+        assert!(pth.contains("\nX15.0Y18.0\n"));
+        assert!(pth.ends_with("M30\n"));
+        assert!(files[1].contents.contains("; #@! TF.FileFunction,NonPlated,1,2,NPTH\n"));
+
+        let inches = drill_files(&board, "demo", &DrillOptions { units: Units::Inches, ..Default::default() });
+        assert!(inches[0].contents.contains("\nINCH\nT1C0.0118\n"));
+
+        let report = drill_report("demo.kicad_pcb", &files);
+        assert!(report.starts_with("Drill report for demo.kicad_pcb\n\nDrill file 'demo-PTH.drl' contains\n"));
+        assert!(report.contains("    T1  0.30mm  0.012\"  (2 holes)\n    T2  1.00mm  0.039\"  (3 holes)\n"));
+        assert!(report.contains("    Total plated holes count 5, vias 2\n"));
+        assert!(report.contains("    unplated through holes:\n"));
+    }
+
+    #[test]
+    fn slots() {
+        let hole = Hole { kind: HoleKind::Pad, at: Point::new(10.0, 10.0), size: Size::new(1.0, 3.0), angle: 90.0 };
+        let span = DrillSpan { front: Layer::FCu, back: Layer::BCu, plated: true, holes: vec![hole] };
+        let excellon = span.to_excellon(Point::default(), Units::Millimeters, &[Layer::FCu, Layer::BCu]);
+        assert!(excellon.contains("\nT1C1.000\n"));
+        assert!(excellon.contains("\nX9.0Y-10.0G85X11.0Y-10.0\n"), "{}", excellon);
+        assert_eq!(span.tool_counts(), [ToolCount { diameter: 1.0, holes: 1, slots: 1 }]);
+    }
+
+    #[test]
+    fn via_spans() {
+        let layers = "(layers\n\t\t(0 \"F.Cu\" signal)\n\t\t(4 \"In1.Cu\" signal)\n\t\t(6 \"In2.Cu\" signal)";
+        let src = DEMO.replacen("(layers\n\t\t(0 \"F.Cu\" signal)", layers, 1);
+        let src = src
+            .replacen("(via\n", "(via micro\n", 1)
+            .replacen("(layers \"F.Cu\" \"B.Cu\")", "(layers \"F.Cu\" \"In1.Cu\")", 1)
+            .replacen("(via\n", "(via blind\n", 1)
+            .replacen("(layers \"F.Cu\" \"B.Cu\")", "(layers \"In2.Cu\" \"In1.Cu\")", 1);
+        let board = Board::parse(&src).unwrap();
+        assert_eq!(board.vias[0].kind, ViaKind::Micro);
+        assert_eq!(board.vias[1].span(), Some((Layer::InCu(1), Layer::InCu(2))));
+
+        let spans = drill_spans(&board);
+        let suffixes: Vec<_> = spans.iter().map(DrillSpan::file_suffix).collect();
+        assert_eq!(suffixes, ["PTH", "front-in1", "in1-in2", "NPTH"]);
+        assert_eq!(spans[0].count(HoleKind::Via(ViaKind::Through)), 0);
+        assert_eq!(spans[1].count(HoleKind::Via(ViaKind::Micro)), 1);
+        assert_eq!(spans[2].holes[0].at, Point::new(112.0, 110.0));
+
+        let files = drill_files(&board, "board", &DrillOptions::default());
+        let names: Vec<_> = files.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["board-PTH.drl", "board-front-in1.drl", "board-in1-in2.drl", "board-NPTH.drl"]);
+        assert!(files[1].contents.contains("; #@! TF.FileFunction,Plated,1,2,Blind\n"));
+        assert!(files[2].contents.contains("; #@! TF.FileFunction,Plated,2,3,Buried\n"));
+        let report = drill_report("board.kicad_pcb", &files);
+        assert!(report.contains("'board-front-in1.drl' contains\n    holes from F.Cu to In1.Cu:\n"));
+        assert!(report.contains("    Total plated holes count 1, vias 1\n"));
+    }
+}
@@ -0,0 +1,96 @@
+//! Pulls one kind of item out of a board file without parsing the rest, for
+//! tools that need a single piece of data from large boards quickly.
+//!
+//! Only the extracted lists are parsed and validated; a file that fails
+//! [`Board::parse`](crate::Board::parse) elsewhere can still extract fine.
+
+use kicad_common::{
+    convert::{parse_child, parse_children, scan_document, FromSexp, List, Result},
+    Error,
+};
+
+use crate::{Layer, Net, Track};
+
+/// The board's net table.
+pub fn extract_nets_only(src: &str) -> std::result::Result<Vec<Net>, Error> {
+    parse_children(src, "kicad_pcb", &["net"])
+}
+
+/// The reference designator in a `property` or `fp_text` list, if it is one.
+struct Reference(Option<String>);
+
+impl FromSexp for Reference {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let is_reference = match list.tag() {
+            "fp_text" => list.symbol(0)? == "reference",
+            _ => list.string(0)? == "Reference",
+        };
+        Ok(Reference(if is_reference { Some(list.string(1)?) } else { None }))
+    }
+}
+
+fn within(tag: &str) -> impl Fn(Error) -> Error + '_ {
+    move |e| match e {
+        Error::Convert(e) => Error::Convert(e.within(tag)),
+        e => e,
+    }
+}
+
+/// Reference designators of all footprints, in file order. Footprints
+/// without one are skipped.
+pub fn extract_footprint_refs_only(src: &str) -> std::result::Result<Vec<String>, Error> {
+    let mut references = Vec::new();
+    for footprint in scan_document(src, "kicad_pcb")?.filter(|child| matches!(child.tag, "footprint" | "module")) {
+        for item in footprint.children().filter(|child| matches!(child.tag, "property" | "fp_text")) {
+            let Reference(reference) = parse_child(&item, footprint.tag).map_err(within("kicad_pcb"))?;
+            if let Some(reference) = reference {
+                references.push(reference);
+                break;
+            }
+        }
+    }
+    Ok(references)
+}
+
+/// Segments and arcs on `layer`. Tracks on other layers are skipped after
+/// reading only their `(layer ...)`.
+pub fn extract_tracks_on(src: &str, layer: Layer) -> std::result::Result<Vec<Track>, Error> {
+    let mut tracks = Vec::new();
+    for track in scan_document(src, "kicad_pcb")?.filter(|child| matches!(child.tag, "segment" | "arc")) {
+        if let Some(item) = track.children().find(|child| child.tag == "layer") {
+            let on: Layer = parse_child(&item, track.tag).map_err(within("kicad_pcb"))?;
+            if on != layer {
+                continue;
+            }
+        }
+        tracks.push(parse_child(&track, "kicad_pcb")?);
+    }
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Board;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn extract() {
+        let board = Board::parse(DEMO).unwrap();
+
+        assert_eq!(extract_nets_only(DEMO).unwrap(), board.nets);
+        let references: Vec<_> = board.footprints.iter().filter_map(|footprint| footprint.reference()).collect();
+        assert_eq!(extract_footprint_refs_only(DEMO).unwrap(), references);
+        let front: Vec<_> = board.tracks.iter().filter(|track| track.layer == Layer::FCu).cloned().collect();
+        assert_eq!(extract_tracks_on(DEMO, Layer::FCu).unwrap(), front);
+
+        let broken = "(kicad_pcb (net 0 \"\") (net 1 \"GND\") (footprint \"R\" (pad oops)))";
+        assert_eq!(extract_nets_only(broken).unwrap().len(), 2);
+        assert!(Board::parse(broken).is_err());
+
+        let error = extract_nets_only("(kicad_pcb (net x))").unwrap_err();
+        assert!(matches!(error, Error::Convert(e) if e.path == ["kicad_pcb", "net"]));
+        assert!(extract_nets_only("(kicad_sch)").is_err());
+    }
+}
@@ -0,0 +1,1114 @@
+//! Zone filling: the copper of each zone computed from its outline and the
+//! items around it, as KiCad does when the board is refilled.
+//!
+//! A zone is clipped to its outline and to the board edge less the edge
+//! clearance. Pads, vias and tracks of other nets, the fills of zones with a
+//! higher priority and keepout rule areas are cut out, the copper with the
+//! zone's clearance around it. Pads of the zone's own net connect as its
+//! `connect_pads` says, through thermal reliefs by default, unless the pad
+//! has a `zone_connect` of its own. A relief is a gap around the pad with
+//! four spokes across it, at 45° for circular pads and 90° otherwise, and
+//! a spoke only stays if it reaches the fill. Parts narrower than the
+//! minimum thickness are dropped, and so are islands that reach no item of
+//! the zone's net, as the zone's [`IslandRemoval`] says.
+//!
+//! [`zone_islands`] finds the islands of the fills a board has, however
+//! they were filled, [`copper_coverage`] sums them up per layer and
+//! [`check_islands`] reports those connected to nothing.
+//! [`thermal_reliefs`] finds which spokes of each relief the fills
+//! actually have, and [`check_thermal_reliefs`] reports pads with too few.
+//!
+//! Trapezoid and custom pads are cut out as their bounding rectangle, and
+//! copper text and drawings are not cut out at all.
+//!
+//! ```no_run
+//! use kicad_pcb::{
+//!     fill::{fill_zones, write_fills, FillOptions},
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! fill_zones(&mut board, &FillOptions::default());
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_fills(&board, &mut doc)?;
+//! std::fs::write("demo.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{borrow::Cow, cmp::Reverse, f64::consts::PI};
+
+use i_overlay::{
+    core::{fill_rule::FillRule, overlay_rule::OverlayRule},
+    float::single::SingleFloatOverlay,
+    mesh::float::{
+        outline::offset::OutlineOffset,
+        style::{LineJoin, OutlineStyle},
+    },
+};
+use kicad_common::{ConvertError, Error, Point};
+use kicad_sexp::{
+    cst::{Document, Node},
+    number::format_float,
+    schema::Issue,
+};
+
+use crate::{
+    bounds::{bezier_points, circle_center, distance, map_points, polygon_contains, polygon_inside, snap},
+    layout::filled_polygon_node,
+    Board, FilledPolygon, Footprint, IslandRemoval, Layer, Pad, PadConnection, PadKind, PadShape, ShapeKind, Zone,
+};
+
+pub(crate) type Contour = Vec<[f64; 2]>;
+pub(crate) type Shapes = Vec<Vec<Contour>>;
+
+/// KiCad's values for zones that leave them out.
+const MIN_THICKNESS: f64 = 0.25;
+const THERMAL_GAP: f64 = 0.5;
+const THERMAL_BRIDGE_WIDTH: f64 = 0.5;
+
+/// KiCad's `min_resolved_spokes` default.
+pub const MIN_SPOKES: usize = 2;
+
+/// Largest distance between two `Edge.Cuts` ends that still join.
+const EDGE_TOLERANCE: f64 = 1e-3;
+
+/// Settings the board file does not carry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FillOptions {
+    /// Largest distance between an arc and the segments standing in for it.
+    /// Cut-outs are made that much larger rather than smaller.
+    pub max_error: f64,
+    /// Clearance for zones without one of their own.
+    pub clearance: f64,
+    /// Clearance between copper and the board edge.
+    pub edge_clearance: f64,
+    /// Whether to drop islands that reach no item of the zone's net, as the
+    /// zones' [`IslandRemoval`] says. Zones without a net keep theirs
+    /// either way.
+    pub remove_islands: bool,
+}
+
+impl Default for FillOptions {
+    /// KiCad's defaults: 5 µm arc error and 0.5 mm clearances.
+    fn default() -> Self {
+        FillOptions { max_error: 0.005, clearance: 0.5, edge_clearance: 0.5, remove_islands: true }
+    }
+}
+
+/// Refills every copper zone of the board, higher priority first so that
+/// lower priority zones flow around the new fills. Rule areas are left
+/// alone.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(zones, islands)))]
+pub fn fill_zones(board: &mut Board, options: &FillOptions) {
+    let mut order: Vec<usize> = (0..board.zones.len()).filter(|&i| board.zones[i].keepout.is_none()).collect();
+    order.sort_by_key(|&i| Reverse(board.zones[i].priority));
+    let edge = board_area(board, options.edge_clearance, options.max_error);
+    for &index in &order {
+        let fills = Filler::new(board, index, edge.as_ref(), options).fill();
+        let zone = &mut board.zones[index];
+        zone.filled_polygons = fills;
+        zone.filled = true;
+    }
+    #[cfg(feature = "tracing")]
+    tracing::Span::current()
+        .record("zones", order.len())
+        .record("islands", order.iter().map(|&i| board.zones[i].filled_polygons.len()).sum::<usize>());
+}
+
+/// Computes the fill of `board.zones[index]` on each of its copper layers,
+/// one polygon per island, without changing the board. The current fills
+/// of zones with a higher priority are cut out; rule areas get no fill.
+pub fn fill_zone(board: &Board, index: usize, options: &FillOptions) -> Vec<FilledPolygon> {
+    if board.zones[index].keepout.is_some() {
+        return Vec::new();
+    }
+    Filler::new(board, index, board_area(board, options.edge_clearance, options.max_error).as_ref(), options).fill()
+}
+
+/// One island of a zone's fill, as [`zone_islands`] finds it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Island {
+    /// Index into [`Board::zones`].
+    pub zone: usize,
+    /// Index into the zone's [`Zone::filled_polygons`].
+    pub polygon: usize,
+    pub layer: Layer,
+    /// In square millimeters, holes left out.
+    pub area: f64,
+    /// Whether the island touches a pad, via or track of the zone's net.
+    pub connected: bool,
+}
+
+/// The islands of the current fills of the copper zones. Unconnected ones
+/// are those that zones keep by their [`IslandRemoval`], or all of them
+/// for zones without a net, and those left by a fill whose connecting
+/// items were moved or deleted since.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(islands)))]
+pub fn zone_islands(board: &Board) -> Vec<Island> {
+    let max_error = FillOptions::default().max_error;
+    let mut islands = Vec::new();
+    for (index, zone) in board.zones.iter().enumerate().filter(|(_, zone)| zone.keepout.is_none()) {
+        for (polygon, fill) in zone.filled_polygons.iter().enumerate() {
+            let shape = vec![vec![fill.points.iter().map(|p| xy(*p)).collect()]];
+            let connected = zone.net != 0 && net_copper(board, zone.net, fill.layer, max_error).any(|copper| {
+                !overlay(&shape, &vec![vec![copper]], OverlayRule::Intersect).is_empty()
+            });
+            islands.push(Island { zone: index, polygon, layer: fill.layer, area: shape_area(&shape[0]), connected });
+        }
+    }
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("islands", islands.len());
+    islands
+}
+
+/// The outlines of the pads, vias and tracks of net `net` on `layer`.
+fn net_copper(board: &Board, net: u32, layer: Layer, max_error: f64) -> impl Iterator<Item = Contour> + '_ {
+    let pads = board.footprints.iter().flat_map(move |footprint| {
+        let pads = footprint.pads.iter().filter(move |pad| {
+            pad.on_layer(layer) && pad.net.as_ref().is_some_and(|pad_net| pad_net.number == net)
+        });
+        pads.map(move |pad| {
+            let place = |local: Point| footprint.pad_position(pad) + local.rotated(pad.at.angle);
+            pad_outline(pad, 0.0, max_error).into_iter().map(|p| xy(place(p))).collect()
+        })
+    });
+    let vias = board.vias.iter().filter(move |via| via.net == net && via.spans(layer));
+    let vias = vias.map(move |via| circle(via.at, via.size / 2.0, max_error));
+    let tracks = board.tracks.iter().filter(move |track| track.net == net && track.layer == layer);
+    let tracks = tracks.flat_map(move |track| {
+        let path = match track.mid {
+            Some(mid) => arc_points(track.start, mid, track.end, max_error),
+            None => vec![track.start, track.end],
+        };
+        let pieces: Vec<Contour> =
+            path.windows(2).map(|pair| capsule(pair[0], pair[1], track.width / 2.0, max_error)).collect();
+        pieces
+    });
+    pads.chain(vias).chain(tracks)
+}
+
+/// How much of a copper layer the zone fills cover.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Coverage {
+    pub layer: Layer,
+    /// The area inside the `Edge.Cuts` outline, in square millimeters;
+    /// `None` for boards without a closed outline.
+    pub board_area: Option<f64>,
+    /// The area of the zone fills on the layer.
+    pub copper: f64,
+    /// The part of `copper` in islands connected to nothing.
+    pub isolated: f64,
+}
+
+impl Coverage {
+    /// The share of the board the fills cover, from 0 to 1.
+    pub fn fraction(&self) -> Option<f64> {
+        self.board_area.filter(|area| *area > 0.0).map(|area| self.copper / area)
+    }
+}
+
+/// The zone fill coverage of each copper layer, front to back, from the
+/// board's current fills.
+pub fn copper_coverage(board: &Board) -> Vec<Coverage> {
+    let board_area =
+        board_area(board, 0.0, FillOptions::default().max_error).map(|area| area.iter().map(|s| shape_area(s)).sum());
+    let islands = zone_islands(board);
+    board
+        .copper_layers()
+        .into_iter()
+        .map(|layer| {
+            let on_layer = islands.iter().filter(|island| island.layer == layer);
+            let (copper, isolated) = on_layer.fold((0.0, 0.0), |(copper, isolated), island| {
+                (copper + island.area, if island.connected { isolated } else { isolated + island.area })
+            });
+            Coverage { layer, board_area, copper, isolated }
+        })
+        .collect()
+}
+
+/// An issue for each island of a zone with a net that connects to none of
+/// the net's items: copper that KiCad's DRC reports as an isolated island
+/// or a dangling zone fill.
+pub fn check_islands(board: &Board) -> Vec<Issue> {
+    zone_islands(board)
+        .into_iter()
+        .filter(|island| !island.connected && board.zones[island.zone].net != 0)
+        .map(|island| {
+            let zone = &board.zones[island.zone];
+            let name = zone.name.as_deref().unwrap_or(&zone.net_name);
+            let at = zone.filled_polygons[island.polygon].points.first().copied().unwrap_or_default();
+            let message = format!(
+                "zone {} has an island of {} mm² on {} at ({}, {}) connected to nothing",
+                name,
+                format_float((island.area * 1000.0).round() / 1000.0),
+                island.layer,
+                format_float(at.x),
+                format_float(at.y)
+            );
+            Issue { path: vec!["kicad_pcb".into(), "zone".into()], message }
+        })
+        .collect()
+}
+
+/// One spoke of a thermal relief.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spoke {
+    /// Where the spoke leaves the pad.
+    pub start: Point,
+    /// Where it meets the fill around the gap.
+    pub end: Point,
+    /// Whether the fill has copper across the gap there.
+    pub connected: bool,
+}
+
+/// The thermal relief of a pad in a zone's fill on one layer, as
+/// [`thermal_reliefs`] finds it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThermalRelief {
+    /// Index into [`Board::zones`].
+    pub zone: usize,
+    /// Index into [`Board::footprints`].
+    pub footprint: usize,
+    /// Index into the footprint's pads.
+    pub pad: usize,
+    pub layer: Layer,
+    pub gap: f64,
+    pub spoke_width: f64,
+    pub spokes: Vec<Spoke>,
+}
+
+impl ThermalRelief {
+    /// How many spokes connect the pad to the fill.
+    pub fn connected(&self) -> usize {
+        self.spokes.iter().filter(|spoke| spoke.connected).count()
+    }
+}
+
+/// The thermal reliefs of the pads inside the copper zones of their net, on
+/// each layer the zone has a fill on. A spoke counts as connected when the
+/// zone's current fill covers the middle of the gap along it, so this reads
+/// fills from KiCad and from [`fill_zones`] alike.
+pub fn thermal_reliefs(board: &Board) -> Vec<ThermalRelief> {
+    let max_error = FillOptions::default().max_error;
+    let mut reliefs = Vec::new();
+    for (index, zone) in board.zones.iter().enumerate() {
+        if zone.keepout.is_some() || zone.net == 0 || zone.outline.len() < 3 {
+            continue;
+        }
+        for (f, footprint) in board.footprints.iter().enumerate() {
+            for (p, pad) in footprint.pads.iter().enumerate() {
+                let center = footprint.pad_position(pad);
+                let same_net = pad.net.as_ref().is_some_and(|net| net.number == zone.net);
+                let Connection::Thermal { gap, spoke_width } = connection(zone, pad) else {
+                    continue;
+                };
+                if !same_net || !polygon_contains(&zone.outline, center) {
+                    continue;
+                }
+                for layer in board.copper_layers().into_iter().filter(|&layer| pad.on_layer(layer)) {
+                    let fills: Vec<Contour> = zone
+                        .filled_polygons
+                        .iter()
+                        .filter(|fill| fill.layer == layer)
+                        .map(|fill| fill.points.iter().map(|p| xy(*p)).collect())
+                        .collect();
+                    if fills.is_empty() {
+                        continue;
+                    }
+                    let place = |local: Point| center + local.rotated(pad.at.angle);
+                    let spokes = spoke_directions(pad, max_error)
+                        .map(|(direction, edge)| {
+                            let along = |t: f64| place(Point::new(direction.x * t, direction.y * t));
+                            let middle = xy(along(edge + gap / 2.0));
+                            let connected = fills.iter().any(|fill| contains(std::slice::from_ref(fill), middle));
+                            Spoke { start: along(edge), end: along(edge + gap), connected }
+                        })
+                        .collect();
+                    reliefs.push(ThermalRelief { zone: index, footprint: f, pad: p, layer, gap, spoke_width, spokes });
+                }
+            }
+        }
+    }
+    reliefs
+}
+
+/// An issue for each thermal relief with fewer than `min_spokes` spokes
+/// connected, [`MIN_SPOKES`] in KiCad: the pads KiCad's DRC reports as
+/// starved thermals.
+pub fn check_thermal_reliefs(board: &Board, min_spokes: usize) -> Vec<Issue> {
+    thermal_reliefs(board)
+        .into_iter()
+        .filter(|relief| relief.connected() < min_spokes)
+        .map(|relief| {
+            let (zone, footprint) = (&board.zones[relief.zone], &board.footprints[relief.footprint]);
+            let message = format!(
+                "pad {}.{} connects to zone {} on {} through {} of {} thermal spokes, fewer than {}",
+                footprint.reference().unwrap_or("?"),
+                footprint.pads[relief.pad].number,
+                zone.name.as_deref().unwrap_or(&zone.net_name),
+                relief.layer,
+                relief.connected(),
+                relief.spokes.len(),
+                min_spokes
+            );
+            Issue { path: vec!["kicad_pcb".into(), "footprint".into(), "pad".into()], message }
+        })
+        .collect()
+}
+
+/// How a pad of a zone's net connects to its fill.
+enum Connection {
+    None,
+    Solid,
+    Thermal { gap: f64, spoke_width: f64 },
+}
+
+/// The pad's own `zone_connect` and thermal settings, or else the zone's.
+fn connection(zone: &Zone, pad: &Pad) -> Connection {
+    let thermal = Connection::Thermal {
+        gap: pad.thermal_gap.or(zone.thermal_gap).unwrap_or(THERMAL_GAP),
+        spoke_width: pad.thermal_bridge_width.or(zone.thermal_bridge_width).unwrap_or(THERMAL_BRIDGE_WIDTH),
+    };
+    match pad.zone_connect.unwrap_or(zone.connect_pads) {
+        PadConnection::Solid => Connection::Solid,
+        PadConnection::None => Connection::None,
+        PadConnection::ThermalRelief => thermal,
+        PadConnection::ThruHoleOnly if pad.kind == PadKind::ThruHole => thermal,
+        PadConnection::ThruHoleOnly => Connection::Solid,
+    }
+}
+
+/// The directions of a pad's four thermal spokes relative to the pad, each
+/// with how far from the pad's center the spoke leaves it.
+fn spoke_directions(pad: &Pad, max_error: f64) -> impl Iterator<Item = (Point, f64)> {
+    let outline = pad_outline(pad, 0.0, max_error);
+    let angle = pad.spoke_angle();
+    (0..4).map(move |i| {
+        let direction = Point::new(1.0, 0.0).rotated(angle + 90.0 * f64::from(i));
+        (direction, ray_exit(&outline, direction))
+    })
+}
+
+/// How far along `direction` from the origin a ray leaves the convex
+/// `outline` around it.
+fn ray_exit(outline: &[Point], direction: Point) -> f64 {
+    let next = outline.iter().cycle().skip(1);
+    let hits = outline.iter().zip(next).filter_map(|(a, b)| {
+        let (ex, ey) = (b.x - a.x, b.y - a.y);
+        let denominator = direction.x * ey - direction.y * ex;
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let t = (a.x * ey - a.y * ex) / denominator;
+        let u = (a.x * direction.y - a.y * direction.x) / denominator;
+        (t >= 0.0 && (-1e-9..=1.0 + 1e-9).contains(&u)).then_some(t)
+    });
+    hits.fold(0.0, f64::max)
+}
+
+/// What the items on a layer take out of and add to a fill.
+#[derive(Default)]
+struct Items {
+    /// Other-net copper with its clearance, holes and keepouts.
+    cuts: Vec<Contour>,
+    /// Thermal relief gaps around pads of the zone's net.
+    gaps: Vec<Contour>,
+    spokes: Vec<Contour>,
+    /// Points on items of the zone's net, which keep the islands they are
+    /// in.
+    anchors: Vec<Point>,
+}
+
+struct Filler<'b> {
+    board: &'b Board,
+    index: usize,
+    zone: &'b Zone,
+    /// The board outline less the edge clearance, if it has one.
+    edge: Option<&'b Shapes>,
+    max_error: f64,
+    remove_islands: bool,
+    clearance: f64,
+    min_thickness: f64,
+}
+
+impl<'b> Filler<'b> {
+    fn new(board: &'b Board, index: usize, edge: Option<&'b Shapes>, options: &FillOptions) -> Self {
+        let zone = &board.zones[index];
+        Filler {
+            board,
+            index,
+            zone,
+            edge,
+            max_error: options.max_error,
+            remove_islands: options.remove_islands && zone.net != 0,
+            clearance: zone.clearance.unwrap_or(options.clearance),
+            min_thickness: zone.min_thickness.unwrap_or(MIN_THICKNESS),
+        }
+    }
+
+    fn fill(&self) -> Vec<FilledPolygon> {
+        if self.zone.outline.len() < 3 {
+            return Vec::new();
+        }
+        let layers = self.board.copper_layers().into_iter().filter(|&layer| self.zone.on_layer(layer));
+        layers
+            .flat_map(|layer| self.fill_layer(layer).into_iter().map(move |points| FilledPolygon { layer, points }))
+            .collect()
+    }
+
+    fn fill_layer(&self, layer: Layer) -> Vec<Vec<Point>> {
+        let mut area: Shapes = vec![vec![self.zone.outline.iter().map(|p| xy(*p)).collect()]];
+        if let Some(edge) = self.edge {
+            area = overlay(&area, edge, OverlayRule::Intersect);
+        }
+
+        let mut items = Items::default();
+        for footprint in &self.board.footprints {
+            for pad in &footprint.pads {
+                self.pad(footprint, pad, layer, &mut items);
+            }
+        }
+        let Items { mut cuts, gaps, spokes, mut anchors } = items;
+        for via in &self.board.vias {
+            if !via.spans(layer) {
+                continue;
+            }
+            if via.net == self.zone.net {
+                anchors.push(via.at);
+            } else {
+                cuts.push(circle(via.at, via.size / 2.0 + self.clearance, self.max_error));
+            }
+        }
+        for track in self.board.tracks.iter().filter(|track| track.layer == layer) {
+            if track.net == self.zone.net {
+                anchors.extend([track.start, track.end]);
+                continue;
+            }
+            let path = match track.mid {
+                Some(mid) => arc_points(track.start, mid, track.end, self.max_error),
+                None => vec![track.start, track.end],
+            };
+            let radius = track.width / 2.0 + self.clearance;
+            cuts.extend(path.windows(2).map(|pair| capsule(pair[0], pair[1], radius, self.max_error)));
+        }
+        for (i, other) in self.board.zones.iter().enumerate() {
+            if i == self.index || !other.on_layer(layer) {
+                continue;
+            }
+            match other.keepout {
+                Some(keepout) if keepout.copperpour => cuts.push(other.outline.iter().map(|p| xy(*p)).collect()),
+                Some(_) => {},
+                None if other.priority > self.zone.priority && other.net != self.zone.net => {
+                    let fills: Vec<Contour> = other
+                        .filled_polygons
+                        .iter()
+                        .filter(|fill| fill.layer == layer)
+                        .map(|fill| fill.points.iter().map(|p| xy(*p)).collect())
+                        .collect();
+                    if !fills.is_empty() {
+                        let grown = fills.outline(&self.offset(self.clearance));
+                        area = overlay(&area, &grown, OverlayRule::Difference);
+                    }
+                },
+                None => {},
+            }
+        }
+
+        let allowed = overlay(&area, &shapes(cuts), OverlayRule::Difference);
+        let mut area = self.drop_thin(overlay(&allowed, &shapes(gaps), OverlayRule::Difference));
+        // A spoke only stays if it reaches the fill around its pad.
+        let spokes: Vec<Contour> = spokes
+            .into_iter()
+            .filter_map(|spoke| {
+                let spoke = overlay(&vec![vec![spoke]], &allowed, OverlayRule::Intersect);
+                let reaches = !overlay(&spoke, &area, OverlayRule::Intersect).is_empty();
+                reaches.then_some(spoke)
+            })
+            .flatten()
+            .flatten()
+            .collect();
+        if !spokes.is_empty() {
+            area = overlay(&area, &shapes(spokes), OverlayRule::Union);
+        }
+        if self.remove_islands {
+            let keep = |island: &Vec<Contour>| match self.zone.island_removal {
+                IslandRemoval::Always => false,
+                IslandRemoval::Never => true,
+                IslandRemoval::BelowArea(min) => shape_area(island) >= min,
+            };
+            area.retain(|island| keep(island) || anchors.iter().any(|p| contains(island, xy(*p))));
+        }
+        area
+            .into_iter()
+            .map(|island| {
+                let mut points: Vec<Point> = fracture(island).into_iter().map(|[x, y]| Point::new(x, y)).collect();
+                // Below KiCad's nanometer resolution.
+                points.dedup_by(|a, b| distance(*a, *b) < 1e-6);
+                points
+            })
+            .collect()
+    }
+
+    /// Adds what `pad` does to the fill on `layer`.
+    fn pad(&self, footprint: &Footprint, pad: &Pad, layer: Layer, items: &mut Items) {
+        let place = |local: Point| footprint.pad_position(pad) + local.rotated(pad.at.angle);
+        let outline = |grow: f64| pad_outline(pad, grow, self.max_error).into_iter().map(|p| xy(place(p))).collect();
+        if pad.kind == PadKind::NpThruHole
+            && let Some(drill) = pad.drill
+        {
+            let (w, h) = (drill.size.width / 2.0 + self.clearance, drill.size.height / 2.0 + self.clearance);
+            let hole = rounded_rect(w, h, w.min(h), self.max_error);
+            items.cuts.push(hole.into_iter().map(|p| xy(place(p + drill.offset))).collect());
+        }
+        let same_net = self.zone.net != 0 && pad.net.as_ref().is_some_and(|net| net.number == self.zone.net);
+        if !pad.on_layer(layer) {
+            return;
+        }
+        if !same_net {
+            items.cuts.push(outline(self.clearance));
+            return;
+        }
+        let (gap, spoke_width) = match connection(self.zone, pad) {
+            Connection::None => {
+                items.cuts.push(outline(self.clearance));
+                return;
+            },
+            Connection::Solid => {
+                items.anchors.push(footprint.pad_position(pad));
+                return;
+            },
+            Connection::Thermal { gap, spoke_width } => (gap, spoke_width),
+        };
+        items.anchors.push(footprint.pad_position(pad));
+        items.gaps.push(outline(gap));
+        // From the pad's center, so that the spoke overlaps the pad.
+        for (direction, edge) in spoke_directions(pad, self.max_error) {
+            let reach = edge + gap + self.max_error;
+            let end = Point::new(direction.x * reach, direction.y * reach);
+            let spoke = rect_along(Point::default(), end, spoke_width / 2.0);
+            items.spokes.push(spoke.into_iter().map(|p| xy(place(p))).collect());
+        }
+    }
+
+    fn offset(&self, distance: f64) -> OutlineStyle<f64> {
+        OutlineStyle::new(distance).line_join(LineJoin::Round(arc_step(distance.abs(), self.max_error)))
+    }
+
+    /// Removes the parts of `area` narrower than the minimum thickness by
+    /// shrinking it by half of that and growing it back.
+    fn drop_thin(&self, area: Shapes) -> Shapes {
+        let half = self.min_thickness / 2.0;
+        if half <= 0.0 || area.is_empty() {
+            return area;
+        }
+        let grown = area.outline(&self.offset(-half)).outline(&self.offset(half));
+        overlay(&grown, &area, OverlayRule::Intersect)
+    }
+}
+
+/// The area inside the `Edge.Cuts` outlines less the edge clearance, `None`
+/// for boards without a closed outline.
+pub(crate) fn board_area(board: &Board, edge_clearance: f64, max_error: f64) -> Option<Shapes> {
+    let edges = board.shapes.iter().filter(|shape| shape.layer == Layer::EdgeCuts);
+    let board_shapes = edges.map(|shape| shape.kind.clone());
+    let footprint_shapes = board.footprints.iter().flat_map(|footprint| {
+        let edges = footprint.shapes.iter().filter(|shape| shape.layer == Layer::EdgeCuts);
+        edges.map(|shape| map_points(&shape.kind, |p| footprint.to_board(p)))
+    });
+    let mut loops: Vec<Vec<Point>> = Vec::new();
+    let mut pieces: Vec<Vec<Point>> = Vec::new();
+    for kind in board_shapes.chain(footprint_shapes) {
+        match kind {
+            ShapeKind::Line { start, end } => pieces.push(vec![start, end]),
+            ShapeKind::Arc { start, mid, end } => pieces.push(arc_points(start, mid, end, max_error)),
+            ShapeKind::Curve(points) => pieces.push(bezier_points(&points)),
+            ShapeKind::Rect { start, end } => {
+                loops.push(vec![start, Point::new(end.x, start.y), end, Point::new(start.x, end.y)])
+            },
+            ShapeKind::Circle { center, end } => {
+                let circle = circle(center, distance(center, end), max_error);
+                loops.push(circle.into_iter().map(|[x, y]| Point::new(x, y)).collect());
+            },
+            ShapeKind::Polygon(points) => loops.push(points),
+        }
+    }
+    loops.extend(chain(pieces));
+    if loops.is_empty() {
+        return None;
+    }
+    let contours: Vec<Contour> = loops.iter().map(|points| points.iter().map(|p| xy(*p)).collect()).collect();
+    let area = contours.overlay(&Vec::<Contour>::new(), OverlayRule::Subject, FillRule::EvenOdd);
+    let edges = loops.iter().flat_map(|points| {
+        let next = points.iter().cycle().skip(1);
+        points.iter().zip(next).map(|(a, b)| capsule(*a, *b, edge_clearance, max_error))
+    });
+    Some(overlay(&area, &shapes(edges.collect()), OverlayRule::Difference))
+}
+
+/// Joins open `Edge.Cuts` pieces end to end into closed loops, dropping
+/// anything that does not close.
+fn chain(mut pieces: Vec<Vec<Point>>) -> Vec<Vec<Point>> {
+    let mut loops = Vec::new();
+    while let Some(mut current) = pieces.pop() {
+        loop {
+            let (first, last) = (current[0], current[current.len() - 1]);
+            if current.len() > 2 && distance(first, last) < EDGE_TOLERANCE {
+                current.pop();
+                loops.push(current);
+                break;
+            }
+            let next = pieces.iter().position(|piece| {
+                distance(piece[0], last) < EDGE_TOLERANCE || distance(piece[piece.len() - 1], last) < EDGE_TOLERANCE
+            });
+            let Some(next) = next else {
+                break;
+            };
+            let mut piece = pieces.swap_remove(next);
+            if distance(piece[0], last) >= EDGE_TOLERANCE {
+                piece.reverse();
+            }
+            current.extend(piece.into_iter().skip(1));
+        }
+    }
+    loops
+}
+
+/// Joins each hole of `shape` to its outline by a zero-width cut along a
+/// horizontal line, giving the single outline KiCad stores.
+fn fracture(mut shape: Vec<Contour>) -> Contour {
+    let mut holes = shape.split_off(1);
+    let mut outline = shape.pop().unwrap_or_default();
+    if signed_area(&outline) < 0.0 {
+        outline.reverse();
+    }
+    let max_x = |contour: &Contour| contour.iter().map(|p| p[0]).fold(f64::MIN, f64::max);
+    holes.sort_by(|a, b| max_x(b).total_cmp(&max_x(a)));
+    for mut hole in holes {
+        if signed_area(&hole) > 0.0 {
+            hole.reverse();
+        }
+        let start = (0..hole.len()).max_by(|&a, &b| hole[a][0].total_cmp(&hole[b][0])).unwrap_or_default();
+        let m = hole[start];
+        // The nearest edge to the right, at the point where the line meets
+        // it; holes further right are part of the outline by now.
+        let mut best: Option<(f64, usize)> = None;
+        for i in 0..outline.len() {
+            let (p, q) = (outline[i], outline[(i + 1) % outline.len()]);
+            if (p[1] > m[1]) == (q[1] > m[1]) {
+                continue;
+            }
+            let x = p[0] + (m[1] - p[1]) * (q[0] - p[0]) / (q[1] - p[1]);
+            if x >= m[0] && best.is_none_or(|(best, _)| x < best) {
+                best = Some((x, i));
+            }
+        }
+        let Some((x, i)) = best else {
+            continue;
+        };
+        let bridge = [x, m[1]];
+        let loop_points = hole[start..].iter().chain(&hole[..=start]).copied();
+        let tail = outline.split_off(i + 1);
+        outline.push(bridge);
+        outline.extend(loop_points);
+        outline.push(bridge);
+        outline.extend(tail);
+    }
+    outline
+}
+
+/// Replaces the `filled_polygon` lists of the zones in `doc`, the file the
+/// board was parsed from, with the board's fills and marks them filled.
+/// Everything else in the file is written back as it was. Zones are matched
+/// to [`Board::zones`] in file order.
+pub fn write_fills(board: &Board, doc: &mut Document<'_>) -> Result<(), Error> {
+    let root = doc.nodes.iter_mut().find(|node| node.tag() == Some("kicad_pcb"));
+    let root = root.ok_or_else(|| ConvertError::new("expected a (kicad_pcb ...) root"))?;
+    let items = root.items_mut().into_iter().flatten();
+    let nodes: Vec<&mut Node<'_>> = items.filter(|item| item.tag() == Some("zone")).collect();
+    if nodes.len() != board.zones.len() {
+        let message = format!("the file has {} zones but the board {}", nodes.len(), board.zones.len());
+        return Err(ConvertError::new(message).into());
+    }
+    for (node, zone) in nodes.into_iter().zip(&board.zones) {
+        write_zone(node, zone);
+    }
+    Ok(())
+}
+
+fn write_zone(node: &mut Node<'_>, zone: &Zone) {
+    let indent = node.child("polygon").map_or(Cow::Borrowed("\n\t\t"), |polygon| polygon.leading.clone());
+    let Some(items) = node.items_mut() else {
+        return;
+    };
+    items.retain(|item| item.tag() != Some("filled_polygon"));
+    if zone.filled
+        && let Some(fill) = items.iter_mut().find(|item| item.tag() == Some("fill"))
+        && let Some(fill_items) = fill.items_mut()
+    {
+        match fill_items.get_mut(1) {
+            Some(flag) if matches!(flag.text(), Some("yes" | "no")) => flag.set_atom("yes"),
+            _ => fill_items.insert(1, Node::atom("yes")),
+        }
+    }
+    for polygon in &zone.filled_polygons {
+        items.push(filled_polygon_node(polygon, &indent));
+    }
+}
+
+/// The parts of `zone` inside the polygon `region`, each with the parts of
+/// the fills inside it; `zone` itself if it is inside the region already.
+pub(crate) fn clip_zone(zone: &Zone, region: &[Point]) -> Vec<Zone> {
+    if polygon_inside(&zone.outline, region) {
+        return vec![zone.clone()];
+    }
+    let contour = |points: &[Point]| -> Contour { points.iter().map(|p| xy(*p)).collect() };
+    let points = |shape: Vec<Contour>| -> Vec<Point> {
+        let mut points: Vec<Point> = fracture(shape).into_iter().map(|[x, y]| snap(Point::new(x, y))).collect();
+        points.dedup();
+        points
+    };
+    let parts = overlay(&vec![vec![contour(&zone.outline)]], &vec![vec![contour(region)]], OverlayRule::Intersect);
+    parts
+        .into_iter()
+        .map(|part| {
+            let part = vec![part];
+            let fills = zone.filled_polygons.iter().flat_map(|fill| {
+                let islands = overlay(&vec![vec![contour(&fill.points)]], &part, OverlayRule::Intersect);
+                islands.into_iter().map(|island| FilledPolygon { layer: fill.layer, points: points(island) })
+            });
+            let filled_polygons = fills.collect();
+            let outline = points(part.into_iter().flatten().collect());
+            Zone { outline, filled_polygons, ..zone.clone() }
+        })
+        .collect()
+}
+
+fn xy(p: Point) -> [f64; 2] {
+    [p.x, p.y]
+}
+
+pub(crate) fn overlay(subject: &Shapes, clip: &Shapes, rule: OverlayRule) -> Shapes {
+    subject.overlay(clip, rule, FillRule::NonZero)
+}
+
+/// Each contour as a shape of its own, wound the same way so that overlaps
+/// add up under the non-zero rule.
+pub(crate) fn shapes(contours: Vec<Contour>) -> Shapes {
+    contours
+        .into_iter()
+        .map(|mut contour| {
+            if signed_area(&contour) < 0.0 {
+                contour.reverse();
+            }
+            vec![contour]
+        })
+        .collect()
+}
+
+/// The area of an outline less its holes.
+fn shape_area(shape: &[Contour]) -> f64 {
+    let mut areas = shape.iter().map(|contour| signed_area(contour).abs());
+    let outline = areas.next().unwrap_or_default();
+    outline - areas.sum::<f64>()
+}
+
+fn signed_area(contour: &Contour) -> f64 {
+    let next = contour.iter().cycle().skip(1);
+    contour.iter().zip(next).map(|(a, b)| a[0] * b[1] - b[0] * a[1]).sum::<f64>() / 2.0
+}
+
+/// Even-odd test of `p` against an outline and its holes.
+pub(crate) fn contains(shape: &[Contour], p: [f64; 2]) -> bool {
+    let mut inside = false;
+    for contour in shape {
+        let next = contour.iter().cycle().skip(1);
+        for (a, b) in contour.iter().zip(next) {
+            if (a[1] > p[1]) != (b[1] > p[1]) && p[0] < (b[0] - a[0]) * (p[1] - a[1]) / (b[1] - a[1]) + a[0] {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Angle between the vertices of an arc of `radius` that strays at most
+/// `max_error` from it.
+fn arc_step(radius: f64, max_error: f64) -> f64 {
+    if radius <= max_error { PI / 2.0 } else { (2.0 * (1.0 - max_error / radius).acos()).min(PI / 2.0) }
+}
+
+/// Points of an arc around `center` from angle `start` through `sweep`,
+/// pushed out so that the segments stay outside the true arc.
+fn arc_around(center: Point, radius: f64, start: f64, sweep: f64, max_error: f64) -> impl Iterator<Item = Point> {
+    let steps = (sweep.abs() / arc_step(radius, max_error)).ceil().max(1.0) as usize;
+    let step = sweep / steps as f64;
+    let outer = radius / (step / 2.0).cos();
+    (0..=steps).map(move |i| {
+        let (sin, cos) = (start + step * i as f64).sin_cos();
+        Point::new(center.x + outer * cos, center.y + outer * sin)
+    })
+}
+
+pub(crate) fn circle(center: Point, radius: f64, max_error: f64) -> Contour {
+    let mut points: Contour = arc_around(center, radius, 0.0, 2.0 * PI, max_error).map(xy).collect();
+    points.pop();
+    points
+}
+
+/// The area within `radius` of the segment from `a` to `b`.
+pub(crate) fn capsule(a: Point, b: Point, radius: f64, max_error: f64) -> Contour {
+    if distance(a, b) < 1e-9 {
+        return circle(a, radius, max_error);
+    }
+    let angle = (b.y - a.y).atan2(b.x - a.x);
+    let far = arc_around(b, radius, angle - PI / 2.0, PI, max_error);
+    let near = arc_around(a, radius, angle + PI / 2.0, PI, max_error);
+    far.chain(near).map(xy).collect()
+}
+
+/// A rectangle of half-width `half` along the segment from `a` to `b`.
+fn rect_along(a: Point, b: Point, half: f64) -> Vec<Point> {
+    let length = distance(a, b);
+    let (nx, ny) = (-(b.y - a.y) / length * half, (b.x - a.x) / length * half);
+    vec![
+        Point::new(a.x - nx, a.y - ny),
+        Point::new(b.x - nx, b.y - ny),
+        Point::new(b.x + nx, b.y + ny),
+        Point::new(a.x + nx, a.y + ny),
+    ]
+}
+
+/// A rectangle of half-size `w` by `h` around the origin with corners of
+/// `radius`.
+fn rounded_rect(w: f64, h: f64, radius: f64, max_error: f64) -> Vec<Point> {
+    let radius = radius.clamp(0.0, w.min(h));
+    if radius <= 0.0 {
+        return vec![Point::new(w, h), Point::new(-w, h), Point::new(-w, -h), Point::new(w, -h)];
+    }
+    let (x, y) = (w - radius, h - radius);
+    let corners = [(x, y), (-x, y), (-x, -y), (x, -y)];
+    corners
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, (cx, cy))| arc_around(Point::new(cx, cy), radius, i as f64 * PI / 2.0, PI / 2.0, max_error))
+        .collect()
+}
+
+/// A pad's copper grown by `grow`, relative to its center and unrotated.
+fn pad_outline(pad: &Pad, grow: f64, max_error: f64) -> Vec<Point> {
+    let (w, h) = (pad.size.width / 2.0, pad.size.height / 2.0);
+    let radius = match pad.shape {
+        PadShape::Circle => {
+            let circle = circle(Point::default(), w + grow, max_error);
+            return circle.into_iter().map(|[x, y]| Point::new(x, y)).collect();
+        },
+        PadShape::Oval => w.min(h),
+        PadShape::RoundRect => 2.0 * w.min(h) * pad.roundrect_rratio.unwrap_or(0.25),
+        PadShape::Rect | PadShape::Trapezoid | PadShape::Custom => 0.0,
+    };
+    rounded_rect(w + grow, h + grow, radius + grow, max_error)
+}
+
+/// Points along a three-point arc, ends included.
+pub(crate) fn arc_points(start: Point, mid: Point, end: Point, max_error: f64) -> Vec<Point> {
+    let Some(center) = circle_center(start, mid, end) else {
+        return vec![start, end];
+    };
+    let radius = distance(center, start);
+    let angle = |p: Point| (p.y - center.y).atan2(p.x - center.x);
+    let (a0, am, a1) = (angle(start), angle(mid), angle(end));
+    let turn = |from: f64, to: f64| (to - from).rem_euclid(2.0 * PI);
+    // Sweep whichever way passes through the midpoint.
+    let sweep = if turn(a0, am) <= turn(a0, a1) { turn(a0, a1) } else { turn(a0, a1) - 2.0 * PI };
+    let steps = (sweep.abs() / arc_step(radius, max_error)).ceil().max(1.0) as usize;
+    let mut points: Vec<Point> = (0..steps)
+        .map(|i| {
+            let (sin, cos) = (a0 + sweep * i as f64 / steps as f64).sin_cos();
+            Point::new(center.x + radius * cos, center.y + radius * sin)
+        })
+        .collect();
+    points.push(end);
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Keepout, LayerSelector};
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn filled(fill: &FilledPolygon, x: f64, y: f64) -> bool {
+        contains(&[fill.points.iter().map(|p| xy(*p)).collect()], [x, y])
+    }
+
+    #[test]
+    fn demo() {
+        let mut board = Board::parse(DEMO).unwrap();
+        fill_zones(&mut board, &FillOptions::default());
+        let zone = &board.zones[0];
+
+        assert_eq!(zone.filled_polygons.len(), 1);
+        let fill = &zone.filled_polygons[0];
+        assert_eq!(fill.layer, Layer::BCu);
+        assert!(filled(fill, 115.0, 115.0));
+        // Board edge.
+        assert!(filled(fill, 100.6, 110.0) && !filled(fill, 100.4, 110.0));
+        // VIN via and track.
+        assert!(!filled(fill, 115.0, 101.3) && filled(fill, 115.0, 101.1));
+        assert!(!filled(fill, 119.225, 105.0) && !filled(fill, 119.8, 105.0) && filled(fill, 120.0, 105.0));
+        // Mounting hole without a net.
+        assert!(!filled(fill, 126.0, 113.95) && filled(fill, 126.0, 113.8));
+        // Thermal relief around the GND pin of J1: spokes, but a gap between them.
+        assert!(filled(fill, 104.0, 110.08) && filled(fill, 105.1, 110.08));
+        assert!(!filled(fill, 104.78, 110.86) && filled(fill, 105.2, 111.3));
+        // And around the GND pad of C1, whose spoke towards the VIN pad is cut.
+        assert!(filled(fill, 121.5, 107.5) && !filled(fill, 121.5, 107.1) && !filled(fill, 120.0, 107.5));
+        // The GND via connects solidly.
+        assert!(filled(fill, 112.0, 110.0));
+    }
+
+    #[test]
+    fn islands() {
+        let mut board = Board::parse(DEMO).unwrap();
+        let strip = [(121.0, 100.0), (121.5, 100.0), (121.5, 120.0), (121.0, 120.0)];
+        board.zones.push(Zone {
+            layers: vec![LayerSelector::Layer(Layer::BCu)],
+            keepout: Some(Keepout { copperpour: true, ..Default::default() }),
+            outline: strip.map(|(x, y)| Point::new(x, y)).to_vec(),
+            ..Default::default()
+        });
+
+        let options = FillOptions { remove_islands: false, ..Default::default() };
+        assert_eq!(fill_zone(&board, 0, &options).len(), 2);
+        fill_zones(&mut board, &FillOptions::default());
+        let fills = &board.zones[0].filled_polygons;
+        assert_eq!(fills.len(), 1);
+        assert!(filled(&fills[0], 115.0, 115.0) && !filled(&fills[0], 125.0, 110.0));
+        assert!(board.zones[1].filled_polygons.is_empty());
+
+        // The zone's own policy: the cut-off island is about 5.5 x 19 mm.
+        let policies =
+            [(IslandRemoval::Never, 2), (IslandRemoval::BelowArea(50.0), 2), (IslandRemoval::BelowArea(500.0), 1)];
+        for (removal, count) in policies {
+            board.zones[0].island_removal = removal;
+            assert_eq!(fill_zone(&board, 0, &FillOptions::default()).len(), count, "{:?}", removal);
+        }
+    }
+
+    #[test]
+    fn analysis() {
+        let never = "(thermal_bridge_width 0.5)\n\t\t\t(island_removal_mode 1)";
+        let src = DEMO.replacen("(thermal_bridge_width 0.5)", never, 1);
+        let mut board = Board::parse(&src).unwrap();
+        assert_eq!(board.zones[0].island_removal, IslandRemoval::Never);
+        let strip = [(121.0, 100.0), (121.5, 100.0), (121.5, 120.0), (121.0, 120.0)];
+        board.zones.push(Zone {
+            layers: vec![LayerSelector::Layer(Layer::BCu)],
+            keepout: Some(Keepout { copperpour: true, ..Default::default() }),
+            outline: strip.map(|(x, y)| Point::new(x, y)).to_vec(),
+            ..Default::default()
+        });
+        fill_zones(&mut board, &FillOptions::default());
+
+        let islands = zone_islands(&board);
+        assert_eq!(islands.len(), 2);
+        let (connected, isolated): (Vec<&Island>, Vec<&Island>) = islands.iter().partition(|island| island.connected);
+        assert_eq!((connected.len(), isolated.len()), (1, 1));
+        assert!(isolated[0].area > 50.0 && isolated[0].area < 500.0 && connected[0].area > isolated[0].area);
+
+        let coverage = copper_coverage(&board);
+        assert_eq!(coverage.iter().map(|c| c.layer).collect::<Vec<_>>(), [Layer::FCu, Layer::BCu]);
+        assert_eq!((coverage[0].copper, coverage[0].fraction()), (0.0, Some(0.0)));
+        let back = &coverage[1];
+        assert!((back.copper - connected[0].area - isolated[0].area).abs() < 1e-6);
+        assert!((back.isolated - isolated[0].area).abs() < 1e-6);
+        assert!(back.fraction().unwrap() > 0.5 && back.fraction().unwrap() < 1.0);
+
+        let issues = check_islands(&board);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.starts_with("zone GND has an island of "), "{}", issues[0]);
+        assert!(issues[0].message.contains(" on B.Cu at ("));
+    }
+
+    #[test]
+    fn thermal() {
+        let mut board = Board::parse(DEMO).unwrap();
+        fill_zones(&mut board, &FillOptions::default());
+        let reliefs = thermal_reliefs(&board);
+        let pads: Vec<_> = reliefs
+            .iter()
+            .map(|relief| {
+                let footprint = &board.footprints[relief.footprint];
+                (footprint.reference().unwrap(), footprint.pads[relief.pad].number.as_str(), relief.connected())
+            })
+            .collect();
+        // The spokes towards J1's VOUT pin and C1's VIN pad are cut.
+        assert_eq!(pads, [("J1", "3", 3), ("C1", "1", 3)]);
+        let spoke = reliefs[0].spokes[0];
+        assert!(!spoke.connected && distance(spoke.start, Point::new(104.0, 109.23)) < 0.01);
+        assert!((distance(spoke.start, spoke.end) - 0.5).abs() < 1e-9);
+        assert!(check_thermal_reliefs(&board, MIN_SPOKES).is_empty());
+        let issues = check_thermal_reliefs(&board, 4);
+        assert_eq!(issues.len(), 2);
+        let message = "pad J1.3 connects to zone GND on B.Cu through 3 of 4 thermal spokes, fewer than 4";
+        assert_eq!(issues[0].message, message);
+
+        // Pads' own settings: diagonal spokes for J1, a solid connection for C1.
+        let pin = "(pinfunction \"Pin_3\")";
+        let src = DEMO.replacen(pin, &format!("{} (thermal_bridge_angle 45) (thermal_width 0.3)", pin), 1);
+        let c1 = src.find("Capacitor_SMD").unwrap();
+        let src = src[..c1].to_string() + &src[c1..].replacen("(net 3 \"GND\")", "(net 3 \"GND\") (zone_connect 2)", 1);
+        let mut board = Board::parse(&src).unwrap();
+        let j1 = &board.footprints[0].pads[2];
+        assert_eq!((j1.spoke_angle(), j1.thermal_bridge_width), (45.0, Some(0.3)));
+        let written = j1.to_sexp().build().to_string();
+        assert!(written.contains("\t(thermal_bridge_width 0.3)\n\t(thermal_bridge_angle 45)\n"), "{}", written);
+        let c1 = board.footprints.iter().find(|footprint| footprint.reference() == Some("C1")).unwrap();
+        assert_eq!(c1.pads[0].zone_connect, Some(PadConnection::Solid));
+        fill_zones(&mut board, &FillOptions::default());
+        let fill = &board.zones[0].filled_polygons[0];
+        assert!(filled(fill, 104.78, 110.86) && !filled(fill, 105.1, 110.08));
+        assert!(filled(fill, 121.3, 108.1));
+        let reliefs = thermal_reliefs(&board);
+        assert_eq!(reliefs.len(), 1);
+        assert_eq!((reliefs[0].spoke_width, reliefs[0].connected()), (0.3, 4));
+    }
+
+    #[test]
+    fn write_back() {
+        let mut board = Board::parse(DEMO).unwrap();
+        fill_zones(&mut board, &FillOptions::default());
+        let mut doc = kicad_sexp::cst::parse(DEMO).unwrap();
+        write_fills(&board, &mut doc).unwrap();
+        let out = doc.to_string();
+
+        let zone = DEMO.find("\t(zone").unwrap();
+        assert_eq!(out[..zone], DEMO[..zone]);
+        assert_eq!(out.matches("(filled_polygon").count(), 1);
+        assert!(out.contains("\t\t(filled_polygon\n\t\t\t(layer \"B.Cu\")\n\t\t\t(pts\n\t\t\t\t(xy "));
+        let written = Board::parse(&out).unwrap();
+        let (fill, original) = (&written.zones[0].filled_polygons[0], &board.zones[0].filled_polygons[0]);
+        assert_eq!(fill.points.len(), original.points.len());
+        assert!(fill.points.iter().zip(&original.points).all(|(a, b)| distance(*a, *b) < 1e-6));
+
+        board.zones.pop();
+        assert!(write_fills(&board, &mut doc).is_err());
+    }
+
+    #[test]
+    fn fractured() {
+        let square = |x: f64, y: f64, size: f64| vec![[x, y], [x + size, y], [x + size, y + size], [x, y + size]];
+        let mut hole = square(2.0, 2.0, 2.0);
+        hole.reverse();
+        let mut other = square(6.0, 6.0, 2.0);
+        other.reverse();
+        let outline = fracture(vec![square(0.0, 0.0, 10.0), hole, other]);
+
+        assert_eq!(outline.len(), 4 + 2 * 7);
+        assert!((signed_area(&outline) - 92.0).abs() < 1e-9);
+        let shape = [outline];
+        assert!(contains(&shape, [1.0, 1.0]) && !contains(&shape, [3.0, 3.0]) && !contains(&shape, [7.0, 7.0]));
+    }
+}
@@ -0,0 +1,130 @@
+//! Flex and rigid-flex boards: bend areas, and a lint for parts and vias
+//! placed where the board bends.
+//!
+//! KiCad has no bend areas of its own, so they are rule areas known by
+//! their name, such as `Bend 1`, which [`add_bend_area`] adds forbidding
+//! footprints, pads and vias. [`check_bend_areas`] reports the footprints
+//! and vias in them, for CI. The stackup's coverlays and flexible
+//! dielectrics are [`StackupLayer::is_coverlay`](crate::StackupLayer::is_coverlay)
+//! and [`StackupLayer::is_flexible`](crate::StackupLayer::is_flexible).
+//!
+//! ```no_run
+//! use kicad_pcb::{flex::check_bend_areas, Board};
+//!
+//! let board = Board::parse(&std::fs::read_to_string("flex.kicad_pcb")?)?;
+//! if board.setup.is_flex() {
+//!     for issue in check_bend_areas(&board, "Bend*") {
+//!         eprintln!("{}", issue);
+//!     }
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{ConvertError, Error, Point};
+use kicad_dru::wildcard_match;
+use kicad_sexp::{number::format_float, schema::Issue};
+
+use crate::{
+    bounds::Bounds,
+    index::Item,
+    selection::bounds,
+    Board, Keepout, LayerClass, LayerSelector, Zone,
+};
+
+/// Adds a bend area named `name` inside `outline`: a rule area on every
+/// copper layer that keeps out footprints, pads and vias. It gets a UUID
+/// from [`Board::assign_missing_uuids`].
+pub fn add_bend_area<'b>(board: &'b mut Board, name: &str, outline: &[Point]) -> Result<&'b mut Zone, Error> {
+    if board.zones.iter().any(|zone| zone.name.as_deref() == Some(name)) {
+        return Err(ConvertError::new(format!("the board already has a zone {}", name)).into());
+    }
+    if outline.len() < 3 {
+        return Err(ConvertError::new(format!("a bend area outline needs 3 points, got {}", outline.len())).into());
+    }
+    board.zones.push(Zone {
+        layers: vec![LayerSelector::All(LayerClass::Cu)],
+        name: Some(name.to_string()),
+        keepout: Some(Keepout { vias: true, pads: true, footprints: true, ..Default::default() }),
+        outline: outline.to_vec(),
+        ..Default::default()
+    });
+    Ok(board.zones.last_mut().expect("a zone was just added"))
+}
+
+/// The rule areas of `board` whose name matches `pattern`, with `*` and
+/// `?` wildcards.
+pub fn bend_areas<'b>(board: &'b Board, pattern: &str) -> Vec<&'b Zone> {
+    let bends = board.zones.iter().filter(|zone| zone.keepout.is_some());
+    bends.filter(|zone| zone.name.as_deref().is_some_and(|name| wildcard_match(pattern, name))).collect()
+}
+
+/// An issue for each footprint and via in a bend area: footprints whose
+/// bounds, pads included, overlap it, and vias whose copper does.
+pub fn check_bend_areas(board: &Board, pattern: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for area in bend_areas(board, pattern) {
+        let name = area.name.as_deref().unwrap_or_default();
+        for footprint in &board.footprints {
+            if bounds(Item::Footprint(footprint)).is_some_and(|bounds| bounds.overlaps(&area.outline)) {
+                let message = format!("{} is in bend area {}", footprint.reference().unwrap_or_default(), name);
+                issues.push(Issue { path: vec!["kicad_pcb".into(), "footprint".into()], message });
+            }
+        }
+        for via in &board.vias {
+            let half = Point::new(via.size / 2.0, via.size / 2.0);
+            let copper = Bounds { min: Point::new(via.at.x - half.x, via.at.y - half.y), max: via.at + half };
+            if copper.overlaps(&area.outline) {
+                let (x, y) = (format_float(via.at.x), format_float(via.at.y));
+                let message = format!("via at ({}, {}) is in bend area {}", x, y, name);
+                issues.push(Issue { path: vec!["kicad_pcb".into(), "via".into()], message });
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StackupLayer;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn rectangle(min: Point, max: Point) -> Vec<Point> {
+        vec![min, Point::new(max.x, min.y), max, Point::new(min.x, max.y)]
+    }
+
+    #[test]
+    fn bends() {
+        let mut board = Board::parse(DEMO).unwrap();
+        assert!(!board.setup.is_flex());
+        add_bend_area(&mut board, "Bend 1", &rectangle(Point::new(108.0, 108.0), Point::new(113.0, 112.0))).unwrap();
+        add_bend_area(&mut board, "Bend 2", &rectangle(Point::new(121.8, 100.5), Point::new(122.3, 119.5))).unwrap();
+        assert!(add_bend_area(&mut board, "Bend 1", &rectangle(Point::default(), Point::new(1.0, 1.0))).is_err());
+        assert!(add_bend_area(&mut board, "Bend 3", &[Point::default()]).is_err());
+        assert_eq!(bend_areas(&board, "bend*").len(), 2);
+
+        let messages: Vec<String> = check_bend_areas(&board, "Bend*").into_iter().map(|issue| issue.message).collect();
+        assert_eq!(messages, ["R2 is in bend area Bend 1", "via at (112, 110) is in bend area Bend 1"]);
+        assert!(check_bend_areas(&board, "Flex*").is_empty());
+    }
+
+    #[test]
+    fn stackup() {
+        let layer = |name: &str, kind: &str, material: &str| StackupLayer {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            material: Some(material.to_string()),
+            ..Default::default()
+        };
+        assert!(layer("F.Mask", "Top Solder Mask", "Polyimide").is_coverlay());
+        assert!(layer("dielectric 2", "core", "Coverlay").is_coverlay());
+        assert!(!layer("F.Mask", "Top Solder Mask", "Epoxy").is_coverlay());
+        assert!(layer("dielectric 1", "core", "Kapton").is_flexible());
+        assert!(!layer("dielectric 1", "core", "FR4").is_flexible());
+
+        let mut board = Board::parse(DEMO).unwrap();
+        board.setup.stackup.push(layer("dielectric 3", "core", "Polyimide"));
+        assert!(board.setup.is_flex());
+    }
+}
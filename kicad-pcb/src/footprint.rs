@@ -0,0 +1,416 @@
+use std::fmt;
+
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    embedded::EmbeddedFile,
+    extension::{parse_extension, ExtensionNode},
+    uuid::item_uuid,
+    ConvertError, Effects, Error, Font, Point, Position, Uuid,
+};
+use kicad_sexp::{Sexp, SexpBuilder, SexpOwned};
+
+use crate::{
+    graphics::at_sexp,
+    layer::child_layer,
+    Board, Layer, Pad, Shape, Text, TextKind,
+};
+
+/// A footprint field, as in `(property "Reference" "R1" (at ...) (layer "F.SilkS") ...)`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Field {
+    pub key: String,
+    pub value: String,
+    pub at: Position,
+    pub layer: Layer,
+    pub hide: bool,
+    pub effects: Effects,
+    pub uuid: Option<Uuid>,
+}
+
+impl Field {
+    /// A field on `layer` in KiCad's default 1 mm text.
+    pub fn new(key: &str, value: &str, at: Position, layer: Layer) -> Self {
+        let font = Font { height: 1.0, width: 1.0, thickness: Some(0.15), ..Default::default() };
+        Field {
+            key: key.to_string(),
+            value: value.to_string(),
+            at,
+            layer,
+            hide: false,
+            effects: Effects { font, ..Default::default() },
+            uuid: None,
+        }
+    }
+
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let effects = Effects { hide: false, ..self.effects.clone() };
+        SexpBuilder::new("property")
+            .string(&self.key)
+            .string(&self.value)
+            .child(at_sexp(&self.at, true))
+            .child(SexpBuilder::new("layer").string(&self.layer.to_string()))
+            .child_opt(self.hide.then(|| SexpBuilder::new("hide").symbol("yes")))
+            .child_opt(self.uuid.map(|uuid| SexpBuilder::new("uuid").string(&uuid.to_string())))
+            .child(effects.to_sexp())
+    }
+}
+
+impl FromSexp for Field {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let effects: Effects = list.parse("effects")?.unwrap_or_default();
+        Ok(Field {
+            key: list.string(0)?,
+            value: list.string(1)?,
+            at: list.parse("at")?.unwrap_or_default(),
+            layer: child_layer(list)?,
+            hide: list.flag("hide")? || effects.hide,
+            effects,
+            uuid: item_uuid(list)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MountKind {
+    #[default]
+    Unspecified,
+    Smd,
+    ThroughHole,
+}
+
+/// The footprint's `(attr ...)` list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attributes {
+    pub mount: MountKind,
+    /// Only on the board, without a schematic symbol.
+    pub board_only: bool,
+    pub exclude_from_pos_files: bool,
+    pub exclude_from_bom: bool,
+    pub allow_missing_courtyard: bool,
+    pub dnp: bool,
+}
+
+impl Attributes {
+    /// The `(attr ...)` list; `None` when there is nothing to say.
+    pub fn to_sexp(&self) -> Option<SexpBuilder> {
+        let mut attr = SexpBuilder::new("attr");
+        match self.mount {
+            MountKind::Unspecified => {},
+            MountKind::Smd => attr = attr.symbol("smd"),
+            MountKind::ThroughHole => attr = attr.symbol("through_hole"),
+        }
+        let flags = [
+            (self.board_only, "board_only"),
+            (self.exclude_from_pos_files, "exclude_from_pos_files"),
+            (self.exclude_from_bom, "exclude_from_bom"),
+            (self.allow_missing_courtyard, "allow_missing_courtyard"),
+            (self.dnp, "dnp"),
+        ];
+        for (_, flag) in flags.iter().filter(|(on, _)| *on) {
+            attr = attr.symbol(flag);
+        }
+        (*self != Attributes::default()).then_some(attr)
+    }
+}
+
+impl FromSexp for Attributes {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mount = if list.has_symbol("smd") {
+            MountKind::Smd
+        } else if list.has_symbol("through_hole") {
+            MountKind::ThroughHole
+        } else {
+            MountKind::Unspecified
+        };
+        Ok(Attributes {
+            mount,
+            board_only: list.has_symbol("board_only"),
+            exclude_from_pos_files: list.has_symbol("exclude_from_pos_files"),
+            exclude_from_bom: list.has_symbol("exclude_from_bom"),
+            allow_missing_courtyard: list.has_symbol("allow_missing_courtyard"),
+            dnp: list.has_symbol("dnp"),
+        })
+    }
+}
+
+fn xyz(list: &List, tag: &str, default: f64) -> Result<[f64; 3]> {
+    let Some(child) = list.child(tag) else {
+        return Ok([default; 3]);
+    };
+    let xyz = child.child("xyz").ok_or_else(|| child.error("missing (xyz ...)").within(list.tag()))?;
+    let number = |i| xyz.number(i).map_err(|e| e.within(child.tag()).within(list.tag()));
+    Ok([number(0)?, number(1)?, number(2)?])
+}
+
+/// A 3D model attached to a footprint.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Model {
+    /// Model path, usually starting with a `${KICAD9_3DMODEL_DIR}` variable.
+    pub path: String,
+    pub hide: bool,
+    /// Offset in millimeters.
+    pub offset: [f64; 3],
+    pub scale: [f64; 3],
+    /// Rotation around each axis in degrees.
+    pub rotate: [f64; 3],
+}
+
+impl Model {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let xyz = |tag: &str, [x, y, z]: [f64; 3]| {
+            SexpBuilder::new(tag).child(SexpBuilder::new("xyz").float(x).float(y).float(z))
+        };
+        SexpBuilder::new("model")
+            .string(&self.path)
+            .child_opt(self.hide.then(|| SexpBuilder::new("hide").symbol("yes")))
+            .child(xyz("offset", self.offset))
+            .child(xyz("scale", self.scale))
+            .child(xyz("rotate", self.rotate))
+    }
+}
+
+impl FromSexp for Model {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Model {
+            path: list.string(0)?,
+            hide: list.flag("hide")?,
+            offset: xyz(list, "offset", 0.0)?,
+            scale: xyz(list, "scale", 1.0)?,
+            rotate: xyz(list, "rotate", 0.0)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    Top,
+    Bottom,
+}
+
+/// A footprint placed on a board or defined in a library file.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Footprint {
+    /// Library identifier, e.g. `Resistor_SMD:R_0603_1608Metric`.
+    pub lib_id: String,
+    /// `F.Cu` or `B.Cu`; back footprints have their items already flipped.
+    pub layer: Option<Layer>,
+    pub uuid: Option<Uuid>,
+    pub at: Position,
+    pub locked: bool,
+    pub placed: bool,
+    pub descr: Option<String>,
+    pub tags: Option<String>,
+    pub properties: Vec<Field>,
+    /// Sheet path of the linked schematic symbol, as UUIDs.
+    pub path: Option<String>,
+    pub sheetname: Option<String>,
+    pub sheetfile: Option<String>,
+    pub attributes: Attributes,
+    /// The KiCad 9 component classes written on the footprint; classes
+    /// from a project's dynamic assignment rules are not resolved.
+    pub component_classes: Vec<String>,
+    pub pads: Vec<Pad>,
+    pub shapes: Vec<Shape>,
+    pub texts: Vec<Text>,
+    pub models: Vec<Model>,
+    pub embedded_files: Vec<EmbeddedFile>,
+    /// Lists handled by an [`Extension`](kicad_common::extension::Extension)
+    /// registered for the parse.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub extensions: Vec<ExtensionNode>,
+}
+
+impl Footprint {
+    /// Parses a standalone `.kicad_mod` file; files from KiCad 5 and older
+    /// have a `(module ...)` root.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let sexps = kicad_sexp::parse(src).map_err(Error::Parse)?;
+        let root = sexps
+            .iter()
+            .find(|sexp| matches!(sexp, Sexp::List(_)))
+            .ok_or_else(|| ConvertError::new("expected a (footprint ...) root"))?;
+        let list = match List::new(root) {
+            Some(list) if list.tag() == "module" => list,
+            _ => List::expect(root, "footprint")?,
+        };
+        #[cfg(feature = "tracing")]
+        let _convert = tracing::debug_span!("convert", children = list.lists().count()).entered();
+        Ok(Footprint::from_sexp(&list)?)
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.iter().find(|field| field.key == key).map(|field| field.value.as_str())
+    }
+
+    /// The `Reference` field, falling back to `fp_text reference` in older files.
+    pub fn reference(&self) -> Option<&str> {
+        self.property("Reference").or_else(|| self.text(TextKind::Reference))
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.property("Value").or_else(|| self.text(TextKind::Value))
+    }
+
+    fn text(&self, kind: TextKind) -> Option<&str> {
+        self.texts.iter().find(|text| text.kind == Some(kind)).map(|text| text.text.as_str())
+    }
+
+    pub fn is_back(&self) -> bool {
+        self.layer == Some(Layer::BCu)
+    }
+
+    pub fn side(&self) -> Side {
+        if self.is_back() { Side::Bottom } else { Side::Top }
+    }
+
+    /// Courtyard outline on `side`, relative to the footprint.
+    pub fn courtyard(&self, side: Side) -> impl Iterator<Item = &Shape> {
+        let layer = match side {
+            Side::Top => Layer::FCrtYd,
+            Side::Bottom => Layer::BCrtYd,
+        };
+        self.shapes.iter().filter(move |shape| shape.layer == layer)
+    }
+
+    pub fn has_component_class(&self, class: &str) -> bool {
+        self.component_classes.iter().any(|own| own == class)
+    }
+
+    pub fn pad(&self, number: &str) -> Option<&Pad> {
+        self.pads.iter().find(|pad| pad.number == number)
+    }
+
+    /// The footprint as a library `.kicad_mod` file, named without the
+    /// library nickname; [`Display`](fmt::Display) formats the whole file.
+    /// The placement and symbol link of a footprint from a board are kept.
+    pub fn to_sexp(&self) -> SexpOwned {
+        let name = self.lib_id.split_once(':').map_or(self.lib_id.as_str(), |(_, name)| name);
+        let string = |tag: &str, value: &Option<String>| value.as_deref().map(|v| SexpBuilder::new(tag).string(v));
+        let yes = |tag: &str, on: bool| on.then(|| SexpBuilder::new(tag).symbol("yes"));
+        let mut sexp = SexpBuilder::new("footprint")
+            .string(name)
+            .child(SexpBuilder::new("version").int(Board::NEWEST_VERSION.into()))
+            .child(SexpBuilder::new("generator").string("pcbnew"))
+            .child(SexpBuilder::new("generator_version").string("9.0"))
+            .child_opt(yes("locked", self.locked))
+            .child_opt(yes("placed", self.placed))
+            .child(SexpBuilder::new("layer").string(&self.layer.unwrap_or(Layer::FCu).to_string()))
+            .child_opt(self.uuid.map(|uuid| SexpBuilder::new("uuid").string(&uuid.to_string())))
+            .child_opt((self.at != Position::default()).then(|| at_sexp(&self.at, false)))
+            .child_opt(string("descr", &self.descr))
+            .child_opt(string("tags", &self.tags));
+        for field in &self.properties {
+            sexp = sexp.child(field.to_sexp());
+        }
+        sexp = sexp
+            .child_opt(string("path", &self.path))
+            .child_opt(string("sheetname", &self.sheetname))
+            .child_opt(string("sheetfile", &self.sheetfile))
+            .child_opt(self.attributes.to_sexp());
+        if !self.component_classes.is_empty() {
+            let classes = self.component_classes.iter().fold(SexpBuilder::new("component_classes"), |classes, class| {
+                classes.child(SexpBuilder::new("class").string(class))
+            });
+            sexp = sexp.child(classes);
+        }
+        for shape in &self.shapes {
+            sexp = sexp.child(shape.to_sexp("fp"));
+        }
+        for text in &self.texts {
+            sexp = sexp.child(text.to_sexp());
+        }
+        for pad in &self.pads {
+            sexp = sexp.child(pad.to_sexp());
+        }
+        if !self.embedded_files.is_empty() {
+            let files = self.embedded_files.iter().fold(SexpBuilder::new("embedded_files"), |files, file| {
+                files.item(file.to_sexp())
+            });
+            sexp = sexp.child(files);
+        }
+        sexp = sexp.child(SexpBuilder::new("embedded_fonts").symbol("no"));
+        for model in &self.models {
+            sexp = sexp.child(model.to_sexp());
+        }
+        for extension in &self.extensions {
+            sexp = sexp.item(extension.to_sexp());
+        }
+        sexp.build()
+    }
+
+    /// Converts a point relative to the footprint into board coordinates.
+    pub fn to_board(&self, local: Point) -> Point {
+        self.at.point() + local.rotated(self.at.angle)
+    }
+
+    /// Board position of a pad's center.
+    pub fn pad_position(&self, pad: &Pad) -> Point {
+        self.to_board(pad.at.point())
+    }
+
+    /// [`Footprint::pad_position`] without float error, for comparing
+    /// against other board coordinates.
+    #[cfg(feature = "decimal")]
+    pub fn exact_pad_position(&self, pad: &Pad) -> kicad_common::exact::Point {
+        kicad_common::exact::Position::from(self.at).to_outer(pad.at.point().into())
+    }
+}
+
+impl fmt::Display for Footprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_sexp())
+    }
+}
+
+impl FromSexp for Footprint {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mut footprint = Footprint {
+            lib_id: list.string(0)?,
+            layer: list.parse("layer")?,
+            uuid: item_uuid(list)?,
+            at: list.parse("at")?.unwrap_or_default(),
+            locked: list.flag("locked")?,
+            placed: list.flag("placed")?,
+            descr: list.child_string("descr")?,
+            tags: list.child_string("tags")?,
+            path: list.child_string("path")?,
+            sheetname: list.child_string("sheetname")?,
+            sheetfile: list.child_string("sheetfile")?,
+            attributes: list.parse("attr")?.unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for item in list.lists() {
+            let within = |e: kicad_common::ConvertError| e.within(list.tag());
+            match item.tag() {
+                "property" => footprint.properties.push(Field::from_sexp(&item).map_err(within)?),
+                "pad" => footprint.pads.push(Pad::from_sexp(&item).map_err(within)?),
+                "fp_text" => footprint.texts.push(Text::from_sexp(&item).map_err(within)?),
+                "model" => footprint.models.push(Model::from_sexp(&item).map_err(within)?),
+                "embedded_files" => footprint.embedded_files = item.parse_all("file").map_err(within)?,
+                "component_classes" => {
+                    footprint.component_classes = item
+                        .lists()
+                        .filter(|class| class.tag() == "class")
+                        .map(|class| class.string(0).map_err(|e| within(e.within(item.tag()))))
+                        .collect::<Result<_>>()?;
+                },
+                tag if Shape::is_shape_tag(tag) => footprint.shapes.push(Shape::from_sexp(&item).map_err(within)?),
+                _ => {
+                    if let Some(node) = parse_extension(&item) {
+                        footprint.extensions.push(node.map_err(within)?);
+                    }
+                },
+            }
+        }
+
+        Ok(footprint)
+    }
+}
@@ -0,0 +1,456 @@
+//! Gerber (RS-274X) files of a board's layers, with the X2 file attributes
+//! fabs read the layer's role from, as KiCad's plotter writes them.
+//!
+//! Pads are flashed where an aperture can draw them, rounded or rotated
+//! ones are written as regions, and trapezoid and custom pads as their
+//! bounding rectangle. Tracks, arcs and drawings are stroked, filled
+//! drawings and zone fills become regions, and solder mask and paste
+//! layers get the pads opened by the board's mask clearance. Texts are
+//! left out: plotting them needs KiCad's stroke font, which this crate
+//! does not have. Coordinates are millimeters with Y pointing up.
+//!
+//! ```no_run
+//! use kicad_pcb::{gerber::{gerber_files, GerberOptions}, Board};
+//!
+//! let board = Board::parse(&std::fs::read_to_string("demo.kicad_pcb")?)?;
+//! for file in gerber_files(&board, "demo", &GerberOptions::default()) {
+//!     std::fs::write(&file.name, &file.contents)?;
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{f64::consts::PI, fmt::Write};
+
+use kicad_common::Point;
+
+use crate::{
+    bounds::{bezier_points, circle_center, distance},
+    placement::Origin,
+    Board, Layer, Pad, PadKind, PadShape, Shape, ShapeKind,
+};
+
+/// Width drawn for unfilled drawings of no width.
+const HAIRLINE: f64 = 0.1;
+/// How far region outlines of rounded shapes may fall inside the curve.
+const MAX_ERROR: f64 = 0.005;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GerberOptions {
+    pub origin: Origin,
+    /// Names files with Protel extensions such as `.gtl` instead of `.gbr`,
+    /// as some fabs ask for.
+    pub protel_extensions: bool,
+}
+
+/// One Gerber file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GerberFile {
+    /// As KiCad names it: `board-F_Cu.gbr`, or `board-F_Cu.gtl` with
+    /// Protel extensions.
+    pub name: String,
+    pub layer: Layer,
+    pub contents: String,
+}
+
+/// The layers a board is made from: copper front to back, then solder
+/// mask, silkscreen and paste of both sides and the board outline.
+pub fn fab_layers(board: &Board) -> Vec<Layer> {
+    let mut layers = board.copper_layers();
+    layers.extend([
+        Layer::FMask,
+        Layer::BMask,
+        Layer::FSilkS,
+        Layer::BSilkS,
+        Layer::FPaste,
+        Layer::BPaste,
+        Layer::EdgeCuts,
+    ]);
+    layers
+}
+
+/// The Gerber files of [`fab_layers`], named after `name`.
+pub fn gerber_files(board: &Board, name: &str, options: &GerberOptions) -> Vec<GerberFile> {
+    fab_layers(board)
+        .into_iter()
+        .map(|layer| {
+            let extension =
+                if options.protel_extensions { protel_extension(board, layer) } else { "gbr".to_string() };
+            GerberFile {
+                name: format!("{}-{}.{}", name, layer.to_string().replace('.', "_"), extension),
+                layer,
+                contents: gerber(board, layer, options),
+            }
+        })
+        .collect()
+}
+
+/// The X2 file function of `layer`, as in `Copper,L1,Top`.
+pub fn file_function(board: &Board, layer: Layer) -> String {
+    let copper = board.copper_layers();
+    match layer {
+        Layer::FCu => "Copper,L1,Top".to_string(),
+        Layer::BCu => format!("Copper,L{},Bot", copper.len()),
+        Layer::InCu(_) => {
+            let index = copper.iter().position(|&l| l == layer).map_or(0, |i| i + 1);
+            format!("Copper,L{},Inr", index)
+        },
+        Layer::FMask => "Soldermask,Top".to_string(),
+        Layer::BMask => "Soldermask,Bot".to_string(),
+        Layer::FSilkS => "Legend,Top".to_string(),
+        Layer::BSilkS => "Legend,Bot".to_string(),
+        Layer::FPaste => "Paste,Top".to_string(),
+        Layer::BPaste => "Paste,Bot".to_string(),
+        Layer::FAdhes => "Glue,Top".to_string(),
+        Layer::BAdhes => "Glue,Bot".to_string(),
+        Layer::EdgeCuts => "Profile,NP".to_string(),
+        other => format!("Other,{}", other.to_string().replace('.', "_")),
+    }
+}
+
+/// The extension KiCad gives `layer` with Protel extensions on.
+fn protel_extension(board: &Board, layer: Layer) -> String {
+    let extension = match layer {
+        Layer::FCu => "gtl",
+        Layer::BCu => "gbl",
+        Layer::InCu(_) => {
+            let index = board.copper_layers().iter().position(|&l| l == layer).unwrap_or(1);
+            return format!("g{}", index);
+        },
+        Layer::FMask => "gts",
+        Layer::BMask => "gbs",
+        Layer::FSilkS => "gto",
+        Layer::BSilkS => "gbo",
+        Layer::FPaste => "gtp",
+        Layer::BPaste => "gbp",
+        Layer::FAdhes => "gta",
+        Layer::BAdhes => "gba",
+        Layer::EdgeCuts => "gm1",
+        _ => "gbr",
+    };
+    extension.to_string()
+}
+
+/// What is drawn, in board coordinates.
+enum Op {
+    Flash { aperture: usize, at: Point },
+    Line { aperture: usize, start: Point, end: Point },
+    Arc { aperture: usize, start: Point, end: Point, center: Point, clockwise: bool },
+    Region(Vec<Point>),
+}
+
+#[derive(Default)]
+struct Plot {
+    /// Aperture definitions such as `C,0.250000`, numbered from 10.
+    apertures: Vec<String>,
+    ops: Vec<Op>,
+}
+
+impl Plot {
+    fn aperture(&mut self, definition: String) -> usize {
+        match self.apertures.iter().position(|existing| *existing == definition) {
+            Some(index) => index,
+            None => {
+                self.apertures.push(definition);
+                self.apertures.len() - 1
+            },
+        }
+    }
+
+    fn circle(&mut self, diameter: f64) -> usize {
+        self.aperture(format!("C,{:.6}", diameter))
+    }
+
+    fn stroke(&mut self, path: &[Point], width: f64) {
+        let aperture = self.circle(width);
+        for pair in path.windows(2) {
+            self.ops.push(Op::Line { aperture, start: pair[0], end: pair[1] });
+        }
+    }
+
+    fn arc(&mut self, start: Point, mid: Point, end: Point, width: f64) {
+        let aperture = self.circle(width);
+        let Some(center) = circle_center(start, mid, end) else {
+            self.ops.push(Op::Line { aperture, start, end });
+            return;
+        };
+        // Clockwise as seen with Y pointing up, which is counter-clockwise
+        // in board coordinates.
+        let cross = (mid.x - start.x) * (end.y - mid.y) - (mid.y - start.y) * (end.x - mid.x);
+        self.ops.push(Op::Arc { aperture, start, end, center, clockwise: cross > 0.0 });
+    }
+
+    fn shape(&mut self, kind: &ShapeKind, width: f64, fill: bool) {
+        let width = if width > 0.0 || fill { width } else { HAIRLINE };
+        match kind {
+            ShapeKind::Line { start, end } => self.stroke(&[*start, *end], width),
+            ShapeKind::Rect { start, end } => {
+                let corners = [*start, Point::new(end.x, start.y), *end, Point::new(start.x, end.y), *start];
+                self.outline(&corners, width, fill);
+            },
+            ShapeKind::Circle { center, end } => {
+                let radius = distance(*center, *end);
+                if fill {
+                    let aperture = self.circle(2.0 * radius + width);
+                    self.ops.push(Op::Flash { aperture, at: *center });
+                } else {
+                    let aperture = self.circle(width);
+                    let start = Point::new(center.x + radius, center.y);
+                    self.ops.push(Op::Arc { aperture, start, end: start, center: *center, clockwise: false });
+                }
+            },
+            ShapeKind::Arc { start, mid, end } => self.arc(*start, *mid, *end, width),
+            ShapeKind::Polygon(points) => {
+                let mut closed = points.clone();
+                closed.extend(points.first().copied());
+                self.outline(&closed, width, fill);
+            },
+            ShapeKind::Curve(points) => self.stroke(&bezier_points(points), width),
+        }
+    }
+
+    fn outline(&mut self, closed: &[Point], width: f64, fill: bool) {
+        if fill {
+            self.ops.push(Op::Region(closed.to_vec()));
+        }
+        if width > 0.0 {
+            self.stroke(closed, width);
+        }
+    }
+
+    /// A pad's copper grown by `margin`, flashed when an aperture fits.
+    fn pad(&mut self, at: Point, pad: &Pad, margin: f64) {
+        let (w, h) = (pad.size.width + 2.0 * margin, pad.size.height + 2.0 * margin);
+        if w <= 0.0 || h <= 0.0 {
+            return;
+        }
+        let quarter = (pad.at.angle / 90.0).round();
+        let upright = (pad.at.angle - quarter * 90.0).abs() < 1e-9;
+        let (fw, fh) = if (quarter as i64).rem_euclid(2) == 1 { (h, w) } else { (w, h) };
+        let radius = match pad.shape {
+            PadShape::Circle => {
+                let aperture = self.circle(w);
+                self.ops.push(Op::Flash { aperture, at });
+                return;
+            },
+            PadShape::Rect | PadShape::Trapezoid | PadShape::Custom if upright => {
+                let aperture = self.aperture(format!("R,{:.6}X{:.6}", fw, fh));
+                self.ops.push(Op::Flash { aperture, at });
+                return;
+            },
+            PadShape::Oval if upright => {
+                let aperture = self.aperture(format!("O,{:.6}X{:.6}", fw, fh));
+                self.ops.push(Op::Flash { aperture, at });
+                return;
+            },
+            PadShape::Rect | PadShape::Trapezoid | PadShape::Custom => 0.0,
+            PadShape::Oval => w.min(h) / 2.0,
+            PadShape::RoundRect => {
+                pad.size.width.min(pad.size.height) * pad.roundrect_rratio.unwrap_or(0.25) + margin
+            },
+        };
+        let mut outline = rounded_rect(w / 2.0, h / 2.0, radius);
+        for point in &mut outline {
+            *point = at + point.rotated(pad.at.angle);
+        }
+        outline.extend(outline.first().copied());
+        self.ops.push(Op::Region(outline));
+    }
+}
+
+/// A `w` by `h` half-size rectangle around the origin with corners rounded
+/// to `radius`.
+fn rounded_rect(w: f64, h: f64, radius: f64) -> Vec<Point> {
+    let radius = radius.clamp(0.0, w.min(h));
+    if radius <= MAX_ERROR {
+        return vec![Point::new(w, h), Point::new(-w, h), Point::new(-w, -h), Point::new(w, -h)];
+    }
+    let step = 2.0 * (1.0 - MAX_ERROR / radius).acos();
+    let steps = ((PI / 2.0) / step).ceil().max(1.0) as usize;
+    let (x, y) = (w - radius, h - radius);
+    [(x, y), (-x, y), (-x, -y), (x, -y)]
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, (cx, cy))| {
+            (0..=steps).map(move |step| {
+                let angle = (i as f64 + step as f64 / steps as f64) * PI / 2.0;
+                Point::new(cx + radius * angle.cos(), cy + radius * angle.sin())
+            })
+        })
+        .collect()
+}
+
+/// The Gerber file of one layer of `board`.
+pub fn gerber(board: &Board, layer: Layer, options: &GerberOptions) -> String {
+    let origin = match options.origin {
+        Origin::Absolute => Point::default(),
+        Origin::AuxAxis => board.setup.aux_axis_origin.unwrap_or_default(),
+    };
+    let mask = matches!(layer, Layer::FMask | Layer::BMask);
+    let paste = matches!(layer, Layer::FPaste | Layer::BPaste);
+    let mut plot = Plot::default();
+
+    for zone in board.zones.iter().filter(|zone| zone.keepout.is_none()) {
+        for fill in zone.filled_polygons.iter().filter(|fill| fill.layer == layer) {
+            let mut closed = fill.points.clone();
+            closed.extend(fill.points.first().copied());
+            plot.ops.push(Op::Region(closed));
+        }
+    }
+    for shape in board.shapes.iter().filter(|shape| shape.layer == layer) {
+        plot.shape(&shape.kind, shape.stroke.width, shape.fill);
+    }
+    for footprint in &board.footprints {
+        for Shape { kind, stroke, fill, .. } in footprint.shapes.iter().filter(|shape| shape.layer == layer) {
+            let kind = crate::bounds::map_points(kind, |p| footprint.to_board(p));
+            plot.shape(&kind, stroke.width, *fill);
+        }
+        for pad in footprint.pads.iter().filter(|pad| pad.on_layer(layer)) {
+            if paste && pad.kind == PadKind::NpThruHole {
+                continue;
+            }
+            let margin = if mask { board.setup.pad_to_mask_clearance } else { 0.0 };
+            plot.pad(footprint.pad_position(pad), pad, margin);
+        }
+    }
+    for track in board.tracks.iter().filter(|track| track.layer == layer) {
+        match track.mid {
+            Some(mid) => plot.arc(track.start, mid, track.end, track.width),
+            None => plot.stroke(&[track.start, track.end], track.width),
+        }
+    }
+    for via in &board.vias {
+        let opened = mask && via.mask_openings(&board.setup).contains(&layer);
+        if (layer.is_copper() && via.spans(layer)) || opened {
+            let aperture = plot.circle(via.size);
+            plot.ops.push(Op::Flash { aperture, at: via.at });
+        }
+    }
+    write(&plot, &file_function(board, layer), if mask { "Negative" } else { "Positive" }, origin)
+}
+
+fn write(plot: &Plot, function: &str, polarity: &str, origin: Point) -> String {
+    let mut out = format!(
+        "%TF.GenerationSoftware,kicad-file-rs,{}*%\n%TF.FileFunction,{}*%\n%TF.FilePolarity,{}*%\n",
+        env!("CARGO_PKG_VERSION"),
+        function,
+        polarity
+    );
+    out.push_str("%FSLAX46Y46*%\n%MOMM*%\n%LPD*%\nG01*\nG75*\n");
+    for (i, definition) in plot.apertures.iter().enumerate() {
+        let (template, sizes) = definition.split_once(',').unwrap_or((definition, ""));
+        let _ = writeln!(out, "%ADD{}{},{}*%", i + 10, template, sizes);
+    }
+    let coordinate = |value: f64| (value * 1e6).round() as i64;
+    let xy = |p: Point| format!("X{}Y{}", coordinate(p.x - origin.x), coordinate(origin.y - p.y));
+    let mut current = None;
+    let mut select = |out: &mut String, aperture: usize| {
+        if current != Some(aperture) {
+            let _ = writeln!(out, "D{}*", aperture + 10);
+            current = Some(aperture);
+        }
+    };
+    for op in &plot.ops {
+        match op {
+            Op::Flash { aperture, at } => {
+                select(&mut out, *aperture);
+                let _ = writeln!(out, "{}D03*", xy(*at));
+            },
+            Op::Line { aperture, start, end } => {
+                select(&mut out, *aperture);
+                let _ = writeln!(out, "{}D02*\n{}D01*", xy(*start), xy(*end));
+            },
+            Op::Arc { aperture, start, end, center, clockwise } => {
+                select(&mut out, *aperture);
+                let (i, j) = (coordinate(center.x - start.x), coordinate(start.y - center.y));
+                let code = if *clockwise { "G02" } else { "G03" };
+                let _ = writeln!(out, "{}D02*\n{}{}I{}J{}D01*\nG01*", xy(*start), code, xy(*end), i, j);
+            },
+            Op::Region(points) => {
+                let Some((first, rest)) = points.split_first() else {
+                    continue;
+                };
+                let _ = writeln!(out, "G36*\n{}D02*", xy(*first));
+                for point in rest {
+                    let _ = writeln!(out, "{}D01*", xy(*point));
+                }
+                out.push_str("G37*\n");
+            },
+        }
+    }
+    out.push_str("M02*\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn demo() {
+        let board = Board::parse(DEMO).unwrap();
+        let files = gerber_files(&board, "demo", &GerberOptions::default());
+        let names: Vec<_> = files.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names[0], "demo-F_Cu.gbr");
+        assert_eq!(names.last(), Some(&"demo-Edge_Cuts.gbr"));
+        for file in &files {
+            assert!(file.contents.starts_with("%TF.GenerationSoftware"), "{}", file.name);
+            assert!(file.contents.ends_with("M02*\n"));
+            assert_eq!(file.contents.matches("G36*").count(), file.contents.matches("G37*").count());
+            // Every aperture used is defined.
+            for line in file.contents.lines().filter(|line| line.starts_with('D') && line.ends_with('*')) {
+                let definition = format!("%ADD{}", &line[1..line.len() - 1]);
+                assert!(file.contents.contains(&definition), "{} in {}", line, file.name);
+            }
+        }
+        let copper = &files[0].contents;
+        assert!(copper.contains("%TF.FileFunction,Copper,L1,Top*%"));
+        assert!(copper.contains("D03*"));
+        let outline = &files.last().unwrap().contents;
+        assert!(outline.contains("%TF.FileFunction,Profile,NP*%") && outline.contains("D01*"));
+        let mask = files.iter().find(|file| file.layer == Layer::FMask).unwrap();
+        assert!(mask.contents.contains("%TF.FilePolarity,Negative*%"));
+
+        let protel = GerberOptions { protel_extensions: true, ..Default::default() };
+        let names: Vec<_> = gerber_files(&board, "demo", &protel).into_iter().map(|file| file.name).collect();
+        assert!(names.contains(&"demo-F_Cu.gtl".to_string()) && names.contains(&"demo-Edge_Cuts.gm1".to_string()));
+    }
+
+    #[test]
+    fn pads() {
+        let mut plot = Plot::default();
+        let mut pad = Pad {
+            number: "1".to_string(),
+            kind: PadKind::Smd,
+            shape: PadShape::Rect,
+            at: kicad_common::Position::new(0.0, 0.0, 90.0),
+            size: kicad_common::Size::new(2.0, 1.0),
+            drill: None,
+            layers: Vec::new(),
+            roundrect_rratio: None,
+            net: None,
+            pinfunction: None,
+            pintype: None,
+            property: None,
+            locked: false,
+            zone_connect: None,
+            thermal_gap: None,
+            thermal_bridge_width: None,
+            thermal_bridge_angle: None,
+            uuid: None,
+        };
+        plot.pad(Point::new(1.0, 1.0), &pad, 0.05);
+        assert_eq!(plot.apertures, ["R,1.100000X2.100000"]);
+        pad.shape = PadShape::RoundRect;
+        pad.at.angle = 45.0;
+        plot.pad(Point::new(1.0, 1.0), &pad, 0.0);
+        let Op::Region(outline) = &plot.ops[1] else { panic!() };
+        assert_eq!(outline.first(), outline.last());
+        assert!(outline.iter().all(|p| distance(*p, Point::new(1.0, 1.0)) <= 1.118034 + 1e-6));
+
+        let written = write(&plot, "Copper,L1,Top", "Positive", Point::default());
+        assert!(written.contains("%ADD10R,1.100000X2.100000*%\n"));
+        assert!(written.contains("D10*\nX1000000Y-1000000D03*\n"));
+    }
+}
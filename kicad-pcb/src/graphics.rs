@@ -0,0 +1,197 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    geometry::child_points,
+    uuid::item_uuid,
+    Effects, Point, Position, Stroke, Uuid,
+};
+use kicad_sexp::SexpBuilder;
+
+use crate::{layer::child_layer, Layer};
+
+/// Geometry of a board or footprint drawing.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShapeKind {
+    Line { start: Point, end: Point },
+    Rect { start: Point, end: Point },
+    /// A circle through `end` around `center`.
+    Circle { center: Point, end: Point },
+    /// A three-point arc, drawn from `start` through `mid` to `end`.
+    Arc { start: Point, mid: Point, end: Point },
+    Polygon(Vec<Point>),
+    /// A cubic Bézier curve given by its four control points.
+    Curve(Vec<Point>),
+}
+
+/// A `gr_*` board drawing or `fp_*` footprint drawing.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shape {
+    pub kind: ShapeKind,
+    pub layer: Layer,
+    pub stroke: Stroke,
+    pub fill: bool,
+    pub locked: bool,
+    pub uuid: Option<Uuid>,
+}
+
+impl Shape {
+    /// Returns true for the tags [`Shape`] can be read from.
+    pub(crate) fn is_shape_tag(tag: &str) -> bool {
+        matches!(
+            tag.strip_prefix("gr_").or_else(|| tag.strip_prefix("fp_")),
+            Some("line" | "rect" | "circle" | "arc" | "poly" | "curve")
+        )
+    }
+}
+
+impl Shape {
+    /// The drawing as KiCad writes it, tagged `fp_*` when `prefix` is
+    /// `"fp"` and `gr_*` when it is `"gr"`.
+    pub fn to_sexp(&self, prefix: &str) -> SexpBuilder {
+        let pts = |points: &[Point]| {
+            points.iter().fold(SexpBuilder::new("pts"), |pts, &point| pts.child(point_sexp("xy", point)))
+        };
+        let (tag, shape) = match &self.kind {
+            ShapeKind::Line { start, end } => ("line", vec![point_sexp("start", *start), point_sexp("end", *end)]),
+            ShapeKind::Rect { start, end } => ("rect", vec![point_sexp("start", *start), point_sexp("end", *end)]),
+            ShapeKind::Circle { center, end } => {
+                ("circle", vec![point_sexp("center", *center), point_sexp("end", *end)])
+            },
+            ShapeKind::Arc { start, mid, end } => {
+                ("arc", vec![point_sexp("start", *start), point_sexp("mid", *mid), point_sexp("end", *end)])
+            },
+            ShapeKind::Polygon(points) => ("poly", vec![pts(points)]),
+            ShapeKind::Curve(points) => ("curve", vec![pts(points)]),
+        };
+        let fills = matches!(self.kind, ShapeKind::Rect { .. } | ShapeKind::Circle { .. } | ShapeKind::Polygon(_));
+        let sexp = shape.into_iter().fold(SexpBuilder::new(&format!("{}_{}", prefix, tag)), SexpBuilder::child);
+        sexp.child(self.stroke.to_sexp())
+            .child_opt(fills.then(|| SexpBuilder::new("fill").symbol(if self.fill { "yes" } else { "no" })))
+            .child_opt(self.locked.then(|| SexpBuilder::new("locked").symbol("yes")))
+            .child(SexpBuilder::new("layer").string(&self.layer.to_string()))
+            .child_opt(self.uuid.map(|uuid| SexpBuilder::new("uuid").string(&uuid.to_string())))
+    }
+}
+
+/// `(tag x y)` for a point.
+pub(crate) fn point_sexp(tag: &str, point: Point) -> SexpBuilder {
+    SexpBuilder::new(tag).float(point.x).float(point.y)
+}
+
+/// `(at x y angle)`; the angle is left out when it is zero, unless
+/// `angle` asks for it.
+pub(crate) fn at_sexp(at: &Position, angle: bool) -> SexpBuilder {
+    let sexp = SexpBuilder::new("at").float(at.x).float(at.y);
+    if angle || at.angle != 0.0 { sexp.float(at.angle) } else { sexp }
+}
+
+impl FromSexp for Shape {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let kind = match &list.tag()[3..] {
+            "line" => ShapeKind::Line { start: list.parse_required("start")?, end: list.parse_required("end")? },
+            "rect" => ShapeKind::Rect { start: list.parse_required("start")?, end: list.parse_required("end")? },
+            "circle" => ShapeKind::Circle { center: list.parse_required("center")?, end: list.parse_required("end")? },
+            "arc" => ShapeKind::Arc {
+                start: list.parse_required("start")?,
+                mid: list.parse_required("mid")?,
+                end: list.parse_required("end")?,
+            },
+            "poly" => ShapeKind::Polygon(child_points(list)?),
+            _ => ShapeKind::Curve(child_points(list)?),
+        };
+        // Files before KiCad 7 give the width directly instead of a stroke.
+        let stroke = match list.parse("stroke")? {
+            Some(stroke) => stroke,
+            None => Stroke { width: list.child_number("width")?.unwrap_or_default(), ..Default::default() },
+        };
+        let fill = match list.child("fill") {
+            Some(fill) => matches!(fill.symbol(0).map_err(|e| e.within(list.tag()))?, "yes" | "solid"),
+            None => false,
+        };
+        Ok(Shape {
+            kind,
+            layer: child_layer(list)?,
+            stroke,
+            fill,
+            locked: list.flag("locked")?,
+            uuid: item_uuid(list)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextKind {
+    Reference,
+    Value,
+    User,
+}
+
+/// A `gr_text` board text or `fp_text` footprint text.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Text {
+    /// The `fp_text` type; `None` for board texts.
+    pub kind: Option<TextKind>,
+    pub text: String,
+    pub at: Position,
+    pub layer: Layer,
+    /// Drawn as a cutout in a filled box.
+    pub knockout: bool,
+    pub hide: bool,
+    pub effects: Effects,
+    pub uuid: Option<Uuid>,
+}
+
+impl Text {
+    /// The text as KiCad writes it: `fp_text` for footprint texts and
+    /// `gr_text` for board texts.
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let sexp = match self.kind {
+            Some(kind) => {
+                let kind = match kind {
+                    TextKind::Reference => "reference",
+                    TextKind::Value => "value",
+                    TextKind::User => "user",
+                };
+                SexpBuilder::new("fp_text").symbol(kind).string(&self.text)
+            },
+            None => SexpBuilder::new("gr_text").string(&self.text),
+        };
+        let layer = SexpBuilder::new("layer").string(&self.layer.to_string());
+        let effects = Effects { hide: false, ..self.effects.clone() };
+        sexp.child(at_sexp(&self.at, true))
+            .child(if self.knockout { layer.symbol("knockout") } else { layer })
+            .child_opt(self.hide.then(|| SexpBuilder::new("hide").symbol("yes")))
+            .child_opt(self.uuid.map(|uuid| SexpBuilder::new("uuid").string(&uuid.to_string())))
+            .child(effects.to_sexp())
+    }
+}
+
+impl FromSexp for Text {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let (kind, text) = if list.tag() == "fp_text" {
+            let kind = match list.symbol(0)? {
+                "reference" => TextKind::Reference,
+                "value" => TextKind::Value,
+                "user" => TextKind::User,
+                other => return Err(list.error(format!("unknown text type `{}`", other))),
+            };
+            (Some(kind), list.string(1)?)
+        } else {
+            (None, list.string(0)?)
+        };
+        let effects: Effects = list.parse("effects")?.unwrap_or_default();
+        Ok(Text {
+            kind,
+            text,
+            at: list.parse_required("at")?,
+            layer: child_layer(list)?,
+            knockout: list.child("layer").is_some_and(|layer| layer.has_symbol("knockout")),
+            hide: list.flag("hide")? || effects.hide,
+            effects,
+            uuid: item_uuid(list)?,
+        })
+    }
+}
@@ -0,0 +1,162 @@
+//! Checks component heights against per-area limits, such as the clearance
+//! under an enclosure lid.
+
+use std::{collections::HashMap, path::Path};
+
+use kicad_common::Point;
+
+use crate::{
+    bounds::{footprint_shape_points, Bounds},
+    Board, Footprint, Layer, Side,
+};
+
+/// Component body heights above the board surface, in millimeters.
+///
+/// KiCad does not store model dimensions, so heights come from this table,
+/// keyed either by footprint library ID or by 3D model file stem. A numeric
+/// `Height` footprint field overrides both.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeightTable {
+    pub by_lib_id: HashMap<String, f64>,
+    pub by_model: HashMap<String, f64>,
+}
+
+impl HeightTable {
+    pub fn new() -> Self {
+        HeightTable::default()
+    }
+
+    pub fn with_lib_id(mut self, lib_id: impl Into<String>, height: f64) -> Self {
+        self.by_lib_id.insert(lib_id.into(), height);
+        self
+    }
+
+    /// Adds a height for models named `stem`, e.g. `R_0603_1608Metric`.
+    pub fn with_model(mut self, stem: impl Into<String>, height: f64) -> Self {
+        self.by_model.insert(stem.into(), height);
+        self
+    }
+
+    /// Height of `footprint`, including the Z offset and scale of its first
+    /// visible model when the height comes from the model table.
+    pub fn height(&self, footprint: &Footprint) -> Option<f64> {
+        if let Some(height) = footprint.property("Height").and_then(|height| height.trim().parse().ok()) {
+            return Some(height);
+        }
+        if let Some(height) = self.by_lib_id.get(&footprint.lib_id) {
+            return Some(*height);
+        }
+        footprint.models.iter().filter(|model| !model.hide).find_map(|model| {
+            let stem = Path::new(&model.path).file_stem()?.to_str()?;
+            self.by_model.get(stem).map(|height| height * model.scale[2] + model.offset[2])
+        })
+    }
+}
+
+/// A maximum component height within a board area on one side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightLimit {
+    pub name: String,
+    pub side: Side,
+    /// Area outline in board coordinates.
+    pub area: Vec<Point>,
+    pub max_height: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightViolation {
+    pub reference: String,
+    pub limit: String,
+    pub height: f64,
+    pub max_height: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeightReport {
+    pub violations: Vec<HeightViolation>,
+    /// Components inside a limit area whose height is unknown.
+    pub unknown: Vec<String>,
+}
+
+impl HeightReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty() && self.unknown.is_empty()
+    }
+}
+
+/// Checks every footprint that overlaps a limit area on its side of the board.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(footprints = board.footprints.len(), limits = limits.len())))]
+pub fn check_heights(board: &Board, table: &HeightTable, limits: &[HeightLimit]) -> HeightReport {
+    let mut report = HeightReport::default();
+    for footprint in &board.footprints {
+        let Some(bounds) = footprint_bounds(footprint) else {
+            continue;
+        };
+        let side = footprint.side();
+        let reference = footprint.reference().unwrap_or_default().to_string();
+        let height = table.height(footprint);
+        for limit in limits.iter().filter(|limit| limit.side == side && bounds.overlaps(&limit.area)) {
+            match height {
+                Some(height) if height > limit.max_height => report.violations.push(HeightViolation {
+                    reference: reference.clone(),
+                    limit: limit.name.clone(),
+                    height,
+                    max_height: limit.max_height,
+                }),
+                Some(_) => {},
+                None => {
+                    report.unknown.push(reference.clone());
+                    break;
+                },
+            }
+        }
+    }
+    report
+}
+
+/// The courtyard extent of a footprint, falling back to its pads.
+fn footprint_bounds(footprint: &Footprint) -> Option<Bounds> {
+    let courtyard: Vec<_> = footprint
+        .shapes
+        .iter()
+        .filter(|shape| matches!(shape.layer, Layer::FCrtYd | Layer::BCrtYd))
+        .flat_map(|shape| footprint_shape_points(footprint, &shape.kind))
+        .collect();
+    if !courtyard.is_empty() {
+        return Bounds::of(courtyard);
+    }
+    Bounds::of(footprint.pads.iter().flat_map(|pad| {
+        let half = pad.size.width.max(pad.size.height) / 2.0;
+        let at = footprint.pad_position(pad);
+        [Point::new(at.x - half, at.y - half), Point::new(at.x + half, at.y + half)]
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Vec<Point> {
+        vec![Point::new(x0, y0), Point::new(x1, y0), Point::new(x1, y1), Point::new(x0, y1)]
+    }
+
+    #[test]
+    fn demo_board() {
+        let board = Board::parse(include_str!("../../reference-files/demo/demo.kicad_pcb")).unwrap();
+        let table = HeightTable::new()
+            .with_lib_id("Connector_PinHeader_2.54mm:PinHeader_1x03_P2.54mm_Vertical", 8.5)
+            .with_model("R_0603_1608Metric", 0.45)
+            .with_model("C_0603_1608Metric", 0.8);
+        let limit = |name: &str, side, area, max_height| HeightLimit { name: name.into(), side, area, max_height };
+        let limits = [
+            limit("lid", Side::Top, rect(100.0, 100.0, 130.0, 120.0), 5.0),
+            limit("standoff", Side::Bottom, rect(115.0, 105.0, 125.0, 110.0), 0.5),
+        ];
+
+        let report = check_heights(&board, &table, &limits);
+        let violations: Vec<_> = report.violations.iter().map(|v| (v.reference.as_str(), v.limit.as_str())).collect();
+        assert_eq!(violations, [("J1", "lid"), ("C1", "standoff")]);
+        assert_eq!(report.unknown, ["H1", "H2"]);
+        assert_eq!(table.height(board.footprint("R1").unwrap()), Some(0.45));
+    }
+}
@@ -0,0 +1,229 @@
+//! Hash indexes over a [`Board`] for analysis code doing many lookups by
+//! reference, net or UUID.
+
+use std::{collections::HashMap, ops::Deref, sync::OnceLock};
+
+use kicad_common::Uuid;
+
+use crate::{Board, Footprint, Net, Pad, Shape, Text, Track, Via, Zone};
+
+/// Where an item with a UUID lives in the [`Board`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ItemRef {
+    Footprint(usize),
+    Pad { footprint: usize, pad: usize },
+    FootprintShape { footprint: usize, shape: usize },
+    FootprintText { footprint: usize, text: usize },
+    Shape(usize),
+    Text(usize),
+    Track(usize),
+    Via(usize),
+    Zone(usize),
+}
+
+/// An item found by UUID.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Item<'a> {
+    Footprint(&'a Footprint),
+    Pad(&'a Footprint, &'a Pad),
+    FootprintShape(&'a Footprint, &'a Shape),
+    FootprintText(&'a Footprint, &'a Text),
+    Shape(&'a Shape),
+    Text(&'a Text),
+    Track(&'a Track),
+    Via(&'a Via),
+    Zone(&'a Zone),
+}
+
+impl ItemRef {
+    /// The item in `board`, `None` if the index is out of range.
+    pub fn resolve(self, board: &Board) -> Option<Item<'_>> {
+        let footprint = |i: usize| board.footprints.get(i);
+        Some(match self {
+            ItemRef::Footprint(i) => Item::Footprint(footprint(i)?),
+            ItemRef::Pad { footprint: f, pad } => {
+                let f = footprint(f)?;
+                Item::Pad(f, f.pads.get(pad)?)
+            },
+            ItemRef::FootprintShape { footprint: f, shape } => {
+                let f = footprint(f)?;
+                Item::FootprintShape(f, f.shapes.get(shape)?)
+            },
+            ItemRef::FootprintText { footprint: f, text } => {
+                let f = footprint(f)?;
+                Item::FootprintText(f, f.texts.get(text)?)
+            },
+            ItemRef::Shape(i) => Item::Shape(board.shapes.get(i)?),
+            ItemRef::Text(i) => Item::Text(board.texts.get(i)?),
+            ItemRef::Track(i) => Item::Track(board.tracks.get(i)?),
+            ItemRef::Via(i) => Item::Via(board.vias.get(i)?),
+            ItemRef::Zone(i) => Item::Zone(board.zones.get(i)?),
+        })
+    }
+}
+
+/// Lookup tables for one state of a board. Where keys repeat, the first
+/// item wins, as with the linear lookups on [`Board`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BoardIndex {
+    references: HashMap<String, usize>,
+    net_names: HashMap<String, u32>,
+    nets: HashMap<u32, usize>,
+    uuids: HashMap<Uuid, ItemRef>,
+}
+
+impl BoardIndex {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(footprints = board.footprints.len())))]
+    pub fn new(board: &Board) -> Self {
+        let mut index = BoardIndex::default();
+        for (i, net) in board.nets.iter().enumerate() {
+            index.nets.entry(net.number).or_insert(i);
+            index.net_names.entry(net.name.clone()).or_insert(net.number);
+        }
+
+        let mut uuid = |uuid: Option<Uuid>, item: ItemRef| {
+            if let Some(uuid) = uuid {
+                index.uuids.entry(uuid).or_insert(item);
+            }
+        };
+        for (i, footprint) in board.footprints.iter().enumerate() {
+            uuid(footprint.uuid, ItemRef::Footprint(i));
+            for (pad, item) in footprint.pads.iter().enumerate() {
+                uuid(item.uuid, ItemRef::Pad { footprint: i, pad });
+            }
+            for (shape, item) in footprint.shapes.iter().enumerate() {
+                uuid(item.uuid, ItemRef::FootprintShape { footprint: i, shape });
+            }
+            for (text, item) in footprint.texts.iter().enumerate() {
+                uuid(item.uuid, ItemRef::FootprintText { footprint: i, text });
+            }
+        }
+        for (i, shape) in board.shapes.iter().enumerate() {
+            uuid(shape.uuid, ItemRef::Shape(i));
+        }
+        for (i, text) in board.texts.iter().enumerate() {
+            uuid(text.uuid, ItemRef::Text(i));
+        }
+        for (i, track) in board.tracks.iter().enumerate() {
+            uuid(Some(track.uuid), ItemRef::Track(i));
+        }
+        for (i, via) in board.vias.iter().enumerate() {
+            uuid(Some(via.uuid), ItemRef::Via(i));
+        }
+        for (i, zone) in board.zones.iter().enumerate() {
+            uuid(zone.uuid, ItemRef::Zone(i));
+        }
+
+        for (i, footprint) in board.footprints.iter().enumerate() {
+            if let Some(reference) = footprint.reference() {
+                index.references.entry(reference.to_string()).or_insert(i);
+            }
+        }
+        index
+    }
+
+    /// Index into `footprints` of the footprint with `reference`.
+    pub fn footprint(&self, reference: &str) -> Option<usize> {
+        self.references.get(reference).copied()
+    }
+
+    /// Net number of the net called `name`.
+    pub fn net_number(&self, name: &str) -> Option<u32> {
+        self.net_names.get(name).copied()
+    }
+
+    /// Index into `nets` of net `number`.
+    pub fn net(&self, number: u32) -> Option<usize> {
+        self.nets.get(&number).copied()
+    }
+
+    pub fn item(&self, uuid: Uuid) -> Option<ItemRef> {
+        self.uuids.get(&uuid).copied()
+    }
+}
+
+/// A board with indexes built on first lookup. Mutable access goes through
+/// [`IndexedBoard::edit`], which drops the indexes, so they never go stale.
+#[derive(Clone, Debug, Default)]
+pub struct IndexedBoard {
+    board: Board,
+    index: OnceLock<BoardIndex>,
+}
+
+impl IndexedBoard {
+    pub fn new(board: Board) -> Self {
+        IndexedBoard { board, index: OnceLock::new() }
+    }
+
+    pub fn index(&self) -> &BoardIndex {
+        self.index.get_or_init(|| BoardIndex::new(&self.board))
+    }
+
+    /// Mutable access to the board; indexes are rebuilt on the next lookup.
+    pub fn edit(&mut self) -> &mut Board {
+        self.index.take();
+        &mut self.board
+    }
+
+    pub fn into_inner(self) -> Board {
+        self.board
+    }
+
+    pub fn footprint(&self, reference: &str) -> Option<&Footprint> {
+        self.index().footprint(reference).map(|i| &self.board.footprints[i])
+    }
+
+    pub fn net(&self, number: u32) -> Option<&Net> {
+        self.index().net(number).map(|i| &self.board.nets[i])
+    }
+
+    pub fn net_by_name(&self, name: &str) -> Option<&Net> {
+        self.net(self.index().net_number(name)?)
+    }
+
+    pub fn item(&self, uuid: Uuid) -> Option<Item<'_>> {
+        self.index().item(uuid)?.resolve(&self.board)
+    }
+}
+
+impl Deref for IndexedBoard {
+    type Target = Board;
+
+    fn deref(&self) -> &Board {
+        &self.board
+    }
+}
+
+impl From<Board> for IndexedBoard {
+    fn from(board: Board) -> Self {
+        IndexedBoard::new(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn lookups() {
+        let mut board = IndexedBoard::new(Board::parse(DEMO).unwrap());
+
+        assert_eq!(board.footprint("R2"), Board::footprint(&board, "R2"));
+        assert_eq!(board.net_by_name("GND"), Board::net_by_name(&board, "GND"));
+        assert_eq!(board.net(1), Board::net(&board, 1));
+        assert_eq!(board.footprint("R9"), None);
+
+        let via = board.vias[1].clone();
+        assert_eq!(board.item(via.uuid), Some(Item::Via(&board.vias[1])));
+        let pad = board.footprints[2].pads[0].uuid.unwrap();
+        assert!(matches!(board.item(pad), Some(Item::Pad(footprint, _)) if footprint.reference() == Some("R2")));
+
+        let r2 = board.index().footprint("R2").unwrap();
+        board.edit().footprints.remove(0);
+        assert_eq!(board.index().footprint("R2"), Some(r2 - 1));
+        assert_eq!(board.footprint("R2").unwrap().reference(), Some("R2"));
+        assert_eq!(board.index().item(via.uuid), Some(ItemRef::Via(1)));
+    }
+}
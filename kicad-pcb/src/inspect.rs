@@ -0,0 +1,278 @@
+//! Per-net figures as KiCad's net inspector shows them, as data for
+//! scripts: pad and via counts, routed length, missing connections, the
+//! closest approach of other nets' copper and the layers in use.
+//!
+//! Clearances are measured between pads, tracks, vias and zone fills on a
+//! shared copper layer, exactly for the shapes the router models: pads as
+//! rounded rectangles, so trapezoid and custom pads by their bounding size.
+//!
+//! ```no_run
+//! use kicad_pcb::{inspect::net_report, Board};
+//!
+//! let board = Board::parse(&std::fs::read_to_string("demo.kicad_pcb")?)?;
+//! for net in net_report(&board) {
+//!     println!("{}: {:.2} mm, {} unrouted", net.name, net.routed_length, net.unrouted);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeMap;
+
+use kicad_common::Point;
+
+use crate::{
+    bounds::Bounds,
+    route::{copper, missing, segment_distance, Copper, Outline},
+    Board, Layer,
+};
+
+/// What [`net_report`] found for one net.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetInfo {
+    pub number: u32,
+    pub name: String,
+    pub pads: usize,
+    pub vias: usize,
+    /// Total length of the net's tracks.
+    pub routed_length: f64,
+    /// Ratsnest lines still missing, see [`crate::route::ratsnest`].
+    pub unrouted: usize,
+    /// Smallest gap between the net's copper and copper of another net on
+    /// the same layer, zero where they touch. `None` if no other copper
+    /// shares a layer with the net.
+    pub min_clearance: Option<f64>,
+    /// Copper layers with the net's tracks, vias or zone fills, front to
+    /// back.
+    pub layers: Vec<Layer>,
+}
+
+/// The figures of every declared net but the unconnected net 0, in
+/// declaration order.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(nets)))]
+pub fn net_report(board: &Board) -> Vec<NetInfo> {
+    let mut nets: BTreeMap<u32, NetInfo> = board
+        .nets
+        .iter()
+        .filter(|net| net.number != 0)
+        .map(|net| (net.number, NetInfo { number: net.number, name: net.name.clone(), ..Default::default() }))
+        .collect();
+    let copper_layers = board.copper_layers();
+    let mut layers = |number: u32, on: &mut dyn Iterator<Item = Layer>| {
+        if let Some(info) = nets.get_mut(&number) {
+            info.layers.extend(on);
+        }
+    };
+    for track in &board.tracks {
+        layers(track.net, &mut std::iter::once(track.layer));
+    }
+    for via in &board.vias {
+        layers(via.net, &mut copper_layers.iter().copied().filter(|&layer| via.spans(layer)));
+    }
+    for zone in board.zones.iter().filter(|zone| zone.keepout.is_none()) {
+        layers(zone.net, &mut zone.filled_polygons.iter().map(|fill| fill.layer));
+    }
+
+    let pads = board.footprints.iter().flat_map(|footprint| &footprint.pads);
+    for number in pads.filter_map(|pad| pad.net.as_ref().map(|net| net.number)) {
+        nets.entry(number).and_modify(|info| info.pads += 1);
+    }
+    for via in &board.vias {
+        nets.entry(via.net).and_modify(|info| info.vias += 1);
+    }
+    for track in &board.tracks {
+        nets.entry(track.net).and_modify(|info| info.routed_length += track.length());
+    }
+
+    let mut items = copper(board);
+    for connection in missing(board, &items) {
+        nets.entry(connection.net).and_modify(|info| info.unrouted += 1);
+    }
+    items.extend(fill_edges(board));
+    for (number, clearance) in clearances(&items) {
+        nets.entry(number).and_modify(|info| info.min_clearance = Some(clearance));
+    }
+
+    let order: BTreeMap<u32, usize> = board.nets.iter().enumerate().map(|(i, net)| (net.number, i)).collect();
+    let mut report: Vec<NetInfo> = nets.into_values().collect();
+    report.sort_by_key(|info| order[&info.number]);
+    for info in &mut report {
+        info.layers.sort();
+        info.layers.dedup();
+    }
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("nets", report.len());
+    report
+}
+
+/// The outlines of the zone fills as copper with no width, enough to
+/// measure the gap to other copper outside them.
+fn fill_edges(board: &Board) -> Vec<Copper> {
+    let zones = board.zones.iter().filter(|zone| zone.keepout.is_none());
+    let fills = zones.flat_map(|zone| zone.filled_polygons.iter().map(move |fill| (zone.net, fill)));
+    fills
+        .filter(|(_, fill)| !fill.points.is_empty())
+        .map(|(net, fill)| {
+            let next = fill.points.iter().cycle().skip(1);
+            Copper {
+                net,
+                pad: false,
+                layers: vec![fill.layer],
+                outlines: fill.points.iter().zip(next).map(|(&a, &b)| Outline::Segment { a, b, radius: 0.0 }).collect(),
+                anchors: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// The smallest gap from each net's copper to other copper on the same
+/// layer, by net number.
+fn clearances(items: &[Copper]) -> BTreeMap<u32, f64> {
+    let bounds: Vec<Bounds> = items.iter().map(Copper::bounds).collect();
+    let mut best: BTreeMap<u32, f64> = BTreeMap::new();
+    for (i, a) in items.iter().enumerate() {
+        for (j, b) in items.iter().enumerate().skip(i + 1) {
+            if a.net == b.net || !a.layers.iter().any(|layer| b.layers.contains(layer)) {
+                continue;
+            }
+            let current = |net: u32| if net == 0 { 0.0 } else { best.get(&net).copied().unwrap_or(f64::INFINITY) };
+            let limit = current(a.net).max(current(b.net));
+            if bounds_gap(&bounds[i], &bounds[j]) >= limit {
+                continue;
+            }
+            let gap = copper_gap(a, b, limit);
+            for net in [a.net, b.net].into_iter().filter(|&net| net != 0) {
+                let entry = best.entry(net).or_insert(f64::INFINITY);
+                *entry = entry.min(gap);
+            }
+        }
+    }
+    best.retain(|_, gap| gap.is_finite());
+    best
+}
+
+/// A lower bound for the gap between anything inside `a` and `b`.
+fn bounds_gap(a: &Bounds, b: &Bounds) -> f64 {
+    let dx = (a.min.x - b.max.x).max(b.min.x - a.max.x).max(0.0);
+    let dy = (a.min.y - b.max.y).max(b.min.y - a.max.y).max(0.0);
+    dx.hypot(dy)
+}
+
+/// The gap between two pieces of copper, or `limit` if it is no smaller.
+pub(crate) fn copper_gap(a: &Copper, b: &Copper, limit: f64) -> f64 {
+    let mut best = limit;
+    for x in &a.outlines {
+        let x_bounds = x.bounds();
+        for y in &b.outlines {
+            if bounds_gap(&x_bounds, &y.bounds()) < best {
+                best = best.min(outline_gap(x, y));
+            }
+        }
+    }
+    best
+}
+
+/// Every outline is a point, segment or rectangle grown by a radius.
+fn core(outline: &Outline) -> (Vec<Point>, f64) {
+    match *outline {
+        Outline::Segment { a, b, radius } => (vec![a, b], radius),
+        Outline::Rect { center, angle, half, radius } => {
+            let corner = |x: f64, y: f64| center + Point::new(x * half.x, y * half.y).rotated(angle);
+            (vec![corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)], radius)
+        },
+    }
+}
+
+fn outline_gap(x: &Outline, y: &Outline) -> f64 {
+    let ((p, rp), (q, rq)) = (core(x), core(y));
+    if inside(&p, q[0]) || inside(&q, p[0]) {
+        return 0.0;
+    }
+    let mut gap = f64::INFINITY;
+    for (a, b) in edges(&p) {
+        for (c, d) in edges(&q) {
+            gap = gap.min(segments_gap(a, b, c, d));
+        }
+    }
+    (gap - rp - rq).max(0.0)
+}
+
+/// The sides of a rectangle, or the one segment.
+fn edges(points: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    let count = if points.len() == 2 { 1 } else { points.len() };
+    (0..count).map(|i| (points[i], points[(i + 1) % points.len()]))
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+/// Whether `p` is strictly inside the convex polygon `points`.
+fn inside(points: &[Point], p: Point) -> bool {
+    if points.len() < 3 {
+        return false;
+    }
+    let sides: Vec<f64> = edges(points).map(|(a, b)| cross(a, b, p)).collect();
+    sides.iter().all(|&side| side > 0.0) || sides.iter().all(|&side| side < 0.0)
+}
+
+fn segments_gap(a: Point, b: Point, c: Point, d: Point) -> f64 {
+    let crosses = |p, q, r, s| (cross(p, q, r) > 0.0) != (cross(p, q, s) > 0.0);
+    if crosses(a, b, c, d) && crosses(c, d, a, b) {
+        return 0.0;
+    }
+    [segment_distance(a, c, d), segment_distance(b, c, d), segment_distance(c, a, b), segment_distance(d, a, b)]
+        .into_iter()
+        .fold(f64::INFINITY, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn demo_board() {
+        let board = Board::parse(DEMO).unwrap();
+        let report = net_report(&board);
+        let names: Vec<_> = report.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, ["VIN", "VOUT", "GND"]);
+
+        let vin = &report[0];
+        assert_eq!((vin.pads, vin.vias, vin.unrouted), (3, 1, 0));
+        assert!((vin.routed_length - (5.175 + 3.0 + 5.825 + 4.225 + 5.5)).abs() < 1e-9);
+        assert_eq!(vin.layers, [Layer::FCu, Layer::BCu]);
+        let gnd = &report[2];
+        assert_eq!((gnd.vias, gnd.layers.as_slice()), (1, [Layer::FCu, Layer::BCu].as_slice()));
+
+        for (info, expected) in report.iter().zip([0.65, 0.84, 0.65]) {
+            assert!((info.min_clearance.unwrap() - expected).abs() < 1e-9, "{:?}", info);
+        }
+
+        let mut unrouted = board.clone();
+        unrouted.tracks.retain(|track| track.net != 1);
+        let vin = &net_report(&unrouted)[0];
+        assert_eq!((vin.routed_length, vin.unrouted), (0.0, 2));
+        assert_eq!(vin.layers, [Layer::FCu, Layer::BCu]);
+    }
+
+    #[test]
+    fn arc_length() {
+        let board = Board::parse(DEMO).unwrap();
+        let arc = board.tracks.iter().find(|track| track.is_arc()).unwrap();
+        let polyline = crate::fill::arc_points(arc.start, arc.mid.unwrap(), arc.end, 1e-6);
+        let expected: f64 = polyline.windows(2).map(|pair| crate::bounds::distance(pair[0], pair[1])).sum();
+        assert!((arc.length() - expected).abs() < 1e-4, "{} {}", arc.length(), expected);
+    }
+
+    #[test]
+    fn gaps() {
+        let pad = Outline::Rect { center: Point::new(0.0, 0.0), angle: 45.0, half: Point::new(1.0, 1.0), radius: 0.0 };
+        let track = Outline::Segment { a: Point::new(3.0, -5.0), b: Point::new(3.0, 5.0), radius: 0.25 };
+        assert!((outline_gap(&pad, &track) - (3.0 - 2f64.sqrt() - 0.25)).abs() < 1e-12);
+        let crossing = Outline::Segment { a: Point::new(-5.0, 0.5), b: Point::new(5.0, 0.5), radius: 0.0 };
+        assert_eq!(outline_gap(&track, &crossing), 0.0);
+        let within = Outline::Segment { a: Point::new(0.1, 0.0), b: Point::new(0.2, 0.0), radius: 0.0 };
+        assert_eq!(outline_gap(&pad, &within), 0.0);
+    }
+}
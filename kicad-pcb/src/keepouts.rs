@@ -0,0 +1,209 @@
+//! Keepout rule areas around mounting holes and along the board edge, the
+//! copper-free margins a board house asks for, added to the board rather
+//! than drawn by hand.
+//!
+//! [`add_hole_keepouts`] puts a round keepout on every copper layer around
+//! each mounting hole: the footprints of KiCad's `MountingHole` library,
+//! and footprints whose pads are all non-plated holes. The circle covers
+//! the hole's pads, copper ring included, and the clearance.
+//! [`add_edge_keepouts`] does the same along each `Edge.Cuts` drawing, a
+//! band of the clearance on both sides of it. Keepouts named like a zone
+//! the board has are left out, so running either again adds nothing.
+//!
+//! The new zones get UUIDs from [`Board::assign_missing_uuids`], and
+//! [`write_zones`](crate::layout::write_zones) adds them to the board file.
+//!
+//! ```no_run
+//! use kicad_common::UuidGenerator;
+//! use kicad_pcb::{
+//!     keepouts::{add_edge_keepouts, add_hole_keepouts, KeepoutOptions},
+//!     layout::write_zones,
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let options = KeepoutOptions { clearance: 1.0, ..Default::default() };
+//! add_hole_keepouts(&mut board, &options);
+//! add_edge_keepouts(&mut board, &options);
+//! board.assign_missing_uuids(&mut UuidGenerator::deterministic("keepouts"));
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_zones(&board, &mut doc)?;
+//! std::fs::write("demo.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use i_overlay::core::overlay_rule::OverlayRule;
+use kicad_common::Point;
+
+use crate::{
+    bounds::{bezier_points, distance, map_points},
+    fill::{arc_points, capsule, circle, overlay, shapes, Contour},
+    Board, Footprint, Keepout, Layer, LayerClass, LayerSelector, PadKind, ShapeKind, Zone,
+};
+
+/// What [`add_hole_keepouts`] and [`add_edge_keepouts`] add.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeepoutOptions {
+    /// Distance kept free around the holes, and on each side of the edge.
+    pub clearance: f64,
+    /// What the keepouts forbid.
+    pub keepout: Keepout,
+    /// Largest distance between an arc and the segments standing in for it.
+    /// The keepouts are made that much larger rather than smaller.
+    pub max_error: f64,
+}
+
+impl Default for KeepoutOptions {
+    /// A 0.5 mm margin free of zone fills, tracks and vias.
+    fn default() -> Self {
+        KeepoutOptions {
+            clearance: 0.5,
+            keepout: Keepout { tracks: true, vias: true, copperpour: true, ..Default::default() },
+            max_error: 0.005,
+        }
+    }
+}
+
+/// Adds a keepout around each mounting hole of `board`, named after the
+/// hole's reference, as in `H1 keepout`. Returns the number added.
+pub fn add_hole_keepouts(board: &mut Board, options: &KeepoutOptions) -> usize {
+    let mut keepouts = Vec::new();
+    for footprint in board.footprints.iter().filter(|footprint| is_mounting_hole(footprint)) {
+        let name = format!("{} keepout", footprint.reference().unwrap_or_default());
+        // Around the largest hole, wide enough for the pads around it too.
+        let holes = footprint.pads.iter().filter_map(|pad| Some((pad, pad.drill?)));
+        let Some((pad, drill)) = holes.max_by(|(_, a), (_, b)| a.size.width.total_cmp(&b.size.width)) else {
+            continue;
+        };
+        let center = footprint.pad_position(pad) + drill.offset.rotated(pad.at.angle);
+        let reach = footprint.pads.iter().map(|pad| {
+            let hole = pad.drill.map_or(0.0, |drill| drill.size.width.max(drill.size.height));
+            distance(center, footprint.pad_position(pad)) + pad.size.width.max(pad.size.height).max(hole) / 2.0
+        });
+        let radius = reach.fold(0.0, f64::max) + options.clearance;
+        keepouts.push((name, vec![circle(center, radius, options.max_error)]));
+    }
+    add_keepouts(board, keepouts, options)
+}
+
+/// Adds a keepout along each `Edge.Cuts` drawing of `board` and its
+/// footprints, named `Edge keepout`, `Edge keepout 2` and so on. Returns
+/// the number added.
+pub fn add_edge_keepouts(board: &mut Board, options: &KeepoutOptions) -> usize {
+    let edges = board.shapes.iter().filter(|shape| shape.layer == Layer::EdgeCuts).map(|shape| shape.kind.clone());
+    let footprint_edges = board.footprints.iter().flat_map(|footprint| {
+        let edges = footprint.shapes.iter().filter(|shape| shape.layer == Layer::EdgeCuts);
+        edges.map(|shape| map_points(&shape.kind, |p| footprint.to_board(p)))
+    });
+    // Closed drawings are cut into open pieces, so that no band has a hole.
+    let mut pieces: Vec<Vec<Point>> = Vec::new();
+    for kind in edges.chain(footprint_edges) {
+        match kind {
+            ShapeKind::Line { start, end } => pieces.push(vec![start, end]),
+            ShapeKind::Arc { start, mid, end } => pieces.push(arc_points(start, mid, end, options.max_error)),
+            ShapeKind::Curve(points) => pieces.push(bezier_points(&points)),
+            ShapeKind::Rect { start, end } => {
+                let corners = [start, Point::new(end.x, start.y), end, Point::new(start.x, end.y)];
+                pieces.extend((0..4).map(|i| vec![corners[i], corners[(i + 1) % 4]]));
+            },
+            ShapeKind::Polygon(points) => {
+                pieces.extend((0..points.len()).map(|i| vec![points[i], points[(i + 1) % points.len()]]));
+            },
+            ShapeKind::Circle { center, end } => {
+                let radius = distance(center, end);
+                let at = |angle: f64| Point::new(center.x + radius * angle.cos(), center.y + radius * angle.sin());
+                let half = std::f64::consts::FRAC_PI_2;
+                for (from, through, to) in [(0.0, half, 2.0 * half), (2.0 * half, 3.0 * half, 4.0 * half)] {
+                    pieces.push(arc_points(at(from), at(through), at(to), options.max_error));
+                }
+            },
+        }
+    }
+    let mut keepouts = Vec::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        let capsules = piece.windows(2).map(|ends| capsule(ends[0], ends[1], options.clearance, options.max_error));
+        let band = overlay(&shapes(capsules.collect()), &Vec::new(), OverlayRule::Subject);
+        let name = match i {
+            0 => "Edge keepout".to_string(),
+            i => format!("Edge keepout {}", i + 1),
+        };
+        keepouts.push((name, band.into_iter().filter_map(|shape| shape.into_iter().next()).collect()));
+    }
+    add_keepouts(board, keepouts, options)
+}
+
+/// Mounting holes are recognized by the `MountingHole` library, or by
+/// having nothing but non-plated holes.
+fn is_mounting_hole(footprint: &Footprint) -> bool {
+    let holes = !footprint.pads.is_empty() && footprint.pads.iter().all(|pad| pad.kind == PadKind::NpThruHole);
+    footprint.lib_id.starts_with("MountingHole:") || holes
+}
+
+/// Adds a keepout on every copper layer for each named outline, leaving out
+/// the names the board's zones have.
+fn add_keepouts(board: &mut Board, keepouts: Vec<(String, Vec<Contour>)>, options: &KeepoutOptions) -> usize {
+    let mut added = 0;
+    for (name, outlines) in keepouts {
+        if board.zones.iter().any(|zone| zone.name.as_deref() == Some(name.as_str())) {
+            continue;
+        }
+        for outline in outlines.into_iter().filter(|outline| outline.len() >= 3) {
+            board.zones.push(Zone {
+                layers: vec![LayerSelector::All(LayerClass::Cu)],
+                name: Some(name.clone()),
+                keepout: Some(options.keepout),
+                outline: outline.into_iter().map(|[x, y]| Point::new(x, y)).collect(),
+                ..Default::default()
+            });
+            added += 1;
+        }
+    }
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::UuidGenerator;
+
+    use super::*;
+    use crate::{bounds::polygon_contains, layout::write_zones};
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn holes() {
+        let mut board = Board::parse(DEMO).unwrap();
+        let options = KeepoutOptions { clearance: 1.0, ..Default::default() };
+        assert_eq!(add_hole_keepouts(&mut board, &options), 2);
+        let zone = board.zones.iter().find(|zone| zone.name.as_deref() == Some("H1 keepout")).unwrap();
+        assert_eq!(zone.keepout, Some(options.keepout));
+        assert!(zone.on_layer(Layer::BCu));
+        let h1 = board.footprint("H1").unwrap().at.point();
+        assert!(polygon_contains(&zone.outline, h1 + Point::new(0.0, 2.5)));
+        assert!(!polygon_contains(&zone.outline, h1 + Point::new(0.0, 3.5)));
+        assert_eq!(add_hole_keepouts(&mut board, &options), 0);
+
+        board.assign_missing_uuids(&mut UuidGenerator::deterministic("keepouts"));
+        let mut doc = kicad_sexp::cst::parse(DEMO).unwrap();
+        write_zones(&board, &mut doc).unwrap();
+        let written = Board::parse(&doc.to_string()).unwrap();
+        let zone = written.zones.iter().find(|zone| zone.name.as_deref() == Some("H2 keepout")).unwrap();
+        assert_eq!(zone.keepout, Some(options.keepout));
+        assert_eq!(zone.layers, [LayerSelector::All(LayerClass::Cu)]);
+    }
+
+    #[test]
+    fn edges() {
+        let mut board = Board::parse(DEMO).unwrap();
+        let options = KeepoutOptions::default();
+        // The demo's outline is one rectangle, from (100, 100) to (130, 120).
+        assert_eq!(add_edge_keepouts(&mut board, &options), 4);
+        let keepouts = board.zones.iter().filter(|zone| zone.name.as_deref().is_some_and(|n| n.starts_with("Edge")));
+        let inside = |p: Point| keepouts.clone().any(|zone| polygon_contains(&zone.outline, p));
+        assert!(inside(Point::new(115.0, 100.4)) && inside(Point::new(115.0, 99.6)));
+        assert!(inside(Point::new(129.6, 119.6)));
+        assert!(!inside(Point::new(115.0, 100.6)) && !inside(Point::new(115.0, 110.0)));
+        assert_eq!(add_edge_keepouts(&mut board, &options), 0);
+    }
+}
@@ -0,0 +1,414 @@
+use std::{fmt, str::FromStr};
+
+use kicad_common::convert::{atom_str, FromSexp, List, Result};
+use kicad_sexp::Sexp;
+
+/// A board layer, identified by its canonical name such as `F.Cu`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Layer {
+    FCu,
+    /// Inner copper layer `In1.Cu` through `In30.Cu`.
+    InCu(u8),
+    BCu,
+    FAdhes,
+    BAdhes,
+    FPaste,
+    BPaste,
+    FSilkS,
+    BSilkS,
+    FMask,
+    BMask,
+    DwgsUser,
+    CmtsUser,
+    Eco1User,
+    Eco2User,
+    EdgeCuts,
+    Margin,
+    FCrtYd,
+    BCrtYd,
+    FFab,
+    BFab,
+    /// User layer `User.1` through `User.45`.
+    User(u8),
+}
+
+/// The technical role shared by a front/back pair of layers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LayerClass {
+    Cu,
+    Adhes,
+    Paste,
+    SilkS,
+    Mask,
+    CrtYd,
+    Fab,
+}
+
+impl LayerClass {
+    fn from_suffix(suffix: &str) -> Option<Self> {
+        Some(match suffix {
+            "Cu" => LayerClass::Cu,
+            "Adhes" => LayerClass::Adhes,
+            "Paste" => LayerClass::Paste,
+            "SilkS" => LayerClass::SilkS,
+            "Mask" => LayerClass::Mask,
+            "CrtYd" => LayerClass::CrtYd,
+            "Fab" => LayerClass::Fab,
+            _ => return None,
+        })
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            LayerClass::Cu => "Cu",
+            LayerClass::Adhes => "Adhes",
+            LayerClass::Paste => "Paste",
+            LayerClass::SilkS => "SilkS",
+            LayerClass::Mask => "Mask",
+            LayerClass::CrtYd => "CrtYd",
+            LayerClass::Fab => "Fab",
+        }
+    }
+}
+
+const SIDED: [(Layer, Layer, LayerClass); 7] = [
+    (Layer::FCu, Layer::BCu, LayerClass::Cu),
+    (Layer::FAdhes, Layer::BAdhes, LayerClass::Adhes),
+    (Layer::FPaste, Layer::BPaste, LayerClass::Paste),
+    (Layer::FSilkS, Layer::BSilkS, LayerClass::SilkS),
+    (Layer::FMask, Layer::BMask, LayerClass::Mask),
+    (Layer::FCrtYd, Layer::BCrtYd, LayerClass::CrtYd),
+    (Layer::FFab, Layer::BFab, LayerClass::Fab),
+];
+
+const UNSIDED: [(Layer, &str); 6] = [
+    (Layer::DwgsUser, "Dwgs.User"),
+    (Layer::CmtsUser, "Cmts.User"),
+    (Layer::Eco1User, "Eco1.User"),
+    (Layer::Eco2User, "Eco2.User"),
+    (Layer::EdgeCuts, "Edge.Cuts"),
+    (Layer::Margin, "Margin"),
+];
+
+impl Layer {
+    pub fn is_copper(&self) -> bool {
+        matches!(self, Layer::FCu | Layer::InCu(_) | Layer::BCu)
+    }
+
+    pub fn is_front(&self) -> bool {
+        SIDED.iter().any(|(front, _, _)| front == self)
+    }
+
+    pub fn is_back(&self) -> bool {
+        SIDED.iter().any(|(_, back, _)| back == self)
+    }
+
+    /// The class of a front, back or inner copper layer.
+    pub fn class(&self) -> Option<LayerClass> {
+        match self {
+            Layer::InCu(_) => Some(LayerClass::Cu),
+            _ => SIDED.iter().find(|(front, back, _)| front == self || back == self).map(|(_, _, class)| *class),
+        }
+    }
+
+    /// The number KiCad 9 gives the layer in the board's layer table.
+    pub fn ordinal(&self) -> u32 {
+        match *self {
+            Layer::FCu => 0,
+            Layer::FMask => 1,
+            Layer::BCu => 2,
+            Layer::BMask => 3,
+            Layer::InCu(n) => 2 + 2 * u32::from(n),
+            Layer::FSilkS => 5,
+            Layer::BSilkS => 7,
+            Layer::FAdhes => 9,
+            Layer::BAdhes => 11,
+            Layer::FPaste => 13,
+            Layer::BPaste => 15,
+            Layer::DwgsUser => 17,
+            Layer::CmtsUser => 19,
+            Layer::Eco1User => 21,
+            Layer::Eco2User => 23,
+            Layer::EdgeCuts => 25,
+            Layer::Margin => 27,
+            Layer::BCrtYd => 29,
+            Layer::FCrtYd => 31,
+            Layer::BFab => 33,
+            Layer::FFab => 35,
+            Layer::User(n) => 37 + 2 * u32::from(n),
+        }
+    }
+
+    /// The same layer on the other side of the board; unsided layers map to themselves.
+    pub fn flipped(&self) -> Layer {
+        for (front, back, _) in SIDED {
+            if *self == front {
+                return back;
+            }
+            if *self == back {
+                return front;
+            }
+        }
+        *self
+    }
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layer::InCu(n) => return write!(f, "In{}.Cu", n),
+            Layer::User(n) => return write!(f, "User.{}", n),
+            _ => {},
+        }
+        if let Some((front, _, class)) = SIDED.iter().find(|(front, back, _)| front == self || back == self) {
+            let side = if front == self { "F" } else { "B" };
+            return write!(f, "{}.{}", side, class.suffix());
+        }
+        let (_, name) = UNSIDED.iter().find(|(layer, _)| layer == self).expect("every layer has a name");
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseLayerError(pub String);
+
+impl fmt::Display for ParseLayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown layer `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseLayerError {}
+
+impl FromStr for Layer {
+    type Err = ParseLayerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let err = || ParseLayerError(s.to_string());
+        if let Some((layer, _)) = UNSIDED.iter().find(|(_, name)| *name == s) {
+            return Ok(*layer);
+        }
+        if let Some(n) = s.strip_prefix("User.") {
+            return n.parse().ok().filter(|n| (1..=45).contains(n)).map(Layer::User).ok_or_else(err);
+        }
+        if let Some(n) = s.strip_prefix("In").and_then(|s| s.strip_suffix(".Cu")) {
+            return n.parse().ok().filter(|n| (1..=30).contains(n)).map(Layer::InCu).ok_or_else(err);
+        }
+        let (side, suffix) = s.split_once('.').ok_or_else(err)?;
+        let class = LayerClass::from_suffix(suffix).ok_or_else(err)?;
+        let (front, back, _) = SIDED.iter().find(|(_, _, c)| *c == class).unwrap();
+        match side {
+            "F" => Ok(*front),
+            "B" => Ok(*back),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// Reads a layer name atom.
+pub(crate) fn layer_arg(list: &List, index: usize) -> Result<Layer> {
+    list.string(index)?.parse().map_err(|e: ParseLayerError| list.error(e.to_string()))
+}
+
+/// Reads the `(layer "F.Cu")` child of `list`.
+pub(crate) fn child_layer(list: &List) -> Result<Layer> {
+    let layer = list.child("layer").ok_or_else(|| list.error("missing (layer ...)"))?;
+    layer_arg(&layer, 0).map_err(|e| e.within(list.tag()))
+}
+
+/// A layer name as written in pad and zone layer lists, which may use
+/// wildcards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LayerSelector {
+    Layer(Layer),
+    /// `*.Cu`, `*.Mask`, ...: every layer of the class, including inner copper.
+    All(LayerClass),
+    /// `F&B.Cu`, `F&B.Mask`, ...: the front and back layers of the class.
+    FrontAndBack(LayerClass),
+}
+
+impl LayerSelector {
+    pub fn matches(&self, layer: Layer) -> bool {
+        match self {
+            LayerSelector::Layer(selected) => *selected == layer,
+            LayerSelector::All(class) => layer.class() == Some(*class),
+            LayerSelector::FrontAndBack(class) => {
+                layer.class() == Some(*class) && (layer.is_front() || layer.is_back())
+            },
+        }
+    }
+}
+
+impl fmt::Display for LayerSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayerSelector::Layer(layer) => layer.fmt(f),
+            LayerSelector::All(class) => write!(f, "*.{}", class.suffix()),
+            LayerSelector::FrontAndBack(class) => write!(f, "F&B.{}", class.suffix()),
+        }
+    }
+}
+
+impl FromStr for LayerSelector {
+    type Err = ParseLayerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let class = |suffix| LayerClass::from_suffix(suffix).ok_or_else(|| ParseLayerError(s.to_string()));
+        if let Some(suffix) = s.strip_prefix("*.") {
+            return Ok(LayerSelector::All(class(suffix)?));
+        }
+        if let Some(suffix) = s.strip_prefix("F&B.") {
+            return Ok(LayerSelector::FrontAndBack(class(suffix)?));
+        }
+        s.parse().map(LayerSelector::Layer)
+    }
+}
+
+/// Reads every argument of a `(layers ...)` list.
+pub(crate) fn layer_selectors(list: &List) -> Result<Vec<LayerSelector>> {
+    (0..list.args().len())
+        .map(|i| list.string(i)?.parse().map_err(|e: ParseLayerError| list.error(e.to_string())))
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LayerKind {
+    #[default]
+    Signal,
+    Power,
+    Mixed,
+    Jumper,
+    User,
+}
+
+/// An entry of the board's `(layers ...)` table, as in `(0 "F.Cu" signal)`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerDef {
+    /// File ordinal; KiCad 9 numbers front and back copper 0 and 2.
+    pub ordinal: u32,
+    pub layer: Layer,
+    pub kind: LayerKind,
+    /// Name shown in the editor when it differs from the canonical one.
+    pub user_name: Option<String>,
+}
+
+impl LayerDef {
+    /// The entry KiCad makes for `layer` on a new board: a signal layer
+    /// for copper, a user layer with KiCad's default name for the others.
+    pub fn new(layer: Layer) -> Self {
+        let user_name = match layer {
+            Layer::FAdhes => Some("F.Adhesive"),
+            Layer::BAdhes => Some("B.Adhesive"),
+            Layer::FSilkS => Some("F.Silkscreen"),
+            Layer::BSilkS => Some("B.Silkscreen"),
+            Layer::DwgsUser => Some("User.Drawings"),
+            Layer::CmtsUser => Some("User.Comments"),
+            Layer::Eco1User => Some("User.Eco1"),
+            Layer::Eco2User => Some("User.Eco2"),
+            Layer::FCrtYd => Some("F.Courtyard"),
+            Layer::BCrtYd => Some("B.Courtyard"),
+            _ => None,
+        };
+        let kind = if layer.is_copper() { LayerKind::Signal } else { LayerKind::User };
+        LayerDef { ordinal: layer.ordinal(), layer, kind, user_name: user_name.map(str::to_string) }
+    }
+}
+
+/// Reads the board's `(layers ...)` table, whose entries start with a
+/// number rather than a tag.
+pub(crate) fn layer_table(list: &List) -> Result<Vec<LayerDef>> {
+    let mut defs = Vec::new();
+    for arg in list.args() {
+        let Sexp::List(items) = arg else {
+            return Err(list.error("expected a layer definition"));
+        };
+        let atom = |index: usize| -> Result<String> {
+            items
+                .get(index)
+                .and_then(atom_str)
+                .map(|s| s.into_owned())
+                .ok_or_else(|| list.error(format!("layer definition {} is incomplete", defs.len() + 1)))
+        };
+        let ordinal = atom(0)?;
+        let name = atom(1)?;
+        let kind = match atom(2)?.as_str() {
+            "signal" => LayerKind::Signal,
+            "power" => LayerKind::Power,
+            "mixed" => LayerKind::Mixed,
+            "jumper" => LayerKind::Jumper,
+            "user" => LayerKind::User,
+            other => return Err(list.error(format!("unknown layer type `{}`", other))),
+        };
+        defs.push(LayerDef {
+            ordinal: ordinal.parse().map_err(|_| list.error(format!("invalid layer number `{}`", ordinal)))?,
+            layer: name.parse().map_err(|e: ParseLayerError| list.error(e.to_string()))?,
+            kind,
+            user_name: atom(3).ok(),
+        });
+    }
+    Ok(defs)
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Layer {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Layer {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        kicad_common::convert::deserialize_from_str(deserializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LayerSelector {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LayerSelector {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        kicad_common::convert::deserialize_from_str(deserializer)
+    }
+}
+
+impl FromSexp for Layer {
+    fn from_sexp(list: &List) -> Result<Self> {
+        layer_arg(list, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for name in ["F.Cu", "In12.Cu", "B.Cu", "B.SilkS", "F.CrtYd", "Edge.Cuts", "Margin", "User.4", "Eco2.User"] {
+            assert_eq!(name.parse::<Layer>().unwrap().to_string(), name);
+        }
+        assert!("In0.Cu".parse::<Layer>().is_err());
+        assert!("X.Cu".parse::<Layer>().is_err());
+        assert_eq!(Layer::BMask.flipped(), Layer::FMask);
+        assert!(Layer::InCu(2).is_copper() && !Layer::InCu(2).is_front());
+    }
+
+    #[test]
+    fn selectors() {
+        let all: LayerSelector = "*.Cu".parse().unwrap();
+        let outer: LayerSelector = "F&B.Cu".parse().unwrap();
+
+        assert!(all.matches(Layer::InCu(3)) && all.matches(Layer::BCu) && !all.matches(Layer::BMask));
+        assert!(outer.matches(Layer::FCu) && !outer.matches(Layer::InCu(1)));
+        assert_eq!(outer.to_string(), "F&B.Cu");
+        assert_eq!("F.Paste".parse(), Ok(LayerSelector::Layer(Layer::FPaste)));
+    }
+}
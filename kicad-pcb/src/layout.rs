@@ -0,0 +1,1081 @@
+//! Board content built in code, for layout generators such as keyboard and
+//! LED panel tools: footprints placed from a library with nets on their
+//! pads, and tracks and vias for regular structures a router is not needed
+//! for.
+//!
+//! Footprints are copied from their library file the way KiCad places
+//! them: turned by the footprint's rotation and, on the bottom side,
+//! flipped, with their layers swapped and their text mirrored.
+//! [`write_footprints`] adds the placed footprints, and the nets they
+//! use, to the board file, and [`write_tracks`] the new tracks and vias.
+//!
+//! Tracks and vias are only checked against the board's nets and layers
+//! when they are added; with the `render` feature,
+//! [`Board::check_clearance`] measures them against the copper of other
+//! nets.
+//!
+//! Zones are added with the settings KiCad gives a new zone, then filled
+//! through a [`ZoneFiller`]: [`FillOptions`](crate::fill::FillOptions)
+//! with the `render` feature, or a closure that hands the board to
+//! another tool. [`write_zones`] adds the new zones to the board file with
+//! their fills and writes the priorities of the others.
+//!
+//! ```no_run
+//! use kicad_common::{Point, UuidGenerator};
+//! use kicad_pcb::{
+//!     layout::{write_footprints, write_tracks, write_zones, ZoneSettings},
+//!     BoardBuilder, FootprintLib, Layer, Side,
+//! };
+//! use kicad_sexp::source::FsSource;
+//!
+//! let source = FsSource::new();
+//! let library = FootprintLib::open(&source, "demo.pretty")?;
+//! let builder = BoardBuilder::new();
+//! let mut board = builder.build()?;
+//! board.place_footprint(&library, "demo:R_0603_1608Metric", Point::new(110.0, 105.0), 90.0, Side::Top, "R1")?;
+//! board.connect_pad("R1", "1", "VIN")?;
+//! let mut generator = UuidGenerator::deterministic("panel");
+//! board.assign_missing_uuids(&mut generator);
+//! let (pad, end) = (Point::new(110.0, 105.825), Point::new(110.0, 108.0));
+//! board.route_segment("VIN", pad, end, 0.25, Layer::FCu, &mut generator)?;
+//! board.add_via("VIN", end, 0.6, 0.3, &[Layer::FCu, Layer::BCu], &mut generator)?;
+//! let src = builder.to_string();
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_footprints(&board, &library, &mut doc)?;
+//! write_tracks(&board, &mut doc)?;
+//! let outline = [(100.0, 100.0), (130.0, 100.0), (130.0, 120.0), (100.0, 120.0)].map(|(x, y)| Point::new(x, y));
+//! board.add_zone("GND", Layer::BCu, &outline, &ZoneSettings::default())?;
+//! board.assign_missing_uuids(&mut generator);
+//! #[cfg(feature = "render")]
+//! board.refill_zones(&kicad_pcb::fill::FillOptions::default())?;
+//! write_zones(&board, &mut doc)?;
+//! std::fs::write("panel.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{cmp::Reverse, collections::HashSet};
+
+use kicad_common::{ConvertError, Error, Point, Position, Uuid, UuidGenerator};
+use kicad_sexp::{
+    cst::{Document, Node, NodeKind},
+    number::format_float,
+};
+
+use crate::{
+    selection::{board_items, node_uuid},
+    Board, FilledPolygon, Footprint, FootprintLib, IslandRemoval, Layer, LayerSelector, Net, PadConnection,
+    PlacementSource, Shape, Side, Track, Via, ViaKind, ViaProtection, Zone,
+};
+#[cfg(feature = "render")]
+use crate::{
+    inspect::copper_gap,
+    route::{copper, track_copper, via_copper},
+};
+
+/// The header of a footprint file, which placed footprints leave out.
+const HEADER: [&str; 3] = ["version", "generator", "generator_version"];
+
+/// Pad children KiCad writes after the net.
+pub(crate) const AFTER_NET: [&str; 14] = [
+    "pinfunction",
+    "pintype",
+    "die_length",
+    "solder_mask_margin",
+    "solder_paste_margin",
+    "solder_paste_margin_ratio",
+    "clearance",
+    "zone_connect",
+    "thermal_bridge_width",
+    "thermal_bridge_angle",
+    "thermal_gap",
+    "options",
+    "primitives",
+    "uuid",
+];
+
+impl Board {
+    /// Places the footprint `lib_id` from `library` at `at`, turned by
+    /// `rotation` degrees, on `side`, and names it `reference`. A nickname
+    /// in `lib_id` must be the library's; without one, the library's is
+    /// added. The footprint and its items get UUIDs from
+    /// [`Board::assign_missing_uuids`].
+    pub fn place_footprint(
+        &mut self,
+        library: &FootprintLib,
+        lib_id: &str,
+        at: Point,
+        rotation: f64,
+        side: Side,
+        reference: &str,
+    ) -> Result<&mut Footprint, Error> {
+        if self.footprint(reference).is_some() {
+            return Err(ConvertError::new(format!("the board already has a footprint {}", reference)).into());
+        }
+        let (lib_id, name) = qualified(library, lib_id)?;
+        let src = library.read(name)?;
+        let in_file = |e: Error| e.in_file(library.path(name));
+        let node = placed(&src, &lib_id, Position::new(at.x, at.y, rotation), side, reference).map_err(in_file)?;
+        self.footprints.push(Footprint::parse(&node.to_string()).map_err(in_file)?);
+        Ok(self.footprints.last_mut().expect("a footprint was just added"))
+    }
+
+    /// Connects the pads numbered `pad` of the footprint `reference` to the
+    /// net `net`, which is added to the board if it is new. Returns the
+    /// net's number.
+    pub fn connect_pad(&mut self, reference: &str, pad: &str, net: &str) -> Result<u32, Error> {
+        let index = self.footprints.iter().position(|footprint| footprint.reference() == Some(reference));
+        let index = index.ok_or_else(|| ConvertError::new(format!("no footprint {} on the board", reference)))?;
+        if self.footprints[index].pad(pad).is_none() {
+            return Err(ConvertError::new(format!("footprint {} has no pad {}", reference, pad)).into());
+        }
+        let net = self.add_net(net).clone();
+        for candidate in self.footprints[index].pads.iter_mut().filter(|candidate| candidate.number == pad) {
+            candidate.net = Some(net.clone());
+        }
+        Ok(net.number)
+    }
+
+    /// The net named `name`, added with the next free number if the board
+    /// does not have it yet.
+    pub fn add_net(&mut self, name: &str) -> &Net {
+        let index = match self.nets.iter().position(|net| net.name == name) {
+            Some(index) => index,
+            None => {
+                // Net 0 is the unconnected net, even on a board without a net list.
+                let number = self.nets.iter().map(|net| net.number + 1).max().unwrap_or(1).max(1);
+                self.nets.push(Net { number, name: name.to_string() });
+                self.nets.len() - 1
+            },
+        };
+        &self.nets[index]
+    }
+}
+
+/// A net given by name or by number, the net code of KiCad's files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetRef<'a> {
+    Name(&'a str),
+    Number(u32),
+}
+
+impl<'a> From<&'a str> for NetRef<'a> {
+    fn from(name: &'a str) -> Self {
+        NetRef::Name(name)
+    }
+}
+
+impl From<u32> for NetRef<'_> {
+    fn from(number: u32) -> Self {
+        NetRef::Number(number)
+    }
+}
+
+impl Board {
+    /// The net `net` refers to.
+    pub fn find_net<'n>(&self, net: impl Into<NetRef<'n>>) -> Result<&Net, Error> {
+        let found = match net.into() {
+            NetRef::Name(name) => self.net_by_name(name).ok_or_else(|| format!("no net {} on the board", name)),
+            NetRef::Number(number) => self.net(number).ok_or_else(|| format!("no net number {} on the board", number)),
+        };
+        Ok(found.map_err(ConvertError::new)?)
+    }
+
+    /// Adds a straight track of `width` on the copper `layer` from `from` to
+    /// `to`, on a net of the board. Its UUID comes from `generator`.
+    pub fn route_segment<'n>(
+        &mut self,
+        net: impl Into<NetRef<'n>>,
+        from: Point,
+        to: Point,
+        width: f64,
+        layer: Layer,
+        generator: &mut UuidGenerator,
+    ) -> Result<&mut Track, Error> {
+        let net = self.find_net(net)?.number;
+        if !self.copper_layers().contains(&layer) {
+            return Err(ConvertError::new(format!("{} is not a copper layer of the board", layer)).into());
+        }
+        positive("track width", width)?;
+        generator.reserve(self.uuids());
+        let uuid = generator.generate(format!("segment {} {:?} {:?}", layer, from, to));
+        let track = Track { start: from, mid: None, end: to, width, layer, net, locked: false, uuid };
+        self.tracks.push(track);
+        Ok(self.tracks.last_mut().expect("a track was just added"))
+    }
+
+    /// Adds a via of `size` and `drill` at `at` between the two copper
+    /// `layers`, on a net of the board. A via between the outer layers is a
+    /// through via, any other a blind or buried one; set its `kind` for a
+    /// micro via. Its UUID comes from `generator`.
+    pub fn add_via<'n>(
+        &mut self,
+        net: impl Into<NetRef<'n>>,
+        at: Point,
+        size: f64,
+        drill: f64,
+        layers: &[Layer],
+        generator: &mut UuidGenerator,
+    ) -> Result<&mut Via, Error> {
+        let net = self.find_net(net)?.number;
+        let copper = self.copper_layers();
+        let span = match layers {
+            [a, b] if a != b && copper.contains(a) && copper.contains(b) => [*a, *b],
+            _ => {
+                let names: Vec<String> = layers.iter().map(Layer::to_string).collect();
+                let message = format!("a via spans two copper layers of the board, not {}", names.join(", "));
+                return Err(ConvertError::new(message).into());
+            },
+        };
+        positive("via size", size)?;
+        positive("via drill", drill)?;
+        if drill >= size {
+            let (drill, size) = (format_float(drill), format_float(size));
+            let message = format!("via drill {} is not smaller than its size {}", drill, size);
+            return Err(ConvertError::new(message).into());
+        }
+        let outer = [copper.first(), copper.last()].map(|layer| layer.copied());
+        let kind = if outer.iter().all(|layer| layer.is_some_and(|layer| span.contains(&layer))) {
+            ViaKind::Through
+        } else {
+            ViaKind::BlindBuried
+        };
+        generator.reserve(self.uuids());
+        let uuid = generator.generate(format!("via {:?}", at));
+        let via = Via {
+            kind,
+            at,
+            size,
+            drill,
+            layers: span.to_vec(),
+            net,
+            locked: false,
+            uuid,
+            protection: ViaProtection::default(),
+        };
+        self.vias.push(via);
+        Ok(self.vias.last_mut().expect("a via was just added"))
+    }
+}
+
+fn positive(what: &str, value: f64) -> Result<(), Error> {
+    match value > 0.0 {
+        true => Ok(()),
+        false => Err(ConvertError::new(format!("{} must be positive, got {}", what, format_float(value))).into()),
+    }
+}
+
+#[cfg(feature = "render")]
+impl Board {
+    /// Checks that the track or via `uuid` keeps `clearance` from the pads,
+    /// tracks and vias of other nets on its layers. Trapezoid and custom
+    /// pads count as their bounding rectangle, as for the router.
+    pub fn check_clearance(&self, uuid: Uuid, clearance: f64) -> Result<(), Error> {
+        let track = self.tracks.iter().find(|track| track.uuid == uuid);
+        let (item, what) = match (track, self.vias.iter().find(|via| via.uuid == uuid)) {
+            (Some(track), _) => (track_copper(track), "track"),
+            (None, Some(via)) => (via_copper(via, &self.copper_layers()), "via"),
+            (None, None) => return Err(ConvertError::new(format!("no track or via {} on the board", uuid)).into()),
+        };
+        let others = copper(self).into_iter().filter(|other| other.net != item.net);
+        for other in others.filter(|other| other.layers.iter().any(|layer| item.layers.contains(layer))) {
+            let gap = copper_gap(&item, &other, clearance);
+            if gap < clearance - 1e-9 {
+                let net = self.net(other.net).map_or("", |net| net.name.as_str());
+                let message = format!(
+                    "{} {} is {} mm from net {}, closer than {} mm",
+                    what,
+                    uuid,
+                    format_float(gap),
+                    net,
+                    format_float(clearance),
+                );
+                return Err(ConvertError::new(message).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The settings of a new copper zone. The defaults are KiCad's.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZoneSettings {
+    pub name: Option<String>,
+    /// Zones with a higher priority fill first, and lower ones flow around
+    /// them.
+    pub priority: u32,
+    pub connect_pads: PadConnection,
+    pub clearance: f64,
+    pub min_thickness: f64,
+    pub thermal_gap: f64,
+    pub thermal_bridge_width: f64,
+    pub island_removal: IslandRemoval,
+}
+
+impl Default for ZoneSettings {
+    fn default() -> Self {
+        ZoneSettings {
+            name: None,
+            priority: 0,
+            connect_pads: PadConnection::ThermalRelief,
+            clearance: 0.5,
+            min_thickness: 0.25,
+            thermal_gap: 0.5,
+            thermal_bridge_width: 0.5,
+            island_removal: IslandRemoval::Always,
+        }
+    }
+}
+
+/// Computes the fills of a board's zones for [`Board::refill_zones`].
+pub trait ZoneFiller {
+    fn fill_zones(&self, board: &mut Board) -> Result<(), Error>;
+}
+
+/// The built-in filler of [`crate::fill`].
+#[cfg(feature = "render")]
+impl ZoneFiller for crate::fill::FillOptions {
+    fn fill_zones(&self, board: &mut Board) -> Result<(), Error> {
+        crate::fill::fill_zones(board, self);
+        Ok(())
+    }
+}
+
+/// Another filler, e.g. one that saves the board and runs KiCad on it.
+impl<F: Fn(&mut Board) -> Result<(), Error>> ZoneFiller for F {
+    fn fill_zones(&self, board: &mut Board) -> Result<(), Error> {
+        self(board)
+    }
+}
+
+impl Board {
+    /// Adds an unfilled copper zone of the net `net` on the copper `layer`
+    /// inside `outline`. It gets a UUID from
+    /// [`Board::assign_missing_uuids`] and its fill from
+    /// [`Board::refill_zones`].
+    pub fn add_zone<'n>(
+        &mut self,
+        net: impl Into<NetRef<'n>>,
+        layer: Layer,
+        outline: &[Point],
+        settings: &ZoneSettings,
+    ) -> Result<&mut Zone, Error> {
+        let net = self.find_net(net)?.clone();
+        if !self.copper_layers().contains(&layer) {
+            return Err(ConvertError::new(format!("{} is not a copper layer of the board", layer)).into());
+        }
+        if outline.len() < 3 {
+            return Err(ConvertError::new(format!("a zone outline needs 3 points, got {}", outline.len())).into());
+        }
+        positive("zone minimum thickness", settings.min_thickness)?;
+        let zone = Zone {
+            net: net.number,
+            net_name: net.name,
+            layers: vec![LayerSelector::Layer(layer)],
+            name: settings.name.clone(),
+            priority: settings.priority,
+            connect_pads: settings.connect_pads,
+            clearance: Some(settings.clearance),
+            min_thickness: Some(settings.min_thickness),
+            thermal_gap: Some(settings.thermal_gap),
+            thermal_bridge_width: Some(settings.thermal_bridge_width),
+            island_removal: settings.island_removal,
+            outline: outline.to_vec(),
+            ..Default::default()
+        };
+        self.zones.push(zone);
+        Ok(self.zones.last_mut().expect("a zone was just added"))
+    }
+
+    /// The copper zones in the order they fill, highest priority first;
+    /// zones of the same priority in board order.
+    pub fn zones_by_priority(&self) -> Vec<&Zone> {
+        let mut zones: Vec<&Zone> = self.zones.iter().filter(|zone| zone.keepout.is_none()).collect();
+        zones.sort_by_key(|zone| Reverse(zone.priority));
+        zones
+    }
+
+    /// Renumbers the priorities of the copper zones for them to fill in
+    /// `order`, given by UUID, and then the zones not in it in their
+    /// current order. The last zone gets priority 0.
+    pub fn set_zone_order(&mut self, order: &[Uuid]) -> Result<(), Error> {
+        let mut ranked: Vec<usize> = Vec::new();
+        for uuid in order {
+            let index = self.zones.iter().position(|zone| zone.keepout.is_none() && zone.uuid == Some(*uuid));
+            match index {
+                Some(index) if !ranked.contains(&index) => ranked.push(index),
+                Some(_) => {},
+                None => return Err(ConvertError::new(format!("no copper zone {} on the board", uuid)).into()),
+            }
+        }
+        let mut rest: Vec<usize> =
+            (0..self.zones.len()).filter(|i| self.zones[*i].keepout.is_none() && !ranked.contains(i)).collect();
+        rest.sort_by_key(|&i| Reverse(self.zones[i].priority));
+        let count = ranked.len() + rest.len();
+        for (rank, index) in ranked.into_iter().chain(rest).enumerate() {
+            self.zones[index].priority = (count - 1 - rank) as u32;
+        }
+        Ok(())
+    }
+
+    /// Recomputes the fills of the zones with `filler`.
+    pub fn refill_zones(&mut self, filler: &impl ZoneFiller) -> Result<(), Error> {
+        filler.fill_zones(self)
+    }
+}
+
+/// Adds the zones of the board that `doc` does not have yet, matched by
+/// UUID, with their fills, after its last zone, and writes the priorities
+/// of the zones it has. Returns the number of zones added.
+pub fn write_zones(board: &Board, doc: &mut Document<'_>) -> Result<usize, Error> {
+    let items = board_items(doc)?;
+    let mut present = HashSet::new();
+    for item in items.iter_mut().filter(|item| item.tag() == Some("zone")) {
+        let Some(uuid) = node_uuid(item) else {
+            continue;
+        };
+        present.insert(uuid);
+        if let Some(zone) = board.zones.iter().find(|zone| zone.uuid == Some(uuid)) {
+            write_priority(item, zone.priority);
+        }
+    }
+    let mut added = Vec::new();
+    for zone in board.zones.iter().filter(|zone| zone.uuid.is_none_or(|uuid| !present.contains(&uuid))) {
+        added.push(zone_node(zone)?);
+    }
+    let at = match items.iter().rposition(|item| item.tag() == Some("zone")) {
+        Some(last) => last + 1,
+        None => {
+            let after = items.iter().position(|item| matches!(item.tag(), Some("group" | "embedded_fonts")));
+            after.unwrap_or(items.len())
+        },
+    };
+    let count = added.len();
+    items.splice(at..at, added);
+    Ok(count)
+}
+
+/// Sets the `(priority ...)` of a zone, which KiCad leaves out for 0.
+fn write_priority(node: &mut Node<'_>, priority: u32) {
+    let existing = node.child_mut("priority").and_then(|child| child.items_mut()?.get_mut(1));
+    match (existing, priority) {
+        (Some(value), _) if priority > 0 => value.set_atom(priority.to_string()),
+        (Some(_), _) => {
+            node.items_mut().expect("zones are lists").retain(|item| item.tag() != Some("priority"));
+        },
+        (None, 0) => {},
+        (None, _) => {
+            let priority = Node::tagged("priority", vec![Node::atom(priority.to_string())]);
+            insert_child(node, priority, &["connect_pads", "min_thickness", "fill", "polygon"]);
+        },
+    }
+}
+
+/// A child of a zone, with its own children on lines of their own.
+fn nested(tag: &'static str, args: Vec<Node<'static>>, children: Vec<Node<'static>>) -> Node<'static> {
+    match children.is_empty() {
+        true => Node::tagged(tag, args),
+        false => Node::block(tag, args, children, "\n\t\t"),
+    }
+}
+
+fn zone_node(zone: &Zone) -> Result<Node<'static>, Error> {
+    let uuid = zone.uuid.ok_or_else(|| ConvertError::new(format!("zone of net {} has no UUID", zone.net_name)))?;
+    let number = |value: f64| Node::atom(format_float(value));
+    let layers: Vec<Node> = zone.layers.iter().map(|layer| Node::string(&layer.to_string())).collect();
+    let mut children = vec![
+        Node::tagged("net", vec![Node::atom(zone.net.to_string())]),
+        Node::tagged("net_name", vec![Node::string(&zone.net_name)]),
+        // KiCad writes `(layer ...)` for one layer, and `(layers ...)` for
+        // several and for wildcards such as `*.Cu`.
+        Node::tagged(if let [LayerSelector::Layer(_)] = zone.layers[..] { "layer" } else { "layers" }, layers),
+        Node::tagged("uuid", vec![Node::string(&uuid.to_string())]),
+    ];
+    children.extend(zone.name.as_deref().map(|name| Node::tagged("name", vec![Node::string(name)])));
+    children.push(Node::tagged("hatch", vec![Node::atom("edge"), Node::atom("0.5")]));
+    if zone.priority > 0 {
+        children.push(Node::tagged("priority", vec![Node::atom(zone.priority.to_string())]));
+    }
+    let connection = match zone.connect_pads {
+        PadConnection::ThermalRelief => None,
+        PadConnection::Solid => Some("yes"),
+        PadConnection::None => Some("no"),
+        PadConnection::ThruHoleOnly => Some("thru_hole_only"),
+    };
+    let clearance = zone.clearance.map(|clearance| Node::tagged("clearance", vec![number(clearance)]));
+    let connection = connection.map(Node::atom).into_iter().collect();
+    children.push(nested("connect_pads", connection, clearance.into_iter().collect()));
+    children.extend(zone.min_thickness.map(|thickness| Node::tagged("min_thickness", vec![number(thickness)])));
+    children.push(Node::tagged("filled_areas_thickness", vec![Node::atom("no")]));
+    if let Some(keepout) = zone.keepout {
+        let rules = [
+            ("tracks", keepout.tracks),
+            ("vias", keepout.vias),
+            ("pads", keepout.pads),
+            ("copperpour", keepout.copperpour),
+            ("footprints", keepout.footprints),
+        ];
+        let rule = |(tag, forbidden)| {
+            Node::tagged(tag, vec![Node::atom(if forbidden { "not_allowed" } else { "allowed" })])
+        };
+        children.push(nested("keepout", Vec::new(), rules.into_iter().map(rule).collect()));
+    }
+    if let Some(placement) = &zone.placement {
+        let (tag, source) = match &placement.source {
+            PlacementSource::Sheet(sheet) => ("sheetname", sheet),
+            PlacementSource::ComponentClass(class) => ("component_class", class),
+            PlacementSource::Group(group) => ("group", group),
+        };
+        let enabled = Node::tagged("enabled", vec![Node::atom(if placement.enabled { "yes" } else { "no" })]);
+        children.push(nested("placement", Vec::new(), vec![enabled, Node::tagged(tag, vec![Node::string(source)])]));
+    }
+    let fill = [("thermal_gap", zone.thermal_gap), ("thermal_bridge_width", zone.thermal_bridge_width)];
+    let mut fill: Vec<Node> =
+        fill.into_iter().filter_map(|(tag, value)| Some(Node::tagged(tag, vec![number(value?)]))).collect();
+    match zone.island_removal {
+        IslandRemoval::Always => {},
+        IslandRemoval::Never => fill.push(Node::tagged("island_removal_mode", vec![Node::atom("1")])),
+        IslandRemoval::BelowArea(area) => {
+            fill.push(Node::tagged("island_removal_mode", vec![Node::atom("2")]));
+            fill.push(Node::tagged("island_area_min", vec![number(area)]));
+        },
+    }
+    let filled = if zone.filled { vec![Node::atom("yes")] } else { Vec::new() };
+    children.push(nested("fill", filled, fill));
+    children.push(polygon_node(&zone.outline, "\n\t\t"));
+    let mut node = Node::block("zone", Vec::new(), children, "\n\t");
+    for polygon in &zone.filled_polygons {
+        node.items_mut().expect("zones are lists").push(filled_polygon_node(polygon, "\n\t\t"));
+    }
+    Ok(node)
+}
+
+/// The outline `polygon` of a zone whose other children follow `indent`.
+pub(crate) fn polygon_node(points: &[Point], indent: &str) -> Node<'static> {
+    let outline = FilledPolygon { layer: Layer::FCu, points: points.to_vec() };
+    let mut polygon = filled_polygon_node(&outline, indent);
+    let items = polygon.items_mut().expect("polygons are lists");
+    items.retain(|item| item.tag() != Some("layer"));
+    items[0].set_atom("polygon");
+    polygon
+}
+
+/// A `filled_polygon` of a zone whose other children follow `indent`.
+pub(crate) fn filled_polygon_node(polygon: &FilledPolygon, indent: &str) -> Node<'static> {
+    let nested = |depth: usize| format!("{}{}", indent, "\t".repeat(depth));
+    let mut pts = Vec::new();
+    for (i, p) in polygon.points.iter().enumerate() {
+        let mut xy = Node::tagged("xy", vec![Node::atom(format_float(p.x)), Node::atom(format_float(p.y))]);
+        if i % 4 == 0 {
+            xy.leading = nested(2).into();
+        }
+        pts.push(xy);
+    }
+    let mut pts = Node::tagged("pts", pts);
+    pts.set_trailing(nested(1));
+    let layer = Node::tagged("layer", vec![Node::string(&polygon.layer.to_string())]);
+    Node::block("filled_polygon", Vec::new(), vec![layer, pts], indent)
+}
+
+/// Adds the tracks and vias of the board that `doc` does not have yet,
+/// matched by UUID, after its last track or via. Returns the number added.
+pub fn write_tracks(board: &Board, doc: &mut Document<'_>) -> Result<usize, Error> {
+    let items = board_items(doc)?;
+    let routed = items.iter().filter(|item| matches!(item.tag(), Some("segment" | "arc" | "via")));
+    let present: HashSet<Uuid> = routed.filter_map(node_uuid).collect();
+    let tracks: Vec<Track> = board.tracks.iter().filter(|track| !present.contains(&track.uuid)).cloned().collect();
+    let vias: Vec<Via> = board.vias.iter().filter(|via| !present.contains(&via.uuid)).cloned().collect();
+    write_tracks_and_vias(&tracks, &vias, doc)?;
+    Ok(tracks.len() + vias.len())
+}
+
+/// Adds `tracks` and `vias` to the board in `doc`, after its last track
+/// or via, or else before its zones.
+pub(crate) fn write_tracks_and_vias(tracks: &[Track], vias: &[Via], doc: &mut Document<'_>) -> Result<(), Error> {
+    let items = board_items(doc)?;
+    let routed = items.iter().rposition(|item| matches!(item.tag(), Some("segment" | "arc" | "via")));
+    let at = match routed {
+        Some(last) => last + 1,
+        None => {
+            let after = items.iter().position(|item| matches!(item.tag(), Some("zone" | "group" | "embedded_fonts")));
+            after.unwrap_or(items.len())
+        },
+    };
+    // Indent like the item before, or one tab for a board written on one line.
+    let indent = match items.get(at.saturating_sub(1)) {
+        Some(item) if at > 1 && item.leading.contains('\n') => item.leading.to_string(),
+        _ => "\n\t".to_string(),
+    };
+    let nodes = tracks.iter().map(|track| track_node(track, &indent));
+    let nodes: Vec<Node<'static>> = nodes.chain(vias.iter().map(|via| via_node(via, &indent))).collect();
+    items.splice(at..at, nodes);
+    Ok(())
+}
+
+fn point_node(tag: &'static str, p: Point) -> Node<'static> {
+    Node::tagged(tag, vec![Node::atom(format_float(p.x)), Node::atom(format_float(p.y))])
+}
+
+fn layer_node(tag: &'static str, layers: &[Layer]) -> Node<'static> {
+    Node::tagged(tag, layers.iter().map(|layer| Node::string(&layer.to_string())).collect())
+}
+
+fn net_and_uuid(net: u32, uuid: &kicad_common::Uuid) -> [Node<'static>; 2] {
+    [
+        Node::tagged("net", vec![Node::atom(net.to_string())]),
+        Node::tagged("uuid", vec![Node::string(&uuid.to_string())]),
+    ]
+}
+
+fn track_node(track: &Track, indent: &str) -> Node<'static> {
+    let mut children = vec![point_node("start", track.start)];
+    children.extend(track.mid.map(|mid| point_node("mid", mid)));
+    children.push(point_node("end", track.end));
+    children.push(Node::tagged("width", vec![Node::atom(format_float(track.width))]));
+    children.push(layer_node("layer", &[track.layer]));
+    children.extend(net_and_uuid(track.net, &track.uuid));
+    Node::block(if track.is_arc() { "arc" } else { "segment" }, Vec::new(), children, indent)
+}
+
+fn via_node(via: &Via, indent: &str) -> Node<'static> {
+    let mut children = vec![
+        point_node("at", via.at),
+        Node::tagged("size", vec![Node::atom(format_float(via.size))]),
+        Node::tagged("drill", vec![Node::atom(format_float(via.drill))]),
+        layer_node("layers", &via.layers),
+    ];
+    children.extend(net_and_uuid(via.net, &via.uuid));
+    let kind = match via.kind {
+        ViaKind::Through => Vec::new(),
+        ViaKind::BlindBuried => vec![Node::atom("blind")],
+        ViaKind::Micro => vec![Node::atom("micro")],
+    };
+    Node::block("via", kind, children, indent)
+}
+
+/// The full library identifier for `lib_id` from `library`, and the
+/// footprint's name in it.
+fn qualified<'i>(library: &FootprintLib, lib_id: &'i str) -> Result<(String, &'i str), Error> {
+    let nickname = library.nickname().unwrap_or_default();
+    match lib_id.split_once(':') {
+        Some((lib, name)) if lib == nickname => Ok((lib_id.to_string(), name)),
+        Some(_) => Err(ConvertError::new(format!("`{}` is not in the library {}", lib_id, nickname)).into()),
+        None => Ok((format!("{}:{}", nickname, lib_id), lib_id)),
+    }
+}
+
+/// The library footprint file `src` as the board footprint `lib_id` named
+/// `reference` at `at`, still indented as a file of its own.
+fn placed(src: &str, lib_id: &str, at: Position, side: Side, reference: &str) -> Result<Node<'static>, Error> {
+    let doc = kicad_sexp::cst::parse(src).map_err(|e| Error::Parse(vec![e]))?;
+    let root = doc.nodes.into_iter().find(|node| matches!(node.tag(), Some("footprint" | "module")));
+    let mut root = root.ok_or_else(|| ConvertError::new("expected a (footprint ...) root"))?;
+    let items = root.items_mut().expect("tagged nodes are lists");
+    if items.len() < 2 {
+        return Err(ConvertError::new("expected a footprint name").into());
+    }
+    items[0].set_atom("footprint");
+    items[1].set_string(lib_id);
+    items.retain(|item| !item.tag().is_some_and(|tag| HEADER.contains(&tag) || tag == "at"));
+    let layer = match items.iter().position(|item| item.tag() == Some("layer")) {
+        Some(layer) => layer,
+        None => {
+            let mut layer = Node::tagged("layer", vec![Node::string("F.Cu")]);
+            layer.leading = indent_of(items);
+            items.insert(2, layer);
+            2
+        },
+    };
+    for item in items.iter_mut().skip(2) {
+        transform(item, at.angle, side);
+    }
+    let mut position = vec![Node::atom(format_float(at.x)), Node::atom(format_float(at.y))];
+    let angle = normalized(at.angle);
+    let angle = if angle > 180.0 { angle - 360.0 } else { angle };
+    if angle != 0.0 {
+        position.push(Node::atom(format_float(angle)));
+    }
+    let mut position = Node::tagged("at", position);
+    position.leading = indent_of(items);
+    items.insert(layer + 1, position);
+
+    for item in items.iter_mut() {
+        let field = match item.tag() {
+            Some("property") => item.items().get(1).and_then(Node::value).is_some_and(|key| key == "Reference"),
+            Some("fp_text") => item.items().get(1).and_then(Node::text) == Some("reference"),
+            _ => false,
+        };
+        if let (true, Some(value)) = (field, item.items_mut().and_then(|items| items.get_mut(2))) {
+            value.set_string(reference);
+        }
+    }
+    Ok(root.into_owned())
+}
+
+/// Turns the `at` positions in `node` by `rotation` degrees and, for the
+/// bottom side, mirrors it top to bottom: Y coordinates and angles change
+/// sign, layers move to the other side and text is mirrored.
+fn transform(node: &mut Node<'_>, rotation: f64, side: Side) {
+    let bottom = side == Side::Bottom;
+    match node.tag() {
+        // 3D model offsets are in model coordinates, flipped by KiCad's viewer.
+        Some("model" | "embedded_files") => return,
+        Some("at") => {
+            let items = node.items_mut().expect("tagged nodes are lists");
+            let angle = items.get(3).and_then(|angle| angle.text()?.parse::<f64>().ok());
+            let turned = normalized(rotation + if bottom { -angle.unwrap_or(0.0) } else { angle.unwrap_or(0.0) });
+            match angle {
+                Some(_) => items[3].set_atom(format_float(turned)),
+                None if turned != 0.0 => items.insert(3.min(items.len()), Node::atom(format_float(turned))),
+                None => {},
+            }
+            if bottom && let Some(y) = items.get_mut(2) {
+                negate(y);
+            }
+            return;
+        },
+        Some("start" | "mid" | "end" | "center" | "xy" | "offset") if bottom => {
+            if let Some(y) = node.items_mut().and_then(|items| items.get_mut(2)) {
+                negate(y);
+            }
+            return;
+        },
+        Some("layer" | "layers") if bottom => {
+            for item in node.items_mut().into_iter().flatten().skip(1) {
+                if let Some(layer) = item.value().and_then(|name| name.parse::<Layer>().ok()) {
+                    item.set_string(&layer.flipped().to_string());
+                }
+            }
+            return;
+        },
+        Some("effects") if bottom => toggle_mirror(node),
+        _ => {},
+    }
+    for item in node.items_mut().into_iter().flatten() {
+        transform(item, rotation, side);
+    }
+}
+
+/// An angle in degrees from 0 up to 360.
+fn normalized(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(360.0);
+    if angle == 360.0 || angle == 0.0 { 0.0 } else { angle }
+}
+
+/// Changes the sign of a number atom, keeping its digits as written.
+fn negate(atom: &mut Node<'_>) {
+    let Some(text) = atom.text().filter(|text| text.parse::<f64>().is_ok_and(|value| value != 0.0)) else {
+        return;
+    };
+    let negated = match text.strip_prefix('-') {
+        Some(positive) => positive.to_string(),
+        None => format!("-{}", text.strip_prefix('+').unwrap_or(text)),
+    };
+    atom.set_atom(negated);
+}
+
+/// Mirrors the text of `effects`, or makes mirrored text read normally.
+fn toggle_mirror(effects: &mut Node<'_>) {
+    let indent = indent_of(effects.items());
+    let items = effects.items_mut().expect("tagged nodes are lists");
+    let Some(justify) = items.iter_mut().find(|item| item.tag() == Some("justify")) else {
+        let mut justify = Node::tagged("justify", vec![Node::atom("mirror")]);
+        justify.leading = indent;
+        items.push(justify);
+        return;
+    };
+    let justify_items = justify.items_mut().expect("tagged nodes are lists");
+    match justify_items.iter().position(|item| item.text() == Some("mirror")) {
+        Some(mirror) => {
+            justify_items.remove(mirror);
+            if justify_items.len() == 1 {
+                items.retain(|item| item.tag() != Some("justify"));
+            }
+        },
+        None => justify_items.push(Node::atom("mirror")),
+    }
+}
+
+/// The whitespace before the child lists of a list, or a space if it has
+/// none.
+fn indent_of<'a>(items: &[Node<'a>]) -> std::borrow::Cow<'a, str> {
+    let child = items.iter().skip(1).find(|item| item.tag().is_some());
+    child.map_or(" ".into(), |child| child.leading.clone())
+}
+
+/// Adds the footprints from `library` that are on the board but not yet in
+/// `doc`, matched by UUID, after its last footprint, and the nets of the
+/// board that `doc` lacks. Footprints from other libraries are left for a
+/// call with theirs. Returns the number of footprints added.
+pub fn write_footprints(board: &Board, library: &FootprintLib, doc: &mut Document<'_>) -> Result<usize, Error> {
+    let items = board_items(doc)?;
+    write_nets(board, items);
+    let footprints = items.iter().filter(|item| item.tag() == Some("footprint"));
+    let present: HashSet<Uuid> = footprints.filter_map(node_uuid).collect();
+    let nickname = library.nickname().unwrap_or_default();
+    let mut added = Vec::new();
+    for footprint in &board.footprints {
+        let Some(name) = footprint.lib_id.strip_prefix(nickname).and_then(|id| id.strip_prefix(':')) else {
+            continue;
+        };
+        let missing = || ConvertError::new(format!("footprint {} has no UUID", footprint.lib_id));
+        let uuid = footprint.uuid.ok_or_else(missing)?;
+        if present.contains(&uuid) {
+            continue;
+        }
+        let src = library.read(name)?;
+        let reference = footprint.reference().unwrap_or_default();
+        let placed = placed(&src, &footprint.lib_id, footprint.at, footprint.side(), reference);
+        let mut node = placed.map_err(|e| e.in_file(library.path(name)))?;
+        stamp(&mut node, footprint);
+        indent(&mut node);
+        node.leading = "\n\t".into();
+        added.push(node);
+    }
+    let at = match items.iter().rposition(|item| item.tag() == Some("footprint")) {
+        Some(last) => last + 1,
+        None => {
+            let after = |tag: &str| {
+                Shape::is_shape_tag(tag)
+                    || matches!(tag, "gr_text" | "segment" | "arc" | "via" | "zone" | "group" | "embedded_fonts")
+            };
+            items.iter().position(|item| item.tag().is_some_and(after)).unwrap_or(items.len())
+        },
+    };
+    let count = added.len();
+    items.splice(at..at, added);
+    Ok(count)
+}
+
+/// Adds the `(net ...)` entries of the board that `items` lack, after the
+/// last one.
+pub(crate) fn write_nets(board: &Board, items: &mut Vec<Node<'_>>) {
+    let number = |item: &Node| item.items().get(1)?.value()?.parse::<u32>().ok();
+    let present: HashSet<u32> = items.iter().filter(|item| item.tag() == Some("net")).filter_map(number).collect();
+    let nets = board.nets.iter().filter(|net| !present.contains(&net.number)).map(|net| {
+        let mut node = Node::tagged("net", vec![Node::atom(net.number.to_string()), Node::string(&net.name)]);
+        node.leading = "\n\t".into();
+        node
+    });
+    let nets: Vec<Node<'static>> = nets.collect();
+    let last = items.iter().rposition(|item| item.tag() == Some("net"));
+    let setup = || items.iter().rposition(|item| matches!(item.tag(), Some("setup" | "layers")));
+    let at = last.or_else(setup).map_or(items.len(), |last| last + 1);
+    items.splice(at..at, nets);
+}
+
+/// Writes the UUIDs and pad nets of `footprint` into `node`, the footprint
+/// as placed from its library file, whose items are in the same order.
+fn stamp(node: &mut Node<'_>, footprint: &Footprint) {
+    let uuid_node = |uuid: Uuid| Node::tagged("uuid", vec![Node::string(&uuid.to_string())]);
+    if let Some(uuid) = footprint.uuid {
+        insert_child(node, uuid_node(uuid), &["at"]);
+    }
+    let (mut pads, mut shapes, mut texts) = (footprint.pads.iter(), footprint.shapes.iter(), footprint.texts.iter());
+    for item in node.items_mut().into_iter().flatten() {
+        let (uuid, net) = match item.tag() {
+            Some("property") => {
+                let key = item.items().get(1).and_then(Node::value);
+                let field = footprint.properties.iter().find(|field| Some(field.key.as_str()) == key.as_deref());
+                (field.and_then(|field| field.uuid), None)
+            },
+            Some("fp_text") => (texts.next().and_then(|text| text.uuid), None),
+            Some("pad") => pads.next().map_or((None, None), |pad| (pad.uuid, pad.net.as_ref())),
+            Some(tag) if Shape::is_shape_tag(tag) => (shapes.next().and_then(|shape| shape.uuid), None),
+            _ => continue,
+        };
+        if let Some(net) = net {
+            let net = Node::tagged("net", vec![Node::atom(net.number.to_string()), Node::string(&net.name)]);
+            insert_child(item, net, &AFTER_NET);
+        }
+        if let Some(uuid) = uuid {
+            insert_child(item, uuid_node(uuid), &["effects"]);
+        }
+    }
+}
+
+/// Inserts `child` into the list `node` before its first child with a tag
+/// in `before`, or at the end, lined up with the other children.
+pub(crate) fn insert_child<'a>(node: &mut Node<'a>, mut child: Node<'a>, before: &[&str]) {
+    child.leading = indent_of(node.items());
+    let items = node.items_mut().expect("tagged nodes are lists");
+    let at = items.iter().position(|item| item.tag().is_some_and(|tag| before.contains(&tag)));
+    items.insert(at.unwrap_or(items.len()), child);
+}
+
+/// Moves the lines of `node`, a top-level item in a file of its own, one
+/// level in, for it to be an item of a board.
+fn indent(node: &mut Node<'_>) {
+    node.leading = node.leading.replace('\n', "\n\t").into();
+    if let NodeKind::List { items, trailing } = &mut node.kind {
+        *trailing = trailing.replace('\n', "\n\t").into();
+        items.iter_mut().for_each(indent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::UuidGenerator;
+    use kicad_sexp::source::FsSource;
+
+    use super::*;
+    use crate::BoardBuilder;
+
+    #[test]
+    fn place_footprints() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let library = FootprintLib::open(&source, "demo/demo.pretty").unwrap();
+        let builder = BoardBuilder::new().net("GND");
+        let mut board = builder.build().unwrap();
+
+        let r1 = board.place_footprint(&library, "R_0603_1608Metric", Point::new(110.0, 105.0), 90.0, Side::Top, "R1");
+        let r1 = r1.unwrap();
+        assert_eq!((r1.lib_id.as_str(), r1.reference()), ("demo:R_0603_1608Metric", Some("R1")));
+        assert_eq!(r1.at, Position::new(110.0, 105.0, 90.0));
+        assert_eq!(r1.pads[0].at, Position::new(-0.825, 0.0, 90.0));
+        let pad = r1.pad_position(&r1.pads[0]);
+        assert!((pad.x - 110.0).abs() < 1e-9 && (pad.y - 105.825).abs() < 1e-9, "{:?}", pad);
+
+        let at = Point::new(120.0, 105.0);
+        let r2 = board.place_footprint(&library, "demo:R_0603_1608Metric", at, 270.0, Side::Bottom, "R2").unwrap();
+        assert_eq!((r2.layer, r2.side(), r2.at.angle), (Some(Layer::BCu), Side::Bottom, -90.0));
+        let layers = [Layer::BCu, Layer::BMask, Layer::BPaste].map(LayerSelector::Layer);
+        assert_eq!((r2.pads[1].at, &r2.pads[1].layers[..]), (Position::new(0.825, 0.0, 270.0), &layers[..]));
+        let reference = &r2.properties[0];
+        assert_eq!((reference.value.as_str(), reference.layer), ("R2", Layer::BSilkS));
+        assert_eq!(reference.at, Position::new(0.0, 1.43, 270.0));
+        assert!(reference.effects.justify.mirror);
+        assert_eq!(r2.courtyard(Side::Bottom).count(), 1);
+
+        assert_eq!(board.connect_pad("R1", "1", "GND").unwrap(), 1);
+        assert_eq!(board.connect_pad("R1", "2", "LED").unwrap(), 2);
+        assert_eq!(board.connect_pad("R2", "1", "LED").unwrap(), 2);
+        assert!(board.connect_pad("R3", "1", "X").is_err() && board.connect_pad("R1", "3", "X").is_err());
+        let nets: Vec<_> = board.nets.iter().map(|net| (net.number, net.name.as_str())).collect();
+        assert_eq!(nets, [(0, ""), (1, "GND"), (2, "LED")]);
+
+        let error = board.place_footprint(&library, "R_0603_1608Metric", at, 0.0, Side::Top, "R1").unwrap_err();
+        assert_eq!(error.to_string(), "the board already has a footprint R1");
+        assert!(board.place_footprint(&library, "Device:R_0603_1608Metric", at, 0.0, Side::Top, "R3").is_err());
+        assert!(board.place_footprint(&library, "R_0805_2012Metric", at, 0.0, Side::Top, "R3").is_err());
+
+        board.assign_missing_uuids(&mut UuidGenerator::deterministic("layout"));
+        let src = builder.to_string();
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(write_footprints(&board, &library, &mut doc).unwrap(), 2);
+        let text = doc.to_string();
+        let r1 = "\t(net 2 \"LED\")\n\t(footprint \"demo:R_0603_1608Metric\"\n\t\t(layer \"F.Cu\")\n\t\t(uuid ";
+        assert!(text.contains(r1), "{}", text);
+        assert!(text.contains("\t\t\t(layers \"B.Cu\" \"B.Mask\" \"B.Paste\")\n"), "{}", text);
+        assert_eq!(Board::parse(&text).unwrap(), board);
+
+        let mut again = kicad_sexp::cst::parse(&text).unwrap();
+        assert_eq!(write_footprints(&board, &library, &mut again).unwrap(), 0);
+        assert_eq!(again.to_string(), text);
+    }
+
+    #[test]
+    fn tracks_and_vias() {
+        let builder = BoardBuilder::new().copper_layers(4).net("GND").net("VIN");
+        let mut board = builder.build().unwrap();
+        let mut generator = UuidGenerator::deterministic("tracks");
+        let (a, b) = (Point::new(10.0, 10.0), Point::new(20.0, 10.0));
+        let track = board.route_segment("GND", a, b, 0.25, Layer::FCu, &mut generator).unwrap().clone();
+        assert_eq!((track.net, track.length()), (1, 10.0));
+        let through = [Layer::FCu, Layer::BCu];
+        let via = board.add_via(2, Point::new(15.0, 10.5), 0.6, 0.3, &through, &mut generator).unwrap().clone();
+        assert_eq!((via.kind, via.net), (ViaKind::Through, 2));
+        let blind = [Layer::FCu, Layer::InCu(1)];
+        let blind = board.add_via("VIN", Point::new(30.0, 10.0), 0.6, 0.3, &blind, &mut generator).unwrap();
+        assert_eq!(blind.kind, ViaKind::BlindBuried);
+
+        let errors = [
+            (board.route_segment("VCC", a, b, 0.25, Layer::FCu, &mut generator).unwrap_err(), "no net VCC"),
+            (board.route_segment(7, a, b, 0.25, Layer::FCu, &mut generator).unwrap_err(), "no net number 7"),
+            (board.route_segment(1, a, b, 0.25, Layer::FSilkS, &mut generator).unwrap_err(), "not a copper layer"),
+            (board.route_segment(1, a, b, 0.0, Layer::FCu, &mut generator).unwrap_err(), "width must be positive"),
+            (board.add_via(1, a, 0.6, 0.3, &[Layer::FCu], &mut generator).unwrap_err(), "not F.Cu"),
+            (board.add_via(1, a, 0.6, 0.6, &through, &mut generator).unwrap_err(), "not smaller than its size"),
+        ];
+        for (error, message) in errors {
+            assert!(error.to_string().contains(message), "{}", error);
+        }
+        assert_eq!((board.tracks.len(), board.vias.len()), (1, 2));
+
+        // The via's edge is 0.5 - 0.3 - 0.125 mm from the track's.
+        #[cfg(feature = "render")]
+        {
+            let error = board.check_clearance(via.uuid, 0.2).unwrap_err();
+            assert!(error.to_string().contains("is 0.075 mm from net GND, closer than 0.2 mm"), "{}", error);
+            assert!(board.check_clearance(track.uuid, 0.1).is_err());
+            board.check_clearance(via.uuid, 0.05).unwrap();
+            assert!(board.check_clearance(Uuid::NIL, 0.2).is_err());
+        }
+
+        let src = builder.to_string();
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(write_tracks(&board, &mut doc).unwrap(), 3);
+        let parsed = Board::parse(&doc.to_string()).unwrap();
+        assert_eq!((parsed.tracks, parsed.vias), (board.tracks.clone(), board.vias.clone()));
+        assert_eq!(write_tracks(&board, &mut doc).unwrap(), 0);
+    }
+
+    #[test]
+    fn zones() {
+        let builder = BoardBuilder::new().net("GND").net("VIN");
+        let mut board = builder.build().unwrap();
+        let square = |x: f64, y: f64, size: f64| {
+            [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(dx, dy)| Point::new(x + dx * size, y + dy * size))
+        };
+        let ground = ZoneSettings { name: Some("ground".to_string()), ..Default::default() };
+        let zone = board.add_zone("GND", Layer::BCu, &square(0.0, 0.0, 20.0), &ground).unwrap();
+        assert_eq!((zone.net, zone.net_name.as_str(), zone.clearance), (1, "GND", Some(0.5)));
+        let island_removal = IslandRemoval::BelowArea(2.5);
+        let connect_pads = PadConnection::Solid;
+        let high = ZoneSettings { priority: 2, connect_pads, island_removal, ..Default::default() };
+        board.add_zone(2, Layer::BCu, &square(5.0, 5.0, 5.0), &high).unwrap();
+        assert!(board.add_zone("GND", Layer::FSilkS, &square(0.0, 0.0, 1.0), &ground).is_err());
+        assert!(board.add_zone("GND", Layer::FCu, &square(0.0, 0.0, 1.0)[..2], &ground).is_err());
+        assert!(board.add_zone("VCC", Layer::FCu, &square(0.0, 0.0, 1.0), &ground).is_err());
+        board.assign_missing_uuids(&mut UuidGenerator::deterministic("zones"));
+        let uuids: Vec<Uuid> = board.zones.iter().filter_map(|zone| zone.uuid).collect();
+        let nets = |board: &Board| board.zones_by_priority().iter().map(|zone| zone.net).collect::<Vec<_>>();
+        assert_eq!(nets(&board), [2, 1]);
+
+        let src = builder.to_string();
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(write_zones(&board, &mut doc).unwrap(), 2);
+        let text = doc.to_string();
+        let expected = "\t\t(hatch edge 0.5)\n\t\t(priority 2)\n\t\t(connect_pads yes\n\t\t\t(clearance 0.5)\n";
+        assert!(text.contains(expected), "{}", text);
+        assert!(text.contains("\t\t\t(island_removal_mode 2)\n\t\t\t(island_area_min 2.5)\n"));
+        assert_eq!(Board::parse(&text).unwrap().zones, board.zones);
+
+        #[cfg(feature = "render")]
+        {
+            let options = crate::fill::FillOptions { remove_islands: false, ..Default::default() };
+            board.refill_zones(&options).unwrap();
+            assert!(board.zones.iter().all(|zone| zone.filled && !zone.filled_polygons.is_empty()));
+            board.set_zone_order(&[uuids[0]]).unwrap();
+            board.refill_zones(&options).unwrap();
+            assert!(!board.zones[0].filled_polygons.is_empty() && board.zones[1].filled_polygons.is_empty());
+        }
+        #[cfg(not(feature = "render"))]
+        board.set_zone_order(&[uuids[0]]).unwrap();
+        assert_eq!(nets(&board), [1, 2]);
+        assert_eq!(board.zones.iter().map(|zone| zone.priority).collect::<Vec<_>>(), [1, 0]);
+        assert!(board.set_zone_order(&[Uuid::NIL]).is_err());
+
+        let filler = |board: &mut Board| -> Result<(), Error> {
+            board.zones.iter_mut().for_each(|zone| zone.filled = false);
+            Ok(())
+        };
+        board.refill_zones(&filler).unwrap();
+        assert!(board.zones.iter().all(|zone| !zone.filled));
+
+        assert_eq!(write_zones(&board, &mut doc).unwrap(), 0);
+        let text = doc.to_string();
+        assert!(text.contains("\t\t(hatch edge 0.5)\n\t\t(priority 1)\n") && !text.contains("(priority 2)"));
+        let priorities: Vec<u32> = Board::parse(&text).unwrap().zones.iter().map(|zone| zone.priority).collect();
+        assert_eq!(priorities, [1, 0]);
+    }
+}
@@ -0,0 +1,461 @@
+//! Typed model of KiCad board (`.kicad_pcb`) files.
+
+pub mod annotate;
+pub mod areas;
+#[cfg(feature = "export")]
+pub mod assembly;
+mod board;
+mod bounds;
+mod builder;
+pub mod clipboard;
+pub mod carrier;
+pub mod constraints;
+#[cfg(feature = "export")]
+pub mod drill;
+pub mod extract;
+#[cfg(feature = "export")]
+pub mod gerber;
+#[cfg(feature = "render")]
+pub mod fill;
+pub mod flex;
+mod footprint;
+mod graphics;
+#[cfg(feature = "render")]
+pub mod height;
+pub mod index;
+#[cfg(feature = "render")]
+pub mod inspect;
+#[cfg(feature = "render")]
+pub mod keepouts;
+mod layer;
+pub mod layout;
+mod library;
+#[cfg(feature = "export")]
+pub mod mechanical;
+pub mod netlist;
+mod pad;
+pub mod pinmap;
+#[cfg(feature = "export")]
+pub mod placement;
+pub mod replicate;
+#[cfg(feature = "render")]
+pub mod route;
+pub mod rules;
+pub mod selection;
+#[cfg(feature = "render")]
+pub mod silkscreen;
+#[cfg(feature = "render")]
+pub mod swaps;
+#[cfg(feature = "render")]
+pub mod thermal;
+mod track;
+#[cfg(feature = "render")]
+pub mod tuning;
+pub mod wizard;
+mod zone;
+
+pub use kicad_common::Error;
+
+pub use board::{Board, Net, Setup, StackupLayer};
+pub use builder::BoardBuilder;
+pub use footprint::{Attributes, Field, Footprint, Model, MountKind, Side};
+pub use graphics::{Shape, ShapeKind, Text, TextKind};
+pub use layer::{Layer, LayerClass, LayerDef, LayerKind, LayerSelector, ParseLayerError};
+pub use library::FootprintLib;
+pub use pad::{Drill, Pad, PadKind, PadProperty, PadShape};
+pub use track::{Track, Via, ViaKind, ViaProtection, ViaSides};
+pub use zone::{FilledPolygon, IslandRemoval, Keepout, PadConnection, Placement, PlacementSource, Zone};
+
+/// Schema of the supported board format versions, for
+/// [`kicad_sexp::schema::Schema::parse`].
+pub const SCHEMA: &str = include_str!("../schema/kicad_pcb.schema");
+
+#[cfg(test)]
+mod tests {
+    use std::{io, path::Path, sync::Arc};
+
+    use kicad_common::{PaperSize, Point, Position, Size, UuidGenerator};
+    use kicad_sexp::source::{FsSource, MemorySource};
+
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn assert_near(a: Point, b: Point) {
+        assert!((a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn empty() {
+        let board = Board::parse(include_str!("../../reference-files/empty/empty.kicad_pcb")).unwrap();
+
+        assert_eq!(board.version, 20241229);
+        assert_eq!(board.generator, "pcbnew");
+        assert_eq!(board.thickness, 1.6);
+        assert_eq!(board.paper.size, PaperSize::A4);
+        assert_eq!(board.layers.len(), 24);
+        assert_eq!(board.copper_layers(), [Layer::FCu, Layer::BCu]);
+        assert_eq!(board.layer(Layer::BSilkS).unwrap().user_name.as_deref(), Some("B.Silkscreen"));
+        assert_eq!(board.layer(Layer::BCu).unwrap().ordinal, 2);
+        assert_eq!(board.nets, [Net { number: 0, name: String::new() }]);
+        assert!(board.footprints.is_empty());
+        assert!(board.check_version().is_ok());
+        let newer = Board { version: 20250901, ..board };
+        assert!(matches!(newer.check_version(), Err(Error::Version { found: 20250901, .. })));
+    }
+
+    #[test]
+    fn header() {
+        let board = Board::parse(DEMO).unwrap();
+
+        assert_eq!(board.title_block.as_ref().unwrap().rev.as_deref(), Some("1.2"));
+        assert_eq!(board.setup.aux_axis_origin, Some(Point::new(100.0, 120.0)));
+        assert_eq!(board.setup.stackup.len(), 9);
+        assert_eq!(board.setup.stackup[4].name, "dielectric 1");
+        assert_eq!(board.setup.stackup[4].thickness, Some(1.51));
+        assert_eq!(board.net_by_name("GND").unwrap().number, 3);
+        assert_eq!(board.net(1).unwrap().name, "VIN");
+    }
+
+    #[test]
+    fn footprints() {
+        let board = Board::parse(DEMO).unwrap();
+        let r1 = board.footprint("R1").unwrap();
+        let c1 = board.footprint("C1").unwrap();
+        let j1 = board.footprint("J1").unwrap();
+        let h1 = board.footprint("H1").unwrap();
+
+        assert_eq!(board.footprints.len(), 6);
+        assert_eq!(r1.lib_id, "Resistor_SMD:R_0603_1608Metric");
+        assert_eq!(r1.value(), Some("10k"));
+        assert_eq!(r1.property("MPN"), Some("RC0603FR-0710KL"));
+        assert_eq!(r1.path.as_deref(), Some("/813a1c5e-c6c4-4caa-891f-603de6599466"));
+        assert_eq!(r1.attributes.mount, MountKind::Smd);
+        assert_eq!(r1.properties[0].layer, Layer::FSilkS);
+        assert_eq!(r1.shapes.len(), 4);
+        assert_eq!(r1.models[0].scale, [1.0; 3]);
+
+        let pad = r1.pad("1").unwrap();
+        assert_eq!((pad.kind, pad.shape), (PadKind::Smd, PadShape::RoundRect));
+        assert_eq!(pad.net.as_ref().unwrap().name, "VIN");
+        assert!(pad.on_layer(Layer::FPaste) && !pad.on_layer(Layer::BCu));
+
+        assert!(c1.is_back());
+        assert_eq!(c1.at, Position::new(120.0, 107.5, 180.0));
+        assert_eq!(c1.sheetfile.as_deref(), Some("power.kicad_sch"));
+        assert_near(c1.pad_position(c1.pad("2").unwrap()), Point::new(119.225, 107.5));
+
+        let pin1 = &j1.pads[0];
+        assert_eq!((pin1.kind, pin1.shape), (PadKind::ThruHole, PadShape::Rect));
+        assert_eq!(pin1.drill, Some(Drill { oval: false, size: Size::new(1.0, 1.0), offset: Point::default() }));
+        assert!(pin1.on_layer(Layer::InCu(1)) && pin1.on_layer(Layer::BMask));
+        assert_near(j1.pad_position(&j1.pads[2]), Point::new(104.0, 110.08));
+
+        assert!(h1.attributes.board_only && h1.attributes.exclude_from_bom);
+        assert_eq!(h1.pads[0].kind, PadKind::NpThruHole);
+        assert_eq!(h1.pads[0].number, "");
+        assert!(matches!(h1.shapes[0].kind, ShapeKind::Circle { .. }));
+    }
+
+    #[test]
+    fn uuids() {
+        let mut board = Board::parse(DEMO).unwrap();
+        let via = board.vias[0].uuid;
+        board.footprints[0].uuid = None;
+        board.footprints[0].pads.iter_mut().for_each(|pad| pad.uuid = None);
+        board.shapes.iter_mut().for_each(|shape| shape.uuid = None);
+
+        let assign = |mut board: Board| {
+            board.assign_missing_uuids(&mut UuidGenerator::deterministic("demo"));
+            board
+        };
+        let (first, second) = (assign(board.clone()), assign(board));
+        assert_eq!(first, second);
+        assert_eq!(first.vias[0].uuid, via);
+        assert!(first.footprints[0].uuid.is_some() && first.shapes.iter().all(|shape| shape.uuid.is_some()));
+        let uuids = first.uuids();
+        assert_eq!(uuids.iter().collect::<std::collections::HashSet<_>>().len(), uuids.len());
+    }
+
+    #[test]
+    fn library() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let lib = FootprintLib::open(&source, "demo/demo.pretty").unwrap();
+        assert_eq!(lib.nickname(), Some("demo"));
+        assert_eq!(lib.names(), ["PinHeader_1x03_P2.54mm_Vertical", "R_0603_1608Metric"]);
+        assert!(lib.contains("R_0603_1608Metric") && !lib.contains("R_0805_2012Metric"));
+
+        let r = lib.get("R_0603_1608Metric").unwrap();
+        assert_eq!(r.lib_id, "R_0603_1608Metric");
+        assert_eq!(r.reference(), Some("REF**"));
+        assert_eq!(r.attributes.mount, MountKind::Smd);
+        assert_eq!(r.at, Position::default());
+        assert!(r.pads.iter().all(|pad| pad.net.is_none()));
+        let courtyard: Vec<_> = r.courtyard(Side::Top).map(|shape| &shape.kind).collect();
+        assert_eq!(
+            courtyard,
+            [&ShapeKind::Rect { start: Point::new(-1.48, -0.73), end: Point::new(1.48, 0.73) }]
+        );
+        assert_eq!(r.courtyard(Side::Bottom).count(), 0);
+        assert!(Arc::ptr_eq(&r, &lib.get("R_0603_1608Metric").unwrap()));
+
+        let loaded: Vec<_> = lib.iter().map(|(name, footprint)| (name, footprint.unwrap().pads.len())).collect();
+        assert_eq!(loaded, [("PinHeader_1x03_P2.54mm_Vertical", 3), ("R_0603_1608Metric", 2)]);
+        let header = lib.get("PinHeader_1x03_P2.54mm_Vertical").unwrap();
+        assert_eq!(
+            header.models[0].path,
+            "${KICAD9_3DMODEL_DIR}/Connector_PinHeader_2.54mm.3dshapes/PinHeader_1x03_P2.54mm_Vertical.wrl"
+        );
+        let error = lib.get("missing").unwrap_err();
+        assert_eq!(error.file(), Some(Path::new("demo/demo.pretty/missing.kicad_mod")));
+        assert!(matches!(error.kind(), Error::Io(e) if e.kind() == io::ErrorKind::NotFound));
+
+        let legacy = Footprint::parse("(module R_0603 (layer F.Cu) (attr smd) (pad 1 smd rect (at 0 0) (size 1 1)))").unwrap();
+        assert_eq!((legacy.lib_id.as_str(), legacy.pads.len()), ("R_0603", 1));
+        let source = MemorySource::new().with("bad.pretty/A.kicad_mod", "(footprint \"A\" (pad \"1\" smd blob))");
+        let error = FootprintLib::open(&source, "bad.pretty").unwrap().get("A").unwrap_err();
+        assert_eq!(error.to_string(), "bad.pretty/A.kicad_mod: footprint > pad: unknown pad shape `blob`");
+        assert_eq!(
+            Footprint::parse("(kicad_pcb)").unwrap_err().to_string(),
+            "expected (footprint ...), found (kicad_pcb ...)"
+        );
+    }
+
+    #[test]
+    fn write_footprint() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let lib = FootprintLib::open(&source, "demo/demo.pretty").unwrap();
+        for (_, footprint) in lib.iter() {
+            let footprint = footprint.unwrap();
+            let written = footprint.to_string();
+            assert!(written.starts_with("(footprint \"") && written.contains("\n\t(version 20241229)\n"));
+            assert_eq!(Footprint::parse(&written).unwrap(), *footprint);
+        }
+        let board = Board::parse(DEMO).unwrap();
+        for footprint in &board.footprints {
+            let mut written = Footprint::parse(&footprint.to_string()).unwrap();
+            assert!(!written.lib_id.contains(':'));
+            written.lib_id.clone_from(&footprint.lib_id);
+            assert_eq!(written, *footprint);
+        }
+    }
+
+    #[test]
+    fn routing() {
+        let board = Board::parse(DEMO).unwrap();
+
+        assert_eq!(board.tracks.len(), 9);
+        assert_eq!(board.tracks.iter().filter(|track| track.is_arc()).count(), 1);
+        assert_eq!(board.tracks[3].layer, Layer::BCu);
+        assert_eq!(board.tracks[0].width, 0.25);
+        assert_eq!(board.vias.len(), 2);
+        assert_eq!(board.vias[0].layers, [Layer::FCu, Layer::BCu]);
+        assert_eq!(board.net(board.vias[1].net).unwrap().name, "GND");
+
+        let zone = &board.zones[0];
+        assert_eq!(zone.net_name, "GND");
+        assert!(zone.on_layer(Layer::BCu) && !zone.on_layer(Layer::FCu));
+        assert_eq!(zone.connect_pads, PadConnection::ThermalRelief);
+        assert_eq!(zone.clearance, Some(0.5));
+        assert!(zone.filled);
+        assert_eq!(zone.outline.len(), 4);
+        assert_eq!(zone.filled_polygons[0].layer, Layer::BCu);
+    }
+
+    #[test]
+    fn via_protection() {
+        let board = Board::parse(DEMO).unwrap();
+        assert_eq!(board.vias[0].protection, ViaProtection::default());
+        assert!(board.vias[0].is_tented(Side::Bottom, &board.setup));
+        assert!(board.vias[0].mask_openings(&board.setup).is_empty());
+
+        let src = DEMO
+            .replacen("(pad_to_mask_clearance 0)", "(pad_to_mask_clearance 0) (tenting front) (filling yes)", 1)
+            .replacen(
+                "(layers \"F.Cu\" \"B.Cu\")",
+                "(layers \"F.Cu\" \"B.Cu\") (tenting (front none) (back yes)) (plugging (front yes) (back none)) \
+                 (capping yes) (filling no)",
+                1,
+            );
+        let board = Board::parse(&src).unwrap();
+        let setup = &board.setup;
+        assert_eq!(setup.via_protection.tenting, ViaSides { front: Some(true), back: Some(false) });
+        let (custom, plain) = (&board.vias[0], &board.vias[1]);
+        assert_eq!(custom.protection.tenting, ViaSides { front: None, back: Some(true) });
+        assert!(custom.is_tented(Side::Top, setup) && custom.is_tented(Side::Bottom, setup));
+        assert_eq!(plain.mask_openings(setup), [Layer::BMask]);
+        assert!(custom.is_plugged(Side::Top, setup) && !custom.is_plugged(Side::Bottom, setup));
+        assert!(custom.is_capped(setup) && !plain.is_capped(setup));
+        assert!(!custom.is_filled(setup) && plain.is_filled(setup));
+
+        let schema = kicad_sexp::schema::Schema::parse(SCHEMA).unwrap();
+        assert!(schema.validate(&kicad_sexp::parse(&src).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn embedded_files() {
+        use kicad_common::embedded::{update_checksums, EmbeddedFile, FileKind, Integrity, Stored};
+
+        let model = EmbeddedFile::new("R_0603.step", FileKind::Model, b"ISO-10303-21;", &Stored).unwrap();
+        let font = EmbeddedFile::new("title.ttf", FileKind::Font, b"\0\x01\0\0", &Stored).unwrap();
+        let section = |file: &EmbeddedFile, checksum: &str| {
+            format!(
+                "(embedded_files (file (name \"{}\") (type {}) (data |{}|) (checksum \"{}\")))",
+                file.name,
+                file.kind.as_str(),
+                file.data,
+                checksum
+            )
+        };
+        let footprint = "\t(footprint \"Resistor_SMD:R_0603_1608Metric\"\n";
+        let board_files = format!("\n\t{}\n)", section(&font, font.checksum.as_ref().unwrap()));
+        let src = DEMO
+            .replacen("\n\t(embedded_fonts no)\n)", &board_files, 1)
+            .replacen(footprint, &format!("{}\t\t{}\n", footprint, section(&model, "0BAD")), 1);
+        let board = Board::parse(&src).unwrap();
+        assert_eq!(board.embedded_files, [font]);
+        let resistor = board.footprints.iter().find(|footprint| !footprint.embedded_files.is_empty()).unwrap();
+        let (contents, integrity) = resistor.embedded_files[0].extract(&Stored).unwrap();
+        assert_eq!(contents, b"ISO-10303-21;");
+        assert!(matches!(integrity, Integrity::Mismatch { stored, .. } if stored == "0BAD"));
+
+        let issues = board.check_embedded_files(&Stored);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, ["kicad_pcb", "footprint", "embedded_files", "file"]);
+        assert!(issues[0].message.contains("\"R_0603.step\": stored checksum 0BAD does not match"));
+
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(update_checksums(&mut doc, &Stored).unwrap(), 1);
+        assert!(Board::parse(&doc.to_string()).unwrap().check_embedded_files(&Stored).is_empty());
+    }
+
+    #[test]
+    fn via_kinds() {
+        let board = Board::parse(DEMO).unwrap();
+        assert_eq!(board.vias[0].kind, ViaKind::Through);
+        assert!(board.check_vias().is_empty());
+
+        let layers = "(layers\n\t\t(0 \"F.Cu\" signal)\n\t\t(4 \"In1.Cu\" signal)\n\t\t(6 \"In2.Cu\" signal)";
+        let src = DEMO
+            .replacen("(layers\n\t\t(0 \"F.Cu\" signal)", layers, 1)
+            .replacen("(via\n", "(via micro\n", 1)
+            .replacen("(layers \"F.Cu\" \"B.Cu\")", "(layers \"F.Cu\" \"In2.Cu\")", 1)
+            .replacen("(via\n", "(via blind locked\n", 1);
+        let board = Board::parse(&src).unwrap();
+        let (micro, blind) = (&board.vias[0], &board.vias[1]);
+        assert_eq!((micro.kind, blind.kind, blind.locked), (ViaKind::Micro, ViaKind::BlindBuried, true));
+        assert!(micro.spans(Layer::InCu(1)) && !micro.spans(Layer::BCu));
+        let issues: Vec<_> = board.check_vias().iter().map(ToString::to_string).collect();
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].contains("micro via at (115, 102) joins F.Cu and In2.Cu, which are not neighbours"));
+        assert!(issues[1].contains("blind via at (112, 110) spans F.Cu to B.Cu, the whole board"));
+
+        let src = src.replacen("(via micro\n", "(via\n", 1);
+        let issues = Board::parse(&src).unwrap().check_vias();
+        assert!(issues[0].message.starts_with("through via at (115, 102) spans F.Cu to In2.Cu"));
+
+        let schema = kicad_sexp::schema::Schema::parse(SCHEMA).unwrap();
+        assert!(schema.validate(&kicad_sexp::parse(&src).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn graphics() {
+        let board = Board::parse(DEMO).unwrap();
+
+        assert_eq!(board.shapes.len(), 1);
+        assert_eq!(board.shapes[0].layer, Layer::EdgeCuts);
+        assert_eq!(
+            board.shapes[0].kind,
+            ShapeKind::Rect { start: Point::new(100.0, 100.0), end: Point::new(130.0, 120.0) }
+        );
+        assert_eq!(board.texts[0].text, "demo v1.2");
+        assert_eq!(board.texts[0].kind, None);
+    }
+
+    #[test]
+    fn errors() {
+        let error = Board::parse("(kicad_pcb (version 1) (segment (start 0 0) (end 1 1) (width 1) (layer \"X.Cu\")))")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "kicad_pcb > segment > layer: unknown layer `X.Cu`");
+
+        let error = Board::parse("(kicad_pcb (version 1) (footprint \"A\" (pad \"1\" smd blob (at 0 0))))").unwrap_err();
+        assert_eq!(error.to_string(), "kicad_pcb > footprint > pad: unknown pad shape `blob`");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let board = Board::parse(DEMO).unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+
+        assert!(json.contains("\"layer\":\"F.Cu\""));
+        assert!(json.contains("\"*.Cu\""));
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn exact() {
+        use kicad_common::exact;
+
+        let board = Board::parse(DEMO).unwrap();
+        let (c1, j1) = (board.footprint("C1").unwrap(), board.footprint("J1").unwrap());
+        let mm = |text: &str| text.parse().unwrap();
+        assert_eq!(c1.exact_pad_position(c1.pad("2").unwrap()), exact::Point::new(mm("119.225"), mm("107.5")));
+        assert_eq!(j1.exact_pad_position(&j1.pads[2]), exact::Point::new(mm("104"), mm("110.08")));
+    }
+
+    #[test]
+    fn schema() {
+        let schema = kicad_sexp::schema::Schema::parse(SCHEMA).unwrap();
+        for src in [include_str!("../../reference-files/empty/empty.kicad_pcb"), DEMO] {
+            let issues = schema.validate(&kicad_sexp::parse(src).unwrap());
+            assert!(issues.is_empty(), "{:?}", issues.iter().map(ToString::to_string).collect::<Vec<_>>());
+        }
+
+        let src = DEMO.replacen("(width 0.25)", "(width wide)", 1).replacen("(pad \"1\" smd", "(pad \"1\" smt", 1);
+        let tree = kicad_sexp::parse(&src).unwrap();
+        let issues: Vec<_> = schema.validate(&tree).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "kicad_pcb > footprint > pad: expected one of thru_hole, smd, connect, np_thru_hole, found `smt`",
+                "kicad_pcb > segment > width: expected a number, found `wide`",
+            ]
+        );
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Panel(String);
+
+    impl kicad_common::FromSexp for Panel {
+        fn from_sexp(list: &kicad_common::List) -> kicad_common::convert::Result<Self> {
+            Ok(Panel(list.string(0)?))
+        }
+    }
+
+    impl kicad_common::extension::Extension for Panel {
+        const TAG: &'static str = "x_panel";
+
+        fn to_sexp(&self) -> kicad_sexp::SexpOwned {
+            kicad_sexp::SexpBuilder::new(Self::TAG).string(&self.0).build()
+        }
+    }
+
+    #[test]
+    fn extensions() {
+        let src = DEMO
+            .replacen("\t(net 0 \"\")", "\t(net 0 \"\")\n\t(x_panel \"board\")", 1)
+            .replacen("_1608Metric\"", "_1608Metric\" (x_panel \"R\")", 1);
+        let mut registry = kicad_common::extension::Registry::new();
+        registry.register::<Panel>();
+
+        let plain = Board::parse(&src).unwrap();
+        assert!(plain.extensions.is_empty());
+        let board = registry.scope(|| Board::parse(&src)).unwrap();
+        assert_eq!(board.extensions[0].get::<Panel>(), Some(&Panel("board".into())));
+        let footprint = board.footprints.iter().find(|f| !f.extensions.is_empty()).unwrap();
+        assert_eq!(footprint.extensions[0].to_sexp().to_string(), "(x_panel \"R\")");
+        assert_eq!(board.footprints.len(), plain.footprints.len());
+    }
+}
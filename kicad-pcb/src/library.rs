@@ -0,0 +1,98 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use kicad_common::Error;
+use kicad_sexp::{cache::Cache, input, source::Source};
+
+use crate::Footprint;
+
+/// A `.pretty` footprint library directory, one `.kicad_mod` file per
+/// footprint.
+///
+/// Opening only lists the directory; footprints are parsed on first access
+/// and cached until their file changes.
+pub struct FootprintLib<'s> {
+    source: &'s dyn Source,
+    dir: PathBuf,
+    names: Vec<String>,
+    cache: Cache<Footprint>,
+}
+
+impl fmt::Debug for FootprintLib<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FootprintLib").field("dir", &self.dir).field("names", &self.names).finish_non_exhaustive()
+    }
+}
+
+impl<'s> FootprintLib<'s> {
+    /// Lists the footprints in `dir`.
+    pub fn open(source: &'s dyn Source, dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        let mut names: Vec<String> = source
+            .read_dir(&dir)?
+            .iter()
+            .filter(|path| path.extension().is_some_and(|extension| extension == "kicad_mod"))
+            .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+            .collect();
+        names.sort();
+        Ok(FootprintLib { source, dir, names, cache: Cache::new() })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The library nickname implied by the directory name, without `.pretty`.
+    pub fn nickname(&self) -> Option<&str> {
+        let name = self.dir.file_name()?.to_str()?;
+        Some(name.strip_suffix(".pretty").unwrap_or(name))
+    }
+
+    /// Footprint names, sorted.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.binary_search_by(|probe| probe.as_str().cmp(name)).is_ok()
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.kicad_mod", name))
+    }
+
+    /// Loads the footprint `name`, parsing its file unless it is cached and
+    /// unchanged. Errors carry the file's path.
+    pub fn get(&self, name: &str) -> Result<Arc<Footprint>, Error> {
+        let path = self.existing_path(name)?;
+        self.cache
+            .get_or_load(self.source, &path, |bytes| {
+                let src = input::normalize(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                Footprint::parse(&src)
+            })
+            .map_err(|e| e.in_file(path))
+    }
+
+    /// The text of the footprint file `name`, for copying it into a board.
+    pub(crate) fn read(&self, name: &str) -> Result<String, Error> {
+        let path = self.existing_path(name)?;
+        self.source.read_to_string(&path).map_err(|e| Error::Io(e).in_file(path))
+    }
+
+    fn existing_path(&self, name: &str) -> Result<PathBuf, Error> {
+        let path = self.path(name);
+        if !self.contains(name) {
+            let message = format!("no footprint `{}` in {}", name, self.dir.display());
+            return Err(Error::Io(io::Error::new(io::ErrorKind::NotFound, message)).in_file(path));
+        }
+        Ok(path)
+    }
+
+    /// Loads every footprint in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Result<Arc<Footprint>, Error>)> {
+        self.names.iter().map(|name| (name.as_str(), self.get(name)))
+    }
+}
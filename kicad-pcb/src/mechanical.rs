@@ -0,0 +1,367 @@
+//! Mechanical interface data for enclosure design: board outline, mounting
+//! holes and connector placement.
+//!
+//! Report coordinates are in millimeters relative to the drill/place origin
+//! (or the board origin if none is set), with Y pointing up as in MCAD tools.
+
+use std::fmt::Write;
+
+use kicad_common::Point;
+use kicad_sexp::number::format_float;
+
+use crate::{
+    bounds::{bezier_points, circle_center, distance, footprint_shape_points, map_points, shape_points, Bounds},
+    Board, Footprint, Layer, PadKind, ShapeKind, Side,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Outline {
+    pub fn width(&self) -> f64 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max.y - self.min.y
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hole {
+    pub reference: String,
+    pub pad: String,
+    pub position: Point,
+    pub diameter: f64,
+    pub plated: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectorPlacement {
+    pub reference: String,
+    pub lib_id: String,
+    /// Footprint origin.
+    pub position: Point,
+    /// Rotation in degrees, counter-clockwise.
+    pub angle: f64,
+    pub side: Side,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MechanicalReport {
+    /// The report origin in board coordinates.
+    pub origin: Point,
+    /// Bounds of the `Edge.Cuts` drawings.
+    pub outline: Option<Outline>,
+    /// `Edge.Cuts` drawings, in report coordinates.
+    pub edges: Vec<ShapeKind>,
+    pub holes: Vec<Hole>,
+    pub connectors: Vec<ConnectorPlacement>,
+}
+
+fn is_mounting_hole(footprint: &Footprint) -> bool {
+    footprint.lib_id.starts_with("MountingHole:")
+}
+
+/// Connectors are recognized by a `Connector*` library or a `J`/`P` reference.
+fn is_connector(footprint: &Footprint) -> bool {
+    let library = footprint.lib_id.split_once(':').map_or("", |(library, _)| library);
+    let prefix = footprint.reference().unwrap_or_default().trim_end_matches(|c: char| c.is_ascii_digit());
+    library.starts_with("Connector") || matches!(prefix, "J" | "P")
+}
+
+/// Collects the mechanical report for `board`.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(footprints = board.footprints.len())))]
+pub fn mechanical_report(board: &Board) -> MechanicalReport {
+    let origin = board.setup.aux_axis_origin.unwrap_or_default();
+    let to_report = |p: Point| Point::new(p.x - origin.x, origin.y - p.y);
+
+    let mut edges = Vec::new();
+    let mut outline_points = Vec::new();
+    for shape in board.shapes.iter().filter(|shape| shape.layer == Layer::EdgeCuts) {
+        outline_points.extend(shape_points(&shape.kind));
+        edges.push(map_points(&shape.kind, to_report));
+    }
+    for footprint in &board.footprints {
+        for shape in footprint.shapes.iter().filter(|shape| shape.layer == Layer::EdgeCuts) {
+            outline_points.extend(footprint_shape_points(footprint, &shape.kind));
+            edges.push(map_points(&shape.kind, |p| to_report(footprint.to_board(p))));
+        }
+    }
+    let outline = Bounds::of(outline_points.into_iter().map(to_report)).map(|bounds| Outline {
+        min: bounds.min,
+        max: bounds.max,
+    });
+
+    let mut holes = Vec::new();
+    let mut connectors = Vec::new();
+    for footprint in &board.footprints {
+        let reference = footprint.reference().unwrap_or_default();
+        for pad in &footprint.pads {
+            let Some(drill) = pad.drill else { continue };
+            let plated = pad.kind != PadKind::NpThruHole;
+            if plated && !is_mounting_hole(footprint) {
+                continue;
+            }
+            holes.push(Hole {
+                reference: reference.to_string(),
+                pad: pad.number.clone(),
+                position: to_report(footprint.pad_position(pad) + drill.offset.rotated(pad.at.angle)),
+                diameter: drill.size.width.min(drill.size.height),
+                plated,
+            });
+        }
+        if is_connector(footprint) {
+            connectors.push(ConnectorPlacement {
+                reference: reference.to_string(),
+                lib_id: footprint.lib_id.clone(),
+                position: to_report(footprint.at.point()),
+                angle: footprint.at.angle,
+                side: footprint.side(),
+            });
+        }
+    }
+
+    MechanicalReport { origin, outline, edges, holes, connectors }
+}
+
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn json_point(out: &mut String, p: Point) {
+    let _ = write!(out, "[{}, {}]", format_float(p.x), format_float(p.y));
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Top => "top",
+        Side::Bottom => "bottom",
+    }
+}
+
+impl MechanicalReport {
+    /// Formats the report as a JSON object.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n  \"origin\": ");
+        json_point(&mut out, self.origin);
+        out.push_str(",\n  \"outline\": ");
+        match &self.outline {
+            Some(outline) => {
+                out.push_str("{\"min\": ");
+                json_point(&mut out, outline.min);
+                out.push_str(", \"max\": ");
+                json_point(&mut out, outline.max);
+                let _ = write!(
+                    out,
+                    ", \"width\": {}, \"height\": {}}}",
+                    format_float(outline.width()),
+                    format_float(outline.height())
+                );
+            },
+            None => out.push_str("null"),
+        }
+
+        out.push_str(",\n  \"holes\": [");
+        for (i, hole) in self.holes.iter().enumerate() {
+            out.push_str(if i == 0 { "\n    " } else { ",\n    " });
+            out.push_str("{\"reference\": ");
+            json_string(&mut out, &hole.reference);
+            out.push_str(", \"pad\": ");
+            json_string(&mut out, &hole.pad);
+            out.push_str(", \"position\": ");
+            json_point(&mut out, hole.position);
+            let _ = write!(out, ", \"diameter\": {}, \"plated\": {}}}", format_float(hole.diameter), hole.plated);
+        }
+        out.push_str(if self.holes.is_empty() { "]" } else { "\n  ]" });
+
+        out.push_str(",\n  \"connectors\": [");
+        for (i, connector) in self.connectors.iter().enumerate() {
+            out.push_str(if i == 0 { "\n    " } else { ",\n    " });
+            out.push_str("{\"reference\": ");
+            json_string(&mut out, &connector.reference);
+            out.push_str(", \"footprint\": ");
+            json_string(&mut out, &connector.lib_id);
+            out.push_str(", \"position\": ");
+            json_point(&mut out, connector.position);
+            let _ = write!(
+                out,
+                ", \"angle\": {}, \"side\": \"{}\"}}",
+                format_float(connector.angle),
+                side_name(connector.side)
+            );
+        }
+        out.push_str(if self.connectors.is_empty() { "]" } else { "\n  ]" });
+        out.push_str("\n}\n");
+        out
+    }
+
+    /// Formats the outline, holes and connector origins as an ASCII DXF
+    /// drawing on the `OUTLINE`, `HOLES` and `CONNECTORS` layers.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(edges = self.edges.len())))]
+    pub fn to_dxf(&self) -> String {
+        let mut dxf = Dxf(String::from("0\nSECTION\n2\nENTITIES\n"));
+        for edge in &self.edges {
+            match edge {
+                ShapeKind::Line { start, end } => dxf.line(*start, *end),
+                ShapeKind::Rect { start, end } => {
+                    let corners = Bounds { min: *start, max: *end }.corners();
+                    dxf.polyline(&corners, true);
+                },
+                ShapeKind::Circle { center, end } => dxf.circle("OUTLINE", *center, distance(*center, *end)),
+                ShapeKind::Arc { start, mid, end } => dxf.arc(*start, *mid, *end),
+                ShapeKind::Polygon(points) => dxf.polyline(points, true),
+                ShapeKind::Curve(points) => dxf.polyline(&bezier_points(points), false),
+            }
+        }
+        for hole in &self.holes {
+            dxf.circle("HOLES", hole.position, hole.diameter / 2.0);
+        }
+        for connector in &self.connectors {
+            dxf.entity("POINT", "CONNECTORS");
+            dxf.xy(10, connector.position);
+            dxf.entity("TEXT", "CONNECTORS");
+            dxf.xy(10, connector.position);
+            dxf.group(40, "1");
+            dxf.group(1, &connector.reference);
+            dxf.group(50, &format_float(connector.angle));
+        }
+        dxf.0.push_str("0\nENDSEC\n0\nEOF\n");
+        dxf.0
+    }
+}
+
+struct Dxf(String);
+
+impl Dxf {
+    fn group(&mut self, code: u32, value: &str) {
+        let _ = writeln!(self.0, "{}\n{}", code, value);
+    }
+
+    fn entity(&mut self, kind: &str, layer: &str) {
+        self.group(0, kind);
+        self.group(8, layer);
+    }
+
+    fn xy(&mut self, code: u32, p: Point) {
+        self.group(code, &format_float(p.x));
+        self.group(code + 10, &format_float(p.y));
+    }
+
+    fn line(&mut self, start: Point, end: Point) {
+        self.entity("LINE", "OUTLINE");
+        self.xy(10, start);
+        self.xy(11, end);
+    }
+
+    fn polyline(&mut self, points: &[Point], closed: bool) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1]);
+        }
+        if let (true, [first, .., last]) = (closed, points) {
+            self.line(*last, *first);
+        }
+    }
+
+    fn circle(&mut self, layer: &str, center: Point, radius: f64) {
+        self.entity("CIRCLE", layer);
+        self.xy(10, center);
+        self.group(40, &format_float(radius));
+    }
+
+    /// DXF arcs run counter-clockwise from the start angle to the end angle.
+    fn arc(&mut self, start: Point, mid: Point, end: Point) {
+        let Some(center) = circle_center(start, mid, end) else {
+            return self.line(start, end);
+        };
+        let angle = |p: Point| (p.y - center.y).atan2(p.x - center.x).to_degrees().rem_euclid(360.0);
+        let clockwise = (mid.x - start.x) * (end.y - mid.y) - (mid.y - start.y) * (end.x - mid.x) < 0.0;
+        let (from, to) = if clockwise { (end, start) } else { (start, end) };
+        self.entity("ARC", "OUTLINE");
+        self.xy(10, center);
+        self.group(40, &format_float(distance(center, start)));
+        self.group(50, &format_float(angle(from)));
+        self.group(51, &format_float(angle(to)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_board() {
+        let board = Board::parse(include_str!("../../reference-files/demo/demo.kicad_pcb")).unwrap();
+        let report = mechanical_report(&board);
+        let outline = report.outline.unwrap();
+
+        assert_eq!(report.origin, Point::new(100.0, 120.0));
+        assert_eq!((outline.min, outline.width(), outline.height()), (Point::new(0.0, 0.0), 30.0, 20.0));
+
+        let holes: Vec<_> = report.holes.iter().map(|hole| (hole.reference.as_str(), hole.position)).collect();
+        assert_eq!(holes, [("H1", Point::new(4.0, 4.0)), ("H2", Point::new(26.0, 4.0))]);
+        assert_eq!(report.holes[0].diameter, 3.2);
+        assert!(!report.holes[0].plated);
+
+        assert_eq!(report.connectors.len(), 1);
+        assert_eq!(report.connectors[0].reference, "J1");
+        assert_eq!(report.connectors[0].position, Point::new(4.0, 15.0));
+        assert_eq!(report.connectors[0].side, Side::Top);
+
+        let json = report.to_json();
+        assert!(json.contains("\"outline\": {\"min\": [0, 0], \"max\": [30, 20], \"width\": 30, \"height\": 20}"));
+        assert!(json.contains(
+            "{\"reference\": \"H1\", \"pad\": \"\", \"position\": [4, 4], \"diameter\": 3.2, \"plated\": false}"
+        ));
+        assert!(json.contains("\"position\": [4, 15], \"angle\": 0, \"side\": \"top\"}"));
+
+        let dxf = report.to_dxf();
+        assert_eq!(dxf.matches("\nLINE\n8\nOUTLINE\n").count(), 4);
+        assert_eq!(dxf.matches("\nCIRCLE\n8\nHOLES\n").count(), 2);
+        assert!(dxf.starts_with("0\nSECTION\n") && dxf.ends_with("0\nEOF\n"));
+    }
+
+    #[test]
+    fn rotated_drills_and_curves() {
+        let board = Board::parse(
+            "(kicad_pcb (version 20240108) (generator \"pcbnew\")
+                (footprint \"MountingHole\" (layer \"F.Cu\") (at 10 10 90)
+                    (pad \"\" np_thru_hole oval (at 0 0 180) (size 3 3) (drill 1 (offset 1 0)) (layers \"*.Cu\")))
+                (gr_curve (pts (xy 0 0) (xy 0 9) (xy 9 9) (xy 9 0)) (layer \"Edge.Cuts\") (width 0.1)))",
+        )
+        .unwrap();
+        let report = mechanical_report(&board);
+        // Pad angles are absolute, so the offset turns with the pad and not the footprint.
+        assert_eq!(report.holes[0].position, Point::new(9.0, -10.0));
+
+        let dxf = report.to_dxf();
+        assert_eq!(dxf.matches("\nLINE\n8\nOUTLINE\n").count(), 16);
+        assert!(dxf.contains("\n10\n0\n20\n0\n") && !dxf.contains("\n10\n9\n20\n-9\n"));
+    }
+
+    #[test]
+    fn arcs() {
+        let mut dxf = Dxf(String::new());
+        dxf.arc(Point::new(1.0, 0.0), Point::new(0.0, 1.0), Point::new(-1.0, 0.0));
+        assert!(dxf.0.ends_with("40\n1\n50\n0\n51\n180\n"));
+
+        let mut dxf = Dxf(String::new());
+        dxf.arc(Point::new(-1.0, 0.0), Point::new(0.0, 1.0), Point::new(1.0, 0.0));
+        assert!(dxf.0.ends_with("50\n0\n51\n180\n"));
+    }
+}
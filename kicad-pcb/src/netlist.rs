@@ -0,0 +1,362 @@
+//! Boards updated from a netlist, as KiCad's "Update PCB from Schematic"
+//! does, for designs that come from a script or an HDL such as skidl
+//! rather than from a schematic: footprints are placed for new parts, the
+//! values and symbol links of the others refreshed, and every pad gets the
+//! net the netlist puts it on.
+//!
+//! New footprints are placed in rows below the footprints already on the
+//! board, a row for each reference prefix, for the layout to start from.
+//! Footprints of parts the netlist does not have are reported, not
+//! deleted.
+//!
+//! ```no_run
+//! use kicad_common::{netlist::Netlist, UuidGenerator};
+//! use kicad_pcb::{
+//!     netlist::{write_netlist_update, NetlistOptions},
+//!     BoardBuilder, FootprintLib,
+//! };
+//! use kicad_sexp::source::FsSource;
+//!
+//! let netlist = Netlist::parse(&std::fs::read_to_string("divider.net")?)?;
+//! let source = FsSource::new();
+//! let libraries = [
+//!     FootprintLib::open(&source, "Resistor_SMD.pretty")?,
+//!     FootprintLib::open(&source, "Connector_PinHeader_2.54mm.pretty")?,
+//! ];
+//! let builder = BoardBuilder::new();
+//! let mut board = builder.build()?;
+//! let mut uuids = UuidGenerator::deterministic("divider");
+//! let changes = board.update_from_netlist(&netlist, &libraries, &NetlistOptions::default(), &mut uuids)?;
+//! let src = builder.to_string();
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_netlist_update(&board, &changes, &libraries, &mut doc)?;
+//! std::fs::write("divider.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashMap;
+
+use kicad_common::{netlist::Netlist, reference::natural_cmp, ConvertError, Error, Point, Position, UuidGenerator};
+use kicad_sexp::cst::{Document, Node};
+
+use crate::{
+    index::Item,
+    layout::{insert_child, write_footprints, write_nets, AFTER_NET},
+    selection::{board_items, bounds, node_uuid, set_property, write_properties, Selection},
+    Board, Footprint, FootprintLib, Side,
+};
+
+/// Footprint children KiCad writes after the symbol link.
+const AFTER_PATH: [&str; 3] = ["sheetname", "sheetfile", "attr"];
+
+/// Settings for [`Board::update_from_netlist`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetlistOptions {
+    /// Top left corner of the first row of new footprints; `None` for
+    /// below the footprints on the board, or (100, 100) on an empty one.
+    pub origin: Option<Point>,
+    /// Width of a row of new footprints.
+    pub width: f64,
+    /// Room between footprints and between rows.
+    pub spacing: f64,
+    /// New footprints are placed on this grid.
+    pub grid: f64,
+    pub side: Side,
+}
+
+impl Default for NetlistOptions {
+    fn default() -> Self {
+        NetlistOptions { origin: None, width: 100.0, spacing: 2.54, grid: 1.27, side: Side::Top }
+    }
+}
+
+/// What [`Board::update_from_netlist`] changed, by reference.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetlistChanges {
+    /// Footprints placed for parts new to the board.
+    pub added: Vec<String>,
+    /// Footprints whose value, symbol link or pad nets changed.
+    pub updated: Vec<String>,
+    /// Footprints linked to a symbol that the netlist does not have.
+    pub orphaned: Vec<String>,
+}
+
+impl Board {
+    /// Makes the board match `netlist`. Parts without a footprint on the
+    /// board get one from `libraries`, found by the nickname of the part's
+    /// footprint, and every footprint of a part gets the part's value and
+    /// symbol link and has its pads on the netlist's nets; pads the netlist
+    /// leaves out are disconnected. Footprints without a symbol link, like
+    /// mounting holes, are left alone. New footprints and pads get UUIDs
+    /// from `uuids`.
+    pub fn update_from_netlist(
+        &mut self,
+        netlist: &Netlist,
+        libraries: &[FootprintLib],
+        options: &NetlistOptions,
+        uuids: &mut UuidGenerator,
+    ) -> Result<NetlistChanges, Error> {
+        let mut changes = NetlistChanges::default();
+        for footprint in &self.footprints {
+            let reference = footprint.reference().unwrap_or_default();
+            if footprint.path.is_some() && !reference.starts_with('#') && netlist.component(reference).is_none() {
+                changes.orphaned.push(reference.to_string());
+            }
+        }
+        let mut components: Vec<_> = netlist.components.iter().collect();
+        components.sort_by(|a, b| natural_cmp(&a.reference, &b.reference));
+        for component in &components {
+            let reference = component.reference.as_str();
+            if self.footprint(reference).is_some() {
+                continue;
+            }
+            let lib_id = component.footprint.as_deref();
+            let lib_id = lib_id.ok_or_else(|| ConvertError::new(format!("part {} has no footprint", reference)))?;
+            let nickname = lib_id.split_once(':').map_or("", |(nickname, _)| nickname);
+            let library = libraries.iter().find(|library| library.nickname() == Some(nickname));
+            let library = library.ok_or_else(|| ConvertError::new(format!("no footprint library {}", nickname)))?;
+            self.place_footprint(library, lib_id, Point::default(), 0.0, options.side, reference)?;
+            changes.added.push(reference.to_string());
+        }
+        self.assign_missing_uuids(uuids);
+
+        let mut pins: HashMap<(&str, &str), &str> = HashMap::new();
+        for net in &netlist.nets {
+            for node in &net.nodes {
+                pins.insert((node.reference.as_str(), node.pin.as_str()), net.name.as_str());
+            }
+        }
+        for component in components {
+            let reference = component.reference.as_str();
+            let index = self.footprints.iter().position(|footprint| footprint.reference() == Some(reference));
+            let index = index.expect("every part has a footprint now");
+            let footprint = &self.footprints[index];
+            let missing = pins.keys().find(|&&(part, pin)| part == reference && footprint.pad(pin).is_none());
+            if let Some((_, pin)) = missing {
+                return Err(ConvertError::new(format!("footprint {} has no pad {}", reference, pin)).into());
+            }
+            let selection: Selection = footprint.uuid.into_iter().collect();
+            let mut changed = set_property(self, &selection, "Value", &component.value) > 0;
+            let footprint = &mut self.footprints[index];
+            if component.path.is_some() && footprint.path != component.path {
+                footprint.path = component.path.clone();
+                changed = true;
+            }
+            for pad in 0..self.footprints[index].pads.len() {
+                let number = self.footprints[index].pads[pad].number.clone();
+                let net = pins.get(&(reference, number.as_str())).map(|name| self.add_net(name).clone());
+                let pad = &mut self.footprints[index].pads[pad];
+                if pad.net != net {
+                    pad.net = net;
+                    changed = true;
+                }
+            }
+            if changed && !changes.added.iter().any(|added| added == reference) {
+                changes.updated.push(reference.to_string());
+            }
+        }
+        self.arrange_new(&changes.added, options);
+        Ok(changes)
+    }
+
+    /// Places the footprints `added` in rows, as [`NetlistOptions`] says.
+    fn arrange_new(&mut self, added: &[String], options: &NetlistOptions) {
+        let old = self.footprints.iter().filter(|footprint| {
+            !footprint.reference().is_some_and(|reference| added.iter().any(|name| name == reference))
+        });
+        let old = old.filter_map(|footprint| bounds(Item::Footprint(footprint)));
+        let old: Vec<_> = old.collect();
+        let origin = options.origin.unwrap_or_else(|| {
+            let left = old.iter().map(|bounds| bounds.min.x).reduce(f64::min);
+            let bottom = old.iter().map(|bounds| bounds.max.y).reduce(f64::max);
+            match (left, bottom) {
+                (Some(left), Some(bottom)) => Point::new(left, bottom + options.spacing),
+                _ => Point::new(100.0, 100.0),
+            }
+        });
+        let snap = |v: f64| if options.grid > 0.0 { (v / options.grid).ceil() * options.grid } else { v };
+        let round = |v: f64| (v * 1e6).round() / 1e6;
+        let (mut x, mut y, mut row_height) = (origin.x, origin.y, 0.0f64);
+        let mut row_prefix: Option<&str> = None;
+        for reference in added {
+            let Some(footprint) = self.footprints.iter_mut().find(|footprint| footprint.reference() == Some(reference))
+            else {
+                continue;
+            };
+            let prefix = reference.trim_end_matches(|c: char| c.is_ascii_digit());
+            let Some(extent) = bounds(Item::Footprint(footprint)) else {
+                continue;
+            };
+            // Relative to the footprint, which is still at the origin.
+            let at = footprint.at.point();
+            let min = Point::new(extent.min.x - at.x, extent.min.y - at.y);
+            let max = Point::new(extent.max.x - at.x, extent.max.y - at.y);
+            let full = x + (max.x - min.x) > origin.x + options.width;
+            if row_prefix.is_some() && (row_prefix != Some(prefix) || full) {
+                (x, y, row_height) = (origin.x, y + row_height + options.spacing, 0.0);
+            }
+            row_prefix = Some(prefix);
+            let at = Point::new(round(snap(x - min.x)), round(snap(y - min.y)));
+            footprint.at = Position::new(at.x, at.y, footprint.at.angle);
+            x = at.x + max.x + options.spacing;
+            row_height = row_height.max(at.y + max.y - y);
+        }
+    }
+}
+
+/// Writes what [`Board::update_from_netlist`] changed into the board
+/// `doc`: the new footprints from `libraries`, the nets, and the values,
+/// symbol links and pad nets of the new and updated footprints.
+pub fn write_netlist_update(
+    board: &Board,
+    changes: &NetlistChanges,
+    libraries: &[FootprintLib],
+    doc: &mut Document<'_>,
+) -> Result<(), Error> {
+    for library in libraries {
+        write_footprints(board, library, doc)?;
+    }
+    let names = changes.added.iter().chain(&changes.updated);
+    let footprints: Vec<_> = names.filter_map(|reference| board.footprint(reference)).collect();
+    let selection: Selection = footprints.iter().filter_map(|footprint| footprint.uuid).collect();
+    write_properties(board, &selection, doc)?;
+    let items = board_items(doc)?;
+    write_nets(board, items);
+    for footprint in footprints {
+        let node = items.iter_mut().find(|item| item.tag() == Some("footprint") && node_uuid(item) == footprint.uuid);
+        let node = node.ok_or_else(|| ConvertError::new(format!("no footprint {:?} in the board", footprint.uuid)))?;
+        if let Some(path) = &footprint.path {
+            let children = node.items_mut().expect("footprint is a list");
+            match children.iter_mut().find(|child| child.tag() == Some("path")) {
+                Some(existing) => existing.items_mut().expect("tagged nodes are lists")[1].set_string(path),
+                None => {
+                    let property = children.iter().rposition(|child| child.tag() == Some("property"));
+                    let mut child = Node::tagged("path", vec![Node::string(path)]);
+                    child.leading = match property {
+                        Some(i) => children[i].leading.clone(),
+                        None => "\n\t\t".into(),
+                    };
+                    let later = |child: &Node| child.tag().is_some_and(|tag| AFTER_PATH.contains(&tag));
+                    let after = children.iter().position(later);
+                    let at = property.map(|i| i + 1).or(after).unwrap_or(children.len());
+                    children.insert(at, child);
+                },
+            }
+        }
+        write_pad_nets(footprint, node);
+    }
+    Ok(())
+}
+
+/// Sets the nets of the pads of `node`, the footprint's node in a board
+/// file, to those of `footprint`'s pads, which are in the same order.
+pub(crate) fn write_pad_nets(footprint: &Footprint, node: &mut Node<'_>) {
+    let pads = node.items_mut().expect("footprint is a list").iter_mut().filter(|item| item.tag() == Some("pad"));
+    for (item, pad) in pads.zip(&footprint.pads) {
+        let children = item.items_mut().expect("tagged nodes are lists");
+        let existing = children.iter().position(|child| child.tag() == Some("net"));
+        match (&pad.net, existing) {
+            (Some(net), Some(i)) => {
+                let leading = children[i].leading.clone();
+                let net = vec![Node::atom(net.number.to_string()), Node::string(&net.name)];
+                children[i] = Node::tagged("net", net);
+                children[i].leading = leading;
+            },
+            (Some(net), None) => {
+                let net = Node::tagged("net", vec![Node::atom(net.number.to_string()), Node::string(&net.name)]);
+                insert_child(item, net, &AFTER_NET);
+            },
+            (None, Some(i)) => {
+                children.remove(i);
+            },
+            (None, None) => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::netlist::NetNode;
+    use kicad_sexp::source::FsSource;
+
+    use super::*;
+    use crate::BoardBuilder;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+    const NETLIST: &str = include_str!("../../reference-files/demo/demo.net");
+
+    fn pad_nets(board: &Board, reference: &str) -> Vec<String> {
+        let footprint = board.footprint(reference).unwrap();
+        footprint.pads.iter().map(|pad| pad.net.as_ref().map_or(String::new(), |net| net.name.clone())).collect()
+    }
+
+    #[test]
+    fn new_board() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let libraries = [FootprintLib::open(&source, "demo/demo.pretty").unwrap()];
+        let mut netlist = Netlist::parse(NETLIST).unwrap();
+        for component in &mut netlist.components {
+            let name = component.footprint.as_deref().unwrap().split_once(':').unwrap().1;
+            component.footprint = Some(format!("demo:{}", name));
+        }
+        netlist.components[1].path = Some("/813a1c5e-c6c4-4caa-891f-603de6599466".to_string());
+
+        let builder = BoardBuilder::new();
+        let mut board = builder.build().unwrap();
+        let mut uuids = UuidGenerator::deterministic("netlist");
+        let changes = board.update_from_netlist(&netlist, &libraries, &NetlistOptions::default(), &mut uuids).unwrap();
+        assert_eq!(changes.added, ["J1", "R1", "R2"]);
+        assert!(changes.updated.is_empty() && changes.orphaned.is_empty());
+        assert_eq!(pad_nets(&board, "J1"), ["VIN", "VOUT", "GND"]);
+        assert_eq!(pad_nets(&board, "R2"), ["VOUT", "GND"]);
+        assert_eq!(board.footprint("R1").unwrap().value(), Some("10k"));
+        // J1 has a row of its own, the resistors share the next one.
+        let at = |reference: &str| board.footprint(reference).unwrap().at.point();
+        let (j1, r1, r2) = (at("J1"), at("R1"), at("R2"));
+        assert!(j1.x >= 100.0 && j1.y >= 100.0 && j1.y < r1.y && r1.y == r2.y && r1.x < r2.x, "{:?}", (j1, r1, r2));
+
+        let src = builder.to_string();
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        write_netlist_update(&board, &changes, &libraries, &mut doc).unwrap();
+        let written = Board::parse(&doc.to_string()).unwrap();
+        assert_eq!(written.nets, board.nets);
+        for reference in ["J1", "R1", "R2"] {
+            assert_eq!(pad_nets(&written, reference), pad_nets(&board, reference));
+            assert_eq!(written.footprint(reference).unwrap().value(), board.footprint(reference).unwrap().value());
+            assert_eq!(written.footprint(reference).unwrap().at, board.footprint(reference).unwrap().at);
+        }
+        assert_eq!(written.footprint("R1").unwrap().path, netlist.components[1].path);
+
+        // Nothing changes the second time.
+        let again = board.update_from_netlist(&netlist, &libraries, &NetlistOptions::default(), &mut uuids).unwrap();
+        assert_eq!(again, NetlistChanges::default());
+    }
+
+    #[test]
+    fn existing_board() {
+        let mut netlist = Netlist::parse(NETLIST).unwrap();
+        netlist.components[1].value = "22k".to_string();
+        // R2's pad 2 moves from GND to VIN.
+        let node = netlist.nets[2].nodes.pop().unwrap();
+        netlist.nets[0].nodes.push(node);
+
+        let mut board = Board::parse(DEMO).unwrap();
+        let mut uuids = UuidGenerator::deterministic("netlist");
+        let changes = board.update_from_netlist(&netlist, &[], &NetlistOptions::default(), &mut uuids).unwrap();
+        assert!(changes.added.is_empty());
+        assert_eq!(changes.updated, ["R1", "R2"]);
+        assert_eq!(changes.orphaned, ["C1"]);
+        assert_eq!(pad_nets(&board, "R2"), ["VOUT", "VIN"]);
+
+        let mut doc = kicad_sexp::cst::parse(DEMO).unwrap();
+        write_netlist_update(&board, &changes, &[], &mut doc).unwrap();
+        let written = Board::parse(&doc.to_string()).unwrap();
+        assert_eq!(written.footprint("R1").unwrap().value(), Some("22k"));
+        assert_eq!(pad_nets(&written, "R2"), ["VOUT", "VIN"]);
+        assert_eq!(pad_nets(&written, "C1"), pad_nets(&Board::parse(DEMO).unwrap(), "C1"));
+
+        netlist.nets[0].nodes.push(NetNode { reference: "R1".into(), pin: "3".into(), ..Default::default() });
+        let error = board.update_from_netlist(&netlist, &[], &NetlistOptions::default(), &mut uuids).unwrap_err();
+        assert_eq!(error.to_string(), "footprint R1 has no pad 3");
+    }
+}
@@ -0,0 +1,267 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    uuid::item_uuid,
+    Point, Position, Size, Uuid,
+};
+
+use kicad_sexp::SexpBuilder;
+
+use crate::{
+    graphics::{at_sexp, point_sexp},
+    layer::{layer_selectors, LayerSelector},
+    Layer, Net, PadConnection,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PadKind {
+    ThruHole,
+    Smd,
+    /// Edge connector finger, copper without paste.
+    Connect,
+    /// Non-plated hole.
+    NpThruHole,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PadShape {
+    Circle,
+    Rect,
+    Oval,
+    Trapezoid,
+    RoundRect,
+    Custom,
+}
+
+/// A pad's fabrication property, as in `(property pad_prop_castellated)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PadProperty {
+    Bga,
+    FiducialGlobal,
+    FiducialLocal,
+    TestPoint,
+    HeatSink,
+    /// A plated hole cut in half by the board edge, for modules soldered
+    /// onto a carrier board.
+    Castellated,
+    Mechanical,
+    PressFit,
+}
+
+impl PadProperty {
+    const NAMES: [(PadProperty, &'static str); 8] = [
+        (PadProperty::Bga, "pad_prop_bga"),
+        (PadProperty::FiducialGlobal, "pad_prop_fiducial_glob"),
+        (PadProperty::FiducialLocal, "pad_prop_fiducial_loc"),
+        (PadProperty::TestPoint, "pad_prop_testpoint"),
+        (PadProperty::HeatSink, "pad_prop_heatsink"),
+        (PadProperty::Castellated, "pad_prop_castellated"),
+        (PadProperty::Mechanical, "pad_prop_mechanical"),
+        (PadProperty::PressFit, "pad_prop_pressfit"),
+    ];
+
+    /// The name in KiCad's files.
+    pub fn name(self) -> &'static str {
+        let (_, name) = Self::NAMES.iter().find(|(property, _)| *property == self).expect("every property is named");
+        name
+    }
+}
+
+/// A pad hole, as in `(drill 1)` or `(drill oval 1 2 (offset 0 0.5))`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Drill {
+    pub oval: bool,
+    /// Hole size; both dimensions are the diameter for round holes.
+    pub size: Size,
+    pub offset: Point,
+}
+
+impl Drill {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let drill = SexpBuilder::new("drill");
+        let drill = if self.oval {
+            drill.symbol("oval").float(self.size.width).float(self.size.height)
+        } else {
+            drill.float(self.size.width)
+        };
+        drill.child_opt((self.offset != Point::default()).then(|| point_sexp("offset", self.offset)))
+    }
+}
+
+impl FromSexp for Drill {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let oval = list.has_symbol("oval");
+        let first = usize::from(oval);
+        let width: f64 = list.number(first)?;
+        Ok(Drill {
+            oval,
+            size: Size::new(width, list.opt_number(first + 1)?.unwrap_or(width)),
+            offset: list.parse("offset")?.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pad {
+    /// Pad number; empty for mounting holes and other unnumbered pads.
+    pub number: String,
+    pub kind: PadKind,
+    pub shape: PadShape,
+    /// Position relative to the footprint. Unlike the footprint's own
+    /// rotation, the angle is absolute.
+    pub at: Position,
+    pub size: Size,
+    pub drill: Option<Drill>,
+    pub layers: Vec<LayerSelector>,
+    pub roundrect_rratio: Option<f64>,
+    pub net: Option<Net>,
+    pub pinfunction: Option<String>,
+    pub pintype: Option<String>,
+    pub property: Option<PadProperty>,
+    pub locked: bool,
+    /// How the pad connects to zones of its net; `None` leaves it to each
+    /// zone's `connect_pads`.
+    pub zone_connect: Option<PadConnection>,
+    /// Thermal relief overrides of the zone's settings.
+    pub thermal_gap: Option<f64>,
+    pub thermal_bridge_width: Option<f64>,
+    /// Angle of the first thermal spoke in degrees, relative to the pad;
+    /// the other three follow at 90° steps.
+    pub thermal_bridge_angle: Option<f64>,
+    pub uuid: Option<Uuid>,
+}
+
+impl Pad {
+    /// Returns true if the pad is on `layer`.
+    pub fn on_layer(&self, layer: Layer) -> bool {
+        self.layers.iter().any(|selector| selector.matches(layer))
+    }
+
+    /// The spoke angle KiCad uses without a `thermal_bridge_angle`: 45° for
+    /// circular pads, 90° for everything else.
+    pub fn spoke_angle(&self) -> f64 {
+        self.thermal_bridge_angle.unwrap_or(if self.shape == PadShape::Circle { 45.0 } else { 90.0 })
+    }
+
+    /// The pad as KiCad writes it.
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let kind = match self.kind {
+            PadKind::ThruHole => "thru_hole",
+            PadKind::Smd => "smd",
+            PadKind::Connect => "connect",
+            PadKind::NpThruHole => "np_thru_hole",
+        };
+        let shape = match self.shape {
+            PadShape::Circle => "circle",
+            PadShape::Rect => "rect",
+            PadShape::Oval => "oval",
+            PadShape::Trapezoid => "trapezoid",
+            PadShape::RoundRect => "roundrect",
+            PadShape::Custom => "custom",
+        };
+        let layers = self.layers.iter().fold(SexpBuilder::new("layers"), |layers, selector| {
+            layers.string(&selector.to_string())
+        });
+        let string = |tag: &str, value: &Option<String>| value.as_deref().map(|v| SexpBuilder::new(tag).string(v));
+        let float = |tag: &str, value: Option<f64>| value.map(|v| SexpBuilder::new(tag).float(v));
+        let zone_connect = self.zone_connect.map(|connection| match connection {
+            PadConnection::None => 0,
+            PadConnection::ThermalRelief => 1,
+            PadConnection::Solid => 2,
+            PadConnection::ThruHoleOnly => 3,
+        });
+        SexpBuilder::new("pad")
+            .string(&self.number)
+            .symbol(kind)
+            .symbol(shape)
+            .child_opt(self.locked.then(|| SexpBuilder::new("locked").symbol("yes")))
+            .child(at_sexp(&self.at, false))
+            .child(SexpBuilder::new("size").float(self.size.width).float(self.size.height))
+            .child_opt(self.drill.map(|drill| drill.to_sexp()))
+            .child_opt(self.property.map(|property| SexpBuilder::new("property").symbol(property.name())))
+            .child(layers)
+            .child_opt(self.roundrect_rratio.map(|ratio| SexpBuilder::new("roundrect_rratio").float(ratio)))
+            .child_opt(self.net.as_ref().map(|net| SexpBuilder::new("net").int(net.number.into()).string(&net.name)))
+            .child_opt(string("pinfunction", &self.pinfunction))
+            .child_opt(string("pintype", &self.pintype))
+            .child_opt(zone_connect.map(|value| SexpBuilder::new("zone_connect").int(value)))
+            .child_opt(float("thermal_bridge_width", self.thermal_bridge_width))
+            .child_opt(float("thermal_bridge_angle", self.thermal_bridge_angle))
+            .child_opt(float("thermal_gap", self.thermal_gap))
+            .child_opt(self.uuid.map(|uuid| SexpBuilder::new("uuid").string(&uuid.to_string())))
+    }
+}
+
+impl FromSexp for Pad {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let kind = match list.symbol(1)? {
+            "thru_hole" => PadKind::ThruHole,
+            "smd" => PadKind::Smd,
+            "connect" => PadKind::Connect,
+            "np_thru_hole" => PadKind::NpThruHole,
+            other => return Err(list.error(format!("unknown pad type `{}`", other))),
+        };
+        let shape = match list.symbol(2)? {
+            "circle" => PadShape::Circle,
+            "rect" => PadShape::Rect,
+            "oval" => PadShape::Oval,
+            "trapezoid" => PadShape::Trapezoid,
+            "roundrect" => PadShape::RoundRect,
+            "custom" => PadShape::Custom,
+            other => return Err(list.error(format!("unknown pad shape `{}`", other))),
+        };
+        let layers = match list.child("layers") {
+            Some(layers) => layer_selectors(&layers).map_err(|e| e.within(list.tag()))?,
+            None => Vec::new(),
+        };
+        let zone_connect = match list.child_number::<i64>("zone_connect")? {
+            None | Some(-1) => None,
+            Some(0) => Some(PadConnection::None),
+            Some(1) => Some(PadConnection::ThermalRelief),
+            Some(2) => Some(PadConnection::Solid),
+            Some(3) => Some(PadConnection::ThruHoleOnly),
+            Some(other) => return Err(list.error(format!("unknown zone_connect {}", other))),
+        };
+        let property = match list.child("property") {
+            Some(property) => {
+                let name = property.symbol(0).map_err(|e| e.within(list.tag()))?;
+                let known = PadProperty::NAMES.iter().find(|(_, known)| *known == name);
+                // `pad_prop_none` is what KiCad writes for no property.
+                match known {
+                    Some((property, _)) => Some(*property),
+                    None if name == "pad_prop_none" => None,
+                    None => return Err(list.error(format!("unknown pad property `{}`", name))),
+                }
+            },
+            None => None,
+        };
+        Ok(Pad {
+            number: list.string(0)?,
+            kind,
+            shape,
+            at: list.parse_required("at")?,
+            size: list.parse_required("size")?,
+            drill: list.parse("drill")?,
+            layers,
+            roundrect_rratio: list.child_number("roundrect_rratio")?,
+            net: list.parse("net")?,
+            pinfunction: list.child_string("pinfunction")?,
+            pintype: list.child_string("pintype")?,
+            property,
+            locked: list.flag("locked")?,
+            zone_connect,
+            thermal_gap: list.child_number("thermal_gap")?,
+            // KiCad 5 wrote `thermal_width`.
+            thermal_bridge_width: match list.child_number("thermal_bridge_width")? {
+                Some(width) => Some(width),
+                None => list.child_number("thermal_width")?,
+            },
+            thermal_bridge_angle: list.child_number("thermal_bridge_angle")?,
+            uuid: item_uuid(list)?,
+        })
+    }
+}
@@ -0,0 +1,411 @@
+//! Pad numbers reconciled with the pin numbers of a netlist, and pads
+//! numbered for footprints built in code.
+//!
+//! [`check_pad_mapping`] finds the footprints whose pads do not fit their
+//! part: pins of the symbol without a pad, numbered pads the symbol has no
+//! pin for, and pads on another net than their pin, with the pairs of pads
+//! that have each other's nets called out as swapped pins.
+//!
+//! ```no_run
+//! use kicad_common::netlist::Netlist;
+//! use kicad_pcb::{pinmap::check_pad_mapping, Board};
+//!
+//! let board = Board::parse(&std::fs::read_to_string("demo.kicad_pcb")?)?;
+//! let netlist = Netlist::parse(&std::fs::read_to_string("demo.net")?)?;
+//! for mismatch in check_pad_mapping(&board, &netlist) {
+//!     println!("{}", mismatch);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    f64::consts::PI,
+    fmt,
+};
+
+use kicad_common::{netlist::Netlist, reference::natural_cmp, ConvertError, Error};
+
+use crate::{Board, Footprint, PadKind};
+
+/// A difference between the pads of a footprint and the pins of its part.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PadMismatch {
+    /// The part has pins but no footprint on the board.
+    MissingFootprint { reference: String },
+    /// A pin of the part without a pad of its number.
+    MissingPad { reference: String, pin: String },
+    /// A numbered pad without a pin of the part.
+    UnmappedPad { reference: String, pad: String },
+    /// Two pads that each carry the other's net.
+    Swapped { reference: String, pads: [String; 2] },
+    /// A pad on another net than its pin; `board` is empty for an
+    /// unconnected pad.
+    WrongNet { reference: String, pad: String, board: String, netlist: String },
+}
+
+impl fmt::Display for PadMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PadMismatch::MissingFootprint { reference } => write!(f, "{} has no footprint", reference),
+            PadMismatch::MissingPad { reference, pin } => write!(f, "{} has no pad {}", reference, pin),
+            PadMismatch::UnmappedPad { reference, pad } => write!(f, "pad {}.{} has no pin", reference, pad),
+            PadMismatch::Swapped { reference, pads } => {
+                write!(f, "pads {}.{} and {}.{} are swapped", reference, pads[0], reference, pads[1])
+            },
+            PadMismatch::WrongNet { reference, pad, board, netlist } if board.is_empty() => {
+                write!(f, "pad {}.{} is not on {}", reference, pad, netlist)
+            },
+            PadMismatch::WrongNet { reference, pad, board, netlist } => {
+                write!(f, "pad {}.{} is on {}, not {}", reference, pad, board, netlist)
+            },
+        }
+    }
+}
+
+/// Compares the pads of every footprint on `board` with the pins the
+/// netlist gives its part. Footprints of parts the netlist does not have
+/// are left out, as [`Board::update_from_netlist`] reports those. The
+/// mismatches come by reference, then by pad number.
+pub fn check_pad_mapping(board: &Board, netlist: &Netlist) -> Vec<PadMismatch> {
+    let mut pins: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+    for component in &netlist.components {
+        pins.entry(component.reference.as_str()).or_default();
+    }
+    for net in &netlist.nets {
+        for node in &net.nodes {
+            pins.entry(node.reference.as_str()).or_default().insert(node.pin.as_str(), net.name.as_str());
+        }
+    }
+    let mut references: Vec<_> = pins.keys().copied().collect();
+    references.sort_by(|a, b| natural_cmp(a, b));
+    let mut mismatches = Vec::new();
+    for reference in references {
+        let pins = &pins[reference];
+        let Some(footprint) = board.footprint(reference) else {
+            mismatches.push(PadMismatch::MissingFootprint { reference: reference.to_string() });
+            continue;
+        };
+        let mut pads: BTreeMap<&str, &str> = BTreeMap::new();
+        for pad in footprint.pads.iter().filter(|pad| !pad.number.is_empty()) {
+            pads.insert(pad.number.as_str(), pad.net.as_ref().map_or("", |net| net.name.as_str()));
+        }
+        let mut found = Vec::new();
+        for (&pin, &net) in pins {
+            match pads.get(pin) {
+                None => found.push(PadMismatch::MissingPad { reference: reference.to_string(), pin: pin.to_string() }),
+                Some(&on) if on != net => found.push(PadMismatch::WrongNet {
+                    reference: reference.to_string(),
+                    pad: pin.to_string(),
+                    board: on.to_string(),
+                    netlist: net.to_string(),
+                }),
+                Some(_) => {},
+            }
+        }
+        for &pad in pads.keys().filter(|pad| !pins.contains_key(*pad)) {
+            found.push(PadMismatch::UnmappedPad { reference: reference.to_string(), pad: pad.to_string() });
+        }
+        let mut found = pair_swaps(found);
+        found.sort_by(|a, b| natural_cmp(pad_of(a), pad_of(b)));
+        mismatches.extend(found);
+    }
+    mismatches
+}
+
+/// Turns pairs of pads on each other's nets into [`PadMismatch::Swapped`].
+fn pair_swaps(mut found: Vec<PadMismatch>) -> Vec<PadMismatch> {
+    let mut i = 0;
+    while i < found.len() {
+        let PadMismatch::WrongNet { reference, pad, board, netlist } = &found[i] else {
+            i += 1;
+            continue;
+        };
+        let other = found.iter().position(|other| {
+            matches!(other, PadMismatch::WrongNet { board: b, netlist: n, .. } if b == netlist && n == board)
+        });
+        if let Some(j) = other {
+            let PadMismatch::WrongNet { pad: other, .. } = &found[j] else { unreachable!() };
+            let swapped = PadMismatch::Swapped { reference: reference.clone(), pads: [pad.clone(), other.clone()] };
+            found[i] = swapped;
+            found.remove(j);
+            if j < i {
+                continue;
+            }
+        }
+        i += 1;
+    }
+    found
+}
+
+/// The pad a mismatch of a footprint sorts by.
+fn pad_of(mismatch: &PadMismatch) -> &str {
+    match mismatch {
+        PadMismatch::MissingFootprint { .. } => "",
+        PadMismatch::MissingPad { pin, .. } => pin,
+        PadMismatch::UnmappedPad { pad, .. } | PadMismatch::WrongNet { pad, .. } => pad,
+        PadMismatch::Swapped { pads, .. } => &pads[0],
+    }
+}
+
+/// The order in which [`Footprint::number_pads`] numbers pads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PadOrder {
+    /// Row by row from the top, each row from the left, as on a dual row
+    /// header.
+    #[default]
+    Rows,
+    /// Column by column from the left, each column from the top.
+    Columns,
+    /// Counterclockwise around the center of the pads, starting at the top
+    /// of the left side, as on an SOIC or a QFP.
+    CounterClockwise,
+    /// Grid names: rows lettered from the top, skipping I, O, Q, S, X and
+    /// Z, and columns numbered from the left, as on a BGA.
+    Grid,
+}
+
+impl Footprint {
+    /// Gives the pads new numbers, from `old` to `new` for each pair. The
+    /// pairs apply at once, so two pads can swap numbers; every pad with an
+    /// old number is renumbered and the count returned.
+    pub fn renumber_pads(&mut self, map: &[(&str, &str)]) -> Result<usize, Error> {
+        if let Some((old, _)) = map.iter().find(|(old, _)| self.pad(old).is_none()) {
+            return Err(ConvertError::new(format!("{} has no pad {}", self.lib_id, old)).into());
+        }
+        let mut count = 0;
+        for pad in &mut self.pads {
+            if let Some((_, new)) = map.iter().find(|(old, _)| *old == pad.number) {
+                pad.number = new.to_string();
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Numbers the pads from 1 in `order`, or by grid name. Pads that share
+    /// a number keep sharing one; unnumbered non-plated holes stay so.
+    pub fn number_pads(&mut self, order: PadOrder) {
+        let mut groups: Vec<(Option<String>, Vec<usize>)> = Vec::new();
+        for (i, pad) in self.pads.iter().enumerate() {
+            if pad.kind == PadKind::NpThruHole && pad.number.is_empty() {
+                continue;
+            }
+            let named = (!pad.number.is_empty()).then(|| pad.number.clone());
+            match groups.iter_mut().find(|(number, _)| named.is_some() && *number == named) {
+                Some((_, pads)) => pads.push(i),
+                None => groups.push((named, vec![i])),
+            }
+        }
+        let positions: Vec<(f64, f64)> = groups
+            .iter()
+            .map(|(_, pads)| {
+                let point = self.pads[pads[0]].at.point();
+                (round(point.x), round(point.y))
+            })
+            .collect();
+        let mut ordered: Vec<usize> = (0..groups.len()).collect();
+        let numbers: Vec<String> = match order {
+            PadOrder::Rows => {
+                ordered.sort_by(|&a, &b| {
+                    let (a, b) = (positions[a], positions[b]);
+                    a.1.total_cmp(&b.1).then(a.0.total_cmp(&b.0))
+                });
+                (1..=ordered.len()).map(|n| n.to_string()).collect()
+            },
+            PadOrder::Columns => {
+                ordered.sort_by(|&a, &b| {
+                    let (a, b) = (positions[a], positions[b]);
+                    a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+                });
+                (1..=ordered.len()).map(|n| n.to_string()).collect()
+            },
+            PadOrder::CounterClockwise => {
+                let (xs, ys) = (positions.iter().map(|p| p.0), positions.iter().map(|p| p.1));
+                let (x0, x1) = (xs.clone().fold(f64::INFINITY, f64::min), xs.fold(f64::NEG_INFINITY, f64::max));
+                let (y0, y1) = (ys.clone().fold(f64::INFINITY, f64::min), ys.fold(f64::NEG_INFINITY, f64::max));
+                let (cx, cy) = ((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+                let (w, h) = (((x1 - x0) / 2.0).max(1e-9), ((y1 - y0) / 2.0).max(1e-9));
+                // Angles are taken with the pads scaled to a square, so the
+                // corners of any package are on the diagonals. With y down,
+                // counterclockwise is a falling angle, and the top left
+                // corner is at -3/4 pi; the pad on it counts as the left
+                // side's.
+                let turn = |&(x, y): &(f64, f64)| {
+                    (1.25 * PI + 1e-6 - ((y - cy) / h).atan2((x - cx) / w)).rem_euclid(2.0 * PI)
+                };
+                ordered.sort_by(|&a, &b| turn(&positions[a]).total_cmp(&turn(&positions[b])));
+                (1..=ordered.len()).map(|n| n.to_string()).collect()
+            },
+            PadOrder::Grid => {
+                let rows: BTreeSet<i64> = positions.iter().map(|p| grid_key(p.1)).collect();
+                let columns: BTreeSet<i64> = positions.iter().map(|p| grid_key(p.0)).collect();
+                let rows: Vec<i64> = rows.into_iter().collect();
+                let columns: Vec<i64> = columns.into_iter().collect();
+                ordered
+                    .iter()
+                    .map(|&i| {
+                        let row = rows.binary_search(&grid_key(positions[i].1)).unwrap_or_default();
+                        let column = columns.binary_search(&grid_key(positions[i].0)).unwrap_or_default();
+                        format!("{}{}", grid_row(row), column + 1)
+                    })
+                    .collect()
+            },
+        };
+        for (&group, number) in ordered.iter().zip(numbers) {
+            for &pad in &groups[group].1 {
+                self.pads[pad].number = number.clone();
+            }
+        }
+    }
+}
+
+/// Name of the zero-based grid row `index`: A to Y without the letters
+/// that read like digits or each other, then AA, AB and on.
+pub fn grid_row(index: usize) -> String {
+    const LETTERS: &[u8] = b"ABCDEFGHJKLMNPRTUVWY";
+    let mut name = Vec::new();
+    let mut n = index;
+    loop {
+        name.insert(0, LETTERS[n % LETTERS.len()]);
+        if n < LETTERS.len() {
+            break;
+        }
+        n = n / LETTERS.len() - 1;
+    }
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Rounds away the float error of pad positions built from pitches.
+fn round(value: f64) -> f64 {
+    (value * 1e4).round() / 1e4
+}
+
+fn grid_key(value: f64) -> i64 {
+    (value * 1e4).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::{Position, Size};
+
+    use super::*;
+    use crate::{Net, Pad, PadShape};
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+    const NETLIST: &str = include_str!("../../reference-files/demo/demo.net");
+
+    fn pad(x: f64, y: f64) -> Pad {
+        Pad {
+            number: String::new(),
+            kind: PadKind::Smd,
+            shape: PadShape::Rect,
+            at: Position::new(x, y, 0.0),
+            size: Size::new(1.0, 1.0),
+            drill: None,
+            layers: Vec::new(),
+            roundrect_rratio: None,
+            net: None,
+            pinfunction: None,
+            pintype: None,
+            property: None,
+            locked: false,
+            zone_connect: None,
+            thermal_gap: None,
+            thermal_bridge_width: None,
+            thermal_bridge_angle: None,
+            uuid: None,
+        }
+    }
+
+    fn numbers(footprint: &Footprint) -> Vec<&str> {
+        footprint.pads.iter().map(|pad| pad.number.as_str()).collect()
+    }
+
+    #[test]
+    fn demo_matches() {
+        let board = Board::parse(DEMO).unwrap();
+        let netlist = Netlist::parse(NETLIST).unwrap();
+        assert_eq!(check_pad_mapping(&board, &netlist), []);
+    }
+
+    #[test]
+    fn mismatches() {
+        let mut board = Board::parse(DEMO).unwrap();
+        let mut netlist = Netlist::parse(NETLIST).unwrap();
+        let footprint = board.footprints.iter_mut().find(|footprint| footprint.reference() == Some("J1")).unwrap();
+        let first = footprint.pads[0].net.take();
+        footprint.pads[0].net = footprint.pads[1].net.take();
+        footprint.pads[1].net = first;
+        footprint.pads[2].net = Some(Net { number: 1, name: "VIN".into() });
+        let r1 = board.footprints.iter_mut().find(|footprint| footprint.reference() == Some("R1")).unwrap();
+        r1.pads[1].number = "3".into();
+        netlist.components.push(netlist.components[0].clone());
+        netlist.components[3].reference = "J2".into();
+
+        let found = check_pad_mapping(&board, &netlist);
+        let text: Vec<String> = found.iter().map(|mismatch| mismatch.to_string()).collect();
+        assert_eq!(
+            text,
+            [
+                "pads J1.1 and J1.2 are swapped",
+                "pad J1.3 is on VIN, not GND",
+                "J2 has no footprint",
+                "R1 has no pad 2",
+                "pad R1.3 has no pin",
+            ]
+        );
+        assert_eq!(found[0], PadMismatch::Swapped { reference: "J1".into(), pads: ["1".into(), "2".into()] });
+    }
+
+    #[test]
+    fn renumber() {
+        let mut footprint = Footprint::parse("(footprint \"X\")").unwrap();
+        footprint.pads = vec![pad(0.0, 0.0), pad(1.0, 0.0)];
+        footprint.number_pads(PadOrder::Rows);
+        assert_eq!(footprint.renumber_pads(&[("1", "2"), ("2", "1")]).unwrap(), 2);
+        assert_eq!(numbers(&footprint), ["2", "1"]);
+        assert!(footprint.renumber_pads(&[("3", "4")]).is_err());
+    }
+
+    #[test]
+    fn orders() {
+        let mut footprint = Footprint::parse("(footprint \"X\")").unwrap();
+        // An SOIC-4: two columns of two.
+        footprint.pads = vec![pad(2.0, -1.0), pad(-2.0, 1.0), pad(2.0, 1.0), pad(-2.0, -1.0)];
+        footprint.number_pads(PadOrder::Columns);
+        assert_eq!(numbers(&footprint), ["3", "2", "4", "1"]);
+        footprint.number_pads(PadOrder::Rows);
+        assert_eq!(numbers(&footprint), ["2", "3", "4", "1"]);
+        footprint.number_pads(PadOrder::CounterClockwise);
+        assert_eq!(numbers(&footprint), ["4", "2", "3", "1"]);
+        footprint.number_pads(PadOrder::Grid);
+        assert_eq!(numbers(&footprint), ["A2", "B1", "B2", "A1"]);
+
+        // A QFN-8, listed clockwise.
+        footprint.pads = vec![
+            pad(-2.0, -0.5),
+            pad(-2.0, 0.5),
+            pad(-0.5, 2.0),
+            pad(0.5, 2.0),
+            pad(2.0, 0.5),
+            pad(2.0, -0.5),
+            pad(0.5, -2.0),
+            pad(-0.5, -2.0),
+        ];
+        footprint.pads.reverse();
+        footprint.number_pads(PadOrder::CounterClockwise);
+        assert_eq!(numbers(&footprint), ["8", "7", "6", "5", "4", "3", "2", "1"]);
+
+        // A narrow, tall SOIC-16 still starts at the top of its left side.
+        footprint.pads = (0..16).map(|i| pad(if i < 8 { -2.7 } else { 2.7 }, (i % 8) as f64 * 1.27)).collect();
+        footprint.number_pads(PadOrder::CounterClockwise);
+        assert_eq!(numbers(&footprint)[..9], ["1", "2", "3", "4", "5", "6", "7", "8", "16"]);
+    }
+
+    #[test]
+    fn grid_rows() {
+        let rows: Vec<String> = [0, 7, 8, 19, 20, 21].into_iter().map(grid_row).collect();
+        assert_eq!(rows, ["A", "H", "J", "Y", "AA", "AB"]);
+    }
+}
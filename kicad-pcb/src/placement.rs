@@ -0,0 +1,249 @@
+//! Component placement for assembly, as in KiCad's footprint position
+//! (`.pos`) files: one row per footprint with its reference, value,
+//! package, position, rotation and side.
+//!
+//! Positions are relative to the chosen origin with Y pointing up, as KiCad
+//! writes them, so a board drawn below the origin has negative Y values.
+//! Footprints marked `exclude_from_pos_files` are always left out.
+
+use std::fmt::Write;
+
+use kicad_common::{reference::natural_cmp, Point};
+
+use crate::{Board, Footprint, MountKind, PadKind, Side};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    #[default]
+    Millimeters,
+    Inches,
+}
+
+impl Units {
+    pub(crate) fn convert(self, mm: f64) -> f64 {
+        match self {
+            Units::Millimeters => mm,
+            Units::Inches => mm / 25.4,
+        }
+    }
+}
+
+/// The point positions are measured from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    /// The board's own coordinate origin.
+    #[default]
+    Absolute,
+    /// The drill/place file origin, or the board origin if none is set.
+    AuxAxis,
+}
+
+/// Which footprints to list by how they are mounted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MountFilter {
+    #[default]
+    All,
+    /// Only footprints with the SMD attribute.
+    SmdOnly,
+    /// Leaves out footprints with any through-hole pad.
+    NoThroughHole,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlacementOptions {
+    pub units: Units,
+    pub origin: Origin,
+    pub mount: MountFilter,
+    /// Only list one side; `None` lists both.
+    pub side: Option<Side>,
+    pub exclude_dnp: bool,
+}
+
+/// One footprint's row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    pub reference: String,
+    pub value: String,
+    /// Footprint name without its library, e.g. `R_0603_1608Metric`.
+    pub package: String,
+    /// Footprint origin, in [`PositionFile::units`].
+    pub position: Point,
+    /// Rotation in degrees, counter-clockwise.
+    pub rotation: f64,
+    pub side: Side,
+}
+
+/// The placements of a board.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PositionFile {
+    pub units: Units,
+    /// The side the file was restricted to, if any.
+    pub side: Option<Side>,
+    /// Sorted by reference in natural order, `R2` before `R10`.
+    pub placements: Vec<Placement>,
+}
+
+/// Collects the placements of `board`'s footprints.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(placements)))]
+pub fn position_file(board: &Board, options: &PlacementOptions) -> PositionFile {
+    let origin = match options.origin {
+        Origin::Absolute => Point::default(),
+        Origin::AuxAxis => board.setup.aux_axis_origin.unwrap_or_default(),
+    };
+    let units = options.units;
+    let mut placements: Vec<Placement> = board
+        .footprints
+        .iter()
+        .filter(|footprint| included(footprint, options))
+        .map(|footprint| {
+            let package = footprint.lib_id.split_once(':').map_or(footprint.lib_id.as_str(), |(_, name)| name);
+            Placement {
+                reference: footprint.reference().unwrap_or_default().to_string(),
+                value: footprint.value().unwrap_or_default().to_string(),
+                package: package.to_string(),
+                position: Point::new(
+                    units.convert(footprint.at.x - origin.x),
+                    units.convert(origin.y - footprint.at.y),
+                ),
+                rotation: footprint.at.angle,
+                side: footprint.side(),
+            }
+        })
+        .collect();
+    placements.sort_by(|a, b| natural_cmp(&a.reference, &b.reference));
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("placements", placements.len());
+    PositionFile { units, side: options.side, placements }
+}
+
+fn included(footprint: &Footprint, options: &PlacementOptions) -> bool {
+    let attributes = &footprint.attributes;
+    let mount = match options.mount {
+        MountFilter::All => true,
+        MountFilter::SmdOnly => attributes.mount == MountKind::Smd,
+        MountFilter::NoThroughHole => !footprint.pads.iter().any(|pad| pad.kind == PadKind::ThruHole),
+    };
+    mount
+        && !attributes.exclude_from_pos_files
+        && !(options.exclude_dnp && attributes.dnp)
+        && options.side.is_none_or(|side| footprint.side() == side)
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Top => "top",
+        Side::Bottom => "bottom",
+    }
+}
+
+impl PositionFile {
+    /// KiCad's ASCII format, with columns padded to line up. The lines
+    /// giving the creation date and KiCad version are left out, so the
+    /// output only changes with the board.
+    pub fn to_ascii(&self) -> String {
+        // Spaces would split a column for tools that read the file back.
+        let text = |s: &str| s.replace(' ', "_");
+        let width = |min: usize, f: fn(&Placement) -> &str| {
+            self.placements.iter().map(|placement| text(f(placement)).chars().count()).fold(min, usize::max)
+        };
+        let (rw, vw, pw) = (width(8, |p| &p.reference), width(8, |p| &p.value), width(16, |p| &p.package));
+
+        let units = match self.units {
+            Units::Millimeters => "mm",
+            Units::Inches => "inches",
+        };
+        let side = self.side.map_or("All", side_name);
+        let mut out = format!("## Unit = {}, Angle = deg.\n## Side : {}\n", units, side);
+        let columns = "       PosX       PosY        Rot  Side";
+        let _ = writeln!(out, "{:<rw$}  {:<vw$}  {:<pw$}{}", "# Ref", "Val", "Package", columns);
+        for p in &self.placements {
+            let _ = writeln!(
+                out,
+                "{:<rw$}  {:<vw$}  {:<pw$}  {:9.4}  {:9.4}  {:9.4}  {}",
+                text(&p.reference),
+                text(&p.value),
+                text(&p.package),
+                p.position.x,
+                p.position.y,
+                p.rotation,
+                side_name(p.side),
+            );
+        }
+        out.push_str("## End\n");
+        out
+    }
+
+    /// KiCad's CSV format.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("Ref,Val,Package,PosX,PosY,Rot,Side\n");
+        let quoted = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+        for p in &self.placements {
+            let _ = writeln!(
+                out,
+                "{},{},{},{:.6},{:.6},{:.6},{}",
+                quoted(&p.reference),
+                quoted(&p.value),
+                quoted(&p.package),
+                p.position.x,
+                p.position.y,
+                p.rotation,
+                side_name(p.side),
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn demo_board() {
+        let board = Board::parse(DEMO).unwrap();
+        let file = position_file(&board, &PlacementOptions::default());
+
+        let references: Vec<_> = file.placements.iter().map(|p| p.reference.as_str()).collect();
+        assert_eq!(references, ["C1", "J1", "R1", "R2"]);
+        let c1 = &file.placements[0];
+        assert_eq!((c1.value.as_str(), c1.package.as_str()), ("100n", "C_0603_1608Metric"));
+        assert_eq!((c1.position, c1.rotation, c1.side), (Point::new(120.0, -107.5), 180.0, Side::Bottom));
+
+        let options = PlacementOptions { origin: Origin::AuxAxis, units: Units::Inches, ..Default::default() };
+        let aux = position_file(&board, &options);
+        assert!((aux.placements[1].position.x - 4.0 / 25.4).abs() < 1e-12);
+        assert!((aux.placements[1].position.y - 15.0 / 25.4).abs() < 1e-12);
+
+        let smd = PlacementOptions { mount: MountFilter::SmdOnly, side: Some(Side::Top), ..Default::default() };
+        let references: Vec<_> = position_file(&board, &smd).placements.into_iter().map(|p| p.reference).collect();
+        assert_eq!(references, ["R1", "R2"]);
+        let no_tht = PlacementOptions { mount: MountFilter::NoThroughHole, ..Default::default() };
+        assert_eq!(position_file(&board, &no_tht).placements.len(), 3);
+    }
+
+    #[test]
+    fn formats() {
+        let board = Board::parse(DEMO).unwrap();
+        let file = position_file(&board, &PlacementOptions::default());
+
+        let ascii = file.to_ascii();
+        let lines: Vec<_> = ascii.lines().collect();
+        assert_eq!(lines[0], "## Unit = mm, Angle = deg.");
+        assert_eq!(lines[1], "## Side : All");
+        assert_eq!(
+            lines[2],
+            "# Ref     Val         Package                               PosX       PosY        Rot  Side"
+        );
+        assert_eq!(
+            lines[4],
+            "J1        Conn_01x03  PinHeader_1x03_P2.54mm_Vertical   104.0000  -105.0000     0.0000  top"
+        );
+        assert_eq!(lines.last(), Some(&"## End"));
+
+        let csv = file.to_csv();
+        assert!(csv.starts_with("Ref,Val,Package,PosX,PosY,Rot,Side\n"));
+        assert!(csv.contains("\n\"C1\",\"100n\",\"C_0603_1608Metric\",120.000000,-107.500000,180.000000,bottom\n"));
+    }
+}
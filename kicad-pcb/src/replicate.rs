@@ -0,0 +1,282 @@
+//! Multichannel layouts: the placement of one sheet instance, the template
+//! channel, repeated for other instances of the sheet, as KiCad's
+//! Replicate Layout plugin does, optionally with its tracks and vias.
+//!
+//! A channel is the footprints whose symbols are in one sheet instance.
+//! Footprints of two channels are matched by the symbol UUID their `path`
+//! ends with, which is the same in every instance of a sheet. Each target
+//! footprint is moved to where its template is plus the channel's offset.
+//! Footprints turned or sided differently from their template are left
+//! where they are and reported, as footprints are not rotated or flipped
+//! here.
+//!
+//! Routing is copied from the template's area, the bounds of its
+//! footprints grown by [`ReplicateOptions::margin`]: the tracks and vias in
+//! it whose net the template's pads connect to one net of the target's
+//! pads. Nets shared by the channels, such as ground, stay the same. The
+//! routing a target channel has already is kept.
+//!
+//! ```no_run
+//! use kicad_common::{Point, UuidGenerator};
+//! use kicad_pcb::{
+//!     layout::write_tracks,
+//!     replicate::{replicate_layout, ReplicateOptions},
+//!     selection::write_moves,
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("mixer.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let options = ReplicateOptions { offset: Point::new(20.0, 0.0), copy_routing: true, margin: 1.0 };
+//! let mut generator = UuidGenerator::deterministic("mixer");
+//! let targets = ["/Channel 2/", "/Channel 3/"];
+//! let replication = replicate_layout(&mut board, "/Channel 1/", &targets, &options, &mut generator)?;
+//! for message in &replication.skipped {
+//!     eprintln!("{}", message);
+//! }
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_moves(&replication.moves, &mut doc)?;
+//! write_tracks(&board, &mut doc)?;
+//! std::fs::write("mixer.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeMap;
+
+use kicad_common::{ConvertError, Error, Point, Position, Uuid, UuidGenerator};
+
+use crate::{bounds::Bounds, index::Item, selection::bounds, Board, Footprint, Track, Via};
+
+/// How [`replicate_layout`] places the target channels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReplicateOptions {
+    /// From the template to the first target, and from each target to the
+    /// next.
+    pub offset: Point,
+    /// Whether to copy the template's tracks and vias.
+    pub copy_routing: bool,
+    /// How far around its footprints the template's routing reaches.
+    pub margin: f64,
+}
+
+/// What [`replicate_layout`] changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replication {
+    /// The footprints moved, by how much, for
+    /// [`write_moves`](crate::selection::write_moves).
+    pub moves: Vec<(Uuid, Point)>,
+    /// Tracks and vias added, which
+    /// [`write_tracks`](crate::layout::write_tracks) writes.
+    pub tracks: usize,
+    pub vias: usize,
+    /// Footprints left where they were, and why.
+    pub skipped: Vec<String>,
+}
+
+/// The sheet path of UUIDs a footprint's symbol is in, its `path` without
+/// the symbol's UUID, with the symbol's UUID.
+fn split_path(footprint: &Footprint) -> Option<(&str, &str)> {
+    footprint.path.as_deref()?.rsplit_once('/')
+}
+
+/// The channels of the board: the footprints of each sheet instance, by
+/// index into [`Board::footprints`], keyed by the sheet path of UUIDs.
+/// Footprints without a symbol are in no channel.
+pub fn channels(board: &Board) -> BTreeMap<&str, Vec<usize>> {
+    let mut channels: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, footprint) in board.footprints.iter().enumerate() {
+        if let Some((sheet, _)) = split_path(footprint) {
+            channels.entry(sheet).or_default().push(i);
+        }
+    }
+    channels
+}
+
+/// The footprints of the channel `name`, a sheet path of UUIDs or of sheet
+/// names as in `/Channel 1/`.
+fn channel<'b>(board: &'b Board, name: &str) -> Result<Vec<&'b Footprint>, Error> {
+    let footprints: Vec<&Footprint> = board
+        .footprints
+        .iter()
+        .filter(|footprint| {
+            let sheet = split_path(footprint).map(|(sheet, _)| sheet);
+            sheet == Some(name) || footprint.sheetname.as_deref() == Some(name)
+        })
+        .collect();
+    if footprints.is_empty() {
+        return Err(ConvertError::new(format!("no footprints in the sheet {}", name)).into());
+    }
+    Ok(footprints)
+}
+
+/// Places the footprints of each channel in `targets` like those of the
+/// channel `template`, each target one more [`ReplicateOptions::offset`]
+/// away, and copies the template's routing if the options say so. New
+/// tracks and vias get UUIDs from `generator`.
+///
+/// Fails without changing the board if a channel has no footprints.
+pub fn replicate_layout(
+    board: &mut Board,
+    template: &str,
+    targets: &[&str],
+    options: &ReplicateOptions,
+    generator: &mut UuidGenerator,
+) -> Result<Replication, Error> {
+    let originals = channel(board, template)?;
+    let by_symbol: BTreeMap<&str, &Footprint> =
+        originals.iter().filter_map(|footprint| Some((split_path(footprint)?.1, *footprint))).collect();
+    let corners = originals.iter().filter_map(|footprint| bounds(Item::Footprint(footprint)));
+    let area = Bounds::of(corners.flat_map(|bounds| [bounds.min, bounds.max])).map(|bounds| {
+        let margin = options.margin;
+        Bounds {
+            min: Point::new(bounds.min.x - margin, bounds.min.y - margin),
+            max: Point::new(bounds.max.x + margin, bounds.max.y + margin),
+        }
+    });
+
+    let mut replication = Replication::default();
+    let mut placements = Vec::new();
+    let mut routing: (Vec<Track>, Vec<Via>) = (Vec::new(), Vec::new());
+    generator.reserve(board.uuids());
+    for (k, target) in targets.iter().enumerate() {
+        let steps = (k + 1) as f64;
+        let shift = Point::new(options.offset.x * steps, options.offset.y * steps);
+        // Template nets by the one net of the target they connect to, or
+        // `None` for nets that connect to several.
+        let mut nets: BTreeMap<u32, Option<u32>> = BTreeMap::new();
+        for footprint in channel(board, target)? {
+            let reference = footprint.reference().unwrap_or("?");
+            let Some(original) = split_path(footprint).and_then(|(_, symbol)| by_symbol.get(symbol)) else {
+                replication.skipped.push(format!("{} in {} has no footprint in {}", reference, target, template));
+                continue;
+            };
+            for pad in &original.pads {
+                let (Some(from), Some(to)) = (&pad.net, footprint.pad(&pad.number).and_then(|pad| pad.net.as_ref()))
+                else {
+                    continue;
+                };
+                let entry = nets.entry(from.number).or_insert(Some(to.number));
+                if *entry != Some(to.number) {
+                    *entry = None;
+                }
+            }
+            let why = match (footprint.uuid, footprint.side() == original.side()) {
+                (None, _) => Some("has no UUID"),
+                (_, false) => Some("is on the other side from its template"),
+                _ if turned(footprint.at.angle - original.at.angle) => Some("is turned differently from its template"),
+                _ => None,
+            };
+            if let Some(why) = why {
+                replication.skipped.push(format!("{} in {} {}", reference, target, why));
+                continue;
+            }
+            let to = original.at.point() + shift;
+            if let Some(uuid) = footprint.uuid {
+                placements.push((uuid, Position::new(to.x, to.y, footprint.at.angle)));
+                replication.moves.push((uuid, Point::new(to.x - footprint.at.x, to.y - footprint.at.y)));
+            }
+        }
+        let (Some(area), true) = (area.as_ref(), options.copy_routing) else {
+            continue;
+        };
+        let net = |net: u32| nets.get(&net).copied().flatten();
+        for track in &board.tracks {
+            let inside = [track.start, track.end].into_iter().chain(track.mid).all(|p| area.contains(p));
+            let Some(net) = net(track.net).filter(|_| inside) else {
+                continue;
+            };
+            let (start, end) = (track.start + shift, track.end + shift);
+            let uuid = generator.generate(format!("segment {} {:?} {:?}", track.layer, start, end));
+            let mid = track.mid.map(|mid| mid + shift);
+            routing.0.push(Track { start, mid, end, net, uuid, ..track.clone() });
+        }
+        for via in &board.vias {
+            let Some(net) = net(via.net).filter(|_| area.contains(via.at)) else {
+                continue;
+            };
+            let at = via.at + shift;
+            let uuid = generator.generate(format!("via {:?}", at));
+            routing.1.push(Via { at, net, uuid, ..via.clone() });
+        }
+    }
+
+    for (uuid, at) in placements {
+        if let Some(footprint) = board.footprints.iter_mut().find(|footprint| footprint.uuid == Some(uuid)) {
+            footprint.at = at;
+        }
+    }
+    (replication.tracks, replication.vias) = (routing.0.len(), routing.1.len());
+    board.tracks.extend(routing.0);
+    board.vias.extend(routing.1);
+    Ok(replication)
+}
+
+/// Whether an angle difference is more than float error.
+fn turned(angle: f64) -> bool {
+    let angle = angle.rem_euclid(360.0);
+    angle > 1e-9 && angle < 360.0 - 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    /// The demo with a second instance of the power sheet, whose C2 sits
+    /// somewhere else and connects VIN2 instead of VIN.
+    fn two_channels() -> Board {
+        let mut board = Board::parse(DEMO).unwrap();
+        let vin2 = board.add_net("VIN2").clone();
+        let mut c2 = board.footprints.iter().find(|footprint| footprint.reference() == Some("C1")).unwrap().clone();
+        let symbol = c2.path.as_deref().unwrap().rsplit_once('/').unwrap().1.to_string();
+        c2.path = Some(format!("/00000000-0000-0000-0000-000000000002/{}", symbol));
+        c2.sheetname = Some("/power 2/".to_string());
+        c2.uuid = Some(UuidGenerator::deterministic("test").generate("C2"));
+        c2.at = Position::new(140.0, 140.0, c2.at.angle);
+        c2.properties.iter_mut().find(|field| field.key == "Reference").unwrap().value = "C2".to_string();
+        c2.pads[1].net = Some(vin2);
+        board.footprints.push(c2);
+        board
+    }
+
+    #[test]
+    fn replicate() {
+        let mut board = two_channels();
+        assert_eq!(channels(&board).len(), 3);
+        let (tracks, vias) = (board.tracks.len(), board.vias.len());
+        let c1 = board.footprint("C1").unwrap().at;
+        let options = ReplicateOptions { offset: Point::new(0.0, 30.0), copy_routing: true, margin: 6.0 };
+        let mut generator = UuidGenerator::deterministic("test");
+        let replication = replicate_layout(&mut board, "/power/", &["/power 2/"], &options, &mut generator).unwrap();
+        assert!(replication.skipped.is_empty(), "{:?}", replication.skipped);
+
+        let c2 = board.footprint("C2").unwrap();
+        assert_eq!(c2.at, Position::new(c1.x, c1.y + 30.0, c1.angle));
+        assert_eq!(replication.moves, [(c2.uuid.unwrap(), Point::new(c1.x - 140.0, c1.y - 110.0))]);
+        // The VIN tracks near C1 and the via between them, on VIN2.
+        assert_eq!((replication.tracks, replication.vias), (2, 1));
+        assert_eq!(board.tracks.len(), tracks + 2);
+        let vin2 = board.find_net("VIN2").unwrap().number;
+        assert!(board.tracks[tracks..].iter().all(|track| track.net == vin2));
+        assert!(board.tracks[tracks..].iter().any(|track| track.end == Point::new(119.225, 137.5)));
+        assert_eq!(board.vias.len(), vias + 1);
+        assert_eq!((board.vias[vias].net, board.vias[vias].at), (vin2, Point::new(115.0, 132.0)));
+    }
+
+    #[test]
+    fn skipped_and_errors() {
+        let mut board = two_channels();
+        let c2 = board.footprints.last_mut().unwrap();
+        c2.at.angle += 90.0;
+        let options = ReplicateOptions { offset: Point::new(0.0, 30.0), ..Default::default() };
+        let mut generator = UuidGenerator::deterministic("test");
+        let replication = replicate_layout(&mut board, "/power/", &["/power 2/"], &options, &mut generator).unwrap();
+        assert_eq!(replication.skipped, ["C2 in /power 2/ is turned differently from its template"]);
+        assert!(replication.moves.is_empty() && replication.tracks == 0);
+        assert_eq!(board.footprint("C2").unwrap().at.x, 140.0);
+
+        let error = replicate_layout(&mut board, "/power/", &["/nothing/"], &options, &mut generator).unwrap_err();
+        assert_eq!(error.to_string(), "no footprints in the sheet /nothing/");
+    }
+}
@@ -0,0 +1,1018 @@
+//! A simple autorouter for generated boards such as keyboards and LED
+//! panels, where hand routing is overkill.
+//!
+//! The connections missing from the ratsnest are routed one at a time,
+//! shortest first, by an A* search over a grid on the allowed copper
+//! layers, changing layers through vias where that is cheaper. Routes keep
+//! the clearance to copper of other nets, to keepout rule areas and to the
+//! board edge; the grid adds up to half a cell diagonal on top. There is no
+//! rip-up and retry, so a connection the search cannot find a way for is
+//! reported and left unrouted.
+//!
+//! Zone fills connect the items they touch but are not obstacles: refill
+//! the zones after routing, e.g. with [`crate::fill::fill_zones`]. Copper
+//! drawings and text are not obstacles either.
+//!
+//! ```no_run
+//! use kicad_common::UuidGenerator;
+//! use kicad_pcb::{
+//!     route::{route, write_routes, RouteOptions},
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("panel.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let report = route(&mut board, &RouteOptions::default(), &mut UuidGenerator::deterministic("panel"))?;
+//! for connection in &report.unrouted {
+//!     eprintln!("unrouted: net {} at {:?}", connection.net, connection.from.at);
+//! }
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_routes(&report, &mut doc)?;
+//! std::fs::write("panel.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    f64::consts::FRAC_1_SQRT_2,
+};
+
+use kicad_common::{ConvertError, Error, Point, UuidGenerator};
+use kicad_sexp::cst::Document;
+
+use crate::{
+    bounds::{distance, Bounds},
+    fill::{arc_points, board_area, contains, Contour},
+    layout::write_tracks_and_vias,
+    Board, Footprint, Layer, Pad, PadShape, Track, Via, ViaKind, ViaProtection,
+};
+
+/// Largest distance between an arc and the segments standing in for it.
+const MAX_ERROR: f64 = 0.005;
+
+/// Largest gap between two items that still connect.
+const TOUCH: f64 = 1e-4;
+
+/// Space around the copper of boards without an outline.
+const MARGIN: f64 = 5.0;
+
+/// Search costs per grid step; diagonal steps are about √2 longer.
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteOptions {
+    /// Copper layers routes may use, e.g. only `F.Cu` for a single-sided
+    /// board.
+    pub layers: Vec<Layer>,
+    /// Spacing of the grid the routes run on.
+    pub grid: f64,
+    pub track_width: f64,
+    /// Clearance to copper of other nets.
+    pub clearance: f64,
+    /// Clearance between copper and the board edge.
+    pub edge_clearance: f64,
+    pub via_size: f64,
+    pub via_drill: f64,
+    /// What a via costs, as a length of track.
+    pub via_cost: f64,
+    /// Names of the nets to route; empty routes every net.
+    pub nets: Vec<String>,
+    /// Grid cells one search may visit before its connection is given up.
+    pub max_visits: usize,
+    /// Most grid cells, counted once per routing layer, [`route`] sets up
+    /// before it fails instead.
+    pub max_cells: usize,
+}
+
+impl Default for RouteOptions {
+    /// Two layers, a 0.25 mm grid, 0.25 mm tracks, 0.2 mm clearance and
+    /// 0.6/0.3 mm vias.
+    fn default() -> Self {
+        RouteOptions {
+            layers: vec![Layer::FCu, Layer::BCu],
+            grid: 0.25,
+            track_width: 0.25,
+            clearance: 0.2,
+            edge_clearance: 0.5,
+            via_size: 0.6,
+            via_drill: 0.3,
+            via_cost: 2.0,
+            nets: Vec::new(),
+            max_visits: 1_000_000,
+            max_cells: 16_000_000,
+        }
+    }
+}
+
+/// One end of a ratsnest line: a pad, a via or the end of a track.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Anchor {
+    pub at: Point,
+    /// The copper layers the item is on.
+    pub layers: Vec<Layer>,
+}
+
+/// A ratsnest line: two parts of a net that no copper connects yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Connection {
+    pub net: u32,
+    pub from: Anchor,
+    pub to: Anchor,
+}
+
+/// What [`route`] added to the board.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RouteReport {
+    pub tracks: Vec<Track>,
+    pub vias: Vec<Via>,
+    pub routed: usize,
+    /// The connections no route was found for.
+    pub unrouted: Vec<Connection>,
+}
+
+/// The connections missing from `board`: for each net, the shortest lines
+/// that join all the groups of connected copper with a pad in them.
+pub fn ratsnest(board: &Board) -> Vec<Connection> {
+    missing(board, &copper(board))
+}
+
+/// Routes the connections missing from `board`, adding the tracks and vias
+/// to it. New items get UUIDs from `generator`.
+///
+/// Fails without changing the board if the grid spacing is not a positive
+/// number or the grid would have more than [`RouteOptions::max_cells`]
+/// cells.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(routed, unrouted)))]
+pub fn route(board: &mut Board, options: &RouteOptions, generator: &mut UuidGenerator) -> Result<RouteReport, Error> {
+    if !(options.grid.is_finite() && options.grid > 0.0) {
+        return Err(ConvertError::new(format!("grid spacing must be positive, got {}", options.grid)).into());
+    }
+    let items = copper(board);
+    let mut connections = missing(board, &items);
+    connections.retain(|connection| {
+        options.nets.is_empty() || board.net(connection.net).is_some_and(|net| options.nets.contains(&net.name))
+    });
+    connections.sort_by(|a, b| distance(a.from.at, a.to.at).total_cmp(&distance(b.from.at, b.to.at)));
+
+    let mut grid = Grid::new(board, &items, options)?;
+    generator.reserve(board.uuids());
+    let mut report = RouteReport::default();
+    for connection in connections {
+        let Some(path) = grid.search(&connection) else {
+            report.unrouted.push(connection);
+            continue;
+        };
+        let (tracks, vias) = grid.build(&connection, &path, generator);
+        for track in &tracks {
+            grid.add(&track_copper(track));
+        }
+        for via in &vias {
+            let copper = via_copper(via, &grid.copper_layers);
+            grid.add(&copper);
+        }
+        board.tracks.extend(tracks.iter().cloned());
+        board.vias.extend(vias.iter().cloned());
+        report.tracks.extend(tracks);
+        report.vias.extend(vias);
+        report.routed += 1;
+    }
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("routed", report.routed).record("unrouted", report.unrouted.len());
+    Ok(report)
+}
+
+/// Adds the tracks and vias of `report` to the board in `doc`, after its
+/// last track or via, or else before its zones.
+pub fn write_routes(report: &RouteReport, doc: &mut Document<'_>) -> Result<(), Error> {
+    write_tracks_and_vias(&report.tracks, &report.vias, doc)
+}
+
+/// The area a piece of copper covers.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Outline {
+    /// Within `radius` of the segment from `a` to `b`.
+    Segment { a: Point, b: Point, radius: f64 },
+    /// Within `radius` of the rectangle of half-size `half` around `center`,
+    /// turned by `angle` degrees.
+    Rect { center: Point, angle: f64, half: Point, radius: f64 },
+}
+
+impl Outline {
+    /// Distance from `p` to the edge, negative inside.
+    pub(crate) fn distance(&self, p: Point) -> f64 {
+        match *self {
+            Outline::Segment { a, b, radius } => segment_distance(p, a, b) - radius,
+            Outline::Rect { center, angle, half, radius } => {
+                let local = Point::new(p.x - center.x, p.y - center.y).rotated(-angle);
+                let (qx, qy) = (local.x.abs() - half.x, local.y.abs() - half.y);
+                qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius
+            },
+        }
+    }
+
+    pub(crate) fn bounds(&self) -> Bounds {
+        let (min, max, grow) = match *self {
+            Outline::Segment { a, b, radius } => {
+                (Point::new(a.x.min(b.x), a.y.min(b.y)), Point::new(a.x.max(b.x), a.y.max(b.y)), radius)
+            },
+            Outline::Rect { center, half, radius, .. } => (center, center, half.x.hypot(half.y) + radius),
+        };
+        Bounds { min: Point::new(min.x - grow, min.y - grow), max: Point::new(max.x + grow, max.y + grow) }
+    }
+}
+
+/// A pad, track or via.
+pub(crate) struct Copper {
+    pub(crate) net: u32,
+    pub(crate) pad: bool,
+    pub(crate) layers: Vec<Layer>,
+    pub(crate) outlines: Vec<Outline>,
+    /// Where ratsnest lines attach.
+    pub(crate) anchors: Vec<Point>,
+}
+
+impl Copper {
+    fn distance(&self, p: Point) -> f64 {
+        self.outlines.iter().map(|outline| outline.distance(p)).fold(f64::INFINITY, f64::min)
+    }
+
+    pub(crate) fn bounds(&self) -> Bounds {
+        let corners = self.outlines.iter().flat_map(|outline| {
+            let bounds = outline.bounds();
+            [bounds.min, bounds.max]
+        });
+        Bounds::of(corners).unwrap_or(Bounds { min: Point::default(), max: Point::default() })
+    }
+
+    fn touches(&self, other: &Copper) -> bool {
+        self.layers.iter().any(|layer| other.layers.contains(layer))
+            && (self.anchors.iter().any(|&p| other.distance(p) <= TOUCH)
+                || other.anchors.iter().any(|&p| self.distance(p) <= TOUCH))
+    }
+}
+
+pub(crate) fn copper(board: &Board) -> Vec<Copper> {
+    let layers = board.copper_layers();
+    let mut items = Vec::new();
+    for footprint in &board.footprints {
+        for pad in &footprint.pads {
+            let on: Vec<Layer> = layers.iter().copied().filter(|&layer| pad.on_layer(layer)).collect();
+            if on.is_empty() {
+                continue;
+            }
+            let center = footprint.pad_position(pad);
+            items.push(Copper {
+                net: pad.net.as_ref().map_or(0, |net| net.number),
+                pad: true,
+                layers: on,
+                outlines: vec![pad_outline(footprint, pad)],
+                anchors: vec![center],
+            });
+        }
+    }
+    items.extend(board.tracks.iter().map(track_copper));
+    items.extend(board.vias.iter().map(|via| via_copper(via, &layers)));
+    items
+}
+
+/// The copper of `pad`, with trapezoids and custom shapes as their
+/// bounding rectangle.
+pub(crate) fn pad_outline(footprint: &Footprint, pad: &Pad) -> Outline {
+    let center = footprint.pad_position(pad);
+    let (w, h) = (pad.size.width / 2.0, pad.size.height / 2.0);
+    let radius = match pad.shape {
+        PadShape::Circle => w,
+        PadShape::Oval => w.min(h),
+        PadShape::RoundRect => 2.0 * w.min(h) * pad.roundrect_rratio.unwrap_or(0.25),
+        PadShape::Rect | PadShape::Trapezoid | PadShape::Custom => 0.0,
+    };
+    let half = Point::new((w - radius).max(0.0), (h - radius).max(0.0));
+    Outline::Rect { center, angle: pad.at.angle, half, radius }
+}
+
+pub(crate) fn track_copper(track: &Track) -> Copper {
+    let path = match track.mid {
+        Some(mid) => arc_points(track.start, mid, track.end, MAX_ERROR),
+        None => vec![track.start, track.end],
+    };
+    let radius = track.width / 2.0;
+    Copper {
+        net: track.net,
+        pad: false,
+        layers: vec![track.layer],
+        outlines: path.windows(2).map(|pair| Outline::Segment { a: pair[0], b: pair[1], radius }).collect(),
+        anchors: vec![track.start, track.end],
+    }
+}
+
+pub(crate) fn via_copper(via: &Via, copper_layers: &[Layer]) -> Copper {
+    Copper {
+        net: via.net,
+        pad: false,
+        layers: copper_layers.iter().copied().filter(|&layer| via.spans(layer)).collect(),
+        outlines: vec![Outline::Segment { a: via.at, b: via.at, radius: via.size / 2.0 }],
+        anchors: vec![via.at],
+    }
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    parent[a] = b;
+}
+
+/// The connected group of each item, as the index of one item in it.
+fn groups(board: &Board, items: &[Copper]) -> Vec<usize> {
+    let mut parent: Vec<usize> = (0..items.len()).collect();
+    let mut nets: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    for (i, item) in items.iter().enumerate().filter(|(_, item)| item.net != 0) {
+        nets.entry(item.net).or_default().push(i);
+    }
+    for members in nets.values() {
+        let bounds: Vec<Bounds> = members.iter().map(|&i| items[i].bounds()).collect();
+        for (a, &i) in members.iter().enumerate() {
+            for (b, &j) in members.iter().enumerate().skip(a + 1) {
+                if overlap(&bounds[a], &bounds[b]) && items[i].touches(&items[j]) {
+                    union(&mut parent, i, j);
+                }
+            }
+        }
+    }
+    let zones = board.zones.iter().filter(|zone| zone.keepout.is_none() && zone.net != 0);
+    for zone in zones {
+        let Some(members) = nets.get(&zone.net) else {
+            continue;
+        };
+        for fill in &zone.filled_polygons {
+            let polygon: Contour = fill.points.iter().map(|p| [p.x, p.y]).collect();
+            let Some(bounds) = Bounds::of(fill.points.iter().copied()) else {
+                continue;
+            };
+            let mut touching = members.iter().copied().filter(|&i| {
+                let item = &items[i];
+                item.layers.contains(&fill.layer)
+                    && overlap(&bounds, &item.bounds())
+                    && (item.anchors.iter().any(|p| contains(std::slice::from_ref(&polygon), [p.x, p.y]))
+                        || fill.points.iter().any(|&p| item.distance(p) <= TOUCH))
+            });
+            if let Some(first) = touching.next() {
+                for other in touching {
+                    union(&mut parent, first, other);
+                }
+            }
+        }
+    }
+    (0..items.len()).map(|i| find(&mut parent, i)).collect()
+}
+
+fn overlap(a: &Bounds, b: &Bounds) -> bool {
+    a.min.x <= b.max.x + TOUCH && b.min.x <= a.max.x + TOUCH && a.min.y <= b.max.y + TOUCH && b.min.y <= a.max.y + TOUCH
+}
+
+/// Joins the groups of each net with a pad in them by a minimum spanning
+/// tree over the shortest lines between them.
+pub(crate) fn missing(board: &Board, items: &[Copper]) -> Vec<Connection> {
+    let groups = groups(board, items);
+    let mut nets: BTreeMap<u32, BTreeMap<usize, Vec<usize>>> = BTreeMap::new();
+    for (i, item) in items.iter().enumerate().filter(|(_, item)| item.net != 0) {
+        nets.entry(item.net).or_default().entry(groups[i]).or_default().push(i);
+    }
+    let mut connections = Vec::new();
+    for (net, groups) in nets {
+        let groups: Vec<Vec<usize>> =
+            groups.into_values().filter(|members| members.iter().any(|&i| items[i].pad)).collect();
+        if groups.len() < 2 {
+            continue;
+        }
+        // Prim's algorithm: the closest line from the tree to each group.
+        let mut best: Vec<Option<(f64, Anchor, Anchor)>> = vec![None; groups.len()];
+        let mut joined = vec![false; groups.len()];
+        let mut last = 0;
+        joined[0] = true;
+        for _ in 1..groups.len() {
+            for (j, members) in groups.iter().enumerate().filter(|&(j, _)| !joined[j]) {
+                let line = closest(items, &groups[last], members);
+                if best[j].as_ref().is_none_or(|(d, ..)| line.0 < *d) {
+                    best[j] = Some(line);
+                }
+            }
+            let next = (0..groups.len()).filter(|&j| !joined[j]).min_by(|&a, &b| {
+                let d = |j: usize| best[j].as_ref().map_or(f64::INFINITY, |(d, ..)| *d);
+                d(a).total_cmp(&d(b))
+            });
+            let Some(next) = next else {
+                break;
+            };
+            let (_, from, to) = best[next].take().expect("every open group has a line");
+            connections.push(Connection { net, from, to });
+            joined[next] = true;
+            last = next;
+        }
+    }
+    connections
+}
+
+fn closest(items: &[Copper], from: &[usize], to: &[usize]) -> (f64, Anchor, Anchor) {
+    let anchors = |members: &[usize]| -> Vec<(Point, usize)> {
+        members.iter().flat_map(|&i| items[i].anchors.iter().map(move |&p| (p, i))).collect()
+    };
+    let (from, to) = (anchors(from), anchors(to));
+    let mut best = (f64::INFINITY, from[0], to[0]);
+    for &a in &from {
+        for &b in &to {
+            let d = distance(a.0, b.0);
+            if d < best.0 {
+                best = (d, a, b);
+            }
+        }
+    }
+    let anchor = |(at, i): (Point, usize)| Anchor { at, layers: items[i].layers.clone() };
+    (best.0, anchor(best.1), anchor(best.2))
+}
+
+pub(crate) fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 { 0.0 } else { (((p.x - a.x) * dx + (p.y - a.y) * dy) / length).clamp(0.0, 1.0) };
+    distance(p, Point::new(a.x + t * dx, a.y + t * dy))
+}
+
+/// What may go at a grid cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cell {
+    Free,
+    /// Near copper of this net only.
+    Net(u32),
+    /// On copper of this net: free for it whatever else is near.
+    Copper(u32),
+    Blocked,
+}
+
+impl Cell {
+    fn open(self, net: u32) -> bool {
+        match self {
+            Cell::Free => true,
+            Cell::Net(n) | Cell::Copper(n) => n == net,
+            Cell::Blocked => false,
+        }
+    }
+
+    fn mark(&mut self, net: u32, inside: bool) {
+        *self = match (*self, inside) {
+            (Cell::Blocked, _) => Cell::Blocked,
+            (Cell::Copper(n), true) if n != net => Cell::Blocked,
+            (_, true) => Cell::Copper(net),
+            (Cell::Free, false) => Cell::Net(net),
+            (Cell::Net(n), false) if n != net => Cell::Blocked,
+            (cell, false) => cell,
+        };
+    }
+}
+
+/// Grid steps to the eight neighbours.
+const STEPS: [(isize, isize); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+struct Grid<'o> {
+    options: &'o RouteOptions,
+    origin: Point,
+    cols: usize,
+    rows: usize,
+    /// The routing layers that are on the board.
+    layers: Vec<Layer>,
+    copper_layers: Vec<Layer>,
+    /// Where a track's center line may pass, per routing layer.
+    tracks: Vec<Vec<Cell>>,
+    /// Where a via may go; vias pass through every copper layer.
+    vias: Vec<Cell>,
+    /// Distance from copper a track's center line keeps.
+    track_margin: f64,
+    via_margin: f64,
+    via_cost: u32,
+    cost: Vec<u32>,
+    came_from: Vec<usize>,
+    /// Which search `cost` and `came_from` are from, per state.
+    visited: Vec<u32>,
+    search: u32,
+}
+
+impl<'o> Grid<'o> {
+    fn new(board: &Board, items: &[Copper], options: &'o RouteOptions) -> Result<Self, Error> {
+        let step = options.grid;
+        // A track between two cell centers is never further than this from
+        // one of them.
+        let slack = step * FRAC_1_SQRT_2;
+        let track_edge = options.edge_clearance + options.track_width / 2.0 + slack;
+        let track_area = board_area(board, track_edge, MAX_ERROR);
+        let via_area = board_area(board, options.edge_clearance + options.via_size / 2.0, MAX_ERROR);
+        let extent = match &track_area {
+            Some(area) => Bounds::of(area.iter().flatten().flatten().map(|&[x, y]| Point::new(x, y))),
+            None => Bounds::of(items.iter().flat_map(|item| {
+                let bounds = item.bounds();
+                [bounds.min, bounds.max]
+            }))
+            .map(|bounds| Bounds {
+                min: Point::new(bounds.min.x - MARGIN, bounds.min.y - MARGIN),
+                max: Point::new(bounds.max.x + MARGIN, bounds.max.y + MARGIN),
+            }),
+        };
+        let extent = extent.unwrap_or(Bounds { min: Point::default(), max: Point::default() });
+        let origin = Point::new((extent.min.x / step).floor() * step, (extent.min.y / step).floor() * step);
+        let cols = ((extent.max.x - origin.x) / step).ceil() + 1.0;
+        let rows = ((extent.max.y - origin.y) / step).ceil() + 1.0;
+
+        let copper_layers = board.copper_layers();
+        let layers: Vec<Layer> = options.layers.iter().copied().filter(|layer| copper_layers.contains(layer)).collect();
+        // The vias need a plane of their own even without routing layers.
+        let cells = cols * rows * layers.len().max(1) as f64;
+        if !cells.is_finite() || cells > options.max_cells as f64 {
+            let max = options.max_cells;
+            let message = format!("a {} mm grid needs {} cells, more than the {} allowed", step, cells, max);
+            return Err(ConvertError::new(message).into());
+        }
+        let (cols, rows) = (cols as usize, rows as usize);
+        let states = cols * rows * layers.len();
+        let mut grid = Grid {
+            options,
+            origin,
+            cols,
+            rows,
+            tracks: vec![vec![Cell::Free; cols * rows]; layers.len()],
+            vias: vec![Cell::Free; cols * rows],
+            layers,
+            copper_layers,
+            track_margin: options.clearance + options.track_width / 2.0 + slack,
+            via_margin: options.clearance + options.via_size / 2.0,
+            via_cost: ((options.via_cost / step) * STRAIGHT as f64).round().max(1.0) as u32,
+            cost: vec![0; states],
+            came_from: vec![0; states],
+            visited: vec![0; states],
+            search: 0,
+        };
+        for cell in 0..cols * rows {
+            let p = grid.center(cell);
+            let outside = |area: &Option<Vec<Vec<Contour>>>| {
+                area.as_ref().is_some_and(|area| !area.iter().any(|shape| contains(shape, [p.x, p.y])))
+            };
+            if outside(&track_area) {
+                for cells in &mut grid.tracks {
+                    cells[cell] = Cell::Blocked;
+                }
+            }
+            if outside(&via_area) {
+                grid.vias[cell] = Cell::Blocked;
+            }
+        }
+        for item in items {
+            grid.add(item);
+        }
+        for zone in &board.zones {
+            let Some(keepout) = zone.keepout else {
+                continue;
+            };
+            let outline: Contour = zone.outline.iter().map(|p| [p.x, p.y]).collect();
+            if keepout.tracks {
+                for l in 0..grid.layers.len() {
+                    if zone.on_layer(grid.layers[l]) {
+                        let margin = options.track_width / 2.0 + slack;
+                        grid.block(l, &zone.outline, &outline, margin);
+                    }
+                }
+            }
+            if keepout.vias && grid.copper_layers.iter().any(|&layer| zone.on_layer(layer)) {
+                grid.block(usize::MAX, &zone.outline, &outline, options.via_size / 2.0);
+            }
+        }
+        Ok(grid)
+    }
+
+    fn center(&self, cell: usize) -> Point {
+        let step = self.options.grid;
+        let snap = |v: f64| (v * 1e6).round() / 1e6;
+        Point::new(
+            snap(self.origin.x + (cell % self.cols) as f64 * step),
+            snap(self.origin.y + (cell / self.cols) as f64 * step),
+        )
+    }
+
+    fn nearest(&self, p: Point) -> usize {
+        let step = self.options.grid;
+        let col = ((p.x - self.origin.x) / step).round().clamp(0.0, (self.cols - 1) as f64) as usize;
+        let row = ((p.y - self.origin.y) / step).round().clamp(0.0, (self.rows - 1) as f64) as usize;
+        row * self.cols + col
+    }
+
+    /// The cells within `margin` of `bounds`.
+    fn cells_near(&self, bounds: &Bounds, margin: f64) -> impl Iterator<Item = usize> + use<> {
+        let step = self.options.grid;
+        let range = |min: f64, max: f64, origin: f64, count: usize| {
+            let first = ((min - margin - origin) / step).floor().max(0.0) as usize;
+            let last = (((max + margin - origin) / step).ceil().max(0.0) as usize).min(count - 1);
+            first..=last
+        };
+        let cols = range(bounds.min.x, bounds.max.x, self.origin.x, self.cols);
+        let rows = range(bounds.min.y, bounds.max.y, self.origin.y, self.rows);
+        let width = self.cols;
+        rows.flat_map(move |row| cols.clone().map(move |col| row * width + col))
+    }
+
+    /// Marks the cells around `item` as taken by its net.
+    fn add(&mut self, item: &Copper) {
+        let bounds = item.bounds();
+        for l in 0..self.layers.len() {
+            if !item.layers.contains(&self.layers[l]) {
+                continue;
+            }
+            for cell in self.cells_near(&bounds, self.track_margin) {
+                let d = item.distance(self.center(cell));
+                if d < self.track_margin {
+                    self.tracks[l][cell].mark(item.net, d <= 0.0);
+                }
+            }
+        }
+        // A via is on every layer, so being on copper of its own net on
+        // one does not make it free on the others.
+        for cell in self.cells_near(&bounds, self.via_margin) {
+            if item.distance(self.center(cell)) < self.via_margin {
+                self.vias[cell].mark(item.net, false);
+            }
+        }
+    }
+
+    /// Blocks the cells within `margin` of a keepout `outline`, on routing
+    /// layer `l` or for vias if `l` is `usize::MAX`.
+    fn block(&mut self, l: usize, points: &[Point], outline: &Contour, margin: f64) {
+        let Some(bounds) = Bounds::of(points.iter().copied()) else {
+            return;
+        };
+        let next = points.iter().cycle().skip(1);
+        let edges: Vec<(Point, Point)> = points.iter().copied().zip(next.copied()).collect();
+        for cell in self.cells_near(&bounds, margin).collect::<Vec<_>>() {
+            let p = self.center(cell);
+            let near = edges.iter().any(|&(a, b)| segment_distance(p, a, b) < margin);
+            if near || contains(std::slice::from_ref(outline), [p.x, p.y]) {
+                let cells = if l == usize::MAX { &mut self.vias } else { &mut self.tracks[l] };
+                cells[cell] = Cell::Blocked;
+            }
+        }
+    }
+
+    /// The routing layers among `layers`, by index.
+    fn layer_indices(&self, layers: &[Layer]) -> Vec<usize> {
+        (0..self.layers.len()).filter(|&l| layers.contains(&self.layers[l])).collect()
+    }
+
+    /// An A* search for `connection`, returning the path as routing layer
+    /// and cell pairs.
+    fn search(&mut self, connection: &Connection) -> Option<Vec<(usize, usize)>> {
+        let (starts, goals) = (self.layer_indices(&connection.from.layers), self.layer_indices(&connection.to.layers));
+        if starts.is_empty() || goals.is_empty() {
+            return None;
+        }
+        let (start, goal) = (self.nearest(connection.from.at), self.nearest(connection.to.at));
+        let net = connection.net;
+        let cells = self.cols * self.rows;
+        let (width, via_cost) = (self.cols, self.via_cost);
+        let (goal_col, goal_row) = ((goal % width) as isize, (goal / width) as isize);
+        let heuristic = |l: usize, cell: usize| {
+            let dx = ((cell % width) as isize - goal_col).unsigned_abs() as u32;
+            let dy = ((cell / width) as isize - goal_row).unsigned_abs() as u32;
+            let via = if goals.contains(&l) { 0 } else { via_cost };
+            STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy) + via
+        };
+        // The ends are on copper of the net, whatever is near them.
+        let open = |grid: &Grid, l: usize, cell: usize| cell == start || cell == goal || grid.tracks[l][cell].open(net);
+
+        self.search += 1;
+        let mut queue = BinaryHeap::new();
+        for &l in &starts {
+            let state = l * cells + start;
+            self.visited[state] = self.search;
+            self.cost[state] = 0;
+            self.came_from[state] = state;
+            queue.push(Reverse((heuristic(l, start), heuristic(l, start), state)));
+        }
+        let mut visits = 0;
+        while let Some(Reverse((_, _, state))) = queue.pop() {
+            let (l, cell) = (state / cells, state % cells);
+            if cell == goal && goals.contains(&l) {
+                let mut path = vec![(l, cell)];
+                let mut state = state;
+                while self.came_from[state] != state {
+                    state = self.came_from[state];
+                    path.push((state / cells, state % cells));
+                }
+                path.reverse();
+                return Some(path);
+            }
+            visits += 1;
+            if visits > self.options.max_visits {
+                return None;
+            }
+            let cost = self.cost[state];
+            let (col, row) = ((cell % self.cols) as isize, (cell / self.cols) as isize);
+            let mut moves: Vec<(usize, u32)> = Vec::with_capacity(STEPS.len() + self.layers.len());
+            for (dx, dy) in STEPS {
+                let (c, r) = (col + dx, row + dy);
+                if c < 0 || r < 0 || c >= self.cols as isize || r >= self.rows as isize {
+                    continue;
+                }
+                let next = r as usize * self.cols + c as usize;
+                if !open(self, l, next) {
+                    continue;
+                }
+                let diagonal = dx != 0 && dy != 0;
+                // Diagonal steps may not cut past a blocked corner.
+                let corners = [row as usize * self.cols + c as usize, r as usize * self.cols + col as usize];
+                if diagonal && !corners.iter().all(|&corner| open(self, l, corner)) {
+                    continue;
+                }
+                moves.push((l * cells + next, if diagonal { DIAGONAL } else { STRAIGHT }));
+            }
+            if self.vias[cell].open(net) {
+                for other in (0..self.layers.len()).filter(|&other| other != l && open(self, other, cell)) {
+                    moves.push((other * cells + cell, self.via_cost));
+                }
+            }
+            for (next, step) in moves {
+                let next_cost = cost + step;
+                if self.visited[next] == self.search && self.cost[next] <= next_cost {
+                    continue;
+                }
+                self.visited[next] = self.search;
+                self.cost[next] = next_cost;
+                self.came_from[next] = state;
+                let h = heuristic(next / cells, next % cells);
+                queue.push(Reverse((next_cost + h, h, next)));
+            }
+        }
+        None
+    }
+
+    /// The tracks and vias along a path, straight runs merged into one
+    /// track and the ends joined to the anchors.
+    fn build(
+        &self,
+        connection: &Connection,
+        path: &[(usize, usize)],
+        generator: &mut UuidGenerator,
+    ) -> (Vec<Track>, Vec<Via>) {
+        let options = self.options;
+        let net = connection.net;
+        let (mut tracks, mut vias) = (Vec::new(), Vec::new());
+        let runs = path.chunk_by(|a, b| a.0 == b.0).collect::<Vec<_>>();
+        for (i, run) in runs.iter().enumerate() {
+            let layer = self.layers[run[0].0];
+            let mut points: Vec<Point> = run.iter().map(|&(_, cell)| self.center(cell)).collect();
+            if i == 0 {
+                points.insert(0, connection.from.at);
+            }
+            if i == runs.len() - 1 {
+                points.push(connection.to.at);
+            } else {
+                let at = *points.last().expect("runs are not empty");
+                let uuid = generator.generate(format!("via {:?}", at));
+                let span = (self.copper_layers.first(), self.copper_layers.last());
+                let layers = [span.0, span.1].into_iter().flatten().copied().collect();
+                vias.push(Via {
+                    kind: ViaKind::Through,
+                    at,
+                    size: options.via_size,
+                    drill: options.via_drill,
+                    layers,
+                    net,
+                    locked: false,
+                    uuid,
+                    protection: ViaProtection::default(),
+                });
+            }
+            points.dedup_by(|a, b| distance(*a, *b) < 1e-6);
+            for (start, end) in corners(&points).windows(2).map(|pair| (pair[0], pair[1])) {
+                let uuid = generator.generate(format!("segment {} {:?} {:?}", layer, start, end));
+                let width = options.track_width;
+                tracks.push(Track { start, mid: None, end, width, layer, net, locked: false, uuid });
+            }
+        }
+        (tracks, vias)
+    }
+}
+
+/// The points of a path where it changes direction, ends included. A
+/// point the path turns back at, as where an anchor is short of the last
+/// cell, is dropped too.
+fn corners(points: &[Point]) -> Vec<Point> {
+    let mut kept: Vec<Point> = Vec::new();
+    for &p in points {
+        if let [.., a, b] = kept[..]
+            && ((b.x - a.x) * (p.y - b.y) - (b.y - a.y) * (p.x - b.x)).abs() < 1e-9
+        {
+            kept.pop();
+        }
+        kept.push(p);
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_sexp::number::format_float;
+
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    /// Two pads of net `A` with a track of net `B` across the front between
+    /// them.
+    const WALL: &str = r#"(kicad_pcb
+	(version 20241229)
+	(generator "pcbnew")
+	(layers
+		(0 "F.Cu" signal)
+		(2 "B.Cu" signal)
+		(25 "Edge.Cuts" user)
+	)
+	(net 0 "")
+	(net 1 "A")
+	(net 2 "B")
+	(footprint "Test:Pads"
+		(layer "F.Cu")
+		(at 0 0)
+		(pad "1" smd rect
+			(at 2 5)
+			(size 1 1)
+			(layers "F.Cu")
+			(net 1 "A")
+		)
+		(pad "2" smd rect
+			(at 18 5)
+			(size 1 1)
+			(layers "F.Cu")
+			(net 1 "A")
+		)
+	)
+	(gr_rect
+		(start 0 0)
+		(end 20 10)
+		(layer "Edge.Cuts")
+	)
+	(segment
+		(start 10 0.2)
+		(end 10 9.8)
+		(width 0.5)
+		(layer "F.Cu")
+		(net 2)
+		(uuid "00000000-0000-0000-0000-000000000001")
+	)
+)
+"#;
+
+    fn unrouted_demo() -> Board {
+        let mut board = Board::parse(DEMO).unwrap();
+        board.tracks.retain(|track| track.net != 1);
+        board
+    }
+
+    /// Smallest distance between the new tracks and vias and copper of other
+    /// nets.
+    fn min_clearance(board: &Board, report: &RouteReport) -> f64 {
+        let items = copper(board);
+        let mut min = f64::INFINITY;
+        for track in &report.tracks {
+            let length = distance(track.start, track.end);
+            let steps = (length / 0.01).ceil().max(1.0) as usize;
+            for i in 0..=steps {
+                let t = i as f64 / steps as f64;
+                let (a, b) = (track.start, track.end);
+                let p = Point::new(a.x + t * (b.x - a.x), a.y + t * (b.y - a.y));
+                let others = items.iter().filter(|item| item.net != track.net && item.layers.contains(&track.layer));
+                for item in others {
+                    min = min.min(item.distance(p) - track.width / 2.0);
+                }
+            }
+        }
+        for via in &report.vias {
+            for item in items.iter().filter(|item| item.net != via.net) {
+                min = min.min(item.distance(via.at) - via.size / 2.0);
+            }
+        }
+        min
+    }
+
+    #[test]
+    fn demo_ratsnest() {
+        let board = Board::parse(DEMO).unwrap();
+        assert_eq!(ratsnest(&board), []);
+
+        let connections = ratsnest(&unrouted_demo());
+        assert_eq!(connections.len(), 2);
+        assert!(connections.iter().all(|connection| connection.net == 1));
+        let j1 = connections.iter().find(|connection| connection.from.at == Point::new(104.0, 105.0)).unwrap();
+        assert_eq!(j1.to.at, Point::new(109.175, 105.0));
+        assert_eq!(j1.from.layers, [Layer::FCu, Layer::BCu]);
+    }
+
+    #[test]
+    fn fills_connect_through_spokes() {
+        let mut board = Board::parse(DEMO).unwrap();
+        crate::fill::fill_zones(&mut board, &crate::fill::FillOptions::default());
+        assert_eq!(ratsnest(&board), []);
+
+        // Without its spokes, C1's GND pad is cut off from the fill.
+        let c1 = board.footprints.iter().position(|footprint| footprint.reference() == Some("C1")).unwrap();
+        board.footprints[c1].pads[0].zone_connect = Some(crate::PadConnection::None);
+        crate::fill::fill_zones(&mut board, &crate::fill::FillOptions::default());
+        let connections = ratsnest(&board);
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].net, 3);
+    }
+
+    #[test]
+    fn demo_routes() {
+        let mut board = unrouted_demo();
+        let options = RouteOptions::default();
+        let report = route(&mut board, &options, &mut UuidGenerator::deterministic("demo")).unwrap();
+
+        assert_eq!((report.routed, report.unrouted.len()), (2, 0));
+        assert_eq!(ratsnest(&board), []);
+        assert!(min_clearance(&board, &report) >= options.clearance - 1e-9);
+        let inside = |p: Point| (100.5..=129.5).contains(&p.x) && (100.5..=119.5).contains(&p.y);
+        assert!(report.tracks.iter().all(|track| inside(track.start) && inside(track.end)));
+
+        let again = route(&mut unrouted_demo(), &options, &mut UuidGenerator::deterministic("demo")).unwrap();
+        assert_eq!(again, report);
+    }
+
+    #[test]
+    fn vias_around_wall() {
+        let mut board = Board::parse(WALL).unwrap();
+        let report = route(&mut board, &RouteOptions::default(), &mut UuidGenerator::deterministic("wall")).unwrap();
+        assert_eq!((report.routed, report.vias.len()), (1, 2));
+        assert!(report.tracks.iter().any(|track| track.layer == Layer::BCu));
+        assert!(min_clearance(&board, &report) >= 0.2 - 1e-9);
+        assert_eq!(ratsnest(&board), []);
+
+        let single = RouteOptions { layers: vec![Layer::FCu], ..Default::default() };
+        let mut board = Board::parse(WALL).unwrap();
+        let report = route(&mut board, &single, &mut UuidGenerator::deterministic("wall")).unwrap();
+        assert_eq!((report.routed, report.unrouted.len()), (0, 1));
+        assert!(board.tracks.len() == 1 && board.vias.is_empty());
+    }
+
+    #[test]
+    fn grid_limits() {
+        for grid in [0.0, -0.25, f64::NAN, f64::INFINITY] {
+            let options = RouteOptions { grid, ..Default::default() };
+            let mut board = unrouted_demo();
+            let error = route(&mut board, &options, &mut UuidGenerator::deterministic("demo")).unwrap_err();
+            assert!(error.to_string().contains("grid spacing must be positive"), "{}", error);
+            assert_eq!(board, unrouted_demo());
+        }
+
+        // 30 mm × 20 mm plus the edges at 1 µm is about 600 million cells per layer.
+        let options = RouteOptions { grid: 0.001, ..Default::default() };
+        let mut board = unrouted_demo();
+        let error = route(&mut board, &options, &mut UuidGenerator::deterministic("demo")).unwrap_err();
+        assert!(error.to_string().contains("more than the 16000000 allowed"), "{}", error);
+        assert_eq!(board, unrouted_demo());
+
+        let options = RouteOptions { max_cells: 1000, ..Default::default() };
+        assert!(route(&mut unrouted_demo(), &options, &mut UuidGenerator::deterministic("demo")).is_err());
+    }
+
+    #[test]
+    fn write_back() {
+        let mut board = Board::parse(WALL).unwrap();
+        let report = route(&mut board, &RouteOptions::default(), &mut UuidGenerator::deterministic("wall")).unwrap();
+        let mut doc = kicad_sexp::cst::parse(WALL).unwrap();
+        write_routes(&report, &mut doc).unwrap();
+
+        let text = doc.to_string();
+        let first = &report.tracks[0];
+        let segment = format!(
+            concat!(
+                "\t)\n\t(segment\n\t\t(start {} {})\n\t\t(end {} {})\n\t\t(width 0.25)\n",
+                "\t\t(layer \"F.Cu\")\n\t\t(net 1)\n\t\t(uuid \"{}\")\n\t)\n",
+            ),
+            format_float(first.start.x),
+            format_float(first.start.y),
+            format_float(first.end.x),
+            format_float(first.end.y),
+            first.uuid,
+        );
+        assert!(text.contains(&segment), "{}", text);
+        assert!(text.contains("\t(via\n\t\t(at "));
+        assert!(text.ends_with("\t)\n)\n"));
+        let saved = Board::parse(&text).unwrap();
+        assert_eq!(saved.tracks.len(), 1 + report.tracks.len());
+        assert_eq!(saved.vias, report.vias);
+        assert_eq!(ratsnest(&saved), []);
+    }
+}
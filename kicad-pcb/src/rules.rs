@@ -0,0 +1,294 @@
+//! Custom design rule conditions evaluated against board items, as KiCad's
+//! DRC evaluates them, including the KiCad 9 component classes and rule
+//! areas a rule can refer to.
+//!
+//! A [`RuleItem`] stands for the `A` or `B` of a condition. It knows these
+//! properties:
+//!
+//! * `Type`: `Footprint`, `Pad`, `Graphic`, `Text`, `Track`, `Via` or `Zone`.
+//! * `NetName` and `NetCode` of pads, tracks, vias and zones; `Layer`.
+//! * `Reference`, `Value`, `Library_Link`, `Sheetname` and `Component_Class`
+//!   of footprints, and any other footprint field with spaces written as
+//!   underscores. Pads and footprint graphics and texts use their
+//!   footprint's.
+//!
+//! and these functions:
+//!
+//! * `hasComponentClass('Power')`, `memberOfSheet('/Power/')` and
+//!   `memberOfFootprint('U1')`, for an item's footprint.
+//! * `enclosedByArea('Analog')` and `intersectsArea('Analog')`, by the
+//!   bounding box of the item and the outline of the rule areas with that
+//!   name or UUID.
+//! * `getField('MPN')`.
+//!
+//! Names may use `*` and `?` wildcards, and string comparisons ignore case.
+//!
+//! ```no_run
+//! use kicad_dru::Rules;
+//! use kicad_pcb::{index::Item, rules::RuleItem, Board};
+//!
+//! let board = Board::parse(&std::fs::read_to_string("demo.kicad_pcb")?)?;
+//! let rules = Rules::parse(&std::fs::read_to_string("demo.kicad_dru")?)?;
+//! for rule in &rules.rules {
+//!     let Some(condition) = &rule.condition else { continue };
+//!     for footprint in &board.footprints {
+//!         if condition.matches(&RuleItem::new(&board, Item::Footprint(footprint)), None)? {
+//!             println!("{} applies to {}", rule.name, footprint.reference().unwrap_or("?"));
+//!         }
+//!     }
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::Point;
+use kicad_dru::{wildcard_match, Outcome};
+
+use crate::{
+    bounds::{polygon_inside, Bounds},
+    index::Item,
+    selection::bounds,
+    Board, Footprint, Layer, Zone,
+};
+
+
+/// A board item as the `A` or `B` of a rule condition.
+#[derive(Clone, Copy, Debug)]
+pub struct RuleItem<'a> {
+    pub board: &'a Board,
+    pub item: Item<'a>,
+}
+
+impl<'a> RuleItem<'a> {
+    pub fn new(board: &'a Board, item: Item<'a>) -> Self {
+        RuleItem { board, item }
+    }
+
+    /// The footprint the item is or belongs to.
+    fn footprint(&self) -> Option<&'a Footprint> {
+        match self.item {
+            Item::Footprint(footprint)
+            | Item::Pad(footprint, _)
+            | Item::FootprintShape(footprint, _)
+            | Item::FootprintText(footprint, _) => Some(footprint),
+            Item::Shape(_) | Item::Text(_) | Item::Track(_) | Item::Via(_) | Item::Zone(_) => None,
+        }
+    }
+
+    fn net(&self) -> Option<(u32, &'a str)> {
+        let by_number = |number: u32| self.board.net(number).map(|net| (net.number, net.name.as_str()));
+        match self.item {
+            Item::Pad(_, pad) => pad.net.as_ref().map(|net| (net.number, net.name.as_str())),
+            Item::Track(track) => by_number(track.net),
+            Item::Via(via) => by_number(via.net),
+            Item::Zone(zone) => Some((zone.net, zone.net_name.as_str())),
+            _ => None,
+        }
+    }
+
+    fn layer(&self) -> Option<String> {
+        Some(match self.item {
+            Item::Footprint(footprint) => footprint.layer.unwrap_or(Layer::FCu).to_string(),
+            Item::Pad(_, pad) => pad.layers.first()?.to_string(),
+            Item::FootprintShape(_, shape) | Item::Shape(shape) => shape.layer.to_string(),
+            Item::FootprintText(_, text) | Item::Text(text) => text.layer.to_string(),
+            Item::Track(track) => track.layer.to_string(),
+            Item::Via(via) => via.layers.first()?.to_string(),
+            Item::Zone(zone) => zone.layers.first()?.to_string(),
+        })
+    }
+
+    /// The item's bounding box; pads count as a square of their larger side.
+    fn bounds(&self) -> Option<Bounds> {
+        match self.item {
+            Item::Pad(footprint, pad) => {
+                let (at, half) = (footprint.pad_position(pad), pad.size.width.max(pad.size.height) / 2.0);
+                Bounds::of([Point::new(at.x - half, at.y - half), Point::new(at.x + half, at.y + half)])
+            },
+            Item::FootprintShape(footprint, _) | Item::FootprintText(footprint, _) => {
+                bounds(Item::Footprint(footprint))
+            },
+            item => bounds(item),
+        }
+    }
+
+    /// The rule areas named or identified by `name`.
+    fn areas(&self, name: &str) -> impl Iterator<Item = &'a Zone> {
+        let name = name.to_string();
+        self.board.zones.iter().filter(move |zone| {
+            zone.keepout.is_some()
+                && (zone.name.as_deref().is_some_and(|own| wildcard_match(&name, own))
+                    || zone.uuid.is_some_and(|uuid| uuid.to_string() == name))
+        })
+    }
+}
+
+impl kicad_dru::Item for RuleItem<'_> {
+    fn property(&self, name: &str) -> Option<Outcome> {
+        let string = |text: &str| Some(Outcome::String(text.to_string()));
+        match name {
+            "Type" => string(match self.item {
+                Item::Footprint(_) => "Footprint",
+                Item::Pad(..) => "Pad",
+                Item::FootprintShape(..) | Item::Shape(_) => "Graphic",
+                Item::FootprintText(..) | Item::Text(_) => "Text",
+                Item::Track(_) => "Track",
+                Item::Via(_) => "Via",
+                Item::Zone(_) => "Zone",
+            }),
+            "NetName" => string(self.net()?.1),
+            "NetCode" => Some(Outcome::Number(self.net()?.0.into())),
+            "Layer" => string(&self.layer()?),
+            _ => {
+                let footprint = self.footprint()?;
+                match name {
+                    "Reference" => string(footprint.reference()?),
+                    "Value" => string(footprint.value()?),
+                    "Library_Link" => string(&footprint.lib_id),
+                    "Sheetname" => string(footprint.sheetname.as_deref()?),
+                    "Component_Class" => string(&footprint.component_classes.join(",")),
+                    _ => {
+                        let field = footprint.properties.iter().find(|field| field.key.replace(' ', "_") == name)?;
+                        string(&field.value)
+                    },
+                }
+            },
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Outcome]) -> Option<Outcome> {
+        let arg = args.first().and_then(Outcome::as_str).unwrap_or_default();
+        let footprint = self.footprint();
+        Some(Outcome::bool(match name {
+            "hasComponentClass" => {
+                footprint.is_some_and(|footprint| footprint.component_classes.iter().any(|c| wildcard_match(arg, c)))
+            },
+            "memberOfSheet" => {
+                footprint.and_then(|footprint| footprint.sheetname.as_deref()).is_some_and(|s| wildcard_match(arg, s))
+            },
+            "memberOfFootprint" => {
+                footprint.and_then(Footprint::reference).is_some_and(|reference| wildcard_match(arg, reference))
+            },
+            "enclosedByArea" => self.bounds().is_some_and(|bounds| {
+                self.areas(arg).any(|area| polygon_inside(&bounds.corners(), &area.outline))
+            }),
+            "intersectsArea" => {
+                self.bounds().is_some_and(|bounds| self.areas(arg).any(|area| bounds.overlaps(&area.outline)))
+            },
+            "getField" => {
+                let value = footprint.and_then(|footprint| footprint.property(arg)).unwrap_or_default();
+                return Some(Outcome::String(value.to_string()));
+            },
+            _ => return None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_dru::{Condition, Item as _};
+
+    use super::*;
+    use crate::PlacementSource;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    /// The demo board with R1 in the `Power` class and a rule area around it.
+    fn board() -> Board {
+        let src = DEMO.replacen(
+            "\t\t(sheetname \"/\")",
+            "\t\t(sheetname \"/\")\n\t\t(component_classes\n\t\t\t(class \"Power\")\n\t\t\t(class \"Hot\")\n\t\t)",
+            1,
+        );
+        let mut board = Board::parse(&src).unwrap();
+        let r1 = board.footprints.iter().find(|footprint| footprint.has_component_class("Power")).unwrap();
+        let at = r1.at.point();
+        let square = |half: f64| {
+            [(-half, -half), (half, -half), (half, half), (-half, half)].map(|(x, y)| Point::new(at.x + x, at.y + y))
+        };
+        board.zones.push(Zone {
+            name: Some("Power area".to_string()),
+            keepout: Some(Default::default()),
+            outline: square(10.0).to_vec(),
+            ..Default::default()
+        });
+        board.zones.push(Zone {
+            name: Some("Tiny".to_string()),
+            keepout: Some(Default::default()),
+            outline: square(0.1).to_vec(),
+            ..Default::default()
+        });
+        board
+    }
+
+    #[test]
+    fn conditions() {
+        let board = board();
+        let r1 = board.footprints.iter().find(|footprint| footprint.has_component_class("Power")).unwrap();
+        assert_eq!(r1.component_classes, ["Power", "Hot"]);
+        let other = board.footprints.iter().find(|footprint| footprint.component_classes.is_empty()).unwrap();
+        let holds = |text: &str, footprint: &Footprint| {
+            Condition::parse(text).unwrap().matches(&RuleItem::new(&board, Item::Footprint(footprint)), None).unwrap()
+        };
+
+        assert!(holds("A.Type == 'Footprint' && A.hasComponentClass('Power')", r1));
+        assert!(!holds("A.hasComponentClass('Power')", other));
+        assert!(holds("A.Component_Class == 'power,hot'", r1));
+        assert!(holds(&format!("A.memberOfSheet('/') && A.Reference == '{}'", r1.reference().unwrap()), r1));
+        assert!(holds("A.enclosedByArea('Power*')", r1));
+        assert!(!holds("A.enclosedByArea('Tiny')", r1));
+        assert!(holds("A.intersectsArea('Tiny')", r1));
+        assert!(holds("A.getField('Reference') == A.Reference", r1));
+
+        let pad = RuleItem::new(&board, Item::Pad(r1, &r1.pads[0]));
+        assert_eq!(pad.property("Type"), Some(Outcome::String("Pad".to_string())));
+        let condition = Condition::parse("A.hasComponentClass('Hot') && A.NetName != ''").unwrap();
+        assert!(condition.matches(&pad, None).unwrap());
+        assert!(Condition::parse("A.isMicroVia()").unwrap().matches(&pad, None).is_err());
+    }
+
+    #[test]
+    fn placement_areas() {
+        let area = |name: &str, placement: &str| {
+            format!(
+                "\t(zone\n\t\t(net 0)\n\t\t(net_name \"\")\n\t\t(layers \"F.Cu\" \"B.Cu\")\n\t\t(name \"{}\")\n\
+                 \t\t(keepout\n\t\t\t(tracks allowed)\n\t\t\t(footprints allowed)\n\t\t)\n\t\t{}\n\
+                 \t\t(polygon\n\t\t\t(pts\n\t\t\t\t(xy 0 0) (xy 10 0) (xy 10 10)\n\t\t\t)\n\t\t)\n\t)\n",
+                name, placement
+            )
+        };
+        let zones = [
+            area("Power", "(placement (enabled yes) (component_class \"Power\"))"),
+            area("Root", "(placement (enabled yes) (source_type sheetname) (source \"/\"))"),
+            area("Off", "(placement (enabled no) (sheetname \"/\"))"),
+        ]
+        .concat();
+        let end = DEMO.rfind(')').unwrap();
+        let src = format!("{}{})\n", &DEMO[..end], zones).replacen(
+            "\t\t(sheetname \"/\")",
+            "\t\t(sheetname \"/\")\n\t\t(component_classes\n\t\t\t(class \"Power\")\n\t\t)",
+            1,
+        );
+        let board = Board::parse(&src).unwrap();
+        let [power, root, off] = &board.zones[board.zones.len() - 3..] else { unreachable!() };
+        let placement = power.placement.as_ref().unwrap();
+        assert_eq!((placement.enabled, &placement.source), (true, &PlacementSource::ComponentClass("Power".into())));
+        assert_eq!(root.placement.as_ref().unwrap().source, PlacementSource::Sheet("/".into()));
+
+        let members: Vec<_> = board.rule_area_footprints(power).iter().filter_map(|f| f.reference()).collect();
+        assert_eq!(members.len(), 1);
+        let on_root = board.footprints.iter().filter(|footprint| footprint.sheetname.as_deref() == Some("/")).count();
+        assert_eq!(board.rule_area_footprints(root).len(), on_root);
+        assert!(board.rule_area_footprints(off).is_empty());
+        let r1 = board.footprint(members[0]).unwrap();
+        let names: Vec<_> = board.rule_areas(r1).iter().filter_map(|zone| zone.name.as_deref()).collect();
+        assert_eq!(names, ["Power", "Root"]);
+    }
+
+    #[test]
+    fn classes_round_trip() {
+        let board = board();
+        let r1 = board.footprints.iter().find(|footprint| footprint.has_component_class("Power")).unwrap();
+        let written = Footprint::parse(&r1.to_string()).unwrap();
+        assert_eq!(written.component_classes, r1.component_classes);
+    }
+}
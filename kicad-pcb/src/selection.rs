@@ -0,0 +1,942 @@
+//! Selections of board items by UUID, the common input of the bulk
+//! operations: moving, aligning, deleting, editing footprint properties and
+//! exporting a subset of the board, or cropping it to a region.
+//!
+//! A [`Selection`] holds the UUIDs of top-level items: footprints, board
+//! drawings and texts, tracks, vias and zones. UUIDs stay valid while items
+//! are added and removed, so one selection can be used for several
+//! operations in a row. Items without a UUID cannot be selected; see
+//! [`Board::assign_missing_uuids`].
+//!
+//! Each operation changes the [`Board`] and has a `write_` counterpart that
+//! makes the same change in the board file:
+//!
+//! ```no_run
+//! use kicad_common::Point;
+//! use kicad_pcb::{
+//!     index::Item,
+//!     selection::{move_selection, write_move, Selection},
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let vias = Selection::query(&board, |item| matches!(item, Item::Via(via) if via.net == 1));
+//! let offset = Point::new(0.0, 2.54);
+//! move_selection(&mut board, &vias, offset);
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_move(&vias, offset, &mut doc)?;
+//! std::fs::write("demo.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`align`], [`distribute`] and [`snap_to_grid`] move each item by its own
+//! offset and return the offsets for [`write_moves`].
+//!
+//! [`crop`] keeps the items in a rectangle or polygon, optionally with the
+//! tracks and zones crossing its boundary cut at it, for sharing part of a
+//! design or drawing a figure of it. [`write_crop`] then makes the board
+//! file match.
+
+use std::collections::{btree_set, BTreeSet};
+
+use kicad_common::{ConvertError, Effects, Error, Font, Point, Position, Uuid, UuidGenerator};
+use kicad_sexp::{
+    cst::{Document, Node},
+    number::format_float,
+};
+
+use crate::{
+    bounds::{clip_segment, footprint_shape_points, map_points, polygon_inside, shape_points, snap, Bounds},
+    index::Item,
+    layout::{filled_polygon_node, polygon_node, write_tracks, write_zones},
+    Board, Field, Footprint, Layer, Shape, Side, Track, Zone,
+};
+
+/// A set of top-level board items, by UUID.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selection {
+    uuids: BTreeSet<Uuid>,
+}
+
+/// How [`Selection::region`] picks items, as KiCad's window and crossing
+/// selections do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RegionMode {
+    /// Items whose bounding box is inside the region.
+    #[default]
+    Inside,
+    /// Items whose bounding box overlaps the region.
+    Touching,
+}
+
+/// The top-level items of `board` that have a UUID.
+fn items(board: &Board) -> impl Iterator<Item = (Uuid, Item<'_>)> {
+    let footprints = board.footprints.iter().filter_map(|f| Some((f.uuid?, Item::Footprint(f))));
+    let shapes = board.shapes.iter().filter_map(|shape| Some((shape.uuid?, Item::Shape(shape))));
+    let texts = board.texts.iter().filter_map(|text| Some((text.uuid?, Item::Text(text))));
+    let tracks = board.tracks.iter().map(|track| (track.uuid, Item::Track(track)));
+    let vias = board.vias.iter().map(|via| (via.uuid, Item::Via(via)));
+    let zones = board.zones.iter().filter_map(|zone| Some((zone.uuid?, Item::Zone(zone))));
+    footprints.chain(shapes).chain(texts).chain(tracks).chain(vias).chain(zones)
+}
+
+/// Bounds of an item. Texts count as their anchor point.
+pub(crate) fn bounds(item: Item<'_>) -> Option<Bounds> {
+    let square = |at: Point, half: f64| [Point::new(at.x - half, at.y - half), Point::new(at.x + half, at.y + half)];
+    match item {
+        Item::Footprint(footprint) => {
+            let shapes = footprint.shapes.iter().flat_map(|shape| footprint_shape_points(footprint, &shape.kind));
+            let pads = footprint.pads.iter().flat_map(|pad| {
+                square(footprint.pad_position(pad), pad.size.width.max(pad.size.height) / 2.0)
+            });
+            Bounds::of(shapes.chain(pads).chain([footprint.at.point()]))
+        },
+        Item::Shape(shape) => Bounds::of(shape_points(&shape.kind)),
+        Item::Text(text) => Bounds::of([text.at.point()]),
+        Item::Track(track) => Bounds::of([track.start, track.end].into_iter().chain(track.mid)),
+        Item::Via(via) => Bounds::of(square(via.at, via.size / 2.0)),
+        Item::Zone(zone) => Bounds::of(zone.outline.iter().copied()),
+        Item::Pad(..) | Item::FootprintShape(..) | Item::FootprintText(..) => None,
+    }
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The top-level items of `board` that `filter` accepts.
+    pub fn query(board: &Board, mut filter: impl FnMut(Item<'_>) -> bool) -> Self {
+        items(board).filter(|(_, item)| filter(*item)).map(|(uuid, _)| uuid).collect()
+    }
+
+    /// The top-level items of `board` in the rectangle from `min` to `max`.
+    /// Arcs are bounded by their three points.
+    pub fn region(board: &Board, min: Point, max: Point, mode: RegionMode) -> Self {
+        let region = Bounds { min, max };
+        let picked = |item: Item<'_>| {
+            let Some(bounds) = bounds(item) else { return false };
+            match mode {
+                RegionMode::Inside => region.contains(bounds.min) && region.contains(bounds.max),
+                RegionMode::Touching => region.overlaps(&bounds.corners()),
+            }
+        };
+        Self::query(board, picked)
+    }
+
+    /// The top-level items of `board` in `polygon`, picked as
+    /// [`Selection::region`] picks them.
+    pub fn polygon(board: &Board, polygon: &[Point], mode: RegionMode) -> Self {
+        let picked = |item: Item<'_>| {
+            let Some(bounds) = bounds(item) else { return false };
+            match mode {
+                RegionMode::Inside => polygon_inside(&bounds.corners(), polygon),
+                RegionMode::Touching => bounds.overlaps(polygon),
+            }
+        };
+        Self::query(board, picked)
+    }
+
+    /// Adds `uuid`; returns false if it was selected already.
+    pub fn insert(&mut self, uuid: Uuid) -> bool {
+        self.uuids.insert(uuid)
+    }
+
+    pub fn remove(&mut self, uuid: Uuid) -> bool {
+        self.uuids.remove(&uuid)
+    }
+
+    pub fn contains(&self, uuid: Uuid) -> bool {
+        self.uuids.contains(&uuid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.uuids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.uuids.is_empty()
+    }
+
+    pub fn iter(&self) -> btree_set::Iter<'_, Uuid> {
+        self.uuids.iter()
+    }
+
+    /// Items in either selection.
+    pub fn union(&self, other: &Selection) -> Selection {
+        self.uuids.union(&other.uuids).copied().collect()
+    }
+
+    /// Items in both selections.
+    pub fn intersection(&self, other: &Selection) -> Selection {
+        self.uuids.intersection(&other.uuids).copied().collect()
+    }
+
+    /// The selected items of `board`, in board order.
+    pub fn items<'b>(&self, board: &'b Board) -> impl Iterator<Item = Item<'b>> {
+        items(board).filter(|(uuid, _)| self.contains(*uuid)).map(|(_, item)| item)
+    }
+
+    fn has(&self, uuid: Option<Uuid>) -> bool {
+        uuid.is_some_and(|uuid| self.contains(uuid))
+    }
+}
+
+impl FromIterator<Uuid> for Selection {
+    fn from_iter<I: IntoIterator<Item = Uuid>>(uuids: I) -> Self {
+        Selection { uuids: uuids.into_iter().collect() }
+    }
+}
+
+impl Extend<Uuid> for Selection {
+    fn extend<I: IntoIterator<Item = Uuid>>(&mut self, uuids: I) {
+        self.uuids.extend(uuids);
+    }
+}
+
+impl<'s> IntoIterator for &'s Selection {
+    type Item = &'s Uuid;
+    type IntoIter = btree_set::Iter<'s, Uuid>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.uuids.iter()
+    }
+}
+
+/// Moves the selected items of `board` by `offset`, with zone fills and
+/// the graphics of footprints. Returns the number of items moved.
+pub fn move_selection(board: &mut Board, selection: &Selection, offset: Point) -> usize {
+    let shift = |p: Point| p + offset;
+    let mut moved = 0;
+    for footprint in board.footprints.iter_mut().filter(|footprint| selection.has(footprint.uuid)) {
+        footprint.at = Position::new(footprint.at.x + offset.x, footprint.at.y + offset.y, footprint.at.angle);
+        moved += 1;
+    }
+    for shape in board.shapes.iter_mut().filter(|shape| selection.has(shape.uuid)) {
+        shape.kind = map_points(&shape.kind, shift);
+        moved += 1;
+    }
+    for text in board.texts.iter_mut().filter(|text| selection.has(text.uuid)) {
+        text.at = Position::new(text.at.x + offset.x, text.at.y + offset.y, text.at.angle);
+        moved += 1;
+    }
+    for track in board.tracks.iter_mut().filter(|track| selection.contains(track.uuid)) {
+        (track.start, track.end, track.mid) = (shift(track.start), shift(track.end), track.mid.map(shift));
+        moved += 1;
+    }
+    for via in board.vias.iter_mut().filter(|via| selection.contains(via.uuid)) {
+        via.at = shift(via.at);
+        moved += 1;
+    }
+    for zone in board.zones.iter_mut().filter(|zone| selection.has(zone.uuid)) {
+        zone.outline.iter_mut().for_each(|p| *p = shift(*p));
+        for fill in &mut zone.filled_polygons {
+            fill.points.iter_mut().for_each(|p| *p = shift(*p));
+        }
+        moved += 1;
+    }
+    moved
+}
+
+/// Removes the selected items from `board`. Returns the number removed.
+pub fn delete_selection(board: &mut Board, selection: &Selection) -> usize {
+    let before = count(board);
+    board.footprints.retain(|footprint| !selection.has(footprint.uuid));
+    board.shapes.retain(|shape| !selection.has(shape.uuid));
+    board.texts.retain(|text| !selection.has(text.uuid));
+    board.tracks.retain(|track| !selection.contains(track.uuid));
+    board.vias.retain(|via| !selection.contains(via.uuid));
+    board.zones.retain(|zone| !selection.has(zone.uuid));
+    before - count(board)
+}
+
+/// Where [`align`] lines items up: at an edge or a center line of the
+/// bounds of the whole selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// On the vertical center line.
+    CenterX,
+    /// On the horizontal center line.
+    CenterY,
+}
+
+/// The axis along which [`distribute`] spaces items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+/// The selected items of `board` that have bounds, with them.
+fn selected_bounds(board: &Board, selection: &Selection) -> Vec<(Uuid, Bounds)> {
+    let selected = items(board).filter(|(uuid, _)| selection.contains(*uuid));
+    selected.filter_map(|(uuid, item)| Some((uuid, bounds(item)?))).collect()
+}
+
+/// Moves each item by its own offset, rounded to nanometers, leaving out
+/// those that stay put.
+pub(crate) fn move_each(board: &mut Board, moves: impl IntoIterator<Item = (Uuid, Point)>) -> Vec<(Uuid, Point)> {
+    let moves: Vec<(Uuid, Point)> = (moves.into_iter())
+        .map(|(uuid, offset)| (uuid, snap(offset)))
+        .filter(|(_, offset)| *offset != Point::default())
+        .collect();
+    for &(uuid, offset) in &moves {
+        move_selection(board, &[uuid].into_iter().collect(), offset);
+    }
+    moves
+}
+
+/// Moves the selected items of `board` so their bounds line up at an edge
+/// or a center line of the bounds of all of them, as KiCad's alignment
+/// commands do. Returns the offset of each item moved, for
+/// [`write_moves`].
+pub fn align(board: &mut Board, selection: &Selection, alignment: Alignment) -> Vec<(Uuid, Point)> {
+    let items = selected_bounds(board, selection);
+    let Some(all) = Bounds::of(items.iter().flat_map(|(_, bounds)| [bounds.min, bounds.max])) else {
+        return Vec::new();
+    };
+    let center = |bounds: &Bounds| Point::new((bounds.min.x + bounds.max.x) / 2.0, (bounds.min.y + bounds.max.y) / 2.0);
+    let moves = items.iter().map(|(uuid, bounds)| {
+        let offset = match alignment {
+            Alignment::Left => Point::new(all.min.x - bounds.min.x, 0.0),
+            Alignment::Right => Point::new(all.max.x - bounds.max.x, 0.0),
+            Alignment::Top => Point::new(0.0, all.min.y - bounds.min.y),
+            Alignment::Bottom => Point::new(0.0, all.max.y - bounds.max.y),
+            Alignment::CenterX => Point::new(center(&all).x - center(bounds).x, 0.0),
+            Alignment::CenterY => Point::new(0.0, center(&all).y - center(bounds).y),
+        };
+        (*uuid, offset)
+    });
+    move_each(board, moves.collect::<Vec<_>>())
+}
+
+/// Moves the selected items of `board` along `direction` so the gaps
+/// between their bounds are equal. The first and last items stay put.
+/// Returns the offset of each item moved, for [`write_moves`].
+pub fn distribute(board: &mut Board, selection: &Selection, direction: Direction) -> Vec<(Uuid, Point)> {
+    let axis = |p: Point| match direction {
+        Direction::Horizontal => p.x,
+        Direction::Vertical => p.y,
+    };
+    let mut items = selected_bounds(board, selection);
+    if items.len() < 3 {
+        return Vec::new();
+    }
+    items.sort_by(|(_, a), (_, b)| axis(a.min).total_cmp(&axis(b.min)));
+    let size = |bounds: &Bounds| axis(bounds.max) - axis(bounds.min);
+    let start = axis(items[0].1.min);
+    let end = items.iter().map(|(_, bounds)| axis(bounds.max)).fold(f64::MIN, f64::max);
+    let gap = (end - start - items.iter().map(|(_, bounds)| size(bounds)).sum::<f64>()) / (items.len() - 1) as f64;
+    let mut at = start;
+    let mut moves = Vec::new();
+    for (uuid, bounds) in &items {
+        let delta = at - axis(bounds.min);
+        moves.push((*uuid, match direction {
+            Direction::Horizontal => Point::new(delta, 0.0),
+            Direction::Vertical => Point::new(0.0, delta),
+        }));
+        at += size(bounds) + gap;
+    }
+    move_each(board, moves)
+}
+
+/// The point of an item that [`snap_to_grid`] puts on the grid: the
+/// position of footprints, texts and vias, and the first point of the
+/// others.
+fn anchor(item: Item<'_>) -> Option<Point> {
+    match item {
+        Item::Footprint(footprint) => Some(footprint.at.point()),
+        Item::Shape(shape) => shape_points(&shape.kind).first().copied(),
+        Item::Text(text) => Some(text.at.point()),
+        Item::Track(track) => Some(track.start),
+        Item::Via(via) => Some(via.at),
+        Item::Zone(zone) => zone.outline.first().copied(),
+        Item::Pad(..) | Item::FootprintShape(..) | Item::FootprintText(..) => None,
+    }
+}
+
+/// Moves the selected items of `board` so their anchors lie on a grid of
+/// `spacing` from the board origin. Returns the offset of each item moved,
+/// for [`write_moves`].
+pub fn snap_to_grid(board: &mut Board, selection: &Selection, spacing: f64) -> Vec<(Uuid, Point)> {
+    let round = |v: f64| (v / spacing).round() * spacing - v;
+    let selected = items(board).filter(|(uuid, _)| selection.contains(*uuid));
+    let moves: Vec<_> = (selected.filter_map(|(uuid, item)| Some((uuid, anchor(item)?))))
+        .map(|(uuid, at)| (uuid, Point::new(round(at.x), round(at.y))))
+        .collect();
+    move_each(board, moves)
+}
+
+pub(crate) fn count(board: &Board) -> usize {
+    let Board { footprints, shapes, texts, tracks, vias, zones, .. } = board;
+    footprints.len() + shapes.len() + texts.len() + tracks.len() + vias.len() + zones.len()
+}
+
+/// A copy of `board` with only the selected items, and the header, layers,
+/// setup and nets of the whole board.
+pub fn subset(board: &Board, selection: &Selection) -> Board {
+    let mut subset = board.clone();
+    let unselected: Selection = items(board).map(|(uuid, _)| uuid).filter(|uuid| !selection.contains(*uuid)).collect();
+    delete_selection(&mut subset, &unselected);
+    subset.shapes.retain(|shape| shape.uuid.is_some());
+    subset.texts.retain(|text| text.uuid.is_some());
+    subset.zones.retain(|zone| zone.uuid.is_some());
+    subset.footprints.retain(|footprint| footprint.uuid.is_some());
+    subset
+}
+
+/// What [`crop`] does with the items on the boundary of its region.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CropOptions {
+    /// Which items are kept whole.
+    pub mode: RegionMode,
+    /// Whether straight tracks crossing the boundary are cut at it, keeping
+    /// the parts inside. Arc tracks are kept or removed whole.
+    pub clip_tracks: bool,
+    /// Whether zones crossing the boundary are cut at it, with their fills.
+    /// Needs the `render` feature.
+    pub clip_zones: bool,
+}
+
+/// Crops `board` to the polygon `region`; a rectangle is its four corners.
+/// The items [`Selection::polygon`] picks are kept, the tracks and zones
+/// crossing the boundary cut as `options` say, and the rest removed as
+/// [`subset`] removes it. Parts beyond the first of a cut item get new
+/// UUIDs from `generator`. Returns the number of items removed.
+pub fn crop(
+    board: &mut Board,
+    region: &[Point],
+    options: &CropOptions,
+    generator: &mut UuidGenerator,
+) -> Result<usize, Error> {
+    if region.len() < 3 {
+        return Err(ConvertError::new("a crop region needs at least three points").into());
+    }
+    if options.clip_zones && !cfg!(feature = "render") {
+        return Err(ConvertError::new("clipping zones needs the `render` feature").into());
+    }
+    generator.reserve(board.uuids());
+    let mut keep = Selection::polygon(board, region, options.mode);
+    if options.clip_tracks {
+        let mut added: Vec<Track> = Vec::new();
+        for track in board.tracks.iter_mut().filter(|track| !track.is_arc()) {
+            let mut parts = clip_segment(track.start, track.end, region).into_iter();
+            let Some((start, end)) = parts.next() else {
+                keep.remove(track.uuid);
+                continue;
+            };
+            (track.start, track.end) = (start, end);
+            keep.insert(track.uuid);
+            for (start, end) in parts {
+                let uuid = generator.generate(format!("{} part {:?} {:?}", track.uuid, start, end));
+                added.push(Track { start, end, uuid, ..track.clone() });
+            }
+        }
+        keep.extend(added.iter().map(|track| track.uuid));
+        board.tracks.extend(added);
+    }
+    #[cfg(feature = "render")]
+    if options.clip_zones {
+        let mut added: Vec<Zone> = Vec::new();
+        for zone in &mut board.zones {
+            let Some(uuid) = zone.uuid else { continue };
+            let mut parts = crate::fill::clip_zone(zone, region).into_iter();
+            let Some(first) = parts.next() else {
+                keep.remove(uuid);
+                continue;
+            };
+            *zone = first;
+            keep.insert(uuid);
+            for part in parts {
+                let part_uuid = generator.generate(format!("{} part {:?}", uuid, part.outline));
+                added.push(Zone { uuid: Some(part_uuid), ..part });
+            }
+        }
+        keep.extend(added.iter().filter_map(|zone| zone.uuid));
+        board.zones.extend(added);
+    }
+    let before = count(board);
+    *board = subset(board, &keep);
+    Ok(before - count(board))
+}
+
+/// The property a new field takes: hidden on the fab layer of the
+/// footprint's side, turned with the footprint.
+fn new_field(footprint: &Footprint, key: &str, value: &str) -> Field {
+    Field {
+        key: key.to_string(),
+        value: value.to_string(),
+        at: Position::new(0.0, 0.0, footprint.at.angle),
+        layer: match footprint.side() {
+            Side::Top => Layer::FFab,
+            Side::Bottom => Layer::BFab,
+        },
+        hide: true,
+        effects: Effects {
+            font: Font { height: 1.0, width: 1.0, thickness: Some(0.15), ..Default::default() },
+            ..Default::default()
+        },
+        uuid: None,
+    }
+}
+
+/// Sets property `key` of the selected footprints to `value`, adding it
+/// where it is missing. Returns the number of footprints changed.
+pub fn set_property(board: &mut Board, selection: &Selection, key: &str, value: &str) -> usize {
+    let mut changed = 0;
+    for footprint in board.footprints.iter_mut().filter(|footprint| selection.has(footprint.uuid)) {
+        match footprint.properties.iter_mut().find(|field| field.key == key) {
+            Some(field) if field.value == value => continue,
+            Some(field) => field.value = value.to_string(),
+            None => {
+                let field = new_field(footprint, key, value);
+                footprint.properties.push(field);
+            },
+        }
+        changed += 1;
+    }
+    changed
+}
+
+/// Tags of the top-level items a selection can hold.
+fn is_item_tag(tag: &str) -> bool {
+    matches!(tag, "footprint" | "gr_text" | "segment" | "arc" | "via" | "zone") || Shape::is_shape_tag(tag)
+}
+
+pub(crate) fn node_uuid(node: &Node) -> Option<Uuid> {
+    let uuid = node.child("uuid").or_else(|| node.child("tstamp"))?;
+    uuid.items().get(1)?.value()?.parse().ok()
+}
+
+pub(crate) fn board_items<'d, 'a>(doc: &'d mut Document<'a>) -> Result<&'d mut Vec<Node<'a>>, Error> {
+    let root = doc.nodes.iter_mut().find(|node| node.tag() == Some("kicad_pcb"));
+    Ok(root.and_then(|root| root.items_mut()).ok_or_else(|| ConvertError::new("expected a (kicad_pcb ...) root"))?)
+}
+
+/// Adds `offset` to the point lists of `node`: its own `at`, `start`, `end`,
+/// `center`, `mid` and `xy` children and, except in footprints, whose
+/// contents are relative to the footprint, those of its other children.
+fn shift_node(node: &mut Node<'_>, offset: Point) {
+    let footprint = node.tag() == Some("footprint");
+    let Some(children) = node.items_mut() else { return };
+    for child in children.iter_mut().skip(1) {
+        match child.tag() {
+            Some("at" | "start" | "end" | "center" | "mid" | "xy") => {
+                let Some(items) = child.items_mut() else { continue };
+                for (item, delta) in items.iter_mut().skip(1).zip([offset.x, offset.y]) {
+                    if let Some(value) = item.value().and_then(|value| value.parse::<f64>().ok()) {
+                        item.set_atom(format_float(value + delta));
+                    }
+                }
+            },
+            Some(_) if !footprint => shift_node(child, offset),
+            _ => {},
+        }
+    }
+}
+
+/// Moves the selected items in the board `doc` by `offset`, as
+/// [`move_selection`] does. Returns the number of items moved.
+pub fn write_move(selection: &Selection, offset: Point, doc: &mut Document<'_>) -> Result<usize, Error> {
+    let mut moved = 0;
+    for item in board_items(doc)?.iter_mut() {
+        if item.tag().is_some_and(is_item_tag) && selection.has(node_uuid(item)) {
+            shift_node(item, offset);
+            moved += 1;
+        }
+    }
+    Ok(moved)
+}
+
+/// Moves items of the board `doc` by their offsets in `moves`, as returned
+/// by [`align`], [`distribute`] and [`snap_to_grid`]. Returns the number
+/// of items moved.
+pub fn write_moves(moves: &[(Uuid, Point)], doc: &mut Document<'_>) -> Result<usize, Error> {
+    let mut moved = 0;
+    for item in board_items(doc)?.iter_mut() {
+        let uuid = node_uuid(item).filter(|_| item.tag().is_some_and(is_item_tag));
+        if let Some(&(_, offset)) = uuid.and_then(|uuid| moves.iter().find(|(id, _)| *id == uuid)) {
+            shift_node(item, offset);
+            moved += 1;
+        }
+    }
+    Ok(moved)
+}
+
+/// Removes the items of the board `doc` for which `remove` is true.
+fn remove_items(doc: &mut Document<'_>, remove: impl Fn(Option<Uuid>) -> bool) -> Result<usize, Error> {
+    let items = board_items(doc)?;
+    let before = items.len();
+    items.retain(|item| !(item.tag().is_some_and(is_item_tag) && remove(node_uuid(item))));
+    Ok(before - items.len())
+}
+
+/// Removes the selected items from the board `doc`, as
+/// [`delete_selection`] does. Returns the number removed.
+pub fn write_delete(selection: &Selection, doc: &mut Document<'_>) -> Result<usize, Error> {
+    remove_items(doc, |uuid| selection.has(uuid))
+}
+
+/// Removes every item but the selected ones from the board `doc`, as
+/// [`subset`] does, leaving a board file of the selection. Returns the
+/// number of items removed.
+pub fn write_subset(selection: &Selection, doc: &mut Document<'_>) -> Result<usize, Error> {
+    remove_items(doc, |uuid| !selection.has(uuid))
+}
+
+/// Makes the board `doc` match `board` after a [`crop`] of the board it was
+/// read from: removes the items that were removed, writes the new ends of
+/// cut tracks and the new outlines and fills of cut zones, and adds the
+/// parts cut items were split into. Returns the number of items removed.
+pub fn write_crop(board: &Board, doc: &mut Document<'_>) -> Result<usize, Error> {
+    let kept: Selection = items(board).map(|(uuid, _)| uuid).collect();
+    let removed = remove_items(doc, |uuid| !kept.has(uuid))?;
+    for node in board_items(doc)?.iter_mut() {
+        let uuid = node_uuid(node);
+        match node.tag() {
+            Some("segment") => {
+                if let Some(track) = board.tracks.iter().find(|track| Some(track.uuid) == uuid) {
+                    set_point(node, "start", track.start);
+                    set_point(node, "end", track.end);
+                }
+            },
+            Some("zone") => {
+                if let Some(zone) = board.zones.iter().find(|zone| zone.uuid.is_some() && zone.uuid == uuid) {
+                    write_outline(node, zone);
+                }
+            },
+            _ => {},
+        }
+    }
+    write_tracks(board, doc)?;
+    write_zones(board, doc)?;
+    Ok(removed)
+}
+
+/// Sets the point of the `tag` child of `node` where it differs from `p`.
+pub(crate) fn set_point(node: &mut Node<'_>, tag: &str, p: Point) {
+    let Some(items) = node.child_mut(tag).and_then(|child| child.items_mut()) else { return };
+    for (item, value) in items.iter_mut().skip(1).zip([p.x, p.y]) {
+        if item.value().and_then(|text| text.parse::<f64>().ok()) != Some(value) {
+            item.set_atom(format_float(value));
+        }
+    }
+}
+
+/// Replaces the outline and fills of the zone `node` with those of `zone`
+/// where its outline differs.
+fn write_outline(node: &mut Node<'_>, zone: &Zone) {
+    let point = |xy: &Node| {
+        let value = |i: usize| xy.items().get(i)?.value()?.parse::<f64>().ok();
+        Some(Point::new(value(1)?, value(2)?))
+    };
+    let pts = node.child("polygon").and_then(|polygon| polygon.child("pts"));
+    let outline: Option<Vec<Point>> = pts.and_then(|pts| pts.items().iter().skip(1).map(point).collect());
+    if outline.as_ref() == Some(&zone.outline) {
+        return;
+    }
+    let indent = node.child("polygon").map_or_else(|| "\n\t\t".to_string(), |polygon| polygon.leading.to_string());
+    let Some(items) = node.items_mut() else { return };
+    items.retain(|item| !matches!(item.tag(), Some("polygon" | "filled_polygon")));
+    items.push(polygon_node(&zone.outline, &indent));
+    items.extend(zone.filled_polygons.iter().map(|fill| filled_polygon_node(fill, &indent)));
+}
+
+/// Writes the properties of the selected footprints of `board` into the
+/// board `doc`: values that differ are replaced and missing properties
+/// added after the last one, as [`set_property`] leaves them.
+pub fn write_properties(board: &Board, selection: &Selection, doc: &mut Document<'_>) -> Result<(), Error> {
+    let items = board_items(doc)?;
+    for footprint in board.footprints.iter().filter(|footprint| selection.has(footprint.uuid)) {
+        let node = items.iter_mut().find(|item| item.tag() == Some("footprint") && node_uuid(item) == footprint.uuid);
+        let node = node.ok_or_else(|| ConvertError::new(format!("no footprint {:?} in the board", footprint.uuid)))?;
+        let indent = match &node.leading {
+            leading if leading.contains('\n') => format!("{}\t", leading),
+            _ => "\n\t\t".to_string(),
+        };
+        let children = node.items_mut().expect("footprint is a list");
+        for field in &footprint.properties {
+            let key = |node: &Node| node.items().get(1).and_then(Node::value).is_some_and(|key| key == field.key);
+            match children.iter_mut().find(|child| child.tag() == Some("property") && key(child)) {
+                Some(existing) => {
+                    let value = existing.items_mut().and_then(|items| items.get_mut(2));
+                    if let Some(value) = value.filter(|value| value.value().as_deref() != Some(field.value.as_str())) {
+                        value.set_string(&field.value);
+                    }
+                },
+                None => {
+                    let last = children.iter().rposition(|child| child.tag() == Some("property"));
+                    let indent = last.map_or(indent.clone(), |i| children[i].leading.to_string());
+                    let at = last.map_or(children.len(), |i| i + 1);
+                    children.insert(at, property_node(field, &indent));
+                },
+            }
+        }
+    }
+    Ok(())
+}
+
+fn property_node(field: &Field, indent: &str) -> Node<'static> {
+    let inner = format!("{}\t", indent);
+    let number = |value: f64| Node::atom(format_float(value));
+    let font = &field.effects.font;
+    let mut font_children = vec![Node::tagged("size", vec![number(font.height), number(font.width)])];
+    font_children.extend(font.thickness.map(|thickness| Node::tagged("thickness", vec![number(thickness)])));
+    let mut children = vec![
+        Node::tagged("at", vec![number(field.at.x), number(field.at.y), number(field.at.angle)]),
+        Node::tagged("layer", vec![Node::string(&field.layer.to_string())]),
+    ];
+    if field.hide {
+        children.push(Node::tagged("hide", vec![Node::atom("yes")]));
+    }
+    children.extend(field.uuid.map(|uuid| Node::tagged("uuid", vec![Node::string(&uuid.to_string())])));
+    let font = Node::block("font", Vec::new(), font_children, &format!("{}\t", inner));
+    children.push(Node::block("effects", Vec::new(), vec![font], &inner));
+    Node::block("property", vec![Node::string(&field.key), Node::string(&field.value)], children, indent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{layout::ZoneSettings, FilledPolygon};
+
+    const BOARD: &str = r#"(kicad_pcb
+	(version 20241229)
+	(generator "pcbnew")
+	(layers
+		(0 "F.Cu" signal)
+		(2 "B.Cu" signal)
+		(25 "Edge.Cuts" user)
+		(49 "F.Fab" user)
+	)
+	(net 0 "")
+	(net 1 "GND")
+	(footprint "Resistor_SMD:R_0603"
+		(layer "F.Cu")
+		(uuid "00000000-0000-0000-0000-000000000001")
+		(at 10 10 90)
+		(property "Reference" "R1"
+			(at 0 -1.5 90)
+			(layer "F.SilkS")
+		)
+		(property "Value" "10k"
+			(at 0 1.5 90)
+			(layer "F.Fab")
+		)
+		(pad "1" smd rect
+			(at -0.8 0 90)
+			(size 0.8 0.9)
+			(layers "F.Cu" "F.Mask" "F.Paste")
+			(net 1 "GND")
+		)
+	)
+	(gr_line
+		(start 0 0)
+		(end 30 0)
+		(layer "Edge.Cuts")
+		(uuid "00000000-0000-0000-0000-000000000002")
+	)
+	(gr_text "TOP"
+		(at 20 5 0)
+		(layer "F.Cu")
+		(uuid "00000000-0000-0000-0000-000000000003")
+	)
+	(segment
+		(start 9.2 10)
+		(end 15 10)
+		(width 0.25)
+		(layer "F.Cu")
+		(net 1)
+		(uuid "00000000-0000-0000-0000-000000000004")
+	)
+	(via
+		(at 15 10)
+		(size 0.6)
+		(drill 0.3)
+		(layers "F.Cu" "B.Cu")
+		(net 1)
+		(uuid "00000000-0000-0000-0000-000000000005")
+	)
+)
+"#;
+
+    fn uuid(n: u8) -> Uuid {
+        format!("00000000-0000-0000-0000-{:012}", n).parse().unwrap()
+    }
+
+    #[test]
+    fn queries() {
+        let board = Board::parse(BOARD).unwrap();
+        let vias = Selection::query(&board, |item| matches!(item, Item::Via(..)));
+        assert_eq!(vias.iter().copied().collect::<Vec<_>>(), [uuid(5)]);
+
+        let inside = Selection::region(&board, Point::new(8.0, 8.0), Point::new(16.0, 12.0), RegionMode::Inside);
+        assert_eq!(inside, [1, 4, 5].map(uuid).into_iter().collect());
+        let touching = Selection::region(&board, Point::new(14.0, -1.0), Point::new(21.0, 6.0), RegionMode::Touching);
+        assert_eq!(touching, [2, 3].map(uuid).into_iter().collect());
+
+        assert_eq!(inside.union(&touching).len(), 5);
+        assert_eq!(inside.intersection(&vias), vias);
+        assert!(inside.intersection(&touching).is_empty());
+        assert_eq!(inside.items(&board).count(), 3);
+    }
+
+    #[test]
+    fn move_and_delete() {
+        let mut board = Board::parse(BOARD).unwrap();
+        let selection: Selection = [1, 2, 4, 5].map(uuid).into_iter().collect();
+        let offset = Point::new(1.0, -2.5);
+        assert_eq!(move_selection(&mut board, &selection, offset), 4);
+        assert_eq!(board.footprints[0].at, Position::new(11.0, 7.5, 90.0));
+        assert_eq!((board.tracks[0].start, board.tracks[0].end), (Point::new(10.2, 7.5), Point::new(16.0, 7.5)));
+        assert_eq!(board.vias[0].at, Point::new(16.0, 7.5));
+        assert_eq!(board.texts[0].at, Position::new(20.0, 5.0, 0.0));
+
+        let mut doc = kicad_sexp::cst::parse(BOARD).unwrap();
+        assert_eq!(write_move(&selection, offset, &mut doc).unwrap(), 4);
+        let text = doc.to_string();
+        // Pads and properties stay put in the footprint.
+        assert!(text.contains("(at 11 7.5 90)\n\t\t(property \"Reference\" \"R1\"\n\t\t\t(at 0 -1.5 90)"), "{}", text);
+        assert_eq!(Board::parse(&text).unwrap(), board);
+
+        let removed: Selection = [3, 4].map(uuid).into_iter().collect();
+        assert_eq!(delete_selection(&mut board, &removed), 2);
+        assert_eq!(write_delete(&removed, &mut doc).unwrap(), 2);
+        let text = doc.to_string();
+        assert!(!text.contains("segment") && !text.contains("gr_text"), "{}", text);
+        assert_eq!(Board::parse(&text).unwrap(), board);
+    }
+
+    #[test]
+    fn properties() {
+        let mut board = Board::parse(BOARD).unwrap();
+        let selection = Selection::query(&board, |item| matches!(item, Item::Footprint(..)));
+        assert_eq!(set_property(&mut board, &selection, "Value", "4k7"), 1);
+        assert_eq!(set_property(&mut board, &selection, "MPN", "RC0603FR-074K7L"), 1);
+        assert_eq!(set_property(&mut board, &selection, "MPN", "RC0603FR-074K7L"), 0);
+        let field = &board.footprints[0].properties[2];
+        assert_eq!((field.layer, field.hide, field.at.angle), (Layer::FFab, true, 90.0));
+
+        let mut doc = kicad_sexp::cst::parse(BOARD).unwrap();
+        write_properties(&board, &selection, &mut doc).unwrap();
+        let text = doc.to_string();
+        assert!(text.contains("\t\t(property \"Value\" \"4k7\"\n"), "{}", text);
+        assert!(text.contains("\t\t)\n\t\t(property \"MPN\" \"RC0603FR-074K7L\"\n\t\t\t(at 0 0 90)\n"), "{}", text);
+        assert_eq!(Board::parse(&text).unwrap(), board);
+    }
+
+    #[test]
+    fn crops() {
+        let mut board = Board::parse(BOARD).unwrap();
+        let outline = [(0.0, 0.0), (30.0, 0.0), (30.0, 20.0), (0.0, 20.0)].map(|(x, y)| Point::new(x, y));
+        board.add_zone("GND", Layer::FCu, &outline, &ZoneSettings::default()).unwrap();
+        board.zones[0].filled_polygons.push(FilledPolygon { layer: Layer::FCu, points: outline.to_vec() });
+        let mut generator = UuidGenerator::deterministic("crop");
+        let zone = generator.generate("zone");
+        board.zones[0].uuid = Some(zone);
+        let mut doc = kicad_sexp::cst::parse(BOARD).unwrap();
+        write_zones(&board, &mut doc).unwrap();
+        let src = doc.to_string();
+        let points = |points: &[(f64, f64)]| points.iter().map(|&(x, y)| Point::new(x, y)).collect::<Vec<_>>();
+
+        // An L with the via in the notch.
+        let region = points(&[(8.0, 8.0), (13.0, 8.0), (13.0, 11.0), (16.0, 11.0), (16.0, 12.0), (8.0, 12.0)]);
+        assert_eq!(Selection::polygon(&board, &region, RegionMode::Inside), [uuid(1)].into_iter().collect());
+        let touching = Selection::polygon(&board, &region, RegionMode::Touching);
+        assert_eq!(touching, [uuid(1), uuid(4), zone].into_iter().collect());
+
+        let mut cropped = board.clone();
+        let options = CropOptions { clip_tracks: true, ..Default::default() };
+        assert_eq!(crop(&mut cropped, &region, &options, &mut generator).unwrap(), 4);
+        assert_eq!((cropped.tracks[0].start, cropped.tracks[0].end), (Point::new(9.2, 10.0), Point::new(13.0, 10.0)));
+        assert!(cropped.vias.is_empty() && cropped.zones.is_empty() && cropped.shapes.is_empty());
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(write_crop(&cropped, &mut doc).unwrap(), 4);
+        let text = doc.to_string();
+        assert!(text.contains("\t\t(start 9.2 10)\n\t\t(end 13 10)\n"), "{}", text);
+        assert_eq!(Board::parse(&text).unwrap(), cropped);
+
+        // A U whose gap cuts the track in two.
+        let region = [(8.0, 8.0), (16.0, 8.0), (16.0, 12.0), (14.0, 12.0), (14.0, 9.0), (12.0, 9.0), (12.0, 12.0)];
+        let region = points(&[&region[..], &[(8.0, 12.0)]].concat());
+        let mut cropped = board.clone();
+        let options = CropOptions { clip_tracks: true, clip_zones: cfg!(feature = "render"), ..Default::default() };
+        let removed = if cfg!(feature = "render") { 2 } else { 3 };
+        assert_eq!(crop(&mut cropped, &region, &options, &mut generator).unwrap(), removed);
+        let ends: Vec<_> = cropped.tracks.iter().map(|track| (track.start, track.end)).collect();
+        assert_eq!(ends, [(9.2, 10.0, 12.0, 10.0), (14.0, 10.0, 15.0, 10.0)].map(|(a, b, c, d)| {
+            (Point::new(a, b), Point::new(c, d))
+        }));
+        assert_eq!(cropped.vias.len(), 1);
+        #[cfg(feature = "render")]
+        {
+            let zone = &cropped.zones[0];
+            assert_eq!((zone.outline.len(), zone.filled_polygons[0].points.len()), (8, 8));
+            assert!(zone.outline.iter().all(|p| region.contains(p)));
+        }
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(write_crop(&cropped, &mut doc).unwrap(), removed);
+        assert_eq!(Board::parse(&doc.to_string()).unwrap(), cropped);
+
+        let options = CropOptions { clip_zones: true, ..Default::default() };
+        #[cfg(not(feature = "render"))]
+        assert!(crop(&mut board.clone(), &region, &options, &mut generator).is_err());
+        assert!(crop(&mut board.clone(), &region[..2], &options, &mut generator).is_err());
+    }
+
+    #[test]
+    fn aligns() {
+        let via = |n: u8, x: f64, y: f64| {
+            let layers = "(layers \"F.Cu\" \"B.Cu\") (net 1)";
+            format!("\t(via (at {} {}) (size 1) (drill 0.5) {} (uuid \"{}\"))\n", x, y, layers, uuid(n))
+        };
+        let vias = [via(1, 1.0, 1.0), via(2, 4.0, 2.0), via(3, 10.0, 3.0)].concat();
+        let src = format!("(kicad_pcb\n\t(version 20241229)\n{})\n", vias);
+        let mut board = Board::parse(&src).unwrap();
+        let selection = Selection::query(&board, |_| true);
+        let at = |board: &Board| board.vias.iter().map(|via| (via.at.x, via.at.y)).collect::<Vec<_>>();
+
+        let moves = align(&mut board, &selection, Alignment::Left);
+        assert_eq!(moves, [(uuid(2), Point::new(-3.0, 0.0)), (uuid(3), Point::new(-9.0, 0.0))]);
+        assert_eq!(at(&board), [(1.0, 1.0), (1.0, 2.0), (1.0, 3.0)]);
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(write_moves(&moves, &mut doc).unwrap(), 2);
+        assert_eq!(Board::parse(&doc.to_string()).unwrap(), board);
+        assert!(align(&mut board, &selection, Alignment::CenterX).is_empty());
+
+        align(&mut board, &selection, Alignment::Bottom);
+        assert_eq!(at(&board), [(1.0, 3.0); 3]);
+
+        let mut board = Board::parse(&src).unwrap();
+        align(&mut board, &selection, Alignment::CenterY);
+        assert_eq!(at(&board), [(1.0, 2.0), (4.0, 2.0), (10.0, 2.0)]);
+        let moves = distribute(&mut board, &selection, Direction::Horizontal);
+        assert_eq!(moves, [(uuid(2), Point::new(1.5, 0.0))]);
+        assert_eq!(at(&board), [(1.0, 2.0), (5.5, 2.0), (10.0, 2.0)]);
+        assert!(distribute(&mut board, &[1, 2].map(uuid).into_iter().collect(), Direction::Vertical).is_empty());
+
+        let mut board = Board::parse(&src.replace("(at 4 2)", "(at 4.3 1.6)")).unwrap();
+        let moves = snap_to_grid(&mut board, &selection, 0.5);
+        assert_eq!(moves, [(uuid(2), Point::new(0.2, -0.1))]);
+        assert_eq!(at(&board)[1], (4.5, 1.5));
+    }
+
+    #[test]
+    fn subsets() {
+        let board = Board::parse(BOARD).unwrap();
+        let selection: Selection = [1, 5].map(uuid).into_iter().collect();
+        let part = subset(&board, &selection);
+        assert_eq!((part.footprints.len(), part.vias.len()), (1, 1));
+        assert!(part.shapes.is_empty() && part.texts.is_empty() && part.tracks.is_empty());
+        assert_eq!(part.nets, board.nets);
+
+        let mut doc = kicad_sexp::cst::parse(BOARD).unwrap();
+        assert_eq!(write_subset(&selection, &mut doc).unwrap(), 3);
+        assert_eq!(Board::parse(&doc.to_string()).unwrap(), part);
+    }
+}
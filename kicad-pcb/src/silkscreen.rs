@@ -0,0 +1,539 @@
+//! A silkscreen cleanup pass before fabrication: reference designators
+//! over pads moved beside their footprint, oversized text shrunk, and silk
+//! outside the board removed.
+//!
+//! [`clean_silkscreen`] measures texts as boxes around their position, 0.8
+//! of the font width per character and the font height tall. A visible
+//! reference designator on a silk layer that comes within the clearance of
+//! any pad or hole is tried above, below, left and right of its footprint's
+//! drawings and pads, in the footprint's own orientation, and hidden if
+//! none of those places is free and on the board. Silk drawings and texts
+//! wholly outside the `Edge.Cuts` outline are removed, and silk lines that
+//! cross it are cut at the edge; items without a UUID are left alone.
+//!
+//! [`write_silkscreen`] writes the changes into the board file, in KiCad
+//! 8's format:
+//!
+//! ```no_run
+//! use kicad_pcb::{
+//!     silkscreen::{clean_silkscreen, write_silkscreen, SilkOptions},
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let changes = clean_silkscreen(&mut board, &SilkOptions::default());
+//! for change in &changes {
+//!     println!("{}", change);
+//! }
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_silkscreen(&board, &changes, &mut doc)?;
+//! std::fs::write("demo.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+
+use kicad_common::{ConvertError, Error, Font, Point, Position, Uuid};
+use kicad_sexp::{
+    cst::{Document, Node},
+    number::format_float,
+};
+
+use crate::{
+    bounds::{clip_segment, shape_points, Bounds},
+    fill::{board_area, contains, Shapes},
+    selection::{board_items, node_uuid, set_point, write_delete, Selection},
+    Board, Footprint, LayerClass, ShapeKind, TextKind,
+};
+
+/// Settings of [`clean_silkscreen`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SilkOptions {
+    /// Gap kept between reference designators and the copper of pads, or
+    /// their holes.
+    pub clearance: f64,
+    /// Tallest silk text; taller text is scaled down to it.
+    pub max_text_height: f64,
+    /// Largest distance between the board outline's arcs and the segments
+    /// standing in for them.
+    pub max_error: f64,
+}
+
+impl Default for SilkOptions {
+    fn default() -> Self {
+        SilkOptions { clearance: 0.15, max_text_height: 1.5, max_error: 0.005 }
+    }
+}
+
+/// What [`clean_silkscreen`] changed. `footprint` is the reference of the
+/// footprint an item belongs to, `None` for board items.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SilkChange {
+    /// A reference designator moved off the pads, to `at` on the board.
+    Moved { reference: String, at: Point },
+    /// A reference designator hidden, with no free place beside its
+    /// footprint.
+    Hidden { reference: String },
+    /// A text scaled down to `height`.
+    Shrunk { footprint: Option<String>, text: String, height: f64 },
+    /// A drawing or text outside the board removed.
+    Removed { footprint: Option<String>, uuid: Uuid },
+    /// A line cut at the board edge.
+    Trimmed { footprint: Option<String>, uuid: Uuid },
+}
+
+impl SilkChange {
+    fn footprint(&self) -> Option<&str> {
+        match self {
+            SilkChange::Moved { reference, .. } | SilkChange::Hidden { reference } => Some(reference),
+            SilkChange::Shrunk { footprint, .. }
+            | SilkChange::Removed { footprint, .. }
+            | SilkChange::Trimmed { footprint, .. } => footprint.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for SilkChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let owner = |footprint: &Option<String>| footprint.as_ref().map(|f| format!("{}: ", f)).unwrap_or_default();
+        match self {
+            SilkChange::Moved { reference, at } => {
+                write!(f, "{}: reference moved to ({}, {})", reference, format_float(at.x), format_float(at.y))
+            },
+            SilkChange::Hidden { reference } => write!(f, "{}: reference hidden, no free place beside it", reference),
+            SilkChange::Shrunk { footprint, text, height } => {
+                write!(f, "{}text \"{}\" shrunk to {} mm", owner(footprint), text, format_float(*height))
+            },
+            SilkChange::Removed { footprint, uuid } => {
+                write!(f, "{}silk {} outside the board removed", owner(footprint), uuid)
+            },
+            SilkChange::Trimmed { footprint, uuid } => {
+                write!(f, "{}silk {} cut at the board edge", owner(footprint), uuid)
+            },
+        }
+    }
+}
+
+/// Cleans up the silkscreen of `board`: shrinks text taller than the
+/// maximum, moves or hides reference designators that collide with pads or
+/// holes, and removes or cuts silk outside the board outline. Boards
+/// without a closed outline only have their text fixed.
+pub fn clean_silkscreen(board: &mut Board, options: &SilkOptions) -> Vec<SilkChange> {
+    let mut changes = Vec::new();
+    shrink_texts(board, options.max_text_height, &mut changes);
+    let area = board_area(board, 0.0, options.max_error);
+    place_references(board, options, area.as_ref(), &mut changes);
+    if let Some(area) = &area {
+        remove_outside(board, area, &mut changes);
+    }
+    changes
+}
+
+/// Writes what [`clean_silkscreen`] changed into the board `doc`: the
+/// positions, visibility and sizes of the texts of the changed footprints
+/// and board texts, and the removed and cut drawings.
+pub fn write_silkscreen(board: &Board, changes: &[SilkChange], doc: &mut Document<'_>) -> Result<(), Error> {
+    let removed: Selection = changes
+        .iter()
+        .filter_map(|change| match change {
+            SilkChange::Removed { uuid, .. } => Some(*uuid),
+            _ => None,
+        })
+        .collect();
+    let trimmed: Vec<Uuid> = changes
+        .iter()
+        .filter_map(|change| match change {
+            SilkChange::Trimmed { uuid, .. } => Some(*uuid),
+            _ => None,
+        })
+        .collect();
+    let mut references: Vec<&str> = changes.iter().filter_map(SilkChange::footprint).collect();
+    references.sort_unstable();
+    references.dedup();
+
+    write_delete(&removed, doc)?;
+    let items = board_items(doc)?;
+    for reference in references {
+        let footprint = (board.footprint(reference))
+            .ok_or_else(|| ConvertError::new(format!("no footprint {} on the board", reference)))?;
+        let node = items.iter_mut().find(|item| item.tag() == Some("footprint") && node_uuid(item) == footprint.uuid);
+        let node = node.ok_or_else(|| ConvertError::new(format!("no footprint {:?} in the board", footprint.uuid)))?;
+        let children = node.items_mut().expect("footprint is a list");
+        let drawing = |node: &Node| node.tag().is_some_and(|tag| tag.starts_with("fp_"));
+        children.retain(|child| !(drawing(child) && node_uuid(child).is_some_and(|uuid| removed.contains(uuid))));
+        let key = |node: &Node| node.items().get(1).and_then(Node::value).map(|key| key.into_owned());
+        let mut texts = footprint.texts.iter();
+        for child in children.iter_mut() {
+            match child.tag() {
+                Some("property") => {
+                    let field = footprint.properties.iter().find(|field| Some(&field.key) == key(child).as_ref());
+                    if let Some(field) = field {
+                        write_text(child, field.at, field.hide, &field.effects.font);
+                    }
+                },
+                Some("fp_text") => {
+                    if let Some(text) = texts.next() {
+                        write_text(child, text.at, text.hide, &text.effects.font);
+                    }
+                },
+                Some("fp_line") if trimmed.contains(&node_uuid(child).unwrap_or_default()) => {
+                    let shape = footprint.shapes.iter().find(|shape| shape.uuid == node_uuid(child));
+                    if let Some(ShapeKind::Line { start, end }) = shape.map(|shape| &shape.kind) {
+                        set_point(child, "start", *start);
+                        set_point(child, "end", *end);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+    for item in items.iter_mut() {
+        let uuid = node_uuid(item);
+        match item.tag() {
+            Some("gr_text") => {
+                if let Some(text) = board.texts.iter().find(|text| text.uuid.is_some() && text.uuid == uuid) {
+                    write_text(item, text.at, text.hide, &text.effects.font);
+                }
+            },
+            Some("gr_line") if uuid.is_some_and(|uuid| trimmed.contains(&uuid)) => {
+                let shape = board.shapes.iter().find(|shape| shape.uuid == uuid);
+                if let Some(ShapeKind::Line { start, end }) = shape.map(|shape| &shape.kind) {
+                    set_point(item, "start", *start);
+                    set_point(item, "end", *end);
+                }
+            },
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+fn shrink_texts(board: &mut Board, max: f64, changes: &mut Vec<SilkChange>) {
+    let mut shrink = |footprint: Option<&str>, text: &str, font: &mut Font| {
+        if font.height <= max {
+            return;
+        }
+        let scale = max / font.height;
+        font.height = max;
+        font.width = round(font.width * scale);
+        font.thickness = font.thickness.map(|thickness| round(thickness * scale));
+        let footprint = footprint.map(str::to_string);
+        changes.push(SilkChange::Shrunk { footprint, text: text.to_string(), height: max });
+    };
+    for footprint in &mut board.footprints {
+        let reference = footprint.reference().unwrap_or_default().to_string();
+        for field in footprint.properties.iter_mut().filter(|field| is_silk(field.layer) && !field.hide) {
+            shrink(Some(&reference), &field.value, &mut field.effects.font);
+        }
+        for text in footprint.texts.iter_mut().filter(|text| is_silk(text.layer) && !text.hide) {
+            shrink(Some(&reference), &text.text, &mut text.effects.font);
+        }
+    }
+    for text in board.texts.iter_mut().filter(|text| is_silk(text.layer) && !text.hide) {
+        shrink(None, &text.text, &mut text.effects.font);
+    }
+}
+
+/// The reference designator of a footprint: its `Reference` field, or the
+/// `fp_text reference` of older files.
+struct Label {
+    at: Position,
+    hide: bool,
+    layer: crate::Layer,
+    font: Font,
+    text: String,
+}
+
+fn label(footprint: &Footprint) -> Option<Label> {
+    let reference = footprint.reference()?.to_string();
+    match footprint.properties.iter().find(|field| field.key == "Reference") {
+        Some(field) => Some(Label {
+            at: field.at,
+            hide: field.hide,
+            layer: field.layer,
+            font: field.effects.font.clone(),
+            text: reference,
+        }),
+        None => footprint.texts.iter().find(|text| text.kind == Some(TextKind::Reference)).map(|text| Label {
+            at: text.at,
+            hide: text.hide,
+            layer: text.layer,
+            font: text.effects.font.clone(),
+            text: reference,
+        }),
+    }
+}
+
+fn set_label(footprint: &mut Footprint, at: Position, hide: bool) {
+    match footprint.properties.iter_mut().find(|field| field.key == "Reference") {
+        Some(field) => (field.at, field.hide) = (at, hide),
+        None => {
+            if let Some(text) = footprint.texts.iter_mut().find(|text| text.kind == Some(TextKind::Reference)) {
+                (text.at, text.hide) = (at, hide);
+            }
+        },
+    }
+}
+
+/// Width and height of a text box, along and across the text.
+fn text_size(text: &str, font: &Font) -> (f64, f64) {
+    (0.8 * text.chars().count().max(1) as f64 * font.width, font.height)
+}
+
+/// The corners of a text box centered on `center`, turned by `angle`.
+fn text_box(center: Point, (width, height): (f64, f64), angle: f64) -> Vec<Point> {
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+    let corner = |(x, y): (f64, f64)| center + Point::new(x * width / 2.0, y * height / 2.0).rotated(angle);
+    corners.into_iter().map(corner).collect()
+}
+
+fn place_references(board: &mut Board, options: &SilkOptions, area: Option<&Shapes>, changes: &mut Vec<SilkChange>) {
+    // Pads and holes as squares grown by the clearance.
+    let copper: Vec<Bounds> = (board.footprints.iter())
+        .flat_map(|footprint| {
+            footprint.pads.iter().map(move |pad| {
+                let hole = pad.drill.map_or(0.0, |drill| drill.size.width.max(drill.size.height));
+                let half = pad.size.width.max(pad.size.height).max(hole) / 2.0 + options.clearance;
+                let at = footprint.pad_position(pad);
+                Bounds { min: Point::new(at.x - half, at.y - half), max: Point::new(at.x + half, at.y + half) }
+            })
+        })
+        .collect();
+    let free = |corners: &[Point]| {
+        let on_board = area.is_none_or(|area| {
+            corners.iter().all(|p| area.iter().any(|shape| contains(shape, [p.x, p.y])))
+        });
+        on_board && !copper.iter().any(|pad| pad.overlaps(corners))
+    };
+    for footprint in &mut board.footprints {
+        let Some(label) = label(footprint).filter(|label| is_silk(label.layer) && !label.hide) else {
+            continue;
+        };
+        let size = text_size(&label.text, &label.font);
+        if free(&text_box(footprint.to_board(label.at.point()), size, label.at.angle)) {
+            continue;
+        }
+        // Beside the footprint's drawings and pads, in its own frame.
+        let points = footprint.shapes.iter().flat_map(|shape| shape_points(&shape.kind));
+        let pads = footprint.pads.iter().flat_map(|pad| {
+            let half = pad.size.width.max(pad.size.height) / 2.0;
+            let at = pad.at.point();
+            [Point::new(at.x - half, at.y - half), Point::new(at.x + half, at.y + half)]
+        });
+        let Some(local) = Bounds::of(points.chain(pads)) else {
+            continue;
+        };
+        let turned = (label.at.angle - footprint.at.angle).rem_euclid(180.0);
+        let (across, along) = match (turned - 90.0).abs() < 45.0 {
+            true => (size.1 / 2.0, size.0 / 2.0),
+            false => (size.0 / 2.0, size.1 / 2.0),
+        };
+        let gap = options.clearance;
+        let center = Point::new((local.min.x + local.max.x) / 2.0, (local.min.y + local.max.y) / 2.0);
+        let places = [
+            Point::new(center.x, local.min.y - gap - along),
+            Point::new(center.x, local.max.y + gap + along),
+            Point::new(local.min.x - gap - across, center.y),
+            Point::new(local.max.x + gap + across, center.y),
+        ];
+        let reference = label.text.clone();
+        let place = places.into_iter().find(|at| free(&text_box(footprint.to_board(*at), size, label.at.angle)));
+        match place {
+            Some(at) => {
+                let at = Position::new(round(at.x), round(at.y), label.at.angle);
+                set_label(footprint, at, false);
+                changes.push(SilkChange::Moved { reference, at: footprint.to_board(at.point()) });
+            },
+            None => {
+                set_label(footprint, label.at, true);
+                changes.push(SilkChange::Hidden { reference });
+            },
+        }
+    }
+}
+
+fn remove_outside(board: &mut Board, area: &Shapes, changes: &mut Vec<SilkChange>) {
+    let inside = |p: Point| area.iter().any(|shape| contains(shape, [p.x, p.y]));
+    // The outer contour around `p`, for cutting lines.
+    let outline = |p: Point| -> Option<Vec<Point>> {
+        let shape = area.iter().find(|shape| contains(shape, [p.x, p.y]))?;
+        Some(shape.first()?.iter().map(|&[x, y]| Point::new(x, y)).collect())
+    };
+    let cut = |start: Point, end: Point| -> Option<(Point, Point)> {
+        let polygon = outline(if inside(start) { start } else { end })?;
+        // Lines ending on the edge come back whole.
+        match clip_segment(start, end, &polygon)[..] {
+            [(a, b)] if (snap(a), snap(b)) != (snap(start), snap(end)) => Some((snap(a), snap(b))),
+            _ => None,
+        }
+    };
+
+    let mut removed = Vec::new();
+    for shape in board.shapes.iter_mut().filter(|shape| is_silk(shape.layer) && shape.uuid.is_some()) {
+        let uuid = shape.uuid.expect("silk shapes with UUIDs");
+        let points = shape_points(&shape.kind);
+        if !points.iter().any(|p| inside(*p)) {
+            removed.push(uuid);
+            changes.push(SilkChange::Removed { footprint: None, uuid });
+        } else if let ShapeKind::Line { start, end } = &mut shape.kind
+            && !(inside(*start) && inside(*end))
+            && let Some((a, b)) = cut(*start, *end)
+        {
+            (*start, *end) = (a, b);
+            changes.push(SilkChange::Trimmed { footprint: None, uuid });
+        }
+    }
+    for text in board.texts.iter().filter(|text| is_silk(text.layer) && !inside(text.at.point())) {
+        if let Some(uuid) = text.uuid {
+            removed.push(uuid);
+            changes.push(SilkChange::Removed { footprint: None, uuid });
+        }
+    }
+    board.shapes.retain(|shape| !shape.uuid.is_some_and(|uuid| removed.contains(&uuid)));
+    board.texts.retain(|text| !text.uuid.is_some_and(|uuid| removed.contains(&uuid)));
+
+    for footprint in &mut board.footprints {
+        let reference = footprint.reference().map(str::to_string);
+        let (at, angle) = (footprint.at.point(), footprint.at.angle);
+        let to_board = |p: Point| at + p.rotated(angle);
+        let to_local = |p: Point| Point::new(p.x - at.x, p.y - at.y).rotated(-angle);
+        let mut removed = Vec::new();
+        for shape in footprint.shapes.iter_mut().filter(|shape| is_silk(shape.layer) && shape.uuid.is_some()) {
+            let uuid = shape.uuid.expect("silk shapes with UUIDs");
+            let points: Vec<Point> = shape_points(&shape.kind).into_iter().map(to_board).collect();
+            if !points.iter().any(|p| inside(*p)) {
+                removed.push(uuid);
+                changes.push(SilkChange::Removed { footprint: reference.clone(), uuid });
+            } else if let ShapeKind::Line { start, end } = &mut shape.kind
+                && !points.iter().all(|p| inside(*p))
+                && let Some((a, b)) = cut(points[0], points[1])
+            {
+                (*start, *end) = (snap(to_local(a)), snap(to_local(b)));
+                changes.push(SilkChange::Trimmed { footprint: reference.clone(), uuid });
+            }
+        }
+        footprint.shapes.retain(|shape| !shape.uuid.is_some_and(|uuid| removed.contains(&uuid)));
+    }
+}
+
+fn is_silk(layer: crate::Layer) -> bool {
+    layer.class() == Some(LayerClass::SilkS)
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1e4).round() / 1e4
+}
+
+fn snap(p: Point) -> Point {
+    Point::new(round(p.x), round(p.y))
+}
+
+/// Sets the position, visibility and font size of the text `node` where
+/// they differ from the model's.
+fn write_text(node: &mut Node<'_>, at: Position, hide: bool, font: &Font) {
+    let numbers = |node: &Node| -> Vec<f64> {
+        node.items().iter().skip(1).filter_map(|item| item.value()?.parse().ok()).collect()
+    };
+    let replace = |child: &mut Node<'_>, tag: &str, values: &[f64]| {
+        if numbers(child) != values {
+            let leading = child.leading.clone();
+            let values = values.iter().map(|value| Node::atom(format_float(*value))).collect();
+            *child = Node::tagged(tag.to_string(), values);
+            child.leading = leading;
+        }
+    };
+    if let Some(child) = node.child_mut("at") {
+        replace(child, "at", &[at.x, at.y, at.angle]);
+    }
+    if let Some(effects) = node.child_mut("effects").and_then(|effects| effects.child_mut("font")) {
+        if let Some(size) = effects.child_mut("size") {
+            replace(size, "size", &[font.height, font.width]);
+        }
+        if let (Some(thickness), Some(child)) = (font.thickness, effects.child_mut("thickness")) {
+            replace(child, "thickness", &[thickness]);
+        }
+    }
+    let children = node.items_mut().expect("texts are lists");
+    match (hide, children.iter().position(|child| child.tag() == Some("hide"))) {
+        (true, None) => {
+            let layer = children.iter().position(|child| child.tag() == Some("layer"));
+            let mut child = Node::tagged("hide", vec![Node::atom("yes")]);
+            child.leading = layer.map_or_else(|| " ".into(), |i| children[i].leading.clone());
+            children.insert(layer.map_or(children.len(), |i| i + 1), child);
+        },
+        (false, Some(i)) => {
+            children.remove(i);
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn clean() {
+        let mut board = Board::parse(DEMO).unwrap();
+        assert!(clean_silkscreen(&mut board.clone(), &SilkOptions::default()).is_empty());
+        let r1 = board.footprints.iter_mut().find(|footprint| footprint.reference() == Some("R1")).unwrap();
+        r1.properties[0].at = Position::new(0.0, 0.0, 0.0);
+        board.texts[0].effects.font.height = 3.0;
+        board.texts[0].effects.font.width = 3.0;
+        let line = |start: Point, end: Point| crate::Shape {
+            kind: ShapeKind::Line { start, end },
+            layer: crate::Layer::FSilkS,
+            stroke: Default::default(),
+            fill: false,
+            locked: false,
+            uuid: Some(kicad_common::UuidGenerator::deterministic("silk").generate(format!("{:?}", start))),
+        };
+        board.shapes.push(line(Point::new(90.0, 90.0), Point::new(95.0, 90.0)));
+        board.shapes.push(line(Point::new(125.0, 110.0), Point::new(135.0, 110.0)));
+        let uuids: Vec<Uuid> = board.shapes.iter().rev().take(2).map(|shape| shape.uuid.unwrap()).collect();
+        let (outside, crossing) = (uuids[1], uuids[0]);
+
+        let changes = clean_silkscreen(&mut board, &SilkOptions::default());
+        let messages: Vec<String> = changes.iter().map(|change| change.to_string()).collect();
+        assert_eq!(messages, [
+            "text \"demo v1.2\" shrunk to 1.5 mm".to_string(),
+            "R1: reference moved to (110, 103.62)".to_string(),
+            format!("silk {} outside the board removed", outside),
+            format!("silk {} cut at the board edge", crossing),
+        ]);
+        let r1 = board.footprint("R1").unwrap();
+        assert_eq!(r1.properties[0].at, Position::new(0.0, -1.38, 0.0));
+        assert!(!board.shapes.iter().any(|shape| shape.uuid == Some(outside)));
+        let cut = board.shapes.iter().find(|shape| shape.uuid == Some(crossing)).unwrap();
+        assert_eq!(cut.kind, ShapeKind::Line { start: Point::new(125.0, 110.0), end: Point::new(130.0, 110.0) });
+        let again = clean_silkscreen(&mut board, &SilkOptions::default());
+        assert!(again.is_empty(), "{:?}", again);
+
+        let mut hidden = Board::parse(DEMO).unwrap();
+        hidden.footprints[1].properties[0].at = Position::new(0.0, 0.0, 0.0);
+        let options = SilkOptions { clearance: 30.0, ..Default::default() };
+        let changes = clean_silkscreen(&mut hidden, &options);
+        assert!(changes.contains(&SilkChange::Hidden { reference: "R1".to_string() }), "{:?}", changes);
+        assert!(hidden.footprint("R1").unwrap().properties[0].hide);
+    }
+
+    #[test]
+    fn write_back() {
+        // R1's reference onto its pads, and the board text too tall.
+        let font = "(size 1 1)\n\t\t\t\t(thickness 0.15)\n\t\t\t)\n\t\t\t(justify left bottom)";
+        let large = "(size 2 2)\n\t\t\t\t(thickness 0.3)\n\t\t\t)\n\t\t\t(justify left bottom)";
+        let src = DEMO.replacen("(at 0 -1.43 0)", "(at 0 0 0)", 1).replacen(font, large, 1);
+        let mut board = Board::parse(&src).unwrap();
+        let changes = clean_silkscreen(&mut board, &SilkOptions::default());
+        assert_eq!(changes.len(), 2, "{:?}", changes);
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        write_silkscreen(&board, &changes, &mut doc).unwrap();
+        let written = Board::parse(&doc.to_string()).unwrap();
+        assert_eq!(written.footprints, board.footprints);
+        assert_eq!(written.texts, board.texts);
+        assert!(clean_silkscreen(&mut written.clone(), &SilkOptions::default()).is_empty());
+    }
+}
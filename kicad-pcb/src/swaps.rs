@@ -0,0 +1,386 @@
+//! Pin and gate swaps that untangle the ratsnest.
+//!
+//! A [`SwapGroup`] lists the gates of a footprint that can trade nets: the
+//! units of a quad op-amp, each a few pads whose order matches from gate to
+//! gate, or single pins such as the I/O of an FPGA bank. Groups come from
+//! symbol data, `kicad_sch::LibSymbol::gates` finds the interchangeable
+//! units of a part, or from a sidecar file in the style of a `.kicad_dru`
+//! file:
+//!
+//! ```text
+//! (version 1)
+//! (group "U1" (gate (unit 1) "1" "2" "3") (gate (unit 2) "7" "6" "5"))
+//! (group "U2" (pins "10" "11" "12" "13"))
+//! ```
+//!
+//! [`optimize_swaps`] proposes swaps, one at a time, while a swap leaves
+//! fewer crossing ratsnest lines, or as many but shorter ones. Tracks are
+//! not moved with the nets, so swap before routing. [`apply_swaps`] makes
+//! the swaps on the board and [`write_swaps`] writes the pads' new nets.
+//! Gate swaps carry the units they trade, for the schematic to follow with
+//! `kicad_sch::Hierarchy::swap_units`; pin swaps have no unit to trade, and
+//! [`check_pad_mapping`](crate::pinmap::check_pad_mapping) reports them
+//! against the netlist until the schematic's wiring is changed to match.
+//!
+//! ```no_run
+//! use kicad_pcb::{
+//!     swaps::{apply_swaps, optimize_swaps, write_swaps, SwapGroups},
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let groups = SwapGroups::parse(&std::fs::read_to_string("demo.swaps")?)?;
+//! let swaps = optimize_swaps(&board, &groups)?;
+//! for swap in &swaps {
+//!     println!("{}", swap);
+//! }
+//! apply_swaps(&mut board, &swaps)?;
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_swaps(&board, &swaps, &mut doc)?;
+//! std::fs::write("demo.kicad_pcb", doc.to_string())?;
+//! // The schematic side: `hierarchy.swap_units(&units)`.
+//! let units: Vec<(String, u32, u32)> = swaps.iter().filter_map(|swap| swap.units()).collect();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    ConvertError, Error,
+};
+use kicad_sexp::{cst::Document, SexpBuilder};
+
+use crate::{
+    bounds::{distance, segments_cross},
+    netlist::write_pad_nets,
+    route::ratsnest,
+    selection::{board_items, node_uuid},
+    Board,
+};
+
+/// Pads of a footprint that trade nets with another gate's, in the order
+/// they correspond.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gate {
+    /// The unit of the part's symbol, for gates that are units.
+    pub unit: Option<u32>,
+    pub pads: Vec<String>,
+}
+
+impl Gate {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let gate = SexpBuilder::new("gate");
+        let gate = match self.unit {
+            Some(unit) => gate.child(SexpBuilder::new("unit").int(unit.into())),
+            None => gate,
+        };
+        self.pads.iter().fold(gate, |gate, pad| gate.string(pad))
+    }
+
+    fn label(&self) -> String {
+        match self.unit {
+            Some(unit) => format!("unit {} (pads {})", unit, self.pads.join(" ")),
+            None => format!("pads {}", self.pads.join(" ")),
+        }
+    }
+}
+
+impl FromSexp for Gate {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let pads = (0..list.args().len()).filter(|&i| list.args()[i].as_str().is_some()).map(|i| list.string(i));
+        Ok(Gate { unit: list.child_number("unit")?, pads: pads.collect::<Result<_>>()? })
+    }
+}
+
+/// The gates of a footprint that can trade nets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapGroup {
+    pub reference: String,
+    pub gates: Vec<Gate>,
+}
+
+impl SwapGroup {
+    /// A group of single pins, any of which can take another's net.
+    pub fn pins(reference: &str, pads: &[&str]) -> Self {
+        let gates = pads.iter().map(|pad| Gate { unit: None, pads: vec![pad.to_string()] }).collect();
+        SwapGroup { reference: reference.to_string(), gates }
+    }
+
+    /// A group of units and their pads, as `kicad_sch::LibSymbol::gates`
+    /// gives them.
+    pub fn units(reference: &str, units: &[(u32, Vec<String>)]) -> Self {
+        let gates = units.iter().map(|(unit, pads)| Gate { unit: Some(*unit), pads: pads.clone() }).collect();
+        SwapGroup { reference: reference.to_string(), gates }
+    }
+
+    /// `(group ...)`, with the gates that are single pins in one `(pins ...)`.
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let group = SexpBuilder::new("group").string(&self.reference);
+        let (pins, gates): (Vec<&Gate>, Vec<&Gate>) =
+            self.gates.iter().partition(|gate| gate.unit.is_none() && gate.pads.len() == 1);
+        let group = match pins.is_empty() {
+            true => group,
+            false => group.child(pins.iter().fold(SexpBuilder::new("pins"), |list, gate| list.string(&gate.pads[0]))),
+        };
+        gates.into_iter().fold(group, |group, gate| group.child(gate.to_sexp()))
+    }
+}
+
+impl FromSexp for SwapGroup {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mut gates = Vec::new();
+        for child in list.lists() {
+            match child.tag() {
+                "gate" => gates.push(Gate::from_sexp(&child).map_err(|e| e.within(list.tag()))?),
+                "pins" => {
+                    for i in 0..child.args().len() {
+                        let pad = child.string(i).map_err(|e| e.within(list.tag()))?;
+                        gates.push(Gate { unit: None, pads: vec![pad] });
+                    }
+                },
+                tag => return Err(child.error(format!("unexpected ({} ...)", tag)).within(list.tag())),
+            }
+        }
+        Ok(SwapGroup { reference: list.string(0)?, gates })
+    }
+}
+
+/// A sidecar file of swap groups.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapGroups {
+    pub version: u32,
+    /// In file order.
+    pub groups: Vec<SwapGroup>,
+}
+
+impl SwapGroups {
+    /// Newest file format version the model is written against.
+    pub const NEWEST_VERSION: u32 = 1;
+
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let sexps = kicad_sexp::parse(src).map_err(Error::Parse)?;
+        let mut groups = SwapGroups::default();
+        for list in sexps.iter().filter_map(List::new) {
+            match list.tag() {
+                "version" => groups.version = list.number(0)?,
+                "group" => groups.groups.push(SwapGroup::from_sexp(&list)?),
+                tag => return Err(ConvertError::new(format!("unexpected ({} ...)", tag)).into()),
+            }
+        }
+        Ok(groups)
+    }
+}
+
+impl fmt::Display for SwapGroups {
+    /// The sidecar file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", SexpBuilder::new("version").int(self.version.into()).build())?;
+        for group in &self.groups {
+            writeln!(f, "{}", group.to_sexp().build())?;
+        }
+        Ok(())
+    }
+}
+
+/// Two gates of a footprint trading nets, pad by pad.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Swap {
+    pub reference: String,
+    /// Sheet path of the footprint's symbol.
+    pub path: Option<String>,
+    pub gates: [Gate; 2],
+}
+
+impl Swap {
+    /// The footprint's sheet path and the units traded, for
+    /// `kicad_sch::Hierarchy::swap_units`; `None` for pin swaps and
+    /// footprints without a symbol.
+    pub fn units(&self) -> Option<(String, u32, u32)> {
+        Some((self.path.clone()?, self.gates[0].unit?, self.gates[1].unit?))
+    }
+}
+
+impl fmt::Display for Swap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: swap {} with {}", self.reference, self.gates[0].label(), self.gates[1].label())
+    }
+}
+
+/// The number of pairs of ratsnest lines of different nets that cross.
+pub fn crossings(board: &Board) -> usize {
+    cost(board).0
+}
+
+/// Proposes swaps within `groups` that leave fewer ratsnest crossings on
+/// `board`, in the order to make them, choosing the best swap each time.
+/// Fails if a group names a footprint or pad the board does not have, or
+/// has gates of different sizes.
+pub fn optimize_swaps(board: &Board, groups: &SwapGroups) -> std::result::Result<Vec<Swap>, Error> {
+    for group in &groups.groups {
+        let footprint = (board.footprint(&group.reference))
+            .ok_or_else(|| ConvertError::new(format!("no footprint {} on the board", group.reference)))?;
+        for gate in &group.gates {
+            if let Some(pad) = gate.pads.iter().find(|pad| footprint.pad(pad).is_none()) {
+                return Err(ConvertError::new(format!("footprint {} has no pad {}", group.reference, pad)).into());
+            }
+            if gate.pads.len() != group.gates[0].pads.len() {
+                let message = format!("the gates of {} have different numbers of pads", group.reference);
+                return Err(ConvertError::new(message).into());
+            }
+        }
+    }
+    let mut board = board.clone();
+    let mut current = cost(&board);
+    let mut swaps = Vec::new();
+    loop {
+        let mut best: Option<(Swap, (usize, f64))> = None;
+        for group in &groups.groups {
+            let path = board.footprint(&group.reference).and_then(|footprint| footprint.path.clone());
+            for (i, a) in group.gates.iter().enumerate() {
+                for b in &group.gates[i + 1..] {
+                    let gates = [a.clone(), b.clone()];
+                    let swap = Swap { reference: group.reference.clone(), path: path.clone(), gates };
+                    let mut trial = board.clone();
+                    swap_nets(&mut trial, &swap)?;
+                    let after = cost(&trial);
+                    if better(after, best.as_ref().map_or(current, |(_, cost)| *cost)) {
+                        best = Some((swap, after));
+                    }
+                }
+            }
+        }
+        let Some((swap, after)) = best else {
+            return Ok(swaps);
+        };
+        swap_nets(&mut board, &swap)?;
+        current = after;
+        swaps.push(swap);
+    }
+}
+
+/// Makes `swaps` on `board`, in order.
+pub fn apply_swaps(board: &mut Board, swaps: &[Swap]) -> std::result::Result<(), Error> {
+    swaps.iter().try_for_each(|swap| swap_nets(board, swap))
+}
+
+/// Writes the pad nets of the footprints of `swaps` into the board `doc`,
+/// after [`apply_swaps`].
+pub fn write_swaps(board: &Board, swaps: &[Swap], doc: &mut Document<'_>) -> std::result::Result<(), Error> {
+    let items = board_items(doc)?;
+    let mut references: Vec<&str> = swaps.iter().map(|swap| swap.reference.as_str()).collect();
+    references.sort_unstable();
+    references.dedup();
+    for reference in references {
+        let footprint = (board.footprint(reference))
+            .ok_or_else(|| ConvertError::new(format!("no footprint {} on the board", reference)))?;
+        let node = items.iter_mut().find(|item| item.tag() == Some("footprint") && node_uuid(item) == footprint.uuid);
+        let node = node.ok_or_else(|| ConvertError::new(format!("no footprint {:?} in the board", footprint.uuid)))?;
+        write_pad_nets(footprint, node);
+    }
+    Ok(())
+}
+
+fn swap_nets(board: &mut Board, swap: &Swap) -> std::result::Result<(), Error> {
+    let reference = swap.reference.as_str();
+    let footprint = board.footprints.iter_mut().find(|footprint| footprint.reference() == Some(reference));
+    let footprint = footprint.ok_or_else(|| ConvertError::new(format!("no footprint {} on the board", reference)))?;
+    for (a, b) in swap.gates[0].pads.iter().zip(&swap.gates[1].pads) {
+        let net = |number: &str| footprint.pad(number).map(|pad| pad.net.clone());
+        let (Some(net_a), Some(net_b)) = (net(a), net(b)) else {
+            let missing = if footprint.pad(a).is_none() { a } else { b };
+            return Err(ConvertError::new(format!("footprint {} has no pad {}", reference, missing)).into());
+        };
+        for pad in &mut footprint.pads {
+            if pad.number == *a {
+                pad.net = net_b.clone();
+            } else if pad.number == *b {
+                pad.net = net_a.clone();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ratsnest crossings, then the ratsnest's length.
+fn cost(board: &Board) -> (usize, f64) {
+    let lines = ratsnest(board);
+    let mut crossings = 0;
+    for (i, a) in lines.iter().enumerate() {
+        let others = lines[i + 1..].iter().filter(|b| b.net != a.net);
+        crossings += others.filter(|b| segments_cross(a.from.at, a.to.at, b.from.at, b.to.at)).count();
+    }
+    (crossings, lines.iter().map(|line| distance(line.from.at, line.to.at)).sum())
+}
+
+fn better((crossings, length): (usize, f64), (than_crossings, than_length): (usize, f64)) -> bool {
+    crossings < than_crossings || (crossings == than_crossings && length < than_length - 1e-6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn unrouted() -> Board {
+        let mut board = Board::parse(DEMO).unwrap();
+        board.tracks.clear();
+        board.vias.clear();
+        board
+    }
+
+    #[test]
+    fn optimize() {
+        let mut board = unrouted();
+        // Net 1 from R1 pad 1 to C1 crosses net 2 from R2 to R1 pad 2.
+        assert_eq!(crossings(&board), 1);
+        let groups = SwapGroups { version: 1, groups: vec![SwapGroup::pins("R1", &["1", "2"])] };
+        let swaps = optimize_swaps(&board, &groups).unwrap();
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].to_string(), "R1: swap pads 1 with pads 2");
+        assert_eq!(swaps[0].units(), None);
+        apply_swaps(&mut board, &swaps).unwrap();
+        assert_eq!(crossings(&board), 0);
+        assert!(optimize_swaps(&board, &groups).unwrap().is_empty());
+
+        let mut doc = kicad_sexp::cst::parse(DEMO).unwrap();
+        write_swaps(&board, &swaps, &mut doc).unwrap();
+        let written = Board::parse(&doc.to_string()).unwrap();
+        let pads = &written.footprint("R1").unwrap().pads;
+        let nets: Vec<_> = pads.iter().map(|pad| pad.net.as_ref().unwrap().number).collect();
+        assert_eq!(nets, [2, 1]);
+
+        // As gates, the same swap trades the units for the schematic.
+        let units = SwapGroup::units("R1", &[(1, vec!["1".into()]), (2, vec!["2".into()])]);
+        let groups = SwapGroups { groups: vec![units], ..groups };
+        let swaps = optimize_swaps(&unrouted(), &groups).unwrap();
+        assert_eq!(swaps[0].to_string(), "R1: swap unit 1 (pads 1) with unit 2 (pads 2)");
+        assert_eq!(swaps[0].units(), Some(("/813a1c5e-c6c4-4caa-891f-603de6599466".to_string(), 1, 2)));
+
+        let error = |group| optimize_swaps(&board, &SwapGroups { version: 1, groups: vec![group] }).unwrap_err();
+        assert_eq!(error(SwapGroup::pins("R1", &["1", "9"])).to_string(), "footprint R1 has no pad 9");
+        assert_eq!(error(SwapGroup::pins("R9", &["1", "2"])).to_string(), "no footprint R9 on the board");
+        let uneven = SwapGroup::units("J1", &[(1, vec!["1".into()]), (2, vec!["2".into(), "3".into()])]);
+        assert_eq!(error(uneven).to_string(), "the gates of J1 have different numbers of pads");
+    }
+
+    #[test]
+    fn sidecar() {
+        let src = "(version 1)\n\
+                   (group \"U1\" (gate (unit 1) \"1\" \"2\" \"3\") (gate (unit 2) \"7\" \"6\" \"5\"))\n\
+                   (group \"U2\" (pins \"10\" \"11\"))\n";
+        let groups = SwapGroups::parse(src).unwrap();
+        assert_eq!(groups.groups[0].gates[1], Gate { unit: Some(2), pads: vec!["7".into(), "6".into(), "5".into()] });
+        assert_eq!(groups.groups[1], SwapGroup::pins("U2", &["10", "11"]));
+        let written = groups.to_string();
+        assert!(written.starts_with("(version 1)\n(group \"U1\""), "{}", written);
+        assert_eq!(SwapGroups::parse(&written).unwrap(), groups);
+        assert!(SwapGroups::parse("(group \"U1\" (swap \"1\"))").is_err());
+    }
+}
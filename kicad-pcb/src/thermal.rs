@@ -0,0 +1,283 @@
+//! Via arrays under the exposed pads of QFN, DFN and power packages, to
+//! carry their heat to the other copper layers.
+//!
+//! Each [`ThermalVias`] names one footprint and the pad to fill. The vias
+//! are laid out on a square grid aligned with the pad and centered on it,
+//! as many as fit with their copper inside the pad. Trapezoid and custom
+//! pads are filled as their bounding rectangle.
+//!
+//! ```no_run
+//! use kicad_common::UuidGenerator;
+//! use kicad_pcb::{
+//!     thermal::{thermal_vias, write_thermal_vias, ThermalVias},
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("regulator.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let arrays = [ThermalVias { pitch: 1.2, ..ThermalVias::new("U1") }];
+//! let report = thermal_vias(&mut board, &arrays, &mut UuidGenerator::deterministic("regulator"))?;
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_thermal_vias(&report, &mut doc)?;
+//! std::fs::write("regulator.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{ConvertError, Error, Point, UuidGenerator};
+use kicad_sexp::cst::Document;
+
+use crate::{
+    bounds::distance,
+    layout::write_tracks_and_vias,
+    route::pad_outline,
+    Board, Layer, Pad, PadKind, Via, ViaKind, ViaProtection,
+};
+
+/// A via array under one pad.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThermalVias {
+    /// Reference of the footprint, e.g. `U1`.
+    pub footprint: String,
+    /// Number of the pad; `None` picks the footprint's largest SMD pad.
+    pub pad: Option<String>,
+    /// Distance between neighbouring vias.
+    pub pitch: f64,
+    pub via_size: f64,
+    pub via_drill: f64,
+    /// Space between the copper of the vias and the pad edge.
+    pub margin: f64,
+    /// Name of the vias' net; `None` takes the pad's.
+    pub net: Option<String>,
+}
+
+impl ThermalVias {
+    /// 0.6/0.3 mm vias at a 1 mm pitch under the largest SMD pad of
+    /// `footprint`, on the pad's net.
+    pub fn new(footprint: &str) -> Self {
+        ThermalVias {
+            footprint: footprint.to_string(),
+            pad: None,
+            pitch: 1.0,
+            via_size: 0.6,
+            via_drill: 0.3,
+            margin: 0.1,
+            net: None,
+        }
+    }
+}
+
+/// What [`thermal_vias`] added to the board.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ThermalReport {
+    pub vias: Vec<Via>,
+    /// Places of the arrays left out because a via is there already.
+    pub skipped: usize,
+}
+
+fn error(array: &ThermalVias, message: String) -> Error {
+    ConvertError::new(format!("thermal vias of {}: {}", array.footprint, message)).into()
+}
+
+/// Fills the pads named by `arrays` with through vias, adding them to
+/// `board`. Places where a via of the board already is are skipped, so a
+/// second run adds nothing. New vias get UUIDs from `generator`.
+///
+/// Fails without changing the board if a footprint, pad or net is not
+/// found, a pad has no net and none is given, or the pitch or via size is
+/// not a positive number.
+pub fn thermal_vias(
+    board: &mut Board,
+    arrays: &[ThermalVias],
+    generator: &mut UuidGenerator,
+) -> Result<ThermalReport, Error> {
+    let layers = board.copper_layers();
+    let span: Vec<Layer> = [layers.first(), layers.last()].into_iter().flatten().copied().collect();
+    let mut places: Vec<(Point, &ThermalVias, u32)> = Vec::new();
+    for array in arrays {
+        if !(array.pitch.is_finite() && array.pitch > 0.0 && array.via_size.is_finite() && array.via_size > 0.0) {
+            return Err(error(array, "pitch and via size must be positive".to_string()));
+        }
+        let footprint = board.footprint(&array.footprint).ok_or_else(|| error(array, "no such footprint".into()))?;
+        let pad = match &array.pad {
+            Some(number) => footprint.pads.iter().find(|pad| pad.number == *number),
+            None => footprint
+                .pads
+                .iter()
+                .filter(|pad| pad.kind == PadKind::Smd)
+                .max_by(|a, b| area(a).total_cmp(&area(b))),
+        };
+        let pad = pad.ok_or_else(|| error(array, format!("no pad {}", array.pad.as_deref().unwrap_or("to fill"))))?;
+        let net = match &array.net {
+            Some(name) => board.net_by_name(name).map(|net| net.number),
+            None => pad.net.as_ref().map(|net| net.number),
+        };
+        let net = net.ok_or_else(|| match &array.net {
+            Some(name) => error(array, format!("no net {}", name)),
+            None => error(array, format!("pad {} has no net", pad.number)),
+        })?;
+
+        let outline = pad_outline(footprint, pad);
+        let center = footprint.pad_position(pad);
+        let reach = array.via_size / 2.0 + array.margin;
+        let count = |extent: f64| ((extent - 2.0 * reach) / array.pitch + 1e-9).floor() as i64 + 1;
+        let (columns, rows) = (count(pad.size.width), count(pad.size.height));
+        for row in 0..rows.max(0) {
+            for column in 0..columns.max(0) {
+                let local = Point::new(
+                    (column as f64 - (columns - 1) as f64 / 2.0) * array.pitch,
+                    (row as f64 - (rows - 1) as f64 / 2.0) * array.pitch,
+                );
+                let at = center + local.rotated(pad.at.angle);
+                if outline.distance(at) <= -reach + 1e-9 {
+                    places.push((at, array, net));
+                }
+            }
+        }
+    }
+
+    generator.reserve(board.uuids());
+    let mut report = ThermalReport::default();
+    for (at, array, net) in places {
+        let taken = board.vias.iter().any(|via| distance(via.at, at) < (via.size + array.via_size) / 2.0);
+        if taken {
+            report.skipped += 1;
+            continue;
+        }
+        let via = Via {
+            kind: ViaKind::Through,
+            at,
+            size: array.via_size,
+            drill: array.via_drill,
+            layers: span.clone(),
+            net,
+            locked: false,
+            uuid: generator.generate(format!("thermal via {:?}", at)),
+            protection: ViaProtection::default(),
+        };
+        board.vias.push(via.clone());
+        report.vias.push(via);
+    }
+    Ok(report)
+}
+
+fn area(pad: &Pad) -> f64 {
+    pad.size.width * pad.size.height
+}
+
+/// Adds the vias of `report` to the board in `doc`, after its last track or
+/// via, or else before its zones.
+pub fn write_thermal_vias(report: &ThermalReport, doc: &mut Document<'_>) -> Result<(), Error> {
+    write_tracks_and_vias(&[], &report.vias, doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD: &str = r#"(kicad_pcb
+	(version 20241229)
+	(generator "pcbnew")
+	(layers
+		(0 "F.Cu" signal)
+		(2 "B.Cu" signal)
+		(25 "Edge.Cuts" user)
+	)
+	(net 0 "")
+	(net 1 "GND")
+	(net 2 "VOUT")
+	(footprint "Package_DFN_QFN:QFN-16"
+		(layer "F.Cu")
+		(at 10 10 90)
+		(property "Reference" "U1"
+			(at 0 -3 90)
+			(layer "F.SilkS")
+		)
+		(pad "1" smd roundrect
+			(at -1.5 -0.75 90)
+			(size 0.8 0.25)
+			(layers "F.Cu" "F.Mask" "F.Paste")
+			(roundrect_rratio 0.25)
+			(net 2 "VOUT")
+		)
+		(pad "17" smd rect
+			(at 0 0.5 90)
+			(size 2.7 1.7)
+			(layers "F.Cu" "F.Mask")
+			(net 1 "GND")
+		)
+		(pad "18" smd circle
+			(at 3 0 90)
+			(size 1.5 1.5)
+			(layers "F.Cu" "F.Mask")
+		)
+	)
+)
+"#;
+
+    fn at(report: &ThermalReport) -> Vec<(f64, f64)> {
+        let round = |v: f64| (v * 1e6).round() / 1e6;
+        report.vias.iter().map(|via| (round(via.at.x), round(via.at.y))).collect()
+    }
+
+    #[test]
+    fn exposed_pad() {
+        let mut board = Board::parse(BOARD).unwrap();
+        let mut generator = UuidGenerator::deterministic("qfn");
+        let report = thermal_vias(&mut board, &[ThermalVias::new("U1")], &mut generator).unwrap();
+        // Two columns across 2.7 mm and one row across 1.7 mm, turned with
+        // the footprint: the pad center is at (10.5, 10).
+        assert_eq!(at(&report), [(10.5, 10.5), (10.5, 9.5)]);
+        assert!(report.vias.iter().all(|via| via.net == 1 && via.layers == [Layer::FCu, Layer::BCu]));
+        assert_eq!(board.vias.len(), 2);
+
+        let again = thermal_vias(&mut board, &[ThermalVias::new("U1")], &mut generator).unwrap();
+        assert_eq!((again.vias.len(), again.skipped), (0, 2));
+
+        let mut board = Board::parse(BOARD).unwrap();
+        let dense = ThermalVias { pitch: 0.5, via_size: 0.4, via_drill: 0.2, margin: 0.0, ..ThermalVias::new("U1") };
+        let report = thermal_vias(&mut board, &[dense], &mut generator).unwrap();
+        assert_eq!(report.vias.len(), 5 * 3);
+
+        let circle = ThermalVias { pad: Some("18".into()), net: Some("GND".into()), ..ThermalVias::new("U1") };
+        let report = thermal_vias(&mut Board::parse(BOARD).unwrap(), &[circle], &mut generator).unwrap();
+        assert_eq!(at(&report), [(10.0, 7.0)]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut board = Board::parse(BOARD).unwrap();
+        let mut generator = UuidGenerator::deterministic("qfn");
+        let arrays = [
+            (ThermalVias::new("U2"), "thermal vias of U2: no such footprint"),
+            (ThermalVias { pad: Some("99".into()), ..ThermalVias::new("U1") }, "no pad 99"),
+            (ThermalVias { pad: Some("18".into()), ..ThermalVias::new("U1") }, "pad 18 has no net"),
+            (ThermalVias { net: Some("VCC".into()), ..ThermalVias::new("U1") }, "no net VCC"),
+            (ThermalVias { pitch: 0.0, ..ThermalVias::new("U1") }, "pitch and via size must be positive"),
+        ];
+        for (array, message) in arrays {
+            let error = thermal_vias(&mut board, &[ThermalVias::new("U1"), array], &mut generator).unwrap_err();
+            assert!(error.to_string().contains(message), "{}", error);
+        }
+        assert!(board.vias.is_empty());
+    }
+
+    #[test]
+    fn write_back() {
+        let mut board = Board::parse(BOARD).unwrap();
+        let report = thermal_vias(&mut board, &[ThermalVias::new("U1")], &mut UuidGenerator::deterministic("qfn"));
+        let report = report.unwrap();
+        let mut doc = kicad_sexp::cst::parse(BOARD).unwrap();
+        write_thermal_vias(&report, &mut doc).unwrap();
+
+        let text = doc.to_string();
+        let via = format!(
+            concat!(
+                "\t)\n\t(via\n\t\t(at 10.5 10.5)\n\t\t(size 0.6)\n\t\t(drill 0.3)\n\t\t(layers \"F.Cu\" \"B.Cu\")\n",
+                "\t\t(net 1)\n\t\t(uuid \"{}\")\n\t)\n",
+            ),
+            report.vias[0].uuid,
+        );
+        assert!(text.contains(&via), "{}", text);
+        assert_eq!(Board::parse(&text).unwrap().vias, board.vias);
+    }
+}
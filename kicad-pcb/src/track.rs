@@ -0,0 +1,244 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    uuid::item_uuid,
+    Point, Uuid,
+};
+
+use crate::{
+    layer::{child_layer, layer_arg},
+    Layer, Setup, Side,
+};
+
+fn required_uuid(list: &List) -> Result<Uuid> {
+    item_uuid(list)?.ok_or_else(|| list.error("missing (uuid ...)"))
+}
+
+/// A routed copper `segment`, or an `arc` if `mid` is set.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Track {
+    pub start: Point,
+    pub mid: Option<Point>,
+    pub end: Point,
+    pub width: f64,
+    pub layer: Layer,
+    /// Net number, see [`crate::Board::net`].
+    pub net: u32,
+    pub locked: bool,
+    pub uuid: Uuid,
+}
+
+impl Track {
+    pub fn is_arc(&self) -> bool {
+        self.mid.is_some()
+    }
+
+    /// Length along the track, around the circle for arcs.
+    pub fn length(&self) -> f64 {
+        let (a, c) = (self.start, self.end);
+        let Some(b) = self.mid else {
+            return (c.x - a.x).hypot(c.y - a.y);
+        };
+        let (ba, bc) = (Point::new(a.x - b.x, a.y - b.y), Point::new(c.x - b.x, c.y - b.y));
+        let cross = ba.x * bc.y - ba.y * bc.x;
+        if cross.abs() < 1e-12 {
+            return ba.x.hypot(ba.y) + bc.x.hypot(bc.y);
+        }
+        // The angle at `mid` is half the central angle of the arc it does not
+        // lie on.
+        let inscribed = cross.abs().atan2(ba.x * bc.x + ba.y * bc.y);
+        let radius = (c.x - a.x).hypot(c.y - a.y) / (2.0 * inscribed.sin());
+        radius * (2.0 * std::f64::consts::PI - 2.0 * inscribed)
+    }
+}
+
+impl FromSexp for Track {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mid = match list.tag() {
+            "arc" => Some(list.parse_required("mid")?),
+            _ => None,
+        };
+        Ok(Track {
+            start: list.parse_required("start")?,
+            mid,
+            end: list.parse_required("end")?,
+            width: list.child_number("width")?.ok_or_else(|| list.error("missing (width ...)"))?,
+            layer: child_layer(list)?,
+            net: list.child_number("net")?.unwrap_or_default(),
+            locked: list.flag("locked")?,
+            uuid: required_uuid(list)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Via {
+    pub kind: ViaKind,
+    pub at: Point,
+    /// Pad diameter.
+    pub size: f64,
+    pub drill: f64,
+    /// The two copper layers the via spans, `F.Cu` and `B.Cu` for through
+    /// vias.
+    pub layers: Vec<Layer>,
+    pub net: u32,
+    pub locked: bool,
+    pub uuid: Uuid,
+    pub protection: ViaProtection,
+}
+
+/// How far a via reaches through the board.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ViaKind {
+    /// From the front copper layer to the back one.
+    #[default]
+    Through,
+    /// Between any two copper layers other than both outer ones: blind when
+    /// it reaches an outer layer, buried when it does not.
+    BlindBuried,
+    /// A laser-drilled via between neighbouring copper layers.
+    Micro,
+}
+
+impl Via {
+    /// The outer layers of the via's span, front first, or `None` when it
+    /// does not list two layers.
+    pub fn span(&self) -> Option<(Layer, Layer)> {
+        match self.layers[..] {
+            [a, b] => Some((a.min(b), a.max(b))),
+            _ => None,
+        }
+    }
+
+    /// Whether the via has copper on `layer`.
+    pub fn spans(&self, layer: Layer) -> bool {
+        self.span().is_some_and(|(front, back)| (front..=back).contains(&layer))
+    }
+
+    /// Whether solder mask covers the via on `side`, by its own setting,
+    /// else the board's, else KiCad's default of tented.
+    pub fn is_tented(&self, side: Side, setup: &Setup) -> bool {
+        self.protection.tenting.get(side).or(setup.via_protection.tenting.get(side)).unwrap_or(true)
+    }
+
+    /// Whether the hole is plugged from `side`, by the via's own setting or
+    /// else the board's.
+    pub fn is_plugged(&self, side: Side, setup: &Setup) -> bool {
+        self.protection.plugging.get(side).or(setup.via_protection.plugging.get(side)).unwrap_or(false)
+    }
+
+    /// Whether a covering is applied over the via on `side`.
+    pub fn is_covered(&self, side: Side, setup: &Setup) -> bool {
+        self.protection.covering.get(side).or(setup.via_protection.covering.get(side)).unwrap_or(false)
+    }
+
+    pub fn is_filled(&self, setup: &Setup) -> bool {
+        self.protection.filling.or(setup.via_protection.filling).unwrap_or(false)
+    }
+
+    pub fn is_capped(&self, setup: &Setup) -> bool {
+        self.protection.capping.or(setup.via_protection.capping).unwrap_or(false)
+    }
+
+    /// The solder mask layers with an opening over the via, for mask
+    /// exports.
+    pub fn mask_openings(&self, setup: &Setup) -> Vec<Layer> {
+        let sides = [(Side::Top, Layer::FMask), (Side::Bottom, Layer::BMask)];
+        sides.into_iter().filter(|&(side, _)| !self.is_tented(side, setup)).map(|(_, layer)| layer).collect()
+    }
+}
+
+/// `(name yes|no)`, with `(name none)` for a setting left to the board.
+fn opt_setting(list: &List, name: &str) -> Result<Option<bool>> {
+    match list.child(name) {
+        Some(child) if child.symbol(0).ok() == Some("none") => Ok(None),
+        _ => list.opt_flag(name),
+    }
+}
+
+/// A setting of a via for its front and back, each `None` where it is left
+/// to the board.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ViaSides {
+    pub front: Option<bool>,
+    pub back: Option<bool>,
+}
+
+impl ViaSides {
+    pub fn get(&self, side: Side) -> Option<bool> {
+        match side {
+            Side::Top => self.front,
+            Side::Bottom => self.back,
+        }
+    }
+}
+
+impl FromSexp for ViaSides {
+    fn from_sexp(list: &List) -> Result<Self> {
+        // Older files list the sides that are on, `(tenting front back)` or
+        // `(tenting none)`; KiCad 9 writes `(front yes|no|none)` for each.
+        if ["front", "back", "none"].iter().any(|name| list.has_symbol(name)) {
+            return Ok(ViaSides { front: Some(list.has_symbol("front")), back: Some(list.has_symbol("back")) });
+        }
+        Ok(ViaSides { front: opt_setting(list, "front")?, back: opt_setting(list, "back")? })
+    }
+}
+
+/// How a via's hole is protected, in the IPC-4761 terms KiCad 9 uses: the
+/// per-via settings of a `via`, or the board's defaults in the `setup`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ViaProtection {
+    /// Solder mask over the via.
+    pub tenting: ViaSides,
+    pub covering: ViaSides,
+    pub plugging: ViaSides,
+    pub capping: Option<bool>,
+    pub filling: Option<bool>,
+}
+
+impl ViaProtection {
+    pub(crate) fn from_parent(list: &List) -> Result<Self> {
+        let sides = |tag: &str| -> Result<ViaSides> { Ok(list.parse(tag)?.unwrap_or_default()) };
+        Ok(ViaProtection {
+            tenting: sides("tenting")?,
+            covering: sides("covering")?,
+            plugging: sides("plugging")?,
+            capping: opt_setting(list, "capping")?,
+            filling: opt_setting(list, "filling")?,
+        })
+    }
+}
+
+impl FromSexp for Via {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let layers = match list.child("layers") {
+            Some(layers) => (0..layers.args().len())
+                .map(|i| layer_arg(&layers, i))
+                .collect::<Result<_>>()
+                .map_err(|e| e.within(list.tag()))?,
+            None => vec![Layer::FCu, Layer::BCu],
+        };
+        let kind = if list.has_symbol("micro") {
+            ViaKind::Micro
+        } else if list.has_symbol("blind") {
+            ViaKind::BlindBuried
+        } else {
+            ViaKind::Through
+        };
+        Ok(Via {
+            kind,
+            at: list.parse_required("at")?,
+            size: list.child_number("size")?.ok_or_else(|| list.error("missing (size ...)"))?,
+            drill: list.child_number("drill")?.ok_or_else(|| list.error("missing (drill ...)"))?,
+            layers,
+            net: list.child_number("net")?.unwrap_or_default(),
+            locked: list.flag("locked")?,
+            uuid: required_uuid(list)?,
+            protection: ViaProtection::from_parent(list)?,
+        })
+    }
+}
@@ -0,0 +1,334 @@
+//! Length tuning: the meanders a board's tracks already have, and new
+//! serpentines that bring a net to a target length.
+//!
+//! [`meanders`] follows each net's tracks end to end on a layer and finds
+//! the runs that zigzag across a straight line: legs across it in
+//! alternating directions, at least four, joined by stretches along it,
+//! with arcs allowed at the corners. Each [`Meander`] tells the length it
+//! adds over the straight line it spans, so a net's routed length can be
+//! split into its path and its tuning.
+//!
+//! [`tune_segment`] replaces one straight track with a serpentine of square
+//! bumps to one side of it, as few as the maximum amplitude allows, and
+//! keeps it if its copper clears the other nets, trying the other side when
+//! it does not. The board file is updated by deleting the old track and
+//! adding the new ones:
+//!
+//! ```no_run
+//! use kicad_common::UuidGenerator;
+//! use kicad_pcb::{
+//!     layout::write_tracks,
+//!     selection::{write_delete, Selection},
+//!     tuning::{tune_segment, MeanderOptions},
+//!     Board,
+//! };
+//!
+//! let src = std::fs::read_to_string("demo.kicad_pcb")?;
+//! let mut board = Board::parse(&src)?;
+//! let segment = board.tracks[2].uuid;
+//! let mut generator = UuidGenerator::deterministic("tuning");
+//! tune_segment(&mut board, segment, 20.0, &MeanderOptions::default(), &mut generator)?;
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_delete(&[segment].into_iter().collect::<Selection>(), &mut doc)?;
+//! write_tracks(&board, &mut doc)?;
+//! std::fs::write("demo.kicad_pcb", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashMap;
+
+use kicad_common::{ConvertError, Error, Point, Uuid, UuidGenerator};
+use kicad_sexp::number::format_float;
+
+use crate::{
+    bounds::{distance, snap},
+    Board, Layer, Track,
+};
+
+/// Legs across the line a meander follows that it needs at least, two
+/// bumps' worth, so that a single detour around an obstacle is not one.
+pub const MIN_LEGS: usize = 4;
+
+/// A run of tracks that zigzags across a straight line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Meander {
+    pub net: u32,
+    pub layer: Layer,
+    /// The tracks of the run, in path order.
+    pub tracks: Vec<Uuid>,
+    /// Length along the tracks.
+    pub length: f64,
+    /// Length of the straight line the run spans.
+    pub baseline: f64,
+    /// Longest leg across the line.
+    pub amplitude: f64,
+}
+
+impl Meander {
+    /// The length the meander adds over its baseline.
+    pub fn added_length(&self) -> f64 {
+        self.length - self.baseline
+    }
+}
+
+/// The meanders of `board`, by net and layer, in path order.
+pub fn meanders(board: &Board) -> Vec<Meander> {
+    let mut found = Vec::new();
+    for chain in chains(board) {
+        let mut start = 0;
+        while start < chain.len() {
+            match meander_at(&chain, start) {
+                Some((end, meander)) => {
+                    found.push(meander);
+                    start = end;
+                },
+                None => start += 1,
+            }
+        }
+    }
+    found
+}
+
+/// Settings of [`tune_segment`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeanderOptions {
+    /// Distance between neighbouring legs, and the straight lead at each
+    /// end of the segment.
+    pub spacing: f64,
+    /// Largest distance a bump reaches from the segment.
+    pub max_amplitude: f64,
+    /// Gap kept from the copper of other nets.
+    pub clearance: f64,
+}
+
+impl Default for MeanderOptions {
+    fn default() -> Self {
+        MeanderOptions { spacing: 0.6, max_amplitude: 1.0, clearance: 0.2 }
+    }
+}
+
+/// Replaces the straight track `uuid` with a serpentine that brings the
+/// total length of its net's tracks to `target` millimeters, and returns
+/// the new tracks' UUIDs, from `generator`, in path order. Fails, leaving
+/// the board as it was, if the net is already that long, if the segment
+/// has too little room for the bumps, or if neither side of it clears the
+/// other nets.
+pub fn tune_segment(
+    board: &mut Board,
+    uuid: Uuid,
+    target: f64,
+    options: &MeanderOptions,
+    generator: &mut UuidGenerator,
+) -> Result<Vec<Uuid>, Error> {
+    let index = board.tracks.iter().position(|track| track.uuid == uuid);
+    let index = index.ok_or_else(|| ConvertError::new(format!("no track {} on the board", uuid)))?;
+    let segment = board.tracks[index].clone();
+    if segment.is_arc() {
+        return Err(ConvertError::new(format!("track {} is an arc", uuid)).into());
+    }
+    if options.spacing <= segment.width {
+        let message = format!("meander spacing {} is not wider than the track", format_float(options.spacing));
+        return Err(ConvertError::new(message).into());
+    }
+    let current: f64 = board.tracks.iter().filter(|track| track.net == segment.net).map(Track::length).sum();
+    let extra = target - current;
+    if extra <= 0.0 {
+        let message = format!("the net is {} mm long, not shorter than {} mm", round(current), target);
+        return Err(ConvertError::new(message).into());
+    }
+    // Each bump takes up twice the spacing along the segment and adds twice
+    // its amplitude.
+    let room = segment.length() - 2.0 * options.spacing;
+    let bumps = (extra / (2.0 * options.max_amplitude)).ceil().max(1.0);
+    if bumps * 2.0 * options.spacing > room + 1e-9 {
+        let most = (room / (2.0 * options.spacing)).floor().max(0.0) * 2.0 * options.max_amplitude;
+        let message = format!("track {} has room for {} mm of tuning, not {} mm", uuid, round(most), round(extra));
+        return Err(ConvertError::new(message).into());
+    }
+    let amplitude = extra / (2.0 * bumps);
+
+    generator.reserve(board.uuids());
+    board.tracks.remove(index);
+    let mut failure = None;
+    for side in [1.0, -1.0] {
+        let points = serpentine(&segment, bumps as usize, amplitude * side, options.spacing);
+        let tracks: Vec<Track> = points
+            .windows(2)
+            .map(|ends| Track {
+                start: ends[0],
+                end: ends[1],
+                uuid: generator.generate(format!("tuning {} {:?} {:?}", uuid, ends[0], ends[1])),
+                ..segment.clone()
+            })
+            .collect();
+        let uuids: Vec<Uuid> = tracks.iter().map(|track| track.uuid).collect();
+        board.tracks.splice(index..index, tracks);
+        match uuids.iter().try_for_each(|&uuid| board.check_clearance(uuid, options.clearance)) {
+            Ok(()) => return Ok(uuids),
+            Err(error) => failure = Some(error),
+        }
+        board.tracks.drain(index..index + uuids.len());
+    }
+    board.tracks.insert(index, segment);
+    Err(failure.expect("both sides were tried"))
+}
+
+/// The corners of `bumps` square bumps of `amplitude` to the left of the
+/// segment, negative for the right, after a lead of `spacing`.
+fn serpentine(segment: &Track, bumps: usize, amplitude: f64, spacing: f64) -> Vec<Point> {
+    let length = segment.length();
+    let along = Point::new((segment.end.x - segment.start.x) / length, (segment.end.y - segment.start.y) / length);
+    let across = Point::new(along.y * amplitude, -along.x * amplitude);
+    let at = |distance: f64| Point::new(segment.start.x + along.x * distance, segment.start.y + along.y * distance);
+    let mut points = vec![segment.start];
+    for bump in 0..bumps {
+        let (rise, fall) = (at(spacing * (1.0 + 2.0 * bump as f64)), at(spacing * (2.0 + 2.0 * bump as f64)));
+        points.extend([rise, rise + across, fall + across, fall]);
+    }
+    points.push(segment.end);
+    points.into_iter().map(snap).collect()
+}
+
+fn round(value: f64) -> String {
+    format_float((value * 1000.0).round() / 1000.0)
+}
+
+/// A track walked from `from` to `to`.
+struct Step<'b> {
+    track: &'b Track,
+    from: Point,
+    to: Point,
+}
+
+/// The tracks of each net and layer as paths, joined where exactly two
+/// tracks meet; branches end a path.
+fn chains(board: &Board) -> Vec<Vec<Step<'_>>> {
+    let key = |p: Point| ((p.x * 1e6).round() as i64, (p.y * 1e6).round() as i64);
+    let mut ends: HashMap<(u32, Layer, (i64, i64)), Vec<usize>> = HashMap::new();
+    for (i, track) in board.tracks.iter().enumerate() {
+        for p in [track.start, track.end] {
+            ends.entry((track.net, track.layer, key(p))).or_default().push(i);
+        }
+    }
+    let degree = |track: &Track, p: Point| ends[&(track.net, track.layer, key(p))].len();
+    let mut used = vec![false; board.tracks.len()];
+    let mut chains = Vec::new();
+    // Paths from their ends first, then the closed loops that are left.
+    let starts = (0..board.tracks.len()).map(|i| (i, true)).chain((0..board.tracks.len()).map(|i| (i, false)));
+    for (first, from_ends) in starts {
+        let track = &board.tracks[first];
+        if used[first] {
+            continue;
+        }
+        let from = match (degree(track, track.start) != 2, degree(track, track.end) != 2) {
+            (true, _) => track.start,
+            (false, true) => track.end,
+            (false, false) if !from_ends => track.start,
+            (false, false) => continue,
+        };
+        let mut chain = Vec::new();
+        let (mut current, mut at) = (first, from);
+        loop {
+            used[current] = true;
+            let track = &board.tracks[current];
+            let to = if key(track.start) == key(at) { track.end } else { track.start };
+            chain.push(Step { track, from: at, to });
+            let next = &ends[&(track.net, track.layer, key(to))];
+            match next.iter().find(|&&i| i != current && !used[i]) {
+                Some(&i) if next.len() == 2 => (current, at) = (i, to),
+                _ => break,
+            }
+        }
+        chains.push(chain);
+    }
+    chains
+}
+
+/// The meander starting at `chain[start]`, with the index after it.
+fn meander_at(chain: &[Step<'_>], start: usize) -> Option<(usize, Meander)> {
+    let first = &chain[start];
+    if first.track.is_arc() || distance(first.from, first.to) < 1e-9 {
+        return None;
+    }
+    let length = distance(first.from, first.to);
+    let along = Point::new((first.to.x - first.from.x) / length, (first.to.y - first.from.y) / length);
+    let (mut legs, mut sign, mut amplitude, mut last) = (0, 0.0, 0.0_f64, start);
+    for (i, step) in chain.iter().enumerate().skip(start + 1) {
+        if step.track.is_arc() {
+            continue;
+        }
+        let (dx, dy) = (step.to.x - step.from.x, step.to.y - step.from.y);
+        let length = dx.hypot(dy);
+        let (dot, cross) = ((dx * along.x + dy * along.y) / length, (dx * along.y - dy * along.x) / length);
+        if dot > 0.99 {
+            last = i;
+        } else if dot.abs() < 0.01 && cross.signum() != sign {
+            (legs, sign, amplitude, last) = (legs + 1, cross.signum(), amplitude.max(length), i);
+        } else {
+            break;
+        }
+    }
+    if legs < MIN_LEGS {
+        return None;
+    }
+    let run = &chain[start..=last];
+    let (from, to) = (run[0].from, run[run.len() - 1].to);
+    let meander = Meander {
+        net: first.track.net,
+        layer: first.track.layer,
+        tracks: run.iter().map(|step| step.track.uuid).collect(),
+        length: run.iter().map(|step| step.track.length()).sum(),
+        baseline: ((to.x - from.x) * along.x + (to.y - from.y) * along.y).abs(),
+        amplitude,
+    };
+    Some((last + 1, meander))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn net_length(board: &Board, net: u32) -> f64 {
+        board.tracks.iter().filter(|track| track.net == net).map(Track::length).sum()
+    }
+
+    #[test]
+    fn tune_and_recognize() {
+        let mut board = Board::parse(DEMO).unwrap();
+        assert!(meanders(&board).is_empty());
+        let segment = board.tracks[2].clone();
+        let length = net_length(&board, segment.net);
+        let mut generator = UuidGenerator::deterministic("tuning");
+        let options = MeanderOptions::default();
+        let tracks = tune_segment(&mut board, segment.uuid, length + 3.0, &options, &mut generator).unwrap();
+        // Two bumps of 0.75 mm, each with a rise, top and fall, and the
+        // stretches between them.
+        assert_eq!(tracks.len(), 9);
+        assert!((net_length(&board, segment.net) - (length + 3.0)).abs() < 1e-6);
+        assert!(board.tracks.iter().all(|track| track.uuid != segment.uuid));
+        let first = board.tracks.iter().find(|track| track.uuid == tracks[0]).unwrap();
+        assert_eq!((first.start, first.width, first.layer), (segment.start, segment.width, segment.layer));
+
+        let found = meanders(&board);
+        assert_eq!(found.len(), 1);
+        let meander = &found[0];
+        assert_eq!((meander.net, meander.layer), (segment.net, segment.layer));
+        assert!((meander.added_length() - 3.0).abs() < 1e-6, "{:?}", meander);
+        assert!((meander.amplitude - 0.75).abs() < 1e-6);
+
+        let error = |board: &mut Board, uuid, target, options: &MeanderOptions| {
+            let before = board.tracks.clone();
+            let error = tune_segment(board, uuid, target, options, &mut UuidGenerator::deterministic("x")).unwrap_err();
+            assert_eq!(board.tracks, before);
+            error.to_string()
+        };
+        let segment = board.tracks.iter().find(|track| track.layer == Layer::BCu).unwrap().uuid;
+        let length = net_length(&board, 1);
+        assert!(error(&mut board, segment, length, &options).contains("not shorter than"));
+        assert!(error(&mut board, segment, length + 100.0, &options).contains("has room for"));
+        let tight = MeanderOptions { clearance: 50.0, ..options };
+        assert!(error(&mut board, segment, length + 1.0, &tight).contains("closer than 50 mm"));
+    }
+}
@@ -0,0 +1,661 @@
+//! Parametric footprints built in code, in place of KiCad's Python
+//! footprint wizards: a [`FootprintWizard`] turns its parameters into a
+//! typed [`Footprint`], which [`Footprint::to_sexp`] writes as a
+//! `.kicad_mod` file.
+//!
+//! The built-in wizards draw what KiCad's library generators do: the pads,
+//! the body on the fab layer with pin 1's corner cut off, a silkscreen
+//! outline clear of the pads with pin 1 marked, and a courtyard around
+//! body and pads.
+//!
+//! ```no_run
+//! use kicad_pcb::wizard::{FootprintWizard, Soic};
+//!
+//! let soic = Soic { pins: 14, ..Default::default() };
+//! let footprint = soic.build()?;
+//! std::fs::write(format!("Package_SO.pretty/{}.kicad_mod", soic.name()), footprint.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{ConvertError, Effects, Error, Font, Point, Position, Size, Stroke, StrokeKind};
+
+use crate::{
+    pinmap::{grid_row, PadOrder},
+    Attributes, Drill, Field, Footprint, Layer, LayerClass, LayerSelector, MountKind, Pad, PadKind, PadShape, Shape,
+    ShapeKind, Text, TextKind,
+};
+
+/// Clearance of the courtyard around body and pads of SMD parts.
+const SMD_COURTYARD: f64 = 0.25;
+/// Clearance of the courtyard around body and pads of through-hole parts.
+const THT_COURTYARD: f64 = 0.5;
+/// Offset of the silkscreen outline from the body.
+const SILK_OFFSET: f64 = 0.11;
+/// Room kept between silkscreen and pads.
+const SILK_CLEARANCE: f64 = 0.2;
+
+/// A parametric footprint.
+pub trait FootprintWizard {
+    /// The footprint's name, after KiCad's library conventions.
+    fn name(&self) -> String;
+
+    /// The footprint, or an error for parameters that make no part.
+    fn build(&self) -> Result<Footprint, Error>;
+}
+
+/// Small outline package with two rows of gull wing leads, numbered
+/// counterclockwise from the top of the left row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Soic {
+    /// Number of pins; even.
+    pub pins: u32,
+    pub pitch: f64,
+    /// Distance between the centers of the two pad rows.
+    pub span: f64,
+    /// Pad size, across and along the row.
+    pub pad: Size,
+    /// Body width across the rows and length along them.
+    pub body: Size,
+    /// Leading part of the name.
+    pub package: String,
+}
+
+impl Default for Soic {
+    /// SOIC-8 in 3.9 mm body width, as in KiCad's Package_SO library.
+    fn default() -> Self {
+        Soic {
+            pins: 8,
+            pitch: 1.27,
+            span: 4.95,
+            pad: Size::new(1.95, 0.6),
+            body: Size::new(3.9, 4.9),
+            package: "SOIC".to_string(),
+        }
+    }
+}
+
+impl FootprintWizard for Soic {
+    fn name(&self) -> String {
+        format!(
+            "{}-{}_{}x{}mm_P{}mm",
+            self.package,
+            self.pins,
+            mm(self.body.width),
+            mm(self.body.height),
+            mm(self.pitch)
+        )
+    }
+
+    fn build(&self) -> Result<Footprint, Error> {
+        if self.pins == 0 || !self.pins.is_multiple_of(2) {
+            return Err(ConvertError::new(format!("{} needs an even number of pins", self.name())).into());
+        }
+        let per_row = self.pins / 2;
+        let length = f64::from(per_row - 1) * self.pitch;
+        let mut footprint = base(&self.name(), MountKind::Smd);
+        footprint.descr = Some(format!(
+            "{}, {} pin, {}x{} mm body, {} mm pitch",
+            self.package,
+            self.pins,
+            mm(self.body.width),
+            mm(self.body.height),
+            mm(self.pitch)
+        ));
+        footprint.tags = Some(format!("{} SO", self.package));
+        for side in [-1.0, 1.0] {
+            for i in 0..per_row {
+                let at = Point::new(side * self.span / 2.0, f64::from(i) * self.pitch - length / 2.0);
+                footprint.pads.push(smd_pad(at, self.pad));
+            }
+        }
+        footprint.number_pads(PadOrder::CounterClockwise);
+
+        let body = Rect::centered(self.body.width, self.body.height);
+        let silk = body.grown(SILK_OFFSET);
+        let pad_edge = self.span / 2.0 + self.pad.width / 2.0;
+        footprint.shapes.push(line(Point::new(-pad_edge, silk.y0), Point::new(silk.x1, silk.y0), Layer::FSilkS));
+        footprint.shapes.push(line(Point::new(silk.x0, silk.y1), Point::new(silk.x1, silk.y1), Layer::FSilkS));
+        finish(&mut footprint, body, SMD_COURTYARD);
+        Ok(footprint)
+    }
+}
+
+/// Quad flat package, with leads or without as a QFN, numbered
+/// counterclockwise from the top of the left side, and an optional exposed
+/// pad numbered last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Qfp {
+    /// Number of pins on the four sides; a multiple of four.
+    pub pins: u32,
+    pub pitch: f64,
+    /// Distance between the centers of opposite pad rows.
+    pub span: f64,
+    /// Pad size of the left and right sides, across and along the side;
+    /// the pads on top and bottom are turned.
+    pub pad: Size,
+    /// Body size; square bodies have the same width and height.
+    pub body: Size,
+    /// Exposed pad in the middle.
+    pub exposed_pad: Option<Size>,
+    /// Leading part of the name, as in LQFP or QFN.
+    pub package: String,
+}
+
+impl Default for Qfp {
+    /// LQFP-32 in a 7x7 mm body.
+    fn default() -> Self {
+        Qfp {
+            pins: 32,
+            pitch: 0.8,
+            span: 8.4,
+            pad: Size::new(1.5, 0.55),
+            body: Size::new(7.0, 7.0),
+            exposed_pad: None,
+            package: "LQFP".to_string(),
+        }
+    }
+}
+
+impl FootprintWizard for Qfp {
+    fn name(&self) -> String {
+        let mut name = format!(
+            "{}-{}_{}x{}mm_P{}mm",
+            self.package,
+            self.pins,
+            mm(self.body.width),
+            mm(self.body.height),
+            mm(self.pitch)
+        );
+        if let Some(pad) = self.exposed_pad {
+            name.push_str(&format!("_EP{}x{}mm", mm(pad.width), mm(pad.height)));
+        }
+        name
+    }
+
+    fn build(&self) -> Result<Footprint, Error> {
+        if self.pins == 0 || !self.pins.is_multiple_of(4) {
+            return Err(ConvertError::new(format!("{} needs a multiple of four pins", self.name())).into());
+        }
+        let per_side = self.pins / 4;
+        let length = f64::from(per_side - 1) * self.pitch;
+        let mut footprint = base(&self.name(), MountKind::Smd);
+        footprint.descr = Some(format!(
+            "{}, {} pin, {}x{} mm body, {} mm pitch",
+            self.package,
+            self.pins,
+            mm(self.body.width),
+            mm(self.body.height),
+            mm(self.pitch)
+        ));
+        footprint.tags = Some(self.package.clone());
+        let turned = Size::new(self.pad.height, self.pad.width);
+        let half = self.span / 2.0;
+        for i in 0..per_side {
+            let along = f64::from(i) * self.pitch - length / 2.0;
+            footprint.pads.push(smd_pad(Point::new(-half, along), self.pad));
+            footprint.pads.push(smd_pad(Point::new(half, along), self.pad));
+            footprint.pads.push(smd_pad(Point::new(along, -half), turned));
+            footprint.pads.push(smd_pad(Point::new(along, half), turned));
+        }
+        footprint.number_pads(PadOrder::CounterClockwise);
+        if let Some(size) = self.exposed_pad {
+            let mut pad = smd_pad(Point::default(), size);
+            pad.number = (self.pins + 1).to_string();
+            footprint.pads.push(pad);
+        }
+
+        // Corner marks along the body, stopping short of the outer pads,
+        // with pin 1's reaching out to the pad's end.
+        let body = Rect::centered(self.body.width, self.body.height);
+        let silk = body.grown(SILK_OFFSET);
+        let clear = length / 2.0 + self.pad.height / 2.0 + SILK_CLEARANCE;
+        for (sx, sy) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            let corner = Point::new(if sx < 0.0 { silk.x0 } else { silk.x1 }, if sy < 0.0 { silk.y0 } else { silk.y1 });
+            if corner.x.abs() > clear {
+                footprint.shapes.push(line(corner, Point::new(sx * clear, corner.y), Layer::FSilkS));
+            }
+            if corner.y.abs() > clear {
+                footprint.shapes.push(line(corner, Point::new(corner.x, sy * clear), Layer::FSilkS));
+            }
+        }
+        let pad_edge = half + self.pad.width / 2.0;
+        footprint.shapes.push(line(Point::new(silk.x0, -clear), Point::new(-pad_edge, -clear), Layer::FSilkS));
+        finish(&mut footprint, body, SMD_COURTYARD);
+        Ok(footprint)
+    }
+}
+
+/// Ball grid array, with balls named by row letter and column number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bga {
+    pub rows: u32,
+    pub columns: u32,
+    pub pitch: f64,
+    /// Pad diameter.
+    pub ball: f64,
+    pub body: Size,
+    /// Grid names of depopulated balls, as in `"E5"`.
+    pub missing: Vec<String>,
+}
+
+impl Default for Bga {
+    /// BGA-64 on a 0.8 mm grid of 8x8 balls.
+    fn default() -> Self {
+        Bga { rows: 8, columns: 8, pitch: 0.8, ball: 0.4, body: Size::new(7.0, 7.0), missing: Vec::new() }
+    }
+}
+
+impl FootprintWizard for Bga {
+    fn name(&self) -> String {
+        let balls = self.rows * self.columns - self.missing.len() as u32;
+        format!(
+            "BGA-{}_{}x{}mm_Layout{}x{}_P{}mm",
+            balls,
+            mm(self.body.width),
+            mm(self.body.height),
+            self.columns,
+            self.rows,
+            mm(self.pitch)
+        )
+    }
+
+    fn build(&self) -> Result<Footprint, Error> {
+        if self.rows == 0 || self.columns == 0 {
+            return Err(ConvertError::new(format!("{} has no balls", self.name())).into());
+        }
+        let mut footprint = base(&self.name(), MountKind::Smd);
+        footprint.descr = Some(format!(
+            "BGA, {}x{} balls, {}x{} mm body, {} mm pitch",
+            self.columns,
+            self.rows,
+            mm(self.body.width),
+            mm(self.body.height),
+            mm(self.pitch)
+        ));
+        footprint.tags = Some("BGA".to_string());
+        let width = f64::from(self.columns - 1) * self.pitch;
+        let height = f64::from(self.rows - 1) * self.pitch;
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let x = f64::from(column) * self.pitch - width / 2.0;
+                let at = Point::new(x, f64::from(row) * self.pitch - height / 2.0);
+                let mut pad = smd_pad(at, Size::new(self.ball, self.ball));
+                pad.shape = PadShape::Circle;
+                pad.roundrect_rratio = None;
+                pad.number = format!("{}{}", grid_row(row as usize), column + 1);
+                footprint.pads.push(pad);
+            }
+        }
+        if let Some(name) = self.missing.iter().find(|name| footprint.pad(name).is_none()) {
+            return Err(ConvertError::new(format!("{} has no ball {}", self.name(), name)).into());
+        }
+        footprint.pads.retain(|pad| !self.missing.contains(&pad.number));
+
+        let body = Rect::centered(self.body.width, self.body.height);
+        outline(&mut footprint, body.grown(SILK_OFFSET), Layer::FSilkS, 0.12);
+        finish(&mut footprint, body, SMD_COURTYARD);
+        Ok(footprint)
+    }
+}
+
+/// Straight through-hole pin header or socket in one or more rows,
+/// numbered row by row from pin 1 at the origin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PinHeader {
+    /// Rows of pins side by side, usually one or two.
+    pub rows: u32,
+    /// Pins in each row.
+    pub pins: u32,
+    pub pitch: f64,
+    pub drill: f64,
+    pub pad: Size,
+    /// Leading part of the name, as in PinHeader or PinSocket.
+    pub package: String,
+}
+
+impl Default for PinHeader {
+    /// A single row 2.54 mm header of three pins.
+    fn default() -> Self {
+        PinHeader {
+            rows: 1,
+            pins: 3,
+            pitch: 2.54,
+            drill: 1.0,
+            pad: Size::new(1.7, 1.7),
+            package: "PinHeader".to_string(),
+        }
+    }
+}
+
+impl FootprintWizard for PinHeader {
+    fn name(&self) -> String {
+        format!("{}_{}x{:02}_P{}mm_Vertical", self.package, self.rows, self.pins, mm(self.pitch))
+    }
+
+    fn build(&self) -> Result<Footprint, Error> {
+        if self.rows == 0 || self.pins == 0 {
+            return Err(ConvertError::new(format!("{} has no pins", self.name())).into());
+        }
+        let rows = if self.rows == 1 { "single row" } else { "double row" };
+        let words = words(&self.package);
+        let layout = format!("{}x{:02}", self.rows, self.pins);
+        let mut footprint = base(&self.name(), MountKind::ThroughHole);
+        footprint.descr =
+            Some(format!("Through hole straight {}, {}, {}mm pitch, {}", words, layout, mm(self.pitch), rows));
+        footprint.tags = Some(format!("Through hole {} THT {} {}mm {}", words, layout, mm(self.pitch), rows));
+        for pin in 0..self.pins {
+            for row in 0..self.rows {
+                let at = Point::new(f64::from(row) * self.pitch, f64::from(pin) * self.pitch);
+                footprint.pads.push(Pad {
+                    number: String::new(),
+                    kind: PadKind::ThruHole,
+                    shape: PadShape::Oval,
+                    at: Position::new(round(at.x), round(at.y), 0.0),
+                    size: self.pad,
+                    drill: Some(Drill { size: Size::new(self.drill, self.drill), ..Default::default() }),
+                    layers: vec![LayerSelector::All(LayerClass::Cu), LayerSelector::All(LayerClass::Mask)],
+                    roundrect_rratio: None,
+                    net: None,
+                    pinfunction: None,
+                    pintype: Some("passive".to_string()),
+                    property: None,
+                    locked: false,
+                    zone_connect: None,
+                    thermal_gap: None,
+                    thermal_bridge_width: None,
+                    thermal_bridge_angle: None,
+                    uuid: None,
+                });
+            }
+        }
+        footprint.number_pads(PadOrder::Rows);
+        footprint.pads[0].shape = PadShape::Rect;
+
+        let half = self.pitch / 2.0;
+        let body = Rect {
+            x0: -half,
+            y0: -half,
+            x1: f64::from(self.rows - 1) * self.pitch + half,
+            y1: f64::from(self.pins - 1) * self.pitch + half,
+        };
+        outline(&mut footprint, body.grown(SILK_OFFSET), Layer::FSilkS, 0.12);
+        finish(&mut footprint, body, THT_COURTYARD);
+        Ok(footprint)
+    }
+}
+
+/// An axis-aligned rectangle, from its top left to its bottom right corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rect {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+}
+
+impl Rect {
+    fn centered(width: f64, height: f64) -> Self {
+        Rect { x0: -width / 2.0, y0: -height / 2.0, x1: width / 2.0, y1: height / 2.0 }
+    }
+
+    fn grown(&self, by: f64) -> Self {
+        Rect { x0: self.x0 - by, y0: self.y0 - by, x1: self.x1 + by, y1: self.y1 + by }
+    }
+
+    fn union(&self, other: &Rect) -> Self {
+        Rect {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+
+    fn center(&self) -> Point {
+        Point::new((self.x0 + self.x1) / 2.0, (self.y0 + self.y1) / 2.0)
+    }
+}
+
+/// A footprint without pads or drawings, carrying the fields KiCad's
+/// library footprints have.
+fn base(name: &str, mount: MountKind) -> Footprint {
+    Footprint {
+        lib_id: name.to_string(),
+        layer: Some(Layer::FCu),
+        attributes: Attributes { mount, ..Default::default() },
+        ..Default::default()
+    }
+}
+
+fn smd_pad(at: Point, size: Size) -> Pad {
+    Pad {
+        number: String::new(),
+        kind: PadKind::Smd,
+        shape: PadShape::RoundRect,
+        at: Position::new(round(at.x), round(at.y), 0.0),
+        size,
+        drill: None,
+        layers: vec![
+            LayerSelector::Layer(Layer::FCu),
+            LayerSelector::Layer(Layer::FMask),
+            LayerSelector::Layer(Layer::FPaste),
+        ],
+        roundrect_rratio: Some(0.25),
+        net: None,
+        pinfunction: None,
+        pintype: None,
+        property: None,
+        locked: false,
+        zone_connect: None,
+        thermal_gap: None,
+        thermal_bridge_width: None,
+        thermal_bridge_angle: None,
+        uuid: None,
+    }
+}
+
+fn line(start: Point, end: Point, layer: Layer) -> Shape {
+    let width = if layer == Layer::FFab { 0.1 } else { 0.12 };
+    shape(ShapeKind::Line { start, end }, layer, width)
+}
+
+fn shape(kind: ShapeKind, layer: Layer, width: f64) -> Shape {
+    let snap = |p: Point| Point::new(round(p.x), round(p.y));
+    let kind = match kind {
+        ShapeKind::Line { start, end } => ShapeKind::Line { start: snap(start), end: snap(end) },
+        ShapeKind::Rect { start, end } => ShapeKind::Rect { start: snap(start), end: snap(end) },
+        kind => kind,
+    };
+    Shape {
+        kind,
+        layer,
+        stroke: Stroke { width, kind: StrokeKind::Solid, color: None },
+        fill: false,
+        locked: false,
+        uuid: None,
+    }
+}
+
+/// Draws `rect` with pin 1's corner cut off.
+fn outline(footprint: &mut Footprint, rect: Rect, layer: Layer, width: f64) {
+    let chamfer = ((rect.x1 - rect.x0).min(rect.y1 - rect.y0) / 4.0).min(1.0);
+    let points = [
+        Point::new(rect.x0 + chamfer, rect.y0),
+        Point::new(rect.x1, rect.y0),
+        Point::new(rect.x1, rect.y1),
+        Point::new(rect.x0, rect.y1),
+        Point::new(rect.x0, rect.y0 + chamfer),
+    ];
+    for (i, &start) in points.iter().enumerate() {
+        let end = points[(i + 1) % points.len()];
+        footprint.shapes.push(shape(ShapeKind::Line { start, end }, layer, width));
+    }
+}
+
+/// Adds the body on the fab layer, the courtyard around body and pads, and
+/// the fields and reference text.
+fn finish(footprint: &mut Footprint, body: Rect, clearance: f64) {
+    outline(footprint, body, Layer::FFab, 0.1);
+    let pads = footprint.pads.iter().fold(body, |bounds, pad| {
+        let (w, h) = (pad.size.width / 2.0, pad.size.height / 2.0);
+        bounds.union(&Rect { x0: pad.at.x - w, y0: pad.at.y - h, x1: pad.at.x + w, y1: pad.at.y + h })
+    });
+    let grown = pads.grown(clearance);
+    let courtyard = Rect {
+        x0: (grown.x0 / 0.05).floor() * 0.05,
+        y0: (grown.y0 / 0.05).floor() * 0.05,
+        x1: (grown.x1 / 0.05).ceil() * 0.05,
+        y1: (grown.y1 / 0.05).ceil() * 0.05,
+    };
+    let courtyard = Rect {
+        x0: round(courtyard.x0),
+        y0: round(courtyard.y0),
+        x1: round(courtyard.x1),
+        y1: round(courtyard.y1),
+    };
+    let corners = ShapeKind::Rect {
+        start: Point::new(courtyard.x0, courtyard.y0),
+        end: Point::new(courtyard.x1, courtyard.y1),
+    };
+    footprint.shapes.push(shape(corners, Layer::FCrtYd, 0.05));
+
+    let center = body.center();
+    let center = Point::new(round(center.x), round(center.y));
+    let name = footprint.lib_id.clone();
+    let hidden = |key: &str, value: &str| Field {
+        hide: true,
+        ..Field::new(key, value, Position::new(center.x, center.y, 0.0), Layer::FFab)
+    };
+    footprint.properties = vec![
+        Field::new("Reference", "REF**", Position::new(center.x, round(courtyard.y0 - 0.8), 0.0), Layer::FSilkS),
+        Field::new("Value", &name, Position::new(center.x, round(courtyard.y1 + 0.8), 0.0), Layer::FFab),
+        hidden("Datasheet", ""),
+        hidden("Description", footprint.descr.as_deref().unwrap_or_default()),
+    ];
+    let size = ((body.x1 - body.x0).min(body.y1 - body.y0) / 4.0).clamp(0.5, 1.0);
+    let font = Font { height: size, width: size, thickness: Some(round(size * 0.15)), ..Default::default() };
+    let vertical = body.y1 - body.y0 > body.x1 - body.x0;
+    footprint.texts.push(Text {
+        kind: Some(TextKind::User),
+        text: "${REFERENCE}".to_string(),
+        at: Position::new(center.x, center.y, if vertical { 90.0 } else { 0.0 }),
+        layer: Layer::FFab,
+        knockout: false,
+        hide: false,
+        effects: Effects { font, ..Default::default() },
+        uuid: None,
+    });
+}
+
+/// `"PinHeader"` as `"pin header"`.
+fn words(name: &str) -> String {
+    let mut words = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if i > 0 && c.is_uppercase() {
+            words.push(' ');
+        }
+        words.extend(c.to_lowercase());
+    }
+    words
+}
+
+/// A length for a footprint name, as in `2.54` or `7`.
+fn mm(value: f64) -> String {
+    kicad_sexp::number::format_float(value)
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1e6).round() / 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_sexp::source::FsSource;
+
+    use super::*;
+    use crate::{FootprintLib, Side};
+
+    fn numbers(footprint: &Footprint) -> Vec<&str> {
+        footprint.pads.iter().map(|pad| pad.number.as_str()).collect()
+    }
+
+    #[test]
+    fn header_matches_kicad() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let library = FootprintLib::open(&source, "demo/demo.pretty").unwrap();
+        let header = PinHeader::default();
+        assert_eq!(header.name(), "PinHeader_1x03_P2.54mm_Vertical");
+        let kicad = library.get(&header.name()).unwrap();
+        let built = header.build().unwrap();
+        assert_eq!(built.pads, kicad.pads);
+        assert_eq!((&built.descr, &built.tags), (&kicad.descr, &kicad.tags));
+        assert_eq!(built.attributes, kicad.attributes);
+        let courtyard = |footprint: &Footprint| footprint.courtyard(Side::Top).cloned().collect::<Vec<_>>();
+        assert_eq!(courtyard(&built), courtyard(&kicad));
+        assert_eq!(Footprint::parse(&built.to_string()).unwrap(), built);
+
+        let double = PinHeader { rows: 2, pins: 2, ..Default::default() }.build().unwrap();
+        assert_eq!(double.lib_id, "PinHeader_2x02_P2.54mm_Vertical");
+        assert_eq!(double.pad("2").unwrap().at, Position::new(2.54, 0.0, 0.0));
+        assert_eq!(double.pad("3").unwrap().at, Position::new(0.0, 2.54, 0.0));
+    }
+
+    #[test]
+    fn soic() {
+        let soic = Soic::default();
+        assert_eq!(soic.name(), "SOIC-8_3.9x4.9mm_P1.27mm");
+        let footprint = soic.build().unwrap();
+        assert_eq!(numbers(&footprint), ["1", "2", "3", "4", "8", "7", "6", "5"]);
+        assert_eq!(footprint.pad("1").unwrap().at, Position::new(-2.475, -1.905, 0.0));
+        assert_eq!(footprint.attributes.mount, MountKind::Smd);
+        let courtyard: Vec<_> = footprint.courtyard(Side::Top).map(|shape| shape.kind.clone()).collect();
+        assert_eq!(courtyard, [ShapeKind::Rect { start: Point::new(-3.7, -2.7), end: Point::new(3.7, 2.7) }]);
+        assert_eq!(footprint.reference(), Some("REF**"));
+        assert_eq!(footprint.value(), Some("SOIC-8_3.9x4.9mm_P1.27mm"));
+        assert_eq!(Footprint::parse(&footprint.to_string()).unwrap(), footprint);
+
+        let error = Soic { pins: 7, ..Default::default() }.build().unwrap_err();
+        assert_eq!(error.to_string(), "SOIC-7_3.9x4.9mm_P1.27mm needs an even number of pins");
+    }
+
+    #[test]
+    fn qfp() {
+        let qfn = Qfp {
+            pins: 16,
+            pitch: 0.5,
+            span: 2.9,
+            pad: Size::new(0.7, 0.25),
+            body: Size::new(3.0, 3.0),
+            exposed_pad: Some(Size::new(1.7, 1.7)),
+            package: "QFN".to_string(),
+        };
+        assert_eq!(qfn.name(), "QFN-16_3x3mm_P0.5mm_EP1.7x1.7mm");
+        let footprint = qfn.build().unwrap();
+        assert_eq!(footprint.pads.len(), 17);
+        let at = |number: &str| footprint.pad(number).unwrap().at.point();
+        assert_eq!(at("1"), Point::new(-1.45, -0.75));
+        assert_eq!(at("4"), Point::new(-1.45, 0.75));
+        assert_eq!(at("5"), Point::new(-0.75, 1.45));
+        assert_eq!(at("9"), Point::new(1.45, 0.75));
+        assert_eq!(at("16"), Point::new(-0.75, -1.45));
+        assert_eq!(at("17"), Point::default());
+        assert_eq!(footprint.pad("5").unwrap().size, Size::new(0.25, 0.7));
+        assert_eq!(Footprint::parse(&footprint.to_string()).unwrap(), footprint);
+
+        assert_eq!(Qfp::default().build().unwrap().pads.len(), 32);
+        assert!(Qfp { pins: 30, ..Default::default() }.build().is_err());
+    }
+
+    #[test]
+    fn bga() {
+        let bga = Bga { rows: 3, columns: 3, missing: vec!["B2".to_string()], ..Default::default() };
+        assert_eq!(bga.name(), "BGA-8_7x7mm_Layout3x3_P0.8mm");
+        let footprint = bga.build().unwrap();
+        assert_eq!(numbers(&footprint), ["A1", "A2", "A3", "B1", "B3", "C1", "C2", "C3"]);
+        assert_eq!(footprint.pad("A1").unwrap().at.point(), Point::new(-0.8, -0.8));
+        assert_eq!(footprint.pad("C3").unwrap().shape, PadShape::Circle);
+
+        let error = Bga { missing: vec!["Z9".to_string()], ..Default::default() }.build().unwrap_err();
+        assert_eq!(error.to_string(), "BGA-63_7x7mm_Layout8x8_P0.8mm has no ball Z9");
+    }
+}
@@ -0,0 +1,230 @@
+use kicad_common::{
+    convert::{atom_str, FromSexp, List, Result},
+    geometry::child_points,
+    uuid::item_uuid,
+    Point, Uuid,
+};
+
+use crate::{
+    layer::{child_layer, layer_selectors, LayerSelector},
+    Layer,
+};
+
+/// How pads inside a zone connect to its fill.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PadConnection {
+    #[default]
+    ThermalRelief,
+    Solid,
+    None,
+    /// Thermal relief for through-hole pads, solid for everything else.
+    ThruHoleOnly,
+}
+
+/// Which islands of a fill that reach no item of the zone's net are
+/// removed, KiCad's `island_removal_mode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IslandRemoval {
+    #[default]
+    Always,
+    Never,
+    /// Islands smaller than this many square millimeters.
+    BelowArea(f64),
+}
+
+/// What a keepout rule area forbids.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keepout {
+    pub tracks: bool,
+    pub vias: bool,
+    pub pads: bool,
+    pub copperpour: bool,
+    pub footprints: bool,
+}
+
+impl FromSexp for Keepout {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let forbidden = |tag| -> Result<bool> {
+            Ok(list.child(tag).map(|child| child.symbol(0)).transpose().map_err(|e| e.within(list.tag()))?
+                == Some("not_allowed"))
+        };
+        Ok(Keepout {
+            tracks: forbidden("tracks")?,
+            vias: forbidden("vias")?,
+            pads: forbidden("pads")?,
+            copperpour: forbidden("copperpour")?,
+            footprints: forbidden("footprints")?,
+        })
+    }
+}
+
+/// Where the footprints of a KiCad 9 placement rule area come from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlacementSource {
+    /// A sheet path, as in `/Power/`.
+    Sheet(String),
+    ComponentClass(String),
+    /// Groups are not part of the board model, so these areas have no
+    /// members here.
+    Group(String),
+}
+
+/// A rule area's `(placement ...)`: the footprints kept together in it,
+/// for multichannel layouts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Placement {
+    pub enabled: bool,
+    pub source: PlacementSource,
+}
+
+impl FromSexp for Placement {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let enabled = list.child("enabled").map(|enabled| enabled.has_symbol("yes")).unwrap_or(false);
+        // KiCad 9.0 writes `(sheetname ...)` or `(component_class ...)`,
+        // later versions a `(source_type ...)` and `(source ...)` pair.
+        let kind = list.child("source_type").map(|kind| kind.symbol(0)).transpose().map_err(|e| e.within(list.tag()))?;
+        let source = match (kind, list.child_string("source")?) {
+            (Some(kind), Some(source)) => match kind {
+                "sheetname" => PlacementSource::Sheet(source),
+                "component_class" => PlacementSource::ComponentClass(source),
+                "group" => PlacementSource::Group(source),
+                _ => return Err(list.error(format!("unknown source_type {}", kind))),
+            },
+            _ => match (list.child_string("component_class")?, list.child_string("group")?) {
+                (Some(class), _) => PlacementSource::ComponentClass(class),
+                (None, Some(group)) => PlacementSource::Group(group),
+                (None, None) => PlacementSource::Sheet(list.child_string("sheetname")?.unwrap_or_default()),
+            },
+        };
+        Ok(Placement { enabled, source })
+    }
+}
+
+/// One island of a zone's computed fill.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilledPolygon {
+    pub layer: Layer,
+    pub points: Vec<Point>,
+}
+
+impl FromSexp for FilledPolygon {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(FilledPolygon { layer: child_layer(list)?, points: child_points(list)? })
+    }
+}
+
+/// A copper zone or rule area.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Zone {
+    /// Net number; 0 for unconnected zones and rule areas.
+    pub net: u32,
+    pub net_name: String,
+    pub layers: Vec<LayerSelector>,
+    pub name: Option<String>,
+    pub uuid: Option<Uuid>,
+    pub priority: u32,
+    pub locked: bool,
+    pub connect_pads: PadConnection,
+    pub clearance: Option<f64>,
+    pub min_thickness: Option<f64>,
+    /// Whether the zone is filled at all, as opposed to drawn as an outline.
+    pub filled: bool,
+    pub thermal_gap: Option<f64>,
+    pub thermal_bridge_width: Option<f64>,
+    pub island_removal: IslandRemoval,
+    /// Set for rule areas.
+    pub keepout: Option<Keepout>,
+    /// Set for rule areas that place footprints.
+    pub placement: Option<Placement>,
+    /// Main outline; holes and extra outlines are not split out.
+    pub outline: Vec<Point>,
+    pub filled_polygons: Vec<FilledPolygon>,
+}
+
+impl Zone {
+    pub fn on_layer(&self, layer: Layer) -> bool {
+        self.layers.iter().any(|selector| selector.matches(layer))
+    }
+}
+
+impl FromSexp for Zone {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let within = |e: kicad_common::ConvertError| e.within(list.tag());
+        let layers = match (list.child("layers"), list.child("layer")) {
+            (Some(layers), _) => layer_selectors(&layers).map_err(within)?,
+            (None, Some(_)) => vec![LayerSelector::Layer(child_layer(list)?)],
+            (None, None) => return Err(list.error("missing (layer ...)")),
+        };
+
+        let (connect_pads, clearance) = match list.child("connect_pads") {
+            Some(connect) => {
+                let connection = match connect.args().first().and_then(atom_str).as_deref() {
+                    Some("yes") => PadConnection::Solid,
+                    Some("no") => PadConnection::None,
+                    Some("thru_hole_only") => PadConnection::ThruHoleOnly,
+                    _ => PadConnection::ThermalRelief,
+                };
+                (connection, connect.child_number("clearance").map_err(within)?)
+            },
+            None => (PadConnection::ThermalRelief, None),
+        };
+
+        let (filled, thermal_gap, thermal_bridge_width, island_removal) = match list.child("fill") {
+            Some(fill) => {
+                let mode: Option<u32> = fill.child_number("island_removal_mode").map_err(within)?;
+                let island_removal = match mode {
+                    None | Some(0) => IslandRemoval::Always,
+                    Some(1) => IslandRemoval::Never,
+                    Some(2) => {
+                        // KiCad's default of 10 mm².
+                        let area = fill.child_number("island_area_min").map_err(within)?;
+                        IslandRemoval::BelowArea(area.unwrap_or(10.0))
+                    },
+                    Some(mode) => {
+                        return Err(within(fill.error(format!("unknown island_removal_mode {}", mode))));
+                    },
+                };
+                (
+                    fill.has_symbol("yes"),
+                    fill.child_number("thermal_gap").map_err(within)?,
+                    fill.child_number("thermal_bridge_width").map_err(within)?,
+                    island_removal,
+                )
+            },
+            None => (false, None, None, IslandRemoval::Always),
+        };
+
+        let outline = match list.child("polygon") {
+            Some(polygon) => child_points(&polygon).map_err(within)?,
+            None => Vec::new(),
+        };
+
+        Ok(Zone {
+            net: list.child_number("net")?.unwrap_or_default(),
+            net_name: list.child_string("net_name")?.unwrap_or_default(),
+            layers,
+            name: list.child_string("name")?,
+            uuid: item_uuid(list)?,
+            priority: list.child_number("priority")?.unwrap_or_default(),
+            locked: list.flag("locked")?,
+            connect_pads,
+            clearance,
+            min_thickness: list.child_number("min_thickness")?,
+            filled,
+            thermal_gap,
+            thermal_bridge_width,
+            island_removal,
+            keepout: list.parse("keepout")?,
+            placement: list.parse("placement")?,
+            outline,
+            filled_polygons: list.parse_all("filled_polygon")?,
+        })
+    }
+}
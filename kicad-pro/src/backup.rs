@@ -0,0 +1,50 @@
+use kicad_common::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::json_error;
+
+/// The `backup` section of KiCad's `kicad_common.json`: when project
+/// backups are made and how many are kept. KiCad keeps these per user, not
+/// in the `.kicad_pro` file. Zero limits are unlimited.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// Also back up on autosave, not only on save.
+    pub backup_on_autosave: bool,
+    pub limit_total_files: u32,
+    /// Backups kept per calendar day.
+    pub limit_daily_files: u32,
+    /// Seconds since the last backup before another is made.
+    pub min_interval: u64,
+    /// Bytes all backups of a project may take.
+    pub limit_total_size: u64,
+}
+
+impl Default for BackupSettings {
+    /// KiCad's defaults.
+    fn default() -> Self {
+        BackupSettings {
+            enabled: true,
+            backup_on_autosave: false,
+            limit_total_files: 25,
+            limit_daily_files: 5,
+            min_interval: 300,
+            limit_total_size: 100 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CommonSettings {
+    #[serde(default)]
+    backup: BackupSettings,
+}
+
+impl BackupSettings {
+    /// Reads the `backup` section of a `kicad_common.json`; missing keys
+    /// take KiCad's defaults.
+    pub fn parse_common(src: &str) -> Result<Self, Error> {
+        serde_json::from_str::<CommonSettings>(src).map(|common| common.backup).map_err(json_error)
+    }
+}
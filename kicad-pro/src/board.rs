@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Board-wide minimums from `board.design_settings.rules`, in millimeters.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesignRules {
+    pub min_clearance: f64,
+    pub min_track_width: f64,
+    pub min_connection: f64,
+    pub min_via_diameter: f64,
+    pub min_via_annular_width: f64,
+    pub min_through_hole_diameter: f64,
+    pub min_microvia_diameter: f64,
+    pub min_microvia_drill: f64,
+    pub min_hole_clearance: f64,
+    pub min_hole_to_hole: f64,
+    pub min_copper_edge_clearance: f64,
+    pub min_silk_clearance: f64,
+    pub min_text_height: f64,
+    pub min_text_thickness: f64,
+    pub solder_mask_to_copper_clearance: f64,
+    /// Allowed deviation when approximating arcs with segments.
+    pub max_error: f64,
+}
+
+/// A predefined via size; all zeros is the net class default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViaDimension {
+    pub diameter: f64,
+    pub drill: f64,
+}
+
+/// A predefined differential pair size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffPairDimension {
+    pub width: f64,
+    pub gap: f64,
+    pub via_gap: f64,
+}
+
+/// The `board.design_settings` section.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesignSettings {
+    pub rules: DesignRules,
+    /// Track widths offered in the board editor; a leading 0 stands for the
+    /// net class width.
+    pub track_widths: Vec<f64>,
+    pub via_dimensions: Vec<ViaDimension>,
+    pub diff_pair_dimensions: Vec<DiffPairDimension>,
+    /// DRC check names mapped to `error`, `warning` or `ignore`.
+    pub rule_severities: BTreeMap<String, String>,
+}
+
+/// The `board` section.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoardSettings {
+    pub design_settings: DesignSettings,
+}
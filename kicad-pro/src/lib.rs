@@ -0,0 +1,94 @@
+//! Typed model of KiCad project (`.kicad_pro`) files, which unlike the
+//! other documents are JSON.
+
+mod backup;
+mod board;
+mod net;
+mod settings;
+
+pub use kicad_common::Error;
+
+pub use backup::BackupSettings;
+pub use board::{BoardSettings, DesignRules, DesignSettings, DiffPairDimension, ViaDimension};
+pub use net::{wildcard_match, NetClass, NetClassPattern, NetSettings};
+pub use settings::{Meta, ProjectSettings, SchematicDrawing, SchematicSettings};
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::property::FieldTemplate;
+
+    use super::*;
+
+    const EMPTY: &str = include_str!("../../reference-files/empty/empty.kicad_pro");
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pro");
+
+    #[test]
+    fn empty() {
+        let settings = ProjectSettings::parse(EMPTY).unwrap();
+
+        assert_eq!(settings.meta, Meta { filename: "empty.kicad_pro".into(), version: 3 });
+        assert!(settings.text_variables.is_empty() && settings.sheets.is_empty());
+        let default = settings.net_settings.default_class().unwrap();
+        assert_eq!((default.clearance, default.track_width), (Some(0.2), Some(0.2)));
+        assert_eq!((default.wire_width, default.priority), (Some(6.0), Some(i32::MAX)));
+        let rules = &settings.board.design_settings.rules;
+        assert_eq!((rules.min_via_diameter, rules.min_copper_edge_clearance), (0.5, 0.5));
+        assert_eq!(settings.board.design_settings.rule_severities["clearance"], "error");
+    }
+
+    #[test]
+    fn demo() {
+        let settings = ProjectSettings::parse(DEMO).unwrap();
+
+        assert_eq!(settings.text_variable("REVISION_NOTE"), Some("first spin"));
+        assert_eq!(settings.sheets, [
+            ("87a3680d-3f24-40cd-ba53-72644a0ce42b".to_string(), "Root".to_string()),
+            ("2a3c5ebe-b43c-4735-aace-eff978af37c6".to_string(), "power".to_string()),
+        ]);
+        assert_eq!(settings.schematic.page_layout_descr_file, "demo.kicad_wks");
+        let fields = &settings.schematic.drawing.field_names;
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[1], FieldTemplate { name: "Supplier".into(), visible: true, url: true });
+        let design = &settings.board.design_settings;
+        assert_eq!(design.track_widths, [0.0, 0.25, 0.5]);
+        assert_eq!(design.via_dimensions[1], ViaDimension { diameter: 0.8, drill: 0.4 });
+
+        let nets = &settings.net_settings;
+        assert_eq!(nets.class_names_for("VIN"), ["Power"]);
+        assert_eq!(nets.class_names_for("GND"), ["Ground", "Power"]);
+        assert!(nets.class_names_for("/SDA").is_empty());
+        let vin = nets.class_for("VIN");
+        assert_eq!((vin.name.as_str(), vin.track_width, vin.clearance), ("Power", Some(0.5), Some(0.2)));
+        assert_eq!(nets.class_for("GND").name, "Power");
+        assert_eq!(nets.class_for("/SDA").name, "Default");
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("+*", "+5V"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("/USB_D?", "/USB_D+"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("a*b*c", "axxbyy"));
+        assert!(!wildcard_match("VIN", "VIN2"));
+    }
+
+    #[test]
+    fn errors() {
+        let error = ProjectSettings::parse("{\"meta\": {\"version\": \"x\"}}").unwrap_err();
+        assert!(matches!(&error, Error::Json { line: 1, .. }));
+        assert_eq!(error.to_string(), "invalid type: string \"x\", expected u32 at line 1, column 24");
+
+        let error = ProjectSettings::parse("{").unwrap_err();
+        assert!(error.to_string().starts_with("EOF while parsing an object"));
+    }
+
+    #[test]
+    fn backup() {
+        let common = "{\"backup\": {\"enabled\": false, \"limit_daily_files\": 2}, \"graphics\": {}}";
+        let settings = BackupSettings::parse_common(common).unwrap();
+        assert_eq!(settings, BackupSettings { enabled: false, limit_daily_files: 2, ..Default::default() });
+        assert_eq!(BackupSettings::parse_common("{}").unwrap().limit_total_files, 25);
+        assert!(matches!(BackupSettings::parse_common("{\"backup\": 1}"), Err(Error::Json { .. })));
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A net class from `net_settings.classes`. Values are in millimeters,
+/// except `wire_width` and `bus_width` which are in mils. Classes other
+/// than `Default` leave unset values to it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetClass {
+    pub name: String,
+    /// Lower numbers win when several classes apply to a net.
+    pub priority: Option<i32>,
+    pub clearance: Option<f64>,
+    pub track_width: Option<f64>,
+    pub via_diameter: Option<f64>,
+    pub via_drill: Option<f64>,
+    pub microvia_diameter: Option<f64>,
+    pub microvia_drill: Option<f64>,
+    pub diff_pair_width: Option<f64>,
+    pub diff_pair_gap: Option<f64>,
+    pub diff_pair_via_gap: Option<f64>,
+    pub wire_width: Option<f64>,
+    pub bus_width: Option<f64>,
+    /// CSS-style color, as in `rgba(0, 0, 0, 0.000)`.
+    pub pcb_color: Option<String>,
+    pub schematic_color: Option<String>,
+}
+
+/// A rule assigning nets whose name matches `pattern` to `netclass`, as in
+/// `{ "netclass": "Power", "pattern": "+*" }`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetClassPattern {
+    /// Wildcard pattern where `*` matches any run and `?` any one character.
+    pub pattern: String,
+    pub netclass: String,
+}
+
+/// Accepts the single class name of KiCad 8 as well as the lists of KiCad 9.
+fn class_names<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Names {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let assignments: Option<BTreeMap<String, Names>> = Option::deserialize(deserializer)?;
+    Ok(assignments
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(net, names)| match names {
+            Names::One(name) => (net, vec![name]),
+            Names::Many(names) => (net, names),
+        })
+        .collect())
+}
+
+/// The `net_settings` section.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetSettings {
+    pub classes: Vec<NetClass>,
+    pub netclass_patterns: Vec<NetClassPattern>,
+    /// Net classes set on individual nets, by full net name.
+    #[serde(deserialize_with = "class_names")]
+    pub netclass_assignments: BTreeMap<String, Vec<String>>,
+}
+
+/// Matches `name` against a pattern with `*` and `?` wildcards.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            },
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                },
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl NetSettings {
+    pub fn class(&self, name: &str) -> Option<&NetClass> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+
+    /// The class named `Default`, which every net falls back to.
+    pub fn default_class(&self) -> Option<&NetClass> {
+        self.class("Default")
+    }
+
+    /// Names of the classes that apply to `net` by assignment or pattern, in
+    /// file order; empty if the net only has the default class.
+    pub fn class_names_for(&self, net: &str) -> Vec<&str> {
+        let assigned = self.netclass_assignments.get(net).into_iter().flatten();
+        let mut names: Vec<&str> = assigned.map(String::as_str).collect();
+        for rule in &self.netclass_patterns {
+            if wildcard_match(&rule.pattern, net) && !names.contains(&rule.netclass.as_str()) {
+                names.push(&rule.netclass);
+            }
+        }
+        names
+    }
+
+    /// The effective class of `net`: the highest priority one that applies,
+    /// with values it leaves unset taken from the default class.
+    pub fn class_for(&self, net: &str) -> NetClass {
+        let default = match self.default_class() {
+            Some(default) => default.clone(),
+            None => NetClass { name: "Default".into(), ..Default::default() },
+        };
+        let class = self
+            .class_names_for(net)
+            .into_iter()
+            .filter_map(|name| self.class(name))
+            .min_by_key(|class| class.priority.unwrap_or(i32::MAX));
+        let Some(class) = class else {
+            return default;
+        };
+        let or = |value: &Option<f64>, fallback: &Option<f64>| value.or(*fallback);
+        NetClass {
+            name: class.name.clone(),
+            priority: class.priority,
+            clearance: or(&class.clearance, &default.clearance),
+            track_width: or(&class.track_width, &default.track_width),
+            via_diameter: or(&class.via_diameter, &default.via_diameter),
+            via_drill: or(&class.via_drill, &default.via_drill),
+            microvia_diameter: or(&class.microvia_diameter, &default.microvia_diameter),
+            microvia_drill: or(&class.microvia_drill, &default.microvia_drill),
+            diff_pair_width: or(&class.diff_pair_width, &default.diff_pair_width),
+            diff_pair_gap: or(&class.diff_pair_gap, &default.diff_pair_gap),
+            diff_pair_via_gap: or(&class.diff_pair_via_gap, &default.diff_pair_via_gap),
+            wire_width: or(&class.wire_width, &default.wire_width),
+            bus_width: or(&class.bus_width, &default.bus_width),
+            pcb_color: class.pcb_color.clone().or(default.pcb_color),
+            schematic_color: class.schematic_color.clone().or(default.schematic_color),
+        }
+    }
+}
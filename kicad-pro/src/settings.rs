@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+use kicad_common::{property::FieldTemplate, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{BoardSettings, NetSettings};
+
+pub(crate) fn json_error(e: serde_json::Error) -> Error {
+    let (line, column) = (e.line(), e.column());
+    let message = e.to_string();
+    let suffix = format!(" at line {} column {}", line, column);
+    let message = message.strip_suffix(&suffix).unwrap_or(&message).to_string();
+    Error::Json { message, line, column }
+}
+
+/// The `meta` section.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Meta {
+    /// File name the project was last saved as.
+    pub filename: String,
+    pub version: u32,
+}
+
+/// The `schematic.drawing` section.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchematicDrawing {
+    /// Fields added to every new symbol.
+    pub field_names: Vec<FieldTemplate>,
+}
+
+/// The `schematic` section.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchematicSettings {
+    /// Drawing sheet (`.kicad_wks`) path; empty for KiCad's built-in one.
+    pub page_layout_descr_file: String,
+    pub drawing: SchematicDrawing,
+}
+
+/// The settings of a `.kicad_pro` project file. Sections and keys the model
+/// does not cover are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    pub meta: Meta,
+    pub board: BoardSettings,
+    pub net_settings: NetSettings,
+    pub schematic: SchematicSettings,
+    /// Values of `${NAME}` variables used in texts and paths.
+    pub text_variables: BTreeMap<String, String>,
+    /// Sheet UUIDs and names of the schematic hierarchy.
+    pub sheets: Vec<(String, String)>,
+}
+
+impl ProjectSettings {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = src.len())))]
+    pub fn parse(src: &str) -> Result<Self, Error> {
+        serde_json::from_str(src).map_err(json_error)
+    }
+
+    pub fn text_variable(&self, name: &str) -> Option<&str> {
+        self.text_variables.get(name).map(String::as_str)
+    }
+}
@@ -0,0 +1,272 @@
+//! Bills of materials: the placed symbols grouped into one row per part.
+//!
+//! Symbols with `(in_bom no)`, virtual symbols such as power flags, and
+//! symbols on sheets excluded from the BOM are left out. Do-not-populate
+//! symbols are left out too unless [`BomOptions::include_dnp`] is set, in
+//! which case they get rows of their own. [`MergedBom`] merges the BOMs of
+//! several boards into one for purchasing.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use kicad_common::reference::natural_cmp;
+
+use crate::{Hierarchy, Schematic, SymbolInstance};
+
+/// How symbols are grouped and which fields the rows carry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BomOptions {
+    /// Symbols with equal values for all of these fields share a row.
+    pub group_by: Vec<String>,
+    /// More fields to copy into the rows, from the first symbol of each.
+    pub fields: Vec<String>,
+    pub include_dnp: bool,
+}
+
+impl Default for BomOptions {
+    /// Groups by `Value`, `Footprint` and `MPN`.
+    fn default() -> Self {
+        BomOptions {
+            group_by: ["Value", "Footprint", "MPN"].map(String::from).to_vec(),
+            fields: Vec::new(),
+            include_dnp: false,
+        }
+    }
+}
+
+/// One part of a BOM.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BomRow {
+    /// In natural order, `R2` before `R10`. A multi-unit symbol is listed
+    /// once.
+    pub references: Vec<String>,
+    pub quantity: usize,
+    /// The grouping fields and the extra fields, empty where unset.
+    pub fields: BTreeMap<String, String>,
+    pub dnp: bool,
+}
+
+impl BomRow {
+    pub fn field(&self, name: &str) -> &str {
+        self.fields.get(name).map_or("", String::as_str)
+    }
+}
+
+/// A bill of materials, rows sorted by their first reference.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bom {
+    /// The field columns in order, grouping fields first.
+    pub columns: Vec<String>,
+    pub rows: Vec<BomRow>,
+}
+
+struct Entry<'a> {
+    reference: &'a str,
+    symbol: &'a SymbolInstance,
+    dnp: bool,
+    /// The `Value` and `Footprint` fields at the symbol's sheet instance.
+    value: &'a str,
+    footprint: &'a str,
+}
+
+impl Bom {
+    /// The BOM of a whole design, each symbol counted at every sheet
+    /// instance it appears at.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(rows)))]
+    pub fn from_hierarchy(hierarchy: &Hierarchy, options: &BomOptions) -> Self {
+        // Whether each sheet, by path, is in the BOM and whether it is DNP,
+        // from its own sheet symbol and those above it.
+        let sheets: BTreeMap<&str, (bool, bool)> = (0..hierarchy.sheets.len())
+            .map(|index| {
+                let symbols = hierarchy.ancestors(index).filter_map(|node| hierarchy.sheet_symbol(node));
+                let flags =
+                    symbols.fold((true, false), |(in_bom, dnp), sheet| (in_bom && sheet.in_bom, dnp || sheet.dnp));
+                (hierarchy.sheets[index].path.as_str(), flags)
+            })
+            .collect();
+        let entries = hierarchy.symbols().filter_map(|flat| {
+            let (in_bom, dnp) = sheets[flat.sheet.path.as_str()];
+            in_bom.then_some(Entry {
+                reference: flat.reference,
+                symbol: flat.symbol,
+                dnp: dnp || flat.symbol.dnp,
+                value: flat.value,
+                footprint: flat.footprint.unwrap_or_default(),
+            })
+        });
+        let bom = Self::build(entries, options);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("rows", bom.rows.len());
+        bom
+    }
+
+    /// The BOM of a single sheet, by the symbols' `Reference` fields.
+    pub fn from_schematic(schematic: &Schematic, options: &BomOptions) -> Self {
+        let entries = schematic.symbols.iter().map(|symbol| Entry {
+            reference: symbol.reference().unwrap_or_default(),
+            symbol,
+            dnp: symbol.dnp,
+            value: symbol.value().unwrap_or_default(),
+            footprint: symbol.footprint().unwrap_or_default(),
+        });
+        Self::build(entries, options)
+    }
+
+    fn build<'a>(entries: impl Iterator<Item = Entry<'a>>, options: &BomOptions) -> Self {
+        let mut groups: BTreeMap<(Vec<&str>, bool), BomRow> = BTreeMap::new();
+        for entry in entries {
+            let symbol = entry.symbol;
+            if !symbol.in_bom || symbol.is_virtual() || (entry.dnp && !options.include_dnp) {
+                continue;
+            }
+            let value = |field: &String| match field.as_str() {
+                "Value" => entry.value,
+                "Footprint" => entry.footprint,
+                _ => symbol.property(field).unwrap_or_default(),
+            };
+            let key = (options.group_by.iter().map(value).collect(), entry.dnp);
+            let row = groups.entry(key).or_insert_with(|| BomRow {
+                fields: options.group_by.iter().chain(&options.fields).map(|f| (f.clone(), value(f).into())).collect(),
+                dnp: entry.dnp,
+                ..Default::default()
+            });
+            if !row.references.iter().any(|reference| reference == entry.reference) {
+                row.references.push(entry.reference.to_string());
+            }
+        }
+        let mut rows: Vec<_> = groups.into_values().collect();
+        for row in &mut rows {
+            row.references.sort_by(|a, b| natural_cmp(a, b));
+            row.quantity = row.references.len();
+        }
+        rows.sort_by(|a, b| natural_cmp(&a.references[0], &b.references[0]));
+        let columns = options.group_by.iter().chain(&options.fields).cloned().collect();
+        Bom { columns, rows }
+    }
+
+    /// The BOM as CSV: `Reference`, `Quantity`, the field columns and
+    /// `DNP`, with references joined by commas.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        let header = ["Reference", "Quantity"].into_iter().chain(self.columns.iter().map(String::as_str));
+        write_record(&mut out, header.chain(["DNP"]));
+        for row in &self.rows {
+            let references = row.references.join(",");
+            let quantity = row.quantity.to_string();
+            let fields = self.columns.iter().map(|column| row.field(column));
+            let dnp = if row.dnp { "DNP" } else { "" };
+            write_record(&mut out, [references.as_str(), quantity.as_str()].into_iter().chain(fields).chain([dnp]));
+        }
+        out
+    }
+}
+
+/// The BOMs of several boards merged into one for purchasing, such as the
+/// boards of a product built together. Rows of different boards with equal
+/// grouping fields become one line item, with the quantity each board
+/// needs and the total for the build.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergedBom {
+    /// The boards by name, with how many of each are built.
+    pub boards: Vec<(String, usize)>,
+    /// The grouping fields, then the other columns of the boards' BOMs.
+    pub columns: Vec<String>,
+    pub rows: Vec<MergedRow>,
+    group_by: Vec<String>,
+}
+
+/// One line item of a [`MergedBom`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergedRow {
+    /// From the first board with the part, completed by the later ones.
+    pub fields: BTreeMap<String, String>,
+    /// The parts on one of each board, in the order of [`MergedBom::boards`].
+    pub per_board: Vec<usize>,
+    /// The parts for the whole build.
+    pub quantity: usize,
+}
+
+impl MergedRow {
+    pub fn field(&self, name: &str) -> &str {
+        self.fields.get(name).map_or("", String::as_str)
+    }
+}
+
+impl MergedBom {
+    /// A merged BOM grouping rows by `group_by`, typically the fields the
+    /// boards' BOMs were grouped by.
+    pub fn new(group_by: &[&str]) -> Self {
+        let group_by: Vec<String> = group_by.iter().map(|field| field.to_string()).collect();
+        MergedBom { columns: group_by.clone(), group_by, ..Default::default() }
+    }
+
+    /// Adds `count` of a board. Do-not-populate rows are left out.
+    pub fn add(&mut self, name: &str, bom: &Bom, count: usize) {
+        let board = self.boards.len();
+        self.boards.push((name.to_string(), count));
+        for row in &mut self.rows {
+            row.per_board.push(0);
+        }
+        for column in &bom.columns {
+            if !self.columns.contains(column) {
+                self.columns.push(column.clone());
+            }
+        }
+        for row in bom.rows.iter().filter(|row| !row.dnp) {
+            let key: Vec<&str> = self.group_by.iter().map(|field| row.field(field)).collect();
+            let same =
+                |merged: &MergedRow| self.group_by.iter().map(|field| merged.field(field)).eq(key.iter().copied());
+            let index = self.rows.iter().position(same);
+            let merged = match index {
+                Some(index) => &mut self.rows[index],
+                None => {
+                    self.rows.push(MergedRow { per_board: vec![0; board + 1], ..Default::default() });
+                    self.rows.last_mut().unwrap()
+                },
+            };
+            for (field, value) in &row.fields {
+                let entry = merged.fields.entry(field.clone()).or_default();
+                if entry.is_empty() {
+                    entry.clone_from(value);
+                }
+            }
+            merged.per_board[board] += row.quantity;
+            merged.quantity += row.quantity * count;
+        }
+    }
+
+    /// The merged BOM as CSV: `Quantity`, the field columns and the parts
+    /// per board, headed by the board's name and count as in `main x2`.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        let boards: Vec<String> = self.boards.iter().map(|(name, count)| format!("{} x{}", name, count)).collect();
+        let header = ["Quantity"].into_iter().chain(self.columns.iter().map(String::as_str));
+        write_record(&mut out, header.chain(boards.iter().map(String::as_str)));
+        for row in &self.rows {
+            let quantity = row.quantity.to_string();
+            let per_board: Vec<String> = row.per_board.iter().map(usize::to_string).collect();
+            let fields = self.columns.iter().map(|column| row.field(column));
+            let per_board = per_board.iter().map(String::as_str);
+            write_record(&mut out, [quantity.as_str()].into_iter().chain(fields).chain(per_board));
+        }
+        out
+    }
+}
+
+fn write_record<'a>(out: &mut String, fields: impl Iterator<Item = &'a str>) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n']) {
+            let _ = write!(out, "\"{}\"", field.replace('"', "\"\""));
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push('\n');
+}
@@ -0,0 +1,138 @@
+//! New schematics from scratch, for tools that generate designs.
+//!
+//! The builder writes a root sheet the way KiCad saves a new, empty one:
+//! version, UUID, paper, title block, an empty symbol library and the
+//! root page number. Content is added to the written file, or to the
+//! [`Schematic`] from [`SchematicBuilder::build`].
+//!
+//! ```
+//! use kicad_common::{TitleBlock, UuidGenerator};
+//! use kicad_sch::SchematicBuilder;
+//!
+//! let uuid = UuidGenerator::deterministic("keyboard").generate("root");
+//! let title_block = TitleBlock { title: Some("Keyboard".to_string()), ..Default::default() };
+//! let schematic = SchematicBuilder::new(uuid).title_block(title_block).build()?;
+//! assert_eq!(schematic.uuid, Some(uuid));
+//! # Ok::<(), kicad_sch::Error>(())
+//! ```
+
+use std::fmt;
+
+use kicad_common::{Error, FromSexp, List, Paper, TitleBlock, Uuid};
+use kicad_sexp::{SexpBuilder, SexpOwned};
+
+use crate::Schematic;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchematicBuilder {
+    generator: String,
+    generator_version: Option<String>,
+    uuid: Uuid,
+    paper: Paper,
+    title_block: Option<TitleBlock>,
+    page: String,
+}
+
+impl SchematicBuilder {
+    /// An empty root sheet on A4 paper, page 1, as eeschema 9.0 creates it.
+    pub fn new(uuid: Uuid) -> Self {
+        SchematicBuilder {
+            generator: "eeschema".to_string(),
+            generator_version: Some("9.0".to_string()),
+            uuid,
+            paper: Paper::default(),
+            title_block: None,
+            page: "1".to_string(),
+        }
+    }
+
+    /// The program named as the file's writer.
+    pub fn generator(mut self, name: &str, version: Option<&str>) -> Self {
+        self.generator = name.to_string();
+        self.generator_version = version.map(str::to_string);
+        self
+    }
+
+    pub fn paper(mut self, paper: Paper) -> Self {
+        self.paper = paper;
+        self
+    }
+
+    pub fn title_block(mut self, title_block: TitleBlock) -> Self {
+        self.title_block = Some(title_block);
+        self
+    }
+
+    /// The root sheet's page number.
+    pub fn page(mut self, page: &str) -> Self {
+        self.page = page.to_string();
+        self
+    }
+
+    /// The schematic file as KiCad writes it; [`Display`](fmt::Display)
+    /// formats the whole file.
+    pub fn to_sexp(&self) -> SexpOwned {
+        let schematic = SexpBuilder::new("kicad_sch")
+            .child(SexpBuilder::new("version").int(Schematic::NEWEST_VERSION.into()))
+            .child(SexpBuilder::new("generator").string(&self.generator))
+            .child_opt(self.generator_version.as_deref().map(|v| SexpBuilder::new("generator_version").string(v)))
+            .child(SexpBuilder::new("uuid").symbol(&self.uuid.to_string()))
+            .item(self.paper.to_sexp());
+        let schematic = match &self.title_block {
+            Some(title_block) => schematic.item(title_block.to_sexp()),
+            None => schematic,
+        };
+        let root = SexpBuilder::new("path").string("/").child(SexpBuilder::new("page").string(&self.page));
+        schematic
+            .child(SexpBuilder::new("lib_symbols"))
+            .child(SexpBuilder::new("sheet_instances").child(root))
+            .child(SexpBuilder::new("embedded_fonts").symbol("no"))
+            .build()
+    }
+
+    /// The schematic as the typed model. Fails if something given to the
+    /// builder, like the title block, does not read back.
+    pub fn build(&self) -> Result<Schematic, Error> {
+        let sexp = self.to_sexp();
+        let sexp = sexp.as_sexp();
+        Ok(Schematic::from_sexp(&List::expect(&sexp, "kicad_sch")?)?)
+    }
+}
+
+impl fmt::Display for SchematicBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_sexp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::PaperSize;
+
+    use super::*;
+
+    const EMPTY: &str = include_str!("../../reference-files/empty/empty.kicad_sch");
+
+    #[test]
+    fn empty_schematic() {
+        let uuid = "7dfecbba-c7b6-4b01-9f5f-e26c00ad6670".parse().unwrap();
+        assert_eq!(SchematicBuilder::new(uuid).to_string().trim_end(), EMPTY.trim_end());
+    }
+
+    #[test]
+    fn title_block() {
+        let mut title_block = TitleBlock { title: Some("Panel".to_string()), rev: Some("B".to_string()), ..Default::default() };
+        title_block.comments.insert(2, "generated".to_string());
+        let builder = SchematicBuilder::new(Uuid::from_u128(1))
+            .paper(Paper { size: PaperSize::A3, portrait: true })
+            .title_block(title_block.clone())
+            .page("3");
+        let text = builder.to_string();
+        assert!(text.contains("\t(paper \"A3\" portrait)\n\t(title_block\n\t\t(title \"Panel\")\n\t\t(rev \"B\")\n"));
+
+        let schematic = Schematic::parse(&text).unwrap();
+        assert_eq!(schematic, builder.build().unwrap());
+        assert_eq!(schematic.title_block, Some(title_block));
+        assert_eq!(schematic.sheet_instances[0].page, "3");
+    }
+}
@@ -0,0 +1,543 @@
+//! Circuits described in Rust, in the spirit of skidl: parts and nets are
+//! handles into a [`Circuit`], pins join nets with `+=` and `&`, and the
+//! result compiles to a [`Netlist`] for a board or to a schematic
+//! [synthesized](crate::synthesis) from it.
+//!
+//! ```no_run
+//! use kicad_common::UuidGenerator;
+//! use kicad_sch::{circuit::Circuit, layout::SymbolLibrary, synthesis::SynthesisOptions};
+//! use kicad_sexp::source::FsSource;
+//!
+//! let circuit = Circuit::new();
+//! let (mut vin, mut vout, mut gnd) = (circuit.net("VIN"), circuit.net("VOUT"), circuit.net("GND"));
+//! let r = circuit.part("R", "Device:R", "10k").footprint("Resistor_SMD:R_0603_1608Metric");
+//! let r2 = circuit.part("R", "Device:R", "4k7").footprint("Resistor_SMD:R_0603_1608Metric");
+//! vin += r.pin("1");
+//! vout += r.pin("2") & r2.pin("1");
+//! gnd += r2.pin("2");
+//! std::fs::write("divider.net", circuit.netlist()?.to_string())?;
+//!
+//! let libraries = [
+//!     SymbolLibrary::open(&FsSource::new(), "Device.kicad_sym")?,
+//!     SymbolLibrary::open(&FsSource::new(), "power.kicad_sym")?,
+//! ];
+//! let mut uuids = UuidGenerator::deterministic("divider");
+//! let schematic = circuit.schematic(&libraries, &SynthesisOptions::default(), &mut uuids)?;
+//! std::fs::write("divider.kicad_sch", schematic)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::{AddAssign, BitAnd, Index, IndexMut},
+    rc::Rc,
+};
+
+use kicad_common::{
+    netlist::{Component, Net as NetlistNet, NetNode, Netlist},
+    ConvertError, Error, UuidGenerator,
+};
+
+use crate::{
+    layout::SymbolLibrary,
+    synthesis::{write_synthesis, SynthesisOptions},
+    SchematicBuilder,
+};
+
+/// A circuit under construction. Clones share the same circuit.
+#[derive(Clone, Debug, Default)]
+pub struct Circuit {
+    inner: Rc<RefCell<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    parts: Vec<PartData>,
+    /// Union-find over the nets: merged nets point at the one they joined.
+    parents: Vec<usize>,
+    names: Vec<Option<String>>,
+    by_name: HashMap<String, usize>,
+    /// The net of each connected pin, by part index and pin number.
+    pins: BTreeMap<(usize, String), usize>,
+    /// Mistakes made through the operators, reported by [`Circuit::netlist`].
+    errors: Vec<String>,
+}
+
+#[derive(Debug)]
+struct PartData {
+    prefix: String,
+    lib_id: String,
+    value: String,
+    reference: Option<String>,
+    footprint: Option<String>,
+    fields: BTreeMap<String, String>,
+}
+
+impl Inner {
+    fn add_net(&mut self, name: Option<String>) -> usize {
+        let id = self.parents.len();
+        self.parents.push(id);
+        self.names.push(name);
+        id
+    }
+
+    fn root(&mut self, mut id: usize) -> usize {
+        while self.parents[id] != id {
+            self.parents[id] = self.parents[self.parents[id]];
+            id = self.parents[id];
+        }
+        id
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        if a != b {
+            self.parents[b] = a;
+        }
+    }
+
+    fn connect(&mut self, net: usize, part: usize, pin: &str) {
+        match self.pins.get(&(part, pin.to_string())) {
+            Some(&other) => self.join(net, other),
+            None => {
+                self.pins.insert((part, pin.to_string()), net);
+            },
+        }
+    }
+}
+
+impl Circuit {
+    pub fn new() -> Self {
+        Circuit::default()
+    }
+
+    /// The net called `name`; the same one every time.
+    pub fn net(&self, name: &str) -> Net {
+        let mut inner = self.inner.borrow_mut();
+        let id = match inner.by_name.get(name) {
+            Some(&id) => id,
+            None => {
+                let id = inner.add_net(Some(name.to_string()));
+                inner.by_name.insert(name.to_string(), id);
+                id
+            },
+        };
+        Net { circuit: self.clone(), id }
+    }
+
+    /// A net without a name, which the netlist names after one of its pins.
+    pub fn anonymous_net(&self) -> Net {
+        let id = self.inner.borrow_mut().add_net(None);
+        Net { circuit: self.clone(), id }
+    }
+
+    /// The nets `name0` to `name{width - 1}`.
+    pub fn bus(&self, name: &str, width: usize) -> Bus {
+        Bus { nets: (0..width).map(|i| self.net(&format!("{}{}", name, i))).collect() }
+    }
+
+    /// A part of the `Library:Symbol` `lib_id`, numbered with the reference
+    /// `prefix` when the netlist is made, unless given a
+    /// [reference](Part::reference).
+    pub fn part(&self, prefix: &str, lib_id: &str, value: &str) -> Part {
+        let mut inner = self.inner.borrow_mut();
+        inner.parts.push(PartData {
+            prefix: prefix.to_string(),
+            lib_id: lib_id.to_string(),
+            value: value.to_string(),
+            reference: None,
+            footprint: None,
+            fields: BTreeMap::new(),
+        });
+        Part { circuit: self.clone(), index: inner.parts.len() - 1 }
+    }
+
+    /// The references the parts get, in the order they were added: fixed
+    /// ones as given, the others numbered per prefix from 1, skipping the
+    /// fixed ones.
+    fn references(&self) -> Result<Vec<String>, Error> {
+        let inner = self.inner.borrow();
+        let mut taken = BTreeSet::new();
+        for reference in inner.parts.iter().filter_map(|part| part.reference.as_ref()) {
+            if !taken.insert(reference.clone()) {
+                return Err(ConvertError::new(format!("two parts are called {}", reference)).into());
+            }
+        }
+        let mut next: HashMap<&str, u32> = HashMap::new();
+        let references = inner.parts.iter().map(|part| {
+            if let Some(reference) = &part.reference {
+                return reference.clone();
+            }
+            let number = next.entry(part.prefix.as_str()).or_insert(1);
+            while taken.contains(&format!("{}{}", part.prefix, number)) {
+                *number += 1;
+            }
+            *number += 1;
+            format!("{}{}", part.prefix, *number - 1)
+        });
+        Ok(references.collect())
+    }
+
+    /// The parts and the nets that connect pins, the latter sorted by name
+    /// and numbered from 1. Nets without a name are named the way KiCad
+    /// does, `Net-(R1-Pad2)` after their first pin or
+    /// `unconnected-(R1-Pad2)` for a pin on its own.
+    pub fn netlist(&self) -> Result<Netlist, Error> {
+        let references = self.references()?;
+        let mut inner = self.inner.borrow_mut();
+        if let Some(error) = inner.errors.first() {
+            return Err(ConvertError::new(error.clone()).into());
+        }
+        let mut names: BTreeMap<usize, BTreeSet<String>> = BTreeMap::new();
+        for id in 0..inner.names.len() {
+            let root = inner.root(id);
+            let entry = names.entry(root).or_default();
+            entry.extend(inner.names[id].clone());
+        }
+        let mut nodes: BTreeMap<usize, Vec<NetNode>> = BTreeMap::new();
+        let pins: Vec<_> = inner.pins.iter().map(|((part, pin), &net)| (*part, pin.clone(), net)).collect();
+        for (part, pin, net) in pins {
+            let node = NetNode { reference: references[part].clone(), pin, ..Default::default() };
+            nodes.entry(inner.root(net)).or_default().push(node);
+        }
+        let mut nets = Vec::new();
+        for (root, mut nodes) in nodes {
+            nodes.sort_by(|a, b| node_order(a).cmp(&node_order(b)));
+            let name = match names.remove(&root).unwrap_or_default().into_iter().collect::<Vec<_>>().as_slice() {
+                [] => {
+                    let kind = if nodes.len() == 1 { "unconnected" } else { "Net" };
+                    format!("{}-({}-Pad{})", kind, nodes[0].reference, nodes[0].pin)
+                },
+                [name] => name.clone(),
+                [first, second, ..] => {
+                    return Err(ConvertError::new(format!("nets {} and {} are joined", first, second)).into());
+                },
+            };
+            nets.push(NetlistNet { name, nodes, ..Default::default() });
+        }
+        nets.sort_by(|a, b| a.name.cmp(&b.name));
+        for (code, net) in nets.iter_mut().enumerate() {
+            net.code = code as u32 + 1;
+        }
+
+        let components = inner.parts.iter().zip(references).map(|(part, reference)| {
+            let (lib, symbol) = match part.lib_id.split_once(':') {
+                Some((lib, symbol)) => (Some(lib.to_string()), symbol.to_string()),
+                None => (None, part.lib_id.clone()),
+            };
+            Component {
+                reference,
+                value: part.value.clone(),
+                footprint: part.footprint.clone(),
+                lib,
+                part: Some(symbol),
+                fields: part.fields.clone(),
+                sheet: Some("/".to_string()),
+                ..Default::default()
+            }
+        });
+        let source = Some("kicad-sch circuit".to_string());
+        Ok(Netlist { version: "E".to_string(), source, components: components.collect(), nets, ..Default::default() })
+    }
+
+    /// The circuit as a schematic file, its parts placed from `libraries`
+    /// and their pins labelled as [`Schematic::synthesize`](crate::Schematic::synthesize) does.
+    pub fn schematic(
+        &self,
+        libraries: &[SymbolLibrary],
+        options: &SynthesisOptions,
+        uuids: &mut UuidGenerator,
+    ) -> Result<String, Error> {
+        let netlist = self.netlist()?;
+        let builder = SchematicBuilder::new(uuids.generate("root"));
+        let mut schematic = builder.build()?;
+        schematic.synthesize(&netlist, libraries, options, uuids)?;
+        let src = builder.to_string();
+        let mut doc = kicad_sexp::cst::parse(&src).map_err(|e| Error::Parse(vec![e]))?;
+        write_synthesis(&schematic, libraries, &mut doc)?;
+        Ok(doc.to_string())
+    }
+}
+
+fn node_order(node: &NetNode) -> ((&str, u64, &str), (&str, u64, &str)) {
+    (natural(&node.reference), natural(&node.pin))
+}
+
+/// Orders `R2` before `R10`.
+fn natural(text: &str) -> (&str, u64, &str) {
+    let digits = text.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let prefix = &text[..text.len() - digits.len()];
+    let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+    (prefix, digits[..end].parse().unwrap_or_default(), &digits[end..])
+}
+
+/// A part of a [`Circuit`].
+#[derive(Clone, Debug)]
+pub struct Part {
+    circuit: Circuit,
+    index: usize,
+}
+
+impl Part {
+    /// Fixes the part's reference instead of numbering it.
+    pub fn reference(self, reference: &str) -> Self {
+        self.circuit.inner.borrow_mut().parts[self.index].reference = Some(reference.to_string());
+        self
+    }
+
+    /// The `Library:Footprint` of the part.
+    pub fn footprint(self, footprint: &str) -> Self {
+        self.circuit.inner.borrow_mut().parts[self.index].footprint = Some(footprint.to_string());
+        self
+    }
+
+    /// Another field, such as `MPN`.
+    pub fn field(self, name: &str, value: &str) -> Self {
+        let mut inner = self.circuit.inner.borrow_mut();
+        inner.parts[self.index].fields.insert(name.to_string(), value.to_string());
+        drop(inner);
+        self
+    }
+
+    /// The pin numbered `number`.
+    pub fn pin(&self, number: &str) -> Pin {
+        Pin { circuit: self.circuit.clone(), part: self.index, number: number.to_string() }
+    }
+
+    /// The pins numbered `numbers`, for connecting to a [`Bus`].
+    pub fn pins<'a>(&self, numbers: impl IntoIterator<Item = &'a str>) -> Vec<Pin> {
+        numbers.into_iter().map(|number| self.pin(number)).collect()
+    }
+}
+
+/// A pin of a [`Part`], connected by adding it to a [`Net`] or joining it
+/// to another pin with `&`.
+#[derive(Clone, Debug)]
+pub struct Pin {
+    circuit: Circuit,
+    part: usize,
+    number: String,
+}
+
+/// A net of a [`Circuit`]. Adding a pin connects it; adding another net
+/// joins the two, which the netlist rejects if both have names.
+#[derive(Clone, Debug)]
+pub struct Net {
+    circuit: Circuit,
+    id: usize,
+}
+
+impl Net {
+    /// Whether `other` is the same net or has been joined to it.
+    pub fn is(&self, other: &Net) -> bool {
+        let mut inner = self.circuit.inner.borrow_mut();
+        inner.root(self.id) == inner.root(other.id)
+    }
+
+    /// Connects `pin` to the net.
+    ///
+    /// # Panics
+    ///
+    /// If the pin is of another circuit.
+    pub fn connect(&self, pin: &Pin) {
+        assert!(Rc::ptr_eq(&self.circuit.inner, &pin.circuit.inner), "pin {} is of another circuit", pin.number);
+        self.circuit.inner.borrow_mut().connect(self.id, pin.part, &pin.number);
+    }
+
+    /// Whether `pin` is on this net.
+    pub fn has(&self, pin: &Pin) -> bool {
+        let mut inner = self.circuit.inner.borrow_mut();
+        match inner.pins.get(&(pin.part, pin.number.clone())).copied() {
+            Some(net) => inner.root(net) == inner.root(self.id),
+            None => false,
+        }
+    }
+}
+
+/// [Connects](Net::connect) the pin.
+impl AddAssign<Pin> for Net {
+    fn add_assign(&mut self, pin: Pin) {
+        self.connect(&pin);
+    }
+}
+
+/// # Panics
+///
+/// If the nets are of different circuits.
+impl AddAssign<Net> for Net {
+    fn add_assign(&mut self, net: Net) {
+        assert!(Rc::ptr_eq(&self.circuit.inner, &net.circuit.inner), "the net is of another circuit");
+        self.circuit.inner.borrow_mut().join(self.id, net.id);
+    }
+}
+
+/// Joins two pins on a net without a name.
+impl BitAnd<Pin> for Pin {
+    type Output = Net;
+
+    fn bitand(self, other: Pin) -> Net {
+        let net = self.circuit.anonymous_net();
+        net.connect(&self);
+        net.connect(&other);
+        net
+    }
+}
+
+/// Connects the pin, for chains like `gnd & r.pin("2") & c.pin("2")`.
+impl BitAnd<Pin> for Net {
+    type Output = Net;
+
+    fn bitand(self, pin: Pin) -> Net {
+        self.connect(&pin);
+        self
+    }
+}
+
+/// Nets of a [`Circuit`] numbered from 0, such as the lines of a data bus.
+#[derive(Clone, Debug)]
+pub struct Bus {
+    nets: Vec<Net>,
+}
+
+impl Bus {
+    pub fn width(&self) -> usize {
+        self.nets.len()
+    }
+
+    pub fn nets(&self) -> &[Net] {
+        &self.nets
+    }
+}
+
+impl Index<usize> for Bus {
+    type Output = Net;
+
+    fn index(&self, index: usize) -> &Net {
+        &self.nets[index]
+    }
+}
+
+impl IndexMut<usize> for Bus {
+    fn index_mut(&mut self, index: usize) -> &mut Net {
+        &mut self.nets[index]
+    }
+}
+
+/// Connects the pins to the nets one by one; a different number of pins
+/// than nets is reported by [`Circuit::netlist`].
+impl AddAssign<Vec<Pin>> for Bus {
+    fn add_assign(&mut self, pins: Vec<Pin>) {
+        if pins.len() != self.nets.len() {
+            let error = format!("{} pins on a bus of {} nets", pins.len(), self.nets.len());
+            if let Some(net) = self.nets.first() {
+                net.circuit.inner.borrow_mut().errors.push(error);
+            }
+            return;
+        }
+        for (net, pin) in self.nets.iter_mut().zip(pins) {
+            *net += pin;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Schematic;
+
+    const LIBRARY: &str = include_str!("../../reference-files/demo/demo.kicad_sym");
+    const NETLIST: &str = include_str!("../../reference-files/demo/demo.net");
+
+    #[test]
+    fn divider() {
+        let circuit = Circuit::new();
+        let (mut vin, mut vout, mut gnd) = (circuit.net("VIN"), circuit.net("VOUT"), circuit.net("GND"));
+        let footprint = "Resistor_SMD:R_0603_1608Metric";
+        let r1 = circuit.part("R", "Device:R", "10k").footprint(footprint).field("MPN", "RC0603FR-0710KL");
+        let r2 = circuit.part("R", "Device:R", "4k7").footprint(footprint);
+        let j1 = circuit.part("J", "Connector_Generic:Conn_01x03", "Conn_01x03");
+        vin += r1.pin("1");
+        vout += r1.pin("2") & r2.pin("1");
+        gnd += r2.pin("2");
+        let mut header = circuit.bus("J", 3);
+        header += j1.pins(["1", "2", "3"]);
+        vin += header[0].clone();
+        vout += header[1].clone();
+        assert!(!gnd.is(&header[2]));
+        let mut ground = circuit.anonymous_net();
+        ground += header[2].clone();
+        gnd += ground;
+        assert!(gnd.is(&header[2]) && gnd.has(&j1.pin("3")) && !gnd.has(&r1.pin("1")));
+
+        // The bus lines carry names of their own.
+        assert_eq!(circuit.netlist().unwrap_err().to_string(), "nets J0 and VIN are joined");
+        let circuit = Circuit::new();
+        let (mut vin, mut vout, mut gnd) = (circuit.net("VIN"), circuit.net("VOUT"), circuit.net("GND"));
+        let j1 = circuit.part("J", "Connector_Generic:Conn_01x03", "Conn_01x03").reference("J1");
+        let r1 = circuit.part("R", "Device:R", "10k").footprint(footprint);
+        let r2 = circuit.part("R", "Device:R", "4k7").footprint(footprint);
+        vin += j1.pin("1");
+        vin += r1.pin("1");
+        vout = vout & j1.pin("2") & r1.pin("2") & r2.pin("1");
+        gnd = gnd & j1.pin("3") & r2.pin("2");
+        assert!(vout.has(&r2.pin("1")) && gnd.has(&r2.pin("2")));
+
+        let netlist = circuit.netlist().unwrap();
+        let expected = Netlist::parse(NETLIST).unwrap();
+        let references: Vec<_> = netlist.components.iter().map(|component| component.reference.as_str()).collect();
+        assert_eq!(references, ["J1", "R1", "R2"]);
+        assert_eq!(netlist.components[1].lib_id().as_deref(), Some("Device:R"));
+        assert_eq!(netlist.components[2].footprint.as_deref(), Some(footprint));
+        let nets = |netlist: &Netlist| {
+            let mut nets: Vec<(String, Vec<(String, String)>)> = (netlist.nets.iter())
+                .map(|net| (net.name.clone(), net.nodes.iter().map(|n| (n.reference.clone(), n.pin.clone())).collect()))
+                .collect();
+            nets.sort();
+            nets
+        };
+        assert_eq!(nets(&netlist), nets(&expected));
+        assert_eq!(netlist.net("GND").map(|net| net.code), Some(1));
+        assert_eq!(Netlist::parse(&netlist.to_string()).unwrap(), netlist);
+
+        let demo = Circuit::new();
+        let mut gnd = demo.net("GND");
+        let r = demo.part("R", "demo:R", "1k");
+        let c = demo.part("C", "demo:C", "100n");
+        gnd = gnd & r.pin("2") & c.pin("2");
+        let _signal = r.pin("1") & c.pin("1");
+        let libraries = [SymbolLibrary::from_text("demo", LIBRARY)];
+        let options = SynthesisOptions { power: Some("demo".into()), ..Default::default() };
+        let mut uuids = UuidGenerator::deterministic("circuit");
+        let schematic = Schematic::parse(&demo.schematic(&libraries, &options, &mut uuids).unwrap()).unwrap();
+        assert!(gnd.has(&c.pin("2")));
+        assert_eq!(schematic.symbols.iter().filter(|symbol| symbol.is_virtual()).count(), 2);
+        let labels: Vec<_> = schematic.labels.iter().map(|label| label.text.as_str()).collect();
+        assert_eq!(labels, ["Net-(C1-Pad1)", "Net-(C1-Pad1)"]);
+    }
+
+    #[test]
+    fn mistakes() {
+        let circuit = Circuit::new();
+        let mut bus = circuit.bus("D", 2);
+        let u1 = circuit.part("U", "demo:R_Pack02_Split", "1k");
+        bus += u1.pins(["1", "2", "3"]);
+        assert_eq!(circuit.netlist().unwrap_err().to_string(), "3 pins on a bus of 2 nets");
+
+        let circuit = Circuit::new();
+        circuit.part("R", "Device:R", "1k").reference("R1");
+        circuit.part("R", "Device:R", "1k").reference("R1");
+        assert_eq!(circuit.netlist().unwrap_err().to_string(), "two parts are called R1");
+
+        // Numbering skips fixed references; lone pins are unconnected.
+        let circuit = Circuit::new();
+        let a = circuit.part("R", "Device:R", "1k");
+        circuit.part("R", "Device:R", "1k").reference("R1");
+        let mut net = circuit.anonymous_net();
+        net += a.pin("2");
+        let netlist = circuit.netlist().unwrap();
+        assert_eq!(netlist.components[0].reference, "R2");
+        assert_eq!(netlist.nets[0].name, "unconnected-(R2-Pad2)");
+        assert_eq!(natural("R10").cmp(&natural("R2")), std::cmp::Ordering::Greater);
+    }
+}
@@ -0,0 +1,504 @@
+//! A cleanup pass over the wires, buses and junctions of a sheet, for
+//! schematics written by generators or mangled by hand: wires are split
+//! into single segments, zero-length ones removed, collinear ones that
+//! touch or overlap merged, and junctions added where three or more
+//! connections meet and removed where they are not needed.
+//!
+//! A junction is needed at a wire end, pin or bus entry end that counts at
+//! least three connections: one for each wire ending there, two for a wire
+//! passing through, one for each pin and bus entry end. Wires crossing
+//! without an end at the crossing do not connect and get no junction.
+//! Merging stops at points where anything else connects, so connectivity
+//! is kept.
+//!
+//! Pins are those of sheets and of placed symbols, from the pins of their
+//! cached library symbols in the unit and body style they show. Pass the
+//! ends of any other pins, such as those of symbols whose library symbol
+//! is missing, to [`cleanup_wires`] to account for them.
+//!
+//! ```no_run
+//! use kicad_common::UuidGenerator;
+//! use kicad_sch::{
+//!     cleanup::{cleanup_wires, write_cleanup},
+//!     Schematic,
+//! };
+//!
+//! let src = std::fs::read_to_string("sheet.kicad_sch")?;
+//! let mut schematic = Schematic::parse(&src)?;
+//! let report = cleanup_wires(&mut schematic, &[], &mut UuidGenerator::random());
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_cleanup(&report, &mut doc)?;
+//! std::fs::write("sheet.kicad_sch", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{Color, ConvertError, Error, Point, Uuid, UuidGenerator};
+use kicad_sexp::{
+    cst::{Document, Node},
+    number::format_float,
+};
+
+use crate::{Junction, Schematic, Wire};
+
+const EPSILON: f64 = 1e-6;
+
+/// What [`cleanup_wires`] changed on the sheet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CleanupReport {
+    /// Wires, buses and junctions taken out of the sheet.
+    pub removed: Vec<Uuid>,
+    /// Wires and buses kept with other points, as they are now.
+    pub changed: Vec<Wire>,
+    /// Wire and bus segments split off a wire of several segments.
+    pub wires: Vec<Wire>,
+    pub buses: Vec<Wire>,
+    pub junctions: Vec<Junction>,
+}
+
+impl CleanupReport {
+    pub fn is_empty(&self) -> bool {
+        *self == CleanupReport::default()
+    }
+}
+
+fn same(a: Point, b: Point) -> bool {
+    (a.x - b.x).abs() < EPSILON && (a.y - b.y).abs() < EPSILON
+}
+
+fn cross(a: Point, b: Point, p: Point) -> f64 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn length(a: Point, b: Point) -> f64 {
+    ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt()
+}
+
+/// Whether `p` is on the line through `a` and `b`.
+fn on_line(a: Point, b: Point, p: Point) -> bool {
+    (cross(a, b, p) / length(a, b)).abs() < EPSILON
+}
+
+/// Where `p` is along the segment `a`–`b`, in units of its length.
+fn along(a: Point, b: Point, p: Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    ((p.x - a.x) * dx + (p.y - a.y) * dy) / (dx * dx + dy * dy)
+}
+
+fn ends(wire: &Wire) -> [Point; 2] {
+    [wire.points[0], wire.points[1]]
+}
+
+fn is_end(wire: &Wire, p: Point) -> bool {
+    ends(wire).into_iter().any(|end| same(end, p))
+}
+
+/// Whether `p` is on the segment `wire` and not one of its ends.
+fn passes(wire: &Wire, p: Point) -> bool {
+    let [a, b] = ends(wire);
+    let t = along(a, b, p);
+    on_line(a, b, p) && t > 0.0 && t < 1.0 && !is_end(wire, p)
+}
+
+/// The segments of `lines`, with zero-length ones dropped. The first
+/// segment of a line keeps its UUID; the others get new ones.
+fn split(lines: &[Wire], report: &mut CleanupReport, generator: &mut UuidGenerator, kind: &str) -> Vec<Wire> {
+    let mut segments = Vec::new();
+    for line in lines {
+        let mut pieces = line.points.windows(2).filter(|pair| !same(pair[0], pair[1]));
+        let Some(first) = pieces.next() else {
+            report.removed.push(line.uuid);
+            continue;
+        };
+        segments.push(Wire { points: first.to_vec(), ..line.clone() });
+        for pair in pieces {
+            let uuid = generator.generate(format!("{} {:?} {:?}", kind, pair[0], pair[1]));
+            segments.push(Wire { points: pair.to_vec(), uuid, ..line.clone() });
+        }
+    }
+    segments
+}
+
+/// `b` merged into `a` if they have the same stroke, lie on one line and
+/// overlap, or share an end where nothing else connects.
+fn merged(a: &Wire, b: &Wire, stops: &dyn Fn(Point) -> bool) -> Option<Vec<Point>> {
+    let [p, q] = ends(a);
+    if a.stroke != b.stroke || !ends(b).into_iter().all(|end| on_line(p, q, end)) {
+        return None;
+    }
+    let (t0, t1) = (along(p, q, b.points[0]), along(p, q, b.points[1]));
+    let (low, high) = (t0.min(t1), t0.max(t1));
+    let tolerance = EPSILON / length(p, q);
+    let touching = if high.abs() < tolerance {
+        Some(p)
+    } else if (low - 1.0).abs() < tolerance {
+        Some(q)
+    } else {
+        None
+    };
+    match touching {
+        Some(at) if stops(at) => return None,
+        Some(_) => {},
+        None if high < 0.0 || low > 1.0 => return None,
+        None => {},
+    }
+    let at = |t: f64| Point::new(p.x + (q.x - p.x) * t, p.y + (q.y - p.y) * t);
+    let start = if low < 0.0 { at(low) } else { p };
+    let end = if high > 1.0 { at(high) } else { q };
+    Some(vec![start, end])
+}
+
+/// Merges the segments in place until no pair merges.
+fn merge(segments: &mut Vec<Wire>, pins: &[Point], removed: &mut Vec<Uuid>) {
+    let mut i = 0;
+    while i < segments.len() {
+        let mut j = i + 1;
+        while j < segments.len() {
+            let stops = |at: Point| {
+                pins.iter().any(|pin| same(*pin, at))
+                    || segments
+                        .iter()
+                        .enumerate()
+                        .any(|(k, other)| k != i && k != j && (is_end(other, at) || passes(other, at)))
+            };
+            match merged(&segments[i], &segments[j], &stops) {
+                Some(points) => {
+                    segments[i].points = points;
+                    removed.push(segments.remove(j).uuid);
+                    j = i + 1;
+                },
+                None => j += 1,
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Connections at `at`, counted as described in the module documentation.
+fn connections(segments: &[Wire], pins: &[Point], at: Point) -> usize {
+    let wires: usize =
+        segments.iter().map(|wire| if is_end(wire, at) { 1 } else { 2 * passes(wire, at) as usize }).sum();
+    wires + pins.iter().filter(|pin| same(**pin, at)).count()
+}
+
+/// Adds the `lines` that differ from the ones `before` to `changed`, or to
+/// `added` if they are new.
+fn sort_out(lines: &[Wire], before: &[Wire], changed: &mut Vec<Wire>, added: &mut Vec<Wire>) {
+    for line in lines {
+        match before.iter().find(|old| old.uuid == line.uuid) {
+            Some(old) if old.points != line.points => changed.push(line.clone()),
+            Some(_) => {},
+            None => added.push(line.clone()),
+        }
+    }
+}
+
+/// Points on `segments` that need a junction.
+fn junction_points(segments: &[Wire], pins: &[Point]) -> Vec<Point> {
+    let mut points: Vec<Point> = Vec::new();
+    for at in segments.iter().flat_map(ends).chain(pins.iter().copied()) {
+        if !points.iter().any(|p| same(*p, at)) && connections(segments, pins, at) >= 3 {
+            points.push(at);
+        }
+    }
+    points
+}
+
+/// Cleans up the wires, buses and junctions of `schematic` as described in
+/// the module documentation. `pins` are the ends of pins the schematic
+/// does not know, see there. New items get UUIDs from `generator`.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(wires = schematic.wires.len())))]
+pub fn cleanup_wires(schematic: &mut Schematic, pins: &[Point], generator: &mut UuidGenerator) -> CleanupReport {
+    generator.reserve(schematic.wires.iter().chain(&schematic.buses).map(|wire| wire.uuid));
+    generator.reserve(schematic.junctions.iter().map(|junction| junction.uuid));
+    let mut report = CleanupReport::default();
+
+    let entries: Vec<Point> =
+        schematic.bus_entries.iter().flat_map(|entry| [entry.at, entry.at + entry.size]).collect();
+    let mut wire_pins: Vec<Point> = pins.to_vec();
+    wire_pins.extend(schematic.sheets.iter().flat_map(|sheet| sheet.pins.iter().map(|pin| pin.at.point())));
+    for symbol in &schematic.symbols {
+        let lib_id = symbol.lib_name.as_deref().unwrap_or(&symbol.lib_id);
+        match schematic.lib_symbol(lib_id) {
+            Some(lib) if lib.pins.is_empty() && lib.power => wire_pins.push(symbol.at.point()),
+            Some(lib) => wire_pins.extend(symbol.placed_pins(lib).map(|(_, at)| at)),
+            None => {},
+        }
+    }
+    wire_pins.extend(&entries);
+
+    let mut wires = split(&schematic.wires, &mut report, generator, "wire");
+    let mut buses = split(&schematic.buses, &mut report, generator, "bus");
+    merge(&mut wires, &wire_pins, &mut report.removed);
+    merge(&mut buses, &entries, &mut report.removed);
+
+    sort_out(&wires, &schematic.wires, &mut report.changed, &mut report.wires);
+    sort_out(&buses, &schematic.buses, &mut report.changed, &mut report.buses);
+
+    let mut needed = junction_points(&wires, &wire_pins);
+    for at in junction_points(&buses, &entries) {
+        if !needed.iter().any(|p| same(*p, at)) {
+            needed.push(at);
+        }
+    }
+    let mut kept: Vec<Point> = Vec::new();
+    schematic.junctions.retain(|junction| {
+        let keep = needed.iter().any(|p| same(*p, junction.at)) && !kept.iter().any(|p| same(*p, junction.at));
+        match keep {
+            true => kept.push(junction.at),
+            false => report.removed.push(junction.uuid),
+        }
+        keep
+    });
+    for at in needed.into_iter().filter(|at| !kept.iter().any(|p| same(*p, *at))) {
+        let uuid = generator.generate(format!("junction {:?}", at));
+        report.junctions.push(Junction { at, diameter: 0.0, color: Some(Color::default()), uuid });
+    }
+    schematic.junctions.extend(report.junctions.iter().cloned());
+
+    schematic.wires = wires;
+    schematic.buses = buses;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(removed = report.removed.len(), junctions = report.junctions.len(), "cleaned up wires");
+    report
+}
+
+fn item_uuid(node: &Node) -> Option<Uuid> {
+    node.child("uuid")?.items().get(1)?.value()?.parse().ok()
+}
+
+fn xy_node(p: Point) -> Node<'static> {
+    Node::tagged("xy", vec![Node::atom(format_float(p.x)), Node::atom(format_float(p.y))])
+}
+
+/// Replaces the points of a `wire` or `bus` node, on the line of the first
+/// old point.
+fn set_points(node: &mut Node<'_>, points: &[Point]) {
+    let Some(pts) = node.child_mut("pts") else {
+        return;
+    };
+    let items = pts.items_mut().expect("pts is a list");
+    let leading = items.get(1).map_or_else(|| " ".to_string(), |first| first.leading.to_string());
+    items.truncate(1);
+    for (i, p) in points.iter().enumerate() {
+        let mut xy = xy_node(*p);
+        if i == 0 {
+            xy.leading = leading.clone().into();
+        }
+        items.push(xy);
+    }
+}
+
+fn wire_node(tag: &'static str, wire: &Wire, indent: &str) -> Node<'static> {
+    let nested = format!("{}\t", indent);
+    let mut points: Vec<Node<'static>> = wire.points.iter().map(|p| xy_node(*p)).collect();
+    if let Some(first) = points.first_mut() {
+        first.leading = format!("{}\t", nested).into();
+    }
+    let mut pts = Node::tagged("pts", points);
+    pts.set_trailing(nested.clone());
+    let mut stroke = vec![
+        Node::tagged("width", vec![Node::atom(format_float(wire.stroke.width))]),
+        Node::tagged("type", vec![Node::atom(wire.stroke.kind.name())]),
+    ];
+    stroke.extend(wire.stroke.color.map(color_node));
+    let children = vec![pts, Node::block("stroke", Vec::new(), stroke, &nested), uuid_node(wire.uuid)];
+    Node::block(tag, Vec::new(), children, indent)
+}
+
+fn color_node(color: Color) -> Node<'static> {
+    let channels = [color.r, color.g, color.b].map(|channel| Node::atom(channel.to_string()));
+    Node::tagged("color", channels.into_iter().chain([Node::atom(format_float(color.a))]).collect())
+}
+
+fn uuid_node(uuid: Uuid) -> Node<'static> {
+    Node::tagged("uuid", vec![Node::string(&uuid.to_string())])
+}
+
+fn junction_node(junction: &Junction, indent: &str) -> Node<'static> {
+    let children = [
+        Node::tagged("at", vec![Node::atom(format_float(junction.at.x)), Node::atom(format_float(junction.at.y))]),
+        Node::tagged("diameter", vec![Node::atom(format_float(junction.diameter))]),
+    ]
+    .into_iter()
+    .chain(junction.color.map(color_node))
+    .chain([uuid_node(junction.uuid)])
+    .collect();
+    Node::block("junction", Vec::new(), children, indent)
+}
+
+/// Applies `report` to the schematic in `doc`: removes and updates the
+/// nodes by UUID and adds the new items after the last one of their kind,
+/// or else after the last connection item.
+pub fn write_cleanup(report: &CleanupReport, doc: &mut Document<'_>) -> Result<(), Error> {
+    let root = doc.nodes.iter_mut().find(|node| node.tag() == Some("kicad_sch"));
+    let items = root.and_then(|root| root.items_mut());
+    let items = items.ok_or_else(|| ConvertError::new("expected a (kicad_sch ...) root"))?;
+    items.retain(|item| !(is_connection(item) && item_uuid(item).is_some_and(|uuid| report.removed.contains(&uuid))));
+    for item in items.iter_mut().filter(|item| matches!(item.tag(), Some("wire" | "bus"))) {
+        if let Some(wire) = report.changed.iter().find(|wire| item_uuid(item) == Some(wire.uuid)) {
+            set_points(item, &wire.points);
+        }
+    }
+
+    let junctions = &report.junctions;
+    insert(items, "junction", |indent| junctions.iter().map(|junction| junction_node(junction, indent)).collect());
+    insert(items, "wire", |indent| report.wires.iter().map(|wire| wire_node("wire", wire, indent)).collect());
+    insert(items, "bus", |indent| report.buses.iter().map(|bus| wire_node("bus", bus, indent)).collect());
+    Ok(())
+}
+
+fn is_connection(node: &Node) -> bool {
+    matches!(node.tag(), Some("junction" | "no_connect" | "bus_entry" | "wire" | "bus"))
+}
+
+/// Inserts the `nodes` of kind `tag` after the last node of that kind. With
+/// none, junctions go before the first connection item and wires and buses
+/// after the last one, in the order KiCad writes them.
+fn insert(items: &mut Vec<Node<'_>>, tag: &str, nodes: impl FnOnce(&str) -> Vec<Node<'static>>) {
+    let connections = match tag {
+        "junction" => items.iter().position(is_connection),
+        _ => items.iter().rposition(is_connection).map(|last| last + 1),
+    };
+    let at = items.iter().rposition(|item| item.tag() == Some(tag)).map(|last| last + 1).or(connections);
+    let at = at.unwrap_or_else(|| {
+        let after = items.iter().position(|item| matches!(item.tag(), Some("sheet_instances" | "embedded_fonts")));
+        after.unwrap_or(items.len())
+    });
+    // Indent like the item before, or one tab for a file written on one line.
+    let indent = match items.get(at.saturating_sub(1)) {
+        Some(item) if at > 1 && item.leading.contains('\n') => item.leading.to_string(),
+        _ => "\n\t".to_string(),
+    };
+    items.splice(at..at, nodes(&indent));
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::Stroke;
+
+    use super::*;
+
+    fn wire(points: &[(f64, f64)], n: u128) -> Wire {
+        let points = points.iter().map(|&(x, y)| Point::new(x, y)).collect();
+        Wire { points, stroke: Stroke::default(), uuid: Uuid::from_u128(n) }
+    }
+
+    fn points(wires: &[Wire]) -> Vec<Vec<(f64, f64)>> {
+        wires.iter().map(|wire| wire.points.iter().map(|p| (p.x, p.y)).collect()).collect()
+    }
+
+    #[test]
+    fn merges_and_removes() {
+        let mut schematic = Schematic {
+            wires: vec![
+                wire(&[(0.0, 0.0), (10.0, 0.0)], 1),
+                wire(&[(10.0, 0.0), (20.0, 0.0)], 2),
+                wire(&[(15.0, 0.0), (25.0, 0.0)], 3),
+                wire(&[(5.0, 5.0), (5.0, 5.0)], 4),
+                wire(&[(0.0, 10.0), (0.0, 20.0), (10.0, 20.0)], 5),
+            ],
+            ..Default::default()
+        };
+        let report = cleanup_wires(&mut schematic, &[], &mut UuidGenerator::deterministic("test"));
+        assert_eq!(points(&schematic.wires), [
+            vec![(0.0, 0.0), (25.0, 0.0)],
+            vec![(0.0, 10.0), (0.0, 20.0)],
+            vec![(0.0, 20.0), (10.0, 20.0)],
+        ]);
+        assert_eq!(report.removed, [4, 2, 3].map(Uuid::from_u128));
+        assert_eq!(report.changed.iter().map(|wire| wire.uuid).collect::<Vec<_>>(), [1, 5].map(Uuid::from_u128));
+        assert_eq!(points(&report.wires), [vec![(0.0, 20.0), (10.0, 20.0)]]);
+        assert!(report.junctions.is_empty());
+
+        let again = cleanup_wires(&mut schematic, &[], &mut UuidGenerator::deterministic("test"));
+        assert!(again.is_empty(), "{:?}", again);
+    }
+
+    #[test]
+    fn junctions() {
+        let stale = Junction { at: Point::new(0.0, 0.0), uuid: Uuid::from_u128(10), ..Default::default() };
+        let kept = Junction { at: Point::new(10.0, 0.0), uuid: Uuid::from_u128(11), ..Default::default() };
+        let mut schematic = Schematic {
+            wires: vec![
+                // A T at (10, 0), a crossing at (30, 0) and a corner at (0, 0).
+                wire(&[(0.0, 0.0), (40.0, 0.0)], 1),
+                wire(&[(10.0, 0.0), (10.0, 10.0)], 2),
+                wire(&[(30.0, -5.0), (30.0, 5.0)], 3),
+                wire(&[(0.0, 0.0), (0.0, 10.0)], 4),
+                // Ends at a T, so it does not merge with the wire along it.
+                wire(&[(10.0, 10.0), (20.0, 10.0)], 5),
+                wire(&[(10.0, 10.0), (10.0, 20.0)], 6),
+            ],
+            junctions: vec![stale.clone(), kept.clone(), kept.clone()],
+            ..Default::default()
+        };
+        let pins = [Point::new(20.0, 0.0)];
+        let report = cleanup_wires(&mut schematic, &pins, &mut UuidGenerator::deterministic("test"));
+        assert_eq!(report.removed, [stale.uuid, kept.uuid]);
+        let added: Vec<_> = report.junctions.iter().map(|junction| (junction.at.x, junction.at.y)).collect();
+        assert_eq!(added, [(10.0, 10.0), (20.0, 0.0)]);
+        assert_eq!(schematic.junctions.len(), 3);
+        assert_eq!(schematic.wires.len(), 6);
+    }
+
+    #[test]
+    fn demo_is_clean() {
+        let mut schematic = Schematic::parse(include_str!("../../reference-files/demo/demo.kicad_sch")).unwrap();
+        let before = schematic.clone();
+        let report = cleanup_wires(&mut schematic, &[], &mut UuidGenerator::deterministic("demo"));
+        assert!(report.is_empty(), "{:?}", report);
+        assert_eq!(schematic, before);
+    }
+
+    #[test]
+    fn junction_on_a_pin() {
+        let mut schematic = Schematic::parse(include_str!("../../reference-files/demo/demo.kicad_sch")).unwrap();
+        // A second wire at pin 1 of R1, below the one in the file.
+        let pin = Point::new(100.33, 59.69);
+        let r1 = schematic.symbol("R1").unwrap();
+        let lib = schematic.lib_symbol(&r1.lib_id).unwrap();
+        assert!(r1.placed_pins(lib).any(|(pin_1, at)| pin_1.number == "1" && at == pin));
+        schematic.wires.push(wire(&[(100.33, 59.69), (110.49, 59.69)], 1));
+        let junction = Junction { at: pin, uuid: Uuid::from_u128(2), ..Default::default() };
+        schematic.junctions.push(junction.clone());
+        let report = cleanup_wires(&mut schematic, &[], &mut UuidGenerator::deterministic("demo"));
+        assert!(report.is_empty(), "{:?}", report);
+        assert!(schematic.junctions.contains(&junction));
+    }
+
+    /// A wire as KiCad writes it.
+    fn wire_text(points: &str, uuid: &str) -> String {
+        let stroke = "\t\t(stroke\n\t\t\t(width 0)\n\t\t\t(type default)\n\t\t)\n";
+        format!("\t(wire\n\t\t(pts\n\t\t\t{}\n\t\t)\n{}\t\t(uuid \"{}\")\n\t)\n", points, stroke, uuid)
+    }
+
+    #[test]
+    fn write_back() {
+        let src = [
+            "(kicad_sch\n\t(version 20250114)\n".to_string(),
+            "\t(junction\n\t\t(at 0 0)\n\t\t(diameter 0)\n\t\t(color 0 0 0 0)\n".to_string(),
+            "\t\t(uuid \"00000000-0000-0000-0000-00000000000a\")\n\t)\n".to_string(),
+            wire_text("(xy 0 0) (xy 10 0)", "00000000-0000-0000-0000-000000000001"),
+            wire_text("(xy 10 0) (xy 20 0)", "00000000-0000-0000-0000-000000000002"),
+            wire_text("(xy 5 0) (xy 5 10) (xy 15 10)", "00000000-0000-0000-0000-000000000003"),
+            "\t(sheet_instances\n\t\t(path \"/\"\n\t\t\t(page \"1\")\n\t\t)\n\t)\n)\n".to_string(),
+        ]
+        .concat();
+        let mut schematic = Schematic::parse(&src).unwrap();
+        let report = cleanup_wires(&mut schematic, &[], &mut UuidGenerator::deterministic("test"));
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        write_cleanup(&report, &mut doc).unwrap();
+
+        let text = doc.to_string();
+        let junction = format!(
+            "\t(junction\n\t\t(at 5 0)\n\t\t(diameter 0)\n\t\t(color 0 0 0 0)\n\t\t(uuid \"{}\")\n\t)\n",
+            report.junctions[0].uuid,
+        );
+        let merged = wire_text("(xy 0 0) (xy 20 0)", "00000000-0000-0000-0000-000000000001");
+        let kept = wire_text("(xy 5 0) (xy 5 10)", "00000000-0000-0000-0000-000000000003");
+        let split = wire_text("(xy 5 10) (xy 15 10)", &report.wires[0].uuid.to_string());
+        assert!(text.contains(&[junction, merged, kept, split].concat()), "{}", text);
+        assert!(!text.contains("00000000-0000-0000-0000-00000000000a"));
+        assert_eq!(Schematic::parse(&text).unwrap(), schematic);
+    }
+}
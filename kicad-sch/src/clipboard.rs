@@ -0,0 +1,116 @@
+//! The text the schematic editor puts on the clipboard: the selected items
+//! without a `(kicad_sch ...)` root, after a `(lib_symbols ...)` list of the
+//! library symbols they use.
+//!
+//! [`parse`] reads such a fragment into the typed model, and [`copy`] cuts
+//! one out of a schematic file so it can be pasted into a running KiCad.
+//!
+//! ```no_run
+//! use kicad_sch::{clipboard, Schematic};
+//!
+//! let src = std::fs::read_to_string("sheet.kicad_sch")?;
+//! let schematic = Schematic::parse(&src)?;
+//! let resistors: Vec<_> = schematic.symbols.iter().filter(|s| s.lib_id == "Device:R").map(|s| s.uuid).collect();
+//! let doc = kicad_sexp::cst::parse(&src)?;
+//! let text = clipboard::copy(&doc, &resistors)?;
+//! assert_eq!(clipboard::parse(&text)?.symbols.len(), resistors.len());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{
+    convert::{FromSexp, List},
+    ConvertError, Error, Uuid,
+};
+use kicad_sexp::cst::{Document, Node};
+
+use crate::Schematic;
+
+/// Parses clipboard text: root-less items, or a whole schematic. The items
+/// of a fragment go into an otherwise empty schematic, whose header fields,
+/// like `version`, keep their defaults.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = src.len())))]
+pub fn parse(src: &str) -> Result<Schematic, Error> {
+    let sexps = kicad_sexp::parse(src).map_err(Error::Parse)?;
+    let mut schematic = Schematic::default();
+    for sexp in &sexps {
+        let list = List::new(sexp).ok_or_else(|| ConvertError::new("expected a list on the clipboard"))?;
+        match list.tag() {
+            "kicad_sch" if sexps.len() == 1 => return Ok(Schematic::from_sexp(&list)?),
+            _ => schematic.add_item(&list)?,
+        }
+    }
+    Ok(schematic)
+}
+
+fn item_uuid(node: &Node) -> Option<Uuid> {
+    node.child("uuid")?.items().get(1)?.value()?.parse().ok()
+}
+
+/// A node as written one level further out.
+fn dedent(node: &Node) -> String {
+    node.to_string().replace("\n\t", "\n")
+}
+
+/// The items of the schematic `doc` with UUIDs in `uuids` as clipboard
+/// text, in file order, after the library symbols of the selected symbols.
+pub fn copy(doc: &Document<'_>, uuids: &[Uuid]) -> Result<String, Error> {
+    let root = doc.nodes.iter().find(|node| node.tag() == Some("kicad_sch"));
+    let root = root.ok_or_else(|| ConvertError::new("expected a (kicad_sch ...) root"))?;
+    let selected: Vec<&Node> = (root.items().iter().skip(1))
+        .filter(|node| node.tag() != Some("lib_symbols") && item_uuid(node).is_some_and(|uuid| uuids.contains(&uuid)))
+        .collect();
+    let lib_id = |node: &Node| {
+        let lib_id = node.child("lib_name").or_else(|| node.child("lib_id"))?;
+        Some(lib_id.items().get(1)?.value()?.into_owned())
+    };
+    let symbols = selected.iter().filter(|node| node.tag() == Some("symbol"));
+    let used: Vec<String> = symbols.filter_map(|node| lib_id(node)).collect();
+    let lib_symbols = root.child("lib_symbols").map_or(&[][..], |node| node.items());
+    let lib_symbols: Vec<&Node> = (lib_symbols.iter())
+        .filter(|node| node.tag() == Some("symbol"))
+        .filter(|node| node.items().get(1).and_then(Node::value).is_some_and(|name| used.iter().any(|id| *id == name)))
+        .collect();
+
+    let mut text = String::new();
+    if !lib_symbols.is_empty() {
+        text.push_str("(lib_symbols");
+        lib_symbols.iter().for_each(|node| text.push_str(&dedent(node)));
+        text.push_str("\n)\n");
+    }
+    for node in selected {
+        text.push_str(dedent(node).trim_start());
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_sch");
+
+    #[test]
+    fn copy_and_paste() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+        let resistors: Vec<_> = schematic.symbols.iter().filter(|s| s.lib_id == "Device:R").cloned().collect();
+        let mut uuids: Vec<Uuid> = resistors.iter().map(|symbol| symbol.uuid).collect();
+        uuids.push(schematic.wires[0].uuid);
+        let doc = kicad_sexp::cst::parse(DEMO).unwrap();
+        let text = copy(&doc, &uuids).unwrap();
+        assert!(text.starts_with("(lib_symbols\n\t(symbol \"Device:R\"\n\t\t(pin_numbers\n"), "{}", text);
+        assert!(text.contains("\n)\n(wire\n\t(pts\n"), "{}", text);
+        assert!(!text.contains("Conn_01x03"), "{}", text);
+
+        let pasted = parse(&text).unwrap();
+        assert_eq!(pasted.lib_symbols, schematic.lib_symbols[..1]);
+        assert_eq!(pasted.symbols, resistors);
+        assert_eq!(pasted.wires, schematic.wires[..1]);
+        assert!(pasted.labels.is_empty() && pasted.sheets.is_empty());
+        assert_eq!(pasted.version, 0);
+
+        assert_eq!(parse(DEMO).unwrap(), schematic);
+        assert!(parse("(wire (pts (xy 0 0) (xy 1 0)) (uuid \"x\"))").is_err());
+        assert!(parse("wire").unwrap_err().to_string().contains("expected a list"));
+    }
+}
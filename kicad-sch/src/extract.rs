@@ -0,0 +1,66 @@
+//! Pulls one kind of item out of a schematic file without parsing the rest,
+//! for tools that need a single piece of data from large sheets quickly.
+
+use kicad_common::{
+    convert::{parse_child, parse_children, scan_document, FromSexp, List, Result},
+    Error,
+};
+
+use crate::{Label, Sheet};
+
+/// The hierarchical sheets placed on this sheet.
+pub fn extract_sheets_only(src: &str) -> std::result::Result<Vec<Sheet>, Error> {
+    parse_children(src, "kicad_sch", &["sheet"])
+}
+
+/// Local, global and hierarchical labels.
+pub fn extract_labels_only(src: &str) -> std::result::Result<Vec<Label>, Error> {
+    parse_children(src, "kicad_sch", &["label", "global_label", "hierarchical_label"])
+}
+
+/// The value of a `property` list if it is the `Reference` field.
+struct Reference(Option<String>);
+
+impl FromSexp for Reference {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Reference(if list.string(0)? == "Reference" { Some(list.string(1)?) } else { None }))
+    }
+}
+
+/// `Reference` fields of the placed symbols, in file order. Library
+/// symbols are not included.
+pub fn extract_symbol_refs_only(src: &str) -> std::result::Result<Vec<String>, Error> {
+    let mut references = Vec::new();
+    for symbol in scan_document(src, "kicad_sch")?.filter(|child| child.tag == "symbol") {
+        for property in symbol.children().filter(|child| child.tag == "property") {
+            let Reference(reference) = parse_child(&property, "symbol").map_err(|e| match e {
+                Error::Convert(e) => Error::Convert(e.within("kicad_sch")),
+                e => e,
+            })?;
+            if let Some(reference) = reference {
+                references.push(reference);
+                break;
+            }
+        }
+    }
+    Ok(references)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Schematic;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_sch");
+
+    #[test]
+    fn extract() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+
+        assert_eq!(extract_sheets_only(DEMO).unwrap(), schematic.sheets);
+        assert_eq!(extract_labels_only(DEMO).unwrap(), schematic.labels);
+        let references: Vec<_> = schematic.symbols.iter().filter_map(|symbol| symbol.reference()).collect();
+        assert_eq!(extract_symbol_refs_only(DEMO).unwrap(), references);
+        assert!(!references.is_empty());
+    }
+}
@@ -0,0 +1,463 @@
+//! Multi-sheet designs: the root schematic, the files its sheets reference,
+//! and a flattened view of the whole design.
+//!
+//! A file used by several sheets is loaded once but appears once per sheet
+//! instance. Its symbols get their references and units per instance from
+//! their `instances` section, keyed by the sheet path of UUIDs.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use kicad_common::{ConvertError, Effects, Error, Font, HAlign, Justify, Position, Uuid, UuidGenerator};
+use kicad_sexp::{schema::Issue, source::Source};
+
+use crate::{LabelKind, LabelShape, Schematic, Sheet, SheetPin, SymbolInstance};
+
+/// Spacing of the pins [`Hierarchy::sync_sheet_pins`] adds, KiCad's
+/// default grid.
+const PIN_PITCH: f64 = 2.54;
+
+/// One instance of a sheet in the design; the root is a sheet too.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SheetNode {
+    /// Sheet path of UUIDs, `/<root>` for the root sheet and
+    /// `/<root>/<sheet>/...` below it, as in symbol `instances`.
+    pub path: String,
+    /// Sheet path of names, `/` for the root and `/power/` below it.
+    pub name_path: String,
+    /// The file, relative to the source like the root's path.
+    pub file: PathBuf,
+    /// The page number, if the files have one for this instance.
+    pub page: Option<String>,
+    /// Index of the parent in [`Hierarchy::sheets`], `None` for the root.
+    pub parent: Option<usize>,
+    /// UUID of the sheet symbol in the parent, `None` for the root.
+    pub uuid: Option<Uuid>,
+}
+
+/// A placed symbol at one sheet instance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlatSymbol<'h> {
+    pub sheet: &'h SheetNode,
+    pub symbol: &'h SymbolInstance,
+    /// The reference at this instance, falling back to the `Reference`
+    /// field for files without `instances`.
+    pub reference: &'h str,
+    pub unit: u32,
+    /// The `Value` and `Footprint` fields, as overridden at this instance.
+    pub value: &'h str,
+    pub footprint: Option<&'h str>,
+}
+
+/// The units of one part, which share a reference at their sheet
+/// instances.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Part<'h> {
+    pub reference: &'h str,
+    /// By unit number.
+    pub units: Vec<FlatSymbol<'h>>,
+}
+
+/// A loaded hierarchy of schematic files.
+#[derive(Clone, Debug, Default)]
+pub struct Hierarchy {
+    /// Project name instance data is looked up for, the root file's name.
+    pub project: String,
+    /// Every file, loaded once.
+    pub files: BTreeMap<PathBuf, Schematic>,
+    /// Every sheet instance, the root first, then depth first in file order.
+    pub sheets: Vec<SheetNode>,
+}
+
+impl Hierarchy {
+    /// Loads the root schematic at `root` and every file below it. Sheet
+    /// file names are relative to the file of the sheet symbol.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(sheets)))]
+    pub fn load(source: &dyn Source, root: impl Into<PathBuf>) -> Result<Self, Error> {
+        let root = root.into();
+        let project = root.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+        let mut hierarchy = Hierarchy { project, ..Default::default() };
+        let schematic = hierarchy.load_file(source, &root)?;
+        let path = format!("/{}", schematic.uuid.map(|uuid| uuid.to_string()).unwrap_or_default());
+        let page = schematic.sheet_instances.iter().find(|instance| instance.path == "/").map(|i| i.page.clone());
+        hierarchy.sheets.push(SheetNode {
+            path,
+            name_path: "/".to_string(),
+            file: root,
+            page,
+            parent: None,
+            uuid: None,
+        });
+        hierarchy.load_children(source, 0)?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("sheets", hierarchy.sheets.len());
+        Ok(hierarchy)
+    }
+
+    fn load_file(&mut self, source: &dyn Source, path: &Path) -> Result<&Schematic, Error> {
+        if !self.files.contains_key(path) {
+            let src = source.read_to_string(path).map_err(|e| Error::from(e).in_file(path))?;
+            let schematic = Schematic::parse(&src).map_err(|e| e.in_file(path))?;
+            self.files.insert(path.to_path_buf(), schematic);
+        }
+        Ok(&self.files[path])
+    }
+
+    fn load_children(&mut self, source: &dyn Source, parent: usize) -> Result<(), Error> {
+        let node = self.sheets[parent].clone();
+        let dir = node.file.parent().unwrap_or(Path::new(""));
+        let sheets = self.files[&node.file].sheets.clone();
+        for sheet in &sheets {
+            let name = sheet.name().unwrap_or_default();
+            let file = sheet.file().ok_or_else(|| sheet_error(&node, name, "has no file"))?;
+            let file = dir.join(file);
+            if self.ancestors(parent).any(|ancestor| ancestor.file == file) {
+                return Err(sheet_error(&node, name, "includes one of its own parents"));
+            }
+            self.load_file(source, &file)?;
+            self.sheets.push(SheetNode {
+                path: format!("{}/{}", node.path, sheet.uuid),
+                name_path: format!("{}{}/", node.name_path, name),
+                file,
+                page: self.page(sheet, &node.path),
+                parent: Some(parent),
+                uuid: Some(sheet.uuid),
+            });
+            self.load_children(source, self.sheets.len() - 1)?;
+        }
+        Ok(())
+    }
+
+    fn page(&self, sheet: &Sheet, parent_path: &str) -> Option<String> {
+        let instances = sheet.instances.iter().filter(|instance| instance.path == parent_path);
+        self.pick(instances, |instance| &instance.project).map(|instance| instance.page.clone())
+    }
+
+    /// The instance for this project, or any instance at the path for
+    /// files copied from another project.
+    fn pick<'a, T>(
+        &self,
+        instances: impl Iterator<Item = &'a T> + Clone,
+        project: impl Fn(&T) -> &String,
+    ) -> Option<&'a T> {
+        instances.clone().find(|instance| *project(instance) == self.project).or_else(|| instances.clone().next())
+    }
+
+    /// `sheet` and the sheets above it, nearest first.
+    pub fn ancestors(&self, sheet: usize) -> impl Iterator<Item = &SheetNode> {
+        std::iter::successors(Some(&self.sheets[sheet]), |node| node.parent.map(|parent| &self.sheets[parent]))
+    }
+
+    pub fn root(&self) -> &Schematic {
+        self.schematic(&self.sheets[0])
+    }
+
+    /// The file of a sheet instance.
+    pub fn schematic(&self, sheet: &SheetNode) -> &Schematic {
+        &self.files[&sheet.file]
+    }
+
+    /// The sheet symbol that places `sheet` in its parent, `None` for the
+    /// root.
+    pub fn sheet_symbol(&self, sheet: &SheetNode) -> Option<&Sheet> {
+        let parent = &self.sheets[sheet.parent?];
+        self.schematic(parent).sheets.iter().find(|symbol| Some(symbol.uuid) == sheet.uuid)
+    }
+
+    /// Finds a sheet instance by its path of names, as in `/power/`.
+    pub fn sheet(&self, name_path: &str) -> Option<&SheetNode> {
+        self.sheets.iter().find(|sheet| sheet.name_path == name_path)
+    }
+
+    /// Every placed symbol at every sheet instance, in sheet order. A file
+    /// used by two sheets lists its symbols twice, with the references of
+    /// each instance, and a multi-unit part once per unit; count parts with
+    /// [`Hierarchy::parts`].
+    pub fn symbols(&self) -> impl Iterator<Item = FlatSymbol<'_>> {
+        self.sheets.iter().flat_map(move |sheet| {
+            self.schematic(sheet).symbols.iter().map(move |symbol| {
+                let instances = symbol.instances.iter().filter(|instance| instance.path == sheet.path);
+                let instance = self.pick(instances, |instance| &instance.project).or_else(|| {
+                    let path = legacy_path(sheet, symbol);
+                    self.root().symbol_instances.iter().find(|instance| instance.path == path)
+                });
+                let value = instance.and_then(|instance| instance.value.as_deref());
+                let footprint = instance.and_then(|instance| instance.footprint.as_deref());
+                FlatSymbol {
+                    sheet,
+                    symbol,
+                    reference: instance.map_or(symbol.reference().unwrap_or_default(), |i| &i.reference),
+                    unit: instance.map_or(symbol.unit, |instance| instance.unit),
+                    value: value.or(symbol.value()).unwrap_or_default(),
+                    footprint: footprint.filter(|footprint| !footprint.is_empty()).or(symbol.footprint()),
+                }
+            })
+        })
+    }
+
+    /// The placed symbols grouped into parts by their references, in the
+    /// order of their first units.
+    pub fn parts(&self) -> Vec<Part<'_>> {
+        let mut parts: Vec<Part> = Vec::new();
+        let mut index: BTreeMap<&str, usize> = BTreeMap::new();
+        for symbol in self.symbols() {
+            match index.get(symbol.reference) {
+                Some(&i) => parts[i].units.push(symbol),
+                None => {
+                    index.insert(symbol.reference, parts.len());
+                    parts.push(Part { reference: symbol.reference, units: vec![symbol] });
+                },
+            }
+        }
+        for part in &mut parts {
+            part.units.sort_by_key(|unit| unit.unit);
+        }
+        parts
+    }
+
+    /// Finds a symbol of the design by its resolved reference.
+    pub fn symbol(&self, reference: &str) -> Option<FlatSymbol<'_>> {
+        self.symbols().find(|symbol| symbol.reference == reference)
+    }
+
+    /// Each sheet symbol once, as the file it is in, its UUID and the file
+    /// it places, in sheet order.
+    pub(crate) fn sheet_symbols(&self) -> Vec<(PathBuf, Uuid, PathBuf)> {
+        let mut out: Vec<(PathBuf, Uuid, PathBuf)> = Vec::new();
+        for sheet in &self.sheets {
+            let (Some(parent), Some(uuid)) = (sheet.parent, sheet.uuid) else {
+                continue;
+            };
+            let parent = &self.sheets[parent].file;
+            if !out.iter().any(|(file, other, _)| file == parent && *other == uuid) {
+                out.push((parent.clone(), uuid, sheet.file.clone()));
+            }
+        }
+        out
+    }
+
+    /// Checks the pins of every sheet symbol against the hierarchical labels
+    /// of the file it places: each label needs a pin of its name and shape,
+    /// each pin a label, and no name may have two pins.
+    pub fn check_sheet_pins(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        for (parent, uuid, child) in self.sheet_symbols() {
+            let sheet = self.sheet_by_uuid(&parent, uuid);
+            let labels = hierarchical_labels(&self.files[&child]);
+            let name = format!("sheet {} in {}", sheet.name().unwrap_or_default(), parent.display());
+            let mut issue = |message: String| {
+                issues.push(Issue { path: vec!["kicad_sch".into(), "sheet".into(), "pin".into()], message });
+            };
+            for (i, pin) in sheet.pins.iter().enumerate() {
+                if sheet.pins[..i].iter().any(|other| other.name == pin.name) {
+                    issue(format!("{} has pin {} twice", name, pin.name));
+                    continue;
+                }
+                match labels.iter().find(|(label, _)| *label == pin.name) {
+                    Some((_, shape)) if *shape != pin.shape => issue(format!(
+                        "{} has pin {} as {} but its hierarchical label is {}",
+                        name,
+                        pin.name,
+                        pin.shape.name(),
+                        shape.name()
+                    )),
+                    Some(_) => {},
+                    None => issue(format!(
+                        "{} has pin {} but {} has no such hierarchical label",
+                        name,
+                        pin.name,
+                        child.display()
+                    )),
+                }
+            }
+            for (label, _) in &labels {
+                if !sheet.pins.iter().any(|pin| pin.name == *label) {
+                    issue(format!("{} has no pin for hierarchical label {} of {}", name, label, child.display()));
+                }
+            }
+        }
+        issues
+    }
+
+    /// Fixes what [`Hierarchy::check_sheet_pins`] reports where it can: pins
+    /// take the shape of their label, and the second pin of a name is
+    /// removed. With `add_missing`, labels without a pin get one, inputs on
+    /// the left edge and the others on the right, on the first free place
+    /// of the 2.54 mm grid from the top; the sheet grows if its edge is
+    /// full. Pins without a label are kept, as wires may end on them.
+    ///
+    /// Returns the changed sheet symbols, as the file they are in and their
+    /// UUID; write them back with [`Sheet::write_pins`]. New pins get UUIDs
+    /// from `generator`.
+    pub fn sync_sheet_pins(&mut self, add_missing: bool, generator: &mut UuidGenerator) -> Vec<(PathBuf, Uuid)> {
+        for schematic in self.files.values() {
+            generator.reserve(schematic.sheets.iter().flat_map(|sheet| sheet.pins.iter().filter_map(|pin| pin.uuid)));
+        }
+        let mut changed = Vec::new();
+        for (parent, uuid, child) in self.sheet_symbols() {
+            let labels = hierarchical_labels(&self.files[&child]);
+            let schematic = self.files.get_mut(&parent).expect("sheet files are loaded");
+            let sheet = schematic.sheets.iter_mut().find(|sheet| sheet.uuid == uuid).expect("sheets are loaded");
+            let before = sheet.clone();
+            let mut seen: Vec<String> = Vec::new();
+            sheet.pins.retain(|pin| {
+                let first = !seen.contains(&pin.name);
+                seen.push(pin.name.clone());
+                first
+            });
+            for pin in &mut sheet.pins {
+                if let Some((_, shape)) = labels.iter().find(|(label, _)| *label == pin.name) {
+                    pin.shape = *shape;
+                }
+            }
+            if add_missing {
+                for (label, shape) in &labels {
+                    if !sheet.pins.iter().any(|pin| pin.name == *label) {
+                        let pin = new_pin(sheet, label, *shape, generator);
+                        sheet.pins.push(pin);
+                    }
+                }
+            }
+            if *sheet != before {
+                changed.push((parent, uuid));
+            }
+        }
+        changed
+    }
+
+    /// Gives the parts linked to footprints new references, as after a
+    /// board's geographic reannotation. `references` pairs the sheet path
+    /// of a footprint, `/<sheet>/.../<symbol>` below the root as boards
+    /// store it, with its new reference; every unit of the part is renamed
+    /// at its sheet instance, so references can be swapped in one call.
+    ///
+    /// Returns the changed symbols, as the file they are in and their UUID;
+    /// write them back with [`SymbolInstance::write_instances`].
+    pub fn back_annotate(&mut self, references: &[(String, String)]) -> Vec<(PathBuf, Uuid)> {
+        let mut renamed: BTreeMap<String, &str> = BTreeMap::new();
+        for symbol in self.symbols() {
+            let path = legacy_path(symbol.sheet, symbol.symbol);
+            if let Some((_, new)) = references.iter().find(|(footprint, _)| *footprint == path) {
+                renamed.insert(symbol.reference.to_string(), new);
+            }
+        }
+        let updates: Vec<(PathBuf, String, Uuid, String, u32)> = (self.symbols())
+            .filter_map(|symbol| {
+                let new = renamed.get(symbol.reference).filter(|new| **new != symbol.reference)?;
+                let sheet = symbol.sheet;
+                Some((sheet.file.clone(), sheet.path.clone(), symbol.symbol.uuid, new.to_string(), symbol.unit))
+            })
+            .collect();
+        let mut changed = Vec::new();
+        for (file, path, uuid, reference, unit) in updates {
+            let schematic = self.files.get_mut(&file).expect("sheet files are loaded");
+            let symbol = schematic.symbols.iter_mut().find(|symbol| symbol.uuid == uuid).expect("symbols are loaded");
+            symbol.set_instance(&self.project, &path, &reference, unit);
+            if !changed.contains(&(file.clone(), uuid)) {
+                changed.push((file, uuid));
+            }
+        }
+        changed
+    }
+
+    /// Swaps units of parts linked to footprints, as after a board's gate
+    /// swaps. Each swap names the sheet path of a footprint, as for
+    /// [`Hierarchy::back_annotate`], and two units of its part; the symbols
+    /// of those units at the footprint's sheet instance trade unit numbers,
+    /// so the wires on them move to the other gate's pins. Swaps apply in
+    /// order.
+    ///
+    /// Returns the changed symbols, as the file they are in and their UUID;
+    /// write them back with [`SymbolInstance::write_unit`] and
+    /// [`SymbolInstance::write_instances`].
+    pub fn swap_units(&mut self, swaps: &[(String, u32, u32)]) -> Vec<(PathBuf, Uuid)> {
+        let mut changed = Vec::new();
+        for (path, a, b) in swaps {
+            let Some(linked) = self.symbols().find(|symbol| legacy_path(symbol.sheet, symbol.symbol) == *path) else {
+                continue;
+            };
+            let (reference, sheet) = (linked.reference.to_string(), linked.sheet.path.clone());
+            let updates: Vec<(PathBuf, Uuid, u32)> = (self.symbols())
+                .filter(|symbol| symbol.reference == reference && symbol.sheet.path == sheet)
+                .filter_map(|symbol| {
+                    let unit = match symbol.unit {
+                        unit if unit == *a => *b,
+                        unit if unit == *b => *a,
+                        _ => return None,
+                    };
+                    Some((symbol.sheet.file.clone(), symbol.symbol.uuid, unit))
+                })
+                .collect();
+            for (file, uuid, unit) in updates {
+                let schematic = self.files.get_mut(&file).expect("sheet files are loaded");
+                let symbol = schematic.symbols.iter_mut().find(|symbol| symbol.uuid == uuid);
+                let symbol = symbol.expect("symbols are loaded");
+                symbol.unit = unit;
+                symbol.set_instance(&self.project, &sheet, &reference, unit);
+                if !changed.contains(&(file.clone(), uuid)) {
+                    changed.push((file, uuid));
+                }
+            }
+        }
+        changed
+    }
+
+    fn sheet_by_uuid(&self, file: &Path, uuid: Uuid) -> &Sheet {
+        self.files[file].sheets.iter().find(|sheet| sheet.uuid == uuid).expect("sheets are loaded")
+    }
+}
+
+/// The names and shapes of the hierarchical labels of `schematic`, each
+/// name once with the shape of its first label.
+fn hierarchical_labels(schematic: &Schematic) -> Vec<(String, LabelShape)> {
+    let mut labels: Vec<(String, LabelShape)> = Vec::new();
+    for label in schematic.labels.iter().filter(|label| label.kind == LabelKind::Hierarchical) {
+        if !labels.iter().any(|(name, _)| *name == label.text) {
+            labels.push((label.text.clone(), label.shape.unwrap_or_default()));
+        }
+    }
+    labels
+}
+
+/// A pin for the label `name` on the first free grid place of its edge of
+/// `sheet`, which grows to fit it.
+fn new_pin(sheet: &mut Sheet, name: &str, shape: LabelShape, generator: &mut UuidGenerator) -> SheetPin {
+    let (x, angle, horizontal) = match shape {
+        LabelShape::Input => (sheet.at.x, 180.0, HAlign::Left),
+        _ => (sheet.at.x + sheet.size.width, 0.0, HAlign::Right),
+    };
+    let taken = |y: f64| {
+        sheet.pins.iter().any(|pin| (pin.at.x - x).abs() < 1e-6 && (pin.at.y - y).abs() < PIN_PITCH / 2.0)
+    };
+    // Rounded to KiCad's nanometers, so that grid places compare equal.
+    let place = |step: u32| ((sheet.at.y + PIN_PITCH * f64::from(step)) * 1e6).round() / 1e6;
+    let y = (1..).map(place).find(|y| !taken(*y)).expect("a free place");
+    sheet.size.height = sheet.size.height.max(y - sheet.at.y + PIN_PITCH);
+    let effects = Effects {
+        font: Font { height: 1.27, width: 1.27, ..Default::default() },
+        justify: Justify { horizontal, ..Default::default() },
+        ..Default::default()
+    };
+    SheetPin {
+        name: name.to_string(),
+        shape,
+        at: Position::new(x, y, angle),
+        effects,
+        uuid: Some(generator.generate(format!("sheet {} pin {}", sheet.uuid, name))),
+    }
+}
+
+/// The path of `symbol` at `sheet` in KiCad 6 `symbol_instances`, which
+/// leaves out the root sheet: `/<sheet>/<symbol>`.
+fn legacy_path(sheet: &SheetNode, symbol: &SymbolInstance) -> String {
+    match sheet.path.splitn(3, '/').nth(2) {
+        Some(below_root) => format!("/{}/{}", below_root, symbol.uuid),
+        None => format!("/{}", symbol.uuid),
+    }
+}
+
+fn sheet_error(node: &SheetNode, name: &str, message: &str) -> Error {
+    Error::from(ConvertError::new(format!("sheet `{}` {}", name, message))).in_file(&node.file)
+}
@@ -0,0 +1,264 @@
+//! Finds the wires, labels and power symbols of one net on a sheet and
+//! renders the sheet as SVG with that net highlighted, for design reviews.
+//!
+//! Connectivity is derived from wire geometry, labels and power symbols.
+//! Symbol pins are not resolved, so a label placed directly on a pin is
+//! highlighted on its own.
+
+use std::fmt::Write;
+
+use kicad_common::Point;
+use kicad_sexp::number::format_float;
+
+use crate::{Schematic, SymbolInstance, Wire};
+
+const EPSILON: f64 = 1e-6;
+
+/// Indexes into the [`Schematic`] item lists that belong to a net.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetHighlight {
+    pub net: String,
+    pub wires: Vec<usize>,
+    pub labels: Vec<usize>,
+    pub junctions: Vec<usize>,
+    /// Power symbols defining the net, indexes into `symbols`.
+    pub power_symbols: Vec<usize>,
+}
+
+fn on_segment(p: Point, a: Point, b: Point) -> bool {
+    let cross = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    let within = |v: f64, a: f64, b: f64| v >= a.min(b) - EPSILON && v <= a.max(b) + EPSILON;
+    cross.abs() < EPSILON && within(p.x, a.x, b.x) && within(p.y, a.y, b.y)
+}
+
+fn wire_contains(wire: &Wire, p: Point) -> bool {
+    wire.points.windows(2).any(|segment| on_segment(p, segment[0], segment[1]))
+}
+
+fn wires_touch(a: &Wire, b: &Wire) -> bool {
+    let ends = |wire: &Wire| [wire.points.first().copied(), wire.points.last().copied()];
+    ends(a).into_iter().flatten().any(|p| wire_contains(b, p))
+        || ends(b).into_iter().flatten().any(|p| wire_contains(a, p))
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn power_net<'s>(schematic: &Schematic, symbol: &'s SymbolInstance) -> Option<&'s str> {
+    let lib_id = symbol.lib_name.as_deref().unwrap_or(&symbol.lib_id);
+    schematic.lib_symbol(lib_id).filter(|lib| lib.power).and(symbol.value())
+}
+
+/// Collects the items of the net named `net`.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(net = %net, wires = schematic.wires.len())))]
+pub fn highlight_net(schematic: &Schematic, net: &str) -> NetHighlight {
+    let wires = &schematic.wires;
+    let mut parent: Vec<usize> = (0..wires.len()).collect();
+    for i in 0..wires.len() {
+        for j in i + 1..wires.len() {
+            if wires_touch(&wires[i], &wires[j]) {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let labels: Vec<usize> = (0..schematic.labels.len()).filter(|&i| schematic.labels[i].text == net).collect();
+    let power_symbols: Vec<usize> = (0..schematic.symbols.len())
+        .filter(|&i| power_net(schematic, &schematic.symbols[i]) == Some(net))
+        .collect();
+    let anchors: Vec<Point> = labels
+        .iter()
+        .map(|&i| schematic.labels[i].at.point())
+        .chain(power_symbols.iter().map(|&i| schematic.symbols[i].at.point()))
+        .collect();
+
+    let mut roots: Vec<usize> = Vec::new();
+    for (i, wire) in wires.iter().enumerate() {
+        if anchors.iter().any(|anchor| wire_contains(wire, *anchor)) {
+            roots.push(find(&mut parent, i));
+        }
+    }
+    let wire_indexes: Vec<usize> = (0..wires.len()).filter(|&i| roots.contains(&find(&mut parent, i))).collect();
+    let junctions = (0..schematic.junctions.len())
+        .filter(|&i| wire_indexes.iter().any(|&w| wire_contains(&wires[w], schematic.junctions[i].at)))
+        .collect();
+
+    NetHighlight { net: net.to_string(), wires: wire_indexes, labels, junctions, power_symbols }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn n(value: f64) -> String {
+    format_float(value)
+}
+
+fn class(highlighted: bool, dimmed: bool) -> &'static str {
+    match (highlighted, dimmed) {
+        (true, _) => " hl",
+        (false, true) => " dim",
+        (false, false) => "",
+    }
+}
+
+fn text(svg: &mut String, class: &str, at: Point, angle: f64, size: f64, value: &str) {
+    let _ = write!(svg, "<text class=\"{}\" x=\"{}\" y=\"{}\" font-size=\"{}\"", class, n(at.x), n(at.y), n(size));
+    if angle != 0.0 {
+        let _ = write!(svg, " transform=\"rotate({} {} {})\"", n(-angle), n(at.x), n(at.y));
+    }
+    let _ = writeln!(svg, ">{}</text>", escape(value));
+}
+
+/// Renders the sheet as SVG in paper coordinates, highlighting `highlight`
+/// and dimming everything else. Symbol bodies are drawn as their visible
+/// fields only.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(symbols = schematic.symbols.len())))]
+pub fn render_svg(schematic: &Schematic, highlight: Option<&NetHighlight>) -> String {
+    let (width, height) = schematic.paper.dimensions();
+    let dimmed = highlight.is_some();
+    let hl = |indexes: fn(&NetHighlight) -> &Vec<usize>, i: usize| highlight.is_some_and(|h| indexes(h).contains(&i));
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\">",
+        w = n(width),
+        h = n(height)
+    );
+    svg.push_str(
+        "<style>\
+         .wire{stroke:#008400;stroke-width:0.15;fill:none;stroke-linecap:round}\
+         .bus{stroke:#0000c8;stroke-width:0.3;fill:none}\
+         .junction{fill:#008400}\
+         .nc{stroke:#0000c8;stroke-width:0.15}\
+         .sheet{stroke:#840000;stroke-width:0.15;fill:none}\
+         text{font-family:sans-serif;fill:#000}\
+         .label{fill:#008400}\
+         .dim{opacity:0.3}\
+         .wire.hl{stroke:#e00000;stroke-width:0.5}\
+         .junction.hl,text.hl{fill:#e00000;font-weight:bold}\
+         </style>\n",
+    );
+    let _ = writeln!(svg, "<rect width=\"{}\" height=\"{}\" fill=\"#fff\"/>", n(width), n(height));
+
+    let polyline = |svg: &mut String, class: &str, wire: &Wire| {
+        let points: Vec<String> = wire.points.iter().map(|p| format!("{},{}", n(p.x), n(p.y))).collect();
+        let _ = writeln!(svg, "<polyline class=\"{}\" points=\"{}\"/>", class, points.join(" "));
+    };
+    for (i, wire) in schematic.wires.iter().enumerate() {
+        polyline(&mut svg, &format!("wire{}", class(hl(|h| &h.wires, i), dimmed)), wire);
+    }
+    for bus in &schematic.buses {
+        polyline(&mut svg, &format!("bus{}", class(false, dimmed)), bus);
+    }
+    for (i, junction) in schematic.junctions.iter().enumerate() {
+        let r = if junction.diameter > 0.0 { junction.diameter / 2.0 } else { 0.4575 };
+        let _ = writeln!(
+            svg,
+            "<circle class=\"junction{}\" cx=\"{}\" cy=\"{}\" r=\"{}\"/>",
+            class(hl(|h| &h.junctions, i), dimmed),
+            n(junction.at.x),
+            n(junction.at.y),
+            n(r)
+        );
+    }
+    for no_connect in &schematic.no_connects {
+        let (x, y, d) = (no_connect.at.x, no_connect.at.y, 0.635);
+        let _ = writeln!(
+            svg,
+            "<path class=\"nc{}\" d=\"M{} {}L{} {}M{} {}L{} {}\"/>",
+            class(false, dimmed),
+            n(x - d),
+            n(y - d),
+            n(x + d),
+            n(y + d),
+            n(x - d),
+            n(y + d),
+            n(x + d),
+            n(y - d)
+        );
+    }
+    for (i, label) in schematic.labels.iter().enumerate() {
+        let class = format!("label{}", class(hl(|h| &h.labels, i), dimmed));
+        text(&mut svg, &class, label.at.point(), label.at.angle, label.effects.font.height, &label.text);
+    }
+    for (i, symbol) in schematic.symbols.iter().enumerate() {
+        let class = class(hl(|h| &h.power_symbols, i), dimmed).trim_start();
+        for property in symbol.properties.iter().filter(|property| !property.hide && !property.value.is_empty()) {
+            let Some(at) = property.at else { continue };
+            let size = property.effects.as_ref().map_or(1.27, |effects| effects.font.height);
+            text(&mut svg, class, at.point(), at.angle, size, &property.value);
+        }
+    }
+    for sheet in &schematic.sheets {
+        let _ = writeln!(
+            svg,
+            "<rect class=\"sheet{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+            class(false, dimmed),
+            n(sheet.at.x),
+            n(sheet.at.y),
+            n(sheet.size.width),
+            n(sheet.size.height)
+        );
+        if let Some(name) = sheet.name() {
+            let at = Point::new(sheet.at.x, sheet.at.y - 0.7);
+            text(&mut svg, class(false, dimmed).trim_start(), at, 0.0, 1.27, name);
+        }
+    }
+    for item in &schematic.texts {
+        text(
+            &mut svg,
+            class(false, dimmed).trim_start(),
+            item.at.point(),
+            item.at.angle,
+            item.effects.font.height,
+            &item.text,
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_sch");
+
+    #[test]
+    fn nets() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+
+        let vout = highlight_net(&schematic, "VOUT");
+        assert_eq!((vout.wires, vout.labels, vout.junctions), (vec![0, 1], vec![0, 3], vec![0]));
+
+        let vin = highlight_net(&schematic, "VIN");
+        assert_eq!((vin.wires, vin.labels), (vec![2, 4], vec![1, 2, 4]));
+
+        let gnd = highlight_net(&schematic, "GND");
+        assert_eq!((gnd.wires, gnd.power_symbols), (vec![3, 5, 6], vec![2, 4]));
+
+        assert_eq!(highlight_net(&schematic, "NC").wires, Vec::<usize>::new());
+    }
+
+    #[test]
+    fn svg() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+        let svg = render_svg(&schematic, Some(&highlight_net(&schematic, "VOUT")));
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"297mm\" height=\"210mm\""));
+        assert_eq!(svg.matches("<polyline class=\"wire hl\"").count(), 2);
+        assert_eq!(svg.matches("<polyline class=\"wire dim\"").count(), 5);
+        assert!(svg.contains("<circle class=\"junction hl\" cx=\"100.33\" cy=\"69.85\""));
+        assert!(svg.contains(">VOUT</text>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(!render_svg(&schematic, None).contains(" dim\""));
+    }
+}
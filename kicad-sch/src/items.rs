@@ -0,0 +1,229 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    geometry::child_points,
+    uuid::item_uuid,
+    Color, ConvertError, Effects, Error, Point, Position, Property, Stroke, Uuid,
+};
+use kicad_sexp::cst::{Document, Node};
+
+fn required_uuid(list: &List) -> Result<Uuid> {
+    item_uuid(list)?.ok_or_else(|| list.error("missing (uuid ...)"))
+}
+
+/// A wire or bus segment chain, `(wire (pts ...) ...)` or `(bus (pts ...) ...)`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Wire {
+    pub points: Vec<Point>,
+    pub stroke: Stroke,
+    pub uuid: Uuid,
+}
+
+impl FromSexp for Wire {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Wire {
+            points: child_points(list)?,
+            stroke: list.parse("stroke")?.unwrap_or_default(),
+            uuid: required_uuid(list)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Junction {
+    pub at: Point,
+    /// Zero means the default diameter.
+    pub diameter: f64,
+    pub color: Option<Color>,
+    pub uuid: Uuid,
+}
+
+impl FromSexp for Junction {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Junction {
+            at: list.parse_required("at")?,
+            diameter: list.child_number("diameter")?.unwrap_or_default(),
+            color: list.parse("color")?,
+            uuid: required_uuid(list)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoConnect {
+    pub at: Point,
+    pub uuid: Uuid,
+}
+
+impl FromSexp for NoConnect {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(NoConnect { at: list.parse_required("at")?, uuid: required_uuid(list)? })
+    }
+}
+
+/// A diagonal wire-to-bus entry starting at `at` and spanning `size`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BusEntry {
+    pub at: Point,
+    pub size: Point,
+    pub stroke: Stroke,
+    pub uuid: Uuid,
+}
+
+impl FromSexp for BusEntry {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(BusEntry {
+            at: list.parse_required("at")?,
+            size: list.parse_required("size")?,
+            stroke: list.parse("stroke")?.unwrap_or_default(),
+            uuid: required_uuid(list)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LabelKind {
+    #[default]
+    Local,
+    Global,
+    Hierarchical,
+}
+
+/// Electrical shape of global and hierarchical labels and of sheet pins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LabelShape {
+    Input,
+    Output,
+    #[default]
+    Bidirectional,
+    TriState,
+    Passive,
+    Dot,
+    Round,
+    Diamond,
+    Rectangle,
+}
+
+impl LabelShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "input" => LabelShape::Input,
+            "output" => LabelShape::Output,
+            "bidirectional" => LabelShape::Bidirectional,
+            "tri_state" => LabelShape::TriState,
+            "passive" => LabelShape::Passive,
+            "dot" => LabelShape::Dot,
+            "round" => LabelShape::Round,
+            "diamond" => LabelShape::Diamond,
+            "rectangle" => LabelShape::Rectangle,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LabelShape::Input => "input",
+            LabelShape::Output => "output",
+            LabelShape::Bidirectional => "bidirectional",
+            LabelShape::TriState => "tri_state",
+            LabelShape::Passive => "passive",
+            LabelShape::Dot => "dot",
+            LabelShape::Round => "round",
+            LabelShape::Diamond => "diamond",
+            LabelShape::Rectangle => "rectangle",
+        }
+    }
+}
+
+pub(crate) fn label_shape(list: &List, index: usize) -> Result<LabelShape> {
+    let name = list.symbol(index)?;
+    LabelShape::from_name(name).ok_or_else(|| list.error(format!("unknown label shape `{}`", name)))
+}
+
+/// A local, global or hierarchical net label.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label {
+    pub kind: LabelKind,
+    pub text: String,
+    pub at: Position,
+    /// Only set for global and hierarchical labels.
+    pub shape: Option<LabelShape>,
+    pub fields_autoplaced: bool,
+    pub effects: Effects,
+    pub uuid: Uuid,
+    pub properties: Vec<Property>,
+}
+
+impl Label {
+    /// Writes [`Label::text`] to this label's node in the schematic `doc`,
+    /// found by UUID.
+    pub fn write_text(&self, doc: &mut Document<'_>) -> std::result::Result<(), Error> {
+        let root = doc.nodes.iter_mut().find(|node| node.tag() == Some("kicad_sch"));
+        let items = root.and_then(|root| root.items_mut());
+        let items = items.ok_or_else(|| ConvertError::new("expected a (kicad_sch ...) root"))?;
+        let uuid = |node: &Node| node.child("uuid")?.items().get(1)?.value()?.parse::<Uuid>().ok();
+        let is_label = |tag: Option<&str>| matches!(tag, Some("label" | "global_label" | "hierarchical_label"));
+        let label = items.iter_mut().find(|item| is_label(item.tag()) && uuid(item) == Some(self.uuid));
+        let label = label.ok_or_else(|| ConvertError::new(format!("no label {} in the schematic", self.uuid)))?;
+        let text = label.items_mut().and_then(|items| items.get_mut(1));
+        let text = text.ok_or_else(|| ConvertError::new(format!("label {} has no text", self.uuid)))?;
+        if text.value().as_deref() != Some(self.text.as_str()) {
+            text.set_string(&self.text);
+        }
+        Ok(())
+    }
+}
+
+impl FromSexp for Label {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let kind = match list.tag() {
+            "label" => LabelKind::Local,
+            "global_label" => LabelKind::Global,
+            "hierarchical_label" => LabelKind::Hierarchical,
+            other => return Err(list.error(format!("`{}` is not a label", other))),
+        };
+        let shape = match list.child("shape") {
+            Some(shape) => Some(label_shape(&shape, 0).map_err(|e| e.within(list.tag()))?),
+            None => None,
+        };
+        Ok(Label {
+            kind,
+            text: list.string(0)?,
+            at: list.parse_required("at")?,
+            shape,
+            fields_autoplaced: list.flag("fields_autoplaced")?,
+            effects: list.parse("effects")?.unwrap_or_default(),
+            uuid: required_uuid(list)?,
+            properties: list.parse_all("property")?,
+        })
+    }
+}
+
+/// A free text item.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Text {
+    pub text: String,
+    pub at: Position,
+    pub exclude_from_sim: bool,
+    pub effects: Effects,
+    pub uuid: Uuid,
+}
+
+impl FromSexp for Text {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Text {
+            text: list.string(0)?,
+            at: list.parse_required("at")?,
+            exclude_from_sim: list.flag("exclude_from_sim")?,
+            effects: list.parse("effects")?.unwrap_or_default(),
+            uuid: required_uuid(list)?,
+        })
+    }
+}
@@ -0,0 +1,807 @@
+//! Schematic content built in code, for generators that turn a netlist or
+//! an HDL description into a sheet: symbols placed from a `.kicad_sym`
+//! library, with labels on their pins instead of wires.
+//!
+//! [`Schematic::arrange`] lays the placed symbols out in rows, a new row
+//! for each reference prefix, so a generated sheet reads like a parts list
+//! instead of a pile at the origin. The labels and power symbols on their
+//! pins move with them. A design of several sheets is arranged a sheet at
+//! a time. [`write_symbols`] adds the placed symbols and their library
+//! symbols to the schematic file, and [`write_labels`] the new labels.
+//!
+//! ```no_run
+//! use kicad_common::{Position, UuidGenerator};
+//! use kicad_sch::{
+//!     layout::{write_labels, write_symbols, ArrangeOptions, SymbolLibrary},
+//!     LabelKind, SchematicBuilder,
+//! };
+//! use kicad_sexp::source::FsSource;
+//!
+//! let library = SymbolLibrary::open(&FsSource::new(), "Device.kicad_sym")?;
+//! let mut uuids = UuidGenerator::deterministic("divider");
+//! let builder = SchematicBuilder::new(uuids.generate("root"));
+//! let mut schematic = builder.build()?;
+//! for reference in ["R1", "R2"] {
+//!     schematic.place_symbol(&library, "Device:R", Position::default(), reference, &mut uuids)?;
+//! }
+//! schematic.arrange(&ArrangeOptions::default());
+//! schematic.label_pin("R1", "2", LabelKind::Local, "VOUT", &mut uuids)?;
+//! schematic.label_pin("R2", "1", LabelKind::Local, "VOUT", &mut uuids)?;
+//! let src = builder.to_string();
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_symbols(&schematic, &library, &mut doc)?;
+//! write_labels(&schematic, &mut doc)?;
+//! std::fs::write("divider.kicad_sch", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use kicad_common::{
+    text::{Effects, Font, HAlign, Justify, VAlign},
+    ConvertError, Error, FromSexp, List, Point, Position, Uuid, UuidGenerator,
+};
+use kicad_sexp::{
+    cst::{Document, Node, NodeKind},
+    number::format_float,
+    source::Source,
+};
+
+use crate::{
+    symbol::{instances_node, property_node},
+    Label, LabelKind, LabelShape, LibPin, LibSymbol, Mirror, Schematic, SymbolInstance, SymbolPin,
+};
+
+/// Top-level items KiCad writes after the placed symbols.
+const AFTER_SYMBOLS: [&str; 4] = ["sheet", "sheet_instances", "symbol_instances", "embedded_fonts"];
+
+/// Top-level items KiCad writes after the no-connect flags.
+const AFTER_NO_CONNECTS: [&str; 12] = [
+    "bus_entry",
+    "wire",
+    "bus",
+    "polyline",
+    "text",
+    "label",
+    "global_label",
+    "hierarchical_label",
+    "symbol",
+    "sheet",
+    "sheet_instances",
+    "embedded_fonts",
+];
+
+/// Top-level items KiCad writes after the labels.
+const AFTER_LABELS: [&str; 5] = ["symbol", "sheet", "sheet_instances", "symbol_instances", "embedded_fonts"];
+
+/// A `.kicad_sym` library whose symbols are placed into schematics.
+#[derive(Clone, Debug)]
+pub struct SymbolLibrary {
+    nickname: String,
+    path: PathBuf,
+    src: String,
+}
+
+impl SymbolLibrary {
+    /// Reads the library at `path`. Its nickname is the file name without
+    /// `.kicad_sym`, the one KiCad suggests when the library is added.
+    pub fn open(source: &dyn Source, path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let src = source.read_to_string(&path).map_err(|e| Error::Io(e).in_file(path.clone()))?;
+        let nickname = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+        Ok(SymbolLibrary { nickname, path, src })
+    }
+
+    /// The library with the text `src`, under `nickname`.
+    pub fn from_text(nickname: &str, src: impl Into<String>) -> Self {
+        let path = PathBuf::from(format!("{}.kicad_sym", nickname));
+        SymbolLibrary { nickname: nickname.to_string(), path, src: src.into() }
+    }
+
+    pub fn nickname(&self) -> &str {
+        &self.nickname
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the library has a power symbol `name`, like `GND` of KiCad's
+    /// `power` library.
+    pub fn is_power(&self, name: &str) -> bool {
+        self.cached(name).is_ok_and(|node| node.child("power").is_some())
+    }
+
+    /// The `lib_symbols` entry of the symbol `name` as KiCad caches it in a
+    /// schematic: named `nickname:name`, and for a derived symbol, the
+    /// units of its root ancestor with the fields and flags it overrides.
+    fn cached(&self, name: &str) -> Result<Node<'static>, Error> {
+        let error = |message: String| Error::from(ConvertError::new(message)).in_file(self.path.clone());
+        let doc = kicad_sexp::cst::parse(&self.src).map_err(|e| Error::Parse(vec![e]).in_file(self.path.clone()))?;
+        let root = doc.nodes.iter().find(|node| node.tag() == Some("kicad_symbol_lib"));
+        let root = root.ok_or_else(|| error("expected a (kicad_symbol_lib ...) root".to_string()))?;
+        let symbols: Vec<&Node> = root.items().iter().filter(|item| item.tag() == Some("symbol")).collect();
+        let find = |name: &str| {
+            let found = symbols.iter().position(|symbol| arg(symbol, 1).as_deref() == Some(name));
+            found.ok_or_else(|| error(format!("no symbol `{}` in the library", name)))
+        };
+        let mut chain = vec![find(name)?];
+        while let Some(parent) = symbols[chain[chain.len() - 1]].child("extends").and_then(|extends| arg(extends, 1)) {
+            let parent = find(&parent)?;
+            if chain.contains(&parent) {
+                return Err(error(format!("symbol `{}` extends itself", name)));
+            }
+            chain.push(parent);
+        }
+
+        let mut node = symbols[chain.pop().expect("the chain starts at the symbol")].clone().into_owned();
+        let base = arg(&node, 1).unwrap_or_default();
+        let items = node.items_mut().expect("tagged nodes are lists");
+        for derived in chain.iter().rev().map(|&i| symbols[i]) {
+            for child in derived.items().iter().skip(2) {
+                let Some(tag @ ("property" | "exclude_from_sim" | "in_bom" | "on_board")) = child.tag() else {
+                    continue;
+                };
+                let key = arg(child, 1);
+                let same = |item: &Node| item.tag() == Some(tag) && (tag != "property" || arg(item, 1) == key);
+                let child = child.clone().into_owned();
+                match items.iter().position(same) {
+                    Some(i) => items[i] = child,
+                    None => {
+                        let last = items.iter().rposition(|item| item.tag() == Some("property"));
+                        items.insert(last.map_or(items.len(), |last| last + 1), child);
+                    },
+                }
+            }
+        }
+        items[1].set_string(&format!("{}:{}", self.nickname, name));
+        if base != name {
+            for unit in items.iter_mut().filter(|item| item.tag() == Some("symbol")) {
+                let renamed = arg(unit, 1).and_then(|unit| Some(format!("{}{}", name, unit.strip_prefix(&base)?)));
+                if let Some(renamed) = renamed {
+                    unit.items_mut().expect("tagged nodes are lists")[1].set_string(&renamed);
+                }
+            }
+        }
+        indent(&mut node);
+        Ok(node)
+    }
+}
+
+/// Settings for [`Schematic::arrange`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArrangeOptions {
+    /// Top left corner of the first row.
+    pub origin: Point,
+    /// Width of a row; `None` for the paper's width less a margin as wide
+    /// as `origin.x` on either side.
+    pub width: Option<f64>,
+    /// Room between symbols and between rows, for their labels.
+    pub spacing: f64,
+    /// Symbols are placed on this grid, which keeps their pins on KiCad's
+    /// connection grid.
+    pub grid: f64,
+}
+
+impl Default for ArrangeOptions {
+    fn default() -> Self {
+        ArrangeOptions { origin: Point::new(25.4, 25.4), width: None, spacing: 12.7, grid: 1.27 }
+    }
+}
+
+impl Schematic {
+    /// Places the symbol `lib_id` from `library` at `at`, named `reference`,
+    /// with the fields of its library symbol, which is cached in
+    /// [`Schematic::lib_symbols`]. The nickname in `lib_id` must be the
+    /// library's. The symbol shows unit 1 and gets an instance at the root
+    /// sheet path of an unnamed project; KiCad matches instances by path.
+    pub fn place_symbol(
+        &mut self,
+        library: &SymbolLibrary,
+        lib_id: &str,
+        at: Position,
+        reference: &str,
+        uuids: &mut UuidGenerator,
+    ) -> Result<&mut SymbolInstance, Error> {
+        if self.symbol(reference).is_some() {
+            return Err(ConvertError::new(format!("the schematic already has a symbol {}", reference)).into());
+        }
+        let lib = self.cache_symbol(library, lib_id)?;
+
+        let uuid = uuids.generate(format!("symbol {}", reference));
+        let mut symbol = SymbolInstance {
+            lib_id: lib_id.to_string(),
+            at,
+            unit: 1,
+            body_style: 1,
+            in_bom: true,
+            on_board: true,
+            uuid,
+            ..Default::default()
+        };
+        for property in lib.properties.iter().filter(|property| !property.key.starts_with("ki_")) {
+            let mut field = property.clone();
+            if let Some(offset) = property.at {
+                let point = symbol.to_sheet(Point::new(offset.x, offset.y));
+                field.at = Some(Position::new(point.x, point.y, (offset.angle + at.angle).rem_euclid(180.0)));
+            }
+            symbol.properties.push(field);
+        }
+        symbol.set_property("Reference", reference);
+        for pin in lib.pins_of(1, 1) {
+            if !symbol.pins.iter().any(|placed| placed.number == pin.number) {
+                let uuid = uuids.generate(format!("pin {} {}", reference, pin.number));
+                symbol.pins.push(SymbolPin { number: pin.number.clone(), uuid: Some(uuid), alternate: None });
+            }
+        }
+        if let Some(root) = self.uuid {
+            symbol.set_instance("", &format!("/{}", root), reference, 1);
+        }
+        self.symbols.push(symbol);
+        Ok(self.symbols.last_mut().expect("a symbol was just added"))
+    }
+
+    /// The library symbol `lib_id` from `library`, added to
+    /// [`Schematic::lib_symbols`] unless it is there already.
+    pub(crate) fn cache_symbol(&mut self, library: &SymbolLibrary, lib_id: &str) -> Result<&LibSymbol, Error> {
+        let name = lib_id.strip_prefix(library.nickname()).and_then(|id| id.strip_prefix(':'));
+        let name = name.ok_or_else(|| {
+            ConvertError::new(format!("`{}` is not a symbol of library {}", lib_id, library.nickname()))
+        })?;
+        if self.lib_symbol(lib_id).is_none() {
+            let node = library.cached(name)?;
+            let sexp = node.to_sexp();
+            let list = List::new(&sexp).expect("symbols are lists");
+            let lib = LibSymbol::from_sexp(&list).map_err(|e| Error::from(e).in_file(library.path().to_path_buf()))?;
+            self.lib_symbols.push(lib);
+        }
+        Ok(self.lib_symbol(lib_id).expect("the library symbol is cached"))
+    }
+
+    /// Places unit `unit` of the symbol `reference` on top of its first
+    /// placed unit, with that unit's fields and pins.
+    pub fn place_unit(
+        &mut self,
+        reference: &str,
+        unit: u32,
+        uuids: &mut UuidGenerator,
+    ) -> Result<&mut SymbolInstance, Error> {
+        let mut units = self.symbols.iter().filter(|symbol| symbol.reference() == Some(reference));
+        let first = units.next();
+        let first = first.ok_or_else(|| ConvertError::new(format!("no symbol {} in the schematic", reference)))?;
+        if first.unit == unit || units.any(|symbol| symbol.unit == unit) {
+            return Err(ConvertError::new(format!("unit {} of {} is placed already", unit, reference)).into());
+        }
+        let lib = self.lib_symbol(first.lib_name.as_deref().unwrap_or(&first.lib_id));
+        if !lib.is_some_and(|lib| lib.pins.iter().any(|pin| pin.unit == unit)) {
+            return Err(ConvertError::new(format!("symbol {} has no unit {}", reference, unit)).into());
+        }
+        let mut symbol = first.clone();
+        let numbers: Vec<String> = lib.map_or_else(Vec::new, |lib| {
+            lib.pins_of(unit, symbol.body_style).map(|pin| pin.number.clone()).collect()
+        });
+        symbol.unit = unit;
+        symbol.uuid = uuids.generate(format!("symbol {} unit {}", reference, unit));
+        symbol.pins.clear();
+        for number in numbers {
+            if !symbol.pins.iter().any(|placed| placed.number == number) {
+                let uuid = uuids.generate(format!("pin {} {}", reference, number));
+                symbol.pins.push(SymbolPin { number, uuid: Some(uuid), alternate: None });
+            }
+        }
+        for instance in &mut symbol.instances {
+            instance.unit = unit;
+        }
+        self.symbols.push(symbol);
+        Ok(self.symbols.last_mut().expect("a unit was just added"))
+    }
+
+    /// Puts a label of `kind` for the net `net` on the end of pin `pin` of
+    /// the symbol `reference`, reading away from the symbol.
+    pub fn label_pin(
+        &mut self,
+        reference: &str,
+        pin: &str,
+        kind: LabelKind,
+        net: &str,
+        uuids: &mut UuidGenerator,
+    ) -> Result<&mut Label, Error> {
+        let (at, angle) = self.pin_end(reference, pin)?;
+        let horizontal = if angle >= 180.0 { HAlign::Right } else { HAlign::Left };
+        let vertical = if kind == LabelKind::Local { VAlign::Bottom } else { VAlign::Center };
+        let font = Font { height: 1.27, width: 1.27, ..Default::default() };
+        self.labels.push(Label {
+            kind,
+            text: net.to_string(),
+            at: Position::new(at.x, at.y, angle),
+            shape: (kind != LabelKind::Local).then_some(LabelShape::Bidirectional),
+            fields_autoplaced: true,
+            effects: Effects { font, justify: Justify { horizontal, vertical, mirror: false }, ..Default::default() },
+            uuid: uuids.generate(format!("label {} {} {}", net, reference, pin)),
+            properties: Vec::new(),
+        });
+        Ok(self.labels.last_mut().expect("a label was just added"))
+    }
+
+    /// Where pin `pin` of the symbol `reference` connects, and the angle
+    /// pointing away from the symbol's body there.
+    pub(crate) fn pin_end(&self, reference: &str, pin: &str) -> Result<(Point, f64), Error> {
+        let mut units = self.symbols.iter().filter(|symbol| symbol.reference() == Some(reference)).peekable();
+        if units.peek().is_none() {
+            return Err(ConvertError::new(format!("no symbol {} in the schematic", reference)).into());
+        }
+        for symbol in units {
+            let lib_id = symbol.lib_name.as_deref().unwrap_or(&symbol.lib_id);
+            let lib = self.lib_symbol(lib_id);
+            let lib = lib.ok_or_else(|| ConvertError::new(format!("no library symbol {} in the schematic", lib_id)))?;
+            if let Some(found) = lib.pins_of(symbol.unit, symbol.body_style).find(|candidate| candidate.number == pin) {
+                return Ok((symbol.pin_position(found), outward(symbol, found)));
+            }
+        }
+        Err(ConvertError::new(format!("symbol {} has no pin {} on its placed units", reference, pin)).into())
+    }
+
+    /// Lays the placed symbols out in rows from `options.origin`, sorted by
+    /// reference, with a new row for each reference prefix. Labels and
+    /// power symbols on the pins of one symbol move with it; power symbols
+    /// and other virtual parts are not arranged on their own. Symbols
+    /// placed on top of each other, as generators tend to, share their pin
+    /// positions, so label those after arranging them.
+    pub fn arrange(&mut self, options: &ArrangeOptions) {
+        let width = options.width.unwrap_or_else(|| self.paper.dimensions().0 - 2.0 * options.origin.x);
+        let snap = |v: f64| if options.grid > 0.0 { round((v / options.grid).ceil() * options.grid) } else { v };
+        let mut order: Vec<usize> = (0..self.symbols.len()).filter(|&i| !self.symbols[i].is_virtual()).collect();
+        order.sort_by_cached_key(|&i| reference_key(self.symbols[i].reference().unwrap_or_default()));
+        let mut attached: Vec<Vec<(bool, usize)>> = order.iter().map(|&i| self.attached(i)).collect();
+        let claims: Vec<(bool, usize)> = attached.iter().flatten().copied().collect();
+        for items in &mut attached {
+            items.retain(|item| claims.iter().filter(|claim| *claim == item).count() == 1);
+        }
+        let (mut x, mut y, mut row_height) = (options.origin.x, options.origin.y, 0.0f64);
+        let mut row_prefix: Option<String> = None;
+        for (i, attached) in order.into_iter().zip(attached) {
+            let (prefix, _) = reference_key(self.symbols[i].reference().unwrap_or_default());
+            let (min, max) = self.extent(i, &attached);
+            let full = x + (max.x - min.x) > options.origin.x + width;
+            if row_prefix.is_some() && (row_prefix.as_ref() != Some(&prefix) || full) {
+                (x, y, row_height) = (options.origin.x, y + row_height + options.spacing, 0.0);
+            }
+            row_prefix = Some(prefix);
+            let at = Point::new(snap(x - min.x), snap(y - min.y));
+            let from = self.symbols[i].at;
+            let delta = Point::new(at.x - from.x, at.y - from.y);
+            shift_symbol(&mut self.symbols[i], delta);
+            for &(is_label, j) in &attached {
+                match is_label {
+                    true => {
+                        let at = &mut self.labels[j].at;
+                        (at.x, at.y) = (round(at.x + delta.x), round(at.y + delta.y));
+                    },
+                    false => shift_symbol(&mut self.symbols[j], delta),
+                }
+            }
+            x = at.x + max.x + options.spacing;
+            row_height = row_height.max(at.y + max.y - y);
+        }
+    }
+
+    /// The pin ends of `symbol` on the sheet.
+    fn pin_ends(&self, symbol: &SymbolInstance) -> Vec<Point> {
+        let lib = self.lib_symbol(symbol.lib_name.as_deref().unwrap_or(&symbol.lib_id));
+        lib.map_or_else(Vec::new, |lib| symbol.placed_pins(lib).map(|(_, at)| at).collect())
+    }
+
+    /// The labels, as `(true, index)`, and the virtual symbols, as
+    /// `(false, index)`, on a pin of the symbol at `index`.
+    fn attached(&self, index: usize) -> Vec<(bool, usize)> {
+        let ends = self.pin_ends(&self.symbols[index]);
+        let on_pin = |at: Point| ends.iter().any(|end| (end.x - at.x).abs() < 1e-6 && (end.y - at.y).abs() < 1e-6);
+        let labels = self.labels.iter().enumerate().filter(|(_, label)| on_pin(Point::new(label.at.x, label.at.y)));
+        let mut attached: Vec<(bool, usize)> = labels.map(|(i, _)| (true, i)).collect();
+        for (i, symbol) in self.symbols.iter().enumerate() {
+            if i != index && symbol.is_virtual() && self.pin_ends(symbol).into_iter().any(on_pin) {
+                attached.push((false, i));
+            }
+        }
+        attached
+    }
+
+    /// The box around the symbol at `index`, its pins and shown fields and
+    /// `attached` items, relative to the symbol's position.
+    fn extent(&self, index: usize, attached: &[(bool, usize)]) -> (Point, Point) {
+        let symbol = &self.symbols[index];
+        let mut points = vec![Point::new(symbol.at.x, symbol.at.y)];
+        let mut texts: Vec<(Position, f64, HAlign)> = Vec::new();
+        let add = |other: &SymbolInstance, points: &mut Vec<Point>, texts: &mut Vec<(Position, f64, HAlign)>| {
+            points.push(Point::new(other.at.x, other.at.y));
+            points.extend(self.pin_ends(other));
+            for field in other.properties.iter().filter(|field| !field.hide) {
+                if let Some(at) = field.at {
+                    let size = field.effects.as_ref().map_or(1.27, |effects| effects.font.width);
+                    texts.push((at, size * field.value.chars().count() as f64, HAlign::Center));
+                }
+            }
+        };
+        add(symbol, &mut points, &mut texts);
+        for &(is_label, j) in attached {
+            match is_label {
+                true => {
+                    let label = &self.labels[j];
+                    let length = label.effects.font.width * (label.text.chars().count() + 2) as f64;
+                    texts.push((label.at, length, label.effects.justify.horizontal));
+                },
+                false => add(&self.symbols[j], &mut points, &mut texts),
+            }
+        }
+        for (at, length, align) in texts {
+            // Text runs along its angle from the anchor, or is centered on it.
+            let (start, end) = match align {
+                HAlign::Left => (0.0, length),
+                HAlign::Right => (-length, 0.0),
+                HAlign::Center => (-length / 2.0, length / 2.0),
+            };
+            let vertical = (at.angle / 90.0).round() as i64 % 2 != 0;
+            for offset in [start, end] {
+                points.push(match vertical {
+                    true => Point::new(at.x, at.y - offset),
+                    false => Point::new(at.x + offset, at.y),
+                });
+            }
+        }
+        let (mut min, mut max) = (Point::new(f64::MAX, f64::MAX), Point::new(f64::MIN, f64::MIN));
+        for point in points {
+            (min.x, min.y) = (min.x.min(point.x), min.y.min(point.y));
+            (max.x, max.y) = (max.x.max(point.x), max.y.max(point.y));
+        }
+        let origin = symbol.at;
+        (Point::new(min.x - origin.x, min.y - origin.y), Point::new(max.x - origin.x, max.y - origin.y))
+    }
+}
+
+/// The label angle pointing from `pin` of `symbol` away from the body.
+pub(crate) fn outward(symbol: &SymbolInstance, pin: &LibPin) -> f64 {
+    let (sin, cos) = pin.at.angle.to_radians().sin_cos();
+    let end = symbol.pin_position(pin);
+    let body = symbol.to_sheet(Point::new(pin.at.x + cos, pin.at.y + sin));
+    let (dx, dy) = (end.x - body.x, end.y - body.y);
+    match dx.abs() > dy.abs() {
+        true if dx > 0.0 => 0.0,
+        true => 180.0,
+        false if dy < 0.0 => 90.0,
+        false => 270.0,
+    }
+}
+
+/// Rounds away the noise of adding up grid steps.
+fn round(value: f64) -> f64 {
+    (value * 1e6).round() / 1e6
+}
+
+pub(crate) fn shift_symbol(symbol: &mut SymbolInstance, delta: Point) {
+    let positions = symbol.properties.iter_mut().filter_map(|field| field.at.as_mut());
+    for at in std::iter::once(&mut symbol.at).chain(positions) {
+        (at.x, at.y) = (round(at.x + delta.x), round(at.y + delta.y));
+    }
+}
+
+/// Sort key of a reference: its prefix and number, so `R10` follows `R9`.
+fn reference_key(reference: &str) -> (String, u64) {
+    let prefix = reference.trim_end_matches(|c: char| c.is_ascii_digit());
+    (prefix.to_string(), reference[prefix.len()..].parse().unwrap_or(0))
+}
+
+/// The text of the atom at `index` of `node`.
+fn arg(node: &Node, index: usize) -> Option<String> {
+    node.items().get(index)?.value().map(|value| value.into_owned())
+}
+
+fn node_uuid(node: &Node) -> Option<Uuid> {
+    arg(node.child("uuid")?, 1)?.parse().ok()
+}
+
+fn sheet_items<'d, 'a>(doc: &'d mut Document<'a>) -> Result<&'d mut Vec<Node<'a>>, Error> {
+    let root = doc.nodes.iter_mut().find(|node| node.tag() == Some("kicad_sch"));
+    Ok(root.and_then(|root| root.items_mut()).ok_or_else(|| ConvertError::new("expected a (kicad_sch ...) root"))?)
+}
+
+/// Where to add new top-level items: after the last one `is_item`
+/// matches, or before the first of `before`.
+fn insertion_point(items: &[Node], is_item: impl Fn(&str) -> bool, before: &[&str]) -> usize {
+    match items.iter().rposition(|item| item.tag().is_some_and(&is_item)) {
+        Some(last) => last + 1,
+        None => {
+            let later = items.iter().position(|item| item.tag().is_some_and(|tag| before.contains(&tag)));
+            later.unwrap_or(items.len())
+        },
+    }
+}
+
+/// Adds the symbols from `library` that are placed in `schematic` but not
+/// yet in `doc`, matched by UUID, after its last symbol, and their library
+/// symbols that its `lib_symbols` lacks. Symbols from other libraries are
+/// left for a call with theirs. Returns the number of symbols added.
+pub fn write_symbols(schematic: &Schematic, library: &SymbolLibrary, doc: &mut Document<'_>) -> Result<usize, Error> {
+    let items = sheet_items(doc)?;
+    let symbols = items.iter().filter(|item| item.tag() == Some("symbol"));
+    let present: HashSet<Uuid> = symbols.filter_map(node_uuid).collect();
+    let prefix = format!("{}:", library.nickname());
+    let new: Vec<&SymbolInstance> = (schematic.symbols.iter())
+        .filter(|symbol| symbol.lib_id.starts_with(&prefix) && !present.contains(&symbol.uuid))
+        .collect();
+    if new.is_empty() {
+        return Ok(0);
+    }
+
+    let lib_symbols = match items.iter().position(|item| item.tag() == Some("lib_symbols")) {
+        Some(i) => i,
+        None => {
+            let at = insertion_point(items, |tag| matches!(tag, "uuid" | "paper" | "title_block"), &[]);
+            let mut node = Node::tagged("lib_symbols", Vec::new());
+            node.leading = "\n\t".into();
+            items.insert(at, node);
+            at
+        },
+    };
+    let cache = items[lib_symbols].items_mut().expect("tagged nodes are lists");
+    for symbol in &new {
+        let lib_id = symbol.lib_name.as_deref().unwrap_or(&symbol.lib_id);
+        if !cache.iter().skip(1).any(|cached| arg(cached, 1).as_deref() == Some(lib_id)) {
+            cache.push(library.cached(&lib_id[prefix.len()..])?);
+        }
+    }
+    if cache.len() > 1 {
+        items[lib_symbols].set_trailing("\n\t");
+    }
+
+    let at = insertion_point(items, |tag| tag == "symbol", &AFTER_SYMBOLS);
+    let new: Vec<Node<'static>> = new.into_iter().map(symbol_node).collect();
+    let count = new.len();
+    items.splice(at..at, new);
+    Ok(count)
+}
+
+/// Adds the labels of `schematic` that are not yet in `doc`, matched by
+/// UUID, after its last label. Returns the number of labels added.
+pub fn write_labels(schematic: &Schematic, doc: &mut Document<'_>) -> Result<usize, Error> {
+    let items = sheet_items(doc)?;
+    let is_label = |tag: &str| matches!(tag, "label" | "global_label" | "hierarchical_label");
+    let labels = items.iter().filter(|item| item.tag().is_some_and(is_label));
+    let present: HashSet<Uuid> = labels.filter_map(node_uuid).collect();
+    let new: Vec<Node<'static>> =
+        schematic.labels.iter().filter(|label| !present.contains(&label.uuid)).map(label_node).collect();
+    let at = insertion_point(items, is_label, &AFTER_LABELS);
+    let count = new.len();
+    items.splice(at..at, new);
+    Ok(count)
+}
+
+/// Adds the no-connect flags of `schematic` that are not yet in `doc`,
+/// matched by UUID, after its last one. Returns the number of flags added.
+pub fn write_no_connects(schematic: &Schematic, doc: &mut Document<'_>) -> Result<usize, Error> {
+    let items = sheet_items(doc)?;
+    let flags = items.iter().filter(|item| item.tag() == Some("no_connect"));
+    let present: HashSet<Uuid> = flags.filter_map(node_uuid).collect();
+    let new: Vec<Node<'static>> = (schematic.no_connects.iter())
+        .filter(|flag| !present.contains(&flag.uuid))
+        .map(|flag| {
+            let at = Node::tagged("at", vec![number(flag.at.x), number(flag.at.y)]);
+            Node::block("no_connect", Vec::new(), vec![at, uuid_node(flag.uuid)], "\n\t")
+        })
+        .collect();
+    let at = insertion_point(items, |tag| tag == "no_connect", &AFTER_NO_CONNECTS);
+    let count = new.len();
+    items.splice(at..at, new);
+    Ok(count)
+}
+
+fn number(value: f64) -> Node<'static> {
+    Node::atom(format_float(value))
+}
+
+fn flag(tag: &str, on: bool) -> Node<'static> {
+    Node::tagged(tag.to_string(), vec![Node::atom(if on { "yes" } else { "no" })])
+}
+
+fn position(at: Position) -> Node<'static> {
+    Node::tagged("at", vec![number(at.x), number(at.y), number(at.angle)])
+}
+
+fn uuid_node(uuid: Uuid) -> Node<'static> {
+    Node::tagged("uuid", vec![Node::string(&uuid.to_string())])
+}
+
+/// A placed symbol as KiCad writes it at the top level of a sheet.
+fn symbol_node(symbol: &SymbolInstance) -> Node<'static> {
+    let inner = "\n\t\t";
+    let mut children = Vec::new();
+    if let Some(lib_name) = &symbol.lib_name {
+        children.push(Node::tagged("lib_name", vec![Node::string(lib_name)]));
+    }
+    children.push(Node::tagged("lib_id", vec![Node::string(&symbol.lib_id)]));
+    children.push(position(symbol.at));
+    if let Some(mirror) = symbol.mirror {
+        let axis = match mirror {
+            Mirror::X => "x",
+            Mirror::Y => "y",
+        };
+        children.push(Node::tagged("mirror", vec![Node::atom(axis)]));
+    }
+    children.push(Node::tagged("unit", vec![Node::atom(symbol.unit.to_string())]));
+    if symbol.body_style != 1 {
+        children.push(Node::tagged("body_style", vec![Node::atom(symbol.body_style.to_string())]));
+    }
+    children.push(flag("exclude_from_sim", symbol.exclude_from_sim));
+    children.push(flag("in_bom", symbol.in_bom));
+    children.push(flag("on_board", symbol.on_board));
+    children.push(flag("dnp", symbol.dnp));
+    if symbol.fields_autoplaced {
+        children.push(flag("fields_autoplaced", true));
+    }
+    children.push(uuid_node(symbol.uuid));
+    children.extend(symbol.properties.iter().map(|property| property_node(property, inner)));
+    for pin in &symbol.pins {
+        let mut fields = Vec::new();
+        fields.extend(pin.alternate.as_deref().map(|name| Node::tagged("alternate", vec![Node::string(name)])));
+        fields.extend(pin.uuid.map(uuid_node));
+        children.push(Node::block("pin", vec![Node::string(&pin.number)], fields, inner));
+    }
+    if !symbol.instances.is_empty() {
+        children.push(instances_node(&symbol.instances, inner));
+    }
+    Node::block("symbol", Vec::new(), children, "\n\t")
+}
+
+/// A label as KiCad writes it at the top level of a sheet.
+fn label_node(label: &Label) -> Node<'static> {
+    let tag = match label.kind {
+        LabelKind::Local => "label",
+        LabelKind::Global => "global_label",
+        LabelKind::Hierarchical => "hierarchical_label",
+    };
+    let mut children = Vec::new();
+    children.extend(label.shape.map(|shape| Node::tagged("shape", vec![Node::atom(shape.name())])));
+    children.push(position(label.at));
+    if label.fields_autoplaced {
+        children.push(flag("fields_autoplaced", true));
+    }
+    children.push(effects_node(&label.effects, "\n\t\t"));
+    children.push(uuid_node(label.uuid));
+    children.extend(label.properties.iter().map(|property| property_node(property, "\n\t\t")));
+    Node::block(tag, vec![Node::string(&label.text)], children, "\n\t")
+}
+
+fn effects_node(effects: &Effects, indent: &str) -> Node<'static> {
+    let size = Node::tagged("size", vec![number(effects.font.height), number(effects.font.width)]);
+    let mut children = vec![Node::block("font", Vec::new(), vec![size], &format!("{}\t", indent))];
+    let justify = effects.justify;
+    let mut align = Vec::new();
+    match justify.horizontal {
+        HAlign::Left => align.push(Node::atom("left")),
+        HAlign::Right => align.push(Node::atom("right")),
+        HAlign::Center => {},
+    }
+    match justify.vertical {
+        VAlign::Top => align.push(Node::atom("top")),
+        VAlign::Bottom => align.push(Node::atom("bottom")),
+        VAlign::Center => {},
+    }
+    if justify.mirror {
+        align.push(Node::atom("mirror"));
+    }
+    if !align.is_empty() {
+        children.push(Node::tagged("justify", align));
+    }
+    if effects.hide {
+        children.push(flag("hide", true));
+    }
+    Node::block("effects", Vec::new(), children, indent)
+}
+
+/// Moves the lines of `node`, a top-level item of a library file, one level
+/// in, for it to be an entry of a sheet's `lib_symbols`.
+fn indent(node: &mut Node<'_>) {
+    node.leading = node.leading.replace('\n', "\n\t").into();
+    if let NodeKind::List { items, trailing } = &mut node.kind {
+        *trailing = trailing.replace('\n', "\n\t").into();
+        items.iter_mut().for_each(indent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SchematicBuilder;
+
+    const LIBRARY: &str = include_str!("../../reference-files/demo/demo.kicad_sym");
+
+    fn generated() -> (SchematicBuilder, Schematic, UuidGenerator) {
+        let mut uuids = UuidGenerator::deterministic("layout");
+        let builder = SchematicBuilder::new(uuids.generate("root"));
+        let schematic = builder.build().unwrap();
+        (builder, schematic, uuids)
+    }
+
+    #[test]
+    fn place_and_write() {
+        let library = SymbolLibrary::from_text("demo", LIBRARY);
+        let (builder, mut schematic, mut uuids) = generated();
+        let at = Position::new(50.8, 50.8, 0.0);
+        let root = format!("/{}", schematic.uuid.unwrap());
+        let r1 = schematic.place_symbol(&library, "demo:R", at, "R1", &mut uuids).unwrap();
+        r1.set_property("Value", "10k");
+        assert_eq!(r1.property("Reference"), Some("R1"));
+        assert_eq!(r1.properties[0].at, Some(Position::new(52.832, 50.8, 90.0)));
+        assert_eq!(r1.property("ki_keywords"), None);
+        assert_eq!(r1.instances[0].path, root);
+        schematic.place_symbol(&library, "demo:R_US", Position::new(76.2, 50.8, 90.0), "R2", &mut uuids).unwrap();
+        assert!(schematic.place_symbol(&library, "demo:R", at, "R1", &mut uuids).is_err());
+        assert!(schematic.place_symbol(&library, "Device:R", at, "R3", &mut uuids).is_err());
+        assert!(schematic.place_symbol(&library, "demo:Missing", at, "R3", &mut uuids).is_err());
+
+        // R1's pin 1 is on top, R2 is turned so its pin 1 is on the left.
+        let label = schematic.label_pin("R1", "1", LabelKind::Local, "VIN", &mut uuids).unwrap();
+        assert_eq!((label.at, label.effects.justify.horizontal), (Position::new(50.8, 46.99, 90.0), HAlign::Left));
+        let label = schematic.label_pin("R2", "1", LabelKind::Global, "VOUT", &mut uuids).unwrap();
+        assert_eq!((label.at, label.shape), (Position::new(72.39, 50.8, 180.0), Some(LabelShape::Bidirectional)));
+        assert!(schematic.label_pin("R2", "3", LabelKind::Local, "VOUT", &mut uuids).is_err());
+
+        let src = builder.to_string();
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        assert_eq!(write_symbols(&schematic, &library, &mut doc).unwrap(), 2);
+        assert_eq!(write_labels(&schematic, &mut doc).unwrap(), 2);
+        assert_eq!(write_symbols(&schematic, &library, &mut doc).unwrap(), 0);
+        let text = doc.to_string();
+        assert!(text.contains("\t(lib_symbols\n\t\t(symbol \"demo:R\"\n\t\t\t(pin_numbers\n"));
+        assert!(text.contains("\t\t(symbol \"demo:R_US\"\n") && text.contains("\t\t\t(symbol \"R_US_1_1\"\n"));
+        assert!(text.contains("\t\t)\n\t)\n\t(label \"VIN\"\n\t\t(at 50.8 46.99 90)\n"));
+        let written = Schematic::parse(&text).unwrap();
+        assert_eq!(written.lib_symbols, schematic.lib_symbols);
+        assert_eq!(written.symbols, schematic.symbols);
+        assert_eq!(written.labels, schematic.labels);
+        let r_us = written.lib_symbol("demo:R_US").unwrap();
+        assert_eq!(kicad_common::property::property(&r_us.properties, "Value"), Some("R_US"));
+        assert_eq!(r_us.pins.len(), 2);
+    }
+
+    #[test]
+    fn arrange() {
+        let library = SymbolLibrary::from_text("demo", LIBRARY);
+        let (_, mut schematic, mut uuids) = generated();
+        // Generated symbols start out on top of each other, but R2 was moved.
+        for (lib_id, reference) in [("demo:R", "R10"), ("demo:C", "C1"), ("demo:R", "R2"), ("demo:GND", "#PWR01")] {
+            schematic.place_symbol(&library, lib_id, Position::default(), reference, &mut uuids).unwrap();
+        }
+        shift_symbol(&mut schematic.symbols[2], Point::new(200.0, 150.0));
+        schematic.label_pin("R2", "1", LabelKind::Local, "VIN", &mut uuids).unwrap();
+        schematic.label_pin("R10", "1", LabelKind::Local, "VIN", &mut uuids).unwrap();
+        let (end, _) = schematic.pin_end("R2", "2").unwrap();
+        let (gnd, _) = schematic.pin_end("#PWR01", "1").unwrap();
+        assert_eq!(gnd, Point::new(0.0, 0.0));
+        let power = schematic.symbols.iter_mut().find(|symbol| symbol.is_virtual()).unwrap();
+        shift_symbol(power, end);
+
+        schematic.arrange(&ArrangeOptions::default());
+        let at = |reference: &str| {
+            let at = schematic.symbol(reference).unwrap().at;
+            (at.x, at.y)
+        };
+        // C1 has a row of its own; R2 comes before R10, whose row lines up
+        // the tops of R2's power symbol and R10's label; all on the grid.
+        let (c1, r2, r10) = (at("C1"), at("R2"), at("R10"));
+        assert_eq!((c1, r2, r10), ((26.67, 29.21), (27.94, 55.88), (43.18, 49.53)));
+        let on_grid = |v: f64| ((v / 1.27).round() * 1.27 - v).abs() < 1e-6;
+        assert!([c1, r2, r10].iter().all(|&(x, y)| on_grid(x) && on_grid(y)));
+
+        // The label and the power symbol stay on R2's pins.
+        let label = schematic.labels[0].at;
+        assert_eq!(Point::new(label.x, label.y), schematic.pin_end("R2", "1").unwrap().0);
+        assert_eq!(schematic.pin_end("#PWR01", "1").unwrap().0, schematic.pin_end("R2", "2").unwrap().0);
+        // R10's label is on C1's pin as well, so it is left where it was.
+        assert_eq!(schematic.labels[1].at, Position::new(0.0, -3.81, 90.0));
+        let power = schematic.symbol("#PWR01").unwrap();
+        assert_eq!(power.property("Value"), Some("GND"));
+    }
+}
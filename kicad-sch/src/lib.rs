@@ -0,0 +1,628 @@
+//! Typed model of KiCad schematic (`.kicad_sch`) files.
+
+#[cfg(feature = "export")]
+pub mod bom;
+mod builder;
+pub mod circuit;
+pub mod cleanup;
+pub mod clipboard;
+pub mod extract;
+pub mod hierarchy;
+#[cfg(feature = "render")]
+pub mod highlight;
+mod items;
+pub mod layout;
+#[cfg(feature = "export")]
+pub mod pricing;
+pub mod rename;
+mod schematic;
+mod sheet;
+pub mod sim;
+#[cfg(feature = "export")]
+pub mod sourcing;
+mod symbol;
+pub mod synthesis;
+
+pub use kicad_common::Error;
+
+#[cfg(feature = "export")]
+pub use bom::{Bom, BomOptions, BomRow, MergedBom, MergedRow};
+pub use builder::SchematicBuilder;
+pub use hierarchy::{FlatSymbol, Hierarchy, Part, SheetNode};
+pub use items::{BusEntry, Junction, Label, LabelKind, LabelShape, NoConnect, Text, Wire};
+pub use schematic::Schematic;
+pub use sheet::{Sheet, SheetInstance, SheetPin};
+pub use symbol::{InstancePath, LibPin, LibSymbol, Mirror, SymbolInstance, SymbolPin};
+
+/// Schema of the supported schematic format versions, for
+/// [`kicad_sexp::schema::Schema::parse`].
+pub const SCHEMA: &str = include_str!("../schema/kicad_sch.schema");
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::{HAlign, PaperSize, Point, Position, UuidGenerator};
+    use kicad_sexp::source::{FsSource, MemorySource};
+
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_sch");
+    const POWER: &str = include_str!("../../reference-files/demo/power.kicad_sch");
+
+    #[test]
+    fn empty() {
+        let schematic = Schematic::parse(include_str!("../../reference-files/empty/empty.kicad_sch")).unwrap();
+
+        assert_eq!(schematic.version, 20250114);
+        assert_eq!(schematic.generator, "eeschema");
+        assert_eq!(schematic.uuid.unwrap().to_string(), "7dfecbba-c7b6-4b01-9f5f-e26c00ad6670");
+        assert_eq!(schematic.paper.size, PaperSize::A4);
+        assert!(schematic.symbols.is_empty());
+        assert_eq!(schematic.sheet_instances.len(), 1);
+        assert_eq!(schematic.sheet_instances[0].path, "/");
+        assert_eq!(schematic.sheet_instances[0].page, "1");
+    }
+
+    #[test]
+    fn header() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+        let title_block = schematic.title_block.as_ref().unwrap();
+
+        assert_eq!(schematic.generator_version.as_deref(), Some("9.0"));
+        assert_eq!(title_block.title.as_deref(), Some("Demo Divider"));
+        assert_eq!(title_block.comments[&1], "Reference design for tests");
+        assert_eq!(schematic.lib_symbols.len(), 3);
+        assert!(schematic.lib_symbol("power:GND").unwrap().power);
+        assert!(!schematic.lib_symbol("Device:R").unwrap().power);
+    }
+
+    #[test]
+    fn pin_positions() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+        let r = schematic.lib_symbol("Device:R").unwrap();
+        let numbers: Vec<_> = r.pins.iter().map(|pin| (pin.number.as_str(), pin.at.y, pin.unit)).collect();
+        assert_eq!(numbers, [("1", 3.81, 1), ("2", -3.81, 1)]);
+        assert_eq!(r.pins_of(2, 1).count(), 0);
+
+        let mut r1 = schematic.symbol("R1").unwrap().clone();
+        let ends = |r1: &SymbolInstance| r1.placed_pins(r).map(|(_, at)| (at.x, at.y)).collect::<Vec<_>>();
+        assert_eq!(ends(&r1), [(100.33, 59.69), (100.33, 67.31)]);
+        r1.at = Position::new(10.0, 20.0, 90.0);
+        assert_eq!(ends(&r1), [(6.19, 20.0), (13.81, 20.0)]);
+        r1.mirror = Some(Mirror::Y);
+        assert_eq!(ends(&r1), [(13.81, 20.0), (6.19, 20.0)]);
+        r1.at.angle = 0.0;
+        r1.mirror = Some(Mirror::X);
+        assert_eq!(ends(&r1), [(10.0, 23.81), (10.0, 16.19)]);
+    }
+
+    #[test]
+    fn symbols() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+        let r1 = schematic.symbol("R1").unwrap();
+        let gnd = schematic.symbol("#PWR01").unwrap();
+
+        assert_eq!(schematic.symbols.len(), 5);
+        assert_eq!(r1.lib_id, "Device:R");
+        assert_eq!(r1.at, Position::new(100.33, 63.5, 0.0));
+        assert_eq!(r1.value(), Some("10k"));
+        assert_eq!(r1.footprint(), Some("Resistor_SMD:R_0603_1608Metric"));
+        assert_eq!(r1.property("MPN"), Some("RC0603FR-0710KL"));
+        assert!(r1.in_bom && r1.on_board && !r1.dnp);
+        assert_eq!(r1.pins.len(), 2);
+        assert_eq!(r1.pins[1].number, "2");
+        assert_eq!(r1.instances[0].project, "demo");
+        assert_eq!(r1.instances[0].path, "/87a3680d-3f24-40cd-ba53-72644a0ce42b");
+        assert_eq!(r1.instances[0].reference, "R1");
+        assert!(!r1.is_virtual());
+        assert!(gnd.is_virtual());
+        assert_eq!(gnd.footprint(), None);
+        assert_eq!(
+            gnd.property("Description"),
+            Some("Power symbol creates a global label with name \"GND\" , ground")
+        );
+    }
+
+    #[test]
+    fn connectivity_items() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+
+        assert_eq!(schematic.wires.len(), 7);
+        assert_eq!(schematic.wires[0].points, [Point::new(100.33, 67.31), Point::new(100.33, 72.39)]);
+        assert_eq!(schematic.junctions.len(), 1);
+        assert_eq!(schematic.junctions[0].at, Point::new(100.33, 69.85));
+        assert_eq!(schematic.no_connects.len(), 1);
+        assert_eq!(schematic.texts[0].text, "Voltage divider");
+        assert_eq!(schematic.texts[0].effects.justify.horizontal, HAlign::Left);
+
+        let labels: Vec<_> = schematic.labels.iter().map(|label| (label.kind, label.text.as_str(), label.shape)).collect();
+        assert_eq!(
+            labels,
+            [
+                (LabelKind::Local, "VOUT", None),
+                (LabelKind::Global, "VIN", Some(LabelShape::Input)),
+                (LabelKind::Global, "VIN", Some(LabelShape::Input)),
+                (LabelKind::Local, "VOUT", None),
+                (LabelKind::Global, "VIN", Some(LabelShape::Input)),
+            ]
+        );
+        assert_eq!(schematic.labels[1].properties[0].key, "Intersheetrefs");
+        assert!(schematic.labels[1].properties[0].hide);
+    }
+
+    #[test]
+    fn sheets() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+        let sheet = &schematic.sheets[0];
+
+        assert_eq!(sheet.name(), Some("power"));
+        assert_eq!(sheet.file(), Some("power.kicad_sch"));
+        assert_eq!(sheet.size.width, 25.4);
+        assert_eq!(sheet.pins[0].name, "VIN");
+        assert_eq!(sheet.pins[0].shape, LabelShape::Input);
+        assert_eq!(sheet.instances[0].page, "2");
+        assert_eq!(sheet.uuid.to_string(), "2a3c5ebe-b43c-4735-aace-eff978af37c6");
+    }
+
+    #[test]
+    fn child_sheet() {
+        let schematic = Schematic::parse(POWER).unwrap();
+
+        assert!(schematic.paper.portrait);
+        assert_eq!(schematic.symbol("C1").unwrap().value(), Some("100n"));
+        assert_eq!(schematic.labels[0].kind, LabelKind::Hierarchical);
+        assert!(schematic.sheet_instances.is_empty());
+    }
+
+    #[test]
+    fn hierarchy() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let hierarchy = Hierarchy::load(&source, "demo/demo.kicad_sch").unwrap();
+
+        let sheets: Vec<_> =
+            hierarchy.sheets.iter().map(|sheet| (sheet.name_path.as_str(), sheet.page.as_deref())).collect();
+        assert_eq!(sheets, [("/", Some("1")), ("/power/", Some("2"))]);
+        let power = hierarchy.sheet("/power/").unwrap();
+        assert_eq!(power.path, "/87a3680d-3f24-40cd-ba53-72644a0ce42b/2a3c5ebe-b43c-4735-aace-eff978af37c6");
+        assert_eq!(power.file, std::path::Path::new("demo/power.kicad_sch"));
+        assert_eq!(hierarchy.ancestors(1).count(), 2);
+        assert_eq!(hierarchy.root().title_block.as_ref().unwrap().rev.as_deref(), Some("1.2"));
+        let references: Vec<_> = hierarchy.symbols().map(|symbol| symbol.reference).collect();
+        assert_eq!(references, ["R1", "R2", "#PWR01", "J1", "#PWR03", "C1", "#PWR02"]);
+        assert_eq!(hierarchy.symbol("C1").unwrap().sheet.name_path, "/power/");
+    }
+
+    #[test]
+    fn hierarchy_instances() {
+        const ROOT: &str = "00000000-0000-0000-0000-000000000001";
+        let sheet = |uuid: &str, name: &str, file: &str| {
+            format!(
+                "(sheet (at 0 0) (size 10 10) (uuid \"{}\") (property \"Sheetname\" \"{}\") \
+                 (property \"Sheetfile\" \"{}\") (instances (project \"top\" (path \"/{}\" (page \"{}\")))))",
+                uuid, name, file, ROOT, name
+            )
+        };
+        let root = format!(
+            "(kicad_sch (version 20250114) (uuid \"{}\") {} {})",
+            ROOT,
+            sheet("00000000-0000-0000-0000-00000000000a", "a", "channel.kicad_sch"),
+            sheet("00000000-0000-0000-0000-00000000000b", "b", "channel.kicad_sch"),
+        );
+        let instance = |sheet: char, reference: &str, unit: u32| {
+            let path = format!("/{}/00000000-0000-0000-0000-00000000000{}", ROOT, sheet);
+            format!("(path \"{}\" (reference \"{}\") (unit {}))", path, reference, unit)
+        };
+        let channel = format!(
+            "(kicad_sch (version 20250114) (uuid \"00000000-0000-0000-0000-0000000000c0\") \
+             (symbol (lib_id \"Device:R\") (at 0 0 0) (uuid \"00000000-0000-0000-0000-0000000000c1\") \
+             (property \"Reference\" \"R?\") (instances (project \"other\" {}) (project \"top\" {} {}))))",
+            instance('a', "R9", 1),
+            instance('a', "R1", 1),
+            instance('b', "R2", 2),
+        );
+        let source = MemorySource::new().with("top.kicad_sch", root).with("channel.kicad_sch", channel);
+        let hierarchy = Hierarchy::load(&source, "top.kicad_sch").unwrap();
+
+        assert_eq!(hierarchy.files.len(), 2);
+        let pages: Vec<_> = hierarchy.sheets.iter().map(|sheet| sheet.page.as_deref()).collect();
+        assert_eq!(pages, [None, Some("a"), Some("b")]);
+        let symbols: Vec<_> =
+            hierarchy.symbols().map(|flat| (flat.sheet.name_path.as_str(), flat.reference, flat.unit)).collect();
+        assert_eq!(symbols, [("/a/", "R1", 1), ("/b/", "R2", 2)]);
+
+        let looped = sheet("00000000-0000-0000-0000-00000000000d", "again", "loop.kicad_sch");
+        let source = MemorySource::new().with("loop.kicad_sch", format!("(kicad_sch (version 20250114) {})", looped));
+        let error = Hierarchy::load(&source, "loop.kicad_sch").unwrap_err();
+        assert_eq!(error.to_string(), "loop.kicad_sch: sheet `again` includes one of its own parents");
+        let missing = sheet("00000000-0000-0000-0000-00000000000e", "gone", "gone.kicad_sch");
+        let source = MemorySource::new().with("top.kicad_sch", format!("(kicad_sch (version 20250114) {})", missing));
+        let error = Hierarchy::load(&source, "top.kicad_sch").unwrap_err();
+        assert_eq!(error.file(), Some(std::path::Path::new("gone.kicad_sch")));
+    }
+
+    #[test]
+    fn back_annotate() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let mut hierarchy = Hierarchy::load(&source, "demo/demo.kicad_sch").unwrap();
+        let references = [
+            ("/813a1c5e-c6c4-4caa-891f-603de6599466", "R2"),
+            ("/2a3c5ebe-b43c-4735-aace-eff978af37c6/ff294785-b81a-4633-8a53-0b1dfb5f0ff1", "C101"),
+            ("/00000000-0000-0000-0000-000000000000", "X1"),
+        ];
+        // R1 and R2 swap.
+        let r2 = hierarchy.symbol("R2").unwrap().symbol.uuid;
+        let mut references = references.map(|(path, reference)| (path.to_string(), reference.to_string())).to_vec();
+        references.push((format!("/{}", r2), "R1".to_string()));
+        let changed = hierarchy.back_annotate(&references);
+        let references: Vec<_> = hierarchy.symbols().map(|symbol| symbol.reference).collect();
+        assert_eq!(references, ["R2", "R1", "#PWR01", "J1", "#PWR03", "C101", "#PWR02"]);
+        assert_eq!(changed.len(), 3);
+
+        let (file, uuid) = &changed[2];
+        assert_eq!(file, std::path::Path::new("demo/power.kicad_sch"));
+        let mut doc = kicad_sexp::cst::parse(POWER).unwrap();
+        let c1 = hierarchy.files[file].symbols.iter().find(|symbol| symbol.uuid == *uuid).unwrap();
+        c1.write_instances(&mut doc).unwrap();
+        assert_eq!(doc.to_string(), POWER.replacen("(reference \"C1\")", "(reference \"C101\")", 1));
+    }
+
+    #[test]
+    fn gate_swap() {
+        const ROOT: &str = "00000000-0000-0000-0000-000000000001";
+        let pin = |number: &str, y: f64| {
+            format!("(pin input line (at -7.62 {} 0) (length 2.54) (number \"{}\"))", y, number)
+        };
+        let unit = |name: &str, pins: [(&str, f64); 3]| {
+            format!("(symbol \"{}\" {})", name, pins.map(|(number, y)| pin(number, y)).join(" "))
+        };
+        let lib = format!(
+            "(lib_symbols (symbol \"Amplifier_Operational:LM358\" {} {} (symbol \"LM358_3_1\" {} {})))",
+            unit("LM358_1_1", [("3", 2.54), ("2", -2.54), ("1", 0.0)]),
+            unit("LM358_2_1", [("5", 2.54), ("6", -2.54), ("7", 0.0)]),
+            pin("8", 7.62),
+            pin("4", -7.62)
+        );
+        let symbol = |uuid: &str, unit: u32| {
+            format!(
+                "(symbol (lib_id \"Amplifier_Operational:LM358\") (at 0 0 0) (unit {unit}) (uuid \"{uuid}\") \
+                 (property \"Reference\" \"U1\") (property \"Value\" \"LM358\") \
+                 (instances (project \"amp\" (path \"/{root}\" (reference \"U1\") (unit {unit})))))",
+                unit = unit,
+                uuid = uuid,
+                root = ROOT
+            )
+        };
+        let (a, b) = ("00000000-0000-0000-0000-0000000000a1", "00000000-0000-0000-0000-0000000000a2");
+        let src =
+            format!("(kicad_sch (version 20250114) (uuid \"{}\") {} {} {})", ROOT, lib, symbol(a, 1), symbol(b, 2));
+        let source = MemorySource::new().with("amp.kicad_sch", src.as_str());
+        let mut hierarchy = Hierarchy::load(&source, "amp.kicad_sch").unwrap();
+
+        let gates = hierarchy.root().lib_symbols[0].gates();
+        let numbers = |pins: [&str; 3]| pins.map(String::from).to_vec();
+        assert_eq!(gates, [vec![(1, numbers(["2", "1", "3"])), (2, numbers(["6", "7", "5"]))]]);
+
+        // The footprint links to the first unit.
+        let changed = hierarchy.swap_units(&[(format!("/{}", a), 1, 2)]);
+        assert_eq!(changed.len(), 2);
+        let units: Vec<_> = hierarchy.symbols().map(|symbol| symbol.unit).collect();
+        assert_eq!(units, [2, 1]);
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        for (file, uuid) in &changed {
+            let symbol = hierarchy.files[file].symbols.iter().find(|symbol| symbol.uuid == *uuid).unwrap();
+            symbol.write_unit(&mut doc).unwrap();
+            symbol.write_instances(&mut doc).unwrap();
+        }
+        let written = Schematic::parse(&doc.to_string()).unwrap();
+        let units: Vec<_> = written.symbols.iter().map(|symbol| (symbol.unit, symbol.instances[0].unit)).collect();
+        assert_eq!(units, [(2, 2), (1, 1)]);
+        assert!(hierarchy.swap_units(&[("/00000000-0000-0000-0000-000000000000".to_string(), 1, 2)]).is_empty());
+    }
+
+    #[test]
+    fn sheet_pins() {
+        let pins = concat!(
+            "\t\t(pin \"VIN\" output\n\t\t\t(at 127 55.88 180)\n",
+            "\t\t\t(uuid \"43f920f7-49b5-4e8c-a58f-979590c146a3\")\n",
+            "\t\t\t(effects\n\t\t\t\t(font\n\t\t\t\t\t(size 1.27 1.27)\n\t\t\t\t)\n",
+            "\t\t\t\t(justify left)\n\t\t\t)\n\t\t)\n",
+            "\t\t(pin \"VIN\" input (at 127 58.42 180) (uuid \"00000000-0000-0000-0000-0000000000b1\"))\n",
+            "\t\t(pin \"OLD\" passive (at 152.4 55.88 0) (uuid \"00000000-0000-0000-0000-0000000000b2\"))\n",
+        );
+        let start = DEMO.find("\t\t(pin \"VIN\" input").unwrap();
+        let end = start + DEMO[start..].find("\t\t(instances").unwrap();
+        let root = format!("{}{}{}", &DEMO[..start], pins, &DEMO[end..]);
+        let label = concat!(
+            "(hierarchical_label \"EN\" (shape input) (at 76.2 60 90) ",
+            "(uuid \"00000000-0000-0000-0000-0000000000b3\"))",
+        );
+        let power = POWER.replacen("\t(symbol\n", &format!("\t{}\n\t(symbol\n", label), 1);
+        let source = MemorySource::new().with("demo.kicad_sch", root.as_str()).with("power.kicad_sch", power);
+        let mut hierarchy = Hierarchy::load(&source, "demo.kicad_sch").unwrap();
+
+        let messages = |hierarchy: &Hierarchy| -> Vec<String> {
+            hierarchy.check_sheet_pins().into_iter().map(|issue| issue.message).collect()
+        };
+        assert_eq!(messages(&hierarchy), [
+            "sheet power in demo.kicad_sch has pin VIN as output but its hierarchical label is input",
+            "sheet power in demo.kicad_sch has pin VIN twice",
+            "sheet power in demo.kicad_sch has pin OLD but power.kicad_sch has no such hierarchical label",
+            "sheet power in demo.kicad_sch has no pin for hierarchical label EN of power.kicad_sch",
+        ]);
+
+        let changed = hierarchy.sync_sheet_pins(true, &mut UuidGenerator::deterministic("demo"));
+        let sheet = hierarchy.sheet_symbol(hierarchy.sheet("/power/").unwrap()).unwrap().clone();
+        assert_eq!(changed, [(std::path::PathBuf::from("demo.kicad_sch"), sheet.uuid)]);
+        assert_eq!(messages(&hierarchy), [
+            "sheet power in demo.kicad_sch has pin OLD but power.kicad_sch has no such hierarchical label",
+        ]);
+        let placed: Vec<_> = sheet.pins.iter().map(|pin| (pin.name.as_str(), pin.shape, pin.at)).collect();
+        assert_eq!(placed, [
+            ("VIN", LabelShape::Input, Position::new(127.0, 55.88, 180.0)),
+            ("OLD", LabelShape::Passive, Position::new(152.4, 55.88, 0.0)),
+            ("EN", LabelShape::Input, Position::new(127.0, 53.34, 180.0)),
+        ]);
+        assert!(hierarchy.sync_sheet_pins(true, &mut UuidGenerator::deterministic("demo")).is_empty());
+
+        let mut doc = kicad_sexp::cst::parse(&root).unwrap();
+        sheet.write_pins(&mut doc).unwrap();
+        let text = doc.to_string();
+        let added = format!(
+            concat!(
+                "\t\t(pin \"OLD\" passive (at 152.4 55.88 0) (uuid \"00000000-0000-0000-0000-0000000000b2\"))\n",
+                "\t\t(pin \"EN\" input\n\t\t\t(at 127 53.34 180)\n\t\t\t(uuid \"{}\")\n\t\t\t(effects\n\t\t\t\t(font\n",
+                "\t\t\t\t\t(size 1.27 1.27)\n\t\t\t\t)\n\t\t\t\t(justify left)\n\t\t\t)\n\t\t)\n\t\t(instances",
+            ),
+            sheet.pins[2].uuid.unwrap(),
+        );
+        assert!(text.contains("\t\t(pin \"VIN\" input\n\t\t\t(at 127 55.88 180)\n"), "{}", text);
+        assert!(text.contains(&added), "{}", text);
+        assert!(!text.contains("0000000000b1"));
+        assert_eq!(Schematic::parse(&text).unwrap().sheets[0], sheet);
+    }
+
+    #[test]
+    fn legacy_instances() {
+        const SHEET: &str = "00000000-0000-0000-0000-00000000000a";
+        let symbol = |uuid: &str, unit: u32| {
+            format!(
+                "(symbol (lib_id \"Amplifier:LM358\") (at 0 0 0) (unit {}) (uuid \"{}\") \
+                 (property \"Reference\" \"U?\") (property \"Value\" \"LM358\") (property \"Footprint\" \"\"))",
+                unit, uuid
+            )
+        };
+        let root = format!(
+            "(kicad_sch (version 20211123) (uuid \"00000000-0000-0000-0000-000000000001\") \
+             (symbol (lib_id \"Device:R\") (at 0 0 0) (uuid \"00000000-0000-0000-0000-0000000000d1\") \
+             (property \"Reference\" \"R?\") (property \"Value\" \"R\")) \
+             (sheet (at 0 0) (size 10 10) (uuid \"{sheet}\") (property \"Sheet name\" \"amp\") \
+             (property \"Sheet file\" \"amp.kicad_sch\")) \
+             (symbol_instances \
+             (path \"/00000000-0000-0000-0000-0000000000d1\" (reference \"R5\") (unit 1) (value \"1k\") \
+             (footprint \"Resistor_SMD:R_0603_1608Metric\")) \
+             (path \"/{sheet}/00000000-0000-0000-0000-0000000000c2\" (reference \"U3\") (unit 2) (value \"LM358\") \
+             (footprint \"\")) \
+             (path \"/{sheet}/00000000-0000-0000-0000-0000000000c1\" (reference \"U3\") (unit 1) (value \"LM358\") \
+             (footprint \"Package_SO:SOIC-8\"))))",
+            sheet = SHEET
+        );
+        let amp = format!(
+            "(kicad_sch (version 20211123) {} {})",
+            symbol("00000000-0000-0000-0000-0000000000c2", 1),
+            symbol("00000000-0000-0000-0000-0000000000c1", 1)
+        );
+        let source = MemorySource::new().with("top.kicad_sch", root).with("amp.kicad_sch", amp);
+        let hierarchy = Hierarchy::load(&source, "top.kicad_sch").unwrap();
+        assert_eq!(hierarchy.root().symbol_instances.len(), 3);
+
+        let symbols: Vec<_> =
+            hierarchy.symbols().map(|flat| (flat.reference, flat.unit, flat.value, flat.footprint)).collect();
+        assert_eq!(symbols, [
+            ("R5", 1, "1k", Some("Resistor_SMD:R_0603_1608Metric")),
+            ("U3", 2, "LM358", None),
+            ("U3", 1, "LM358", Some("Package_SO:SOIC-8")),
+        ]);
+        let parts: Vec<_> = hierarchy
+            .parts()
+            .iter()
+            .map(|part| (part.reference, part.units.iter().map(|unit| unit.unit).collect::<Vec<_>>()))
+            .collect();
+        assert_eq!(parts, [("R5", vec![1]), ("U3", vec![1, 2])]);
+
+        #[cfg(feature = "export")]
+        {
+            let bom = Bom::from_hierarchy(&hierarchy, &BomOptions::default());
+            let rows: Vec<_> = bom.rows.iter().map(|row| (row.references.join(" "), row.field("Value"))).collect();
+            assert_eq!(rows, [("R5".into(), "1k"), ("U3".into(), "LM358"), ("U3".into(), "LM358")]);
+        }
+    }
+
+    #[test]
+    fn write_instances() {
+        let mut doc = kicad_sexp::cst::parse(DEMO).unwrap();
+        let mut r1 = Schematic::parse(DEMO).unwrap().symbol("R1").unwrap().clone();
+        let path = r1.instances[0].path.clone();
+        r1.set_instance("demo", &path, "R7", 1);
+        r1.write_instances(&mut doc).unwrap();
+        assert_eq!(doc.to_string(), DEMO.replacen("(reference \"R1\")", "(reference \"R7\")", 1));
+
+        r1.set_instance("copy", "/00000000-0000-0000-0000-000000000002", "R107", 1);
+        r1.instances[1].value = Some("22k".into());
+        r1.write_instances(&mut doc).unwrap();
+        let written = doc.to_string();
+        assert!(written.contains(
+            "\t\t\t(project \"copy\"\n\t\t\t\t(path \"/00000000-0000-0000-0000-000000000002\"\n\
+             \t\t\t\t\t(reference \"R107\")\n\t\t\t\t\t(unit 1)\n\t\t\t\t\t(value \"22k\")\n\
+             \t\t\t\t)\n\t\t\t)\n\t\t)\n\t)"
+        ));
+        let reparsed = Schematic::parse(&written).unwrap();
+        assert_eq!(reparsed.symbols.iter().find(|symbol| symbol.uuid == r1.uuid).unwrap().instances, r1.instances);
+        let schema = kicad_sexp::schema::Schema::parse(SCHEMA).unwrap();
+        assert!(schema.validate(&kicad_sexp::parse(&written).unwrap()).is_empty());
+
+        r1.instances.clear();
+        r1.write_instances(&mut doc).unwrap();
+        assert_eq!(doc.to_string().matches("(instances").count(), DEMO.matches("(instances").count() - 1);
+        r1.uuid = Default::default();
+        assert!(r1.write_instances(&mut doc).unwrap_err().to_string().contains("no symbol"));
+    }
+
+    #[test]
+    fn field_templates() {
+        let templates = [
+            kicad_common::property::FieldTemplate { name: "MPN".into(), ..Default::default() },
+            kicad_common::property::FieldTemplate { name: "Supplier".into(), visible: true, url: true },
+        ];
+        let mut schematic = Schematic::parse(DEMO).unwrap();
+        let symbols = schematic.symbols.len();
+        assert!(schematic.apply_field_templates(&templates).is_empty());
+        assert!(schematic.symbols.iter().all(|symbol| symbol.property("Supplier") == Some("")));
+        let r1 = schematic.symbol("R1").unwrap();
+        assert_eq!(r1.property("MPN"), Some("RC0603FR-0710KL"));
+
+        let mut doc = kicad_sexp::cst::parse(DEMO).unwrap();
+        for symbol in &schematic.symbols {
+            symbol.write_fields(&mut doc).unwrap();
+        }
+        let written = doc.to_string();
+        assert!(written.contains(concat!(
+            "\t\t(property \"MPN\" \"RC0603FR-0710KL\"\n\t\t\t(at 100.33 63.5 0)\n\t\t\t(effects\n\t\t\t\t(font\n",
+            "\t\t\t\t\t(size 1.27 1.27)\n\t\t\t\t)\n\t\t\t\t(hide yes)\n\t\t\t)\n\t\t)\n",
+            "\t\t(property \"Supplier\" \"\"\n\t\t\t(at 100.33 63.5 0)\n\t\t\t(effects\n\t\t\t\t(font\n",
+            "\t\t\t\t\t(size 1.27 1.27)\n\t\t\t\t)\n\t\t\t)\n\t\t)\n\t\t(pin \"1\"",
+        )));
+        assert_eq!(written.matches("(property \"Supplier\" \"\"").count(), symbols);
+        let reparsed = Schematic::parse(&written).unwrap();
+        assert_eq!(reparsed.symbols, schematic.symbols);
+
+        // Written fields are not added twice.
+        schematic.symbols[0].write_fields(&mut doc).unwrap();
+        assert_eq!(doc.to_string(), written);
+
+        let mut schematic = Schematic::parse(DEMO).unwrap();
+        schematic.symbols[0].properties.retain(|property| property.key != "Footprint" && property.key != "Datasheet");
+        let issues = schematic.apply_field_templates(&[]);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.ends_with("has no Footprint, Datasheet fields"), "{}", issues[0].message);
+    }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn bom() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let hierarchy = Hierarchy::load(&source, "demo/demo.kicad_sch").unwrap();
+        let bom = Bom::from_hierarchy(&hierarchy, &BomOptions::default());
+
+        let rows: Vec<_> = bom.rows.iter().map(|row| (row.references[0].as_str(), row.field("Value"))).collect();
+        assert_eq!(rows, [("C1", "100n"), ("J1", "Conn_01x03"), ("R1", "10k"), ("R2", "4k7")]);
+        assert_eq!(bom.rows[2].field("MPN"), "RC0603FR-0710KL");
+        assert_eq!(bom.columns, ["Value", "Footprint", "MPN"]);
+        assert_eq!(Bom::from_schematic(hierarchy.root(), &BomOptions::default()).rows.len(), 3);
+
+        let symbol = |reference: &str, value: &str, flags: &str| {
+            format!(
+                "(symbol (lib_id \"Device:R\") (at 0 0 0) {} (uuid \"00000000-0000-0000-0000-000000000001\") \
+                 (property \"Reference\" \"{}\") (property \"Value\" \"{}\") \
+                 (property \"Note\" \"a, \\\"b\\\"\"))",
+                flags, reference, value
+            )
+        };
+        let symbols = [
+            symbol("R10", "1k", ""),
+            symbol("R2", "1k", ""),
+            symbol("R3", "1k", "(dnp yes)"),
+            symbol("R4", "1k", "(in_bom no)"),
+            symbol("U1", "MCU", "(unit 1)"),
+            symbol("U1", "MCU", "(unit 2)"),
+            symbol("#PWR1", "GND", ""),
+        ];
+        let schematic = Schematic::parse(&format!("(kicad_sch (version 20250114) {})", symbols.join(" "))).unwrap();
+        let options = BomOptions { group_by: vec!["Value".into()], fields: vec!["Note".into()], include_dnp: true };
+        let bom = Bom::from_schematic(&schematic, &options);
+        let rows: Vec<_> = bom.rows.iter().map(|row| (row.references.join(" "), row.quantity, row.dnp)).collect();
+        assert_eq!(rows, [("R2 R10".into(), 2, false), ("R3".into(), 1, true), ("U1".into(), 1, false)]);
+        let csv: Vec<_> = bom.to_csv().lines().map(String::from).collect();
+        assert_eq!(csv, [
+            "Reference,Quantity,Value,Note,DNP",
+            "\"R2,R10\",2,1k,\"a, \"\"b\"\"\",",
+            "R3,1,1k,\"a, \"\"b\"\"\",DNP",
+            "U1,1,MCU,\"a, \"\"b\"\"\",",
+        ]);
+        assert_eq!(Bom::from_schematic(&schematic, &BomOptions::default()).rows.len(), 2);
+    }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn merged_bom() {
+        let row = |references: &[&str], value: &str, mpn: &str, dnp: bool| BomRow {
+            references: references.iter().map(|reference| reference.to_string()).collect(),
+            quantity: references.len(),
+            fields: [("Value", value), ("MPN", mpn)].map(|(k, v)| (k.to_string(), v.to_string())).into(),
+            dnp,
+        };
+        let columns = vec!["Value".to_string(), "MPN".to_string()];
+        let main = Bom {
+            columns: columns.clone(),
+            rows: vec![row(&["C1", "C2"], "100n", "", false), row(&["R1"], "10k", "RC0603", false)],
+        };
+        let io = Bom {
+            columns: vec!["Value".to_string(), "MPN".to_string(), "Note".to_string()],
+            rows: vec![
+                row(&["R5", "R6", "R7"], "10k", "RC0603", false),
+                row(&["R8"], "4k7", "", false),
+                row(&["R9"], "10k", "RC0603", true),
+            ],
+        };
+        let mut merged = MergedBom::new(&["Value", "MPN"]);
+        merged.add("main", &main, 1);
+        merged.add("io", &io, 2);
+        assert_eq!(merged.columns, ["Value", "MPN", "Note"]);
+        let rows: Vec<_> =
+            merged.rows.iter().map(|row| (row.field("Value"), row.per_board.as_slice(), row.quantity)).collect();
+        assert_eq!(rows, [("100n", &[2, 0][..], 2), ("10k", &[1, 3], 7), ("4k7", &[0, 1], 2)]);
+        assert_eq!(merged.to_csv().lines().take(3).collect::<Vec<_>>(), [
+            "Quantity,Value,MPN,Note,main x1,io x2",
+            "2,100n,,,2,0",
+            "7,10k,RC0603,,1,3",
+        ]);
+    }
+
+    #[test]
+    fn errors() {
+        let error = Schematic::parse("(kicad_sch (version 1) (wire (pts (xy 0 0))))").unwrap_err();
+        assert_eq!(error.to_string(), "kicad_sch > wire: missing (uuid ...)");
+
+        let error = Schematic::parse("(kicad_sch (version 1) (symbol (lib_id \"R\") (at 0 zero) (uuid 1)))").unwrap_err();
+        assert_eq!(error.to_string(), "kicad_sch > symbol > at: expected a number, found symbol `zero`");
+
+        assert!(matches!(Schematic::parse("(kicad_pcb (version 1))"), Err(Error::Convert(_))));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let schematic = Schematic::parse(DEMO).unwrap();
+        let json = serde_json::to_string(&schematic).unwrap();
+
+        assert!(json.contains(&format!("\"uuid\":\"{}\"", schematic.uuid.unwrap())));
+        assert_eq!(serde_json::from_str::<Schematic>(&json).unwrap(), schematic);
+    }
+    #[test]
+    fn schema() {
+        let schema = kicad_sexp::schema::Schema::parse(SCHEMA).unwrap();
+        for src in [include_str!("../../reference-files/empty/empty.kicad_sch"), DEMO, POWER] {
+            let issues = schema.validate(&kicad_sexp::parse(src).unwrap());
+            assert!(issues.is_empty(), "{:?}", issues.iter().map(ToString::to_string).collect::<Vec<_>>());
+        }
+
+        let src = "(kicad_sch (version 20250114) (wire (pts (xy 0 0) (xy 1))) (junction (at 0 0) (size 1 1)))";
+        let tree = kicad_sexp::parse(src).unwrap();
+        let issues: Vec<_> = schema.validate(&tree).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "kicad_sch > wire > pts > xy: missing argument, expected a number",
+                "kicad_sch > junction: unexpected (size ...)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Cost estimates for BOMs from a pluggable [`PriceProvider`]: a static
+//! [`PriceTable`], or a closure that asks an ERP or a distributor's API.
+//!
+//! ```no_run
+//! use std::collections::BTreeMap;
+//!
+//! use kicad_sch::{
+//!     pricing::{Price, PriceTable},
+//!     Bom, BomOptions, Schematic,
+//! };
+//!
+//! let schematic = Schematic::parse(&std::fs::read_to_string("demo.kicad_sch")?)?;
+//! let mut bom = Bom::from_schematic(&schematic, &BomOptions::default());
+//! let mut table = PriceTable::new("MPN", "EUR");
+//! table.add("RC0603FR-0710KL", &[(1, 0.10), (100, 0.008)]);
+//! let costs = bom.price(&table, 25)?;
+//! println!("{:.2} {} for 25 boards", costs.total, costs.currency.unwrap_or_default());
+//!
+//! // Or any other source of prices.
+//! let lookup = |fields: &BTreeMap<String, String>, quantity: usize| {
+//!     let unit = if quantity < 100 { 0.02 } else { 0.01 };
+//!     Ok(fields.get("Value").map(|_| Price { unit, currency: "USD".into() }))
+//! };
+//! bom.price(&lookup, 25)?;
+//! std::fs::write("bom.csv", bom.to_csv())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeMap;
+
+use kicad_common::{ConvertError, Error};
+
+use crate::{Bom, MergedBom};
+
+/// The price of one part.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Price {
+    pub unit: f64,
+    /// An ISO 4217 code such as `USD`.
+    pub currency: String,
+}
+
+/// Looks up the price of a BOM row's part.
+pub trait PriceProvider {
+    /// The unit price when buying `quantity` of the part with the row's
+    /// `fields`, or `None` if it has none.
+    fn price(&self, fields: &BTreeMap<String, String>, quantity: usize) -> Result<Option<Price>, Error>;
+}
+
+/// Another provider, e.g. one that queries a distributor.
+impl<F: Fn(&BTreeMap<String, String>, usize) -> Result<Option<Price>, Error>> PriceProvider for F {
+    fn price(&self, fields: &BTreeMap<String, String>, quantity: usize) -> Result<Option<Price>, Error> {
+        self(fields, quantity)
+    }
+}
+
+/// Prices with quantity breaks, by the value of one field.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriceTable {
+    /// The field naming the part, such as `MPN`.
+    pub key: String,
+    pub currency: String,
+    /// Unit prices by part, from the smallest quantity they apply at.
+    pub prices: BTreeMap<String, Vec<(usize, f64)>>,
+}
+
+impl PriceTable {
+    pub fn new(key: &str, currency: &str) -> Self {
+        PriceTable { key: key.to_string(), currency: currency.to_string(), prices: BTreeMap::new() }
+    }
+
+    /// Sets the quantity breaks of a part.
+    pub fn add(&mut self, part: &str, breaks: &[(usize, f64)]) {
+        let mut breaks = breaks.to_vec();
+        breaks.sort_by_key(|&(quantity, _)| quantity);
+        self.prices.insert(part.to_string(), breaks);
+    }
+}
+
+/// The price of the largest break `quantity` reaches, or of the smallest
+/// break for fewer parts.
+impl PriceProvider for PriceTable {
+    fn price(&self, fields: &BTreeMap<String, String>, quantity: usize) -> Result<Option<Price>, Error> {
+        let Some(breaks) = fields.get(&self.key).and_then(|part| self.prices.get(part)) else {
+            return Ok(None);
+        };
+        let reached = breaks.iter().rev().find(|&&(minimum, _)| minimum <= quantity).or(breaks.first());
+        Ok(reached.map(|&(_, unit)| Price { unit, currency: self.currency.clone() }))
+    }
+}
+
+/// What a priced BOM costs.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Costs {
+    /// `None` when no row got a price.
+    pub currency: Option<String>,
+    /// The sum of the priced rows.
+    pub total: f64,
+    /// The first reference or grouping value of each row without a price.
+    pub unpriced: Vec<String>,
+}
+
+/// Column with the price of one part.
+pub const UNIT_PRICE: &str = "Unit Price";
+/// Column with the price of the row's quantity.
+pub const EXTENDED_PRICE: &str = "Extended Price";
+
+impl Bom {
+    /// Prices the rows for building `boards` boards, filling in the
+    /// [`UNIT_PRICE`] and [`EXTENDED_PRICE`] columns. Fails if the
+    /// provider does, or if it quotes in more than one currency.
+    pub fn price(&mut self, provider: &impl PriceProvider, boards: usize) -> Result<Costs, Error> {
+        let rows = self.rows.iter_mut().map(|row| {
+            let name = row.references.first().cloned().unwrap_or_default();
+            (&mut row.fields, row.quantity * boards, name)
+        });
+        let costs = price_rows(rows, provider)?;
+        add_price_columns(&mut self.columns);
+        Ok(costs)
+    }
+}
+
+impl MergedBom {
+    /// Prices the rows at their total quantities, filling in the
+    /// [`UNIT_PRICE`] and [`EXTENDED_PRICE`] columns.
+    pub fn price(&mut self, provider: &impl PriceProvider) -> Result<Costs, Error> {
+        let first = self.columns.first().cloned().unwrap_or_default();
+        let rows = self.rows.iter_mut().map(|row| {
+            let name = row.field(&first).to_string();
+            (&mut row.fields, row.quantity, name)
+        });
+        let costs = price_rows(rows, provider)?;
+        add_price_columns(&mut self.columns);
+        Ok(costs)
+    }
+}
+
+fn price_rows<'a>(
+    rows: impl Iterator<Item = (&'a mut BTreeMap<String, String>, usize, String)>,
+    provider: &impl PriceProvider,
+) -> Result<Costs, Error> {
+    let mut costs = Costs::default();
+    for (fields, quantity, name) in rows {
+        let Some(price) = provider.price(fields, quantity)? else {
+            fields.insert(UNIT_PRICE.to_string(), String::new());
+            fields.insert(EXTENDED_PRICE.to_string(), String::new());
+            costs.unpriced.push(name);
+            continue;
+        };
+        match &costs.currency {
+            Some(currency) if *currency != price.currency => {
+                let message = format!("prices in both {} and {}", currency, price.currency);
+                return Err(ConvertError::new(message).into());
+            },
+            Some(_) => {},
+            None => costs.currency = Some(price.currency.clone()),
+        }
+        let extended = price.unit * quantity as f64;
+        costs.total += extended;
+        fields.insert(UNIT_PRICE.to_string(), format!("{:.4}", price.unit));
+        fields.insert(EXTENDED_PRICE.to_string(), format!("{:.2}", extended));
+    }
+    Ok(costs)
+}
+
+fn add_price_columns(columns: &mut Vec<String>) {
+    for column in [UNIT_PRICE, EXTENDED_PRICE] {
+        if !columns.iter().any(|existing| existing == column) {
+            columns.push(column.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BomRow;
+
+    fn bom() -> Bom {
+        let row = |references: &[&str], mpn: &str| BomRow {
+            references: references.iter().map(|reference| reference.to_string()).collect(),
+            quantity: references.len(),
+            fields: [("MPN".to_string(), mpn.to_string())].into(),
+            dnp: false,
+        };
+        Bom {
+            columns: vec!["MPN".to_string()],
+            rows: vec![row(&["C1", "C2"], "CL10B104"), row(&["R1"], "RC0603"), row(&["U1"], "STM32")],
+        }
+    }
+
+    #[test]
+    fn table() {
+        let mut table = PriceTable::new("MPN", "EUR");
+        table.add("CL10B104", &[(100, 0.002), (1, 0.01)]);
+        table.add("RC0603", &[(10, 0.005)]);
+
+        let mut bom = bom();
+        let costs = bom.price(&table, 50).unwrap();
+        assert_eq!(costs.currency.as_deref(), Some("EUR"));
+        assert!((costs.total - (100.0 * 0.002 + 50.0 * 0.005)).abs() < 1e-9);
+        assert_eq!(costs.unpriced, ["U1"]);
+        assert_eq!(bom.columns, ["MPN", UNIT_PRICE, EXTENDED_PRICE]);
+        assert_eq!(bom.rows[0].field(UNIT_PRICE), "0.0020");
+        assert_eq!(bom.rows[0].field(EXTENDED_PRICE), "0.20");
+        assert_eq!(bom.rows[2].field(EXTENDED_PRICE), "");
+        assert!(bom.to_csv().starts_with("Reference,Quantity,MPN,Unit Price,Extended Price,DNP\n"));
+        // Fewer parts than the smallest break still get its price.
+        let one = bom.price(&table, 1).unwrap();
+        assert!((one.total - (2.0 * 0.01 + 0.005)).abs() < 1e-9);
+        assert_eq!(bom.columns.len(), 3);
+
+        let mut merged = MergedBom::new(&["MPN"]);
+        merged.add("main", &self::bom(), 3);
+        let costs = merged.price(&table).unwrap();
+        assert_eq!(costs.unpriced, ["STM32"]);
+        assert_eq!(merged.rows[0].field(EXTENDED_PRICE), "0.06");
+    }
+
+    #[test]
+    fn closure() {
+        let currencies = |fields: &BTreeMap<String, String>, _: usize| {
+            let currency = if fields["MPN"] == "STM32" { "USD" } else { "EUR" };
+            Ok(Some(Price { unit: 1.0, currency: currency.to_string() }))
+        };
+        let error = bom().price(&currencies, 1).unwrap_err();
+        assert_eq!(error.to_string(), "prices in both EUR and USD");
+
+        let failing = |_: &BTreeMap<String, String>, _: usize| Err(ConvertError::new("offline").into());
+        assert!(bom().price(&failing, 1).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+//! Renaming a net everywhere its name is written: the labels that carry
+//! it, the pins of the sheet symbols that export a renamed hierarchical
+//! label, and the buses it is a member of.
+//!
+//! Bus names follow KiCad's syntax. A vector `D[0..7]` carries the nets
+//! `D0` to `D7`, so renaming it to `DATA[0..7]` also renames the labels of
+//! its members and of the sub-vectors such as `D[0..3]`. A group
+//! `{SDA SCL}` carries its members, a named group `MEM{A D[0..1]}` the
+//! nets `MEM.A`, `MEM.D0` and `MEM.D1`. Renaming `MEM` renames the group
+//! and the `MEM.`-prefixed labels, renaming `MEM.A` to `MEM.ADDR` the
+//! member of the group, and renaming `SDA` the member of the unnamed one.
+//!
+//! ```no_run
+//! use kicad_sch::Hierarchy;
+//! use kicad_sexp::source::FsSource;
+//!
+//! let mut hierarchy = Hierarchy::load(&FsSource::new(), "demo.kicad_sch")?;
+//! let renamed = hierarchy.rename_net(Some("/power/"), "VIN", "VSUPPLY")?;
+//! for (file, uuid) in &renamed.labels {
+//!     let label = hierarchy.files[file].labels.iter().find(|label| label.uuid == *uuid).unwrap();
+//!     let src = std::fs::read_to_string(file)?;
+//!     let mut doc = kicad_sexp::cst::parse(&src)?;
+//!     label.write_text(&mut doc)?;
+//!     std::fs::write(file, doc.to_string())?;
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{collections::BTreeSet, path::PathBuf};
+
+use kicad_common::{ConvertError, Error, Uuid};
+
+use crate::{Hierarchy, LabelKind, Schematic};
+
+/// What [`Hierarchy::rename_net`] changed, as the file each item is in and
+/// its UUID.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Renamed {
+    /// Write back with [`Label::write_text`](crate::Label::write_text).
+    pub labels: Vec<(PathBuf, Uuid)>,
+    /// Sheet symbols with renamed pins; write back with
+    /// [`Sheet::write_pins`](crate::Sheet::write_pins).
+    pub sheets: Vec<(PathBuf, Uuid)>,
+}
+
+/// A label text read as a bus name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum BusName<'a> {
+    Net(&'a str),
+    /// `prefix[from..to]`.
+    Vector { prefix: &'a str, from: i64, to: i64 },
+    /// `name{members}`, the name empty for an unnamed group.
+    Group { name: &'a str, members: Vec<&'a str> },
+}
+
+fn parse_bus(text: &str) -> BusName<'_> {
+    if let Some((name, rest)) = text.split_once('{')
+        && let Some(members) = rest.strip_suffix('}')
+    {
+        let members = members.split([' ', ',']).filter(|member| !member.is_empty()).collect();
+        return BusName::Group { name, members };
+    }
+    if let Some((prefix, rest)) = text.split_once('[')
+        && let Some((from, to)) = rest.strip_suffix(']').and_then(|range| range.split_once(".."))
+        && let (Ok(from), Ok(to)) = (from.parse(), to.parse())
+    {
+        return BusName::Vector { prefix, from, to };
+    }
+    BusName::Net(text)
+}
+
+/// The nets the label text `text` carries, and the group's own name.
+fn carried(text: &str) -> Vec<String> {
+    let members = |member: &str| match parse_bus(member) {
+        BusName::Vector { prefix, from, to } => {
+            (from.min(to)..=from.max(to)).map(|i| format!("{}{}", prefix, i)).collect()
+        },
+        _ => vec![member.to_string()],
+    };
+    match parse_bus(text) {
+        BusName::Net(net) => vec![net.to_string()],
+        BusName::Vector { .. } => {
+            let mut nets = members(text);
+            nets.push(text.to_string());
+            nets
+        },
+        BusName::Group { name, members: list } => {
+            let nets = list.into_iter().flat_map(members);
+            let mut nets: Vec<String> = match name {
+                "" => nets.collect(),
+                name => nets.map(|net| format!("{}.{}", name, net)).collect(),
+            };
+            if !name.is_empty() {
+                nets.push(name.to_string());
+            }
+            nets
+        },
+    }
+}
+
+/// How one net name, plain or a vector, becomes the other.
+struct Rename<'a> {
+    old: BusName<'a>,
+    new: BusName<'a>,
+}
+
+impl Rename<'_> {
+    /// The new name of a plain net or vector, outside of groups.
+    fn name(&self, text: &str) -> Option<String> {
+        match (&self.old, &self.new, parse_bus(text)) {
+            (BusName::Net(old), BusName::Net(new), BusName::Net(net)) => {
+                if net == *old {
+                    return Some(new.to_string());
+                }
+                // A member of the named group being renamed.
+                let member = net.strip_prefix(old)?.strip_prefix('.')?;
+                Some(format!("{}.{}", new, member))
+            },
+            (BusName::Vector { prefix: old, from, to }, BusName::Vector { prefix: new, .. }, bus) => {
+                let range = *from.min(to)..=*from.max(to);
+                match bus {
+                    BusName::Net(net) => {
+                        let index: i64 = net.strip_prefix(old)?.parse().ok()?;
+                        range.contains(&index).then(|| format!("{}{}", new, index))
+                    },
+                    BusName::Vector { prefix, from, to } if prefix == *old => {
+                        let inside = range.contains(&from) && range.contains(&to);
+                        inside.then(|| format!("{}[{}..{}]", new, from, to))
+                    },
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// The new text of a label, or `None` if it carries none of the nets.
+    fn text(&self, text: &str) -> Option<String> {
+        let BusName::Group { name, members } = parse_bus(text) else {
+            return self.name(text);
+        };
+        let mut changed = false;
+        let mut rename = |member: &str, new: Option<String>| match new {
+            Some(new) => {
+                changed |= new != member;
+                new
+            },
+            None => member.to_string(),
+        };
+        let new_name = match (&self.old, &self.new) {
+            (BusName::Net(old), BusName::Net(new)) if !name.is_empty() && name == *old => {
+                rename(name, Some(new.to_string()))
+            },
+            _ => name.to_string(),
+        };
+        // The members of a named group are the nets `MEM.A`, which keep
+        // their group.
+        let members: Vec<String> = match name {
+            "" => members.into_iter().map(|member| rename(member, self.name(member))).collect(),
+            name => (members.into_iter())
+                .map(|member| {
+                    let new = self.name(&format!("{}.{}", name, member));
+                    let new = new.and_then(|new| Some(new.strip_prefix(name)?.strip_prefix('.')?.to_string()));
+                    rename(member, new)
+                })
+                .collect(),
+        };
+        changed.then(|| format!("{}{{{}}}", new_name, members.join(" ")))
+    }
+}
+
+impl Hierarchy {
+    /// Renames the net `old` to `new`: on the sheet `sheet`, by sheet path
+    /// of names like `/power/`, its local and hierarchical labels and the
+    /// pins of the sheet symbols that place the sheet's file; with no sheet
+    /// its global labels on every sheet. A file used by several sheets is
+    /// renamed for each of them. Both names may be bus vectors of the same
+    /// range, such as `D[0..7]` and `DATA[0..7]`.
+    ///
+    /// Fails without changing anything when no label carries `old`, or when
+    /// a label in the renamed files or a power symbol already carries one of
+    /// the nets of `new`, as the two nets would merge.
+    pub fn rename_net(&mut self, sheet: Option<&str>, old: &str, new: &str) -> Result<Renamed, Error> {
+        let rename = Rename { old: parse_bus(old), new: parse_bus(new) };
+        match (&rename.old, &rename.new) {
+            (BusName::Net(_), BusName::Net(_)) => {},
+            (BusName::Vector { from, to, .. }, BusName::Vector { from: new_from, to: new_to, .. })
+                if (from, to) == (new_from, new_to) => {},
+            (BusName::Vector { .. }, BusName::Vector { .. }) => {
+                return Err(ConvertError::new(format!("{} and {} are buses of different ranges", old, new)).into());
+            },
+            _ => return Err(ConvertError::new(format!("cannot rename {} to {}", old, new)).into()),
+        }
+        if old == new {
+            return Ok(Renamed::default());
+        }
+        let (files, kinds): (Vec<PathBuf>, &[LabelKind]) = match sheet {
+            Some(name_path) => {
+                let node = self.sheet(name_path).ok_or_else(|| ConvertError::new(format!("no sheet {}", name_path)))?;
+                (vec![node.file.clone()], &[LabelKind::Local, LabelKind::Hierarchical])
+            },
+            None => (self.files.keys().cloned().collect(), &[LabelKind::Global]),
+        };
+
+        let mut used: BTreeSet<String> = BTreeSet::new();
+        for file in &files {
+            let schematic = &self.files[file];
+            used.extend(schematic.labels.iter().flat_map(|label| carried(&label.text)));
+            used.extend(power_nets(schematic));
+        }
+        if let Some(taken) = carried(new).into_iter().find(|net| used.contains(net)) {
+            let place = match sheet {
+                Some(name_path) => format!("sheet {}", name_path),
+                None => "the design".to_string(),
+            };
+            return Err(ConvertError::new(format!("net {} is already used on {}", taken, place)).into());
+        }
+
+        let mut renamed = Renamed::default();
+        let mut ports: Vec<(PathBuf, String, String)> = Vec::new();
+        for file in &files {
+            let schematic = self.files.get_mut(file).expect("sheet files are loaded");
+            for label in schematic.labels.iter_mut().filter(|label| kinds.contains(&label.kind)) {
+                let Some(text) = rename.text(&label.text) else {
+                    continue;
+                };
+                if label.kind == LabelKind::Hierarchical {
+                    ports.push((file.clone(), label.text.clone(), text.clone()));
+                }
+                label.text = text;
+                renamed.labels.push((file.clone(), label.uuid));
+            }
+        }
+        if renamed.labels.is_empty() {
+            return Err(ConvertError::new(format!("no label carries the net {}", old)).into());
+        }
+
+        // The sheet symbols placing a renamed file export its labels as pins.
+        for (parent, uuid, child) in self.sheet_symbols() {
+            let schematic = self.files.get_mut(&parent).expect("sheet files are loaded");
+            let sheet = schematic.sheets.iter_mut().find(|sheet| sheet.uuid == uuid).expect("sheets are loaded");
+            let mut changed = false;
+            for pin in &mut sheet.pins {
+                let port = ports.iter().find(|(file, old, _)| file == &child && *old == pin.name);
+                if let Some((_, _, new)) = port {
+                    pin.name = new.clone();
+                    changed = true;
+                }
+            }
+            if changed && !renamed.sheets.contains(&(parent.clone(), uuid)) {
+                renamed.sheets.push((parent, uuid));
+            }
+        }
+        Ok(renamed)
+    }
+}
+
+/// The global nets the power symbols of `schematic` create, by value.
+fn power_nets(schematic: &Schematic) -> impl Iterator<Item = String> + '_ {
+    let power = |lib_id: &str| schematic.lib_symbol(lib_id).is_some_and(|lib| lib.power);
+    (schematic.symbols.iter())
+        .filter(move |symbol| power(symbol.lib_name.as_deref().unwrap_or(&symbol.lib_id)))
+        .filter_map(|symbol| symbol.value().map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, path::Path};
+
+    use kicad_sexp::source::{FsSource, MemorySource, Source};
+
+    use super::*;
+    use crate::Label;
+
+    #[test]
+    fn demo() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let mut hierarchy = Hierarchy::load(&source, "demo/demo.kicad_sch").unwrap();
+        let (root, power) = (PathBuf::from("demo/demo.kicad_sch"), PathBuf::from("demo/power.kicad_sch"));
+
+        let error = hierarchy.rename_net(Some("/power/"), "VIN", "GND").unwrap_err();
+        assert_eq!(error.to_string(), "net GND is already used on sheet /power/");
+        let error = hierarchy.rename_net(None, "VIN", "VOUT").unwrap_err();
+        assert_eq!(error.to_string(), "net VOUT is already used on the design");
+        let error = hierarchy.rename_net(Some("/"), "VIN", "VCC").unwrap_err();
+        assert_eq!(error.to_string(), "no label carries the net VIN");
+
+        // Writes the renamed items back into their files and reads them.
+        let write = |hierarchy: &Hierarchy, renamed: &Renamed| {
+            let mut files: BTreeMap<PathBuf, String> = BTreeMap::new();
+            for (file, uuid) in renamed.labels.iter().chain(&renamed.sheets) {
+                let schematic = &hierarchy.files[file];
+                let src = match files.get(file) {
+                    Some(src) => src.clone(),
+                    None => source.read_to_string(file).unwrap(),
+                };
+                let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+                match schematic.labels.iter().find(|label| label.uuid == *uuid) {
+                    Some(label) => label.write_text(&mut doc).unwrap(),
+                    None => {
+                        let sheet = schematic.sheets.iter().find(|sheet| sheet.uuid == *uuid).unwrap();
+                        sheet.write_pins(&mut doc).unwrap();
+                    },
+                }
+                files.insert(file.clone(), doc.to_string());
+            }
+            let files = files.into_iter().map(|(file, src)| (file, Schematic::parse(&src).unwrap()));
+            files.collect::<BTreeMap<_, _>>()
+        };
+
+        let renamed = hierarchy.rename_net(Some("/power/"), "VIN", "VSUPPLY").unwrap();
+        assert_eq!(renamed.labels.len(), 1);
+        assert_eq!(renamed.labels[0].0, power);
+        assert_eq!(renamed.sheets, [(root.clone(), hierarchy.files[&root].sheets[0].uuid)]);
+        assert!(hierarchy.check_sheet_pins().is_empty());
+        // The global labels on the root are another net.
+        assert!(hierarchy.files[&root].labels.iter().any(|label| label.text == "VIN"));
+        let written = write(&hierarchy, &renamed);
+        assert_eq!(written[&root].sheets, hierarchy.files[&root].sheets);
+        assert_eq!(written[&power].labels, hierarchy.files[&power].labels);
+
+        let renamed = hierarchy.rename_net(None, "VIN", "VBUS").unwrap();
+        assert_eq!(renamed.labels.len(), 3);
+        assert!(renamed.sheets.is_empty());
+        let written = write(&hierarchy, &renamed);
+        assert_eq!(written[&root].labels, hierarchy.files[&root].labels);
+        assert_eq!(written[&root].labels.iter().filter(|label| label.text == "VBUS").count(), 3);
+        let mut doc = kicad_sexp::cst::parse("(kicad_sch)").unwrap();
+        let label = Label { text: "VBUS".into(), ..Default::default() };
+        let error = label.write_text(&mut doc).unwrap_err();
+        assert_eq!(error.to_string(), format!("no label {} in the schematic", label.uuid));
+    }
+
+    #[test]
+    fn buses() {
+        let labels = ["D[0..7]", "D3", "D[0..3]", "E3", "{SDA SCL}", "MEM{A D[0..1]}", "MEM.A", "D9"];
+        let label = |(i, text): (usize, &&str)| {
+            format!("(label \"{}\" (at 0 {} 0) (uuid \"00000000-0000-0000-0000-{:012}\"))", text, i, i + 1)
+        };
+        let items: String = labels.iter().enumerate().map(label).collect();
+        let source = MemorySource::new().with("top.kicad_sch", format!("(kicad_sch (version 20250114) {})", items));
+        let mut hierarchy = Hierarchy::load(&source, "top.kicad_sch").unwrap();
+        let texts = |hierarchy: &Hierarchy| -> Vec<String> {
+            hierarchy.files[Path::new("top.kicad_sch")].labels.iter().map(|label| label.text.clone()).collect()
+        };
+
+        let renamed = hierarchy.rename_net(Some("/"), "D[0..7]", "DATA[0..7]").unwrap();
+        assert_eq!(renamed.labels.len(), 3);
+        assert_eq!(texts(&hierarchy)[..4], ["DATA[0..7]", "DATA3", "DATA[0..3]", "E3"]);
+        hierarchy.rename_net(Some("/"), "SDA", "I2C_SDA").unwrap();
+        hierarchy.rename_net(Some("/"), "MEM", "RAM").unwrap();
+        hierarchy.rename_net(Some("/"), "RAM.A", "RAM.ADDR").unwrap();
+        assert_eq!(texts(&hierarchy)[4..], ["{I2C_SDA SCL}", "RAM{ADDR D[0..1]}", "RAM.ADDR", "D9"]);
+
+        let error = hierarchy.rename_net(Some("/"), "SCL", "I2C_SDA").unwrap_err();
+        assert_eq!(error.to_string(), "net I2C_SDA is already used on sheet /");
+        let error = hierarchy.rename_net(Some("/"), "DATA[0..7]", "E[0..7]").unwrap_err();
+        assert_eq!(error.to_string(), "net E3 is already used on sheet /");
+        let error = hierarchy.rename_net(Some("/"), "DATA[0..7]", "Q[0..3]").unwrap_err();
+        assert_eq!(error.to_string(), "DATA[0..7] and Q[0..3] are buses of different ranges");
+        let error = hierarchy.rename_net(Some("/"), "E3", "{E3 E4}").unwrap_err();
+        assert_eq!(error.to_string(), "cannot rename E3 to {E3 E4}");
+        assert_eq!(carried("MEM{A D[0..1]}"), ["MEM.A", "MEM.D0", "MEM.D1", "MEM"]);
+    }
+}
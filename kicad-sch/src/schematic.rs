@@ -0,0 +1,174 @@
+use kicad_common::{
+    convert::{check_version, parse_document, FromSexp, List, Result},
+    embedded::{self, Codec, EmbeddedFile},
+    extension::{parse_extension, ExtensionNode},
+    property::{FieldTemplate, MANDATORY_FIELDS},
+    text::{Effects, Font},
+    uuid::item_uuid,
+    Error, Paper, Property, TitleBlock, Uuid,
+};
+use kicad_sexp::schema::Issue;
+
+use crate::{
+    sheet::sheet_instances, symbol::symbol_instances, BusEntry, InstancePath, Junction, Label, LibSymbol, NoConnect,
+    Sheet, SheetInstance, SymbolInstance, Text, Wire,
+};
+
+/// A `.kicad_sch` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Schematic {
+    /// File format version, a date like `20250114`.
+    pub version: u32,
+    pub generator: String,
+    pub generator_version: Option<String>,
+    pub uuid: Option<Uuid>,
+    pub paper: Paper,
+    pub title_block: Option<TitleBlock>,
+    pub lib_symbols: Vec<LibSymbol>,
+    pub junctions: Vec<Junction>,
+    pub no_connects: Vec<NoConnect>,
+    pub bus_entries: Vec<BusEntry>,
+    pub wires: Vec<Wire>,
+    pub buses: Vec<Wire>,
+    pub texts: Vec<Text>,
+    pub labels: Vec<Label>,
+    pub symbols: Vec<SymbolInstance>,
+    pub sheets: Vec<Sheet>,
+    /// Page numbers, only present in the root sheet's file.
+    pub sheet_instances: Vec<SheetInstance>,
+    /// Symbol references of the whole design, only present in the root
+    /// sheet's file of KiCad 6 designs; newer files keep them in each
+    /// symbol's `instances`.
+    pub symbol_instances: Vec<InstancePath>,
+    pub embedded_files: Vec<EmbeddedFile>,
+    /// Lists handled by an [`Extension`](kicad_common::extension::Extension)
+    /// registered for the parse.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub extensions: Vec<ExtensionNode>,
+}
+
+impl Schematic {
+    /// Newest file format version the model is written against, KiCad 9.
+    pub const NEWEST_VERSION: u32 = 20250114;
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(symbols, wires, sheets)))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let schematic: Schematic = parse_document(src, "kicad_sch")?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current()
+            .record("symbols", schematic.symbols.len())
+            .record("wires", schematic.wires.len())
+            .record("sheets", schematic.sheets.len());
+        Ok(schematic)
+    }
+
+    /// Fails with [`Error::Version`] for files from a newer KiCad.
+    pub fn check_version(&self) -> std::result::Result<(), Error> {
+        check_version("kicad_sch", self.version, Self::NEWEST_VERSION)
+    }
+
+    pub fn lib_symbol(&self, lib_id: &str) -> Option<&LibSymbol> {
+        self.lib_symbols.iter().find(|symbol| symbol.lib_id == lib_id)
+    }
+
+    /// Finds a placed symbol by its `Reference` field.
+    pub fn symbol(&self, reference: &str) -> Option<&SymbolInstance> {
+        self.symbols.iter().find(|symbol| symbol.reference() == Some(reference))
+    }
+
+    /// Adds the fields of `templates` that a placed symbol lacks, empty and
+    /// shown as the template says, at the symbol's position; fields the
+    /// symbol has keep their value. Then reports the symbols missing one of
+    /// KiCad's [`MANDATORY_FIELDS`]. Write the added fields back with
+    /// [`SymbolInstance::write_fields`].
+    pub fn apply_field_templates(&mut self, templates: &[FieldTemplate]) -> Vec<Issue> {
+        // Files from before KiCad 8 have no description field.
+        let mandatory = match self.version < 20231120 {
+            true => &MANDATORY_FIELDS[..4],
+            false => &MANDATORY_FIELDS[..],
+        };
+        let mut issues = Vec::new();
+        for symbol in &mut self.symbols {
+            for template in templates {
+                if symbol.property(&template.name).is_some() {
+                    continue;
+                }
+                let font = Font { height: 1.27, width: 1.27, ..Default::default() };
+                symbol.properties.push(Property {
+                    key: template.name.clone(),
+                    at: Some(symbol.at),
+                    effects: Some(Effects { font, hide: !template.visible, ..Default::default() }),
+                    hide: !template.visible,
+                    ..Default::default()
+                });
+            }
+            let missing: Vec<&str> = mandatory.iter().copied().filter(|key| symbol.property(key).is_none()).collect();
+            if !missing.is_empty() {
+                let name = symbol.reference().map_or_else(|| symbol.uuid.to_string(), str::to_string);
+                issues.push(Issue {
+                    path: vec!["kicad_sch".into(), "symbol".into()],
+                    message: format!(
+                        "symbol {} has no {} field{}",
+                        name,
+                        missing.join(", "),
+                        if missing.len() > 1 { "s" } else { "" }
+                    ),
+                });
+            }
+        }
+        issues
+    }
+
+    /// Checks the embedded files against their checksums, see
+    /// [`embedded::verify`].
+    pub fn check_embedded_files(&self, codec: &dyn Codec) -> Vec<Issue> {
+        embedded::verify(&self.embedded_files, codec, &["kicad_sch"])
+    }
+
+    /// Adds a top-level item, like `(wire ...)` or `(lib_symbols ...)`, to
+    /// the schematic. Unknown items are kept as extensions or skipped.
+    pub(crate) fn add_item(&mut self, item: &List) -> Result<()> {
+        match item.tag() {
+            "lib_symbols" => self.lib_symbols = item.parse_all("symbol")?,
+            "junction" => self.junctions.push(Junction::from_sexp(item)?),
+            "no_connect" => self.no_connects.push(NoConnect::from_sexp(item)?),
+            "bus_entry" => self.bus_entries.push(BusEntry::from_sexp(item)?),
+            "wire" => self.wires.push(Wire::from_sexp(item)?),
+            "bus" => self.buses.push(Wire::from_sexp(item)?),
+            "text" => self.texts.push(Text::from_sexp(item)?),
+            "label" | "global_label" | "hierarchical_label" => self.labels.push(Label::from_sexp(item)?),
+            "symbol" => self.symbols.push(SymbolInstance::from_sexp(item)?),
+            "sheet" => self.sheets.push(Sheet::from_sexp(item)?),
+            "sheet_instances" => self.sheet_instances = sheet_instances(item)?,
+            "symbol_instances" => self.symbol_instances = symbol_instances(item)?,
+            "embedded_files" => self.embedded_files = item.parse_all("file")?,
+            _ => {
+                if let Some(node) = parse_extension(item) {
+                    self.extensions.push(node?);
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+impl FromSexp for Schematic {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mut schematic = Schematic {
+            version: list.child_number("version")?.ok_or_else(|| list.error("missing (version ...)"))?,
+            generator: list.child_string("generator")?.unwrap_or_default(),
+            generator_version: list.child_string("generator_version")?,
+            uuid: item_uuid(list)?,
+            paper: list.parse("paper")?.unwrap_or_default(),
+            title_block: list.parse("title_block")?,
+            ..Default::default()
+        };
+
+        for item in list.lists() {
+            schematic.add_item(&item).map_err(|e| e.within(list.tag()))?;
+        }
+
+        Ok(schematic)
+    }
+}
@@ -0,0 +1,209 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    property::property,
+    uuid::item_uuid,
+    Color, ConvertError, Effects, Error, HAlign, Point, Position, Property, Size, Stroke, Uuid, VAlign,
+};
+use kicad_sexp::{
+    cst::{Document, Node},
+    number::format_float,
+};
+
+use crate::{items::label_shape, symbol::project_instances, LabelShape};
+
+/// A pin on a sheet symbol, matching a hierarchical label in the child sheet.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SheetPin {
+    pub name: String,
+    pub shape: LabelShape,
+    pub at: Position,
+    pub effects: Effects,
+    pub uuid: Option<Uuid>,
+}
+
+impl FromSexp for SheetPin {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(SheetPin {
+            name: list.string(0)?,
+            shape: label_shape(list, 1)?,
+            at: list.parse_required("at")?,
+            effects: list.parse("effects")?.unwrap_or_default(),
+            uuid: item_uuid(list)?,
+        })
+    }
+}
+
+/// Page number of a sheet at one sheet path, from `sheet_instances` or a
+/// sheet's own `instances`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SheetInstance {
+    /// Project name; empty for the root file's `sheet_instances`.
+    pub project: String,
+    pub path: String,
+    pub page: String,
+}
+
+fn sheet_instance(project: String, path: &List) -> Result<SheetInstance> {
+    Ok(SheetInstance {
+        project,
+        path: path.string(0)?,
+        page: path.child_string("page")?.unwrap_or_default(),
+    })
+}
+
+/// Reads a root `(sheet_instances (path "/" (page "1")) ...)` section.
+pub(crate) fn sheet_instances(list: &List) -> Result<Vec<SheetInstance>> {
+    list.children("path")
+        .map(|path| sheet_instance(String::new(), &path).map_err(|e| e.within(list.tag())))
+        .collect()
+}
+
+/// A hierarchical sheet symbol referencing another schematic file.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sheet {
+    pub at: Point,
+    pub size: Size,
+    pub exclude_from_sim: bool,
+    pub in_bom: bool,
+    pub on_board: bool,
+    pub dnp: bool,
+    pub fields_autoplaced: bool,
+    pub stroke: Stroke,
+    pub fill: Option<Color>,
+    pub uuid: Uuid,
+    pub properties: Vec<Property>,
+    pub pins: Vec<SheetPin>,
+    pub instances: Vec<SheetInstance>,
+}
+
+impl Sheet {
+    pub fn property(&self, key: &str) -> Option<&str> {
+        property(&self.properties, key)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.property("Sheetname").or_else(|| self.property("Sheet name"))
+    }
+
+    /// Path of the child schematic, relative to this file.
+    pub fn file(&self) -> Option<&str> {
+        self.property("Sheetfile").or_else(|| self.property("Sheet file"))
+    }
+
+    /// Writes the pins and size of this sheet to its node in the schematic
+    /// `doc`, found by UUID. Pin nodes are matched by UUID, or by name for
+    /// pins without one: those of removed pins go, those of kept pins get
+    /// their name and shape, and new pins are added after the last pin as
+    /// KiCad writes them.
+    pub fn write_pins(&self, doc: &mut Document<'_>) -> std::result::Result<(), Error> {
+        let root = doc.nodes.iter_mut().find(|node| node.tag() == Some("kicad_sch"));
+        let items = root.and_then(|root| root.items_mut());
+        let items = items.ok_or_else(|| ConvertError::new("expected a (kicad_sch ...) root"))?;
+        let uuid = |node: &Node| node.child("uuid")?.items().get(1)?.value()?.parse::<Uuid>().ok();
+        let sheet = items.iter_mut().find(|item| item.tag() == Some("sheet") && uuid(item) == Some(self.uuid));
+        let sheet = sheet.ok_or_else(|| ConvertError::new(format!("no sheet {} in the schematic", self.uuid)))?;
+        let indent = match &sheet.leading {
+            leading if leading.contains('\n') => format!("{}\t", leading),
+            _ => "\n\t\t".to_string(),
+        };
+
+        if let Some(size) = sheet.child_mut("size").and_then(|size| size.items_mut()) {
+            for (item, value) in size.iter_mut().skip(1).zip([self.size.width, self.size.height]) {
+                if item.value().and_then(|text| text.parse::<f64>().ok()) != Some(value) {
+                    item.set_atom(format_float(value));
+                }
+            }
+        }
+
+        let children = sheet.items_mut().expect("sheet is a list");
+        let name = |node: &Node| node.items().get(1).and_then(|name| name.value()).map(|name| name.into_owned());
+        let matches = |node: &Node, pin: &SheetPin| match pin.uuid {
+            Some(pin_uuid) => uuid(node) == Some(pin_uuid),
+            None => name(node).as_deref() == Some(pin.name.as_str()),
+        };
+        children.retain(|child| child.tag() != Some("pin") || self.pins.iter().any(|pin| matches(child, pin)));
+        let mut written = Vec::new();
+        for child in children.iter_mut().filter(|child| child.tag() == Some("pin")) {
+            let Some(pin) = self.pins.iter().find(|pin| matches(child, pin)) else {
+                continue;
+            };
+            if let Some(name) = child.items_mut().and_then(|items| items.get_mut(1))
+                && name.value().as_deref() != Some(pin.name.as_str())
+            {
+                name.set_string(&pin.name);
+            }
+            if let Some(shape) = child.items_mut().and_then(|items| items.get_mut(2))
+                && shape.text() != Some(pin.shape.name())
+            {
+                shape.set_atom(pin.shape.name());
+            }
+            written.push(pin);
+        }
+
+        let last = children.iter().rposition(|child| child.tag() == Some("pin"));
+        let before = children.iter().position(|child| child.tag() == Some("instances"));
+        let at = last.map_or(before.unwrap_or(children.len()), |last| last + 1);
+        let indent = last.map_or(indent, |i| children[i].leading.to_string());
+        let new = self.pins.iter().filter(|pin| !written.iter().any(|written| std::ptr::eq(*written, *pin)));
+        let nodes: Vec<Node<'static>> = new.map(|pin| pin_node(pin, &indent)).collect();
+        children.splice(at..at, nodes);
+        Ok(())
+    }
+}
+
+fn pin_node(pin: &SheetPin, indent: &str) -> Node<'static> {
+    let (inner, font_indent) = (format!("{}\t", indent), format!("{}\t\t", indent));
+    let number = |value: f64| Node::atom(format_float(value));
+    let font = pin.effects.font.clone();
+    let size = Node::tagged("size", vec![number(font.height), number(font.width)]);
+    let mut effects = vec![Node::block("font", Vec::new(), vec![size], &font_indent)];
+    let justify = pin.effects.justify;
+    let words = [
+        match justify.horizontal {
+            HAlign::Left => Some("left"),
+            HAlign::Center => None,
+            HAlign::Right => Some("right"),
+        },
+        match justify.vertical {
+            VAlign::Top => Some("top"),
+            VAlign::Center => None,
+            VAlign::Bottom => Some("bottom"),
+        },
+        justify.mirror.then_some("mirror"),
+    ];
+    let words: Vec<Node<'static>> = words.into_iter().flatten().map(Node::atom).collect();
+    if !words.is_empty() {
+        effects.push(Node::tagged("justify", words));
+    }
+    let mut children = vec![Node::tagged("at", vec![number(pin.at.x), number(pin.at.y), number(pin.at.angle)])];
+    children.extend(pin.uuid.map(|uuid| Node::tagged("uuid", vec![Node::string(&uuid.to_string())])));
+    children.push(Node::block("effects", Vec::new(), effects, &inner));
+    Node::block("pin", vec![Node::string(&pin.name), Node::atom(pin.shape.name())], children, indent)
+}
+
+impl FromSexp for Sheet {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let fill = match list.child("fill") {
+            Some(fill) => fill.parse("color").map_err(|e| e.within(list.tag()))?,
+            None => None,
+        };
+        Ok(Sheet {
+            at: list.parse_required("at")?,
+            size: list.parse_required("size")?,
+            exclude_from_sim: list.flag("exclude_from_sim")?,
+            in_bom: list.opt_flag("in_bom")?.unwrap_or(true),
+            on_board: list.opt_flag("on_board")?.unwrap_or(true),
+            dnp: list.flag("dnp")?,
+            fields_autoplaced: list.flag("fields_autoplaced")?,
+            stroke: list.parse("stroke")?.unwrap_or_default(),
+            fill,
+            uuid: item_uuid(list)?.ok_or_else(|| list.error("missing (uuid ...)"))?,
+            properties: list.parse_all("property")?,
+            pins: list.parse_all("pin")?,
+            instances: project_instances(list, sheet_instance)?,
+        })
+    }
+}
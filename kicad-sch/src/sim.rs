@@ -0,0 +1,605 @@
+//! Simulation models: the `Sim.Device`, `Sim.Type`, `Sim.Pins`,
+//! `Sim.Params`, `Sim.Name` and `Sim.Library` fields KiCad's simulator
+//! keeps on symbols, and the SPICE directives written as text on the
+//! sheet, such as `.model`, `.include` and `.tran`.
+//!
+//! [`spice_netlist`] turns a netlist into a SPICE deck for ngspice, and
+//! [`Schematic::check_sim_models`] reports the symbols it could not
+//! simulate. Like KiCad, resistors, capacitors and inductors without
+//! `Sim.*` fields are simulated from their value.
+//!
+//! ```no_run
+//! use kicad_common::netlist::Netlist;
+//! use kicad_sch::{sim::spice_netlist, Schematic};
+//!
+//! let schematic = Schematic::parse(&std::fs::read_to_string("filter.kicad_sch")?)?;
+//! for issue in schematic.check_sim_models() {
+//!     eprintln!("{}", issue.message);
+//! }
+//! let netlist = Netlist::parse(&std::fs::read_to_string("filter.net")?)?;
+//! std::fs::write("filter.cir", spice_netlist(&netlist, &schematic.sim_directives())?)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt::Write;
+
+use kicad_common::{
+    netlist::{Component, Netlist},
+    ConvertError, Error,
+};
+use kicad_sexp::schema::Issue;
+
+use crate::{Schematic, SymbolInstance};
+
+/// The simulation model of one symbol.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimModel {
+    /// `Sim.Device`, such as `R`, `V`, `D`, `NPN`, `NMOS`, `SUBCKT` or
+    /// `SPICE` for a raw SPICE line.
+    pub device: String,
+    /// `Sim.Type`, such as `SIN` for a voltage source.
+    pub kind: Option<String>,
+    /// `Sim.Name`: the `.model` or `.subckt` the symbol uses.
+    pub name: Option<String>,
+    /// `Sim.Library`: the file that defines the model.
+    pub library: Option<String>,
+    /// `Sim.Pins`: symbol pin numbers and the model pins they are.
+    pub pins: Vec<(String, String)>,
+    /// `Sim.Params`, in the order written.
+    pub params: Vec<(String, String)>,
+}
+
+/// A SPICE directive from a text item on the sheet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Directive {
+    /// `.model name kind(params)`.
+    Model { name: String, kind: String, params: String },
+    /// `.include "path"`.
+    Include(String),
+    /// `.lib "path" section`.
+    Lib { path: String, section: Option<String> },
+    /// `.subckt name ...` up to its `.ends`, as written.
+    Subckt { name: String, text: String },
+    /// Any other directive, such as `.tran 1u 1m`, as written.
+    Other(String),
+}
+
+impl SimModel {
+    /// The model of a placed symbol; `None` for symbols excluded from
+    /// simulation and for those that have none.
+    pub fn of_symbol(symbol: &SymbolInstance) -> Result<Option<Self>, Error> {
+        if symbol.exclude_from_sim || symbol.is_virtual() {
+            return Ok(None);
+        }
+        let reference = symbol.reference().unwrap_or_default();
+        let model = Self::from_fields(reference, symbol.value().unwrap_or_default(), |key| symbol.property(key));
+        model.map_err(|e| ConvertError::new(format!("symbol {}: {}", reference, e)).into())
+    }
+
+    /// The model of a netlist part, from the `Sim.*` fields KiCad exports.
+    pub fn of_component(component: &Component) -> Result<Option<Self>, Error> {
+        let field = |key: &str| component.fields.get(key).map(String::as_str);
+        let model = Self::from_fields(&component.reference, &component.value, field);
+        model.map_err(|e| ConvertError::new(format!("part {}: {}", component.reference, e)).into())
+    }
+
+    fn from_fields<'a>(
+        reference: &str,
+        value: &str,
+        field: impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<Option<Self>, ConvertError> {
+        if field("Sim.Enable") == Some("0") {
+            return Ok(None);
+        }
+        let Some(device) = field("Sim.Device") else {
+            // KiCad simulates passives from the value alone.
+            let prefix = reference.trim_end_matches(|c: char| c.is_ascii_digit());
+            return Ok(match prefix {
+                "R" | "C" | "L" if spice_value(value).is_some() => Some(SimModel {
+                    device: prefix.to_string(),
+                    ..Default::default()
+                }),
+                _ => None,
+            });
+        };
+        let text = |key: &str| field(key).filter(|text| !text.is_empty()).map(str::to_string);
+        Ok(Some(SimModel {
+            device: device.to_ascii_uppercase(),
+            kind: text("Sim.Type").map(|kind| kind.to_ascii_uppercase()),
+            name: text("Sim.Name"),
+            library: text("Sim.Library"),
+            pins: parse_pairs("Sim.Pins", field("Sim.Pins").unwrap_or_default())?,
+            params: parse_pairs("Sim.Params", field("Sim.Params").unwrap_or_default())?,
+        }))
+    }
+
+    pub fn param(&self, key: &str) -> Option<&str> {
+        let param = self.params.iter().find(|(name, _)| name.eq_ignore_ascii_case(key));
+        param.map(|(_, value)| value.as_str())
+    }
+
+    /// The SPICE element letter and the model pins in node order, for the
+    /// devices with a fixed pin order.
+    fn element(&self) -> Option<(char, &'static [&'static str])> {
+        Some(match self.device.as_str() {
+            "R" | "C" | "L" | "V" | "I" => (self.device.chars().next()?, &["+", "-"]),
+            "D" => ('D', &["A", "K"]),
+            "NPN" | "PNP" => ('Q', &["C", "B", "E"]),
+            "NJFET" | "PJFET" => ('J', &["D", "G", "S"]),
+            "NMOS" | "PMOS" => ('M', &["D", "G", "S", "B"]),
+            "SUBCKT" => ('X', &[]),
+            "SPICE" => (self.param("type").and_then(|kind| kind.chars().next()).unwrap_or('X'), &[]),
+            _ => return None,
+        })
+    }
+
+    /// The `.model` type of the semiconductor devices.
+    fn model_kind(&self) -> Option<&'static str> {
+        Some(match self.device.as_str() {
+            "D" => "D",
+            "NPN" => "NPN",
+            "PNP" => "PNP",
+            "NJFET" => "NJF",
+            "PJFET" => "PJF",
+            "NMOS" => "NMOS",
+            "PMOS" => "PMOS",
+            _ => return None,
+        })
+    }
+}
+
+/// Splits `1=+ 2=-` or `dc=5 ac="1 0"` into its pairs.
+fn parse_pairs(field: &str, text: &str) -> Result<Vec<(String, String)>, ConvertError> {
+    let mut pairs = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=').ok_or_else(|| {
+            let token = rest.split_whitespace().next().unwrap_or_default();
+            ConvertError::new(format!("{}: expected key=value, found `{}`", field, token))
+        })?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').ok_or_else(|| ConvertError::new(format!("{}: unclosed quote", field)))?;
+                (&quoted[..end], &quoted[end + 1..])
+            },
+            None => after.split_at(after.find(char::is_whitespace).unwrap_or(after.len())),
+        };
+        if key.trim().is_empty() || key.contains(char::is_whitespace) {
+            return Err(ConvertError::new(format!("{}: expected key=value, found `{}`", field, key.trim())));
+        }
+        pairs.push((key.to_string(), value.to_string()));
+        rest = after.trim_start();
+    }
+    Ok(pairs)
+}
+
+/// A value like `4k7` or `100n` as SPICE reads it, `4.7k`; `None` if it
+/// is not a number.
+fn spice_value(value: &str) -> Option<String> {
+    let value = value.trim().replace('µ', "u");
+    let digits = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    if digits == 0 {
+        return None;
+    }
+    let (number, suffix) = value.split_at(digits);
+    let mut chars = suffix.chars();
+    let multiplier = chars.next();
+    let fraction = chars.as_str();
+    // `4k7` writes the decimal point as the multiplier, `4R7` as an R.
+    match multiplier {
+        Some(m) if "pnumkMGTRr".contains(m) && !fraction.is_empty() && fraction.bytes().all(|b| b.is_ascii_digit()) => {
+            let multiplier = if m == 'R' || m == 'r' { String::new() } else { m.to_string() };
+            Some(format!("{}.{}{}", number, fraction, multiplier))
+        },
+        Some('R' | 'r') if fraction.is_empty() => Some(number.to_string()),
+        _ => Some(value.to_string()),
+    }
+}
+
+impl Schematic {
+    /// The SPICE directives in the sheet's text items, in the order they
+    /// appear; texts excluded from simulation and lines not starting with
+    /// a `.` are left out, and lines starting with `+` continue the line
+    /// before.
+    pub fn sim_directives(&self) -> Vec<Directive> {
+        let mut lines: Vec<String> = Vec::new();
+        for text in self.texts.iter().filter(|text| !text.exclude_from_sim) {
+            let (mut in_directive, mut in_subckt) = (false, false);
+            for line in text.text.lines().map(str::trim) {
+                let command = line.to_ascii_lowercase();
+                match line.strip_prefix('+') {
+                    Some(rest) if in_directive => {
+                        let last = lines.last_mut().expect("a directive is open");
+                        last.push(' ');
+                        last.push_str(rest.trim());
+                    },
+                    // The body of a subcircuit is kept whole.
+                    _ if in_subckt => {
+                        in_subckt = !command.starts_with(".ends");
+                        lines.push(line.to_string());
+                    },
+                    _ => {
+                        in_directive = line.starts_with('.');
+                        in_subckt = command.starts_with(".subckt");
+                        if in_directive {
+                            lines.push(line.to_string());
+                        }
+                    },
+                }
+            }
+        }
+        let mut directives = Vec::new();
+        let mut lines = lines.into_iter();
+        while let Some(line) = lines.next() {
+            let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((&line, ""));
+            let unquote = |text: &str| text.trim().trim_matches('"').to_string();
+            let directive = match command.to_ascii_lowercase().as_str() {
+                ".model" => match rest.trim().split_once(char::is_whitespace) {
+                    Some((name, model)) => {
+                        let model = model.trim();
+                        let end = model.find(|c: char| c == '(' || c.is_whitespace()).unwrap_or(model.len());
+                        let params = model[end..].trim().trim_start_matches('(').trim_end_matches(')');
+                        let (name, kind) = (name.to_string(), model[..end].to_string());
+                        Directive::Model { name, kind, params: params.trim().to_string() }
+                    },
+                    None => Directive::Other(line.clone()),
+                },
+                ".include" | ".inc" => Directive::Include(unquote(rest)),
+                ".lib" => {
+                    let rest = rest.trim();
+                    let (path, section) = match rest.strip_prefix('"').and_then(|quoted| quoted.split_once('"')) {
+                        Some((path, section)) => (path.to_string(), section.trim()),
+                        None => {
+                            let (path, section) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                            (path.to_string(), section.trim())
+                        },
+                    };
+                    let section = (!section.is_empty()).then(|| section.to_string());
+                    Directive::Lib { path, section }
+                },
+                ".subckt" => {
+                    let name = rest.split_whitespace().next().unwrap_or_default().to_string();
+                    let mut text = line.clone();
+                    for line in lines.by_ref() {
+                        text.push('\n');
+                        text.push_str(&line);
+                        if line.to_ascii_lowercase().starts_with(".ends") {
+                            break;
+                        }
+                    }
+                    Directive::Subckt { name, text }
+                },
+                _ => Directive::Other(line.clone()),
+            };
+            directives.push(directive);
+        }
+        directives
+    }
+
+    /// Reports the symbols that cannot be simulated: those without a model
+    /// that are not excluded from simulation, those with malformed `Sim.*`
+    /// fields or pins their library symbol lacks, and those using a model
+    /// that neither the sheet's directives nor a library define.
+    pub fn check_sim_models(&self) -> Vec<Issue> {
+        let directives = self.sim_directives();
+        let libraries = directives.iter().any(|d| matches!(d, Directive::Include(_) | Directive::Lib { .. }));
+        let defined = |name: &str| {
+            directives.iter().any(|directive| match directive {
+                Directive::Model { name: model, .. } | Directive::Subckt { name: model, .. } => {
+                    model.eq_ignore_ascii_case(name)
+                },
+                _ => false,
+            })
+        };
+        let mut issues = Vec::new();
+        let mut issue = |message: String| {
+            issues.push(Issue { path: vec!["kicad_sch".into(), "symbol".into()], message });
+        };
+        for symbol in &self.symbols {
+            let reference = symbol.reference().unwrap_or_default();
+            let model = match SimModel::of_symbol(symbol) {
+                Ok(Some(model)) => model,
+                Ok(None) => {
+                    if !symbol.exclude_from_sim && !symbol.is_virtual() && symbol.property("Sim.Enable") != Some("0") {
+                        issue(format!("symbol {} has no simulation model", reference));
+                    }
+                    continue;
+                },
+                Err(e) => {
+                    issue(e.to_string());
+                    continue;
+                },
+            };
+            if model.element().is_none() {
+                issue(format!("symbol {} has the unknown simulation device {}", reference, model.device));
+            }
+            if let Some(lib) = self.lib_symbol(symbol.lib_name.as_deref().unwrap_or(&symbol.lib_id)) {
+                for (pin, _) in &model.pins {
+                    if !lib.pins.iter().any(|lib_pin| &lib_pin.number == pin) {
+                        issue(format!("symbol {} maps the pin {} it does not have", reference, pin));
+                    }
+                }
+            }
+            if let Some(name) = &model.name
+                && model.library.is_none()
+                && !libraries
+                && !defined(name)
+            {
+                issue(format!("symbol {} uses the model {}, which nothing defines", reference, name));
+            }
+        }
+        issues
+    }
+}
+
+/// Writes `netlist` as a SPICE deck: a title, the `.include`s of the
+/// models' libraries, `directives` and one element line per part with a
+/// model. Parts without one are left out, as
+/// [`Schematic::check_sim_models`] reports. The `GND` net is node 0.
+pub fn spice_netlist(netlist: &Netlist, directives: &[Directive]) -> Result<String, Error> {
+    let mut deck = String::new();
+    let title = netlist.source.as_deref().unwrap_or("KiCad schematic");
+    let _ = writeln!(deck, ".title {}", title);
+    let mut models = Vec::new();
+    for component in &netlist.components {
+        if let Some(model) = SimModel::of_component(component)? {
+            models.push((component, model));
+        }
+    }
+    let mut includes: Vec<&str> = Vec::new();
+    for (_, model) in &models {
+        let library = model.library.as_deref().or(model.param("lib").filter(|_| model.device == "SPICE"));
+        if let Some(library) = library
+            && !includes.contains(&library)
+        {
+            includes.push(library);
+        }
+    }
+    for library in includes {
+        let _ = writeln!(deck, ".include \"{}\"", library);
+    }
+    let mut commands = Vec::new();
+    for directive in directives {
+        match directive {
+            Directive::Model { name, kind, params } => {
+                let _ = writeln!(deck, ".model {} {}({})", name, kind, params);
+            },
+            Directive::Include(path) => {
+                let _ = writeln!(deck, ".include \"{}\"", path);
+            },
+            Directive::Lib { path, section } => {
+                let section = section.as_deref().map(|section| format!(" {}", section)).unwrap_or_default();
+                let _ = writeln!(deck, ".lib \"{}\"{}", path, section);
+            },
+            Directive::Subckt { text, .. } => {
+                let _ = writeln!(deck, "{}", text);
+            },
+            // Analyses and options go after the circuit.
+            Directive::Other(text) => commands.push(text),
+        }
+    }
+    for (component, model) in &models {
+        let _ = writeln!(deck, "{}", element(netlist, component, model)?);
+    }
+    for command in commands {
+        let _ = writeln!(deck, "{}", command);
+    }
+    deck.push_str(".end\n");
+    Ok(deck)
+}
+
+/// The element line of one part, preceded by the `.model` of parameters
+/// given on the symbol itself.
+fn element(netlist: &Netlist, component: &Component, model: &SimModel) -> Result<String, Error> {
+    let reference = &component.reference;
+    let error = |message: String| -> Error { ConvertError::new(message).into() };
+    let (letter, order) = model.element().ok_or_else(|| {
+        error(format!("part {} has the unknown simulation device {}", reference, model.device))
+    })?;
+    // KiCad prefixes references that do not start with the element letter.
+    let mut name = reference.clone();
+    if !name.to_ascii_uppercase().starts_with(letter.to_ascii_uppercase()) {
+        name.insert(0, letter);
+    }
+
+    let node = |pin: &str| -> Result<String, Error> {
+        let net = netlist.net_of(reference, pin);
+        let net = net.ok_or_else(|| error(format!("pin {}.{} is on no net", reference, pin)))?;
+        Ok(match net.name.as_str() {
+            "GND" | "/GND" | "0" => "0".to_string(),
+            name => name.replace(char::is_whitespace, "_"),
+        })
+    };
+    let mut symbol_pins: Vec<&str> = (netlist.nets.iter().flat_map(|net| &net.nodes))
+        .filter(|node| &node.reference == reference)
+        .map(|node| node.pin.as_str())
+        .collect();
+    symbol_pins.sort_by_key(|pin| (pin.parse::<u64>().unwrap_or(u64::MAX), pin.to_string()));
+    let pins: Vec<&str> = match (model.pins.is_empty(), order.is_empty()) {
+        (true, _) => symbol_pins,
+        (false, true) => model.pins.iter().map(|(pin, _)| pin.as_str()).collect(),
+        (false, false) => {
+            let mut pins = Vec::new();
+            for model_pin in order {
+                let found = model.pins.iter().find(|(_, name)| name.eq_ignore_ascii_case(model_pin));
+                match found {
+                    Some((pin, _)) => pins.push(pin.as_str()),
+                    // A MOSFET without a bulk pin has it tied to the source.
+                    None if *model_pin == "B" => pins.push(pins[2]),
+                    None => return Err(error(format!("part {} maps no pin to {}", reference, model_pin))),
+                }
+            }
+            pins
+        },
+    };
+    let mut nodes = Vec::new();
+    for pin in pins {
+        nodes.push(node(pin)?);
+    }
+
+    let mut line = String::new();
+    let value = |key: &str| {
+        let value = model.param(key).map(str::to_string).or_else(|| spice_value(&component.value));
+        value.ok_or_else(|| error(format!("part {} has no {} value", reference, key)))
+    };
+    let params = |skip: &[&str]| -> String {
+        (model.params.iter().filter(|(key, _)| !skip.iter().any(|skip| key.eq_ignore_ascii_case(skip))))
+            .map(|(key, value)| format!(" {}={}", key, value))
+            .collect()
+    };
+    let _ = write!(line, "{} {}", name, nodes.join(" "));
+    match model.device.as_str() {
+        device @ ("R" | "C" | "L") => {
+            let _ = write!(line, " {}", value(&device.to_ascii_lowercase())?);
+        },
+        "V" | "I" => {
+            let kind = model.kind.as_deref().unwrap_or("DC");
+            let _ = match source_params(kind) {
+                Some(_) if kind == "DC" => write!(line, " DC {}", value("dc")?),
+                Some(keys) => {
+                    let values: Vec<&str> = keys.iter().map_while(|key| model.param(key)).collect();
+                    write!(line, " {}({})", kind, values.join(" "))
+                },
+                None => return Err(error(format!("part {} has the unknown source type {}", reference, kind))),
+            };
+            if let Some(ac) = model.param("ac") {
+                let _ = write!(line, " AC {}", ac);
+            }
+        },
+        "SUBCKT" => {
+            let subckt = model.name.as_deref().unwrap_or(&component.value);
+            let _ = write!(line, " {}{}", subckt, params(&[]));
+        },
+        "SPICE" => {
+            let _ = write!(line, " {}", model.param("model").unwrap_or(&component.value));
+        },
+        _ => match &model.name {
+            Some(name) => {
+                let _ = write!(line, " {}", name);
+            },
+            None if !model.params.is_empty() => {
+                let kind = model.model_kind().expect("semiconductor devices have a model type");
+                let inline = format!("__{}", name);
+                line = format!(".model {} {}({})\n{} {}", inline, kind, params(&[]).trim(), line, inline);
+            },
+            None => {
+                let _ = write!(line, " {}", component.value);
+            },
+        },
+    }
+    Ok(line)
+}
+
+/// The parameters of a source `Sim.Type`, in SPICE's order.
+fn source_params(kind: &str) -> Option<&'static [&'static str]> {
+    Some(match kind {
+        "DC" => &["dc"],
+        "SIN" => &["dc", "ampl", "f", "td", "theta", "phase"],
+        "PULSE" => &["y1", "y2", "td", "tr", "tf", "tw", "per", "np"],
+        "EXP" => &["y1", "y2", "td1", "tau1", "td2", "tau2"],
+        "AM" => &["sa", "oc", "fm", "fc", "td"],
+        "SFFM" => &["vo", "va", "fm", "mdi", "fc"],
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::{netlist::NetNode, Position};
+
+    use super::*;
+    use crate::Text;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_sch");
+    const NETLIST: &str = include_str!("../../reference-files/demo/demo.net");
+
+    #[test]
+    fn models() {
+        let mut schematic = Schematic::parse(DEMO).unwrap();
+        // The passives are simulated from their values; the connector has
+        // no model yet.
+        let issues: Vec<_> = schematic.check_sim_models().into_iter().map(|issue| issue.message).collect();
+        assert_eq!(issues, ["symbol J1 has no simulation model"]);
+        let r2 = SimModel::of_symbol(schematic.symbol("R2").unwrap()).unwrap().unwrap();
+        assert_eq!((r2.device.as_str(), r2.pins.len()), ("R", 0));
+
+        let j1 = schematic.symbols.iter_mut().find(|symbol| symbol.reference() == Some("J1")).unwrap();
+        j1.set_property("Sim.Device", "V");
+        j1.set_property("Sim.Type", "sin");
+        j1.set_property("Sim.Pins", "1=+ 3=- 4=x");
+        j1.set_property("Sim.Params", "dc=0 ampl=5 f=1k ac=1");
+        let r1 = schematic.symbols.iter_mut().find(|symbol| symbol.reference() == Some("R1")).unwrap();
+        r1.set_property("Sim.Device", "SUBCKT");
+        r1.set_property("Sim.Name", "RMODEL");
+        let issues: Vec<_> = schematic.check_sim_models().into_iter().map(|issue| issue.message).collect();
+        let undefined = "symbol R1 uses the model RMODEL, which nothing defines";
+        assert_eq!(issues, [undefined, "symbol J1 maps the pin 4 it does not have"]);
+
+        let text = ".subckt RMODEL a b\nR1 a b 10k\n.ends\n.tran 10u 2m\n+ 0 1u";
+        let at = Position::default();
+        schematic.texts.push(Text { text: text.into(), at, ..Default::default() });
+        let models = ".model D1N4148 D(is=2.52n n=1.752)\n.lib \"diodes.lib\" fast\nnot a directive";
+        schematic.texts.push(Text { text: models.into(), at, ..Default::default() });
+        let hidden = ".include \"hidden.lib\"";
+        let excluded = Text { text: hidden.into(), at, exclude_from_sim: true, ..Default::default() };
+        schematic.texts.push(excluded);
+        let directives = schematic.sim_directives();
+        assert_eq!(directives, [
+            Directive::Subckt { name: "RMODEL".into(), text: ".subckt RMODEL a b\nR1 a b 10k\n.ends".into() },
+            Directive::Other(".tran 10u 2m 0 1u".into()),
+            Directive::Model { name: "D1N4148".into(), kind: "D".into(), params: "is=2.52n n=1.752".into() },
+            Directive::Lib { path: "diodes.lib".into(), section: Some("fast".into()) },
+        ]);
+        let issues: Vec<_> = schematic.check_sim_models().into_iter().map(|issue| issue.message).collect();
+        assert_eq!(issues, ["symbol J1 maps the pin 4 it does not have"]);
+
+        let mut netlist = Netlist::parse(NETLIST).unwrap();
+        for component in &mut netlist.components {
+            if let Some(symbol) = schematic.symbol(&component.reference) {
+                let sim = symbol.properties.iter().filter(|property| property.key.starts_with("Sim."));
+                component.fields.extend(sim.map(|property| (property.key.clone(), property.value.clone())));
+            }
+        }
+        // A diode with its parameters on the symbol.
+        netlist.components.push(Component { reference: "D1".into(), value: "1N4148".into(), ..Default::default() });
+        let diode = &mut netlist.components.last_mut().unwrap().fields;
+        diode.insert("Sim.Device".into(), "D".into());
+        diode.insert("Sim.Pins".into(), "1=K 2=A".into());
+        diode.insert("Sim.Params".into(), "is=1e-14 n=\"1.5\"".into());
+        let node = |pin: &str| NetNode { reference: "D1".into(), pin: pin.into(), ..Default::default() };
+        netlist.nets[1].nodes.push(node("1"));
+        netlist.nets[2].nodes.push(node("2"));
+        let deck = spice_netlist(&netlist, &directives).unwrap();
+        let expected = [
+            ".title demo.kicad_sch",
+            ".subckt RMODEL a b\nR1 a b 10k\n.ends",
+            ".model D1N4148 D(is=2.52n n=1.752)",
+            ".lib \"diodes.lib\" fast",
+            "VJ1 VIN 0 SIN(0 5 1k) AC 1",
+            "XR1 VIN VOUT RMODEL",
+            "R2 VOUT 0 4.7k",
+            ".model __D1 D(is=1e-14 n=1.5)\nD1 0 VOUT __D1",
+            ".tran 10u 2m 0 1u",
+            ".end\n",
+        ];
+        assert_eq!(deck, expected.join("\n"));
+
+        netlist.components[3].fields.insert("Sim.Pins".into(), "1=K".into());
+        assert_eq!(spice_netlist(&netlist, &[]).unwrap_err().to_string(), "part D1 maps no pin to A");
+        netlist.components[3].fields.insert("Sim.Params".into(), "is".into());
+        let error = spice_netlist(&netlist, &[]).unwrap_err();
+        assert_eq!(error.to_string(), "part D1: Sim.Params: expected key=value, found `is`");
+    }
+
+    #[test]
+    fn values() {
+        let values = ["4k7", "10k", "100n", "4R7", "10R", "2µ2", "1.5meg"].map(|value| spice_value(value).unwrap());
+        assert_eq!(values, ["4.7k", "10k", "100n", "4.7", "10", "2.2u", "1.5meg"]);
+        assert_eq!(spice_value("DNP"), None);
+        let pairs = parse_pairs("Sim.Params", " r=1k  tc=\"1 2\"").unwrap();
+        assert_eq!(pairs, [("r".to_string(), "1k".to_string()), ("tc".to_string(), "1 2".to_string())]);
+        assert_eq!(parse_pairs("Sim.Pins", "1=+ 2=\"-").unwrap_err().to_string(), "Sim.Pins: unclosed quote");
+    }
+}
@@ -0,0 +1,251 @@
+//! Distributor part numbers, lifecycle status and attributes of parts, kept
+//! in a JSON sidecar next to a symbol library rather than in the KiCad
+//! files, where they go stale with every price change and would show up as
+//! fields on every placed symbol.
+//!
+//! Parts are keyed by manufacturer part number, the `MPN` field of the
+//! symbols, and merged into a [`Bom`] as extra columns:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "parts": {
+//!     "RC0603FR-0710KL": {
+//!       "manufacturer": "Yageo",
+//!       "lifecycle": "active",
+//!       "distributors": { "Digi-Key": "311-10.0KHRCT-ND", "LCSC": "C98220" },
+//!       "attributes": { "Tolerance": "1%" }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! ```no_run
+//! use kicad_sch::{sourcing::Sourcing, Bom, BomOptions, Schematic};
+//!
+//! let schematic = Schematic::parse(&std::fs::read_to_string("demo.kicad_sch")?)?;
+//! let mut bom = Bom::from_schematic(&schematic, &BomOptions::default());
+//! let sourcing = Sourcing::load(&Sourcing::sidecar_path("Device.kicad_sym".as_ref()))?;
+//! for mpn in sourcing.merge_into(&mut bom, "MPN") {
+//!     eprintln!("no sourcing data for {}", mpn);
+//! }
+//! std::fs::write("bom.csv", bom.to_csv())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use kicad_common::{convert::check_version, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::Bom;
+
+/// Where a part is in its production life.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lifecycle {
+    Active,
+    /// Not recommended for new designs.
+    Nrnd,
+    LastTimeBuy,
+    Obsolete,
+}
+
+impl Lifecycle {
+    /// The status as a BOM shows it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lifecycle::Active => "Active",
+            Lifecycle::Nrnd => "NRND",
+            Lifecycle::LastTimeBuy => "Last time buy",
+            Lifecycle::Obsolete => "Obsolete",
+        }
+    }
+}
+
+/// The sourcing data of one part.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lifecycle: Option<Lifecycle>,
+    /// Order numbers by distributor, as in `"LCSC": "C98220"`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub distributors: BTreeMap<String, String>,
+    /// More columns for the BOM, such as `Tolerance`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// A parsed sourcing sidecar.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sourcing {
+    pub version: u32,
+    /// The parts by manufacturer part number.
+    pub parts: BTreeMap<String, PartInfo>,
+}
+
+impl Default for Sourcing {
+    fn default() -> Self {
+        Sourcing { version: Self::NEWEST_VERSION, parts: BTreeMap::new() }
+    }
+}
+
+impl Sourcing {
+    /// Newest sidecar format version.
+    pub const NEWEST_VERSION: u32 = 1;
+
+    /// The sidecar of the library at `path`: `Device.kicad_sym` keeps its
+    /// sourcing data in `Device.parts.json`.
+    pub fn sidecar_path(path: &Path) -> PathBuf {
+        path.with_extension("parts.json")
+    }
+
+    pub fn parse(src: &str) -> Result<Self, Error> {
+        serde_json::from_str(src).map_err(json_error)
+    }
+
+    /// Reads the sidecar at `path`; an empty one if there is no file yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(src) => Self::parse(&src).map_err(|e| e.in_file(path)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Sourcing::default()),
+            Err(e) => Err(Error::from(e).in_file(path)),
+        }
+    }
+
+    /// Fails with [`Error::Version`] for sidecars from a newer version.
+    pub fn check_version(&self) -> Result<(), Error> {
+        check_version("sourcing", self.version, Self::NEWEST_VERSION)
+    }
+
+    /// Adds the parts of another sidecar, such as that of another library;
+    /// its data wins for parts both have.
+    pub fn extend(&mut self, other: Sourcing) {
+        self.parts.extend(other.parts);
+    }
+
+    pub fn part(&self, mpn: &str) -> Option<&PartInfo> {
+        self.parts.get(mpn)
+    }
+
+    /// Fills in the rows of `bom` whose `key` field names a part: the
+    /// `Manufacturer` and `Lifecycle` columns, a column for each
+    /// distributor's order number and one for each attribute, added after
+    /// the existing columns. Sidecar data replaces what the symbols' fields
+    /// say. Returns the keys of the rows no part matched, sorted.
+    pub fn merge_into(&self, bom: &mut Bom, key: &str) -> Vec<String> {
+        let mut columns = BTreeSet::new();
+        let mut distributors = BTreeSet::new();
+        let mut attributes = BTreeSet::new();
+        let mut missing = BTreeSet::new();
+        for row in &mut bom.rows {
+            let mpn = row.field(key).to_string();
+            if mpn.is_empty() {
+                continue;
+            }
+            let Some(part) = self.parts.get(&mpn) else {
+                missing.insert(mpn);
+                continue;
+            };
+            if let Some(manufacturer) = &part.manufacturer {
+                row.fields.insert("Manufacturer".to_string(), manufacturer.clone());
+                columns.insert("Manufacturer");
+            }
+            if let Some(lifecycle) = part.lifecycle {
+                row.fields.insert("Lifecycle".to_string(), lifecycle.name().to_string());
+                columns.insert("Lifecycle");
+            }
+            for (distributor, number) in &part.distributors {
+                row.fields.insert(distributor.clone(), number.clone());
+                distributors.insert(distributor.as_str());
+            }
+            for (name, value) in &part.attributes {
+                row.fields.insert(name.clone(), value.clone());
+                attributes.insert(name.as_str());
+            }
+        }
+        // Manufacturer before Lifecycle, then distributors and attributes.
+        let added = columns.into_iter().rev().chain(distributors).chain(attributes);
+        for column in added {
+            if !bom.columns.iter().any(|existing| existing == column) {
+                bom.columns.push(column.to_string());
+            }
+        }
+        missing.into_iter().collect()
+    }
+}
+
+/// Writes the sidecar as indented JSON.
+impl fmt::Display for Sourcing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string_pretty(self).map_err(|_| fmt::Error)?;
+        writeln!(f, "{}", json)
+    }
+}
+
+fn json_error(e: serde_json::Error) -> Error {
+    let (line, column) = (e.line(), e.column());
+    let message = e.to_string();
+    let suffix = format!(" at line {} column {}", line, column);
+    let message = message.strip_suffix(&suffix).unwrap_or(&message).to_string();
+    Error::Json { message, line, column }
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_sexp::source::FsSource;
+
+    use super::*;
+    use crate::{BomOptions, Hierarchy};
+
+    const SIDECAR: &str = r#"{
+        "version": 1,
+        "parts": {
+            "RC0603FR-0710KL": {
+                "manufacturer": "Yageo",
+                "lifecycle": "active",
+                "distributors": { "LCSC": "C98220", "Digi-Key": "311-10.0KHRCT-ND" },
+                "attributes": { "Tolerance": "1%" }
+            },
+            "GRM188R71H104KA93D": { "lifecycle": "nrnd", "distributors": { "LCSC": "C14663" } }
+        }
+    }"#;
+
+    #[test]
+    fn merge() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+        let hierarchy = Hierarchy::load(&source, "demo/demo.kicad_sch").unwrap();
+        let mut bom = Bom::from_hierarchy(&hierarchy, &BomOptions::default());
+        let sourcing = Sourcing::parse(SIDECAR).unwrap();
+        sourcing.check_version().unwrap();
+        assert_eq!(sourcing.part("RC0603FR-0710KL").unwrap().manufacturer.as_deref(), Some("Yageo"));
+
+        // Only R1 has an MPN in the demo.
+        bom.rows[0].fields.insert("MPN".to_string(), "CL10B104KB8NNNC".to_string());
+        assert_eq!(sourcing.merge_into(&mut bom, "MPN"), ["CL10B104KB8NNNC"]);
+        assert_eq!(
+            bom.columns,
+            ["Value", "Footprint", "MPN", "Manufacturer", "Lifecycle", "Digi-Key", "LCSC", "Tolerance"]
+        );
+        let r1 = &bom.rows[2];
+        assert_eq!((r1.field("Lifecycle"), r1.field("LCSC"), r1.field("Tolerance")), ("Active", "C98220", "1%"));
+        assert!(bom.to_csv().lines().next().unwrap().ends_with(",Digi-Key,LCSC,Tolerance,DNP"));
+
+        let written = Sourcing::parse(&sourcing.to_string()).unwrap();
+        assert_eq!(written, sourcing);
+        assert!(!sourcing.to_string().contains("attributes\": {}"));
+        assert_eq!(Sourcing::sidecar_path("lib/Device.kicad_sym".as_ref()), Path::new("lib/Device.parts.json"));
+
+        let error = Sourcing::parse("{\"parts\": {\"X\": {\"lifecycle\": \"gone\"}}}").unwrap_err();
+        assert!(matches!(error, Error::Json { line: 1, .. }), "{}", error);
+        assert!(Sourcing::parse("{\"version\": 2}").unwrap().check_version().is_err());
+    }
+}
@@ -0,0 +1,462 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    property::property,
+    text::{Effects, Font},
+    uuid::item_uuid,
+    ConvertError, Error, Point, Position, Property, Uuid,
+};
+use kicad_sexp::{
+    cst::{Document, Node},
+    number::format_float,
+};
+
+/// A symbol definition cached in the schematic's `lib_symbols` section.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LibSymbol {
+    /// Library identifier, e.g. `Device:R`.
+    pub lib_id: String,
+    pub power: bool,
+    pub properties: Vec<Property>,
+    /// Pins of all units and body styles.
+    pub pins: Vec<LibPin>,
+}
+
+impl LibSymbol {
+    /// Pins of `unit` in body style `body_style`, including shared ones.
+    pub fn pins_of(&self, unit: u32, body_style: u32) -> impl Iterator<Item = &LibPin> {
+        let applies = |part: u32, of: u32| part == 0 || part == of;
+        self.pins.iter().filter(move |pin| applies(pin.unit, unit) && applies(pin.body_style, body_style))
+    }
+
+    /// The interchangeable units, the gates a board may swap: units whose
+    /// own pins sit at the same places, grouped, in groups of two or more.
+    /// Each unit comes with its pin numbers ordered by place, so the pins
+    /// at the same index of two units correspond.
+    pub fn gates(&self) -> Vec<Vec<(u32, Vec<String>)>> {
+        let mut units: Vec<(u32, Vec<&LibPin>)> = Vec::new();
+        for pin in self.pins.iter().filter(|pin| pin.unit > 0 && pin.body_style <= 1) {
+            match units.iter_mut().find(|(unit, _)| *unit == pin.unit) {
+                Some((_, pins)) => pins.push(pin),
+                None => units.push((pin.unit, vec![pin])),
+            }
+        }
+        units.sort_by_key(|(unit, _)| *unit);
+        let place = |pin: &LibPin| {
+            let round = |v: f64| (v * 1e4).round() as i64;
+            (round(pin.at.x), round(pin.at.y), round(pin.at.angle))
+        };
+        let mut groups: Vec<(Vec<_>, Vec<_>)> = Vec::new();
+        for (unit, mut pins) in units {
+            pins.sort_by_key(|pin| place(pin));
+            let layout: Vec<_> = pins.iter().map(|pin| place(pin)).collect();
+            let numbers: Vec<String> = pins.iter().map(|pin| pin.number.clone()).collect();
+            match groups.iter_mut().find(|(places, _)| *places == layout) {
+                Some((_, gates)) => gates.push((unit, numbers)),
+                None => groups.push((layout, vec![(unit, numbers)])),
+            }
+        }
+        groups.into_iter().map(|(_, gates)| gates).filter(|gates| gates.len() > 1).collect()
+    }
+}
+
+impl FromSexp for LibSymbol {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mut pins = Vec::new();
+        for part in list.children("symbol") {
+            let within = |e: ConvertError| e.within(list.tag());
+            let name = part.string(0).map_err(within)?;
+            let mut numbers = name.rsplitn(3, '_').map(str::parse::<u32>);
+            let (Some(Ok(body_style)), Some(Ok(unit))) = (numbers.next(), numbers.next()) else {
+                let message = format!("expected a unit name like `NAME_1_1`, found `{}`", name);
+                return Err(part.error(message).within(list.tag()));
+            };
+            for pin in part.children("pin") {
+                let within = |e: ConvertError| e.within(part.tag()).within(list.tag());
+                let number = match pin.child("number") {
+                    Some(number) => number.string(0).map_err(within)?,
+                    None => String::new(),
+                };
+                pins.push(LibPin { number, at: pin.parse_required("at").map_err(within)?, unit, body_style });
+            }
+        }
+        Ok(LibSymbol {
+            lib_id: list.string(0)?,
+            power: list.child("power").is_some(),
+            properties: list.parse_all("property")?,
+            pins,
+        })
+    }
+}
+
+/// A pin of a library symbol, where wires connect to it.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LibPin {
+    pub number: String,
+    /// Connection point in library coordinates, whose Y axis points up; the
+    /// angle points from there towards the body.
+    pub at: Position,
+    /// Unit from 1; 0 for pins of all units.
+    pub unit: u32,
+    /// Body style: 1 for the normal body, 2 for the De Morgan alternate; 0
+    /// for pins of both.
+    pub body_style: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mirror {
+    /// Mirrored around the X axis (flipped vertically).
+    X,
+    /// Mirrored around the Y axis (flipped horizontally).
+    Y,
+}
+
+/// A pin of a placed symbol, carrying its UUID and selected alternate.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolPin {
+    pub number: String,
+    pub uuid: Option<Uuid>,
+    pub alternate: Option<String>,
+}
+
+impl FromSexp for SymbolPin {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(SymbolPin {
+            number: list.string(0)?,
+            uuid: item_uuid(list)?,
+            alternate: list.child_string("alternate")?,
+        })
+    }
+}
+
+/// Reference and unit of a symbol at one sheet path of a project.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InstancePath {
+    /// Project name; empty for the root file's `symbol_instances`.
+    pub project: String,
+    /// Sheet path of UUIDs from the root sheet, e.g. `/<root>/<sheet>`. In
+    /// `symbol_instances` the path leaves out the root and ends in the
+    /// symbol's UUID.
+    pub path: String,
+    pub reference: String,
+    pub unit: u32,
+    /// Overrides of the `Value` and `Footprint` fields at this instance,
+    /// written by KiCad 6 and 7.
+    pub value: Option<String>,
+    pub footprint: Option<String>,
+}
+
+fn instance_path(project: String, path: &List) -> Result<InstancePath> {
+    Ok(InstancePath {
+        project,
+        path: path.string(0)?,
+        reference: path.child_string("reference")?.unwrap_or_default(),
+        unit: path.child_number("unit")?.unwrap_or(1),
+        value: path.child_string("value")?,
+        footprint: path.child_string("footprint")?,
+    })
+}
+
+/// Reads a KiCad 6 root `(symbol_instances (path "/<sheet>/<symbol>" ...))`
+/// section.
+pub(crate) fn symbol_instances(list: &List) -> Result<Vec<InstancePath>> {
+    list.children("path")
+        .map(|path| instance_path(String::new(), &path).map_err(|e| e.within(list.tag())))
+        .collect()
+}
+
+pub(crate) fn project_instances<T>(
+    list: &List,
+    convert: impl Fn(String, &List) -> Result<T>,
+) -> Result<Vec<T>> {
+    let Some(instances) = list.child("instances") else {
+        return Ok(Vec::new());
+    };
+    let mut out = Vec::new();
+    for project in instances.children("project") {
+        let name = project.string(0).map_err(|e| e.within(instances.tag()))?;
+        for path in project.children("path") {
+            out.push(
+                convert(name.clone(), &path).map_err(|e| e.within(project.tag()).within(instances.tag()))?,
+            );
+        }
+    }
+    Ok(out)
+}
+
+/// A symbol placed on a schematic sheet.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolInstance {
+    /// Library identifier, e.g. `Device:R`.
+    pub lib_id: String,
+    /// Name of the `lib_symbols` entry if it differs from `lib_id`.
+    pub lib_name: Option<String>,
+    pub at: Position,
+    pub mirror: Option<Mirror>,
+    pub unit: u32,
+    /// De Morgan body style, 1 for the normal body.
+    pub body_style: u32,
+    pub exclude_from_sim: bool,
+    pub in_bom: bool,
+    pub on_board: bool,
+    pub dnp: bool,
+    pub fields_autoplaced: bool,
+    pub uuid: Uuid,
+    pub properties: Vec<Property>,
+    pub pins: Vec<SymbolPin>,
+    pub instances: Vec<InstancePath>,
+}
+
+impl SymbolInstance {
+    pub fn property(&self, key: &str) -> Option<&str> {
+        property(&self.properties, key)
+    }
+
+    /// The `Reference` field. Multi-instance sheets override it through
+    /// [`SymbolInstance::instances`].
+    pub fn reference(&self) -> Option<&str> {
+        self.property("Reference")
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.property("Value")
+    }
+
+    pub fn footprint(&self) -> Option<&str> {
+        self.property("Footprint").filter(|footprint| !footprint.is_empty())
+    }
+
+    /// Sets the value of the field `key`, adding a hidden field at the
+    /// symbol's position if it has none.
+    pub fn set_property(&mut self, key: &str, value: &str) {
+        match self.properties.iter_mut().find(|property| property.key == key) {
+            Some(property) => property.value = value.to_string(),
+            None => {
+                let font = Font { height: 1.27, width: 1.27, ..Default::default() };
+                self.properties.push(Property {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    at: Some(self.at),
+                    effects: Some(Effects { font, hide: true, ..Default::default() }),
+                    hide: true,
+                    ..Default::default()
+                });
+            },
+        }
+    }
+
+    /// Where `pin` of this symbol's library symbol connects on the sheet,
+    /// with the symbol's rotation and mirror applied.
+    pub fn pin_position(&self, pin: &LibPin) -> Point {
+        self.to_sheet(Point::new(pin.at.x, pin.at.y))
+    }
+
+    /// Where the point `at` of this symbol's library symbol lies on the
+    /// sheet.
+    pub(crate) fn to_sheet(&self, at: Point) -> Point {
+        // Library Y points up; the sheet's points down.
+        let (x, y) = (at.x, -at.y);
+        let (sin, cos) = self.at.angle.to_radians().sin_cos();
+        let (x, y) = (x * cos + y * sin, y * cos - x * sin);
+        let (x, y) = match self.mirror {
+            Some(Mirror::X) => (x, -y),
+            Some(Mirror::Y) => (-x, y),
+            None => (x, y),
+        };
+        // Away from the cardinal angles' rounding noise.
+        let round = |v: f64| (v * 1e6).round() / 1e6;
+        Point::new(round(self.at.x + x), round(self.at.y + y))
+    }
+
+    /// The pins of this symbol's unit and body style in `lib`, its library
+    /// symbol, with where they connect on the sheet.
+    pub fn placed_pins<'l>(&self, lib: &'l LibSymbol) -> impl Iterator<Item = (&'l LibPin, Point)> {
+        lib.pins_of(self.unit, self.body_style).map(|pin| (pin, self.pin_position(pin)))
+    }
+
+    /// Power symbols and other virtual parts use references starting with `#`.
+    pub fn is_virtual(&self) -> bool {
+        self.reference().is_some_and(|reference| reference.starts_with('#'))
+    }
+
+    /// Sets the reference and unit at the sheet instance `path` of
+    /// `project`, adding the instance if there is none yet.
+    pub fn set_instance(&mut self, project: &str, path: &str, reference: &str, unit: u32) {
+        let existing = self.instances.iter_mut().find(|instance| instance.project == project && instance.path == path);
+        match existing {
+            Some(instance) => {
+                instance.reference = reference.to_string();
+                instance.unit = unit;
+            },
+            None => self.instances.push(InstancePath {
+                project: project.to_string(),
+                path: path.to_string(),
+                reference: reference.to_string(),
+                unit,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// This symbol's node in the schematic `doc`, found by UUID.
+    fn node<'d, 'a>(&self, doc: &'d mut Document<'a>) -> std::result::Result<&'d mut Node<'a>, Error> {
+        let root = doc.nodes.iter_mut().find(|node| node.tag() == Some("kicad_sch"));
+        let items = root.and_then(|root| root.items_mut());
+        let items = items.ok_or_else(|| ConvertError::new("expected a (kicad_sch ...) root"))?;
+        let uuid = |node: &Node| node.child("uuid")?.items().get(1)?.value()?.parse::<Uuid>().ok();
+        let symbol = items.iter_mut().find(|item| item.tag() == Some("symbol") && uuid(item) == Some(self.uuid));
+        Ok(symbol.ok_or_else(|| ConvertError::new(format!("no symbol {} in the schematic", self.uuid)))?)
+    }
+
+    /// Adds the fields of this symbol that its node in the schematic `doc`
+    /// lacks after its last field, laid out as KiCad writes them. Fields
+    /// the node has are left as they are.
+    pub fn write_fields(&self, doc: &mut Document<'_>) -> std::result::Result<(), Error> {
+        let symbol = self.node(doc)?;
+        let indent = match &symbol.leading {
+            leading if leading.contains('\n') => format!("{}\t", leading),
+            _ => "\n\t\t".to_string(),
+        };
+        let children = symbol.items_mut().expect("symbol is a list");
+        let key = |node: &Node| node.items().get(1).and_then(|key| key.value()).map(|key| key.into_owned());
+        let existing: Vec<String> =
+            children.iter().filter(|child| child.tag() == Some("property")).filter_map(key).collect();
+        let last = children.iter().rposition(|child| child.tag() == Some("property"));
+        let indent = last.map_or(indent, |i| children[i].leading.to_string());
+        let at = last.map_or(children.len(), |i| i + 1);
+        let fields = self.properties.iter().filter(|property| !existing.contains(&property.key));
+        let nodes: Vec<Node<'static>> = fields.map(|property| property_node(property, &indent)).collect();
+        children.splice(at..at, nodes);
+        Ok(())
+    }
+
+    /// Sets the `(unit ...)` of this symbol, found by UUID in the schematic
+    /// `doc`, to [`SymbolInstance::unit`].
+    pub fn write_unit(&self, doc: &mut Document<'_>) -> std::result::Result<(), Error> {
+        let symbol = self.node(doc)?;
+        let children = symbol.items_mut().expect("symbol is a list");
+        let unit = Node::tagged("unit", vec![Node::atom(self.unit.to_string())]);
+        match children.iter().position(|child| child.tag() == Some("unit")) {
+            Some(i) => {
+                let leading = children[i].leading.clone();
+                children[i] = unit;
+                children[i].leading = leading;
+            },
+            None => {
+                // KiCad writes the unit after the position and the mirror.
+                let placed = children.iter().rposition(|child| matches!(child.tag(), Some("at" | "mirror")));
+                let at = placed.map_or(children.len(), |i| i + 1);
+                let mut unit = unit;
+                unit.leading = placed.map_or_else(|| " ".into(), |i| children[i].leading.clone());
+                children.insert(at, unit);
+            },
+        }
+        Ok(())
+    }
+
+    /// Replaces the `instances` section of this symbol, found by UUID in the
+    /// schematic `doc`, with [`SymbolInstance::instances`], laid out as
+    /// KiCad writes it.
+    pub fn write_instances(&self, doc: &mut Document<'_>) -> std::result::Result<(), Error> {
+        let symbol = self.node(doc)?;
+        let indent = match &symbol.leading {
+            leading if leading.contains('\n') => format!("{}\t", leading),
+            _ => "\n\t\t".to_string(),
+        };
+        let children = symbol.items_mut().expect("symbol is a list");
+        let existing = children.iter().position(|child| child.tag() == Some("instances"));
+        let indent = existing.map_or(indent, |i| children[i].leading.to_string());
+        let node = (!self.instances.is_empty()).then(|| instances_node(&self.instances, &indent));
+        match (existing, node) {
+            (Some(i), Some(node)) => children[i] = node,
+            (Some(i), None) => {
+                children.remove(i);
+            },
+            (None, Some(node)) => children.push(node),
+            (None, None) => {},
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn property_node(property: &Property, indent: &str) -> Node<'static> {
+    let inner = format!("{}\t", indent);
+    let number = |value: f64| Node::atom(format_float(value));
+    let mut children = Vec::new();
+    if let Some(at) = property.at {
+        children.push(Node::tagged("at", vec![number(at.x), number(at.y), number(at.angle)]));
+    }
+    let effects = property.effects.clone().unwrap_or_default();
+    let (height, width) = (effects.font.height, effects.font.width);
+    let size = Node::tagged("size", vec![number(height), number(width)]);
+    let mut effect_children = vec![Node::block("font", Vec::new(), vec![size], &format!("{}\t", inner))];
+    if property.hide || effects.hide {
+        effect_children.push(Node::tagged("hide", vec![Node::atom("yes")]));
+    }
+    children.push(Node::block("effects", Vec::new(), effect_children, &inner));
+    let args = vec![Node::string(&property.key), Node::string(&property.value)];
+    Node::block("property", args, children, indent)
+}
+
+pub(crate) fn instances_node(instances: &[InstancePath], indent: &str) -> Node<'static> {
+    let (project_indent, path_indent) = (format!("{}\t", indent), format!("{}\t\t", indent));
+    let mut projects: Vec<(&str, Vec<Node<'static>>)> = Vec::new();
+    for instance in instances {
+        let mut fields = vec![
+            Node::tagged("reference", vec![Node::string(&instance.reference)]),
+            Node::tagged("unit", vec![Node::atom(instance.unit.to_string())]),
+        ];
+        fields.extend(instance.value.as_deref().map(|value| Node::tagged("value", vec![Node::string(value)])));
+        let footprint = instance.footprint.as_deref();
+        fields.extend(footprint.map(|footprint| Node::tagged("footprint", vec![Node::string(footprint)])));
+        let path = Node::block("path", vec![Node::string(&instance.path)], fields, &path_indent);
+        match projects.iter_mut().find(|(project, _)| *project == instance.project) {
+            Some((_, paths)) => paths.push(path),
+            None => projects.push((&instance.project, vec![path])),
+        }
+    }
+    let projects = projects
+        .into_iter()
+        .map(|(project, paths)| Node::block("project", vec![Node::string(project)], paths, &project_indent))
+        .collect();
+    Node::block("instances", Vec::new(), projects, indent)
+}
+
+impl FromSexp for SymbolInstance {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mirror = match list.child("mirror") {
+            Some(mirror) => match mirror.symbol(0).map_err(|e| e.within(list.tag()))? {
+                "x" => Some(Mirror::X),
+                "y" => Some(Mirror::Y),
+                other => return Err(mirror.error(format!("unknown mirror axis `{}`", other)).within(list.tag())),
+            },
+            None => None,
+        };
+        let body_style = match list.child_number("body_style")? {
+            Some(body_style) => body_style,
+            None => list.child_number("convert")?.unwrap_or(1),
+        };
+        Ok(SymbolInstance {
+            lib_id: list.child_string("lib_id")?.ok_or_else(|| list.error("missing (lib_id ...)"))?,
+            lib_name: list.child_string("lib_name")?,
+            at: list.parse_required("at")?,
+            mirror,
+            unit: list.child_number("unit")?.unwrap_or(1),
+            body_style,
+            exclude_from_sim: list.flag("exclude_from_sim")?,
+            in_bom: list.opt_flag("in_bom")?.unwrap_or(true),
+            on_board: list.opt_flag("on_board")?.unwrap_or(true),
+            dnp: list.flag("dnp")?,
+            fields_autoplaced: list.flag("fields_autoplaced")?,
+            uuid: item_uuid(list)?.ok_or_else(|| list.error("missing (uuid ...)"))?,
+            properties: list.parse_all("property")?,
+            pins: list.parse_all("pin")?,
+            instances: project_instances(list, instance_path)?,
+        })
+    }
+}
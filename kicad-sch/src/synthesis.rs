@@ -0,0 +1,261 @@
+//! Schematics drawn from a netlist, the inverse of netlist export, for
+//! flows that start from an HDL or a script and still want a sheet to
+//! review: each part is placed from its symbol library, every pin gets a
+//! label of its net, power nets get power symbols and unconnected pins a
+//! no-connect flag. There are no wires; KiCad connects the labels to the
+//! pins they sit on.
+//!
+//! ```no_run
+//! use kicad_common::{netlist::Netlist, UuidGenerator};
+//! use kicad_sch::{
+//!     layout::SymbolLibrary,
+//!     synthesis::{write_synthesis, SynthesisOptions},
+//!     SchematicBuilder,
+//! };
+//! use kicad_sexp::source::FsSource;
+//!
+//! let netlist = Netlist::parse(&std::fs::read_to_string("divider.net")?)?;
+//! let libraries = [
+//!     SymbolLibrary::open(&FsSource::new(), "Device.kicad_sym")?,
+//!     SymbolLibrary::open(&FsSource::new(), "Connector_Generic.kicad_sym")?,
+//!     SymbolLibrary::open(&FsSource::new(), "power.kicad_sym")?,
+//! ];
+//! let mut uuids = UuidGenerator::deterministic("divider");
+//! let builder = SchematicBuilder::new(uuids.generate("root"));
+//! let mut schematic = builder.build()?;
+//! schematic.synthesize(&netlist, &libraries, &SynthesisOptions::default(), &mut uuids)?;
+//! let src = builder.to_string();
+//! let mut doc = kicad_sexp::cst::parse(&src)?;
+//! write_synthesis(&schematic, &libraries, &mut doc)?;
+//! std::fs::write("divider.kicad_sch", doc.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{
+    netlist::{Netlist, NetNode},
+    ConvertError, Error, Point, Position, UuidGenerator,
+};
+use kicad_sexp::cst::Document;
+
+use crate::{
+    layout::{outward, shift_symbol, write_labels, write_no_connects, write_symbols, ArrangeOptions, SymbolLibrary},
+    LabelKind, NoConnect, Schematic, SymbolInstance,
+};
+
+/// Settings for [`Schematic::synthesize`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SynthesisOptions {
+    pub arrange: ArrangeOptions,
+    /// The labels put on the pins.
+    pub labels: LabelKind,
+    /// Nickname of the library whose power symbols stand in for the
+    /// labels of nets with the same name, such as `GND`.
+    pub power: Option<String>,
+}
+
+impl Default for SynthesisOptions {
+    fn default() -> Self {
+        let power = Some("power".to_string());
+        SynthesisOptions { arrange: ArrangeOptions::default(), labels: LabelKind::Global, power }
+    }
+}
+
+impl Schematic {
+    /// Places the parts of `netlist` from `libraries`, which the parts'
+    /// `lib` nicknames name, with their values, footprints and other
+    /// fields, and the units their connected pins are on. The parts are
+    /// [arranged](Schematic::arrange), then every pin on a net gets a
+    /// label, or a power symbol from the `options.power` library, and the
+    /// pins of KiCad's `unconnected-` nets a no-connect flag.
+    pub fn synthesize(
+        &mut self,
+        netlist: &Netlist,
+        libraries: &[SymbolLibrary],
+        options: &SynthesisOptions,
+        uuids: &mut UuidGenerator,
+    ) -> Result<(), Error> {
+        let library = |nickname: &str| {
+            let found = libraries.iter().find(|library| library.nickname() == nickname);
+            found.ok_or_else(|| ConvertError::new(format!("no symbol library {}", nickname)))
+        };
+        for component in &netlist.components {
+            let reference = &component.reference;
+            let lib_id = component.lib_id();
+            let lib_id = lib_id.ok_or_else(|| ConvertError::new(format!("part {} names no symbol", reference)))?;
+            let from = library(component.lib.as_deref().unwrap_or_default())?;
+            let symbol = self.place_symbol(from, &lib_id, Position::default(), reference, uuids)?;
+            symbol.set_property("Value", &component.value);
+            if let Some(footprint) = &component.footprint {
+                symbol.set_property("Footprint", footprint);
+            }
+            for (key, value) in &component.fields {
+                symbol.set_property(key, value);
+            }
+            let lib = self.lib_symbol(&lib_id).expect("placing caches the library symbol");
+            let mut units: Vec<u32> = (netlist.nets.iter().flat_map(|net| &net.nodes))
+                .filter(|node| &node.reference == reference)
+                .filter_map(|node| Some(lib.pins.iter().find(|pin| pin.number == node.pin)?.unit))
+                .filter(|&unit| unit > 1)
+                .collect();
+            units.sort();
+            units.dedup();
+            for unit in units {
+                self.place_unit(reference, unit, uuids)?;
+            }
+        }
+        self.arrange(&options.arrange);
+
+        let power = options.power.as_deref().map(library).transpose()?;
+        let mut no_connects = Vec::new();
+        for net in &netlist.nets {
+            if net.name.starts_with("unconnected-") && net.nodes.len() == 1 {
+                no_connects.extend(&net.nodes);
+                continue;
+            }
+            // Local nets are named by their sheet path, `/` on the root.
+            let name = net.name.strip_prefix('/').unwrap_or(&net.name);
+            for node in &net.nodes {
+                match power.filter(|power| power.is_power(name)) {
+                    Some(power) => self.place_power(power, name, node, uuids)?,
+                    None => {
+                        self.label_pin(&node.reference, &node.pin, options.labels, name, uuids)?;
+                    },
+                }
+            }
+        }
+        // Once more, now that the labels and power symbols take up room.
+        self.arrange(&options.arrange);
+        for node in no_connects {
+            let (at, _) = self.pin_end(&node.reference, &node.pin)?;
+            let uuid = uuids.generate(format!("no_connect {} {}", node.reference, node.pin));
+            self.no_connects.push(NoConnect { at, uuid });
+        }
+        Ok(())
+    }
+
+    /// Puts the power symbol `name` from `library` on the pin of `node`,
+    /// turned to point away from the part.
+    fn place_power(
+        &mut self,
+        library: &SymbolLibrary,
+        name: &str,
+        node: &NetNode,
+        uuids: &mut UuidGenerator,
+    ) -> Result<(), Error> {
+        let (end, away) = self.pin_end(&node.reference, &node.pin)?;
+        let lib_id = format!("{}:{}", library.nickname(), name);
+        let lib = self.cache_symbol(library, &lib_id)?;
+        let pin = lib.pins_of(1, 1).next();
+        let pin = pin.ok_or_else(|| ConvertError::new(format!("power symbol {} has no pin", lib_id)))?;
+        // Pointing away from its own body, the power symbol's pin faces the part.
+        let angle = [0.0, 90.0, 180.0, 270.0].into_iter().find(|&angle| {
+            let probe = SymbolInstance { at: Position::new(0.0, 0.0, angle), ..Default::default() };
+            outward(&probe, pin) == (away + 180.0) % 360.0
+        });
+        let number = pin.number.clone();
+        let count = self.symbols.iter().filter(|symbol| symbol.is_virtual()).count();
+        let reference = format!("#PWR{:02}", count + 1);
+        let at = Position::new(end.x, end.y, angle.unwrap_or_default());
+        self.place_symbol(library, &lib_id, at, &reference, uuids)?;
+        let (placed, _) = self.pin_end(&reference, &number)?;
+        let symbol = self.symbols.last_mut().expect("a power symbol was just placed");
+        shift_symbol(symbol, Point::new(end.x - placed.x, end.y - placed.y));
+        Ok(())
+    }
+}
+
+/// Writes what [`Schematic::synthesize`] added to `schematic` into `doc`:
+/// the symbols of each of `libraries`, the labels and the no-connect flags.
+pub fn write_synthesis(
+    schematic: &Schematic,
+    libraries: &[SymbolLibrary],
+    doc: &mut Document<'_>,
+) -> Result<(), Error> {
+    for library in libraries {
+        write_symbols(schematic, library, doc)?;
+    }
+    write_labels(schematic, doc)?;
+    write_no_connects(schematic, doc)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::netlist::{Component, Net};
+
+    use super::*;
+    use crate::SchematicBuilder;
+
+    const LIBRARY: &str = include_str!("../../reference-files/demo/demo.kicad_sym");
+    const NETLIST: &str = include_str!("../../reference-files/demo/demo.net");
+
+    #[test]
+    fn synthesize() {
+        let mut netlist = Netlist::parse(NETLIST).unwrap();
+        for component in &mut netlist.components {
+            component.lib = Some("demo".to_string());
+        }
+        // A resistor pack with a pin of its second unit on VOUT and one
+        // left open.
+        let pack = Component { reference: "RN1".into(), value: "1k".into(), ..netlist.components[1].clone() };
+        netlist.components.push(Component { part: Some("R_Pack02_Split".into()), ..pack });
+        let node = |pin: &str| NetNode { reference: "RN1".into(), pin: pin.into(), ..Default::default() };
+        netlist.nets[1].nodes.push(node("3"));
+        let open = "unconnected-(RN1-R2.2-Pad4)".to_string();
+        netlist.nets.push(Net { name: open, nodes: vec![node("4")], ..Default::default() });
+
+        let library = SymbolLibrary::from_text("demo", LIBRARY);
+        let mut uuids = UuidGenerator::deterministic("synthesis");
+        let builder = SchematicBuilder::new(uuids.generate("root"));
+        let mut schematic = builder.build().unwrap();
+        let options = SynthesisOptions { power: Some("demo".into()), ..Default::default() };
+        let libraries = [library];
+        schematic.synthesize(&netlist, &libraries, &options, &mut uuids).unwrap();
+
+        let r1 = schematic.symbol("R1").unwrap();
+        assert_eq!((r1.value(), r1.property("Footprint")), (Some("10k"), Some("Resistor_SMD:R_0603_1608Metric")));
+        let pack = schematic.symbols.iter().filter(|symbol| symbol.reference() == Some("RN1"));
+        assert_eq!(pack.map(|symbol| symbol.unit).collect::<Vec<_>>(), [1, 2]);
+        // Every pin is on a label of its net, GND pins on a power symbol.
+        for net in &netlist.nets {
+            for node in &net.nodes {
+                let (end, _) = schematic.pin_end(&node.reference, &node.pin).unwrap();
+                let on = |at: Point| (at.x - end.x).abs() < 1e-6 && (at.y - end.y).abs() < 1e-6;
+                let labelled = (schematic.labels.iter())
+                    .any(|label| label.text == net.name && on(Point::new(label.at.x, label.at.y)));
+                let powered = schematic.symbols.iter().filter(|symbol| symbol.is_virtual()).any(|symbol| {
+                    let (at, _) = schematic.pin_end(symbol.reference().unwrap(), "1").unwrap();
+                    symbol.value() == Some(net.name.as_str()) && on(at)
+                });
+                let flagged = schematic.no_connects.iter().any(|flag| on(flag.at));
+                let expected = match net.name.as_str() {
+                    "GND" => (false, true, false),
+                    "VIN" | "VOUT" => (true, false, false),
+                    _ => (false, false, true),
+                };
+                assert_eq!((labelled, powered, flagged), expected, "{} {}", node.reference, node.pin);
+            }
+        }
+        assert_eq!(schematic.labels.len(), 6);
+        assert!(schematic.labels.iter().all(|label| label.kind == LabelKind::Global));
+        // No two parts share a spot.
+        let mut spots: Vec<(i64, i64)> = (schematic.symbols.iter().filter(|symbol| !symbol.is_virtual()))
+            .map(|symbol| ((symbol.at.x * 100.0) as i64, (symbol.at.y * 100.0) as i64))
+            .collect();
+        spots.sort();
+        spots.dedup();
+        assert_eq!(spots.len(), 5);
+
+        let src = builder.to_string();
+        let mut doc = kicad_sexp::cst::parse(&src).unwrap();
+        write_synthesis(&schematic, &libraries, &mut doc).unwrap();
+        let written = Schematic::parse(&doc.to_string()).unwrap();
+        assert_eq!(written.symbols, schematic.symbols);
+        assert_eq!(written.labels, schematic.labels);
+        assert_eq!(written.no_connects, schematic.no_connects);
+
+        netlist.components[0].lib = Some("Connector_Generic".into());
+        let error = Schematic::parse(&src).unwrap().synthesize(&netlist, &libraries, &options, &mut uuids).unwrap_err();
+        assert_eq!(error.to_string(), "no symbol library Connector_Generic");
+    }
+}
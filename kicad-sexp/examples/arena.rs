@@ -0,0 +1,102 @@
+//! Compares parsing and querying a board as a `Sexp` tree and as an
+//! `arena::Tree`: time, allocations and bytes allocated.
+//!
+//! Usage: `cargo run --release -p kicad-sexp --example arena -- [file] [copies]`
+//!
+//! Without a file, the demo board is used. Its sections are repeated
+//! `copies` times (default 50) inside one root to make a large board.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    env, fs,
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use kicad_sexp::{arena, input, parse, scan, Sexp};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+const RUNS: u32 = 5;
+const QUERY: &str = "footprint > pad";
+
+/// Fastest of [`RUNS`] runs of `f`, with the allocations of one run.
+fn measure<T>(mut f: impl FnMut() -> T) -> (Duration, usize, usize) {
+    let mut best = Duration::MAX;
+    let mut counts = (0, 0);
+    for _ in 0..RUNS {
+        let (allocations, bytes) = (ALLOCATIONS.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed));
+        let start = Instant::now();
+        let result = f();
+        best = best.min(start.elapsed());
+        counts = (ALLOCATIONS.load(Ordering::Relaxed) - allocations, BYTES.load(Ordering::Relaxed) - bytes);
+        drop(black_box(result));
+    }
+    (best, counts.0, counts.1)
+}
+
+fn report(name: &str, (time, allocations, bytes): (Duration, usize, usize)) {
+    println!("{:<20} {:>10.2?} {:>12} allocations {:>10.1} MiB", name, time, allocations, bytes as f64 / 1048576.0);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let src = match args.next() {
+        Some(path) => {
+            let bytes = fs::read(&path).expect("Failed to read file");
+            input::normalize(&bytes).expect("Failed to decode file").into_owned()
+        },
+        None => {
+            let copies: usize = args.next().map_or(50, |n| n.parse().expect("Expected a number of copies"));
+            let sections: Vec<_> = scan::children(DEMO).expect("Demo board is a list").map(|c| c.text).collect();
+            let body = sections.join("\n\t");
+            format!("(kicad_pcb\n\t{}\n)\n", vec![body; copies].join("\n\t"))
+        },
+    };
+    println!("{} bytes", src.len());
+
+    report("parse", measure(|| parse(&src).expect("Failed to parse")));
+    report("arena::parse", measure(|| arena::parse(&src).expect("Failed to parse")));
+
+    let sexps = parse(&src).expect("Failed to parse");
+    let tree = arena::parse(&src).expect("Failed to parse");
+    println!("{} nodes, {} matches for {:?}", tree.len(), tree.query(QUERY).unwrap().count(), QUERY);
+    report("query", measure(|| sexps.iter().map(|sexp| sexp.query(QUERY).unwrap().count()).sum::<usize>()));
+    report("arena query", measure(|| tree.query(QUERY).unwrap().count()));
+    report("walk", measure(|| sexps.iter().map(count).sum::<usize>()));
+    report("arena walk", measure(|| tree.iter().filter(|node| !node.is_list()).count()));
+}
+
+/// Number of atoms in `sexp`.
+fn count(sexp: &Sexp) -> usize {
+    match sexp {
+        Sexp::List(items) => items.iter().map(count).sum(),
+        _ => 1,
+    }
+}
@@ -1,25 +1,24 @@
-use chumsky::prelude::*;
-use std::{env, fs};
+use std::{env, fs, process};
 
-use kicad_sexp::{parser, pretty_print};
+use kicad_sexp::{diagnostic, input, parse, pretty_print};
 
 fn main() {
-    let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument")).expect("Failed to read file");
+    let path = env::args().nth(1).expect("Expected file argument");
+    let bytes = fs::read(&path).expect("Failed to read file");
+    let src = input::normalize(&bytes).expect("Failed to decode file");
+    let src = src.trim();
 
-    let result = parser().parse(src.trim()).into_result();
-
-    //println!("{:?}", result);
-    match result {
+    match parse(src) {
         Ok(sexps) => {
             println!("Parse success. We got:");
             pretty_print(&sexps);
             println!();
         },
-        Err(err) => {
-            eprintln!("Parse failed. Errors are:");
-            for e in err {
-                eprintln!("{}", e);
+        Err(_) => {
+            for report in diagnostic::diagnose(src) {
+                eprintln!("{}", report.render(&path, src));
             }
+            process::exit(1);
         },
     }
-}
\ No newline at end of file
+}
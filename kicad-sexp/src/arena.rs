@@ -0,0 +1,439 @@
+//! A tree kept in one flat buffer instead of a `Vec` per list, for large
+//! files where [`Sexp`]'s allocations dominate. Nodes are stored in
+//! document order, each knowing its parent and where its subtree ends, so
+//! walking the tree or a subtree is a walk along the buffer.
+//!
+//! ```
+//! let tree = kicad_sexp::arena::parse("(kicad_pcb (net 1 \"GND\") (segment (net 1)))").unwrap();
+//! let root = tree.roots().next().unwrap();
+//! assert_eq!(root.tag(), Some("kicad_pcb"));
+//! assert_eq!(tree.query("segment > net").unwrap().count(), 1);
+//! ```
+//!
+//! [`NodeRef::to_sexp`] builds the usual tree for a node, for use with the
+//! typed models. The `arena` example compares both representations on a
+//! board.
+
+use std::borrow::Cow;
+
+use crate::{
+    cst::atom_sexp,
+    query::{Query, QueryError},
+    ParseError, Sexp,
+};
+
+/// Index of a node in its [`Tree`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+/// What a node holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Invalid,
+    Symbol,
+    /// Raw escaped text without the quotes, as in [`Sexp::StringLiteral`].
+    String,
+    Int,
+    Hex,
+    Float,
+    List,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry<'a> {
+    kind: Kind,
+    /// Token text of atoms; empty for lists.
+    text: &'a str,
+    /// Index of the parent list, `u32::MAX` for roots.
+    parent: u32,
+    /// Index just past the node's subtree.
+    end: u32,
+}
+
+const ROOT: u32 = u32::MAX;
+
+/// A parsed document in one buffer of nodes.
+#[derive(Clone, Debug, Default)]
+pub struct Tree<'a> {
+    nodes: Vec<Entry<'a>>,
+}
+
+/// A node and the tree it is in.
+#[derive(Clone, Copy, Debug)]
+pub struct NodeRef<'t, 'a> {
+    tree: &'t Tree<'a>,
+    index: u32,
+}
+
+/// Iterator over consecutive siblings, see [`NodeRef::children`].
+#[derive(Clone, Debug)]
+pub struct Siblings<'t, 'a> {
+    tree: &'t Tree<'a>,
+    next: u32,
+    end: u32,
+}
+
+/// Iterator over the lists matching a [`Query`], see [`Tree::select`].
+#[derive(Clone, Debug)]
+pub struct Matches<'q, 't, 'a> {
+    tree: &'t Tree<'a>,
+    query: Cow<'q, Query>,
+    next: u32,
+    /// Ends of the lists around the next node, and their tags.
+    ends: Vec<u32>,
+    tags: Vec<Option<&'a str>>,
+}
+
+/// Parses `src` into a [`Tree`]. Accepts and rejects the same input as
+/// [`crate::parse`], with the same errors.
+pub fn parse(src: &str) -> Result<Tree<'_>, Vec<ParseError>> {
+    match build(src) {
+        Some(tree) => Ok(tree),
+        // Let the parser report the errors, or use its tree if it reads the
+        // input after all.
+        None => Ok(Tree::from_sexps(&crate::parse(src)?)),
+    }
+}
+
+/// Length of the whitespace character at `i`, or 0.
+fn whitespace_at(src: &str, i: usize) -> usize {
+    match src.as_bytes()[i] {
+        b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c => 1,
+        b if b < 0x80 => 0,
+        _ => src[i..].chars().next().filter(|c| c.is_whitespace()).map_or(0, char::len_utf8),
+    }
+}
+
+fn skip_whitespace(src: &str, mut i: usize) -> usize {
+    while i < src.len() {
+        match whitespace_at(src, i) {
+            0 => break,
+            n => i += n,
+        }
+    }
+    i
+}
+
+/// Builds the tree directly from `src`, following the rules of
+/// [`crate::parser`]: atoms end at whitespace or a parenthesis, and
+/// whitespace before an atom is only allowed where the whitespace after a
+/// previous node is. `None` on any error.
+fn build(src: &str) -> Option<Tree<'_>> {
+    let bytes = src.as_bytes();
+    let mut nodes: Vec<Entry> = Vec::with_capacity(src.len() / 6);
+    let mut open: Vec<u32> = Vec::new();
+    let mut after_node = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        if after_node {
+            i = skip_whitespace(src, i);
+            after_node = false;
+            continue;
+        }
+        let parent = open.last().copied().unwrap_or(ROOT);
+        let index = u32::try_from(nodes.len()).ok()?;
+        match bytes[i] {
+            b' ' | b'\n' | b'\t' => {
+                // Only a list may follow.
+                i = skip_whitespace(src, i);
+                if bytes.get(i) != Some(&b'(') {
+                    return None;
+                }
+            },
+            b'(' => {
+                nodes.push(Entry { kind: Kind::List, text: "", parent, end: 0 });
+                open.push(index);
+                i += 1;
+            },
+            b')' => {
+                let list = open.pop()?;
+                nodes[list as usize].end = index;
+                i += 1;
+                after_node = true;
+            },
+            b'"' => {
+                let mut j = i + 1;
+                loop {
+                    match bytes.get(j)? {
+                        b'\\' if j + 1 < bytes.len() => j += 2,
+                        b'\\' => return None,
+                        b'"' => break,
+                        _ => j += 1,
+                    }
+                }
+                nodes.push(Entry { kind: Kind::String, text: &src[i + 1..j], parent, end: index + 1 });
+                i = j + 1;
+                if i < bytes.len() && !matches!(bytes[i], b'(' | b')') && whitespace_at(src, i) == 0 {
+                    return None;
+                }
+                after_node = true;
+            },
+            _ => {
+                let len = bytes[i..].iter().position(|b| matches!(b, b' ' | b'"' | b'(' | b')' | b'\n' | b'\t'));
+                let j = len.map_or(bytes.len(), |len| i + len);
+                if bytes.get(j) == Some(&b'"') {
+                    return None;
+                }
+                let text = &src[i..j];
+                let kind = match atom_sexp(text) {
+                    Sexp::IntLiteral(_) => Kind::Int,
+                    Sexp::HexIntLiteral(_) => Kind::Hex,
+                    Sexp::FloatLiteral(_) => Kind::Float,
+                    _ => Kind::Symbol,
+                };
+                nodes.push(Entry { kind, text, parent, end: index + 1 });
+                i = j;
+                after_node = true;
+            },
+        }
+    }
+    open.is_empty().then_some(Tree { nodes })
+}
+
+impl<'a> Tree<'a> {
+    /// Copies the structure of a parsed tree, borrowing its text.
+    pub fn from_sexps(sexps: &[Sexp<'a>]) -> Self {
+        fn add<'a>(nodes: &mut Vec<Entry<'a>>, sexp: &Sexp<'a>, parent: u32) {
+            let index = nodes.len() as u32;
+            let (kind, text) = match *sexp {
+                Sexp::Invalid => (Kind::Invalid, ""),
+                Sexp::Symbol(text) => (Kind::Symbol, text),
+                Sexp::StringLiteral(text) => (Kind::String, text),
+                Sexp::IntLiteral(text) => (Kind::Int, text),
+                Sexp::HexIntLiteral(text) => (Kind::Hex, text),
+                Sexp::FloatLiteral(text) => (Kind::Float, text),
+                Sexp::List(_) => (Kind::List, ""),
+            };
+            nodes.push(Entry { kind, text, parent, end: 0 });
+            if let Sexp::List(items) = sexp {
+                for item in items {
+                    add(nodes, item, index);
+                }
+            }
+            nodes[index as usize].end = nodes.len() as u32;
+        }
+        let mut nodes = Vec::new();
+        for sexp in sexps {
+            add(&mut nodes, sexp, ROOT);
+        }
+        Tree { nodes }
+    }
+
+    /// Number of nodes, atoms and lists.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<NodeRef<'_, 'a>> {
+        ((id.0 as usize) < self.nodes.len()).then_some(NodeRef { tree: self, index: id.0 })
+    }
+
+    /// The top-level nodes.
+    pub fn roots(&self) -> Siblings<'_, 'a> {
+        Siblings { tree: self, next: 0, end: self.nodes.len() as u32 }
+    }
+
+    /// Every node in document order, each list before its items.
+    pub fn iter(&self) -> impl Iterator<Item = NodeRef<'_, 'a>> + '_ {
+        (0..self.nodes.len() as u32).map(|index| NodeRef { tree: self, index })
+    }
+
+    /// The lists matching `query`, in document order, as
+    /// [`Query::select`] finds them in a [`Sexp`] tree.
+    pub fn select<'q>(&self, query: &'q Query) -> Matches<'q, '_, 'a> {
+        self.matches(Cow::Borrowed(query))
+    }
+
+    /// The lists matching `selector`, see the [`query`](crate::query)
+    /// module.
+    pub fn query(&self, selector: &str) -> Result<Matches<'static, '_, 'a>, QueryError> {
+        Ok(self.matches(Cow::Owned(selector.parse()?)))
+    }
+
+    fn matches<'q>(&self, query: Cow<'q, Query>) -> Matches<'q, '_, 'a> {
+        Matches { tree: self, query, next: 0, ends: Vec::new(), tags: Vec::new() }
+    }
+
+    /// The tree as [`Sexp`] nodes.
+    pub fn to_sexps(&self) -> Vec<Sexp<'a>> {
+        self.roots().map(|root| root.to_sexp()).collect()
+    }
+}
+
+impl<'t, 'a> NodeRef<'t, 'a> {
+    fn entry(&self) -> &'t Entry<'a> {
+        &self.tree.nodes[self.index as usize]
+    }
+
+    pub fn id(&self) -> NodeId {
+        NodeId(self.index)
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.entry().kind
+    }
+
+    pub fn is_list(&self) -> bool {
+        self.kind() == Kind::List
+    }
+
+    /// The token text of an atom, strings without their quotes.
+    pub fn text(&self) -> Option<&'a str> {
+        (!self.is_list()).then_some(self.entry().text)
+    }
+
+    /// An atom as a [`Sexp`], for its typed accessors such as
+    /// [`Sexp::as_f64`].
+    pub fn atom(&self) -> Option<Sexp<'a>> {
+        let text = self.entry().text;
+        match self.kind() {
+            Kind::Invalid => Some(Sexp::Invalid),
+            Kind::Symbol => Some(Sexp::Symbol(text)),
+            Kind::String => Some(Sexp::StringLiteral(text)),
+            Kind::Int => Some(Sexp::IntLiteral(text)),
+            Kind::Hex => Some(Sexp::HexIntLiteral(text)),
+            Kind::Float => Some(Sexp::FloatLiteral(text)),
+            Kind::List => None,
+        }
+    }
+
+    /// The leading symbol of a list.
+    pub fn tag(&self) -> Option<&'a str> {
+        let first = self.children().next()?;
+        (first.kind() == Kind::Symbol).then(|| first.entry().text)
+    }
+
+    pub fn parent(&self) -> Option<NodeRef<'t, 'a>> {
+        let parent = self.entry().parent;
+        (parent != ROOT).then_some(NodeRef { tree: self.tree, index: parent })
+    }
+
+    /// The items of a list, including the tag; nothing for atoms.
+    pub fn children(&self) -> Siblings<'t, 'a> {
+        let start = if self.is_list() { self.index + 1 } else { self.entry().end };
+        Siblings { tree: self.tree, next: start, end: self.entry().end }
+    }
+
+    /// The first child list tagged `tag`.
+    pub fn child(&self, tag: &str) -> Option<NodeRef<'t, 'a>> {
+        self.children().find(|child| child.tag() == Some(tag))
+    }
+
+    /// Every node inside this one in document order.
+    pub fn descendants(&self) -> impl Iterator<Item = NodeRef<'t, 'a>> + use<'t, 'a> {
+        let tree = self.tree;
+        (self.index + 1..self.entry().end).map(move |index| NodeRef { tree, index })
+    }
+
+    /// Builds the [`Sexp`] tree of this node.
+    pub fn to_sexp(&self) -> Sexp<'a> {
+        match self.atom() {
+            Some(atom) => atom,
+            None => Sexp::List(self.children().map(|child| child.to_sexp()).collect()),
+        }
+    }
+}
+
+impl<'t, 'a> Iterator for Siblings<'t, 'a> {
+    type Item = NodeRef<'t, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        let node = NodeRef { tree: self.tree, index: self.next };
+        self.next = node.entry().end;
+        Some(node)
+    }
+}
+
+impl<'t, 'a> Iterator for Matches<'_, 't, 'a> {
+    type Item = NodeRef<'t, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while (self.next as usize) < self.tree.nodes.len() {
+            let node = NodeRef { tree: self.tree, index: self.next };
+            self.next += 1;
+            if !node.is_list() {
+                continue;
+            }
+            while self.ends.last().is_some_and(|&end| end <= node.index) {
+                self.ends.pop();
+                self.tags.pop();
+            }
+            self.ends.push(node.entry().end);
+            self.tags.push(node.tag());
+            if self.query.matches_path(&self.tags) {
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn owned(sexps: &[Sexp]) -> Vec<crate::SexpOwned> {
+        sexps.iter().map(Sexp::to_owned).collect()
+    }
+
+    #[test]
+    fn same_tree() {
+        let tree = parse(DEMO).unwrap();
+        let sexps = crate::parse(DEMO).unwrap();
+        assert_eq!(owned(&tree.to_sexps()), owned(&sexps));
+        assert_eq!(tree.len(), Tree::from_sexps(&sexps).len());
+
+        for selector in ["footprint > pad", "net", "> kicad_pcb > segment", "zone polygon xy", "* *"] {
+            let expected: Vec<_> = owned(&sexps[0].query(selector).unwrap().cloned().collect::<Vec<_>>());
+            let found: Vec<_> = tree.query(selector).unwrap().map(|node| node.to_sexp()).collect();
+            assert_eq!(owned(&found), expected, "{}", selector);
+        }
+    }
+
+    #[test]
+    fn navigation() {
+        let tree = parse("(a (b 1 \"x y\" 0x1F) (c (d 2.5)) e) (f)").unwrap();
+        let roots: Vec<_> = tree.roots().map(|root| root.tag()).collect();
+        assert_eq!(roots, [Some("a"), Some("f")]);
+        let a = tree.roots().next().unwrap();
+        let b = a.child("b").unwrap();
+        let kinds: Vec<_> = b.children().map(|child| child.kind()).collect();
+        assert_eq!(kinds, [Kind::Symbol, Kind::Int, Kind::String, Kind::Hex]);
+        assert_eq!(b.children().nth(2).unwrap().text(), Some("x y"));
+        assert_eq!(b.children().nth(3).unwrap().atom().unwrap().as_hex(), Some(0x1f));
+
+        let d = a.child("c").unwrap().child("d").unwrap();
+        assert_eq!(d.children().nth(1).unwrap().atom().unwrap().as_f64(), Some(2.5));
+        let ancestors: Vec<_> = std::iter::successors(d.parent(), NodeRef::parent).map(|node| node.tag()).collect();
+        assert_eq!(ancestors, [Some("c"), Some("a")]);
+        assert_eq!(a.descendants().count(), 12);
+        assert_eq!(a.children().last().unwrap().text(), Some("e"));
+        assert!(tree.get(d.id()).unwrap().is_list() && tree.get(NodeId(99)).is_none());
+        assert_eq!(tree.iter().filter(NodeRef::is_list).count(), 5);
+    }
+
+    #[test]
+    fn grammar() {
+        // The direct builder must agree with the parser on what is valid.
+        for src in [
+            "", " ", "(a)", " (a)", "(a) ", " a", "a", "a b", "(a)(b)", "(a(b))", "((a))", "( (a))", "( a)", "( )",
+            "()", "(a )", "(a\t\n)", "(\"a\"b)", "(\"a\"(b))", "(\"a\")", "(a\"b\")", "(\r)", "(a\rb)", "(\u{a0}a)",
+            "(a \u{a0}b)", "(a\u{a0}(b))", "(\"a\\", "(\"a\\\"\")", "(a))", "((a)", "(a) x", "(a b)  (c)\n",
+            "(\"x\"\u{a0})", "(1.5 -2 0x1_f 0x 1e5 .5 abc\"", "(a b(c))", "(a 1(c)\"x\"(d))", "(a b\"x\")",
+        ] {
+            let direct = build(src).map(|tree| owned(&tree.to_sexps()));
+            let parsed = crate::parse(src).ok().map(|sexps| owned(&sexps));
+            assert_eq!(direct, parsed, "{:?}", src);
+        }
+        assert_eq!(parse("(a (b)").unwrap_err(), crate::parse("(a (b)").unwrap_err());
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use crate::source::{Fingerprint, Source};
+
+/// A thread-safe cache of values loaded from a [`Source`], keyed by path.
+///
+/// Entries are revalidated against the file's [`Fingerprint`] on every
+/// lookup, so edited files are reloaded while unchanged ones are shared.
+/// Loading happens outside the lock; if two threads miss on the same path at
+/// once both load it and the last one stored wins.
+#[derive(Debug)]
+pub struct Cache<T> {
+    entries: RwLock<HashMap<PathBuf, (Fingerprint, Arc<T>)>>,
+}
+
+impl<T> Default for Cache<T> {
+    fn default() -> Self {
+        Cache { entries: RwLock::new(HashMap::new()) }
+    }
+}
+
+fn content_hash(bytes: &[u8]) -> Fingerprint {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Fingerprint::Content(hasher.finish())
+}
+
+impl<T> Cache<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lookup(&self, path: &Path, fingerprint: &Fingerprint) -> Option<Arc<T>> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(path)
+            .filter(|(cached, _)| cached == fingerprint)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn store(&self, path: &Path, fingerprint: Fingerprint, value: T) -> Arc<T> {
+        let value = Arc::new(value);
+        self.entries.write().unwrap().insert(path.to_path_buf(), (fingerprint, value.clone()));
+        value
+    }
+
+    /// Returns the cached value for `path`, calling `load` with the file
+    /// contents if there is none or the file changed since it was cached.
+    pub fn get_or_load<E>(
+        &self,
+        source: &dyn Source,
+        path: &Path,
+        load: impl FnOnce(Vec<u8>) -> Result<T, E>,
+    ) -> Result<Arc<T>, E>
+    where
+        E: From<io::Error>,
+    {
+        let (fingerprint, bytes) = match source.fingerprint(path)? {
+            Some(fingerprint) => {
+                if let Some(value) = self.lookup(path, &fingerprint) {
+                    return Ok(value);
+                }
+                (fingerprint, source.read(path)?)
+            },
+            None => {
+                let bytes = source.read(path)?;
+                let fingerprint = content_hash(&bytes);
+                if let Some(value) = self.lookup(path, &fingerprint) {
+                    return Ok(value);
+                }
+                (fingerprint, bytes)
+            },
+        };
+
+        Ok(self.store(path, fingerprint, load(bytes)?))
+    }
+
+    /// Drops the entry for `path`, forcing the next lookup to reload it.
+    pub fn invalidate(&self, path: &Path) {
+        self.entries.write().unwrap().remove(path);
+    }
+
+    /// Drops all entries whose path is below `dir`.
+    pub fn invalidate_dir(&self, dir: &Path) {
+        self.entries.write().unwrap().retain(|path, _| !path.starts_with(dir));
+    }
+
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    use super::*;
+    use crate::source::MemorySource;
+
+    fn load_len(loads: &AtomicUsize) -> impl FnOnce(Vec<u8>) -> io::Result<usize> + '_ {
+        move |bytes| {
+            loads.fetch_add(1, Ordering::SeqCst);
+            Ok(bytes.len())
+        }
+    }
+
+    #[test]
+    fn reuse_and_reload() {
+        let mut source = MemorySource::new().with("lib/R.kicad_mod", "(footprint)");
+        let cache = Cache::new();
+        let loads = AtomicUsize::new(0);
+        let path = Path::new("lib/R.kicad_mod");
+
+        assert_eq!(*cache.get_or_load(&source, path, load_len(&loads)).unwrap(), 11);
+        assert_eq!(*cache.get_or_load(&source, path, load_len(&loads)).unwrap(), 11);
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        source.insert("lib/R.kicad_mod", "(footprint \"R\")");
+        assert_eq!(*cache.get_or_load(&source, path, load_len(&loads)).unwrap(), 15);
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+        cache.invalidate(path);
+        assert!(cache.is_empty());
+        cache.get_or_load(&source, path, load_len(&loads)).unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let source = MemorySource::new();
+        let cache: Cache<usize> = Cache::new();
+
+        let result = cache.get_or_load(&source, Path::new("missing"), |bytes| Ok::<_, io::Error>(bytes.len()));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let source = MemorySource::new().with("a", "aaa").with("b", "bbbbb");
+        let cache = Cache::new();
+        let loads = AtomicUsize::new(0);
+        cache.get_or_load(&source, Path::new("a"), load_len(&loads)).unwrap();
+        cache.get_or_load(&source, Path::new("b"), load_len(&loads)).unwrap();
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    assert_eq!(*cache.get_or_load(&source, Path::new("a"), load_len(&loads)).unwrap(), 3);
+                    assert_eq!(*cache.get_or_load(&source, Path::new("b"), load_len(&loads)).unwrap(), 5);
+                });
+            }
+        });
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+        cache.invalidate_dir(Path::new(""));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,425 @@
+//! A lossless syntax tree that keeps whitespace, comments and original
+//! token text, so that edited files are written back byte for byte except
+//! for the nodes that changed.
+//!
+//! Comments are lines whose first non-blank character is `#`, as accepted
+//! by KiCad's lexer. [`crate::parse`] and the other parsers of this crate
+//! do not know about comments and read their words as atoms, or fail on
+//! their parentheses; for a file with comments, [`Document::to_sexps`]
+//! gives the tree KiCad sees.
+
+use std::{borrow::Cow, fmt, ops::Range};
+
+use crate::{
+    escape::{quote, unescape},
+    ParseError, Sexp,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeKind<'a> {
+    /// Token text as written, including the quotes of strings.
+    Atom(Cow<'a, str>),
+    List {
+        items: Vec<Node<'a>>,
+        /// Whitespace and comments before the closing parenthesis.
+        trailing: Cow<'a, str>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node<'a> {
+    /// Whitespace and comments before the node.
+    pub leading: Cow<'a, str>,
+    /// Byte range in the source; `None` for inserted or replaced nodes.
+    pub span: Option<Range<usize>>,
+    pub kind: NodeKind<'a>,
+}
+
+/// A parsed file: its top-level nodes and whatever follows the last one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Document<'a> {
+    pub nodes: Vec<Node<'a>>,
+    pub trailing: Cow<'a, str>,
+}
+
+fn is_hex_group(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Whether `text` is a float literal to [`crate::parser`]: a mantissa of
+/// `1.5`, `.5` or `1.` or digits with an exponent, and an optional sign.
+fn is_float(text: &str) -> bool {
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent.strip_prefix(['-', '+']).unwrap_or(exponent))),
+        None => (unsigned, None),
+    };
+    let (int, frac) = mantissa.split_once('.').map_or((mantissa, None), |(int, frac)| (int, Some(frac)));
+    (frac.is_some() || exponent.is_some())
+        && digits(int)
+        && frac.is_none_or(digits)
+        && (!int.is_empty() || frac.is_some_and(|frac| !frac.is_empty()))
+        && exponent.is_none_or(|exponent| !exponent.is_empty() && digits(exponent))
+}
+
+/// Classifies token text the same way as [`crate::parser`].
+pub(crate) fn atom_sexp(text: &str) -> Sexp<'_> {
+    if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        return Sexp::StringLiteral(inner);
+    }
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let unsigned = text.strip_prefix('-').unwrap_or(text);
+    if digits(unsigned) {
+        return Sexp::IntLiteral(text);
+    }
+    if is_float(text) {
+        return Sexp::FloatLiteral(text);
+    }
+    if let Some(hex) = text.strip_prefix("0x")
+        && hex.split('_').all(is_hex_group)
+    {
+        return Sexp::HexIntLiteral(text);
+    }
+    Sexp::Symbol(text)
+}
+
+impl<'a> Node<'a> {
+    /// A new atom with raw token `text`, preceded by a space.
+    pub fn atom(text: impl Into<Cow<'a, str>>) -> Self {
+        Node { leading: Cow::Borrowed(" "), span: None, kind: NodeKind::Atom(text.into()) }
+    }
+
+    /// A new quoted string atom, preceded by a space.
+    pub fn string(value: &str) -> Self {
+        Node::atom(quote(value))
+    }
+
+    /// A new list, preceded by a space.
+    pub fn list(items: Vec<Node<'a>>) -> Self {
+        Node {
+            leading: Cow::Borrowed(" "),
+            span: None,
+            kind: NodeKind::List { items, trailing: Cow::Borrowed("") },
+        }
+    }
+
+    pub fn items(&self) -> &[Node<'a>] {
+        match &self.kind {
+            NodeKind::List { items, .. } => items,
+            NodeKind::Atom(_) => &[],
+        }
+    }
+
+    /// `(tag items...)` with the items on the same line.
+    pub fn tagged(tag: impl Into<Cow<'a, str>>, mut items: Vec<Node<'a>>) -> Self {
+        let mut tag = Node::atom(tag);
+        tag.leading = Cow::Borrowed("");
+        items.insert(0, tag);
+        Node::list(items)
+    }
+
+    /// `(tag args...)` starting after `indent`, with each child on a line of
+    /// its own one tab further in and the closing parenthesis on a line of
+    /// its own, the way KiCad writes nested lists.
+    pub fn block(tag: impl Into<Cow<'a, str>>, args: Vec<Node<'a>>, children: Vec<Node<'a>>, indent: &str) -> Self {
+        let nested = format!("{}\t", indent);
+        let children = children.into_iter().map(|mut child| {
+            child.leading = nested.clone().into();
+            child
+        });
+        let mut node = Node::tagged(tag, args.into_iter().chain(children).collect());
+        node.leading = indent.to_string().into();
+        node.set_trailing(indent.to_string());
+        node
+    }
+
+    /// The node with its text copied, for inserting it into a document
+    /// parsed from another source.
+    pub fn into_owned(self) -> Node<'static> {
+        let kind = match self.kind {
+            NodeKind::Atom(text) => NodeKind::Atom(Cow::Owned(text.into_owned())),
+            NodeKind::List { items, trailing } => NodeKind::List {
+                items: items.into_iter().map(Node::into_owned).collect(),
+                trailing: Cow::Owned(trailing.into_owned()),
+            },
+        };
+        Node { leading: Cow::Owned(self.leading.into_owned()), span: None, kind }
+    }
+
+    /// Replaces the whitespace before a list's closing parenthesis.
+    pub fn set_trailing(&mut self, text: impl Into<Cow<'a, str>>) {
+        if let NodeKind::List { trailing, .. } = &mut self.kind {
+            *trailing = text.into();
+        }
+    }
+
+    /// Child nodes; `None` for atoms.
+    pub fn items_mut(&mut self) -> Option<&mut Vec<Node<'a>>> {
+        match &mut self.kind {
+            NodeKind::List { items, .. } => Some(items),
+            NodeKind::Atom(_) => None,
+        }
+    }
+
+    /// Raw token text of an atom.
+    pub fn text(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Atom(text) => Some(text),
+            NodeKind::List { .. } => None,
+        }
+    }
+
+    /// An atom's value: strings without their quotes and with their escapes
+    /// decoded, other tokens as written.
+    pub fn value(&self) -> Option<Cow<'_, str>> {
+        let text = self.text()?;
+        match text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            Some(inner) => Some(unescape(inner)),
+            None => Some(Cow::Borrowed(text)),
+        }
+    }
+
+    /// The leading symbol of a list.
+    pub fn tag(&self) -> Option<&str> {
+        let first = self.items().first()?.text()?;
+        (!first.starts_with('"')).then_some(first)
+    }
+
+    /// The first child list tagged `tag`.
+    pub fn child(&self, tag: &str) -> Option<&Node<'a>> {
+        self.items().iter().find(|item| item.tag() == Some(tag))
+    }
+
+    pub fn child_mut(&mut self, tag: &str) -> Option<&mut Node<'a>> {
+        self.items_mut()?.iter_mut().find(|item| item.tag() == Some(tag))
+    }
+
+    /// Replaces an atom's token text, keeping the whitespace before it.
+    pub fn set_atom(&mut self, text: impl Into<Cow<'a, str>>) {
+        self.kind = NodeKind::Atom(text.into());
+        self.span = None;
+    }
+
+    /// Replaces an atom with a quoted string.
+    pub fn set_string(&mut self, value: &str) {
+        self.set_atom(quote(value));
+    }
+
+    /// Converts to the plain tree used by the typed models.
+    pub fn to_sexp(&self) -> Sexp<'_> {
+        match &self.kind {
+            NodeKind::Atom(text) => atom_sexp(text),
+            NodeKind::List { items, .. } => Sexp::List(items.iter().map(Node::to_sexp).collect()),
+        }
+    }
+
+    fn write(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str(&self.leading)?;
+        match &self.kind {
+            NodeKind::Atom(text) => f.write_str(text),
+            NodeKind::List { items, trailing } => {
+                f.write_char('(')?;
+                for item in items {
+                    item.write(f)?;
+                }
+                f.write_str(trailing)?;
+                f.write_char(')')
+            },
+        }
+    }
+}
+
+impl fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f)
+    }
+}
+
+impl Document<'_> {
+    /// Converts every top-level node to the plain tree, dropping comments.
+    pub fn to_sexps(&self) -> Vec<Sexp<'_>> {
+        self.nodes.iter().map(Node::to_sexp).collect()
+    }
+}
+
+impl fmt::Display for Document<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            node.write(f)?;
+        }
+        f.write_str(&self.trailing)
+    }
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error(&self, span: Range<usize>, message: &str) -> ParseError {
+        ParseError { span, message: message.to_string() }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.src[..self.pos].rsplit('\n').next().is_none_or(|line| line.trim().is_empty())
+    }
+
+    /// Consumes whitespace and comment lines.
+    fn trivia(&mut self) -> Cow<'a, str> {
+        let start = self.pos;
+        loop {
+            let rest = &self.src[self.pos..];
+            let Some(c) = rest.chars().next() else { break };
+            if c.is_whitespace() {
+                self.pos += c.len_utf8();
+            } else if c == '#' && self.at_line_start() {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else {
+                break;
+            }
+        }
+        Cow::Borrowed(&self.src[start..self.pos])
+    }
+
+    fn atom(&mut self) -> Result<Range<usize>, ParseError> {
+        let start = self.pos;
+        let bytes = self.src.as_bytes();
+        if bytes[start] == b'"' {
+            let mut i = start + 1;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'\\' => i += 2,
+                    b'"' => {
+                        self.pos = i + 1;
+                        return Ok(start..self.pos);
+                    },
+                    _ => i += 1,
+                }
+            }
+            return Err(self.error(start..bytes.len(), "unterminated string"));
+        }
+        let len = self.src[start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+            .unwrap_or(self.src.len() - start);
+        self.pos += len;
+        Ok(start..self.pos)
+    }
+
+    /// Parses nodes until a closing parenthesis or the end of input.
+    fn nodes(&mut self, open: Option<usize>) -> Result<(Vec<Node<'a>>, Cow<'a, str>), ParseError> {
+        let mut nodes = Vec::new();
+        loop {
+            let leading = self.trivia();
+            match self.src.as_bytes().get(self.pos) {
+                None => match open {
+                    Some(open) => return Err(self.error(open..self.src.len(), "unclosed list")),
+                    None => return Ok((nodes, leading)),
+                },
+                Some(b')') => match open {
+                    Some(_) => return Ok((nodes, leading)),
+                    None => return Err(self.error(self.pos..self.pos + 1, "unexpected `)`")),
+                },
+                Some(b'(') => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    let (items, trailing) = self.nodes(Some(start))?;
+                    self.pos += 1;
+                    nodes.push(Node { leading, span: Some(start..self.pos), kind: NodeKind::List { items, trailing } });
+                },
+                Some(_) => {
+                    let span = self.atom()?;
+                    let text = Cow::Borrowed(&self.src[span.clone()]);
+                    nodes.push(Node { leading, span: Some(span), kind: NodeKind::Atom(text) });
+                },
+            }
+        }
+    }
+}
+
+/// Parses `src` into a lossless tree.
+pub fn parse(src: &str) -> Result<Document<'_>, ParseError> {
+    let (nodes, trailing) = Lexer { src, pos: 0 }.nodes(None)?;
+    Ok(Document { nodes, trailing })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_sch");
+
+    #[test]
+    fn lossless() {
+        let src = "# header comment\n(a  1 \"two (2)\"\t(b)\n  # inner\n  )\n";
+        let doc = parse(src).unwrap();
+
+        assert_eq!(doc.to_string(), src);
+        assert_eq!(doc.nodes[0].leading, "# header comment\n");
+        assert_eq!(doc.nodes[0].tag(), Some("a"));
+        assert_eq!(&src[doc.nodes[0].items()[2].span.clone().unwrap()], "\"two (2)\"");
+
+        for src in [
+            include_str!("../../reference-files/empty/empty.kicad_sch"),
+            include_str!("../../reference-files/empty/empty.kicad_pcb"),
+            include_str!("../../reference-files/demo/demo.kicad_pcb"),
+            DEMO,
+        ] {
+            let doc = parse(src).unwrap();
+            assert_eq!(doc.to_string(), src);
+            assert_eq!(
+                crate::writer::to_string(&doc.to_sexps()),
+                crate::writer::to_string(&crate::parse(src).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn comments() {
+        let plain = |src| crate::writer::to_string(&crate::parse(src).unwrap());
+        let src = "# header\n(a 1\n\t# inner (b)\n\t(c))\n";
+        assert_eq!(crate::writer::to_string(&parse(src).unwrap().to_sexps()), plain("(a 1\n\t(c))\n"));
+        assert_eq!(crate::parse(src).unwrap().len(), 3);
+        assert!(crate::parse("# (\n(a)").is_err());
+
+        // Only whole lines are comments.
+        assert_eq!(crate::writer::to_string(&parse("(a #b)").unwrap().to_sexps()), plain("(a #b)"));
+    }
+
+    #[test]
+    fn atoms() {
+        let src = "(a 1 -2 1.5 1e-05 .25 1. +3 - 1e F.Cu 0xdeadbeef_beefdead_44552255_12345678 0x5F3C 0x_1 \"s\")";
+        let doc = parse(src).unwrap();
+        let atoms: Vec<String> = doc.nodes[0].items().iter().map(|item| format!("{:?}", item.to_sexp())).collect();
+        let parsed = crate::parse(src).unwrap();
+        let Sexp::List(expected) = &parsed[0] else { unreachable!() };
+        assert_eq!(atoms, expected.iter().map(|sexp| format!("{:?}", sexp)).collect::<Vec<_>>());
+        assert!(atoms[4].starts_with("FloatLiteral") && atoms[9].starts_with("Symbol"));
+        assert!(atoms[12].starts_with("HexIntLiteral") && atoms[13].starts_with("Symbol"));
+    }
+
+    #[test]
+    fn edits() {
+        let mut doc = parse(DEMO).unwrap();
+        let root = &mut doc.nodes[0];
+        let version = root.child_mut("version").unwrap();
+        version.items_mut().unwrap()[1].set_atom("20250610");
+        let generator = root.child_mut("generator").unwrap();
+        generator.items_mut().unwrap()[1].set_string("kicad-file-rs \"test\"");
+        assert_eq!(generator.items()[1].value().as_deref(), Some("kicad-file-rs \"test\""));
+        assert_eq!(root.child("version").unwrap().items()[1].value().as_deref(), Some("20250610"));
+
+        let expected = DEMO
+            .replacen("(version 20250114)", "(version 20250610)", 1)
+            .replacen("(generator \"eeschema\")", "(generator \"kicad-file-rs \\\"test\\\"\")", 1);
+        assert_eq!(doc.to_string(), expected);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("(a (b)").unwrap_err().message, "unclosed list");
+        assert_eq!(parse("(a \"b)").unwrap_err().span, 3..6);
+        assert_eq!(parse("a)").unwrap_err().message, "unexpected `)`");
+    }
+}
@@ -0,0 +1,233 @@
+//! Labeled error reports rendered against the source text, for tools that
+//! show parse errors to people.
+//!
+//! ```text
+//! error: unclosed `(`
+//!  --> board.kicad_pcb:1:7
+//!   |
+//! 1 | (a (b)
+//!   | - unclosed paren opened here
+//!   |       ^ expected `)` before end of input
+//! ```
+
+use std::{fmt::Write, ops::Range};
+
+use crate::{span::LineIndex, ParseError};
+
+/// A message attached to a byte range. Primary labels mark where the error
+/// is, secondary ones add context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    pub span: Range<usize>,
+    pub message: String,
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(span: Range<usize>, message: impl Into<String>) -> Self {
+        Label { span, message: message.into(), primary: true }
+    }
+
+    pub fn secondary(span: Range<usize>, message: impl Into<String>) -> Self {
+        Label { span, message: message.into(), primary: false }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub labels: Vec<Label>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Diagnostic { message: message.into(), labels: Vec::new() }
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    /// Renders the report for the file `name` with contents `src`.
+    pub fn render(&self, name: &str, src: &str) -> String {
+        let index = LineIndex::new(src);
+        let mut labels: Vec<(usize, &Label)> =
+            self.labels.iter().map(|label| (index.location(label.span.start).line, label)).collect();
+        labels.sort_by_key(|(line, label)| (*line, label.span.start));
+
+        let mut out = format!("error: {}\n", self.message);
+        let Some((_, first)) = labels.iter().find(|(_, label)| label.primary).or(labels.first()) else {
+            return out;
+        };
+        let width = labels.last().map_or(1, |(line, _)| line.to_string().len());
+        let _ = writeln!(out, "{:width$}--> {}:{}", "", name, index.location(first.span.start));
+        let _ = writeln!(out, "{:width$} |", "");
+        let mut previous: Option<usize> = None;
+        for (line, label) in &labels {
+            let Some(line_span) = index.line_span(*line) else { continue };
+            let text = &src[line_span.clone()];
+            if previous != Some(*line) {
+                if previous.is_some_and(|previous| previous + 1 < *line) {
+                    let _ = writeln!(out, "{:width$} ...", "");
+                }
+                let _ = writeln!(out, "{:>width$} | {}", line, text);
+                previous = Some(*line);
+            }
+
+            // Keep tabs in the padding so the marker lines up with the text.
+            let start = label.span.start.clamp(line_span.start, line_span.end) - line_span.start;
+            let end = label.span.end.clamp(line_span.start, line_span.end) - line_span.start;
+            let padding: String = text[..start].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let marker = if label.primary { '^' } else { '-' };
+            let len = text[start..end].chars().count().max(1);
+            let _ = write!(out, "{:width$} | {}{}", "", padding, marker.to_string().repeat(len));
+            if !label.message.is_empty() {
+                let _ = write!(out, " {}", label.message);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::new(&error.message).with_label(Label::primary(error.span.clone(), "not valid here"))
+    }
+}
+
+/// Indentation of the line at `pos` if only whitespace precedes it there.
+fn indent(src: &str, pos: usize) -> Option<usize> {
+    let line = src[..pos].rsplit('\n').next().unwrap_or("");
+    line.chars().all(char::is_whitespace).then(|| line.chars().count())
+}
+
+fn unclosed(open: usize, closed_by: Option<usize>, end: usize) -> Diagnostic {
+    let label = match closed_by {
+        Some(close) => Label::primary(close..close + 1, "this `)` closes an outer list instead"),
+        None => Label::primary(end..end, "expected `)` before end of input"),
+    };
+    Diagnostic::new("unclosed `(`")
+        .with_label(label)
+        .with_label(Label::secondary(open..open + 1, "unclosed paren opened here"))
+}
+
+/// Finds unbalanced parentheses and unterminated strings, which are behind
+/// most parse errors and which the parser itself reports far from the cause.
+fn delimiter_errors(src: &str) -> Vec<Diagnostic> {
+    if scan(src, false).is_empty() {
+        return Vec::new();
+    }
+    scan(src, true)
+}
+
+/// With `by_indent`, a `)` starting a line first closes the open lists that
+/// start a line further indented, as KiCad lays them out, so that a missing
+/// `)` is blamed on the list that lacks it rather than the outermost one.
+fn scan(src: &str, by_indent: bool) -> Vec<Diagnostic> {
+    let bytes = src.as_bytes();
+    let end = src.len();
+    let mut open: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut errors = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let start = i;
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        None => {
+                            errors.push(
+                                Diagnostic::new("unterminated string")
+                                    .with_label(Label::primary(end..end, "expected `\"` before end of input"))
+                                    .with_label(Label::secondary(start..start + 1, "string starts here")),
+                            );
+                            return errors;
+                        },
+                        Some(b'\\') => i += 2,
+                        Some(b'"') => break,
+                        Some(_) => i += 1,
+                    }
+                }
+            },
+            b'(' => open.push((i, indent(src, i))),
+            b')' => {
+                if let Some(close) = indent(src, i).filter(|_| by_indent) {
+                    while let Some(&(start, Some(indent))) = open.last()
+                        && indent > close
+                    {
+                        errors.push(unclosed(start, Some(i), end));
+                        open.pop();
+                    }
+                }
+                if open.pop().is_none() {
+                    let label = Label::primary(i..i + 1, "no matching `(`");
+                    errors.push(Diagnostic::new("unexpected `)`").with_label(label));
+                }
+            },
+            _ => {},
+        }
+        i += 1;
+    }
+    errors.extend(open.into_iter().map(|(start, _)| unclosed(start, None, end)));
+    errors
+}
+
+/// Parses `src` and explains every problem found; empty if it parses.
+pub fn diagnose(src: &str) -> Vec<Diagnostic> {
+    let errors = delimiter_errors(src);
+    if !errors.is_empty() {
+        return errors;
+    }
+    match crate::parse(src) {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors.iter().map(Diagnostic::from).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unclosed() {
+        let src = "(kicad_pcb\n\t(version 20241229)\n\t(net 0 \"\"\n\t(net 1 \"GND\")\n";
+        let diagnostics = diagnose(src);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[1].render("board.kicad_pcb", src),
+            "error: unclosed `(`\n \
+             --> board.kicad_pcb:5:1\n  \
+             |\n\
+             3 | \t(net 0 \"\"\n  \
+             | \t- unclosed paren opened here\n  \
+             ...\n\
+             5 | \n  \
+             | ^ expected `)` before end of input\n"
+        );
+    }
+
+    #[test]
+    fn others() {
+        let src = "(a\n  (b\n    (c 1)\n)";
+        let nested = diagnose(src);
+        assert_eq!(nested.len(), 1);
+        assert_eq!(nested[0].labels[1].span, 5..6);
+        assert!(nested[0].render("x", src).ends_with("\n4 | )\n  | ^ this `)` closes an outer list instead\n"));
+
+        let stray = diagnose("(a)\n(b))");
+        assert_eq!(stray.len(), 1);
+        assert_eq!(stray[0].labels[0].span, 7..8);
+
+        let string = diagnose("(a \"b)");
+        assert_eq!(string[0].message, "unterminated string");
+        assert!(string[0].render("x", "(a \"b)").contains("\n1 | (a \"b)\n  |    - string starts here\n"));
+
+        let token = diagnose("(a \"b\"c)");
+        assert_eq!(token[0].message, "unexpected 'c'");
+        assert!(diagnose(include_str!("../../reference-files/empty/empty.kicad_pcb")).is_empty());
+    }
+}
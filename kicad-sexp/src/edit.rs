@@ -0,0 +1,231 @@
+//! Structural editing of [`SexpOwned`] trees and a builder for new ones.
+//! Edited trees are written with [`crate::writer`] through
+//! [`SexpOwned::as_sexp`]; to keep the original formatting of untouched
+//! nodes, edit a [`crate::cst::Document`] instead.
+
+use crate::{SexpOwned, escape::escape, number::format_float};
+
+impl SexpOwned {
+    pub fn symbol(name: &str) -> Self {
+        SexpOwned::Symbol(name.to_string())
+    }
+
+    /// A string literal holding `value`, escaped as needed.
+    pub fn string(value: &str) -> Self {
+        SexpOwned::StringLiteral(escape(value).into_owned())
+    }
+
+    pub fn int(value: i64) -> Self {
+        SexpOwned::IntLiteral(value.to_string())
+    }
+
+    /// A number formatted as KiCad writes it. Whole values become integer
+    /// literals, as they would when reading the written text back.
+    pub fn float(value: f64) -> Self {
+        let text = format_float(value);
+        if text.contains('.') { SexpOwned::FloatLiteral(text) } else { SexpOwned::IntLiteral(text) }
+    }
+
+    /// The list's items including the tag; empty for atoms.
+    pub fn items(&self) -> &[SexpOwned] {
+        match self {
+            SexpOwned::List(items) => items,
+            _ => &[],
+        }
+    }
+
+    /// The list's items including the tag; `None` for atoms.
+    pub fn items_mut(&mut self) -> Option<&mut Vec<SexpOwned>> {
+        match self {
+            SexpOwned::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The leading symbol of a list.
+    pub fn tag(&self) -> Option<&str> {
+        match self.items().first() {
+            Some(SexpOwned::Symbol(tag)) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Renames a tagged list; other nodes are left alone.
+    pub fn rename(&mut self, tag: &str) {
+        if let Some(SexpOwned::Symbol(old)) = self.items_mut().and_then(|items| items.first_mut()) {
+            *old = tag.to_string();
+        }
+    }
+
+    /// The first child list tagged `tag`.
+    pub fn child(&self, tag: &str) -> Option<&SexpOwned> {
+        self.items().iter().find(|item| item.tag() == Some(tag))
+    }
+
+    pub fn child_mut(&mut self, tag: &str) -> Option<&mut SexpOwned> {
+        self.items_mut()?.iter_mut().find(|item| item.tag() == Some(tag))
+    }
+
+    /// All child lists tagged `tag`.
+    pub fn children<'s, 't>(&'s self, tag: &'t str) -> impl Iterator<Item = &'s SexpOwned> + use<'s, 't> {
+        self.items().iter().filter(move |item| item.tag() == Some(tag))
+    }
+
+    /// Appends `item` to a list; does nothing for atoms.
+    pub fn push(&mut self, item: impl Into<SexpOwned>) {
+        if let Some(items) = self.items_mut() {
+            items.push(item.into());
+        }
+    }
+
+    /// Inserts `item` at `index`, counting the tag as item 0. Indexes past
+    /// the end append.
+    pub fn insert(&mut self, index: usize, item: impl Into<SexpOwned>) {
+        if let Some(items) = self.items_mut() {
+            items.insert(index.min(items.len()), item.into());
+        }
+    }
+
+    /// Inserts `item` after the last child list tagged `tag`, or appends it
+    /// if there is none; this keeps items of one kind together.
+    pub fn insert_after(&mut self, tag: &str, item: impl Into<SexpOwned>) {
+        let index = self.items().iter().rposition(|i| i.tag() == Some(tag)).map_or(usize::MAX, |i| i + 1);
+        self.insert(index, item);
+    }
+
+    /// Removes the item at `index`, counting the tag as item 0.
+    pub fn remove(&mut self, index: usize) -> Option<SexpOwned> {
+        let items = self.items_mut()?;
+        (index < items.len()).then(|| items.remove(index))
+    }
+
+    /// Removes every child list tagged `tag`, returning how many there were.
+    pub fn remove_children(&mut self, tag: &str) -> usize {
+        let Some(items) = self.items_mut() else {
+            return 0;
+        };
+        let before = items.len();
+        items.retain(|item| item.tag() != Some(tag));
+        before - items.len()
+    }
+
+    /// Replaces the argument at `index` after the tag, returning the old one.
+    pub fn set_arg(&mut self, index: usize, value: SexpOwned) -> Option<SexpOwned> {
+        let slot = self.items_mut()?.get_mut(index + 1)?;
+        Some(std::mem::replace(slot, value))
+    }
+
+    /// Sets the value of a `(tag value)` child, adding the child at the end
+    /// if it is missing.
+    pub fn set_value(&mut self, tag: &str, value: SexpOwned) {
+        match self.child_mut(tag) {
+            Some(child) if child.items().len() > 1 => {
+                child.set_arg(0, value);
+            },
+            Some(child) => child.push(value),
+            None => self.push(SexpBuilder::new(tag).item(value)),
+        }
+    }
+}
+
+/// Builds a tagged list item by item:
+///
+/// ```
+/// # use kicad_sexp::SexpBuilder;
+/// let segment = SexpBuilder::new("segment")
+///     .child(SexpBuilder::new("start").float(10.0).float(2.5))
+///     .child(SexpBuilder::new("layer").string("F.Cu"))
+///     .child(SexpBuilder::new("net").int(1))
+///     .build();
+/// assert_eq!(segment.to_string(), "(segment\n\t(start 10 2.5)\n\t(layer \"F.Cu\")\n\t(net 1)\n)");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SexpBuilder {
+    items: Vec<SexpOwned>,
+}
+
+impl SexpBuilder {
+    pub fn new(tag: &str) -> Self {
+        SexpBuilder { items: vec![SexpOwned::symbol(tag)] }
+    }
+
+    pub fn item(mut self, item: impl Into<SexpOwned>) -> Self {
+        self.items.push(item.into());
+        self
+    }
+
+    pub fn symbol(self, name: &str) -> Self {
+        self.item(SexpOwned::symbol(name))
+    }
+
+    pub fn string(self, value: &str) -> Self {
+        self.item(SexpOwned::string(value))
+    }
+
+    pub fn int(self, value: i64) -> Self {
+        self.item(SexpOwned::int(value))
+    }
+
+    pub fn float(self, value: f64) -> Self {
+        self.item(SexpOwned::float(value))
+    }
+
+    pub fn child(self, child: SexpBuilder) -> Self {
+        self.item(child)
+    }
+
+    /// Adds `child` if it is `Some`, for optional fields.
+    pub fn child_opt(self, child: Option<SexpBuilder>) -> Self {
+        match child {
+            Some(child) => self.child(child),
+            None => self,
+        }
+    }
+
+    pub fn build(self) -> SexpOwned {
+        SexpOwned::List(self.items)
+    }
+}
+
+impl From<SexpBuilder> for SexpOwned {
+    fn from(builder: SexpBuilder) -> Self {
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit() {
+        let src = "(kicad_pcb (net 0 \"\") (net 1 \"GND\") (segment (width 0.2) (layer \"F.Cu\") (net 1)) (gr_line))";
+        let mut board = crate::parse(src).unwrap().remove(0).into_owned();
+
+        let segment = board.child_mut("segment").unwrap();
+        segment.set_value("width", SexpOwned::float(0.25));
+        segment.set_value("locked", SexpOwned::symbol("yes"));
+        segment.child_mut("layer").unwrap().set_arg(0, SexpOwned::string("B.Cu"));
+        segment.rename("arc");
+
+        board.insert_after("net", SexpBuilder::new("net").int(2).string("a \"b\"").build());
+        assert_eq!(board.remove_children("gr_line"), 1);
+        assert_eq!(board.children("net").count(), 3);
+        assert_eq!(board.remove(1).and_then(|net| net.tag().map(str::to_string)).as_deref(), Some("net"));
+        board.insert(1, SexpBuilder::new("version").int(20241229));
+
+        let written = board.to_string();
+        assert_eq!(
+            written,
+            "(kicad_pcb\n\t(version 20241229)\n\t(net 1 \"GND\")\n\t(net 2 \"a \\\"b\\\"\")\n\
+             \t(arc\n\t\t(width 0.25)\n\t\t(layer \"B.Cu\")\n\t\t(net 1)\n\t\t(locked yes)\n\t)\n)"
+        );
+        assert_eq!(crate::parse(&written).unwrap().remove(0).into_owned(), board);
+
+        let mut atom = SexpOwned::symbol("x");
+        atom.push(SexpOwned::int(1));
+        atom.rename("y");
+        assert_eq!(atom, SexpOwned::symbol("x"));
+        assert_eq!(SexpOwned::float(-0.0), SexpOwned::int(0));
+    }
+}
@@ -0,0 +1,309 @@
+//! Escaping of string literals, and the `{slash}`-style substitutions KiCad
+//! writes for characters a name may not contain.
+//!
+//! Trees keep string literals as written, escapes included, so unchanged
+//! strings are written back exactly. Decode a value when it is needed with
+//! [`Sexp::as_str`](crate::Sexp::as_str) or [`unescape`]; the typed models
+//! decode every string as they convert. New literals from [`quote`],
+//! [`SexpOwned::string`](crate::SexpOwned::string) or
+//! [`Node::string`](crate::cst::Node::string) are escaped to match.
+//!
+//! Both layers follow KiCad's own code, so text written here reads back the
+//! same in KiCad and the other way round.
+//!
+//! Substitutions are a separate layer inside the value: a net named `A/B`
+//! is stored as `"A{slash}B"`, which [`unescape`] leaves alone and
+//! [`decode_substitutions`] turns back into `A/B`. Which characters are
+//! substituted depends on the field's [`Context`].
+
+use std::borrow::Cow;
+
+/// The characters KiCad substitutes, with the token written for each.
+const SUBSTITUTIONS: [(char, &str); 14] = [
+    ('"', "dblquote"),
+    ('\'', "quote"),
+    ('<', "lt"),
+    ('>', "gt"),
+    ('\\', "backslash"),
+    ('/', "slash"),
+    ('|', "bar"),
+    (',', "comma"),
+    (':', "colon"),
+    (' ', "space"),
+    ('$', "dollar"),
+    ('\t', "tab"),
+    ('\n', "return"),
+    ('{', "brace"),
+];
+
+/// Escapes `value` for use between the quotes of a string literal, as
+/// KiCad's `OUTPUTFORMATTER::Quotes` does: quotes, backslashes and line
+/// breaks are escaped, tabs are written as they are.
+pub fn escape(value: &str) -> Cow<'_, str> {
+    if !value.contains(['"', '\\', '\n', '\r']) {
+        return Cow::Borrowed(value);
+    }
+    let mut out = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Decodes the raw text between a string literal's quotes as KiCad's lexer
+/// reads it: the C escapes `\a \b \f \n \r \t \v`, up to two hex digits
+/// after `\x` and up to three octal digits give single bytes, and any other
+/// escaped character keeps its backslash. Bytes that do not form UTF-8 are
+/// replaced.
+pub fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        let Some(&c) = bytes.get(i) else {
+            out.push(b'\\');
+            break;
+        };
+        i += 1;
+        let byte = match c {
+            b'"' | b'\\' => c,
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            b'x' => match digits(&bytes[i..], 2, |b| b.is_ascii_hexdigit()) {
+                0 => b'x',
+                n => {
+                    i += n;
+                    u8::from_str_radix(&raw[i - n..i], 16).unwrap_or_default()
+                },
+            },
+            _ => match digits(&bytes[i - 1..], 3, |b| matches!(b, b'0'..=b'7')) {
+                0 => {
+                    // Not an escape: the backslash stands for itself and
+                    // the character is read as usual.
+                    i -= 1;
+                    b'\\'
+                },
+                n => {
+                    i += n - 1;
+                    // Octal escapes above 0o377 wrap, as C's char does.
+                    u32::from_str_radix(&raw[i - n..i], 8).unwrap_or_default() as u8
+                },
+            },
+        };
+        out.push(byte);
+    }
+    match String::from_utf8(out) {
+        Ok(text) => Cow::Owned(text),
+        Err(e) => Cow::Owned(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+    }
+}
+
+/// How many of the first `max` bytes are digits.
+fn digits(bytes: &[u8], max: usize, digit: impl Fn(u8) -> bool) -> usize {
+    bytes.iter().take(max).take_while(|&&b| digit(b)).count()
+}
+
+/// `value` as a string literal token, quotes included.
+pub fn quote(value: &str) -> String {
+    format!("\"{}\"", escape(value))
+}
+
+/// Replaces substitution tokens such as `{slash}` with their characters, as
+/// KiCad's `UnescapeString` does. Other braced text, such as the
+/// `${REFERENCE}` variables and `~{overbar}` markup, is kept.
+pub fn decode_substitutions(text: &str) -> Cow<'_, str> {
+    if !text.contains('{') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            out.push(c);
+            continue;
+        }
+        let (mut token, mut depth, mut closed) = (String::new(), 1, false);
+        for c in chars.by_ref() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {},
+            }
+            if depth == 0 {
+                closed = true;
+                break;
+            }
+            token.push(c);
+        }
+        match SUBSTITUTIONS.iter().find(|(_, name)| *name == token) {
+            Some((c, _)) if closed => out.push(*c),
+            _ => {
+                out.push('{');
+                out.push_str(&decode_substitutions(&token));
+                if closed {
+                    out.push('}');
+                }
+            },
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// What a string is used as, which decides the characters
+/// [`encode_substitutions`] replaces. These are the contexts of KiCad's
+/// `EscapeString`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Context {
+    /// Net and label names: `/` is substituted, line breaks are dropped.
+    NetName,
+    /// Library identifiers: `\ < > : "` are substituted, line breaks are
+    /// dropped.
+    LibId,
+    /// Library identifiers as KiCad 5 wrote them, with `/` substituted too.
+    LegacyLibId,
+    /// IPC-D-356 netlists: `/ , "` are substituted.
+    Ipc,
+    /// `"` is substituted.
+    QuotedStr,
+    /// JavaScript strings: `' \ ( )` and non-ASCII characters become `\u`
+    /// escapes.
+    JsStr,
+    /// Single-line fields: line breaks are substituted.
+    Line,
+    /// File names: `/ \ " < > | :`, tabs and line breaks are substituted.
+    Filename,
+    /// Spaces are substituted.
+    NoSpace,
+    /// CSV fields: `,` and line breaks are substituted.
+    Csv,
+}
+
+/// Replaces the characters `context` reserves with substitution tokens, as
+/// KiCad's `EscapeString` does, so `A/B` becomes `A{slash}B` as a net name.
+/// [`decode_substitutions`] reverses it, except for the line breaks that
+/// net names and library identifiers drop.
+pub fn encode_substitutions(text: &str, context: Context) -> Cow<'_, str> {
+    enum Out {
+        Keep,
+        Drop,
+        Token(&'static str),
+        Unicode,
+    }
+    let token = |c: char| {
+        let name = SUBSTITUTIONS.iter().find(|(s, _)| *s == c).map(|(_, name)| *name);
+        Out::Token(name.expect("reserved characters have a substitution"))
+    };
+    let line_break = matches!(context, Context::NetName | Context::LibId | Context::LegacyLibId);
+    let map = |c: char| match (context, c) {
+        (_, '\n' | '\r') if line_break => Out::Drop,
+        (Context::NetName, '/') => token(c),
+        (Context::LibId | Context::LegacyLibId, '\\' | '<' | '>' | ':' | '"') => token(c),
+        (Context::LegacyLibId, '/') => token(c),
+        (Context::Ipc, '/' | ',' | '"') => token(c),
+        (Context::QuotedStr, '"') => token(c),
+        (Context::JsStr, c) if c >= '\u{7f}' || matches!(c, '\'' | '\\' | '(' | ')') => Out::Unicode,
+        (Context::Line | Context::Csv | Context::Filename, '\r') => Out::Token("return"),
+        (Context::Line | Context::Csv | Context::Filename, '\n') => token(c),
+        (Context::Filename, '/' | '\\' | '"' | '<' | '>' | '|' | ':' | '\t') => token(c),
+        (Context::NoSpace, ' ') => token(c),
+        (Context::Csv, ',') => token(c),
+        _ => Out::Keep,
+    };
+    if text.chars().all(|c| matches!(map(c), Out::Keep)) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match map(c) {
+            Out::Keep => out.push(c),
+            Out::Drop => {},
+            Out::Token(name) => {
+                out.push('{');
+                out.push_str(name);
+                out.push('}');
+            },
+            Out::Unicode => out.push_str(&format!("\\u{:04X}", c as u32)),
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literals() {
+        let value = "a \"b\"\n\tc\\d\r";
+        assert_eq!(quote(value), "\"a \\\"b\\\"\\n\tc\\\\d\\r\"");
+        assert_eq!(unescape(&escape(value)), value);
+        assert!(matches!(escape("plain\t"), Cow::Borrowed("plain\t")));
+        assert!(crate::parse(&quote(value)).is_ok());
+    }
+
+    #[test]
+    fn lexer_escapes() {
+        assert_eq!(unescape("\\a\\b\\f\\v\\t"), "\x07\x08\x0c\x0b\t");
+        assert_eq!(unescape("\\x41\\x4g\\xg\\x414"), "A\x04gxgA4");
+        assert_eq!(unescape("\\101\\0\\1012\\77"), "A\0A2?");
+        assert_eq!(unescape("\\xC3\\xA9 \\303\\251"), "\u{e9} \u{e9}");
+        assert_eq!(unescape("\\xC3"), "\u{fffd}");
+        assert_eq!(unescape("\\q\\8\\"), "\\q\\8\\");
+        assert_eq!(unescape("\\\\q"), "\\q");
+    }
+
+    #[test]
+    fn substitutions() {
+        assert_eq!(decode_substitutions("A{slash}B{colon}{space}{dblquote}"), "A/B: \"");
+        assert_eq!(decode_substitutions("${REFERENCE} ~{RESET} {brace}x}"), "${REFERENCE} ~{RESET} {x}");
+        assert_eq!(decode_substitutions("~{A{slash}B}"), "~{A/B}");
+        assert_eq!(decode_substitutions("open {slash"), "open {slash");
+        for text in ["A/B", "x{y}", "$:\"'<>|,\\ \t\n"] {
+            assert_eq!(decode_substitutions(&encode_substitutions(text, Context::Filename)), text);
+        }
+    }
+
+    #[test]
+    fn contexts() {
+        let text = "/a\\b<c>:\"d\"|e,f g\t\n(h)\r'\u{e9}";
+        let cases = [
+            (Context::NetName, "{slash}a\\b<c>:\"d\"|e,f g\t(h)'\u{e9}"),
+            (Context::LibId, "/a{backslash}b{lt}c{gt}{colon}{dblquote}d{dblquote}|e,f g\t(h)'\u{e9}"),
+            (Context::LegacyLibId, "{slash}a{backslash}b{lt}c{gt}{colon}{dblquote}d{dblquote}|e,f g\t(h)'\u{e9}"),
+            (Context::Ipc, "{slash}a\\b<c>:{dblquote}d{dblquote}|e{comma}f g\t\n(h)\r'\u{e9}"),
+            (Context::QuotedStr, "/a\\b<c>:{dblquote}d{dblquote}|e,f g\t\n(h)\r'\u{e9}"),
+            (Context::JsStr, "/a\\u005Cb<c>:\"d\"|e,f g\t\n\\u0028h\\u0029\r\\u0027\\u00E9"),
+            (Context::Line, "/a\\b<c>:\"d\"|e,f g\t{return}(h){return}'\u{e9}"),
+            (
+                Context::Filename,
+                "{slash}a{backslash}b{lt}c{gt}{colon}{dblquote}d{dblquote}{bar}e,f g{tab}{return}(h){return}'\u{e9}",
+            ),
+            (Context::NoSpace, "/a\\b<c>:\"d\"|e,f{space}g\t\n(h)\r'\u{e9}"),
+            (Context::Csv, "/a\\b<c>:\"d\"|e{comma}f g\t{return}(h){return}'\u{e9}"),
+        ];
+        for (context, expected) in cases {
+            assert_eq!(encode_substitutions(text, context), expected, "{:?}", context);
+        }
+        assert!(matches!(encode_substitutions("A-B", Context::NetName), Cow::Borrowed("A-B")));
+    }
+}
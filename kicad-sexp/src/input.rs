@@ -0,0 +1,107 @@
+use std::{borrow::Cow, fmt};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputError {
+    /// The input is UTF-16 encoded, which KiCad never writes.
+    Utf16 { big_endian: bool },
+    /// The input is not valid UTF-8.
+    InvalidUtf8 { valid_up_to: usize },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Utf16 { big_endian } => write!(
+                f,
+                "input is UTF-16 ({}) encoded, re-save the file as UTF-8",
+                if *big_endian { "big endian" } else { "little endian" }
+            ),
+            InputError::InvalidUtf8 { valid_up_to } => write!(f, "input is not valid UTF-8 after byte {}", valid_up_to),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+fn detect_utf16(bytes: &[u8]) -> Option<bool> {
+    match bytes {
+        [0xFE, 0xFF, ..] => Some(true),
+        [0xFF, 0xFE, ..] => Some(false),
+        // No BOM, but an ASCII opening paren padded with a NUL byte.
+        [0, b'(', ..] => Some(true),
+        [b'(', 0, ..] => Some(false),
+        _ => None,
+    }
+}
+
+/// Turns raw file contents into text the parser accepts.
+///
+/// Strips a UTF-8 BOM and converts CRLF and lone CR line endings to LF.
+/// Only allocates when line endings need rewriting.
+pub fn normalize(bytes: &[u8]) -> Result<Cow<'_, str>, InputError> {
+    if let Some(big_endian) = detect_utf16(bytes) {
+        return Err(InputError::Utf16 { big_endian });
+    }
+
+    let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+    let text = std::str::from_utf8(bytes).map_err(|e| InputError::InvalidUtf8 { valid_up_to: e.valid_up_to() })?;
+
+    Ok(normalize_line_endings(text))
+}
+
+/// Converts CRLF and lone CR line endings to LF.
+pub fn normalize_line_endings(text: &str) -> Cow<'_, str> {
+    if !text.contains('\r') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\r' {
+            chars.next_if_eq(&'\n');
+            out.push('\n');
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passthrough() {
+        let result = normalize(b"(kicad_sch\n\t(version 20250114)\n)").unwrap();
+
+        assert!(matches!(result, Cow::Borrowed(_)));
+        assert_eq!(result, "(kicad_sch\n\t(version 20250114)\n)");
+    }
+
+    #[test]
+    fn bom() {
+        assert_eq!(normalize(b"\xEF\xBB\xBF(kicad_sch)").unwrap(), "(kicad_sch)");
+    }
+
+    #[test]
+    fn line_endings() {
+        assert_eq!(normalize(b"(a\r\n\t(b)\r(c)\n)").unwrap(), "(a\n\t(b)\n(c)\n)");
+        assert_eq!(normalize(b"(a)\r\r\n").unwrap(), "(a)\n\n");
+    }
+
+    #[test]
+    fn utf16() {
+        assert_eq!(normalize(b"\xFF\xFE(\0k\0"), Err(InputError::Utf16 { big_endian: false }));
+        assert_eq!(normalize(b"\xFE\xFF\0(\0k"), Err(InputError::Utf16 { big_endian: true }));
+        assert_eq!(normalize(b"(\0k\0"), Err(InputError::Utf16 { big_endian: false }));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert_eq!(normalize(b"(a \xC3)"), Err(InputError::InvalidUtf8 { valid_up_to: 3 }));
+    }
+}
@@ -1,5 +1,32 @@
+use std::{borrow::Cow, fmt, ops::Range};
+
 use chumsky::{prelude::*, text::whitespace};
 
+pub mod arena;
+pub mod cache;
+pub mod cst;
+pub mod diagnostic;
+pub mod edit;
+pub mod escape;
+pub mod input;
+pub mod number;
+pub mod ordering;
+mod owned;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod query;
+pub mod scan;
+pub mod schema;
+pub mod source;
+pub mod span;
+pub mod stream;
+pub mod writer;
+#[cfg(feature = "zip")]
+pub mod zip;
+
+pub use edit::SexpBuilder;
+pub use owned::SexpOwned;
+
 #[derive(Clone, Debug)]
 pub enum Sexp<'a> {
     Invalid,
@@ -11,23 +38,78 @@ pub enum Sexp<'a> {
     List(Vec<Self>)
 }
 
+impl<'a> Sexp<'a> {
+    /// The value of an integer literal, or `None` if it does not fit.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Sexp::IntLiteral(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer or float literal.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Sexp::IntLiteral(s) | Sexp::FloatLiteral(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The text of any atom, with the escapes of string literals decoded.
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        match *self {
+            Sexp::StringLiteral(s) => Some(escape::unescape(s)),
+            Sexp::Symbol(s) | Sexp::IntLiteral(s) | Sexp::HexIntLiteral(s) | Sexp::FloatLiteral(s) => {
+                Some(Cow::Borrowed(s))
+            },
+            Sexp::Invalid | Sexp::List(_) => None,
+        }
+    }
+
+    pub fn as_symbol(&self) -> Option<&'a str> {
+        match *self {
+            Sexp::Symbol(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The value of a hex literal, with any underscores taken as digit
+    /// separators, or `None` if it does not fit 128 bits.
+    pub fn as_hex(&self) -> Option<u128> {
+        let groups = self.hex_digits()?;
+        u128::from_str_radix(&groups.replace('_', ""), 16).ok()
+    }
+
+    /// The values of a hex literal's underscore-separated groups, most
+    /// significant first, for masks too wide for [`Sexp::as_hex`]. A literal
+    /// without underscores is a single group.
+    pub fn hex_groups(&self) -> Option<Vec<u64>> {
+        self.hex_digits()?.split('_').map(|group| u64::from_str_radix(group, 16).ok()).collect()
+    }
+
+    fn hex_digits(&self) -> Option<&str> {
+        match self {
+            Sexp::HexIntLiteral(s) => s.strip_prefix("0x"),
+            _ => None,
+        }
+    }
+}
+
 fn parse_end<'src>() -> impl Parser<'src, &'src str, (), extra::Err<Simple<'src, char>>> + Copy {
     choice((
-        whitespace(),
+        whitespace().at_least(1),
         just(')').ignored().rewind(),
+        just('(').ignored().rewind(),
         end(),
     ))
         .then_ignore(whitespace())
 }
 
+/// A backslash and the character after it. KiCad's lexer accepts any
+/// escape, so the literal is kept as written and decoded by
+/// [`escape::unescape`].
 fn parse_escape<'src>() -> impl Parser<'src, &'src str, char, extra::Err<Simple<'src, char>>> + Copy {
-    just('\\')
-        .ignore_then(choice((
-            just('\\'),
-            just('"'),
-            just('n').to('\n'),
-            just('t').to('\t')
-        )))
+    just('\\').ignore_then(any())
 }
 
 fn parse_string<'src>() -> impl Parser<'src, &'src str, &'src str, extra::Err<Simple<'src, char>>> + Copy {
@@ -46,25 +128,32 @@ fn parse_int<'src>() -> impl Parser<'src, &'src str, &'src str, extra::Err<Simpl
         .then_ignore(parse_end())
 }
 
-fn parse_hexint64<'src>() -> impl Parser<'src, &'src str, &'src str, extra::Err<Simple<'src, char>>> + Copy {
+/// A hex literal such as `0x5F3C`, its digits optionally split into groups
+/// by underscores as in the `0x00000000_00000000_55555555_5755f5ff` layer
+/// masks.
+fn parse_hex<'src>() -> impl Parser<'src, &'src str, &'src str, extra::Err<Simple<'src, char>>> + Copy {
     just("0x")
-        .then(text::digits(16).exactly(8))
-        .then(just('_'))
-        .then(text::digits(16).exactly(8))
-        .then(just('_'))
-        .then(text::digits(16).exactly(8))
-        .then(just('_'))
-        .then(text::digits(16).exactly(8))
+        .then(text::digits(16))
+        .then(just('_').then(text::digits(16)).repeated())
         .to_slice()
         .then_ignore(parse_end())
 }
 
+/// A float as C's `strtod` reads it: `1.5`, `.5`, `1.` or `1e-05`, with an
+/// optional sign. Atoms without a dot or an exponent are integers, and
+/// those of hex digits only, like the legacy tstamp `5E123456`, stay
+/// symbols.
 fn parse_float<'src>() -> impl Parser<'src, &'src str, &'src str, extra::Err<Simple<'src, char>>> + Copy {
-    just('-').or_not()
-        .then(text::digits(10))
-        .then(just('.'))
-        .then(text::digits(10))
+    let mantissa = choice((
+        text::digits(10).then(just('.').then(text::digits(10).or_not()).or_not()).ignored(),
+        just('.').then(text::digits(10)).ignored(),
+    ));
+    let exponent = one_of("eE").then(one_of("+-").or_not()).then(text::digits(10));
+    one_of("+-").or_not()
+        .then(mantissa)
+        .then(exponent.or_not())
         .to_slice()
+        .filter(|s: &&str| s.contains('.') || s.contains(['e', 'E']) && !s.bytes().all(|b| b.is_ascii_hexdigit()))
         .then_ignore(parse_end())
 }
 
@@ -76,6 +165,9 @@ fn parse_symbol<'src>() -> impl Parser<'src, &'src str, &'src str, extra::Err<Si
         .then_ignore(parse_end())
 }
 
+/// The chumsky parser behind [`parse`], for use inside other chumsky
+/// parsers. It reads every top-level item, so fragments without a root list
+/// parse too.
 pub fn parser<'a>() -> impl Parser<'a, &'a str, Vec<Sexp<'a>>, extra::Err<Simple<'a, char>>> {
     use Sexp::*;
     recursive(|bf| {
@@ -84,7 +176,7 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Vec<Sexp<'a>>, extra::Err<Simple
                 .map(StringLiteral),
             parse_int()
                 .map(IntLiteral),
-            parse_hexint64()
+            parse_hex()
                 .map(HexIntLiteral),
             parse_float()
                 .map(FloatLiteral),
@@ -98,6 +190,38 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Vec<Sexp<'a>>, extra::Err<Simple
     })
 }
 
+/// A parse error detached from the parser's borrowed error type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte range of the offending input.
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ParseError {
+    pub(crate) fn from_simple(e: Simple<'_, char>) -> Self {
+        let message = match e.found() {
+            Some(c) => format!("unexpected {:?}", c),
+            None => "unexpected end of input".to_string(),
+        };
+        ParseError { span: e.span().into_range(), message }
+    }
+}
+
+/// Parses `src`, failing if the parser had to recover from any error.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = src.len())))]
+pub fn parse(src: &str) -> Result<Vec<Sexp<'_>>, Vec<ParseError>> {
+    parser().parse(src).into_result().map_err(|errors| errors.into_iter().map(ParseError::from_simple).collect())
+}
+
 pub fn pretty_print(sexps: &Vec<Sexp>) {
     for sexp in sexps {
         match sexp {
@@ -126,8 +250,9 @@ mod tests {
 
         assert_eq!(parser.parse("\\\\").unwrap(), '\\');
         assert_eq!(parser.parse("\\\"").unwrap(), '"');
-        assert_eq!(parser.parse("\\n").unwrap(), '\n');
-        assert_eq!(parser.parse("\\t").unwrap(), '\t');
+        assert_eq!(parser.parse("\\n").unwrap(), 'n');
+        assert_eq!(parser.parse("\\r").unwrap(), 'r');
+        assert!(parser.parse("\\").has_errors());
     }
 
     #[test]
@@ -138,6 +263,7 @@ mod tests {
         assert_eq!(parser.parse("\"\\\"\"").unwrap(), "\\\"");
         assert_eq!(parser.parse("\"\\n\"").unwrap(), "\\n");
         assert_eq!(parser.parse("\"\\t\"").unwrap(), "\\t");
+        assert_eq!(parser.parse("\"\\r\\x41\\101\\q\"").unwrap(), "\\r\\x41\\101\\q");
         assert_eq!(parser.parse("\"this is a normal string\" ").unwrap(), "this is a normal string");
     }
 
@@ -146,13 +272,34 @@ mod tests {
         let parser = parse_int();
 
         assert_eq!(parser.parse("12345 ").unwrap(), "12345");
+        assert!(parser.parse("7dfecbba-c7b6").has_errors());
     }
 
     #[test]
-    fn hexint64() {
-        let parser = parse_hexint64();
+    fn hex() {
+        let parser = parse_hex();
 
         assert_eq!(parser.parse("0xdeadbeef_beefdead_44552255_12345678 ").unwrap(), "0xdeadbeef_beefdead_44552255_12345678");
+        for text in ["0x5F3C", "0x0", "0x00010fc_ffffffff"] {
+            assert_eq!(parser.parse(text).into_result(), Ok(text));
+        }
+        for text in ["0x", "0x_1", "0x1_", "0x1__2", "0xfg", "0X1"] {
+            assert!(parser.parse(text).has_errors(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn hex_values() {
+        let result = parse("(tstamps 0x5F3C 0x00010fc_ffffffff 0x00000000_00000000_55555555_5755f5ff 5F3C)").unwrap();
+        let Sexp::List(items) = &result[0] else { unreachable!() };
+        assert!(matches!(items[4], Sexp::Symbol("5F3C")));
+        let values: Vec<_> = items.iter().map(Sexp::as_hex).collect();
+        assert_eq!(values, [None, Some(0x5f3c), Some(0x10fc_ffff_ffff), Some(0x5555_5555_5755_f5ff), None]);
+        assert_eq!(items[2].hex_groups(), Some(vec![0x10fc, 0xffff_ffff]));
+        assert_eq!(items[3].hex_groups(), Some(vec![0, 0, 0x5555_5555, 0x5755_f5ff]));
+
+        let wide = parse("0x10000000000000000_0000000000000000").unwrap();
+        assert_eq!((wide[0].as_hex(), wide[0].hex_groups()), (None, None));
     }
 
     #[test]
@@ -161,6 +308,33 @@ mod tests {
 
         assert_eq!(parser.parse("-123.123456").unwrap(), "-123.123456");
         assert_eq!(parser.parse("321.6543210").unwrap(), "321.6543210");
+        for text in ["1e-05", "-2.5E+3", ".25", "-.5", "1.", "+1.5"] {
+            assert_eq!(parser.parse(text).into_result(), Ok(text));
+        }
+        for text in ["12", ".", "1e", "1.2.3", "e5", "-", "5E123456", "1e5"] {
+            assert!(parser.parse(text).has_errors(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn as_f64() {
+        let result = parse("(at 1e-05 .25 1. 7 \"8\" 0.5mm)").unwrap();
+        let Sexp::List(items) = &result[0] else { unreachable!() };
+        let values: Vec<_> = items.iter().map(Sexp::as_f64).collect();
+        assert_eq!(values, [None, Some(1e-5), Some(0.25), Some(1.0), Some(7.0), None, None]);
+    }
+
+    #[test]
+    fn accessors() {
+        let result = parse("(name \"a \\\"b\\\"\\n\\\\\" F.Cu -12 99999999999999999999 1.5 0x1F)").unwrap();
+        let Sexp::List(items) = &result[0] else { unreachable!() };
+        assert_eq!(items[1].as_str().as_deref(), Some("a \"b\"\n\\"));
+        assert!(matches!(items[2].as_str(), Some(Cow::Borrowed("F.Cu"))));
+        assert_eq!((items[2].as_symbol(), items[1].as_symbol()), (Some("F.Cu"), None));
+        let integers: Vec<_> = items.iter().map(Sexp::as_i64).collect();
+        assert_eq!(integers, [None, None, None, Some(-12), None, None, None]);
+        assert_eq!(items[6].as_str().as_deref(), Some("0x1F"));
+        assert_eq!(result[0].as_str(), None);
     }
 
     #[test]
@@ -170,6 +344,51 @@ mod tests {
         assert_eq!(parser.parse("97-something-bla-9_the \n").unwrap(), "97-something-bla-9_the");
     }
 
+    #[test]
+    fn atom_boundaries() {
+        let result = parser().parse("(uuid 7dfecbba-c7b6-4b01-9f5f-e26c00ad6670)(at 1.5 2)").unwrap();
+
+        assert!(matches!(&result[0], Sexp::List(l) if matches!(l[1], Sexp::Symbol("7dfecbba-c7b6-4b01-9f5f-e26c00ad6670"))));
+        assert!(matches!(&result[1], Sexp::List(l) if matches!(l[..], [_, Sexp::FloatLiteral("1.5"), Sexp::IntLiteral("2")])));
+
+        // An atom may also run into the next list.
+        for src in ["(a b(c))", "(a \"x\"(c))", "(a 1(c))"] {
+            let result = parse(src).unwrap();
+            assert!(matches!(&result[0], Sexp::List(l) if l.len() == 3 && matches!(&l[2], Sexp::List(_))), "{}", src);
+        }
+        assert!(parse("(a b\"x\")").is_err());
+    }
+
+    #[test]
+    fn legacy_tstamps() {
+        let src = "(gr_line (tstamp 5E123456))\n(segment (tstamp 12345678))\n(via (tstamp 1e5) (at 1e+05 -1E5))";
+        let sexps = parse(src).unwrap();
+        let arg = |i: usize, j: usize| match &sexps[i] {
+            Sexp::List(items) => match &items[j] {
+                Sexp::List(args) => args[1..].to_vec(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        assert!(matches!(arg(0, 1)[..], [Sexp::Symbol("5E123456")]));
+        assert!(matches!(arg(1, 1)[..], [Sexp::IntLiteral("12345678")]));
+        assert!(matches!(arg(2, 1)[..], [Sexp::Symbol("1e5")]));
+        assert!(matches!(arg(2, 2)[..], [Sexp::FloatLiteral("1e+05"), Sexp::FloatLiteral("-1E5")]));
+
+        let text = writer::to_string(&sexps);
+        assert!(text.contains("(tstamp 5E123456)") && text.contains("(tstamp 1e5)"), "{}", text);
+        assert_eq!(writer::to_string(&parse(&text).unwrap()), text);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("(a \"b\")").unwrap().len(), 1);
+
+        let errors = parse("(a (b)").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span, 6..6);
+    }
+
     #[test]
     fn kicad_sexp() {
         let parser = parser();
@@ -177,11 +396,11 @@ mod tests {
         let empty_pcb_file = include_str!("../../reference-files/empty/empty.kicad_pcb");
 
         let result = parser.parse(empty_sch_file);
-        assert_eq!(result.has_errors(), false);
-        assert_eq!(result.has_output(), true);
+        assert!(!result.has_errors());
+        assert!(result.has_output());
 
         let result = parser.parse(empty_pcb_file);
-        assert_eq!(result.has_errors(), false);
-        assert_eq!(result.has_output(), true);
+        assert!(!result.has_errors());
+        assert!(result.has_output());
     }
 }
@@ -0,0 +1,321 @@
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+/// Number of decimals KiCad writes at most (1nm resolution in mm).
+pub const MAX_DECIMALS: usize = 6;
+
+const SCALE: i64 = 1_000_000;
+
+fn trim_fraction(mut s: String) -> String {
+    if s.contains('.') {
+        let trimmed = s.trim_end_matches('0').trim_end_matches('.').len();
+        s.truncate(trimmed);
+    }
+    if s == "-0" {
+        s.remove(0);
+    }
+    s
+}
+
+/// Formats a float the way KiCad writes it: at most six decimals, no
+/// trailing zeros and never `-0`.
+pub fn format_float(value: f64) -> String {
+    trim_fraction(format!("{:.*}", MAX_DECIMALS, value))
+}
+
+/// An exact decimal with six fractional digits, matching KiCad's nanometer
+/// resolution. Values read with this type are written back unchanged.
+///
+/// The range is that of an `i64` of millionths, about ±9.2e12. The
+/// operators overflow like `i64`'s, panicking in debug builds and wrapping
+/// in release builds; the `checked_*` methods return `None` instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal(i64);
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal(0);
+
+    /// Creates a decimal from millionths, e.g. nanometers for a value in mm.
+    pub const fn from_millionths(value: i64) -> Self {
+        Decimal(value)
+    }
+
+    pub const fn millionths(self) -> i64 {
+        self.0
+    }
+
+    /// Rounds a float to the nearest representable decimal, saturating at
+    /// the ends of the range.
+    pub fn from_f64(value: f64) -> Self {
+        Decimal((value * SCALE as f64).round() as i64)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+
+    /// The whole number `value`, or `None` if it is out of range.
+    pub const fn checked_from_int(value: i64) -> Option<Self> {
+        match value.checked_mul(SCALE) {
+            Some(value) => Some(Decimal(value)),
+            None => None,
+        }
+    }
+
+    /// `self + other`, or `None` on overflow.
+    pub const fn checked_add(self, other: Decimal) -> Option<Self> {
+        match self.0.checked_add(other.0) {
+            Some(value) => Some(Decimal(value)),
+            None => None,
+        }
+    }
+
+    /// `self - other`, or `None` on overflow.
+    pub const fn checked_sub(self, other: Decimal) -> Option<Self> {
+        match self.0.checked_sub(other.0) {
+            Some(value) => Some(Decimal(value)),
+            None => None,
+        }
+    }
+
+    /// `-self`, or `None` for the smallest value, which has no negation.
+    pub const fn checked_neg(self) -> Option<Self> {
+        match self.0.checked_neg() {
+            Some(value) => Some(Decimal(value)),
+            None => None,
+        }
+    }
+
+    /// `self * factor`, or `None` on overflow.
+    pub const fn checked_mul(self, factor: i64) -> Option<Self> {
+        match self.0.checked_mul(factor) {
+            Some(value) => Some(Decimal(value)),
+            None => None,
+        }
+    }
+}
+
+/// Overflows like `i64` addition; see [`Decimal::checked_add`].
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        Decimal(self.0 + other.0)
+    }
+}
+
+/// Overflows like `i64` subtraction; see [`Decimal::checked_sub`].
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        Decimal(self.0 - other.0)
+    }
+}
+
+/// Overflows like `i64` negation; see [`Decimal::checked_neg`].
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal(-self.0)
+    }
+}
+
+/// Overflows like `i64` multiplication; see [`Decimal::checked_mul`].
+impl Mul<i64> for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, factor: i64) -> Decimal {
+        Decimal(self.0 * factor)
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Decimal {
+        iter.fold(Decimal::ZERO, Add::add)
+    }
+}
+
+/// Overflows for whole numbers beyond the range; see
+/// [`Decimal::checked_from_int`].
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Decimal(value * SCALE)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseDecimalError {
+    /// The input is not of the form `[-]digits[.digits]`.
+    Invalid,
+    /// The input has more than six fractional digits.
+    TooPrecise,
+    /// The value does not fit.
+    Overflow,
+}
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDecimalError::Invalid => write!(f, "invalid decimal literal"),
+            ParseDecimalError::TooPrecise => write!(f, "decimal literal has more than {} fractional digits", MAX_DECIMALS),
+            ParseDecimalError::Overflow => write!(f, "decimal literal out of range"),
+        }
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (s, exponent) = match s.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().map_err(|_| ParseDecimalError::Invalid)?),
+            None => (s, 0),
+        };
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (int.is_empty() && frac.is_empty()) || !is_digits(int) || !is_digits(frac) {
+            return Err(ParseDecimalError::Invalid);
+        }
+        // Move the decimal point by the exponent, within the digits.
+        let shifted;
+        let (int, frac) = if exponent == 0 {
+            (int, frac)
+        } else {
+            let digits = format!("{}{}", int, frac);
+            let point = int.len() as i64 + exponent as i64;
+            if digits.bytes().all(|b| b == b'0') {
+                return Ok(Decimal::ZERO);
+            }
+            if point > 19 {
+                return Err(ParseDecimalError::Overflow);
+            }
+            if point < -(MAX_DECIMALS as i64) {
+                return Err(ParseDecimalError::TooPrecise);
+            }
+            let lead = (-point).max(0) as usize;
+            let tail = (point - digits.len() as i64).max(0) as usize;
+            shifted = format!("{}{}{}", "0".repeat(lead), digits, "0".repeat(tail));
+            shifted.split_at(point.max(0) as usize)
+        };
+        let frac = frac.trim_end_matches('0');
+        if frac.len() > MAX_DECIMALS {
+            return Err(ParseDecimalError::TooPrecise);
+        }
+
+        let int: i64 = if int.is_empty() { 0 } else { int.parse().map_err(|_| ParseDecimalError::Overflow)? };
+        let frac: i64 = format!("{:0<width$}", frac, width = MAX_DECIMALS).parse().unwrap();
+        let value = int
+            .checked_mul(SCALE)
+            .and_then(|v| v.checked_add(frac))
+            .ok_or(ParseDecimalError::Overflow)?;
+
+        Ok(Decimal(if negative { -value } else { value }))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let s = format!("{}{}.{:06}", sign, abs / SCALE as u64, abs % SCALE as u64);
+        f.write_str(&trim_fraction(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float() {
+        assert_eq!(format_float(1.6), "1.6");
+        assert_eq!(format_float(12.0), "12");
+        assert_eq!(format_float(0.000_000_4), "0");
+        assert_eq!(format_float(-0.000_000_4), "0");
+        assert_eq!(format_float(-0.0), "0");
+        assert_eq!(format_float(-1.2345678), "-1.234568");
+        assert_eq!(format_float(100.0000001), "100");
+    }
+
+    #[test]
+    fn float_round_trip() {
+        // Every value in KiCad's range of 32-bit nanometers, ±2147.483647 mm,
+        // is written back as read after a trip through f64.
+        let extremes = [-i64::from(i32::MAX), i64::from(i32::MAX), -1, 1, 999_999];
+        let sweep = (-i64::from(i32::MAX)..=i64::from(i32::MAX)).step_by(9_973);
+        for nm in extremes.into_iter().chain(sweep) {
+            let text = Decimal::from_millionths(nm).to_string();
+            assert_eq!(format_float(text.parse().unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn decimal_parse() {
+        assert_eq!("1.6".parse(), Ok(Decimal::from_millionths(1_600_000)));
+        assert_eq!("-0.000001".parse(), Ok(Decimal::from_millionths(-1)));
+        assert_eq!("12".parse(), Ok(Decimal::from_millionths(12_000_000)));
+        assert_eq!("3.000000000".parse(), Ok(Decimal::from_millionths(3_000_000)));
+        assert_eq!("0.0000001".parse::<Decimal>(), Err(ParseDecimalError::TooPrecise));
+        assert_eq!("1.2.3".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        assert_eq!("-".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+
+        assert_eq!(".25".parse(), Ok(Decimal::from_millionths(250_000)));
+        assert_eq!("1.".parse(), Ok(Decimal::from_millionths(1_000_000)));
+        assert_eq!("1e-05".parse(), Ok(Decimal::from_millionths(10)));
+        assert_eq!("-2.5E+3".parse(), Ok(Decimal::from_millionths(-2_500_000_000)));
+        assert_eq!("12.5e-1".parse(), Ok(Decimal::from_millionths(1_250_000)));
+        assert_eq!("1e-7".parse::<Decimal>(), Err(ParseDecimalError::TooPrecise));
+        assert_eq!("1e30".parse::<Decimal>(), Err(ParseDecimalError::Overflow));
+        assert_eq!("1e".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        assert_eq!("1e+-5".parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        assert_eq!("0e-999999".parse(), Ok(Decimal::ZERO));
+    }
+
+    #[test]
+    fn decimal_round_trip() {
+        for s in ["1.6", "-123.123456", "0.1", "-0.05", "0", "2000"] {
+            assert_eq!(s.parse::<Decimal>().unwrap().to_string(), s);
+        }
+        assert_eq!("-0.0".parse::<Decimal>().unwrap().to_string(), "0");
+        assert_eq!("15.000000".parse::<Decimal>().unwrap().to_string(), "15");
+    }
+
+    #[test]
+    fn decimal_ops() {
+        let tenth: Decimal = "0.1".parse().unwrap();
+        assert_eq!(tenth + "0.2".parse().unwrap(), "0.3".parse().unwrap());
+        assert_eq!(std::iter::repeat_n(tenth, 10).sum::<Decimal>(), Decimal::from(1));
+        assert_eq!((-(tenth * 3) - tenth).to_string(), "-0.4");
+
+        let max = Decimal::from_millionths(i64::MAX);
+        let min = Decimal::from_millionths(i64::MIN);
+        assert_eq!(tenth.checked_add(tenth), Some(Decimal::from_millionths(200_000)));
+        assert_eq!(max.checked_add(tenth), None);
+        assert_eq!(min.checked_sub(tenth), None);
+        assert_eq!(min.checked_neg(), None);
+        assert_eq!(max.checked_neg(), Some(Decimal::from_millionths(-i64::MAX)));
+        assert_eq!(tenth.checked_mul(-3), Some(-(tenth * 3)));
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(Decimal::checked_from_int(2), Some(Decimal::from(2)));
+        assert_eq!(Decimal::checked_from_int(i64::MAX / 1000), None);
+        assert_eq!(Decimal::from_f64(1e300), max);
+    }
+
+    #[test]
+    fn decimal_f64() {
+        assert_eq!(Decimal::from_f64(0.1 + 0.2).to_string(), "0.3");
+        assert_eq!(Decimal::from_millionths(-2_540_000).to_f64(), -2.54);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::Sexp;
+
+/// How the items of a document are ordered before writing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OrderPolicy {
+    /// Keep the order the items were read in.
+    #[default]
+    Preserve,
+    /// Sort items by their UUID.
+    ByUuid,
+    /// Group items by type in KiCad's save order, then sort each group by UUID.
+    ByType,
+}
+
+const PCB_ORDER: &[&str] = &[
+    "footprint",
+    "gr_line", "gr_rect", "gr_circle", "gr_arc", "gr_poly", "gr_curve",
+    "gr_text", "gr_text_box", "image", "table", "dimension", "target",
+    "segment", "arc", "via",
+    "zone",
+    "generated", "group",
+];
+
+/// Items of a footprint in the order KiCad's footprint editor saves them.
+const FOOTPRINT_ORDER: &[&str] = &[
+    "property",
+    "fp_line", "fp_rect", "fp_circle", "fp_arc", "fp_poly", "fp_curve",
+    "image", "fp_text", "fp_text_box", "table", "dimension",
+    "pad",
+    "zone",
+    "group",
+];
+
+const SCH_ORDER: &[&str] = &[
+    "junction", "no_connect", "bus_entry",
+    "wire", "bus",
+    "polyline", "arc", "circle", "rectangle", "bezier", "image",
+    "text", "text_box", "table",
+    "label", "global_label", "hierarchical_label", "rule_area", "netclass_flag",
+    "symbol", "sheet",
+];
+
+fn tag<'a>(sexp: &Sexp<'a>) -> Option<&'a str> {
+    match sexp {
+        Sexp::List(children) => match children.first() {
+            Some(Sexp::Symbol(tag)) => Some(tag),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the `uuid` (or legacy `tstamp`) of an item, if it has one. Legacy
+/// tstamps are bare hex like `5E123456`, so any atom is taken by its text.
+pub fn item_uuid<'a>(sexp: &Sexp<'a>) -> Option<&'a str> {
+    let Sexp::List(children) = sexp else {
+        return None;
+    };
+    children.iter().find_map(|child| match child {
+        Sexp::List(fields) => match fields.as_slice() {
+            [Sexp::Symbol("uuid" | "tstamp"), id] => match *id {
+                Sexp::Symbol(id)
+                | Sexp::StringLiteral(id)
+                | Sexp::IntLiteral(id)
+                | Sexp::HexIntLiteral(id)
+                | Sexp::FloatLiteral(id) => Some(id),
+                Sexp::Invalid | Sexp::List(_) => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn type_rank(order: &[&str], sexp: &Sexp) -> usize {
+    tag(sexp)
+        .and_then(|tag| order.iter().position(|known| *known == tag))
+        .unwrap_or(order.len())
+}
+
+/// Reorders the items (children carrying a UUID) of a document root list
+/// according to `policy`. Header sections without a UUID keep their place.
+/// The items of a board's footprints are reordered too.
+pub fn reorder(root: &mut Sexp, policy: OrderPolicy) {
+    let board = tag(root) == Some("kicad_pcb");
+    let order = match tag(root) {
+        Some("kicad_pcb") => PCB_ORDER,
+        Some("footprint" | "module") => FOOTPRINT_ORDER,
+        Some("kicad_sch") => SCH_ORDER,
+        _ => &[],
+    };
+    let Sexp::List(children) = root else {
+        return;
+    };
+
+    let slots: Vec<usize> = children
+        .iter()
+        .enumerate()
+        .filter(|(_, child)| item_uuid(child).is_some())
+        .map(|(i, _)| i)
+        .collect();
+    let mut items: Vec<Sexp> = slots
+        .iter()
+        .map(|&i| std::mem::replace(&mut children[i], Sexp::Invalid))
+        .collect();
+
+    let by_uuid = |a: &Sexp, b: &Sexp| item_uuid(a).cmp(&item_uuid(b));
+    match policy {
+        OrderPolicy::Preserve => {},
+        OrderPolicy::ByUuid => items.sort_by(by_uuid),
+        OrderPolicy::ByType => items.sort_by(|a, b| {
+            type_rank(order, a).cmp(&type_rank(order, b))
+                .then_with(|| tag(a).cmp(&tag(b)))
+                .then_with(|| by_uuid(a, b))
+        }),
+    }
+
+    for (slot, item) in slots.into_iter().zip(items) {
+        children[slot] = item;
+    }
+    if board {
+        let footprints = children.iter_mut().filter(|child| matches!(tag(child), Some("footprint" | "module")));
+        footprints.for_each(|footprint| reorder(footprint, policy));
+    }
+}
+
+/// Applies [`reorder`] to every top level list.
+pub fn reorder_all(sexps: &mut [Sexp], policy: OrderPolicy) {
+    for sexp in sexps {
+        reorder(sexp, policy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+    use crate::parser;
+
+    const BOARD: &str = "(kicad_pcb (version 20241229) \
+        (via (at 1 1) (uuid \"c\")) \
+        (net 0 \"\") \
+        (segment (start 0 0) (uuid \"b\")) \
+        (footprint \"R\" (uuid \"d\")) \
+        (segment (start 1 1) (uuid \"a\")))";
+
+    fn order(root: &Sexp) -> Vec<String> {
+        let Sexp::List(children) = root else {
+            panic!("not a list");
+        };
+        children
+            .iter()
+            .filter_map(|child| match child {
+                Sexp::List(fields) => match fields.first() {
+                    Some(Sexp::Symbol(tag)) => Some(match item_uuid(child) {
+                        Some(uuid) => format!("{tag}:{uuid}"),
+                        None => tag.to_string(),
+                    }),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn preserve() {
+        let mut sexps = parser().parse(BOARD).unwrap();
+        reorder_all(&mut sexps, OrderPolicy::Preserve);
+
+        assert_eq!(order(&sexps[0]), ["version", "via:c", "net", "segment:b", "footprint:d", "segment:a"]);
+    }
+
+    #[test]
+    fn by_uuid() {
+        let mut sexps = parser().parse(BOARD).unwrap();
+        reorder_all(&mut sexps, OrderPolicy::ByUuid);
+
+        assert_eq!(order(&sexps[0]), ["version", "segment:a", "net", "segment:b", "via:c", "footprint:d"]);
+    }
+
+    #[test]
+    fn footprint_by_type() {
+        let src = "(footprint \"R\" (layer \"F.Cu\") \
+            (pad \"1\" smd rect (uuid \"a\")) \
+            (fp_line (start 0 0) (end 1 0) (uuid \"b\")) \
+            (property \"Reference\" \"R1\" (uuid \"c\")) \
+            (fp_text user \"x\" (uuid \"d\")) \
+            (model \"r.step\"))";
+        let mut sexps = parser().parse(src).unwrap();
+        reorder_all(&mut sexps, OrderPolicy::ByType);
+
+        assert_eq!(order(&sexps[0]), ["layer", "property:c", "fp_line:b", "fp_text:d", "pad:a", "model"]);
+    }
+
+    #[test]
+    fn legacy_tstamps() {
+        let src = "(kicad_pcb (gr_line (tstamp 5E123456)) (segment (tstamp 12345678)) (via (tstamp 0A1B2C3D)))";
+        let mut sexps = parser().parse(src).unwrap();
+        reorder_all(&mut sexps, OrderPolicy::ByUuid);
+
+        assert_eq!(order(&sexps[0]), ["via:0A1B2C3D", "segment:12345678", "gr_line:5E123456"]);
+    }
+
+    #[test]
+    fn board_footprints_by_type() {
+        let src = "(kicad_pcb (version 20241229) \
+            (segment (start 0 0) (uuid \"b\")) \
+            (footprint \"R\" (layer \"F.Cu\") (uuid \"a\") \
+                (pad \"1\" smd rect (uuid \"c\")) \
+                (property \"Reference\" \"R1\" (uuid \"d\"))))";
+        let mut sexps = parser().parse(src).unwrap();
+        reorder_all(&mut sexps, OrderPolicy::ByType);
+
+        assert_eq!(order(&sexps[0]), ["version", "footprint:a", "segment:b"]);
+        let Sexp::List(children) = &sexps[0] else {
+            panic!("not a list");
+        };
+        assert_eq!(order(&children[2]), ["layer", "uuid", "property:d", "pad:c"]);
+    }
+
+    #[test]
+    fn by_type() {
+        let mut sexps = parser().parse(BOARD).unwrap();
+        reorder_all(&mut sexps, OrderPolicy::ByType);
+
+        assert_eq!(order(&sexps[0]), ["version", "footprint:d", "net", "segment:a", "segment:b", "via:c"]);
+    }
+}
@@ -0,0 +1,100 @@
+//! A [`Sexp`] that owns its text, for keeping parse results after the
+//! source buffer is gone or moving them between threads.
+
+use std::fmt;
+
+use crate::Sexp;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SexpOwned {
+    Invalid,
+    Symbol(String),
+    /// Raw escaped text without the quotes, as in [`Sexp::StringLiteral`].
+    StringLiteral(String),
+    IntLiteral(String),
+    HexIntLiteral(String),
+    FloatLiteral(String),
+    List(Vec<SexpOwned>),
+}
+
+impl SexpOwned {
+    /// A borrowed view, for use with the typed models and the writer.
+    pub fn as_sexp(&self) -> Sexp<'_> {
+        match self {
+            SexpOwned::Invalid => Sexp::Invalid,
+            SexpOwned::Symbol(s) => Sexp::Symbol(s),
+            SexpOwned::StringLiteral(s) => Sexp::StringLiteral(s),
+            SexpOwned::IntLiteral(s) => Sexp::IntLiteral(s),
+            SexpOwned::HexIntLiteral(s) => Sexp::HexIntLiteral(s),
+            SexpOwned::FloatLiteral(s) => Sexp::FloatLiteral(s),
+            SexpOwned::List(items) => Sexp::List(items.iter().map(SexpOwned::as_sexp).collect()),
+        }
+    }
+}
+
+impl Sexp<'_> {
+    /// Copies the tree into owned strings.
+    pub fn to_owned(&self) -> SexpOwned {
+        match self {
+            Sexp::Invalid => SexpOwned::Invalid,
+            Sexp::Symbol(s) => SexpOwned::Symbol(s.to_string()),
+            Sexp::StringLiteral(s) => SexpOwned::StringLiteral(s.to_string()),
+            Sexp::IntLiteral(s) => SexpOwned::IntLiteral(s.to_string()),
+            Sexp::HexIntLiteral(s) => SexpOwned::HexIntLiteral(s.to_string()),
+            Sexp::FloatLiteral(s) => SexpOwned::FloatLiteral(s.to_string()),
+            Sexp::List(items) => SexpOwned::List(items.iter().map(Sexp::to_owned).collect()),
+        }
+    }
+
+    /// Like [`Sexp::to_owned`], reusing the list allocations.
+    pub fn into_owned(self) -> SexpOwned {
+        match self {
+            Sexp::List(items) => SexpOwned::List(items.into_iter().map(Sexp::into_owned).collect()),
+            atom => atom.to_owned(),
+        }
+    }
+}
+
+impl From<Sexp<'_>> for SexpOwned {
+    fn from(sexp: Sexp<'_>) -> Self {
+        sexp.into_owned()
+    }
+}
+
+impl fmt::Display for SexpOwned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_sexp().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owned() {
+        let owned: Vec<SexpOwned> = {
+            let src = String::from(include_str!("../../reference-files/empty/empty.kicad_pcb"));
+            crate::parse(&src).unwrap().into_iter().map(Sexp::into_owned).collect()
+        };
+        let expected = include_str!("../../reference-files/empty/empty.kicad_pcb");
+        let expected = crate::writer::to_string(&crate::parse(expected).unwrap());
+
+        let written = std::thread::spawn(move || {
+            crate::writer::to_string(&owned.iter().map(SexpOwned::as_sexp).collect::<Vec<_>>())
+        });
+        assert_eq!(written.join().unwrap(), expected);
+
+        let sexp = crate::parse("(a \"b\\\"c\" 1 2.5)").unwrap().remove(0);
+        assert_eq!(sexp.to_owned(), sexp.clone().into_owned());
+        assert_eq!(
+            sexp.into_owned(),
+            SexpOwned::List(vec![
+                SexpOwned::Symbol("a".into()),
+                SexpOwned::StringLiteral("b\\\"c".into()),
+                SexpOwned::IntLiteral("1".into()),
+                SexpOwned::FloatLiteral("2.5".into()),
+            ])
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Parses a document's root list on several threads, splitting it between
+//! top-level children such as footprints, segments and zones. Boards of
+//! thousands of tracks spend nearly all of their parse time in those.
+//!
+//! The result is the same tree [`crate::parse`] builds. Documents that are
+//! not a single root list, and chunks that fail to parse, are parsed again
+//! in one piece so that errors and their spans are also the same.
+
+use std::{num::NonZeroUsize, thread};
+
+use chumsky::Parser;
+
+use crate::{parser, scan, ParseError, Sexp};
+
+/// Smallest share of the input worth a thread of its own.
+const MIN_CHUNK: usize = 1 << 20;
+
+/// Like [`crate::parse`], on as many threads as the machine has and the
+/// input is large enough for.
+pub fn parse(src: &str) -> Result<Vec<Sexp<'_>>, Vec<ParseError>> {
+    let jobs = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    parse_with(src, jobs.min(src.len() / MIN_CHUNK))
+}
+
+/// Like [`crate::parse`], splitting the root list into at most `jobs`
+/// chunks parsed on threads of their own.
+pub fn parse_with(src: &str, jobs: usize) -> Result<Vec<Sexp<'_>>, Vec<ParseError>> {
+    match chunks(src, jobs) {
+        Some(chunks) if chunks.len() > 1 => {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("parse", bytes = src.len(), chunks = chunks.len()).entered();
+            let parsed: Vec<_> = thread::scope(|scope| {
+                let handles: Vec<_> = chunks[1..]
+                    .iter()
+                    .map(|&chunk| scope.spawn(move || parser().parse(chunk).into_result().ok()))
+                    .collect();
+                let first = parser().parse(chunks[0]).into_result().ok();
+                std::iter::once(first).chain(handles.into_iter().map(|handle| handle.join().ok().flatten())).collect()
+            });
+            match parsed.into_iter().collect::<Option<Vec<_>>>() {
+                Some(parts) => Ok(vec![Sexp::List(parts.into_iter().flatten().collect())]),
+                None => crate::parse(src),
+            }
+        },
+        _ => crate::parse(src),
+    }
+}
+
+/// The root list's contents split at its children into at most `jobs`
+/// pieces of similar size, or `None` if `src` is not one list surrounded by
+/// whitespace.
+fn chunks(src: &str, jobs: usize) -> Option<Vec<&str>> {
+    let open = src.find('(')?;
+    let close = scan::skip_list(src.as_bytes(), open).checked_sub(1)?;
+    let whitespace = |s: &str| s.bytes().all(|b| b.is_ascii_whitespace());
+    if !whitespace(&src[..open]) || !whitespace(&src[close + 1..]) || src.as_bytes()[close] != b')' || jobs < 2 {
+        return None;
+    }
+    let size = (close - open) / jobs + 1;
+    let mut bounds = vec![open + 1];
+    for child in scan::children(src)? {
+        // Only an unterminated root has children past its end.
+        if child.span.end > close {
+            return None;
+        }
+        // A chunk must not end in an atom that ran into the child.
+        let after = src.as_bytes()[child.span.start - 1];
+        if child.span.start - bounds[bounds.len() - 1] >= size && (after == b')' || after.is_ascii_whitespace()) {
+            bounds.push(child.span.start);
+        }
+    }
+    bounds.push(close);
+    Some(bounds.windows(2).map(|pair| &src[pair[0]..pair[1]]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    fn owned(sexps: &[Sexp]) -> Vec<crate::SexpOwned> {
+        sexps.iter().map(Sexp::to_owned).collect()
+    }
+
+    #[test]
+    fn same_tree() {
+        let expected = owned(&crate::parse(DEMO).unwrap());
+        for jobs in [1, 2, 3, 8, 64, 10_000] {
+            assert_eq!(owned(&parse_with(DEMO, jobs).unwrap()), expected, "{} jobs", jobs);
+        }
+        assert!(chunks(DEMO, 8).unwrap().len() > 4);
+        assert_eq!(chunks(DEMO, 8).unwrap().concat(), DEMO.trim_end()[1..DEMO.trim_end().len() - 1]);
+        assert_eq!(owned(&parse(DEMO).unwrap()), expected);
+    }
+
+    #[test]
+    fn boundaries() {
+        // Atoms ending at a child only split where whitespace or a list
+        // ends before it.
+        for src in ["(a (b) x(c))", "(\t(##)-(-)a)", "\t\n(1a()1)", "(a (b) x\"y\"(c))", "(a (b)(c) 1(d))"] {
+            let expected = crate::parse(src).map(|sexps| owned(&sexps));
+            assert_eq!(parse_with(src, 4).map(|sexps| owned(&sexps)), expected, "{:?}", src);
+        }
+        assert_eq!(chunks("(a (b) x(c))", 8).unwrap(), ["a ", "(b) x(c)"]);
+        assert_eq!(chunks("(a (b)(c) (d))", 8).unwrap(), ["a ", "(b)", "(c) ", "(d)"]);
+    }
+
+    #[test]
+    fn fallback() {
+        for src in ["(a (b 1) (c 2)) (d)", "x (a (b) (c))", "(a (b) (c)", "(a (b) (c))) ", ""] {
+            assert!(chunks(src, 4).is_none(), "{:?}", src);
+            assert_eq!(parse_with(src, 4).map(|sexps| owned(&sexps)), crate::parse(src).map(|sexps| owned(&sexps)));
+        }
+        let broken = "(a (b 1) (c \"unterminated) (d))";
+        assert_eq!(parse_with(broken, 4).unwrap_err(), crate::parse(broken).unwrap_err());
+        let invalid = "(a (b 1) (c ) x\" (d 2))";
+        assert_eq!(parse_with(invalid, 4).unwrap_err(), crate::parse(invalid).unwrap_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Selecting lists by tag path, in the style of CSS selectors:
+//!
+//! ```
+//! # use kicad_sexp::{query::Query, Sexp};
+//! let tree = kicad_sexp::parse("(kicad_pcb (footprint \"R\" (pad \"1\") (pad \"2\")))").unwrap();
+//! assert_eq!(tree[0].query("kicad_pcb > footprint > pad").unwrap().count(), 2);
+//! assert_eq!(Query::new().tag("footprint").child("pad").select(&tree).count(), 2);
+//! ```
+//!
+//! `a > b` selects `b` lists directly inside an `a` list, `a b` selects `b`
+//! lists anywhere inside one, and `*` matches any tag. The first tag matches
+//! anywhere in the tree, including the roots, unless the query starts with
+//! `>`, which anchors it to the roots. Matches come in document order.
+
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use crate::Sexp;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid query: {}", self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Step {
+    /// `None` for `*`.
+    tag: Option<String>,
+    /// Whether the list must be a direct child of the previous step's.
+    child: bool,
+}
+
+impl Step {
+    /// Whether a list with leading symbol `tag` matches.
+    fn matches(&self, tag: Option<&str>) -> bool {
+        self.tag.as_deref().is_none_or(|step| tag == Some(step))
+    }
+}
+
+/// A tag path, built with [`Query::tag`] and [`Query::child`] or parsed
+/// from a selector string.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    steps: Vec<Step>,
+}
+
+fn step_tag(tag: &str) -> Option<String> {
+    (tag != "*").then(|| tag.to_string())
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    /// Lists tagged `tag` anywhere inside the current matches.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.steps.push(Step { tag: step_tag(tag), child: false });
+        self
+    }
+
+    /// Lists tagged `tag` directly inside the current matches, or roots
+    /// tagged `tag` if this is the first step.
+    pub fn child(mut self, tag: &str) -> Self {
+        self.steps.push(Step { tag: step_tag(tag), child: true });
+        self
+    }
+
+    /// Iterates over the matching lists in `roots` and their descendants.
+    pub fn select<'q, 's, 'a>(&'q self, roots: &'s [Sexp<'a>]) -> Matches<'q, 's, 'a> {
+        Matches::new(Cow::Borrowed(self), roots)
+    }
+
+    /// Whether the last list of `path` matches, with the rest of `path`
+    /// being its ancestors from the root down. Lists are given by their
+    /// leading symbols.
+    pub(crate) fn matches_path(&self, path: &[Option<&str>]) -> bool {
+        Query::matches(&self.steps, path)
+    }
+
+    fn matches(steps: &[Step], path: &[Option<&str>]) -> bool {
+        let (Some((step, rest)), Some((node, ancestors))) = (steps.split_last(), path.split_last()) else {
+            return false;
+        };
+        if !step.matches(*node) {
+            return false;
+        }
+        match (rest.is_empty(), step.child) {
+            (true, child) => !child || ancestors.is_empty(),
+            (false, true) => Query::matches(rest, ancestors),
+            (false, false) => (1..=ancestors.len()).rev().any(|i| Query::matches(rest, &ancestors[..i])),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |message: &str| Err(QueryError { message: message.to_string() });
+        let mut query = Query::new();
+        let mut child = false;
+        for token in s.replace('>', " > ").split_whitespace() {
+            if token == ">" {
+                if child {
+                    return error("`>` without a tag before it");
+                }
+                child = true;
+            } else {
+                query = if child { query.child(token) } else { query.tag(token) };
+                child = false;
+            }
+        }
+        if child {
+            return error("`>` without a tag after it");
+        }
+        if query.steps.is_empty() {
+            return error("no tags");
+        }
+        Ok(query)
+    }
+}
+
+/// Iterator over the lists matching a [`Query`], see [`Query::select`].
+#[derive(Clone, Debug)]
+pub struct Matches<'q, 's, 'a> {
+    query: Cow<'q, Query>,
+    /// Nodes left to visit, with their depth.
+    stack: Vec<(&'s Sexp<'a>, usize)>,
+    /// Tags of the ancestors of the next node on the stack.
+    path: Vec<Option<&'a str>>,
+}
+
+impl<'q, 's, 'a> Matches<'q, 's, 'a> {
+    fn new(query: Cow<'q, Query>, roots: &'s [Sexp<'a>]) -> Self {
+        Matches { query, stack: roots.iter().rev().map(|root| (root, 0)).collect(), path: Vec::new() }
+    }
+}
+
+impl<'s, 'a> Iterator for Matches<'_, 's, 'a> {
+    type Item = &'s Sexp<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, depth)) = self.stack.pop() {
+            let Sexp::List(items) = node else {
+                continue;
+            };
+            self.path.truncate(depth);
+            self.path.push(items.first().and_then(Sexp::as_symbol));
+            self.stack.extend(items.iter().rev().map(|item| (item, depth + 1)));
+            if self.query.matches_path(&self.path) {
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Sexp<'a> {
+    /// Iterates over the lists in this tree matching `selector`, see the
+    /// [module docs](self).
+    pub fn query(&self, selector: &str) -> Result<Matches<'static, '_, 'a>, QueryError> {
+        Ok(Matches::new(Cow::Owned(selector.parse()?), std::slice::from_ref(self)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "(kicad_pcb (net 1 \"GND\") \
+        (footprint \"R\" (property \"Reference\" \"R1\") (pad \"1\" (net 1 \"GND\")) (pad \"2\")) \
+        (footprint \"C\" (property \"Reference\" \"C1\") (group (pad \"3\"))))";
+
+    fn first_args<'s>(matches: impl Iterator<Item = &'s Sexp<'s>>) -> Vec<String> {
+        matches.map(|m| match m {
+            Sexp::List(items) => items.get(1).map(|arg| arg.to_string()).unwrap_or_default(),
+            _ => unreachable!(),
+        }).collect()
+    }
+
+    #[test]
+    fn query() {
+        let tree = crate::parse(SRC).unwrap();
+        let root = &tree[0];
+
+        assert_eq!(first_args(root.query("kicad_pcb > footprint > pad").unwrap()), ["\"1\"", "\"2\""]);
+        assert_eq!(first_args(root.query("footprint pad").unwrap()), ["\"1\"", "\"2\"", "\"3\""]);
+        assert_eq!(first_args(root.query("net").unwrap()), ["1", "1"]);
+        assert_eq!(first_args(root.query("> kicad_pcb>net").unwrap()), ["1"]);
+        assert_eq!(root.query("> footprint").unwrap().count(), 0);
+        assert_eq!(root.query("footprint > *").unwrap().count(), 5);
+        // Each list once, even where several ancestors match.
+        assert_eq!(root.query("* *").unwrap().count(), 10);
+
+        let query = Query::new().tag("footprint").child("property");
+        assert_eq!(first_args(query.select(&tree)), ["\"Reference\"", "\"Reference\""]);
+        assert_eq!(query, "footprint > property".parse().unwrap());
+        assert_eq!(Query::new().child("kicad_pcb").child("net"), "> kicad_pcb > net".parse().unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        for (selector, message) in [
+            ("", "no tags"),
+            ("a >", "`>` without a tag after it"),
+            ("a > > b", "`>` without a tag before it"),
+        ] {
+            assert_eq!(selector.parse::<Query>(), Err(QueryError { message: message.to_string() }));
+        }
+    }
+}
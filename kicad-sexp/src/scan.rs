@@ -0,0 +1,154 @@
+//! Finds the child lists of a document's root without building a tree, so
+//! that callers interested in a few tags can parse just those.
+//!
+//! The scanner only tracks parentheses and strings; anything it skips is
+//! not validated.
+
+use std::ops::Range;
+
+/// A direct child list of the scanned list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Child<'a> {
+    src: &'a str,
+    pub tag: &'a str,
+    /// The list's text, including its parentheses.
+    pub text: &'a str,
+    /// Byte range of `text` in the scanned source.
+    pub span: Range<usize>,
+}
+
+/// Iterator over the child lists of one list, see [`children`].
+#[derive(Clone, Debug)]
+pub struct Children<'a> {
+    src: &'a str,
+    tag: &'a str,
+    pos: usize,
+}
+
+impl<'a> Child<'a> {
+    /// Scans this list's own children, with spans still relative to the
+    /// original source.
+    pub fn children(&self) -> Children<'a> {
+        Children { src: self.src, tag: self.tag, pos: self.span.start + 1 + self.tag.len() }
+    }
+}
+
+impl<'a> Children<'a> {
+    /// Tag of the list being scanned.
+    pub fn tag(&self) -> &'a str {
+        self.tag
+    }
+}
+
+fn is_delimiter(b: u8) -> bool {
+    b.is_ascii_whitespace() || matches!(b, b'(' | b')' | b'"')
+}
+
+/// Index just past the string starting at `start`, or the end of input.
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Index just past the list opened at `start`, or the end of input.
+pub(crate) fn skip_list(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i = skip_string(bytes, i);
+                continue;
+            },
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            },
+            _ => {},
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// The symbol following the `(` at `open`, and the index after it.
+fn tag_at(src: &str, open: usize) -> (&str, usize) {
+    let bytes = src.as_bytes();
+    let start = open + 1;
+    let end = (start..bytes.len()).find(|&i| is_delimiter(bytes[i])).unwrap_or(bytes.len());
+    (&src[start..end], end)
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = Child<'a>;
+
+    fn next(&mut self) -> Option<Child<'a>> {
+        let bytes = self.src.as_bytes();
+        while self.pos < bytes.len() {
+            match bytes[self.pos] {
+                b'"' => self.pos = skip_string(bytes, self.pos),
+                b')' => {
+                    self.pos = bytes.len();
+                    return None;
+                },
+                b'(' => {
+                    let start = self.pos;
+                    self.pos = skip_list(bytes, start);
+                    let tag = tag_at(self.src, start).0;
+                    return Some(Child { src: self.src, tag, text: &self.src[start..self.pos], span: start..self.pos });
+                },
+                _ => self.pos += 1,
+            }
+        }
+        None
+    }
+}
+
+/// Scans the child lists of the first list in `src`; `None` if there is no
+/// list. Spans are relative to `src`.
+pub fn children(src: &str) -> Option<Children<'_>> {
+    let open = src.find('(')?;
+    let (tag, pos) = tag_at(src, open);
+    Some(Children { src, tag, pos })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan() {
+        let src = "(kicad_pcb (version 1) \"(not a list)\" (net 0 \"\")\n\
+                   \t(net 1 \"a)\\\"b\") (footprint x (at 1 2))) (after)";
+        let children = children(src).unwrap();
+
+        assert_eq!(children.tag(), "kicad_pcb");
+        let found: Vec<_> = children.map(|child| (child.tag, child.text)).collect();
+        assert_eq!(
+            found,
+            [
+                ("version", "(version 1)"),
+                ("net", "(net 0 \"\")"),
+                ("net", "(net 1 \"a)\\\"b\")"),
+                ("footprint", "(footprint x (at 1 2))")
+            ]
+        );
+
+        let footprint = super::children(src).unwrap().nth(3).unwrap();
+        let at = footprint.children().next().unwrap();
+        assert_eq!(&src[at.span], "(at 1 2)");
+        assert_eq!(footprint.children().count(), 1);
+        assert!(super::children("no lists").is_none());
+        assert_eq!(super::children("(a (b").unwrap().next().unwrap().text, "(b");
+    }
+}
@@ -0,0 +1,459 @@
+//! Validation of documents against a schema describing which child lists
+//! each list allows and the atoms it takes. Schemas are s-expression files
+//! themselves, so format coverage is data rather than code:
+//!
+//! ```text
+//! (schema kicad_pcb
+//!   (version 20240108 20241229)
+//!   (list kicad_pcb (children version net segment))
+//!   (list version (args int))
+//!   (list net (args int string))
+//!   (list segment
+//!     (children start end width layer net uuid)
+//!     (list net (args int)))
+//!   ...)
+//! ```
+//!
+//! A `list` rule for one tag, or a list of tags sharing the rule, gives the
+//! atoms allowed in a list with `args` and the tags of its child lists with
+//! `children`; without them a list takes no atoms or
+//! no child lists. `(children *)` allows any child. Rules nested in a rule
+//! apply to that list's children and override top-level rules of the same
+//! tag; a rule tagged `*` matches any tag. Child lists without a rule are
+//! not checked further.
+//!
+//! Argument types are `int`, `number`, `string`, `symbol`, `text` (string or
+//! symbol), `hex`, `any` and `(one_of a b ...)`, each optionally wrapped in
+//! `(opt T)` or `(many T)`. Arguments are matched greedily in order.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::RangeInclusive,
+};
+
+use crate::{ParseError, Sexp};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaError {
+    Parse(Vec<ParseError>),
+    Invalid(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Parse(errors) => {
+                write!(f, "schema parse failed")?;
+                for e in errors {
+                    write!(f, "; {}", e)?;
+                }
+                Ok(())
+            },
+            SchemaError::Invalid(message) => write!(f, "invalid schema: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// A node that does not match the schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    /// Tags of the lists from the root down to the offending one.
+    pub path: Vec<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.join(" > "), self.message)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ArgType {
+    Int,
+    Number,
+    String,
+    Symbol,
+    Text,
+    Hex,
+    Any,
+    OneOf(Vec<String>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Repeat {
+    One,
+    Opt,
+    Many,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Arg {
+    ty: ArgType,
+    repeat: Repeat,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Children {
+    None,
+    Any,
+    Tags(Vec<String>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Rule {
+    args: Vec<Arg>,
+    children: Children,
+    rules: HashMap<String, Rule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schema {
+    root: String,
+    versions: Option<RangeInclusive<u64>>,
+    rules: HashMap<String, Rule>,
+    extensions: HashSet<String>,
+}
+
+fn invalid(message: impl Into<String>) -> SchemaError {
+    SchemaError::Invalid(message.into())
+}
+
+/// Text of an atom, whatever its kind.
+fn atom_text<'a>(sexp: &Sexp<'a>) -> Option<&'a str> {
+    match sexp {
+        Sexp::Symbol(s)
+        | Sexp::StringLiteral(s)
+        | Sexp::IntLiteral(s)
+        | Sexp::HexIntLiteral(s)
+        | Sexp::FloatLiteral(s) => Some(s),
+        Sexp::Invalid | Sexp::List(_) => None,
+    }
+}
+
+fn list_tag<'s, 'a>(sexp: &'s Sexp<'a>) -> Option<(&'a str, &'s [Sexp<'a>])> {
+    match sexp {
+        Sexp::List(items) => Some((atom_text(items.first()?)?, &items[1..])),
+        _ => None,
+    }
+}
+
+fn symbols<'a>(items: &[Sexp<'a>]) -> Result<Vec<&'a str>, SchemaError> {
+    items.iter().map(|item| atom_text(item).ok_or_else(|| invalid("expected names"))).collect()
+}
+
+fn arg_type(sexp: &Sexp) -> Result<ArgType, SchemaError> {
+    if let Some(("one_of", names)) = list_tag(sexp) {
+        return Ok(ArgType::OneOf(symbols(names)?.into_iter().map(str::to_string).collect()));
+    }
+    Ok(match atom_text(sexp) {
+        Some("int") => ArgType::Int,
+        Some("number") => ArgType::Number,
+        Some("string") => ArgType::String,
+        Some("symbol") => ArgType::Symbol,
+        Some("text") => ArgType::Text,
+        Some("hex") => ArgType::Hex,
+        Some("any") => ArgType::Any,
+        _ => return Err(invalid("unknown argument type")),
+    })
+}
+
+fn arg(sexp: &Sexp) -> Result<Arg, SchemaError> {
+    let repeat = match list_tag(sexp) {
+        Some(("opt", [inner])) => Some((Repeat::Opt, inner)),
+        Some(("many", [inner])) => Some((Repeat::Many, inner)),
+        _ => None,
+    };
+    Ok(match repeat {
+        Some((repeat, inner)) => Arg { ty: arg_type(inner)?, repeat },
+        None => Arg { ty: arg_type(sexp)?, repeat: Repeat::One },
+    })
+}
+
+/// Parses the `(list tag ...)` rules among `items` into `rules`.
+fn rules(items: &[Sexp], rules: &mut HashMap<String, Rule>) -> Result<(), SchemaError> {
+    for item in items {
+        let Some(("list", body)) = list_tag(item) else { continue };
+        let tags = match body.first() {
+            Some(Sexp::List(tags)) => symbols(tags)?,
+            first => vec![first.and_then(atom_text).ok_or_else(|| invalid("list rule without a tag"))?],
+        };
+        let tag = tags.join(" ");
+        let mut rule = Rule { args: Vec::new(), children: Children::None, rules: HashMap::new() };
+        for part in &body[1..] {
+            match list_tag(part) {
+                Some(("args", args)) => rule.args = args.iter().map(arg).collect::<Result<_, _>>()?,
+                Some(("children", tags)) => {
+                    let tags = symbols(tags)?;
+                    rule.children = if tags == ["*"] {
+                        Children::Any
+                    } else {
+                        Children::Tags(tags.into_iter().map(str::to_string).collect())
+                    }
+                },
+                Some(("list", _)) => {},
+                _ => return Err(invalid(format!("unexpected item in rule for `{}`", tag))),
+            }
+        }
+        self::rules(&body[1..], &mut rule.rules)?;
+        for tag in tags {
+            if rules.insert(tag.to_string(), rule.clone()).is_some() {
+                return Err(invalid(format!("duplicate rule for `{}`", tag)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn matches(ty: &ArgType, sexp: &Sexp) -> bool {
+    match (ty, sexp) {
+        (ArgType::Any, _) => true,
+        (ArgType::Int, Sexp::IntLiteral(_)) => true,
+        (ArgType::Number, Sexp::IntLiteral(_) | Sexp::FloatLiteral(_)) => true,
+        (ArgType::String, Sexp::StringLiteral(_)) => true,
+        (ArgType::Symbol, Sexp::Symbol(_)) => true,
+        (ArgType::Text, Sexp::StringLiteral(_) | Sexp::Symbol(_)) => true,
+        (ArgType::Hex, Sexp::HexIntLiteral(_)) => true,
+        (ArgType::OneOf(names), Sexp::Symbol(s) | Sexp::StringLiteral(s)) => names.iter().any(|name| name == s),
+        _ => false,
+    }
+}
+
+fn describe(sexp: &Sexp) -> String {
+    match sexp {
+        Sexp::StringLiteral(s) => format!("\"{}\"", s),
+        sexp => atom_text(sexp).map_or_else(|| "invalid input".to_string(), |s| format!("`{}`", s)),
+    }
+}
+
+fn type_name(ty: &ArgType) -> String {
+    match ty {
+        ArgType::Int => "an integer".to_string(),
+        ArgType::Number => "a number".to_string(),
+        ArgType::String => "a string".to_string(),
+        ArgType::Symbol => "a symbol".to_string(),
+        ArgType::Text => "a string or symbol".to_string(),
+        ArgType::Hex => "a hex number".to_string(),
+        ArgType::Any => "an atom".to_string(),
+        ArgType::OneOf(names) => format!("one of {}", names.join(", ")),
+    }
+}
+
+struct Validator<'r> {
+    global: &'r HashMap<String, Rule>,
+    extensions: &'r HashSet<String>,
+    path: Vec<String>,
+    issues: Vec<Issue>,
+}
+
+impl<'r> Validator<'r> {
+    fn issue(&mut self, message: String) {
+        self.issues.push(Issue { path: self.path.clone(), message });
+    }
+
+    fn args(&mut self, rule: &Rule, atoms: &[&Sexp]) {
+        let mut next = 0;
+        for arg in &rule.args {
+            match arg.repeat {
+                Repeat::One => match atoms.get(next) {
+                    Some(atom) if matches(&arg.ty, atom) => next += 1,
+                    Some(atom) => {
+                        self.issue(format!("expected {}, found {}", type_name(&arg.ty), describe(atom)));
+                        return;
+                    },
+                    None => {
+                        self.issue(format!("missing argument, expected {}", type_name(&arg.ty)));
+                        return;
+                    },
+                },
+                Repeat::Opt => {
+                    if atoms.get(next).is_some_and(|atom| matches(&arg.ty, atom)) {
+                        next += 1;
+                    }
+                },
+                Repeat::Many => {
+                    while atoms.get(next).is_some_and(|atom| matches(&arg.ty, atom)) {
+                        next += 1;
+                    }
+                },
+            }
+        }
+        if let Some(atom) = atoms.get(next) {
+            self.issue(format!("unexpected argument {}", describe(atom)));
+        }
+    }
+
+    fn list(&mut self, rule: &Rule, items: &[Sexp]) {
+        let atoms: Vec<&Sexp> = items.iter().filter(|item| !matches!(item, Sexp::List(_))).collect();
+        self.args(rule, &atoms);
+
+        for item in items {
+            let Sexp::List(_) = item else { continue };
+            let Some((tag, children)) = list_tag(item) else {
+                self.issue("list without a tag".to_string());
+                continue;
+            };
+            if self.extensions.contains(tag) {
+                continue;
+            }
+            let allowed = match &rule.children {
+                Children::Any => true,
+                Children::None => false,
+                Children::Tags(tags) => tags.iter().any(|allowed| allowed == tag || allowed == "*"),
+            };
+            if !allowed {
+                self.issue(format!("unexpected ({} ...)", tag));
+                continue;
+            }
+            let child = [&rule.rules, self.global].into_iter().find_map(|rules| rules.get(tag).or(rules.get("*")));
+            if let Some(child) = child {
+                self.path.push(tag.to_string());
+                self.list(child, children);
+                self.path.pop();
+            }
+        }
+    }
+}
+
+impl Schema {
+    /// Parses a schema file.
+    pub fn parse(src: &str) -> Result<Schema, SchemaError> {
+        let sexps = crate::parse(src).map_err(SchemaError::Parse)?;
+        let (root, items) = match sexps.iter().find_map(list_tag) {
+            Some(("schema", [root, items @ ..])) => {
+                (atom_text(root).ok_or_else(|| invalid("missing root tag"))?, items)
+            },
+            _ => return Err(invalid("expected a (schema <root> ...) list")),
+        };
+
+        let mut versions = None;
+        for item in items {
+            if let Some(("version", range)) = list_tag(item) {
+                let numbers = symbols(range)?
+                    .into_iter()
+                    .map(|n| n.parse::<u64>().map_err(|_| invalid("bad version")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                versions = match numbers[..] {
+                    [v] => Some(v..=v),
+                    [min, max] => Some(min..=max),
+                    _ => return Err(invalid("expected (version <min> [<max>])")),
+                };
+            }
+        }
+        let mut schema = Schema { root: root.to_string(), versions, rules: HashMap::new(), extensions: HashSet::new() };
+        rules(items, &mut schema.rules)?;
+        if !schema.rules.contains_key(root) {
+            return Err(invalid(format!("no rule for the root `{}`", root)));
+        }
+        Ok(schema)
+    }
+
+    /// Tag of the documents this schema describes.
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Format versions the schema covers.
+    pub fn versions(&self) -> Option<&RangeInclusive<u64>> {
+        self.versions.as_ref()
+    }
+
+    /// Accepts lists tagged `tag` anywhere without checking them, for
+    /// extension lists validated by their own handlers.
+    pub fn allow_extension(&mut self, tag: &str) {
+        self.extensions.insert(tag.to_string());
+    }
+
+    /// Checks a parsed document against the schema; empty if it conforms.
+    pub fn validate(&self, sexps: &[Sexp]) -> Vec<Issue> {
+        let mut validator =
+            Validator { global: &self.rules, extensions: &self.extensions, path: Vec::new(), issues: Vec::new() };
+        let Some((tag, items)) = sexps.iter().find_map(list_tag) else {
+            validator.issue(format!("expected a ({} ...) root", self.root));
+            return validator.issues;
+        };
+        validator.path.push(tag.to_string());
+        if tag != self.root {
+            validator.issue(format!("expected ({} ...)", self.root));
+            return validator.issues;
+        }
+
+        let version = items.iter().find_map(|item| match list_tag(item) {
+            Some(("version", [version])) => atom_text(version)?.parse::<u64>().ok(),
+            _ => None,
+        });
+        if let (Some(version), Some(versions)) = (version, &self.versions)
+            && !versions.contains(&version)
+        {
+            validator.issue(format!(
+                "format version {} is outside the schema's {}..={}",
+                version,
+                versions.start(),
+                versions.end()
+            ));
+        }
+
+        validator.list(&self.rules[&self.root], items);
+        validator.issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "(schema kicad_pcb
+        (version 20240108 20241229)
+        (list kicad_pcb (children version net layers segment))
+        (list version (args int))
+        (list net (args int string))
+        (list layers (children *) (list * (args text (one_of signal user) (opt string))))
+        (list segment
+            (children start end net)
+            (list net (args int)))
+        (list (start end) (args number number)))";
+
+    fn issues(src: &str) -> Vec<String> {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        schema.validate(&crate::parse(src).unwrap()).iter().map(Issue::to_string).collect()
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(
+            issues(
+                "(kicad_pcb (version 20241229) (net 0 \"\") (layers (0 \"F.Cu\" signal) (5 \"F.SilkS\" user \"Top\")) \
+                 (segment (start 0 1.5) (end 1 1) (net 0)))"
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            issues(
+                "(kicad_pcb (version 20200101) (net \"GND\") (layers (0 \"F.Cu\" power)) (via) \
+                 (segment (start 0) (end 1 1 1) (net 0 \"\") (width 1)))"
+            ),
+            [
+                "kicad_pcb: format version 20200101 is outside the schema's 20240108..=20241229",
+                "kicad_pcb > net: expected an integer, found \"GND\"",
+                "kicad_pcb > layers > 0: expected one of signal, user, found `power`",
+                "kicad_pcb: unexpected (via ...)",
+                "kicad_pcb > segment > start: missing argument, expected a number",
+                "kicad_pcb > segment > end: unexpected argument `1`",
+                "kicad_pcb > segment > net: unexpected argument \"\"",
+                "kicad_pcb > segment: unexpected (width ...)",
+            ]
+        );
+        assert_eq!(issues("(kicad_sch)"), ["kicad_sch: expected (kicad_pcb ...)"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(Schema::parse("(schema a (list a (args bogus)))"), Err(SchemaError::Invalid(_))));
+        assert!(matches!(Schema::parse("(schema a (list b))"), Err(SchemaError::Invalid(_))));
+        assert!(matches!(Schema::parse("(schema a (list a) (list a))"), Err(SchemaError::Invalid(_))));
+        assert!(matches!(Schema::parse("(schema a"), Err(SchemaError::Parse(_))));
+    }
+}
@@ -0,0 +1,528 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::input;
+#[cfg(feature = "zip")]
+use crate::zip::{ZipArchive, ZipEntry};
+
+/// Identifies one version of a file's contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fingerprint {
+    Modified { time: SystemTime, len: u64 },
+    Content(u64),
+}
+
+/// Where documents are loaded from.
+///
+/// Loaders and library resolvers take a `&dyn Source` instead of touching the
+/// filesystem directly, so they also work on in-memory or archived projects.
+pub trait Source: Send + Sync {
+    /// Reads the full contents of a file.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Returns true if `path` is a file or directory.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Lists the direct children of a directory.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Returns a cheap fingerprint of the file, if the source has one.
+    /// `None` makes callers hash the contents instead.
+    fn fingerprint(&self, _path: &Path) -> io::Result<Option<Fingerprint>> {
+        Ok(None)
+    }
+
+    /// Reads a file as text, passed through [`input::normalize`].
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        let bytes = self.read(path)?;
+        let text = input::normalize(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(text.into_owned())
+    }
+}
+
+/// Reads from the local filesystem, optionally relative to a root directory.
+#[derive(Clone, Debug, Default)]
+pub struct FsSource {
+    root: Option<PathBuf>,
+}
+
+impl FsSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves relative paths against `root`.
+    pub fn rooted(root: impl Into<PathBuf>) -> Self {
+        FsSource { root: Some(root.into()) }
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        match &self.root {
+            Some(root) => root.join(path),
+            // The directory of a file name without one.
+            None if path.as_os_str().is_empty() => PathBuf::from("."),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+impl Source for FsSource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.resolve(path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.resolve(path).exists()
+    }
+
+    fn fingerprint(&self, path: &Path) -> io::Result<Option<Fingerprint>> {
+        let metadata = fs::metadata(self.resolve(path))?;
+        Ok(Some(Fingerprint::Modified { time: metadata.modified()?, len: metadata.len() }))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = fs::read_dir(self.resolve(path))?
+            .map(|entry| entry.map(|entry| path.join(entry.file_name())))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        Ok(entries)
+    }
+}
+
+/// An in-memory file tree, handy for tests, servers and wasm.
+#[derive(Clone, Debug, Default)]
+pub struct MemorySource {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) -> &mut Self {
+        self.files.insert(path.into(), contents.into());
+        self
+    }
+
+    pub fn with(mut self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) -> Self {
+        self.insert(path, contents);
+        self
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<Vec<u8>> {
+        self.files.remove(path)
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display()))
+}
+
+impl Source for MemorySource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files.get(path).cloned().ok_or_else(|| not_found(path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.keys().any(|file| file.starts_with(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        list_dir(self.files.keys().map(PathBuf::as_path), path)
+    }
+}
+
+/// The direct children of `path` in a tree of `files`, sorted.
+fn list_dir<'f>(files: impl IntoIterator<Item = &'f Path>, path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = false;
+    let mut entries = BTreeSet::new();
+    for rest in files.into_iter().filter_map(|file| file.strip_prefix(path).ok()) {
+        found = true;
+        entries.extend(rest.components().next().map(|first| path.join(first)));
+    }
+    if !found {
+        return Err(not_found(path));
+    }
+    Ok(entries.into_iter().collect())
+}
+
+/// The files of a zip archive, decompressed as they are read.
+#[cfg(feature = "zip")]
+#[derive(Clone, Debug)]
+pub struct ZipSource {
+    archive: ZipArchive,
+    dir: PathBuf,
+}
+
+#[cfg(feature = "zip")]
+impl ZipSource {
+    /// Reads the central directory of the archive in `data`. Files are at
+    /// their paths inside the archive.
+    pub fn new(data: Vec<u8>) -> io::Result<Self> {
+        Ok(ZipSource { archive: ZipArchive::new(data)?, dir: PathBuf::new() })
+    }
+
+    /// Places the files of the archive under `dir`.
+    pub fn at(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    pub fn archive(&self) -> &ZipArchive {
+        &self.archive
+    }
+
+    fn entry(&self, path: &Path) -> io::Result<&ZipEntry> {
+        let rest = path.strip_prefix(&self.dir).map_err(|_| not_found(path))?;
+        let names: Option<Vec<&str>> = rest
+            .components()
+            .map(|component| match component {
+                std::path::Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        let name = names.ok_or_else(|| not_found(path))?.join("/");
+        self.archive.entry(&name).filter(|entry| !entry.is_dir()).ok_or_else(|| not_found(path))
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        self.archive.entries().iter().map(|entry| self.dir.join(entry.name.trim_end_matches('/'))).collect()
+    }
+}
+
+#[cfg(feature = "zip")]
+impl Source for ZipSource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.archive.read(self.entry(path)?)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.paths().iter().any(|file| file.starts_with(path))
+    }
+
+    /// The CRC and size of the entry, which the archive stores.
+    fn fingerprint(&self, path: &Path) -> io::Result<Option<Fingerprint>> {
+        let entry = self.entry(path)?;
+        Ok(Some(Fingerprint::Content(u64::from(entry.crc) << 32 ^ entry.size)))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        list_dir(self.paths().iter().map(PathBuf::as_path), path)
+    }
+}
+
+/// Reads over HTTP or HTTPS from a base URL, such as a project directory
+/// served by `python -m http.server`, nginx or an artifact store, through a
+/// [`ureq`] agent that follows redirects.
+///
+/// Directories are listed from the server's index page: the relative links
+/// of `GET dir/`, as directory autoindexes write them.
+#[cfg(feature = "http")]
+#[derive(Clone, Debug)]
+pub struct HttpSource {
+    agent: ureq::Agent,
+    /// The base URL, without a slash at the end.
+    base: String,
+}
+
+#[cfg(feature = "http")]
+type Response = ureq::http::Response<ureq::Body>;
+
+#[cfg(feature = "http")]
+fn agent(timeout: std::time::Duration) -> ureq::Agent {
+    let config = ureq::Agent::config_builder().timeout_global(Some(timeout)).http_status_as_error(false).build();
+    ureq::Agent::new_with_config(config)
+}
+
+#[cfg(feature = "http")]
+impl HttpSource {
+    /// Reads from below `base`, an `http://` or `https://` URL, with a 30
+    /// second timeout.
+    pub fn new(base: &str) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("http: {}", message));
+        let rest = base.strip_prefix("http://").or_else(|| base.strip_prefix("https://"));
+        let rest = rest.ok_or_else(|| invalid("expected an http:// or https:// URL"))?;
+        if rest.split('/').next().is_none_or(str::is_empty) {
+            return Err(invalid("the URL has no host"));
+        }
+        let agent = agent(std::time::Duration::from_secs(30));
+        Ok(HttpSource { agent, base: base.trim_end_matches('/').to_string() })
+    }
+
+    /// Sets the timeout of each request, from connecting to the end of the
+    /// body.
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.agent = agent(timeout);
+        self
+    }
+
+    /// The URL of `path`, percent-encoded.
+    fn url(&self, path: &Path, dir: bool) -> io::Result<String> {
+        let mut url = self.base.clone();
+        for component in path.components() {
+            let std::path::Component::Normal(name) = component else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("http: bad path {}", path.display())));
+            };
+            url.push('/');
+            for byte in name.as_encoded_bytes() {
+                match byte {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                        url.push(char::from(*byte));
+                    },
+                    _ => url.push_str(&format!("%{:02X}", byte)),
+                }
+            }
+        }
+        if dir || path.as_os_str().is_empty() {
+            url.push('/');
+        }
+        Ok(url)
+    }
+
+    fn request(&self, head: bool, url: &str) -> io::Result<Response> {
+        let response = if head { self.agent.head(url).call() } else { self.agent.get(url).call() };
+        response.map_err(ureq::Error::into_io)
+    }
+
+    /// Sends a `GET`, or a `HEAD` if `head`, for `path`, failing unless the
+    /// server answers 200.
+    fn fetch(&self, head: bool, path: &Path, dir: bool) -> io::Result<Response> {
+        let response = self.request(head, &self.url(path, dir)?)?;
+        match response.status().as_u16() {
+            200 => Ok(response),
+            404 | 410 => Err(not_found(path)),
+            403 => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is forbidden", path.display()))),
+            status => Err(io::Error::other(format!("http: status {} for {}", status, path.display()))),
+        }
+    }
+
+    /// The body of a `GET` for `path`, of any size.
+    fn body(&self, path: &Path, dir: bool) -> io::Result<Vec<u8>> {
+        let mut response = self.fetch(false, path, dir)?;
+        response.body_mut().with_config().limit(u64::MAX).read_to_vec().map_err(ureq::Error::into_io)
+    }
+}
+
+/// Decodes `%XX` escapes, or `None` for bad ones and non-UTF-8 results.
+#[cfg(feature = "http")]
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The names an index page links to in its own directory.
+#[cfg(feature = "http")]
+fn index_links(page: &str) -> BTreeSet<String> {
+    page.split("href=\"")
+        .skip(1)
+        .filter_map(|rest| rest.split('"').next())
+        .filter(|link| !link.starts_with(['?', '#', '/', '.']) && !link.contains("://"))
+        .filter_map(|link| percent_decode(link.trim_end_matches('/')))
+        .filter(|name| !name.is_empty() && !name.contains('/'))
+        .collect()
+}
+
+#[cfg(feature = "http")]
+impl Source for HttpSource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.body(path, false)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let response = self.url(path, false).and_then(|url| self.request(true, &url));
+        response.is_ok_and(|response| response.status().is_success())
+    }
+
+    /// A hash of the `ETag` or `Last-Modified` header, with the length.
+    fn fingerprint(&self, path: &Path) -> io::Result<Option<Fingerprint>> {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+
+        let response = self.fetch(true, path, false)?;
+        let header = |name: &str| response.headers().get(name).and_then(|value| value.to_str().ok());
+        let Some(version) = header("etag").or_else(|| header("last-modified")) else {
+            return Ok(None);
+        };
+        let mut hasher = DefaultHasher::new();
+        (version, header("content-length")).hash(&mut hasher);
+        Ok(Some(Fingerprint::Content(hasher.finish())))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let page = self.body(path, true)?;
+        Ok(index_links(&String::from_utf8_lossy(&page)).into_iter().map(|name| path.join(name)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> MemorySource {
+        MemorySource::new()
+            .with("demo/demo.kicad_sch", "\u{feff}(kicad_sch)\r\n")
+            .with("demo/demo.kicad_pcb", "(kicad_pcb)")
+            .with("demo/lib.pretty/R.kicad_mod", "(footprint \"R\")")
+    }
+
+    #[test]
+    fn memory_read() {
+        let source = project();
+
+        assert_eq!(source.read(Path::new("demo/demo.kicad_pcb")).unwrap(), b"(kicad_pcb)");
+        assert_eq!(source.read_to_string(Path::new("demo/demo.kicad_sch")).unwrap(), "(kicad_sch)\n");
+        assert_eq!(source.read(Path::new("demo/missing")).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn memory_dirs() {
+        let source = project();
+
+        assert!(source.exists(Path::new("demo/lib.pretty")));
+        assert!(!source.exists(Path::new("other")));
+        assert_eq!(
+            source.read_dir(Path::new("demo")).unwrap(),
+            [
+                PathBuf::from("demo/demo.kicad_pcb"),
+                PathBuf::from("demo/demo.kicad_sch"),
+                PathBuf::from("demo/lib.pretty"),
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_rooted() {
+        let source = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+
+        assert!(source.exists(Path::new("empty/empty.kicad_pcb")));
+        assert!(source.read_dir(Path::new("empty")).unwrap().contains(&PathBuf::from("empty/empty.kicad_sch")));
+        assert!(source.read_to_string(Path::new("empty/empty.kicad_sch")).unwrap().starts_with("(kicad_sch"));
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn zip_archive() {
+        let archive = fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../reference-files/demo/demo-backups/demo-2026-10-14_120000.zip"
+        ))
+        .unwrap();
+        let source = ZipSource::new(archive.clone()).unwrap().at("demo");
+        let demo = FsSource::rooted(concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files"));
+
+        for path in ["demo/demo.kicad_sch", "demo/demo.pretty/R_0603_1608Metric.kicad_mod"] {
+            assert_eq!(source.read(Path::new(path)).unwrap(), demo.read(Path::new(path)).unwrap());
+        }
+        assert_eq!(source.read(Path::new("demo.kicad_sch")).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(source.read(Path::new("demo/demo.pretty")).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(source.exists(Path::new("demo/demo.pretty")) && !source.exists(Path::new("demo/demo.net")));
+        assert_eq!(
+            source.read_dir(Path::new("demo/demo.pretty")).unwrap(),
+            [
+                PathBuf::from("demo/demo.pretty/PinHeader_1x03_P2.54mm_Vertical.kicad_mod"),
+                PathBuf::from("demo/demo.pretty/R_0603_1608Metric.kicad_mod"),
+            ]
+        );
+        assert_eq!(source.read_dir(Path::new("demo")).unwrap().len(), 10);
+
+        let pcb = Path::new("demo/demo.kicad_pcb");
+        let other = ZipSource::new(archive).unwrap().at("demo");
+        assert_eq!(source.fingerprint(pcb).unwrap(), other.fingerprint(pcb).unwrap());
+        assert_ne!(source.fingerprint(pcb).unwrap(), source.fingerprint(Path::new("demo/demo.kicad_sch")).unwrap());
+    }
+
+    /// Serves `responses` to one connection each, in order, and returns
+    /// the request lines received.
+    #[cfg(feature = "http")]
+    fn serve(responses: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/projects/demo", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                requests.push(line.trim_end().to_string());
+                while line != "\r\n" {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                }
+                reader.into_inner().write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn http() {
+        let ok = |headers: &str, body: &str| {
+            format!("HTTP/1.1 200 OK\r\nConnection: close\r\n{}\r\n{}", headers, body)
+        };
+        let index = concat!(
+            "<ul><li><a href=\"../\">..</a></li><li><a href=\"R%200603.kicad_mod\">R 0603.kicad_mod</a></li>",
+            "<li><a href=\"sub/\">sub/</a></li><li><a href=\"?C=N;O=D\">Name</a></li></ul>",
+        );
+        let responses = vec![
+            ok("Content-Length: 11\r\n", "(kicad_pcb)"),
+            ok("Transfer-Encoding: chunked\r\n", "4\r\n(kic\r\n7\r\nad_sch)\r\n0\r\n\r\n"),
+            "HTTP/1.1 404 Not Found\r\nConnection: close\r\nContent-Length: 0\r\n\r\n".to_string(),
+            ok("", index),
+            concat!(
+                "HTTP/1.1 301 Moved Permanently\r\nConnection: close\r\n",
+                "Location: /projects/demo/lib.pretty/\r\n\r\n",
+            )
+            .to_string(),
+            ok("Content-Length: 0\r\n", ""),
+            ok("ETag: \"abc\"\r\nContent-Length: 11\r\n", ""),
+        ];
+        let (url, server) = serve(responses);
+        let source = HttpSource::new(&url).unwrap();
+
+        assert_eq!(source.read(Path::new("demo.kicad_pcb")).unwrap(), b"(kicad_pcb)");
+        assert_eq!(source.read_to_string(Path::new("demo.kicad_sch")).unwrap(), "(kicad_sch)");
+        assert_eq!(source.read(Path::new("missing")).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            source.read_dir(Path::new("lib.pretty")).unwrap(),
+            [PathBuf::from("lib.pretty/R 0603.kicad_mod"), PathBuf::from("lib.pretty/sub")]
+        );
+        assert!(source.exists(Path::new("lib.pretty")));
+        assert!(matches!(source.fingerprint(Path::new("demo.kicad_pcb")).unwrap(), Some(Fingerprint::Content(_))));
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "GET /projects/demo/demo.kicad_pcb HTTP/1.1",
+                "GET /projects/demo/demo.kicad_sch HTTP/1.1",
+                "GET /projects/demo/missing HTTP/1.1",
+                "GET /projects/demo/lib.pretty/ HTTP/1.1",
+                "HEAD /projects/demo/lib.pretty HTTP/1.1",
+                "HEAD /projects/demo/lib.pretty/ HTTP/1.1",
+                "HEAD /projects/demo/demo.kicad_pcb HTTP/1.1",
+            ]
+        );
+
+        assert_eq!(HttpSource::new("https://example.com/lib/").unwrap().base, "https://example.com/lib");
+        assert_eq!(HttpSource::new("ftp://example.com").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(HttpSource::new("http:///lib").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
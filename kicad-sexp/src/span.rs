@@ -0,0 +1,173 @@
+//! A variant of [`Sexp`] that records the byte range of every atom and list,
+//! plus line/column lookup for reporting locations.
+
+use std::{fmt, ops::Range};
+
+use chumsky::{prelude::*, recursive::Direct};
+
+use crate::{parse_float, parse_hex, parse_int, parse_string, parse_symbol, ParseError, Sexp};
+
+#[derive(Clone, Debug)]
+pub enum SpannedKind<'a> {
+    /// Any [`Sexp`] other than a list.
+    Atom(Sexp<'a>),
+    List(Vec<SpannedSexp<'a>>),
+}
+
+/// A node with its byte range in the source. Strings include their quotes
+/// and lists their parentheses.
+#[derive(Clone, Debug)]
+pub struct SpannedSexp<'a> {
+    pub span: Range<usize>,
+    pub kind: SpannedKind<'a>,
+}
+
+impl<'a> SpannedSexp<'a> {
+    pub fn items(&self) -> &[SpannedSexp<'a>] {
+        match &self.kind {
+            SpannedKind::List(items) => items,
+            SpannedKind::Atom(_) => &[],
+        }
+    }
+
+    /// The leading symbol of a list.
+    pub fn tag(&self) -> Option<&'a str> {
+        match self.items().first()?.kind {
+            SpannedKind::Atom(Sexp::Symbol(tag)) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// The first child list tagged `tag`.
+    pub fn child(&self, tag: &str) -> Option<&SpannedSexp<'a>> {
+        self.items().iter().find(|item| item.tag() == Some(tag))
+    }
+
+    /// The innermost node containing byte `offset`, if any.
+    pub fn node_at(&self, offset: usize) -> Option<&SpannedSexp<'a>> {
+        if !self.span.contains(&offset) {
+            return None;
+        }
+        self.items().iter().find_map(|item| item.node_at(offset)).or(Some(self))
+    }
+
+    /// Drops the spans.
+    pub fn to_sexp(&self) -> Sexp<'a> {
+        match &self.kind {
+            SpannedKind::Atom(atom) => atom.clone(),
+            SpannedKind::List(items) => Sexp::List(items.iter().map(SpannedSexp::to_sexp).collect()),
+        }
+    }
+}
+
+fn atom<'a>(sexp: Sexp<'a>, start: usize, len: usize) -> SpannedSexp<'a> {
+    SpannedSexp { span: start..start + len, kind: SpannedKind::Atom(sexp) }
+}
+
+type Extra<'a> = extra::Err<Simple<'a, char>>;
+
+/// Like the parser behind [`crate::parse`], keeping spans.
+pub(crate) fn spanned_parser<'a>() -> impl Parser<'a, &'a str, Vec<SpannedSexp<'a>>, Extra<'a>> {
+    recursive(|bf: Recursive<Direct<'a, 'a, &'a str, Vec<SpannedSexp<'a>>, Extra<'a>>>| {
+        choice((
+            parse_string().map_with(|s, e| atom(Sexp::StringLiteral(s), e.span().start, s.len() + 2)),
+            parse_int().map_with(|s, e| atom(Sexp::IntLiteral(s), e.span().start, s.len())),
+            parse_hex().map_with(|s, e| atom(Sexp::HexIntLiteral(s), e.span().start, s.len())),
+            parse_float().map_with(|s, e| atom(Sexp::FloatLiteral(s), e.span().start, s.len())),
+            parse_symbol().map_with(|s, e| atom(Sexp::Symbol(s), e.span().start, s.len())),
+        ))
+        .or(bf
+            .delimited_by(just('('), just(')'))
+            .map_with(|items, e| SpannedSexp { span: e.span().into_range(), kind: SpannedKind::List(items) })
+            .padded())
+        .recover_with(via_parser(nested_delimiters('(', ')', [], |span: SimpleSpan| SpannedSexp {
+            span: span.into_range(),
+            kind: SpannedKind::Atom(Sexp::Invalid),
+        })))
+        .repeated()
+        .collect()
+    })
+}
+
+/// Parses `src` keeping spans, failing if the parser had to recover from any error.
+pub fn parse_spanned(src: &str) -> Result<Vec<SpannedSexp<'_>>, Vec<ParseError>> {
+    let result = spanned_parser().parse(src).into_result();
+    result.map_err(|errors| errors.into_iter().map(ParseError::from_simple).collect())
+}
+
+/// A 1-based line and column; columns count characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Maps byte offsets in a source text to lines and columns.
+#[derive(Clone, Debug)]
+pub struct LineIndex<'a> {
+    src: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(src: &'a str) -> Self {
+        let line_starts = std::iter::once(0).chain(src.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        LineIndex { src, line_starts }
+    }
+
+    /// Location of byte `offset`, clamped to the end of the source.
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = offset.min(self.src.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let column = self.src.get(start..offset).map_or(offset - start, |prefix| prefix.chars().count()) + 1;
+        Location { line: line + 1, column }
+    }
+
+    /// Byte range of line `line` (1-based), without its newline.
+    pub fn line_span(&self, line: usize) -> Option<Range<usize>> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts.get(line).map_or(self.src.len(), |next| next - 1);
+        Some(start..end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let src = "(kicad_pcb\n\t(pad \"1\" smd (size 0.8 0.95))\n)";
+        let root = &parse_spanned(src).unwrap()[0];
+        let pad = root.child("pad").unwrap();
+        let size = pad.child("size").unwrap();
+
+        assert_eq!(root.span, 0..src.len());
+        assert_eq!(&src[pad.span.clone()], "(pad \"1\" smd (size 0.8 0.95))");
+        assert_eq!(&src[pad.items()[1].span.clone()], "\"1\"");
+        assert_eq!(&src[size.items()[2].span.clone()], "0.95");
+        assert_eq!(root.node_at(src.find("0.8").unwrap()).unwrap().span, size.items()[1].span);
+
+        let index = LineIndex::new(src);
+        assert_eq!(index.location(size.span.start), Location { line: 2, column: 15 });
+        assert_eq!(index.location(src.len()).to_string(), "3:2");
+        assert_eq!(&src[index.line_span(2).unwrap()], "\t(pad \"1\" smd (size 0.8 0.95))");
+        assert_eq!(index.line_span(4), None);
+    }
+
+    #[test]
+    fn plain_tree() {
+        let src = include_str!("../../reference-files/empty/empty.kicad_pcb");
+        let spanned: Vec<_> = parse_spanned(src).unwrap().iter().map(SpannedSexp::to_sexp).collect();
+
+        assert_eq!(crate::writer::to_string(&spanned), crate::writer::to_string(&crate::parse(src).unwrap()));
+        assert!(parse_spanned("(a (b)").is_err());
+    }
+}
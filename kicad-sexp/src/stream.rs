@@ -0,0 +1,308 @@
+//! Reads the lists at one depth of a document from an [`io::BufRead`], one
+//! at a time, so tools working on boards too large to hold as a tree can
+//! count, pick or drop items while only one of them is in memory.
+//!
+//! Like [`scan`](crate::scan), the reader only tracks parentheses and
+//! strings. Text outside the items is not validated, and an item is only
+//! parsed when [`Item::parse`] is called.
+//!
+//! ```no_run
+//! use std::{fs::File, io::BufReader};
+//!
+//! let file = BufReader::new(File::open("huge.kicad_pcb")?);
+//! let mut segments = 0;
+//! for item in kicad_sexp::stream::items(file, 1) {
+//!     segments += (item?.tag == "segment") as usize;
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io::{self, BufRead, Write};
+
+use crate::{ParseError, Sexp};
+
+/// A list read by [`items`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    pub tag: String,
+    /// Tags of the enclosing lists, outermost first.
+    pub path: Vec<String>,
+    /// The list's text, including its parentheses.
+    pub text: String,
+    /// Byte offset of `text` in the stream.
+    pub offset: u64,
+}
+
+impl Item {
+    pub fn parse(&self) -> Result<Sexp<'_>, Vec<ParseError>> {
+        let mut sexps = crate::parse(&self.text)?;
+        Ok(sexps.swap_remove(0))
+    }
+}
+
+/// What the scanner made of one byte.
+enum Step {
+    /// Outside any item.
+    Outside,
+    /// Part of the item being read.
+    Inside,
+    Done(Item),
+}
+
+#[derive(Debug)]
+struct Scanner {
+    target: usize,
+    depth: usize,
+    string: bool,
+    escape: bool,
+    /// The tag of an enclosing list, while it is being read.
+    tag: Option<Vec<u8>>,
+    path: Vec<String>,
+    item: Option<(u64, Vec<u8>)>,
+    offset: u64,
+}
+
+fn is_delimiter(b: u8) -> bool {
+    b.is_ascii_whitespace() || matches!(b, b'(' | b')' | b'"')
+}
+
+fn utf8(bytes: Vec<u8>, offset: u64) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|e| {
+        let at = offset + e.utf8_error().valid_up_to() as u64;
+        io::Error::new(io::ErrorKind::InvalidData, format!("input is not valid UTF-8 at byte {}", at))
+    })
+}
+
+impl Scanner {
+    fn new(target: usize) -> Self {
+        Scanner { target, depth: 0, string: false, escape: false, tag: None, path: Vec::new(), item: None, offset: 0 }
+    }
+
+    fn step(&mut self, b: u8) -> io::Result<Step> {
+        let offset = self.offset;
+        self.offset += 1;
+        if let Some(tag) = &mut self.tag {
+            if !is_delimiter(b) {
+                tag.push(b);
+                return Ok(Step::Outside);
+            }
+            let tag = self.tag.take().unwrap_or_default();
+            let start = offset - tag.len() as u64;
+            self.path.push(utf8(tag, start)?);
+        }
+
+        if self.string {
+            match (self.escape, b) {
+                (true, _) => self.escape = false,
+                (false, b'\\') => self.escape = true,
+                (false, b'"') => self.string = false,
+                _ => {},
+            }
+        } else {
+            match b {
+                b'"' => self.string = true,
+                b'(' if self.item.is_none() && self.depth == self.target => self.item = Some((offset, Vec::new())),
+                b'(' if self.item.is_none() => self.tag = Some(Vec::new()),
+                b')' if self.item.is_none() => {
+                    self.depth = self.depth.saturating_sub(1);
+                    self.path.pop();
+                    return Ok(Step::Outside);
+                },
+                _ => {},
+            }
+            match b {
+                b'(' => self.depth += 1,
+                b')' => self.depth -= 1,
+                _ => {},
+            }
+        }
+
+        let Some((start, text)) = &mut self.item else {
+            return Ok(Step::Outside);
+        };
+        text.push(b);
+        if b != b')' || self.string || self.depth != self.target {
+            return Ok(Step::Inside);
+        }
+        let start = *start;
+        let text = utf8(self.item.take().map(|(_, text)| text).unwrap_or_default(), start)?;
+        let tag = text[1..].split(|c: char| c.is_ascii() && is_delimiter(c as u8)).next().unwrap_or_default();
+        let tag = tag.to_string();
+        Ok(Step::Done(Item { tag, path: self.path.clone(), text, offset: start }))
+    }
+
+    /// Fails if the input ended inside an item.
+    fn finish(&self) -> io::Result<()> {
+        match &self.item {
+            Some((start, _)) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("input ended inside the list at byte {}", start),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Iterator over the lists at one depth, see [`items`].
+#[derive(Debug)]
+pub struct Items<R> {
+    reader: R,
+    scanner: Scanner,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for Items<R> {
+    type Item = io::Result<Item>;
+
+    fn next(&mut self) -> Option<io::Result<Item>> {
+        while !self.done {
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            };
+            if chunk.is_empty() {
+                self.done = true;
+                return self.scanner.finish().err().map(Err);
+            }
+            let mut used = 0;
+            let mut found = None;
+            for &b in chunk {
+                used += 1;
+                match self.scanner.step(b) {
+                    Ok(Step::Done(item)) => found = Some(Ok(item)),
+                    Err(e) => found = Some(Err(e)),
+                    Ok(Step::Outside | Step::Inside) => continue,
+                }
+                break;
+            }
+            self.reader.consume(used);
+            if let Some(found) = found {
+                self.done = found.is_err();
+                return Some(found);
+            }
+        }
+        None
+    }
+}
+
+/// Reads the lists `depth` levels into `reader`: depth 0 yields the
+/// documents themselves, depth 1 a board's or schematic's sections, such as
+/// each track, footprint and zone. Lists inside an item are part of it.
+pub fn items<R: BufRead>(reader: R, depth: usize) -> Items<R> {
+    Items { reader, scanner: Scanner::new(depth), done: false }
+}
+
+/// Copies `reader` to `writer`, leaving out the items at `depth` for which
+/// `keep` returns false together with the whitespace before them, so
+/// dropping a line-per-item section leaves no blank lines. Returns how many
+/// items were dropped.
+pub fn retain<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    depth: usize,
+    mut keep: impl FnMut(&Item) -> bool,
+) -> io::Result<usize> {
+    let mut scanner = Scanner::new(depth);
+    let mut whitespace = Vec::new();
+    let mut dropped = 0;
+    loop {
+        let chunk = match reader.fill_buf() {
+            Ok(chunk) => chunk,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if chunk.is_empty() {
+            scanner.finish()?;
+            writer.write_all(&whitespace)?;
+            return Ok(dropped);
+        }
+        for &b in chunk {
+            match scanner.step(b)? {
+                Step::Outside if b.is_ascii_whitespace() => whitespace.push(b),
+                Step::Outside => {
+                    writer.write_all(&whitespace)?;
+                    writer.write_all(&[b])?;
+                    whitespace.clear();
+                },
+                Step::Inside => {},
+                Step::Done(item) => {
+                    if keep(&item) {
+                        writer.write_all(&whitespace)?;
+                        writer.write_all(item.text.as_bytes())?;
+                    } else {
+                        dropped += 1;
+                    }
+                    whitespace.clear();
+                },
+            }
+        }
+        let used = chunk.len();
+        reader.consume(used);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_pcb");
+
+    #[test]
+    fn depths() {
+        let src = "(kicad_pcb (version 1) \"(not a list)\" (net 1 \"a)\\\"b\")\n\
+                   \t(footprint x (at 1 2) (pad \"1\" (at 0 0))))";
+        // A one byte buffer puts every byte at a chunk boundary.
+        let items = |depth| {
+            let reader = io::BufReader::with_capacity(1, src.as_bytes());
+            super::items(reader, depth).collect::<io::Result<Vec<_>>>().unwrap()
+        };
+        let top: Vec<_> = items(1).into_iter().map(|item| (item.tag, item.text)).collect();
+        assert_eq!(top, [
+            ("version".into(), "(version 1)".into()),
+            ("net".into(), "(net 1 \"a)\\\"b\")".into()),
+            ("footprint".into(), "(footprint x (at 1 2) (pad \"1\" (at 0 0)))".into()),
+        ]);
+
+        let nested = items(2);
+        let found: Vec<_> = nested.iter().map(|item| format!("{}/{}", item.path.join("/"), item.tag)).collect();
+        assert_eq!(found, ["kicad_pcb/footprint/at", "kicad_pcb/footprint/pad"]);
+        assert_eq!(&src[nested[1].offset as usize..][..nested[1].text.len()], nested[1].text);
+        assert!(matches!(nested[1].parse().unwrap(), Sexp::List(items) if items.len() == 3));
+        assert_eq!(items(0)[0].text, src);
+    }
+
+    #[test]
+    fn demo_board() {
+        let sections = crate::scan::children(DEMO).unwrap();
+        let expected: Vec<_> = sections.map(|child| child.text).collect();
+        let items: Vec<_> = items(DEMO.as_bytes(), 1).map(Result::unwrap).collect();
+        assert_eq!(items.iter().map(|item| item.text.as_str()).collect::<Vec<_>>(), expected);
+        assert!(items.iter().all(|item| item.path == ["kicad_pcb"]));
+
+        let mut out = Vec::new();
+        let dropped = retain(DEMO.as_bytes(), &mut out, 1, |item| item.tag != "zone").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(dropped, DEMO.matches("\n\t(zone").count());
+        assert!(dropped > 0 && !out.contains("(zone"));
+        let kept: Vec<_> = expected.iter().filter(|text| !text.starts_with("(zone")).copied().collect();
+        assert_eq!(crate::scan::children(&out).unwrap().map(|child| child.text).collect::<Vec<_>>(), kept);
+        assert!(!out.contains("\n\t\n") && out.ends_with(")\n"));
+
+        let mut same = Vec::new();
+        assert_eq!(retain(DEMO.as_bytes(), &mut same, 1, |_| true).unwrap(), 0);
+        assert_eq!(same, DEMO.as_bytes());
+    }
+
+    #[test]
+    fn errors() {
+        let truncated: Vec<_> = items("(kicad_pcb (a) (b".as_bytes(), 1).collect();
+        assert_eq!(truncated.len(), 2);
+        assert_eq!(truncated[1].as_ref().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        let invalid = items(&b"(a (b \xC3))"[..], 1).next().unwrap().unwrap_err();
+        assert!(invalid.to_string().contains("byte 6"), "{}", invalid);
+    }
+}
@@ -0,0 +1,190 @@
+//! Writes trees in the layout KiCad's own prettifier produces, so saved
+//! output diffs cleanly against files written by KiCad.
+
+use std::fmt;
+
+use crate::{
+    ordering::{reorder_all, OrderPolicy},
+    Sexp,
+};
+
+/// Runs of `(xy ...)` lists share a line until it reaches this column.
+const XY_COLUMN_LIMIT: usize = 99;
+
+/// Atoms that would start past this column are moved to a new line, except
+/// within `(xy ...)` points.
+const TOKEN_WRAP_COLUMN: usize = 72;
+
+struct Writer<'w, W> {
+    out: &'w mut W,
+    column: usize,
+}
+
+fn is_xy(sexp: &Sexp) -> bool {
+    matches!(sexp, Sexp::List(items) if matches!(items.first(), Some(Sexp::Symbol("xy"))))
+}
+
+impl<W: fmt::Write> Writer<'_, W> {
+    fn push(&mut self, s: &str) -> fmt::Result {
+        self.column += s.len();
+        self.out.write_str(s)
+    }
+
+    fn newline(&mut self, depth: usize) -> fmt::Result {
+        self.out.write_char('\n')?;
+        for _ in 0..depth {
+            self.out.write_char('\t')?;
+        }
+        self.column = depth;
+        Ok(())
+    }
+
+    fn atom(&mut self, sexp: &Sexp) -> fmt::Result {
+        match sexp {
+            Sexp::StringLiteral(s) => {
+                self.push("\"")?;
+                self.push(s)?;
+                self.push("\"")
+            },
+            Sexp::Symbol(s) | Sexp::IntLiteral(s) | Sexp::HexIntLiteral(s) | Sexp::FloatLiteral(s) => self.push(s),
+            Sexp::Invalid | Sexp::List(_) => Ok(()),
+        }
+    }
+
+    fn list(&mut self, items: &[Sexp], depth: usize) -> fmt::Result {
+        self.push("(")?;
+        let point = matches!(items.first(), Some(Sexp::Symbol("xy")));
+        let mut wrapped = false;
+        let mut previous: Option<&Sexp> = None;
+        for item in items.iter().filter(|item| !matches!(item, Sexp::Invalid)) {
+            match item {
+                Sexp::List(children) => {
+                    if is_xy(item) && previous.is_some_and(is_xy) && self.column < XY_COLUMN_LIMIT {
+                        self.push(" ")?;
+                    } else {
+                        self.newline(depth + 1)?;
+                    }
+                    self.list(children, depth + 1)?;
+                },
+                atom => {
+                    if previous.is_some() {
+                        if self.column >= TOKEN_WRAP_COLUMN && !point {
+                            self.newline(depth + 1)?;
+                            wrapped = true;
+                        } else {
+                            self.push(" ")?;
+                        }
+                    }
+                    self.atom(atom)?;
+                },
+            }
+            previous = Some(item);
+        }
+        if wrapped || matches!(previous, Some(Sexp::List(_))) {
+            self.newline(depth)?;
+        }
+        self.push(")")
+    }
+
+    fn sexp(&mut self, sexp: &Sexp) -> fmt::Result {
+        match sexp {
+            Sexp::List(items) => self.list(items, 0),
+            atom => self.atom(atom),
+        }
+    }
+}
+
+/// Writes `sexps` as a file, each top-level item followed by a newline.
+pub fn write(out: &mut impl fmt::Write, sexps: &[Sexp]) -> fmt::Result {
+    for sexp in sexps.iter().filter(|sexp| !matches!(sexp, Sexp::Invalid)) {
+        Writer { out: &mut *out, column: 0 }.sexp(sexp)?;
+        out.write_char('\n')?;
+    }
+    Ok(())
+}
+
+/// How [`write_with`] lays out a file beyond KiCad's formatting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Order of the items in each top-level list.
+    pub order: OrderPolicy,
+}
+
+/// Like [`write`], with the items ordered as `options` asks.
+pub fn write_with(out: &mut impl fmt::Write, sexps: &[Sexp], options: &WriteOptions) -> fmt::Result {
+    if options.order == OrderPolicy::Preserve {
+        return write(out, sexps);
+    }
+    let mut sexps = sexps.to_vec();
+    reorder_all(&mut sexps, options.order);
+    write(out, &sexps)
+}
+
+/// Formats `sexps` as a file.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(items = sexps.len())))]
+pub fn to_string(sexps: &[Sexp]) -> String {
+    let mut out = String::new();
+    write(&mut out, sexps).expect("writing to a String cannot fail");
+    out
+}
+
+impl fmt::Display for Sexp<'_> {
+    /// Formats a single item in file layout, without a trailing newline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Writer { out: f, column: 0 }.sexp(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(src: &str) {
+        let sexps = crate::parse(src).unwrap();
+        let written = to_string(&sexps);
+        if let Some((line, (a, b))) = src.lines().zip(written.lines()).enumerate().find(|(_, (a, b))| a != b) {
+            panic!("line {} differs:\n  file: {:?}\n  ours: {:?}", line + 1, a, b);
+        }
+        // Some checked-in files lost KiCad's final newline.
+        assert_eq!(written.trim_end_matches('\n'), src.trim_end_matches('\n'));
+    }
+
+    #[test]
+    fn ordered() {
+        let sexps = crate::parse("(kicad_pcb (segment (uuid \"b\")) (net 0 \"\") (via (uuid \"a\")))").unwrap();
+        let mut out = String::new();
+        write_with(&mut out, &sexps, &WriteOptions::default()).unwrap();
+        assert_eq!(out, to_string(&sexps));
+
+        let mut out = String::new();
+        write_with(&mut out, &sexps, &WriteOptions { order: OrderPolicy::ByUuid }).unwrap();
+        let via = "\t(via\n\t\t(uuid \"a\")\n\t)\n";
+        assert_eq!(out, format!("(kicad_pcb\n{}\t(net 0 \"\")\n\t(segment\n\t\t(uuid \"b\")\n\t)\n)\n", via));
+    }
+
+    #[test]
+    fn reference_files() {
+        round_trip(include_str!("../../reference-files/empty/empty.kicad_pcb"));
+        round_trip(include_str!("../../reference-files/empty/empty.kicad_sch"));
+        round_trip(include_str!("../../reference-files/demo/demo.kicad_pcb"));
+        round_trip(include_str!("../../reference-files/demo/demo.kicad_sch"));
+        round_trip(include_str!("../../reference-files/demo/power.kicad_sch"));
+        round_trip(include_str!("../../reference-files/demo/demo.kicad_wks"));
+    }
+
+    #[test]
+    fn layout() {
+        let sexps = crate::parse("(a (b 1 \"x y\") (pts (xy 0 0) (xy 1 1)) c)").unwrap();
+
+        assert_eq!(to_string(&sexps), "(a\n\t(b 1 \"x y\")\n\t(pts\n\t\t(xy 0 0) (xy 1 1)\n\t) c)\n");
+        assert_eq!(sexps[0].to_string(), to_string(&sexps).trim_end());
+
+        let long = format!("(data {})", ["\"0123456789abcdef\""; 6].join(" "));
+        let sexps = crate::parse(&long).unwrap();
+        assert_eq!(
+            to_string(&sexps),
+            "(data \"0123456789abcdef\" \"0123456789abcdef\" \"0123456789abcdef\" \"0123456789abcdef\"\n\t\
+             \"0123456789abcdef\" \"0123456789abcdef\"\n)\n"
+        );
+    }
+}
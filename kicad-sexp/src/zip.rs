@@ -0,0 +1,379 @@
+//! Reading and writing zip archives, as KiCad uses for project backups and
+//! archives.
+//!
+//! The reader handles stored and deflated entries, inflated with
+//! [`miniz_oxide`], and checks their size and CRC; Zip64, encryption and
+//! split archives are not supported. The writer
+//! stores entries uncompressed, which every zip tool reads.
+
+use std::io;
+
+/// Signature of the end of central directory record.
+const END: u32 = 0x0605_4b50;
+const CENTRAL: u32 = 0x0201_4b50;
+const LOCAL: u32 = 0x0403_4b50;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+/// Flag bit of names and comments encoded as UTF-8.
+const UTF8: u16 = 1 << 11;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("zip: {}", message))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> io::Result<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or_else(|| invalid("data is truncated"))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> io::Result<u32> {
+    let bytes = data.get(offset..offset + 4).ok_or_else(|| invalid("data is truncated"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The CRC-32 of `data`, as zip stores it.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| CRC_TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// A date and time with a resolution of two seconds, as zip entries store
+/// their modification time. Zip has no time zone; KiCad writes local time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// The UTC time `seconds` after 1970-01-01.
+    pub fn from_unix(seconds: u64) -> Self {
+        // Days to civil date, after Howard Hinnant's algorithm.
+        let days = (seconds / 86400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        let time = seconds % 86400;
+        DateTime {
+            year: year as u16,
+            month: month as u8,
+            day: (day_of_year - (153 * mp + 2) / 5 + 1) as u8,
+            hour: (time / 3600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
+    /// Seconds since 1970-01-01, taking the time as UTC.
+    pub fn to_unix(&self) -> u64 {
+        let (month, day) = (i64::from(self.month), i64::from(self.day));
+        let year = i64::from(self.year) - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+        let time = u64::from(self.hour) * 3600 + u64::from(self.minute) * 60 + u64::from(self.second);
+        days.max(0) as u64 * 86400 + time
+    }
+
+    fn from_dos(date: u16, time: u16) -> Self {
+        DateTime {
+            year: 1980 + (date >> 9),
+            month: (date >> 5 & 0xF) as u8,
+            day: (date & 0x1F) as u8,
+            hour: (time >> 11) as u8,
+            minute: (time >> 5 & 0x3F) as u8,
+            second: ((time & 0x1F) * 2) as u8,
+        }
+    }
+
+    /// DOS date and time; years outside 1980..=2107 are clamped.
+    fn to_dos(self) -> (u16, u16) {
+        let year = self.year.clamp(1980, 2107) - 1980;
+        let date = year << 9 | u16::from(self.month) << 5 | u16::from(self.day);
+        let time = u16::from(self.hour) << 11 | u16::from(self.minute) << 5 | u16::from(self.second / 2);
+        (date, time)
+    }
+}
+
+/// An entry of the central directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZipEntry {
+    /// Path inside the archive, `/` separated; directories end in `/`.
+    pub name: String,
+    pub modified: DateTime,
+    /// Uncompressed size.
+    pub size: u64,
+    method: u16,
+    pub(crate) crc: u32,
+    compressed_size: u64,
+    offset: u64,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// A zip archive held in memory.
+#[derive(Clone, Debug)]
+pub struct ZipArchive {
+    data: Vec<u8>,
+    entries: Vec<ZipEntry>,
+}
+
+impl ZipArchive {
+    /// Reads the central directory of the archive in `data`.
+    pub fn new(data: Vec<u8>) -> io::Result<Self> {
+        let search = data.len().saturating_sub(22 + usize::from(u16::MAX));
+        let end = (search..data.len().saturating_sub(21))
+            .rev()
+            .find(|&at| u32_at(&data, at).is_ok_and(|signature| signature == END))
+            .ok_or_else(|| invalid("no end of central directory"))?;
+        let count = u16_at(&data, end + 10)?;
+        let mut at = u32_at(&data, end + 16)? as usize;
+        if count == u16::MAX || at == u32::MAX as usize {
+            return Err(invalid("Zip64 archives are not supported"));
+        }
+
+        let mut entries = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            if u32_at(&data, at)? != CENTRAL {
+                return Err(invalid("bad central directory entry"));
+            }
+            let flags = u16_at(&data, at + 8)?;
+            if flags & 1 != 0 {
+                return Err(invalid("encrypted entries are not supported"));
+            }
+            let (name_len, extra_len, comment_len) =
+                (u16_at(&data, at + 28)? as usize, u16_at(&data, at + 30)? as usize, u16_at(&data, at + 32)? as usize);
+            let name = data.get(at + 46..at + 46 + name_len).ok_or_else(|| invalid("data is truncated"))?;
+            // Names without the flag are CP437, which only matters past ASCII.
+            let name = match flags & UTF8 {
+                0 => name.iter().map(|&b| char::from(b)).collect(),
+                _ => String::from_utf8_lossy(name).into_owned(),
+            };
+            entries.push(ZipEntry {
+                name,
+                modified: DateTime::from_dos(u16_at(&data, at + 14)?, u16_at(&data, at + 12)?),
+                size: u32_at(&data, at + 24)?.into(),
+                method: u16_at(&data, at + 10)?,
+                crc: u32_at(&data, at + 16)?,
+                compressed_size: u32_at(&data, at + 20)?.into(),
+                offset: u32_at(&data, at + 42)?.into(),
+            });
+            at += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(ZipArchive { data, entries })
+    }
+
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// The uncompressed contents of `entry`, checked against its CRC.
+    pub fn read(&self, entry: &ZipEntry) -> io::Result<Vec<u8>> {
+        let at = entry.offset as usize;
+        if u32_at(&self.data, at)? != LOCAL {
+            return Err(invalid("bad local header"));
+        }
+        let start = at + 30 + u16_at(&self.data, at + 26)? as usize + u16_at(&self.data, at + 28)? as usize;
+        let stored = self.data.get(start..start + entry.compressed_size as usize);
+        let stored = stored.ok_or_else(|| invalid("data is truncated"))?;
+        let data = match entry.method {
+            STORED => stored.to_vec(),
+            DEFLATED => inflate(stored, entry.size as usize)?,
+            method => return Err(invalid(&format!("compression method {} is not supported", method))),
+        };
+        if data.len() as u64 != entry.size || crc32(&data) != entry.crc {
+            return Err(invalid(&format!("{} is corrupt", entry.name)));
+        }
+        Ok(data)
+    }
+
+    /// The uncompressed contents of the entry called `name`.
+    pub fn read_file(&self, name: &str) -> io::Result<Vec<u8>> {
+        let not_found = || io::Error::new(io::ErrorKind::NotFound, format!("{} not found", name));
+        let entry = self.entry(name).ok_or_else(not_found)?;
+        self.read(entry)
+    }
+}
+
+/// Writes an archive of stored entries into memory.
+#[derive(Clone, Debug, Default)]
+pub struct ZipWriter {
+    out: Vec<u8>,
+    central: Vec<u8>,
+    count: u16,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file. Fails once the archive would need Zip64, past 65534
+    /// entries or 4 GiB.
+    pub fn add(&mut self, name: &str, modified: DateTime, data: &[u8]) -> io::Result<()> {
+        let too_large = || invalid("archive is too large without Zip64");
+        let size = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let offset = u32::try_from(self.out.len()).map_err(|_| too_large())?;
+        let name_len = u16::try_from(name.len()).map_err(|_| invalid("entry name is too long"))?;
+        if self.count == u16::MAX - 1 {
+            return Err(too_large());
+        }
+        let (date, time) = modified.to_dos();
+        let flags = if name.is_ascii() { 0 } else { UTF8 };
+        // Version needed, flags, method, time, date, CRC and sizes, shared by both headers.
+        let mut common = Vec::with_capacity(26);
+        for half in [20, flags, STORED, time, date] {
+            common.extend_from_slice(&u16::to_le_bytes(half));
+        }
+        for word in [crc32(data), size, size] {
+            common.extend_from_slice(&word.to_le_bytes());
+        }
+        common.extend_from_slice(&name_len.to_le_bytes());
+
+        self.out.extend_from_slice(&LOCAL.to_le_bytes());
+        self.out.extend_from_slice(&common);
+        self.out.extend_from_slice(&[0, 0]);
+        self.out.extend_from_slice(name.as_bytes());
+        self.out.extend_from_slice(data);
+        u32::try_from(self.out.len()).map_err(|_| too_large())?;
+
+        self.central.extend_from_slice(&CENTRAL.to_le_bytes());
+        self.central.extend_from_slice(&[20, 0]);
+        self.central.extend_from_slice(&common);
+        // Extra and comment lengths, disk, internal and external attributes.
+        self.central.extend_from_slice(&[0; 12]);
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+        self.count += 1;
+        Ok(())
+    }
+
+    /// The finished archive.
+    pub fn finish(mut self) -> io::Result<Vec<u8>> {
+        let offset = u32::try_from(self.out.len()).map_err(|_| invalid("archive is too large without Zip64"))?;
+        let size = self.central.len() as u32;
+        self.out.append(&mut self.central);
+        self.out.extend_from_slice(&END.to_le_bytes());
+        self.out.extend_from_slice(&[0; 4]);
+        self.out.extend_from_slice(&self.count.to_le_bytes());
+        self.out.extend_from_slice(&self.count.to_le_bytes());
+        self.out.extend_from_slice(&size.to_le_bytes());
+        self.out.extend_from_slice(&offset.to_le_bytes());
+        self.out.extend_from_slice(&[0, 0]);
+        Ok(self.out)
+    }
+}
+
+/// Decodes a raw deflate stream (RFC 1951) of at most `size` bytes, the
+/// size the archive declares; longer output is an error rather than an
+/// allocation the header did not announce.
+pub(crate) fn inflate(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(data, size).map_err(|error| match error.status {
+        miniz_oxide::inflate::TINFLStatus::HasMoreOutput => invalid("entry is larger than its declared size"),
+        _ => invalid(&format!("bad deflate data: {}", error)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn dates() {
+        let time = DateTime { year: 2026, month: 10, day: 14, hour: 12, minute: 30, second: 6 };
+        assert_eq!(DateTime::from_unix(time.to_unix()), time);
+        assert_eq!(DateTime::from_unix(0), DateTime { year: 1970, month: 1, day: 1, ..Default::default() });
+        assert_eq!(DateTime::from_unix(951_782_400).day, 29);
+        let (date, dos) = time.to_dos();
+        assert_eq!(DateTime::from_dos(date, dos), time);
+    }
+
+    #[test]
+    fn inflate_fixed() {
+        // zlib's raw deflate of the text, a single block with the fixed codes.
+        let data = [
+            0xd3, 0xc8, 0xce, 0x4c, 0x4e, 0x4c, 0x89, 0x2f, 0x48, 0x4e, 0x52, 0xd0, 0xc0, 0xc6, 0xd4, 0xd4, 0xd4, 0x04,
+            0x00,
+        ];
+        assert_eq!(inflate(&data, 35).unwrap(), b"(kicad_pcb (kicad_pcb (kicad_pcb)))");
+        assert_eq!(inflate(&[0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'], 3).unwrap(), b"abc");
+        assert!(inflate(&data[..10], 35).is_err());
+        assert!(inflate(&[0x07], 35).is_err());
+        let error = inflate(&data, 34).unwrap_err();
+        assert_eq!(error.to_string(), "zip: entry is larger than its declared size");
+    }
+
+    #[test]
+    fn deflated_archive() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../reference-files/demo");
+        let data = std::fs::read(format!("{}/demo-backups/demo-2026-10-14_120000.zip", dir)).unwrap();
+        let archive = ZipArchive::new(data).unwrap();
+        assert_eq!(archive.entries().len(), 11);
+        for entry in archive.entries() {
+            assert_eq!(entry.method, DEFLATED);
+            let original = std::fs::read(format!("{}/{}", dir, entry.name)).unwrap();
+            assert_eq!(archive.read(entry).unwrap(), original, "{}", entry.name);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let time = DateTime { year: 2026, month: 10, day: 14, hour: 9, minute: 5, second: 0 };
+        let mut writer = ZipWriter::new();
+        writer.add("demo.kicad_pro", time, b"{}").unwrap();
+        writer.add("lib.pretty/R.kicad_mod", time, b"(footprint \"R\")").unwrap();
+        writer.add("größe.kicad_sch", time, b"(kicad_sch)").unwrap();
+        let archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let names: Vec<_> = archive.entries().iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["demo.kicad_pro", "lib.pretty/R.kicad_mod", "größe.kicad_sch"]);
+        assert_eq!(archive.entries()[1].modified, time);
+        assert_eq!(archive.read_file("lib.pretty/R.kicad_mod").unwrap(), b"(footprint \"R\")");
+        assert_eq!(archive.read_file("missing").unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        let mut data = archive.data.clone();
+        let at = data.windows(4).position(|window| window == b"{}PK").unwrap();
+        data[at] = b'[';
+        let error = ZipArchive::new(data).unwrap().read_file("demo.kicad_pro").unwrap_err();
+        assert_eq!(error.to_string(), "zip: demo.kicad_pro is corrupt");
+        assert!(ZipArchive::new(b"not a zip".to_vec()).is_err());
+    }
+}
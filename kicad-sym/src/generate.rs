@@ -0,0 +1,517 @@
+//! Symbols generated from a pin table, for libraries built from vendor
+//! pinouts instead of drawn in the symbol editor.
+//!
+//! [`BoxSymbol`] draws the rectangular body of an IC: inputs on the left,
+//! outputs on the right, supplies on top and grounds at the bottom unless a
+//! pin asks for a side, with the other pins shared out between left and
+//! right. Pins of different groups on a side are kept apart by a gap, and
+//! large parts can be split into units by group or by pin count, with the
+//! supply pins in a unit of their own. [`Connector`] draws KiCad's generic
+//! connector symbols.
+//!
+//! ```no_run
+//! use kicad_sym::{
+//!     generate::{BoxSymbol, PinDef, Split},
+//!     PinType, SymbolLib,
+//! };
+//!
+//! let pins = vec![
+//!     PinDef::new("1", "VDD", PinType::PowerIn),
+//!     PinDef::new("2", "SDA", PinType::Bidirectional),
+//!     PinDef::new("3", "SCL", PinType::Input),
+//!     PinDef::new("4", "GND", PinType::PowerIn),
+//! ];
+//! let symbol = BoxSymbol { split: Split::Single, ..BoxSymbol::new("SENSOR", pins) }.build()?;
+//! let mut library = SymbolLib::new();
+//! library.symbols.push(symbol);
+//! std::fs::write("sensors.kicad_sym", library.to_string())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashSet;
+
+use kicad_common::{ConvertError, Effects, Error, Font, Point, Position, Property, Stroke};
+
+use crate::{Fill, GraphicItem, GraphicKind, Pin, PinAlternate, PinShape, PinType, Symbol, SymbolUnit};
+
+/// KiCad's symbol grid, 100 mils.
+const GRID: f64 = 2.54;
+/// Length of IC pins.
+const PIN_LENGTH: f64 = 2.54;
+/// Inset of pin names in the body.
+const NAME_OFFSET: f64 = 0.508;
+/// Room a character of a pin name takes at the default text size, on the
+/// generous side.
+const CHAR_WIDTH: f64 = 1.27;
+
+/// A side of a symbol body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// A row of a pin table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PinDef {
+    pub number: String,
+    pub name: String,
+    pub kind: PinType,
+    pub shape: PinShape,
+    /// Side of the body; `None` picks one from the pin's type.
+    pub side: Option<Side>,
+    /// Group the pin is drawn with, such as a GPIO port; groups on a side
+    /// are kept apart, and [`Split::Groups`] gives each a unit.
+    pub group: Option<String>,
+    pub alternates: Vec<PinAlternate>,
+}
+
+impl PinDef {
+    pub fn new(number: &str, name: &str, kind: PinType) -> Self {
+        PinDef {
+            number: number.to_string(),
+            name: name.to_string(),
+            kind,
+            shape: PinShape::Line,
+            side: None,
+            group: None,
+            alternates: Vec::new(),
+        }
+    }
+
+    /// The side the pin goes on when it names none.
+    fn default_side(&self) -> Option<Side> {
+        let name = self.name.to_uppercase();
+        match self.kind {
+            PinType::Input => Some(Side::Left),
+            PinType::Output
+            | PinType::TriState
+            | PinType::OpenCollector
+            | PinType::OpenEmitter
+            | PinType::PowerOut => Some(Side::Right),
+            PinType::PowerIn if name.contains("GND") || name.starts_with("VSS") || name.starts_with("VEE") => {
+                Some(Side::Bottom)
+            },
+            PinType::PowerIn => Some(Side::Top),
+            _ => None,
+        }
+    }
+
+    fn is_power(&self) -> bool {
+        matches!(self.kind, PinType::PowerIn | PinType::PowerOut)
+    }
+}
+
+/// The pins of a unit, with the unit's name.
+type Unit<'p> = (Option<String>, Vec<&'p PinDef>);
+
+/// How [`BoxSymbol`] splits its pins into units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Split {
+    /// One unit with every pin.
+    #[default]
+    Single,
+    /// A unit for each pin group, in the order the groups first appear;
+    /// pins without a group share one.
+    Groups,
+    /// Units of at most this many pins, in pin table order.
+    MaxPins(usize),
+}
+
+/// A symbol with a rectangular body and pins from a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoxSymbol {
+    pub name: String,
+    /// Reference prefix, `U` by default.
+    pub reference: String,
+    pub footprint: String,
+    pub datasheet: String,
+    pub description: String,
+    pub keywords: Option<String>,
+    /// Footprint filters, as in `"SOIC*3.9x4.9mm*P1.27mm*"`.
+    pub fp_filters: Option<String>,
+    pub pins: Vec<PinDef>,
+    pub split: Split,
+    /// Supply pins get a unit of their own, after the others.
+    pub power_unit: bool,
+}
+
+impl BoxSymbol {
+    pub fn new(name: &str, pins: Vec<PinDef>) -> Self {
+        BoxSymbol {
+            name: name.to_string(),
+            reference: "U".to_string(),
+            footprint: String::new(),
+            datasheet: "~".to_string(),
+            description: String::new(),
+            keywords: None,
+            fp_filters: None,
+            pins,
+            split: Split::Single,
+            power_unit: false,
+        }
+    }
+
+    /// The symbol. Fails for an empty pin table, pins sharing a number, or
+    /// units of no pins.
+    pub fn build(&self) -> Result<Symbol, Error> {
+        if self.pins.is_empty() {
+            return Err(ConvertError::new(format!("{} has no pins", self.name)).into());
+        }
+        let mut numbers = HashSet::new();
+        if let Some(pin) = self.pins.iter().find(|pin| !numbers.insert(pin.number.as_str())) {
+            return Err(ConvertError::new(format!("two pins of {} are numbered {}", self.name, pin.number)).into());
+        }
+        let mut units = self.units()?;
+        let single = units.len() == 1;
+        let mut symbol = Symbol {
+            name: self.name.clone(),
+            pin_name_offset: NAME_OFFSET,
+            in_bom: true,
+            on_board: true,
+            ..Default::default()
+        };
+        let mut extent = (0.0f64, 0.0f64);
+        for (i, (unit_name, pins)) in units.drain(..).enumerate() {
+            let layout = Layout::new(&pins);
+            extent = (extent.0.max(layout.half_width), extent.1.max(layout.half_height));
+            let unit = i as u32 + 1;
+            let body = layout.body();
+            if single {
+                let shared = format!("{}_0_1", self.name);
+                symbol.units.push(SymbolUnit { name: shared, convert: 1, graphics: vec![body], ..Default::default() });
+                let name = format!("{}_1_1", self.name);
+                symbol.units.push(SymbolUnit { name, unit, convert: 1, pins: layout.pins, ..Default::default() });
+            } else {
+                symbol.units.push(SymbolUnit {
+                    name: format!("{}_{}_1", self.name, unit),
+                    unit,
+                    convert: 1,
+                    unit_name,
+                    graphics: vec![body],
+                    pins: layout.pins,
+                });
+            }
+        }
+        let top = extent.1 + GRID / 2.0;
+        symbol.properties = vec![
+            property("Reference", &self.reference, Some(Position::new(0.0, top, 0.0)), false),
+            property("Value", &self.name, Some(Position::new(0.0, -top, 0.0)), false),
+            property("Footprint", &self.footprint, None, true),
+            property("Datasheet", &self.datasheet, None, true),
+            property("Description", &self.description, None, true),
+        ];
+        if let Some(keywords) = &self.keywords {
+            symbol.properties.push(property("ki_keywords", keywords, None, true));
+        }
+        if let Some(filters) = &self.fp_filters {
+            symbol.properties.push(property("ki_fp_filters", filters, None, true));
+        }
+        Ok(symbol)
+    }
+
+    fn units(&self) -> Result<Vec<Unit<'_>>, Error> {
+        let (power, pins): (Vec<&PinDef>, Vec<&PinDef>) =
+            self.pins.iter().partition(|pin| self.power_unit && pin.is_power());
+        let mut units: Vec<Unit> = match self.split {
+            Split::Single => vec![(None, pins)],
+            Split::Groups => {
+                let mut groups: Vec<Unit> = Vec::new();
+                for pin in pins {
+                    match groups.iter_mut().find(|(group, _)| *group == pin.group) {
+                        Some((_, pins)) => pins.push(pin),
+                        None => groups.push((pin.group.clone(), vec![pin])),
+                    }
+                }
+                groups
+            },
+            Split::MaxPins(0) => {
+                return Err(ConvertError::new(format!("units of {} cannot have no pins", self.name)).into());
+            },
+            Split::MaxPins(count) => pins.chunks(count).map(|pins| (None, pins.to_vec())).collect(),
+        };
+        units.retain(|(_, pins)| !pins.is_empty());
+        if !power.is_empty() {
+            units.push((self.split.eq(&Split::Groups).then(|| "Power".to_string()), power));
+        }
+        Ok(units)
+    }
+}
+
+/// Placed pins and the body of one unit.
+struct Layout {
+    half_width: f64,
+    half_height: f64,
+    pins: Vec<Pin>,
+}
+
+impl Layout {
+    fn new(defs: &[&PinDef]) -> Self {
+        let mut sides: [Vec<&PinDef>; 4] = Default::default();
+        let mut free = Vec::new();
+        for &pin in defs {
+            match pin.side.or_else(|| pin.default_side()) {
+                Some(side) => sides[side as usize].push(pin),
+                None => free.push(pin),
+            }
+        }
+        // The free pins go to the left until it matches the right, then
+        // the rest to the right.
+        let (left, right) = (sides[0].len(), sides[1].len());
+        let to_left = ((right + free.len()).saturating_sub(left)).div_ceil(2).min(free.len());
+        let mut free = free.into_iter();
+        sides[0].extend(free.by_ref().take(to_left));
+        sides[1].extend(free);
+        for side in &mut sides {
+            side.sort_by_key(|pin| defs.iter().position(|def| std::ptr::eq(*def, *pin)));
+        }
+        let slots: Vec<Vec<Option<&PinDef>>> = sides.iter().map(|side| with_gaps(side)).collect();
+        let longest = |side: usize| sides[side].iter().map(|pin| pin.name.chars().count()).max();
+        let reserve = |side: usize| longest(side).map_or(0.0, |chars| chars as f64 * CHAR_WIDTH + NAME_OFFSET);
+
+        let rows = slots[0].len().max(slots[1].len()).max(1);
+        let columns = slots[2].len().max(slots[3].len()).max(1);
+        let names = reserve(0).max(reserve(1)) + GRID / 2.0;
+        let half_width = ceil_grid(names.max((columns - 1) as f64 * GRID / 2.0 + GRID));
+        let half_height = ceil_grid((rows - 1) as f64 * GRID / 2.0 + GRID + reserve(2).max(reserve(3)));
+
+        let mut pins = Vec::new();
+        for (side, slots) in slots.iter().enumerate() {
+            let start = ((slots.len().max(1) - 1) / 2) as f64 * GRID;
+            for (i, slot) in slots.iter().enumerate() {
+                let Some(def) = slot else {
+                    continue;
+                };
+                let step = i as f64 * GRID;
+                let at = match side {
+                    0 => Position::new(-half_width - PIN_LENGTH, start - step, 0.0),
+                    1 => Position::new(half_width + PIN_LENGTH, start - step, 180.0),
+                    2 => Position::new(step - start, half_height + PIN_LENGTH, 270.0),
+                    _ => Position::new(step - start, -half_height - PIN_LENGTH, 90.0),
+                };
+                pins.push(pin(def, at, PIN_LENGTH));
+            }
+        }
+        pins.sort_by_key(|pin| defs.iter().position(|def| def.number == pin.number));
+        Layout { half_width, half_height, pins }
+    }
+
+    fn body(&self) -> GraphicItem {
+        rectangle(Point::new(-self.half_width, self.half_height), Point::new(self.half_width, -self.half_height))
+    }
+}
+
+/// The pins of a side with an empty slot between groups.
+fn with_gaps<'p>(pins: &[&'p PinDef]) -> Vec<Option<&'p PinDef>> {
+    let mut slots = Vec::new();
+    for (i, &pin) in pins.iter().enumerate() {
+        if i > 0 && pins[i - 1].group != pin.group {
+            slots.push(None);
+        }
+        slots.push(Some(pin));
+    }
+    slots
+}
+
+/// A generic connector symbol, as in KiCad's `Connector_Generic` library:
+/// one row of pins on the left, or two numbered odd on the left and even
+/// on the right.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Connector {
+    /// Rows of pins, one or two.
+    pub rows: u32,
+    /// Pins in each row.
+    pub pins: u32,
+}
+
+impl Connector {
+    /// `Conn_01x03` or `Conn_02x05_Odd_Even`.
+    pub fn name(&self) -> String {
+        let name = format!("Conn_{:02}x{:02}", self.rows, self.pins);
+        if self.rows == 2 { format!("{}_Odd_Even", name) } else { name }
+    }
+
+    pub fn build(&self) -> Result<Symbol, Error> {
+        if !matches!(self.rows, 1 | 2) || self.pins == 0 {
+            return Err(ConvertError::new(format!("no generic connector has {}x{} pins", self.rows, self.pins)).into());
+        }
+        let name = self.name();
+        let first = ((self.pins - 1) / 2) as f64 * GRID;
+        let last = first - f64::from(self.pins - 1) * GRID;
+        let (top, bottom) = (first + GRID / 2.0, last - GRID / 2.0);
+        let right = if self.rows == 2 { 3.81 } else { 1.27 };
+        let mut pins = Vec::new();
+        for i in 0..self.pins {
+            let y = first - f64::from(i) * GRID;
+            for row in 0..self.rows {
+                let number = (i * self.rows + row + 1).to_string();
+                let def = PinDef::new(&number, &format!("Pin_{}", number), PinType::Passive);
+                let at = if row == 0 { Position::new(-5.08, y, 0.0) } else { Position::new(7.62, y, 180.0) };
+                pins.push(pin(&def, at, 3.81));
+            }
+        }
+        let middle = (right - 1.27) / 2.0;
+        let description = match self.rows {
+            1 => format!("Generic connector, single row, 01x{:02}", self.pins),
+            _ => format!(
+                "Generic connector, double row, 02x{:02}, odd/even pin numbering scheme (row 1 odd numbers, row 2 \
+                 even numbers)",
+                self.pins
+            ),
+        };
+        Ok(Symbol {
+            name: name.clone(),
+            hide_pin_names: true,
+            pin_name_offset: 1.016,
+            in_bom: true,
+            on_board: true,
+            properties: vec![
+                property("Reference", "J", Some(Position::new(middle, top + 1.27, 0.0)), false),
+                property("Value", &name, Some(Position::new(middle, bottom - 1.27, 0.0)), false),
+                property("Footprint", "", None, true),
+                property("Datasheet", "~", None, true),
+                property("Description", &description, None, true),
+                property("ki_keywords", "connector", None, true),
+                property("ki_fp_filters", &format!("Connector*:*_{}x??_*", self.rows), None, true),
+            ],
+            units: vec![SymbolUnit {
+                name: format!("{}_1_1", name),
+                unit: 1,
+                convert: 1,
+                graphics: vec![rectangle(Point::new(-1.27, top), Point::new(right, bottom))],
+                pins,
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+    }
+}
+
+/// Text in KiCad's default 50 mil size.
+fn effects(hide: bool) -> Effects {
+    Effects { font: Font { height: 1.27, width: 1.27, ..Default::default() }, hide, ..Default::default() }
+}
+
+/// A property at `at`, or hidden at the origin.
+fn property(key: &str, value: &str, at: Option<Position>, hide: bool) -> Property {
+    Property {
+        key: key.to_string(),
+        value: value.to_string(),
+        at: Some(at.unwrap_or_default()),
+        effects: Some(effects(hide)),
+        hide,
+        ..Default::default()
+    }
+}
+
+fn pin(def: &PinDef, at: Position, length: f64) -> Pin {
+    Pin {
+        kind: def.kind,
+        shape: def.shape,
+        at: Position::new(round(at.x), round(at.y), at.angle),
+        length,
+        hide: false,
+        name: if def.name.is_empty() { "~".to_string() } else { def.name.clone() },
+        name_effects: Some(effects(false)),
+        number: def.number.clone(),
+        number_effects: Some(effects(false)),
+        alternates: def.alternates.clone(),
+    }
+}
+
+fn rectangle(start: Point, end: Point) -> GraphicItem {
+    let (start, end) = (Point::new(round(start.x), round(start.y)), Point::new(round(end.x), round(end.y)));
+    GraphicItem {
+        kind: GraphicKind::Rectangle { start, end },
+        stroke: Stroke { width: 0.254, ..Default::default() },
+        fill: Fill::Background,
+    }
+}
+
+/// Rounds up to the symbol grid.
+fn ceil_grid(value: f64) -> f64 {
+    round((value / GRID - 1e-9).ceil() * GRID)
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1e6).round() / 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SymbolLib;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_sym");
+
+    fn pins(symbol: &Symbol, unit: u32) -> Vec<(&str, Position)> {
+        symbol.pins(unit, 1).map(|pin| (pin.number.as_str(), pin.at)).collect()
+    }
+
+    #[test]
+    fn connector_matches_kicad() {
+        let demo = SymbolLib::parse(DEMO).unwrap();
+        let connector = Connector { rows: 1, pins: 3 };
+        assert_eq!(connector.build().unwrap(), *demo.symbol(&connector.name()).unwrap());
+
+        let double = Connector { rows: 2, pins: 2 }.build().unwrap();
+        assert_eq!(double.name, "Conn_02x02_Odd_Even");
+        let at = pins(&double, 1);
+        assert_eq!(at[1], ("2", Position::new(7.62, 0.0, 180.0)));
+        assert_eq!(at[2], ("3", Position::new(-5.08, -2.54, 0.0)));
+        assert!(Connector { rows: 3, pins: 2 }.build().is_err());
+    }
+
+    #[test]
+    fn box_symbol() {
+        let mut table = vec![
+            PinDef::new("1", "VDD", PinType::PowerIn),
+            PinDef::new("2", "SDA", PinType::Bidirectional),
+            PinDef::new("3", "SCL", PinType::Input),
+            PinDef::new("4", "GND", PinType::PowerIn),
+            PinDef::new("5", "INT", PinType::OpenCollector),
+            PinDef::new("6", "ADDR", PinType::Passive),
+        ];
+        table[1].group = Some("I2C".to_string());
+        table[2].group = Some("I2C".to_string());
+        let symbol = BoxSymbol { keywords: Some("sensor".to_string()), ..BoxSymbol::new("SENSOR", table.clone()) }
+            .build()
+            .unwrap();
+        assert_eq!(symbol.unit_count(), 1);
+        assert_eq!(symbol.property("Reference"), Some("U"));
+        assert_eq!(symbol.property("ki_keywords"), Some("sensor"));
+        let GraphicKind::Rectangle { start, end } = symbol.units[0].graphics[0].kind else { panic!() };
+        assert_eq!((start, end), (Point::new(-7.62, 10.16), Point::new(7.62, -10.16)));
+        // SCL is an input and SDA joins it on the left, leaving the passive
+        // ADDR to even out the right under the open collector INT.
+        assert_eq!(
+            pins(&symbol, 1),
+            [
+                ("1", Position::new(0.0, 12.7, 270.0)),
+                ("2", Position::new(-10.16, 0.0, 0.0)),
+                ("3", Position::new(-10.16, -2.54, 0.0)),
+                ("4", Position::new(0.0, -12.7, 90.0)),
+                ("5", Position::new(10.16, 0.0, 180.0)),
+                ("6", Position::new(10.16, -2.54, 180.0)),
+            ]
+        );
+        let library = SymbolLib { symbols: vec![symbol], ..SymbolLib::new() };
+        assert_eq!(SymbolLib::parse(&library.to_string()).unwrap(), library);
+
+        let split = BoxSymbol { split: Split::Groups, power_unit: true, ..BoxSymbol::new("SENSOR", table.clone()) };
+        let split = split.build().unwrap();
+        let names: Vec<_> = split.units.iter().map(|unit| (unit.name.as_str(), unit.unit_name.as_deref())).collect();
+        assert_eq!(names, [("SENSOR_1_1", Some("I2C")), ("SENSOR_2_1", None), ("SENSOR_3_1", Some("Power"))]);
+        let numbers: Vec<_> = split.pins(3, 1).map(|pin| pin.number.as_str()).collect();
+        assert_eq!(numbers, ["1", "4"]);
+
+        let chunks = BoxSymbol { split: Split::MaxPins(4), ..BoxSymbol::new("SENSOR", table.clone()) };
+        assert_eq!(chunks.build().unwrap().unit_count(), 2);
+
+        table[5].number = "1".to_string();
+        let error = BoxSymbol::new("SENSOR", table).build().unwrap_err();
+        assert_eq!(error.to_string(), "two pins of SENSOR are numbered 1");
+    }
+}
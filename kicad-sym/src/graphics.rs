@@ -0,0 +1,138 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    geometry::child_points,
+    Color, Effects, Point, Position, Stroke,
+};
+use kicad_sexp::SexpBuilder;
+
+/// Interior of a closed symbol drawing.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fill {
+    #[default]
+    None,
+    /// Filled with the outline color.
+    Outline,
+    /// Filled with the body background color.
+    Background,
+    Color(Color),
+}
+
+impl Fill {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let kind = match self {
+            Fill::None => "none",
+            Fill::Outline => "outline",
+            Fill::Background => "background",
+            Fill::Color(_) => "color",
+        };
+        let color = match self {
+            Fill::Color(color) => Some(color.to_sexp()),
+            _ => None,
+        };
+        SexpBuilder::new("fill").child(SexpBuilder::new("type").symbol(kind)).child_opt(color)
+    }
+}
+
+impl FromSexp for Fill {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let Some(kind) = list.child("type") else {
+            return Ok(Fill::None);
+        };
+        Ok(match kind.symbol(0).map_err(|e| e.within(list.tag()))? {
+            "none" => Fill::None,
+            "outline" => Fill::Outline,
+            "background" => Fill::Background,
+            "color" => Fill::Color(list.parse("color")?.unwrap_or_default()),
+            other => return Err(list.error(format!("unknown fill type `{}`", other))),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GraphicKind {
+    Polyline(Vec<Point>),
+    Rectangle { start: Point, end: Point },
+    Circle { center: Point, radius: f64 },
+    /// A three-point arc, drawn from `start` through `mid` to `end`.
+    Arc { start: Point, mid: Point, end: Point },
+    /// A cubic Bézier curve given by its four control points.
+    Bezier(Vec<Point>),
+    Text { text: String, at: Position, effects: Effects },
+}
+
+/// A drawing in a symbol unit's body.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphicItem {
+    pub kind: GraphicKind,
+    /// Default for texts.
+    pub stroke: Stroke,
+    pub fill: Fill,
+}
+
+impl GraphicItem {
+    /// Returns true for the tags [`GraphicItem`] can be read from.
+    pub(crate) fn is_graphic_tag(tag: &str) -> bool {
+        matches!(tag, "polyline" | "rectangle" | "circle" | "arc" | "bezier" | "text")
+    }
+}
+
+impl GraphicItem {
+    /// The drawing as KiCad writes it; texts carry no stroke or fill.
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let point = |tag: &str, p: &Point| SexpBuilder::new(tag).float(p.x).float(p.y);
+        let pts = |points: &[Point]| points.iter().fold(SexpBuilder::new("pts"), |pts, p| pts.child(point("xy", p)));
+        let sexp = match &self.kind {
+            GraphicKind::Polyline(points) => SexpBuilder::new("polyline").child(pts(points)),
+            GraphicKind::Rectangle { start, end } => {
+                SexpBuilder::new("rectangle").child(point("start", start)).child(point("end", end))
+            },
+            GraphicKind::Circle { center, radius } => {
+                let radius = SexpBuilder::new("radius").float(*radius);
+                SexpBuilder::new("circle").child(point("center", center)).child(radius)
+            },
+            GraphicKind::Arc { start, mid, end } => {
+                SexpBuilder::new("arc").child(point("start", start)).child(point("mid", mid)).child(point("end", end))
+            },
+            GraphicKind::Bezier(points) => SexpBuilder::new("bezier").child(pts(points)),
+            GraphicKind::Text { text, at, effects } => {
+                let at = SexpBuilder::new("at").float(at.x).float(at.y).float(at.angle);
+                return SexpBuilder::new("text").string(text).child(at).child(effects.to_sexp());
+            },
+        };
+        sexp.child(self.stroke.to_sexp()).child(self.fill.to_sexp())
+    }
+}
+
+impl FromSexp for GraphicItem {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let kind = match list.tag() {
+            "polyline" => GraphicKind::Polyline(child_points(list)?),
+            "rectangle" => {
+                GraphicKind::Rectangle { start: list.parse_required("start")?, end: list.parse_required("end")? }
+            },
+            "circle" => GraphicKind::Circle {
+                center: list.parse_required("center")?,
+                radius: list.child_number("radius")?.ok_or_else(|| list.error("missing (radius ...)"))?,
+            },
+            "arc" => GraphicKind::Arc {
+                start: list.parse_required("start")?,
+                mid: list.parse_required("mid")?,
+                end: list.parse_required("end")?,
+            },
+            "bezier" => GraphicKind::Bezier(child_points(list)?),
+            _ => GraphicKind::Text {
+                text: list.string(0)?,
+                at: list.parse_required("at")?,
+                effects: list.parse("effects")?.unwrap_or_default(),
+            },
+        };
+        Ok(GraphicItem {
+            kind,
+            stroke: list.parse("stroke")?.unwrap_or_default(),
+            fill: list.parse("fill")?.unwrap_or_default(),
+        })
+    }
+}
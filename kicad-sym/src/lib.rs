@@ -0,0 +1,148 @@
+//! Typed model of KiCad symbol library (`.kicad_sym`) files.
+
+pub mod generate;
+mod graphics;
+mod library;
+mod pin;
+pub mod pinout;
+mod symbol;
+
+pub use kicad_common::{Error, Property};
+
+pub use graphics::{Fill, GraphicItem, GraphicKind};
+pub use library::SymbolLib;
+pub use pin::{Pin, PinAlternate, PinShape, PinType};
+pub use symbol::{Symbol, SymbolUnit};
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::{Point, Position};
+
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_sym");
+
+    fn numbers<'a>(pins: impl Iterator<Item = &'a Pin>) -> Vec<&'a str> {
+        pins.map(|pin| pin.number.as_str()).collect()
+    }
+
+    #[test]
+    fn library() {
+        let lib = SymbolLib::parse(DEMO).unwrap();
+
+        assert_eq!(lib.version, 20241209);
+        assert_eq!(lib.generator, "kicad_symbol_editor");
+        assert_eq!(lib.generator_version.as_deref(), Some("9.0"));
+        let names: Vec<_> = lib.symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+        assert_eq!(names, ["C", "R", "R_US", "R_Pack02_Split", "Conn_01x03", "GND"]);
+
+        let r = lib.symbol("R").unwrap();
+        assert_eq!(r.property("Reference"), Some("R"));
+        assert_eq!(r.property("ki_fp_filters"), Some("R_*"));
+        assert!(r.hide_pin_numbers && !r.hide_pin_names);
+        assert_eq!(r.pin_name_offset, 0.0);
+        assert!(r.in_bom && r.on_board && !r.exclude_from_sim && !r.power);
+        assert_eq!(r.unit_count(), 1);
+        assert!(!r.has_convert());
+        assert_eq!((r.units[0].name.as_str(), r.units[0].unit, r.units[0].convert), ("R_0_1", 0, 1));
+        assert_eq!(
+            r.units[0].graphics[0].kind,
+            GraphicKind::Rectangle { start: Point { x: -1.016, y: -2.54 }, end: Point { x: 1.016, y: 2.54 } }
+        );
+        assert_eq!(r.units[0].graphics[0].stroke.width, 0.254);
+        assert_eq!(r.units[0].graphics[0].fill, Fill::None);
+
+        let pin = &r.units[1].pins[0];
+        assert_eq!((pin.kind, pin.shape), (PinType::Passive, PinShape::Line));
+        assert_eq!(pin.at, Position::new(0.0, 3.81, 270.0));
+        assert_eq!((pin.length, pin.name.as_str(), pin.number.as_str()), (1.27, "~", "1"));
+        assert_eq!(pin.number_effects.as_ref().unwrap().font.height, 1.27);
+        assert_eq!(numbers(r.pins(1, 1)), ["1", "2"]);
+
+        let gnd = lib.symbol("GND").unwrap();
+        assert!(gnd.power && gnd.hide_pin_names);
+        assert!(matches!(&gnd.units[0].graphics[0].kind, GraphicKind::Polyline(points) if points.len() == 6));
+        assert_eq!(gnd.all_pins().next().unwrap().kind, PinType::PowerIn);
+        assert!(gnd.all_pins().next().unwrap().hide);
+
+        let conn = lib.symbol("Conn_01x03").unwrap();
+        assert_eq!(conn.pin_name_offset, 1.016);
+        assert_eq!(numbers(conn.all_pins()), ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn units() {
+        let lib = SymbolLib::parse(DEMO).unwrap();
+        let pack = lib.symbol("R_Pack02_Split").unwrap();
+
+        assert_eq!(pack.unit_count(), 2);
+        assert_eq!(numbers(pack.pins(1, 1)), ["1", "4"]);
+        assert_eq!(numbers(pack.pins(2, 1)), ["2", "3"]);
+        let parts: Vec<_> = pack.parts(2, 1).map(|part| part.name.as_str()).collect();
+        assert_eq!(parts, ["R_Pack02_Split_0_1", "R_Pack02_Split_2_1"]);
+        assert_eq!(pack.pins(2, 2).count(), 0);
+
+        let src = "(kicad_symbol_lib (version 20241209) (symbol \"G\" \
+            (symbol \"G_1_1\" (unit_name \"A\") (pin input line (at 0 0 0) (length 2.54) (name \"A\") (number \"1\"))) \
+            (symbol \"G_1_2\" (pin input inverted (at 0 0 0) (length 2.54) (name \"A\") (number \"1\") \
+                (alternate \"B\" output clock)))))";
+        let gate = &SymbolLib::parse(src).unwrap().symbols[0];
+        assert!(gate.has_convert());
+        assert_eq!(gate.units[0].unit_name.as_deref(), Some("A"));
+        assert_eq!(gate.pins(1, 2).next().unwrap().shape, PinShape::Inverted);
+        let alternate = PinAlternate { name: "B".into(), kind: PinType::Output, shape: PinShape::Clock };
+        assert_eq!(gate.pins(1, 2).next().unwrap().alternates, [alternate]);
+    }
+
+    #[test]
+    fn write() {
+        let lib = SymbolLib::parse(DEMO).unwrap();
+        let written = lib.to_string();
+        assert_eq!(written, DEMO);
+        assert_eq!(SymbolLib::parse(&written).unwrap(), lib);
+    }
+
+    #[test]
+    fn derived() {
+        let lib = SymbolLib::parse(DEMO).unwrap();
+        let r_us = lib.symbol("R_US").unwrap();
+        assert_eq!(r_us.extends.as_deref(), Some("R"));
+        assert!(r_us.units.is_empty());
+
+        let resolved = lib.resolve("R_US").unwrap();
+        assert_eq!(resolved.name, "R_US");
+        assert_eq!(resolved.extends, None);
+        assert_eq!(resolved.property("Value"), Some("R_US"));
+        assert_eq!(resolved.property("Description"), Some("Resistor, US symbol"));
+        assert_eq!(resolved.units, lib.symbol("R").unwrap().units);
+        assert!(resolved.hide_pin_numbers);
+        assert_eq!(lib.resolve("R"), lib.symbol("R").cloned());
+        assert_eq!(lib.resolve("missing"), None);
+
+        let looped = "(kicad_symbol_lib (version 1) (symbol \"A\" (extends \"B\")) (symbol \"B\" (extends \"A\")))";
+        assert_eq!(SymbolLib::parse(looped).unwrap().resolve("A"), None);
+    }
+
+    #[test]
+    fn errors() {
+        let error = SymbolLib::parse("(kicad_symbol_lib (version 1) (symbol \"A\" (symbol \"A_x\")))").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "kicad_symbol_lib > symbol > symbol: expected a unit name like `NAME_1_1`, found `A_x`"
+        );
+
+        let src = "(kicad_symbol_lib (version 1) (symbol \"A\" (symbol \"A_1_1\" (pin analog line (at 0 0)))))";
+        let error = SymbolLib::parse(src).unwrap_err();
+        assert_eq!(error.to_string(), "kicad_symbol_lib > symbol > symbol > pin: unknown pin type `analog`");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let lib = SymbolLib::parse(DEMO).unwrap();
+        let json = serde_json::to_string(&lib).unwrap();
+
+        assert!(json.contains("\"PowerIn\""));
+        assert_eq!(serde_json::from_str::<SymbolLib>(&json).unwrap(), lib);
+    }
+}
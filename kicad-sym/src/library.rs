@@ -0,0 +1,110 @@
+use std::fmt;
+
+use kicad_common::{
+    convert::{check_version, parse_document, FromSexp, List, Result},
+    Error,
+};
+use kicad_sexp::{SexpBuilder, SexpOwned};
+
+use crate::Symbol;
+
+/// A `.kicad_sym` symbol library file.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolLib {
+    /// File format version, a date like `20241209`.
+    pub version: u32,
+    pub generator: String,
+    pub generator_version: Option<String>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl SymbolLib {
+    /// An empty library of the newest version, as the symbol editor
+    /// writes it.
+    pub fn new() -> Self {
+        SymbolLib {
+            version: Self::NEWEST_VERSION,
+            generator: "kicad_symbol_editor".to_string(),
+            generator_version: Some("9.0".to_string()),
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Newest file format version the model is written against, KiCad 9.
+    pub const NEWEST_VERSION: u32 = 20241209;
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(symbols)))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let library: SymbolLib = parse_document(src, "kicad_symbol_lib")?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("symbols", library.symbols.len());
+        Ok(library)
+    }
+
+    /// Fails with [`Error::Version`] for files from a newer KiCad.
+    pub fn check_version(&self) -> std::result::Result<(), Error> {
+        check_version("kicad_symbol_lib", self.version, Self::NEWEST_VERSION)
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// The symbol `name` with its `extends` chain flattened: units and pin
+    /// settings come from the root ancestor, properties from the derived
+    /// symbols where they set them. `None` if the symbol or an ancestor is
+    /// missing, or the chain loops.
+    pub fn resolve(&self, name: &str) -> Option<Symbol> {
+        let mut chain = vec![self.symbol(name)?];
+        while let Some(parent) = &chain[chain.len() - 1].extends {
+            let parent = self.symbol(parent)?;
+            if chain.iter().any(|symbol| symbol.name == parent.name) {
+                return None;
+            }
+            chain.push(parent);
+        }
+
+        let mut resolved = chain.pop()?.clone();
+        for derived in chain.into_iter().rev() {
+            for property in &derived.properties {
+                match resolved.properties.iter_mut().find(|p| p.key == property.key) {
+                    Some(existing) => *existing = property.clone(),
+                    None => resolved.properties.push(property.clone()),
+                }
+            }
+            resolved.name = derived.name.clone();
+            resolved.exclude_from_sim = derived.exclude_from_sim;
+            resolved.in_bom = derived.in_bom;
+            resolved.on_board = derived.on_board;
+        }
+        Some(resolved)
+    }
+
+    /// The `.kicad_sym` file; [`Display`](fmt::Display) formats the whole
+    /// file.
+    pub fn to_sexp(&self) -> SexpOwned {
+        let library = SexpBuilder::new("kicad_symbol_lib")
+            .child(SexpBuilder::new("version").int(self.version.into()))
+            .child(SexpBuilder::new("generator").string(&self.generator))
+            .child_opt(self.generator_version.as_deref().map(|v| SexpBuilder::new("generator_version").string(v)));
+        self.symbols.iter().fold(library, |library, symbol| library.child(symbol.to_sexp())).build()
+    }
+}
+
+impl fmt::Display for SymbolLib {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_sexp())
+    }
+}
+
+impl FromSexp for SymbolLib {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(SymbolLib {
+            version: list.child_number("version")?.ok_or_else(|| list.error("missing (version ...)"))?,
+            generator: list.child_string("generator")?.unwrap_or_default(),
+            generator_version: list.child_string("generator_version")?,
+            symbols: list.parse_all("symbol")?,
+        })
+    }
+}
@@ -0,0 +1,202 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    Effects, Position,
+};
+use kicad_sexp::SexpBuilder;
+
+/// Electrical type of a pin, used by the electrical rules check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PinType {
+    Input,
+    Output,
+    Bidirectional,
+    TriState,
+    Passive,
+    Free,
+    Unspecified,
+    PowerIn,
+    PowerOut,
+    OpenCollector,
+    OpenEmitter,
+    NoConnect,
+}
+
+impl PinType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "input" => PinType::Input,
+            "output" => PinType::Output,
+            "bidirectional" => PinType::Bidirectional,
+            "tri_state" => PinType::TriState,
+            "passive" => PinType::Passive,
+            "free" => PinType::Free,
+            "unspecified" => PinType::Unspecified,
+            "power_in" => PinType::PowerIn,
+            "power_out" => PinType::PowerOut,
+            "open_collector" => PinType::OpenCollector,
+            "open_emitter" => PinType::OpenEmitter,
+            "no_connect" => PinType::NoConnect,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PinType::Input => "input",
+            PinType::Output => "output",
+            PinType::Bidirectional => "bidirectional",
+            PinType::TriState => "tri_state",
+            PinType::Passive => "passive",
+            PinType::Free => "free",
+            PinType::Unspecified => "unspecified",
+            PinType::PowerIn => "power_in",
+            PinType::PowerOut => "power_out",
+            PinType::OpenCollector => "open_collector",
+            PinType::OpenEmitter => "open_emitter",
+            PinType::NoConnect => "no_connect",
+        }
+    }
+}
+
+/// How the pin line is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PinShape {
+    #[default]
+    Line,
+    Inverted,
+    Clock,
+    InvertedClock,
+    InputLow,
+    ClockLow,
+    OutputLow,
+    EdgeClockHigh,
+    NonLogic,
+}
+
+impl PinShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "line" => PinShape::Line,
+            "inverted" => PinShape::Inverted,
+            "clock" => PinShape::Clock,
+            "inverted_clock" => PinShape::InvertedClock,
+            "input_low" => PinShape::InputLow,
+            "clock_low" => PinShape::ClockLow,
+            "output_low" => PinShape::OutputLow,
+            "edge_clock_high" => PinShape::EdgeClockHigh,
+            "non_logic" => PinShape::NonLogic,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PinShape::Line => "line",
+            PinShape::Inverted => "inverted",
+            PinShape::Clock => "clock",
+            PinShape::InvertedClock => "inverted_clock",
+            PinShape::InputLow => "input_low",
+            PinShape::ClockLow => "clock_low",
+            PinShape::OutputLow => "output_low",
+            PinShape::EdgeClockHigh => "edge_clock_high",
+            PinShape::NonLogic => "non_logic",
+        }
+    }
+}
+
+fn pin_type(list: &List, index: usize) -> Result<PinType> {
+    let name = list.symbol(index)?;
+    PinType::from_name(name).ok_or_else(|| list.error(format!("unknown pin type `{}`", name)))
+}
+
+fn pin_shape(list: &List, index: usize) -> Result<PinShape> {
+    let name = list.symbol(index)?;
+    PinShape::from_name(name).ok_or_else(|| list.error(format!("unknown pin shape `{}`", name)))
+}
+
+/// An alternate pin function, as in `(alternate "SDA" bidirectional line)`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PinAlternate {
+    pub name: String,
+    pub kind: PinType,
+    pub shape: PinShape,
+}
+
+impl PinAlternate {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        SexpBuilder::new("alternate").string(&self.name).symbol(self.kind.name()).symbol(self.shape.name())
+    }
+}
+
+impl FromSexp for PinAlternate {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(PinAlternate { name: list.string(0)?, kind: pin_type(list, 1)?, shape: pin_shape(list, 2)? })
+    }
+}
+
+/// A pin of a library symbol unit, as in
+/// `(pin passive line (at 0 3.81 270) (length 1.27) (name "~") (number "1"))`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pin {
+    pub kind: PinType,
+    pub shape: PinShape,
+    /// Connection point; the angle points from there towards the body.
+    pub at: Position,
+    pub length: f64,
+    pub hide: bool,
+    /// `~` for an unnamed pin.
+    pub name: String,
+    pub name_effects: Option<Effects>,
+    pub number: String,
+    pub number_effects: Option<Effects>,
+    pub alternates: Vec<PinAlternate>,
+}
+
+impl Pin {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let text = |tag: &str, text: &str, effects: &Option<Effects>| {
+            SexpBuilder::new(tag).string(text).child_opt(effects.as_ref().map(Effects::to_sexp))
+        };
+        let pin = SexpBuilder::new("pin")
+            .symbol(self.kind.name())
+            .symbol(self.shape.name())
+            .child(SexpBuilder::new("at").float(self.at.x).float(self.at.y).float(self.at.angle))
+            .child(SexpBuilder::new("length").float(self.length))
+            .child_opt(self.hide.then(|| SexpBuilder::new("hide").symbol("yes")))
+            .child(text("name", &self.name, &self.name_effects))
+            .child(text("number", &self.number, &self.number_effects));
+        self.alternates.iter().fold(pin, |pin, alternate| pin.child(alternate.to_sexp()))
+    }
+}
+
+impl FromSexp for Pin {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let text = |tag: &str| -> Result<(String, Option<Effects>)> {
+            match list.child(tag) {
+                Some(child) => {
+                    let within = |e: kicad_common::ConvertError| e.within(list.tag());
+                    Ok((child.string(0).map_err(within)?, child.parse("effects").map_err(within)?))
+                },
+                None => Ok((String::new(), None)),
+            }
+        };
+        let (name, name_effects) = text("name")?;
+        let (number, number_effects) = text("number")?;
+        Ok(Pin {
+            kind: pin_type(list, 0)?,
+            shape: pin_shape(list, 1)?,
+            at: list.parse_required("at")?,
+            length: list.child_number("length")?.unwrap_or_default(),
+            hide: list.flag("hide")?,
+            name,
+            name_effects,
+            number,
+            number_effects,
+            alternates: list.parse_all("alternate")?,
+        })
+    }
+}
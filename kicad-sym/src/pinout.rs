@@ -0,0 +1,268 @@
+//! Pin tables read from vendor pinouts, for [`BoxSymbol`](crate::generate::BoxSymbol).
+//!
+//! [`parse_csv`] reads a plain table with a header row naming its columns;
+//! [`parse_cubemx`] reads the pinout STM32CubeMX exports from
+//! *File > Export pinout*, with pins grouped by GPIO port and the signals
+//! they are configured for as alternates.
+//!
+//! ```no_run
+//! use kicad_sym::{
+//!     generate::{BoxSymbol, Split},
+//!     pinout,
+//! };
+//!
+//! let pins = pinout::parse_cubemx(&std::fs::read_to_string("pinout.csv")?)?;
+//! let symbol = BoxSymbol { split: Split::Groups, ..BoxSymbol::new("STM32F103C8Tx", pins) }.build()?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use kicad_common::{ConvertError, Error};
+
+use crate::{
+    generate::{PinDef, Side},
+    PinAlternate, PinShape, PinType,
+};
+
+/// Reads a pin table separated by commas, semicolons or tabs.
+///
+/// The header names the columns, in any order and case: the pin number
+/// (`Number`, `Pin` or `No`), `Name` (or `Signal`) and optionally the
+/// electrical `Type`, `Side`, `Group` (or `Port`, `Bank`) and `Shape`.
+/// Types are KiCad's names, such as `power_in` or `tri_state`, or common
+/// short forms such as `in`, `I/O`, `OD` or `NC`; a pin without one is
+/// unspecified.
+pub fn parse_csv(src: &str) -> Result<Vec<PinDef>, Error> {
+    let mut records = records(src)?.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let column = |names: &[&str]| {
+        header.iter().position(|title| names.iter().any(|name| title.trim().eq_ignore_ascii_case(name)))
+    };
+    let number = column(&["number", "pin", "no", "pin number"]);
+    let (Some(number), Some(name)) = (number, column(&["name", "signal", "pin name"])) else {
+        return Err(ConvertError::new("pin table needs number and name columns").into());
+    };
+    let kind = column(&["type", "electrical type", "kind"]);
+    let side = column(&["side"]);
+    let group = column(&["group", "port", "bank"]);
+    let shape = column(&["shape"]);
+
+    let mut pins = Vec::new();
+    for (line, record) in records {
+        let cell = |index: Option<usize>| index.and_then(|index| record.get(index)).map_or("", |cell| cell.trim());
+        let mut pin = PinDef::new(cell(Some(number)), cell(Some(name)), PinType::Unspecified);
+        if pin.number.is_empty() {
+            return Err(ConvertError::new(format!("pin on line {} has no number", line)).into());
+        }
+        if !cell(kind).is_empty() {
+            pin.kind = pin_type(cell(kind))
+                .ok_or_else(|| ConvertError::new(format!("unknown pin type {} on line {}", cell(kind), line)))?;
+        }
+        pin.side = match cell(side).to_ascii_lowercase().as_str() {
+            "" => None,
+            "l" | "left" => Some(Side::Left),
+            "r" | "right" => Some(Side::Right),
+            "t" | "top" => Some(Side::Top),
+            "b" | "bottom" => Some(Side::Bottom),
+            other => return Err(ConvertError::new(format!("unknown side {} on line {}", other, line)).into()),
+        };
+        pin.group = Some(cell(group).to_string()).filter(|group| !group.is_empty());
+        if !cell(shape).is_empty() {
+            pin.shape = PinShape::from_name(&cell(shape).to_ascii_lowercase())
+                .ok_or_else(|| ConvertError::new(format!("unknown pin shape {} on line {}", cell(shape), line)))?;
+        }
+        pins.push(pin);
+    }
+    Ok(pins)
+}
+
+/// Reads an STM32CubeMX pinout export, with its `Position`, `Name`, `Type`
+/// and `Signal` columns.
+///
+/// Supplies become power inputs, reset and boot pins inputs and the others
+/// bidirectional. Port pins are named without their default function, so
+/// `PC13-ANTI_TAMP` is `PC13`, and grouped by port; supplies are grouped
+/// as `Power`. A configured signal other than a plain GPIO becomes an
+/// alternate of its pin.
+pub fn parse_cubemx(src: &str) -> Result<Vec<PinDef>, Error> {
+    let mut records = records(src)?.into_iter();
+    let header = records.next().map(|(_, header)| header).unwrap_or_default();
+    let column = |name: &str| header.iter().position(|title| title.trim().eq_ignore_ascii_case(name));
+    let (Some(position), Some(name), Some(kind)) = (column("position"), column("name"), column("type")) else {
+        return Err(ConvertError::new("not a CubeMX pinout: needs Position, Name and Type columns").into());
+    };
+    let signal = column("signal");
+
+    let mut pins = Vec::new();
+    for (line, record) in records {
+        let cell = |index: Option<usize>| index.and_then(|index| record.get(index)).map_or("", |cell| cell.trim());
+        let full = cell(Some(name));
+        let kind = match cell(Some(kind)) {
+            "Power" => PinType::PowerIn,
+            "Reset" | "Boot" => PinType::Input,
+            "I/O" | "MonoIO" => PinType::Bidirectional,
+            other => {
+                return Err(ConvertError::new(format!("unknown CubeMX pin type {} on line {}", other, line)).into());
+            },
+        };
+        let port = port(full);
+        let short = if port.is_some() { full.split(['-', ' ']).next().unwrap_or(full) } else { full };
+        let mut pin = PinDef::new(cell(Some(position)), short, kind);
+        pin.group = match kind {
+            PinType::PowerIn => Some("Power".to_string()),
+            _ => port,
+        };
+        let signal = cell(signal);
+        if !signal.is_empty() && !signal.starts_with("GPIO_") && signal != short {
+            pin.alternates.push(PinAlternate {
+                name: signal.to_string(),
+                kind: PinType::Bidirectional,
+                shape: PinShape::Line,
+            });
+        }
+        pins.push(pin);
+    }
+    Ok(pins)
+}
+
+/// KiCad's pin type names and the short forms vendors use.
+fn pin_type(name: &str) -> Option<PinType> {
+    let lower = name.to_ascii_lowercase();
+    if let Some(kind) = PinType::from_name(&lower.replace([' ', '-'], "_")) {
+        return Some(kind);
+    }
+    Some(match lower.as_str() {
+        "i" | "in" => PinType::Input,
+        "o" | "out" => PinType::Output,
+        "io" | "i/o" | "bidir" | "inout" => PinType::Bidirectional,
+        "tristate" | "3state" | "z" => PinType::TriState,
+        "p" | "analog" => PinType::Passive,
+        "pwr" | "power" | "supply" | "ground" | "gnd" => PinType::PowerIn,
+        "oc" | "od" | "open drain" | "open collector" => PinType::OpenCollector,
+        "oe" | "open source" | "open emitter" => PinType::OpenEmitter,
+        "nc" | "n/c" => PinType::NoConnect,
+        _ => return None,
+    })
+}
+
+/// The GPIO port of an STM32 pin name: `PA` for `PA0-WKUP`.
+fn port(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('P'), Some(port @ 'A'..='K'), Some('0'..='9')) => Some(format!("P{}", port)),
+        _ => None,
+    }
+}
+
+/// The non-empty records of a delimited file with their line numbers. The
+/// delimiter is the first of tab, semicolon or comma in the header; fields
+/// may be quoted, with `""` for a quote.
+fn records(src: &str) -> Result<Vec<(usize, Vec<String>)>, Error> {
+    let header = src.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+    let delimiter = ['\t', ';', ','].into_iter().find(|c| header.contains(*c)).unwrap_or(',');
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let (mut quoted, mut line, mut start) = (false, 1, 1);
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            '"' => quoted = !quoted,
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|field| !field.trim().is_empty()) {
+                    records.push((start, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                start = line;
+            },
+            '\r' if !quoted => {},
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            },
+        }
+    }
+    if quoted {
+        return Err(ConvertError::new(format!("unterminated quote on line {}", start)).into());
+    }
+    record.push(field);
+    if record.iter().any(|field| !field.trim().is_empty()) {
+        records.push((start, record));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{BoxSymbol, Split};
+
+    #[test]
+    fn csv() {
+        let pins = parse_csv(
+            "Pin;Name;Type;Group\n1;VCC;power_in;\n2;\"EN; active high\";in;ctl\n\n3;OUT;OD;\n4;NC;n/c;\n",
+        )
+        .unwrap();
+        let rows: Vec<_> = pins.iter().map(|pin| (pin.number.as_str(), pin.name.as_str(), pin.kind)).collect();
+        assert_eq!(
+            rows,
+            [
+                ("1", "VCC", PinType::PowerIn),
+                ("2", "EN; active high", PinType::Input),
+                ("3", "OUT", PinType::OpenCollector),
+                ("4", "NC", PinType::NoConnect),
+            ]
+        );
+        assert_eq!(pins[1].group.as_deref(), Some("ctl"));
+        assert_eq!(pins[0].group, None);
+
+        let error = parse_csv("number,name,type\n1,A,in\n2,B,sideways\n").unwrap_err();
+        assert_eq!(error.to_string(), "unknown pin type sideways on line 3");
+        assert!(parse_csv("name\nA\n").is_err());
+        assert!(parse_csv("number,name\n1,\"A\n").is_err());
+        assert_eq!(parse_csv("number\tname\tside\n7\tCLK\tbottom\n").unwrap()[0].side, Some(Side::Bottom));
+    }
+
+    #[test]
+    fn cubemx() {
+        let src = "\"Position\",\"Name\",\"Type\",\"Signal\",\"Label\"\n\
+                   \"1\",\"VBAT\",\"Power\",\"\",\"\"\n\
+                   \"2\",\"PC13-ANTI_TAMP\",\"I/O\",\"GPIO_Output\",\"LED\"\n\
+                   \"7\",\"NRST\",\"Reset\",\"\",\"\"\n\
+                   \"10\",\"PA0-WKUP\",\"I/O\",\"ADC1_IN0\",\"\"\n\
+                   \"23\",\"VSS\",\"Power\",\"\",\"\"\n\
+                   \"30\",\"PA9\",\"I/O\",\"USART1_TX\",\"\"\n";
+        let pins = parse_cubemx(src).unwrap();
+        let rows: Vec<_> =
+            pins.iter().map(|pin| (pin.number.as_str(), pin.name.as_str(), pin.group.as_deref())).collect();
+        assert_eq!(
+            rows,
+            [
+                ("1", "VBAT", Some("Power")),
+                ("2", "PC13", Some("PC")),
+                ("7", "NRST", None),
+                ("10", "PA0", Some("PA")),
+                ("23", "VSS", Some("Power")),
+                ("30", "PA9", Some("PA")),
+            ]
+        );
+        assert!(pins[1].alternates.is_empty());
+        assert_eq!(pins[3].alternates[0].name, "ADC1_IN0");
+        assert_eq!(pins[2].kind, PinType::Input);
+
+        let symbol = BoxSymbol { split: Split::Groups, ..BoxSymbol::new("STM32", pins) }.build().unwrap();
+        let units: Vec<_> = symbol.units.iter().map(|unit| unit.unit_name.as_deref()).collect();
+        assert_eq!(units, [Some("Power"), Some("PC"), None, Some("PA")]);
+        assert!(parse_cubemx("number,name\n1,A\n").is_err());
+    }
+}
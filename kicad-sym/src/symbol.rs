@@ -0,0 +1,184 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    property::property,
+    Property,
+};
+use kicad_sexp::SexpBuilder;
+
+use crate::{GraphicItem, Pin};
+
+/// One unit and body style of a symbol, the nested `(symbol "R_1_1" ...)`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolUnit {
+    pub name: String,
+    /// Unit number from 1; 0 for items shared by all units.
+    pub unit: u32,
+    /// Body style: 1 for the normal body, 2 for the De Morgan alternate; 0
+    /// for items shared by both.
+    pub convert: u32,
+    /// Display name of the unit, e.g. `A`.
+    pub unit_name: Option<String>,
+    pub graphics: Vec<GraphicItem>,
+    pub pins: Vec<Pin>,
+}
+
+impl SymbolUnit {
+    /// Whether this unit's items are drawn for `unit` in body style `convert`.
+    pub fn applies_to(&self, unit: u32, convert: u32) -> bool {
+        (self.unit == 0 || self.unit == unit) && (self.convert == 0 || self.convert == convert)
+    }
+}
+
+impl SymbolUnit {
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let unit = SexpBuilder::new("symbol")
+            .string(&self.name)
+            .child_opt(self.unit_name.as_deref().map(|name| SexpBuilder::new("unit_name").string(name)));
+        let unit = self.graphics.iter().fold(unit, |unit, graphic| unit.child(graphic.to_sexp()));
+        self.pins.iter().fold(unit, |unit, pin| unit.child(pin.to_sexp()))
+    }
+}
+
+impl FromSexp for SymbolUnit {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let name = list.string(0)?;
+        let mut parts = name.rsplitn(3, '_');
+        let (Some(convert), Some(unit), Some(_)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(list.error(format!("expected a unit name like `NAME_1_1`, found `{}`", name)));
+        };
+        let (Ok(unit), Ok(convert)) = (unit.parse(), convert.parse()) else {
+            return Err(list.error(format!("expected a unit name like `NAME_1_1`, found `{}`", name)));
+        };
+        let mut symbol_unit = SymbolUnit {
+            unit,
+            convert,
+            unit_name: list.child_string("unit_name")?,
+            name,
+            ..Default::default()
+        };
+        for item in list.lists() {
+            let within = |e: kicad_common::ConvertError| e.within(list.tag());
+            match item.tag() {
+                "pin" => symbol_unit.pins.push(Pin::from_sexp(&item).map_err(within)?),
+                tag if GraphicItem::is_graphic_tag(tag) => {
+                    symbol_unit.graphics.push(GraphicItem::from_sexp(&item).map_err(within)?);
+                },
+                _ => {},
+            }
+        }
+        Ok(symbol_unit)
+    }
+}
+
+/// KiCad's pin name inset when `pin_names` gives none, 20 mils.
+const DEFAULT_PIN_NAME_OFFSET: f64 = 0.508;
+
+/// A library symbol, as in a `.kicad_sym` file or a schematic's
+/// `lib_symbols`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symbol {
+    /// Name in the library; `lib_symbols` entries carry the library
+    /// nickname too, as in `Device:R`.
+    pub name: String,
+    /// Name of the symbol this one is derived from. Derived symbols only
+    /// carry their own properties, see [`crate::SymbolLib::resolve`].
+    pub extends: Option<String>,
+    pub power: bool,
+    pub hide_pin_numbers: bool,
+    pub hide_pin_names: bool,
+    /// Inset of pin names inside the body; 0 puts them outside, next to
+    /// the numbers.
+    pub pin_name_offset: f64,
+    pub exclude_from_sim: bool,
+    pub in_bom: bool,
+    pub on_board: bool,
+    pub properties: Vec<Property>,
+    pub units: Vec<SymbolUnit>,
+}
+
+impl Symbol {
+    pub fn property(&self, key: &str) -> Option<&str> {
+        property(&self.properties, key)
+    }
+
+    /// Number of units a placed symbol can pick from.
+    pub fn unit_count(&self) -> u32 {
+        self.units.iter().map(|unit| unit.unit).max().unwrap_or(0).max(1)
+    }
+
+    /// Whether the symbol has a De Morgan alternate body style.
+    pub fn has_convert(&self) -> bool {
+        self.units.iter().any(|unit| unit.convert == 2)
+    }
+
+    /// Units and body styles drawn for `unit` in body style `convert`.
+    pub fn parts(&self, unit: u32, convert: u32) -> impl Iterator<Item = &SymbolUnit> {
+        self.units.iter().filter(move |part| part.applies_to(unit, convert))
+    }
+
+    /// Pins of `unit` in body style `convert`, including shared ones.
+    pub fn pins(&self, unit: u32, convert: u32) -> impl Iterator<Item = &Pin> {
+        self.parts(unit, convert).flat_map(|part| &part.pins)
+    }
+
+    /// Every pin of every unit and body style.
+    pub fn all_pins(&self) -> impl Iterator<Item = &Pin> {
+        self.units.iter().flat_map(|unit| &unit.pins)
+    }
+
+    /// The symbol as a symbol library writes it.
+    pub fn to_sexp(&self) -> SexpBuilder {
+        let yes_no = |tag: &str, on: bool| SexpBuilder::new(tag).symbol(if on { "yes" } else { "no" });
+        let hide = |on: bool| on.then(|| SexpBuilder::new("hide").symbol("yes"));
+        let offset = self.pin_name_offset != DEFAULT_PIN_NAME_OFFSET;
+        let pin_names = (offset || self.hide_pin_names).then(|| {
+            SexpBuilder::new("pin_names")
+                .child_opt(offset.then(|| SexpBuilder::new("offset").float(self.pin_name_offset)))
+                .child_opt(hide(self.hide_pin_names))
+        });
+        let symbol = SexpBuilder::new("symbol")
+            .string(&self.name)
+            .child_opt(self.extends.as_deref().map(|extends| SexpBuilder::new("extends").string(extends)))
+            .child_opt(self.power.then(|| SexpBuilder::new("power")))
+            .child_opt(self.hide_pin_numbers.then(|| SexpBuilder::new("pin_numbers").child_opt(hide(true))))
+            .child_opt(pin_names)
+            .child(yes_no("exclude_from_sim", self.exclude_from_sim))
+            .child(yes_no("in_bom", self.in_bom))
+            .child(yes_no("on_board", self.on_board));
+        let symbol = self.properties.iter().fold(symbol, |symbol, property| symbol.child(property.to_sexp()));
+        let symbol = self.units.iter().fold(symbol, |symbol, unit| symbol.child(unit.to_sexp()));
+        symbol.child(yes_no("embedded_fonts", false))
+    }
+}
+
+impl FromSexp for Symbol {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let (hide_pin_names, pin_name_offset) = match list.child("pin_names") {
+            Some(pin_names) => {
+                let within = |e: kicad_common::ConvertError| e.within(list.tag());
+                (pin_names.flag("hide").map_err(within)?, pin_names.child_number("offset").map_err(within)?)
+            },
+            None => (false, None),
+        };
+        let pin_name_offset = pin_name_offset.unwrap_or(DEFAULT_PIN_NAME_OFFSET);
+        let hide_pin_numbers = match list.child("pin_numbers") {
+            Some(pin_numbers) => pin_numbers.flag("hide").map_err(|e| e.within(list.tag()))?,
+            None => false,
+        };
+        Ok(Symbol {
+            name: list.string(0)?,
+            extends: list.child_string("extends")?,
+            power: list.child("power").is_some(),
+            hide_pin_numbers,
+            hide_pin_names,
+            pin_name_offset,
+            exclude_from_sim: list.flag("exclude_from_sim")?,
+            in_bom: list.opt_flag("in_bom")?.unwrap_or(true),
+            on_board: list.opt_flag("on_board")?.unwrap_or(true),
+            properties: list.parse_all("property")?,
+            units: list.parse_all("symbol")?,
+        })
+    }
+}
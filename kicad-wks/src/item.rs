@@ -0,0 +1,476 @@
+use kicad_common::{
+    convert::{FromSexp, List, Result},
+    geometry::points,
+    Color, HAlign, Point, Size, VAlign,
+};
+use kicad_sexp::SexpBuilder;
+
+/// The page corner a coordinate is measured from, inwards from the margins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Corner {
+    #[default]
+    RightBottom,
+    RightTop,
+    LeftBottom,
+    LeftTop,
+}
+
+impl Corner {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "rbcorner" => Corner::RightBottom,
+            "rtcorner" => Corner::RightTop,
+            "lbcorner" => Corner::LeftBottom,
+            "ltcorner" => Corner::LeftTop,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Corner::RightBottom => "rbcorner",
+            Corner::RightTop => "rtcorner",
+            Corner::LeftBottom => "lbcorner",
+            Corner::LeftTop => "ltcorner",
+        }
+    }
+}
+
+/// A position relative to a page corner, as in `(pos 25 1 ltcorner)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coord {
+    pub x: f64,
+    pub y: f64,
+    pub corner: Corner,
+}
+
+impl Coord {
+    pub const fn new(x: f64, y: f64, corner: Corner) -> Self {
+        Coord { x, y, corner }
+    }
+
+    pub(crate) fn to_sexp(self, tag: &str) -> SexpBuilder {
+        let builder = SexpBuilder::new(tag).float(self.x).float(self.y);
+        match self.corner {
+            Corner::RightBottom => builder,
+            corner => builder.symbol(corner.name()),
+        }
+    }
+}
+
+impl FromSexp for Coord {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let corner = match list.args().get(2) {
+            Some(_) => {
+                let name = list.symbol(2)?;
+                Corner::from_name(name).ok_or_else(|| list.error(format!("unknown corner `{}`", name)))?
+            },
+            None => Corner::default(),
+        };
+        Ok(Coord { x: list.number(0)?, y: list.number(1)?, corner })
+    }
+}
+
+/// Which pages an item is drawn on, from `(option page1only)` or
+/// `(option notonpage1)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pages {
+    #[default]
+    All,
+    FirstOnly,
+    NotFirst,
+}
+
+impl Pages {
+    pub fn includes(&self, first_page: bool) -> bool {
+        match self {
+            Pages::All => true,
+            Pages::FirstOnly => first_page,
+            Pages::NotFirst => !first_page,
+        }
+    }
+}
+
+/// How often an item is repeated and how far each copy moves, as in
+/// `(repeat 30) (incrx 50)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Repeat {
+    /// Number of copies, at least 1.
+    pub count: u32,
+    /// Offset added per copy, in the corner's inward direction.
+    pub step: Point,
+    /// Added to the last character of a text's label per copy.
+    pub label_step: i32,
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Repeat { count: 1, step: Point::default(), label_step: 1 }
+    }
+}
+
+impl Repeat {
+    fn from_item(list: &List) -> Result<Self> {
+        Ok(Repeat {
+            count: list.child_number::<u32>("repeat")?.unwrap_or(1).max(1),
+            step: Point::new(
+                list.child_number("incrx")?.unwrap_or_default(),
+                list.child_number("incry")?.unwrap_or_default(),
+            ),
+            label_step: list.child_number("incrlabel")?.unwrap_or(1),
+        })
+    }
+
+    fn to_sexp(self, mut item: SexpBuilder, text: bool) -> SexpBuilder {
+        if self.count <= 1 {
+            return item;
+        }
+        item = item.child(SexpBuilder::new("repeat").int(self.count.into()));
+        if self.step.x != 0.0 {
+            item = item.child(SexpBuilder::new("incrx").float(self.step.x));
+        }
+        if self.step.y != 0.0 {
+            item = item.child(SexpBuilder::new("incry").float(self.step.y));
+        }
+        if text && self.label_step != 1 {
+            item = item.child(SexpBuilder::new("incrlabel").int(self.label_step.into()));
+        }
+        item
+    }
+}
+
+/// A line or the outline of a rectangle between two corners.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Line {
+    pub start: Coord,
+    pub end: Coord,
+    /// `None` uses the setup's line width.
+    pub line_width: Option<f64>,
+}
+
+pub type Rect = Line;
+
+impl FromSexp for Line {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(Line {
+            start: list.parse_required("start")?,
+            end: list.parse_required("end")?,
+            line_width: list.child_number("linewidth")?,
+        })
+    }
+}
+
+/// Font overrides of a text; unset fields use the setup's defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextFont {
+    /// Outline font name; `None` is the KiCad stroke font.
+    pub face: Option<String>,
+    pub line_width: Option<f64>,
+    pub size: Option<Size>,
+    pub bold: bool,
+    pub italic: bool,
+    pub color: Option<Color>,
+}
+
+impl TextFont {
+    fn is_default(&self) -> bool {
+        *self == TextFont::default()
+    }
+}
+
+impl FromSexp for TextFont {
+    fn from_sexp(list: &List) -> Result<Self> {
+        Ok(TextFont {
+            face: list.child_string("face")?,
+            line_width: list.child_number("linewidth")?,
+            size: list.parse("size")?,
+            bold: list.flag("bold")?,
+            italic: list.flag("italic")?,
+            color: list.parse("color")?,
+        })
+    }
+}
+
+/// A title block text, as in `(tbtext "Rev: ${REVISION}" (pos 24 6.9) (font bold))`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tbtext {
+    /// The text, with `${VAR}` references left unexpanded.
+    pub text: String,
+    pub pos: Coord,
+    /// Rotation in degrees.
+    pub rotate: f64,
+    pub font: TextFont,
+    /// Left by default.
+    pub horizontal: HAlign,
+    pub vertical: VAlign,
+    /// Width and height in mm the text is shrunk to fit, if set.
+    pub max_len: Option<f64>,
+    pub max_height: Option<f64>,
+}
+
+impl Default for Tbtext {
+    fn default() -> Self {
+        Tbtext {
+            text: String::new(),
+            pos: Coord::default(),
+            rotate: 0.0,
+            font: TextFont::default(),
+            horizontal: HAlign::Left,
+            vertical: VAlign::Center,
+            max_len: None,
+            max_height: None,
+        }
+    }
+}
+
+impl FromSexp for Tbtext {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let (mut horizontal, mut vertical) = (HAlign::Left, VAlign::Center);
+        if let Some(justify) = list.child("justify") {
+            for index in 0..justify.args().len() {
+                match justify.symbol(index).map_err(|e| e.within(list.tag()))? {
+                    "left" => horizontal = HAlign::Left,
+                    "center" => horizontal = HAlign::Center,
+                    "right" => horizontal = HAlign::Right,
+                    "top" => vertical = VAlign::Top,
+                    "bottom" => vertical = VAlign::Bottom,
+                    other => {
+                        return Err(justify.error(format!("unknown justification `{}`", other)).within(list.tag()));
+                    },
+                }
+            }
+        }
+        Ok(Tbtext {
+            text: list.string(0)?,
+            pos: list.parse_required("pos")?,
+            rotate: list.child_number("rotate")?.unwrap_or_default(),
+            font: list.parse("font")?.unwrap_or_default(),
+            horizontal,
+            vertical,
+            max_len: list.child_number("maxlen")?,
+            max_height: list.child_number("maxheight")?,
+        })
+    }
+}
+
+/// Filled polygons sharing a position, as in `(polygon (pos 10 10) (pts ...) (pts ...))`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Polygon {
+    pub pos: Coord,
+    /// Rotation of the outlines around `pos` in degrees.
+    pub rotate: f64,
+    pub line_width: Option<f64>,
+    /// Outlines relative to `pos`, with Y pointing down whatever the corner.
+    pub outlines: Vec<Vec<Point>>,
+}
+
+impl FromSexp for Polygon {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let outlines = list.children("pts").map(|pts| points(&pts).map_err(|e| e.within(list.tag())));
+        Ok(Polygon {
+            pos: list.parse_required("pos")?,
+            rotate: list.child_number("rotate")?.unwrap_or_default(),
+            line_width: list.child_number("linewidth")?,
+            outlines: outlines.collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// An embedded PNG image, as in `(bitmap (pos 10 10) (scale 1) (pngdata (data "89 50 ...")))`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bitmap {
+    pub pos: Coord,
+    pub scale: f64,
+    /// The `(data ...)` lines of hex bytes, as stored.
+    pub data: Vec<String>,
+}
+
+impl FromSexp for Bitmap {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let data = match list.child("pngdata") {
+            Some(pngdata) => pngdata
+                .children("data")
+                .map(|data| data.string(0).map_err(|e| e.within(pngdata.tag()).within(list.tag())))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+        Ok(Bitmap {
+            pos: list.parse_required("pos")?,
+            scale: list.child_number("scale")?.unwrap_or(1.0),
+            data,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ItemKind {
+    Line(Line),
+    Rect(Rect),
+    Tbtext(Tbtext),
+    Polygon(Polygon),
+    Bitmap(Bitmap),
+}
+
+/// A drawing sheet item with the settings all kinds share.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Item {
+    pub kind: ItemKind,
+    pub name: String,
+    /// Note shown in the drawing sheet editor.
+    pub comment: Option<String>,
+    pub pages: Pages,
+    pub repeat: Repeat,
+}
+
+impl Item {
+    pub fn new(kind: ItemKind) -> Self {
+        Item { kind, name: String::new(), comment: None, pages: Pages::All, repeat: Repeat::default() }
+    }
+
+    /// Returns true for the tags [`Item`] can be read from.
+    pub(crate) fn is_item_tag(tag: &str) -> bool {
+        matches!(tag, "line" | "rect" | "tbtext" | "polygon" | "bitmap")
+    }
+
+    /// The item in the order KiCad writes it; values equal to the setup
+    /// defaults are left out.
+    pub(crate) fn to_sexp(&self) -> SexpBuilder {
+        let tag = match &self.kind {
+            ItemKind::Line(_) => "line",
+            ItemKind::Rect(_) => "rect",
+            ItemKind::Tbtext(_) => "tbtext",
+            ItemKind::Polygon(_) => "polygon",
+            ItemKind::Bitmap(_) => "bitmap",
+        };
+        let mut item = SexpBuilder::new(tag);
+        if let ItemKind::Tbtext(text) = &self.kind {
+            item = item.string(&text.text);
+        }
+        item = item.child(SexpBuilder::new("name").string(&self.name));
+        let pages = |item: SexpBuilder| match self.pages {
+            Pages::All => item,
+            Pages::FirstOnly => item.child(SexpBuilder::new("option").symbol("page1only")),
+            Pages::NotFirst => item.child(SexpBuilder::new("option").symbol("notonpage1")),
+        };
+        let line_width = |width: Option<f64>| width.map(|width| SexpBuilder::new("linewidth").float(width));
+        let rotate = |angle: f64| (angle != 0.0).then(|| SexpBuilder::new("rotate").float(angle));
+        let comment = self.comment.as_ref().map(|comment| SexpBuilder::new("comment").string(comment));
+
+        match &self.kind {
+            ItemKind::Line(line) | ItemKind::Rect(line) => {
+                item = pages(item.child(line.start.to_sexp("start")).child(line.end.to_sexp("end")));
+                item = self.repeat.to_sexp(item.child_opt(line_width(line.line_width)), false).child_opt(comment);
+            },
+            ItemKind::Tbtext(text) => {
+                item = pages(item.child(text.pos.to_sexp("pos"))).child_opt(rotate(text.rotate));
+                if !text.font.is_default() {
+                    item = item.child(font_sexp(&text.font));
+                }
+                item = item.child_opt(justify_sexp(text.horizontal, text.vertical));
+                item = item.child_opt(text.max_len.map(|len| SexpBuilder::new("maxlen").float(len)));
+                item = item.child_opt(text.max_height.map(|height| SexpBuilder::new("maxheight").float(height)));
+                item = self.repeat.to_sexp(item, true).child_opt(comment);
+            },
+            ItemKind::Polygon(polygon) => {
+                item = pages(item.child(polygon.pos.to_sexp("pos"))).child_opt(rotate(polygon.rotate));
+                item = self.repeat.to_sexp(item.child_opt(line_width(polygon.line_width)), false).child_opt(comment);
+                for outline in &polygon.outlines {
+                    let pts = outline.iter().fold(SexpBuilder::new("pts"), |pts, point| {
+                        pts.child(SexpBuilder::new("xy").float(point.x).float(point.y))
+                    });
+                    item = item.child(pts);
+                }
+            },
+            ItemKind::Bitmap(bitmap) => {
+                item = pages(item.child(bitmap.pos.to_sexp("pos")));
+                item = item.child(SexpBuilder::new("scale").float(bitmap.scale));
+                item = self.repeat.to_sexp(item, false).child_opt(comment);
+                let pngdata = bitmap.data.iter().fold(SexpBuilder::new("pngdata"), |pngdata, data| {
+                    pngdata.child(SexpBuilder::new("data").string(data))
+                });
+                item = item.child(pngdata);
+            },
+        }
+        item
+    }
+}
+
+fn font_sexp(font: &TextFont) -> SexpBuilder {
+    let mut sexp = SexpBuilder::new("font")
+        .child_opt(font.face.as_ref().map(|face| SexpBuilder::new("face").string(face)))
+        .child_opt(font.line_width.map(|width| SexpBuilder::new("linewidth").float(width)))
+        .child_opt(font.size.map(|size| SexpBuilder::new("size").float(size.width).float(size.height)));
+    if font.bold {
+        sexp = sexp.symbol("bold");
+    }
+    if font.italic {
+        sexp = sexp.symbol("italic");
+    }
+    sexp.child_opt(font.color.map(|color| {
+        SexpBuilder::new("color")
+            .int(color.r.into())
+            .int(color.g.into())
+            .int(color.b.into())
+            .float(color.a)
+    }))
+}
+
+fn justify_sexp(horizontal: HAlign, vertical: VAlign) -> Option<SexpBuilder> {
+    let horizontal = match horizontal {
+        HAlign::Left => None,
+        HAlign::Center => Some("center"),
+        HAlign::Right => Some("right"),
+    };
+    let vertical = match vertical {
+        VAlign::Center => None,
+        VAlign::Top => Some("top"),
+        VAlign::Bottom => Some("bottom"),
+    };
+    if horizontal.is_none() && vertical.is_none() {
+        return None;
+    }
+    let justify = SexpBuilder::new("justify");
+    let justify = match horizontal {
+        Some(name) => justify.symbol(name),
+        None => justify,
+    };
+    Some(match vertical {
+        Some(name) => justify.symbol(name),
+        None => justify,
+    })
+}
+
+impl FromSexp for Item {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let kind = match list.tag() {
+            "line" => ItemKind::Line(Line::from_sexp(list)?),
+            "rect" => ItemKind::Rect(Rect::from_sexp(list)?),
+            "tbtext" => ItemKind::Tbtext(Tbtext::from_sexp(list)?),
+            "polygon" => ItemKind::Polygon(Polygon::from_sexp(list)?),
+            _ => ItemKind::Bitmap(Bitmap::from_sexp(list)?),
+        };
+        let pages = match list.child("option") {
+            Some(option) if option.has_symbol("page1only") => Pages::FirstOnly,
+            Some(option) if option.has_symbol("notonpage1") => Pages::NotFirst,
+            _ => Pages::All,
+        };
+        Ok(Item {
+            kind,
+            name: list.child_string("name")?.unwrap_or_default(),
+            comment: list.child_string("comment")?,
+            pages,
+            repeat: Repeat::from_item(list)?,
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use kicad_common::{Point, Size};
+
+use crate::{Coord, Corner, DrawingSheet, Item, ItemKind};
+
+/// One drawn copy of an item, in page coordinates from the top left corner
+/// of the sheet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placed<'a> {
+    pub item: &'a Item,
+    /// Copy number from 0.
+    pub copy: u32,
+    pub start: Point,
+    /// End of lines and rectangles; `start` for other items.
+    pub end: Point,
+    /// Text of a `tbtext` copy with its label incremented.
+    pub text: Option<String>,
+}
+
+/// The area inside the margins, as top left and bottom right corners.
+struct Frame {
+    left_top: Point,
+    right_bottom: Point,
+}
+
+impl Frame {
+    fn resolve(&self, coord: Coord, copy: u32, step: Point) -> Point {
+        let x = coord.x + step.x * f64::from(copy);
+        let y = coord.y + step.y * f64::from(copy);
+        let (left_top, right_bottom) = (self.left_top, self.right_bottom);
+        match coord.corner {
+            Corner::RightBottom => Point::new(right_bottom.x - x, right_bottom.y - y),
+            Corner::RightTop => Point::new(right_bottom.x - x, left_top.y + y),
+            Corner::LeftBottom => Point::new(left_top.x + x, right_bottom.y - y),
+            Corner::LeftTop => Point::new(left_top.x + x, left_top.y + y),
+        }
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        (self.left_top.x..=self.right_bottom.x).contains(&point.x)
+            && (self.left_top.y..=self.right_bottom.y).contains(&point.y)
+    }
+}
+
+/// Adds `step` to the last character of a repeated label, so `1` becomes
+/// `2`, `3`, ... and `A` becomes `B`, `C`, ... A trailing digit is replaced
+/// by the incremented number, so `9` steps to `10`.
+pub fn increment_label(text: &str, step: i32) -> String {
+    let Some(last) = text.chars().last() else {
+        return String::new();
+    };
+    let mut label = text[..text.len() - last.len_utf8()].to_string();
+    match last.to_digit(10) {
+        Some(digit) => label.push_str(&(digit as i32 + step).to_string()),
+        None => label.extend(char::from_u32((last as i64 + i64::from(step)) as u32)),
+    }
+    label
+}
+
+impl DrawingSheet {
+    /// Expands the items drawn on a page of `size` into their copies. Like
+    /// KiCad, repeated copies starting outside the margins are dropped.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(items = self.items.len())))]
+    pub fn layout(&self, size: Size, first_page: bool) -> Vec<Placed<'_>> {
+        let setup = &self.setup;
+        let frame = Frame {
+            left_top: Point::new(setup.left_margin, setup.top_margin),
+            right_bottom: Point::new(size.width - setup.right_margin, size.height - setup.bottom_margin),
+        };
+        let mut placed = Vec::new();
+        for item in self.items.iter().filter(|item| item.pages.includes(first_page)) {
+            let step = item.repeat.step;
+            for copy in 0..item.repeat.count {
+                let (start, end) = match &item.kind {
+                    ItemKind::Line(line) | ItemKind::Rect(line) => {
+                        (frame.resolve(line.start, copy, step), frame.resolve(line.end, copy, step))
+                    },
+                    ItemKind::Tbtext(text) => {
+                        let pos = frame.resolve(text.pos, copy, step);
+                        (pos, pos)
+                    },
+                    ItemKind::Polygon(polygon) => {
+                        let pos = frame.resolve(polygon.pos, copy, step);
+                        (pos, pos)
+                    },
+                    ItemKind::Bitmap(bitmap) => {
+                        let pos = frame.resolve(bitmap.pos, copy, step);
+                        (pos, pos)
+                    },
+                };
+                if copy > 0 && !frame.contains(start) {
+                    continue;
+                }
+                let text = match &item.kind {
+                    ItemKind::Tbtext(text) if copy > 0 => {
+                        Some(increment_label(&text.text, item.repeat.label_step * copy as i32))
+                    },
+                    ItemKind::Tbtext(text) => Some(text.text.clone()),
+                    _ => None,
+                };
+                placed.push(Placed { item, copy, start, end, text });
+            }
+        }
+        placed
+    }
+}
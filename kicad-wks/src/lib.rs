@@ -0,0 +1,147 @@
+//! Typed model of KiCad drawing sheet (`.kicad_wks`) files.
+
+mod item;
+mod layout;
+mod sheet;
+
+pub use kicad_common::Error;
+
+pub use item::{Bitmap, Coord, Corner, Item, ItemKind, Line, Pages, Polygon, Rect, Repeat, Tbtext, TextFont};
+pub use layout::{increment_label, Placed};
+pub use sheet::{DrawingSheet, Setup};
+
+#[cfg(test)]
+mod tests {
+    use kicad_common::{Color, HAlign, Point, Size, VAlign};
+
+    use super::*;
+
+    const DEMO: &str = include_str!("../../reference-files/demo/demo.kicad_wks");
+
+    const A4: Size = Size::new(297.0, 210.0);
+
+    fn text(item: &Item) -> &Tbtext {
+        match &item.kind {
+            ItemKind::Tbtext(text) => text,
+            other => panic!("expected a tbtext, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sheet() {
+        let sheet = DrawingSheet::parse(DEMO).unwrap();
+
+        assert_eq!(sheet.version, 20231118);
+        assert_eq!(sheet.generator, "pl_editor");
+        assert_eq!(sheet.setup, Setup::default());
+        assert!(sheet.check_version().is_ok());
+        assert_eq!(sheet.items.len(), 24);
+
+        let frame = &sheet.items[1];
+        assert_eq!(frame.kind, ItemKind::Rect(Line {
+            start: Coord::new(0.0, 0.0, Corner::LeftTop),
+            end: Coord::new(0.0, 0.0, Corner::RightBottom),
+            line_width: None,
+        }));
+        assert_eq!(frame.repeat, Repeat { count: 2, step: Point::new(2.0, 2.0), label_step: 1 });
+        assert_eq!(sheet.items[0].comment.as_deref(), Some("rect around the title block"));
+
+        let row = text(&sheet.items[5]);
+        assert_eq!((row.text.as_str(), row.horizontal, row.vertical), ("A", HAlign::Center, VAlign::Center));
+        let title = text(&sheet.items[14]);
+        assert_eq!(title.font.size, Some(Size::new(2.0, 2.0)));
+        assert!(title.font.bold && title.font.italic);
+        assert_eq!(title.horizontal, HAlign::Left);
+
+        let drawn = &sheet.items[20];
+        assert_eq!((drawn.name.as_str(), drawn.pages), ("drawn", Pages::FirstOnly));
+        let drawn = text(drawn);
+        assert_eq!((drawn.rotate, drawn.max_len, drawn.max_height), (90.0, Some(40.0), None));
+        assert_eq!(drawn.font.color, Some(Color { r: 200, g: 0, b: 0, a: 1.0 }));
+        assert_eq!((drawn.horizontal, drawn.vertical), (HAlign::Right, VAlign::Top));
+
+        let ItemKind::Polygon(logo) = &sheet.items[23].kind else { panic!("expected a polygon") };
+        assert_eq!(sheet.items[23].pages, Pages::NotFirst);
+        assert_eq!((logo.rotate, logo.outlines.len()), (45.0, 2));
+        assert_eq!(logo.outlines[1], [Point::new(5.0, 0.0), Point::new(7.0, 0.0), Point::new(6.0, 2.0)]);
+    }
+
+    #[test]
+    fn write() {
+        let sheet = DrawingSheet::parse(DEMO).unwrap();
+        assert_eq!(sheet.to_string(), DEMO);
+
+        let mut sheet = DrawingSheet::default();
+        let mut item = Item::new(ItemKind::Tbtext(Tbtext {
+            text: "${TITLE}".into(),
+            pos: Coord::new(5.0, 5.0, Corner::LeftTop),
+            ..Default::default()
+        }));
+        item.repeat = Repeat { count: 3, step: Point::new(0.0, 4.5), label_step: 2 };
+        sheet.items.push(item);
+        let written = sheet.to_string();
+        assert!(written.contains("\t(tbtext \"${TITLE}\"\n\t\t(name \"\")\n\t\t(pos 5 5 ltcorner)\n\t\t(repeat 3)\n"));
+        assert!(written.contains("\t\t(incry 4.5)\n\t\t(incrlabel 2)\n\t)\n"));
+        assert_eq!(DrawingSheet::parse(&written).unwrap(), sheet);
+    }
+
+    #[test]
+    fn layout() {
+        let sheet = DrawingSheet::parse(DEMO).unwrap();
+        let placed = sheet.layout(A4, true);
+
+        let frames: Vec<_> = placed.iter().filter(|p| p.item == &sheet.items[1]).map(|p| (p.start, p.end)).collect();
+        assert_eq!(
+            frames,
+            [
+                (Point::new(10.0, 10.0), Point::new(287.0, 200.0)),
+                (Point::new(12.0, 12.0), Point::new(285.0, 198.0)),
+            ]
+        );
+
+        // Column labels every 50 mm until they leave the frame.
+        let columns: Vec<_> = placed.iter().filter(|p| p.item == &sheet.items[3]).collect();
+        let labels: Vec<_> = columns.iter().map(|p| p.text.as_deref().unwrap()).collect();
+        assert_eq!(labels, ["1", "2", "3", "4", "5", "6"]);
+        assert_eq!(columns[5].start, Point::new(285.0, 11.0));
+        let rows: Vec<_> =
+            placed.iter().filter(|p| p.item == &sheet.items[5]).map(|p| p.text.as_deref().unwrap()).collect();
+        assert_eq!(rows, ["A", "B", "C", "D"]);
+
+        let date = placed.iter().find(|p| p.item == &sheet.items[6]).unwrap();
+        assert_eq!(date.start, Point::new(200.0, 193.1));
+
+        assert!(placed.iter().any(|p| p.item.name == "drawn"));
+        assert!(!placed.iter().any(|p| p.item.name == "logo"));
+        let later = sheet.layout(A4, false);
+        assert!(later.iter().any(|p| p.item.name == "logo") && !later.iter().any(|p| p.item.name == "drawn"));
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(increment_label("1", 1), "2");
+        assert_eq!(increment_label("9", 3), "12");
+        assert_eq!(increment_label("A", 2), "C");
+        assert_eq!(increment_label("Row A", 1), "Row B");
+        assert_eq!(increment_label("", 1), "");
+    }
+
+    #[test]
+    fn errors() {
+        let error = DrawingSheet::parse("(kicad_wks (version 1) (line (start 0 0 middle) (end 1 1)))").unwrap_err();
+        assert_eq!(error.to_string(), "kicad_wks > line > start: unknown corner `middle`");
+
+        let error = DrawingSheet::parse("(kicad_wks (version 1) (tbtext \"x\" (pos 0 0) (justify up)))").unwrap_err();
+        assert_eq!(error.to_string(), "kicad_wks > tbtext > justify: unknown justification `up`");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let sheet = DrawingSheet::parse(DEMO).unwrap();
+        let json = serde_json::to_string(&sheet).unwrap();
+
+        assert!(json.contains("\"LeftTop\""));
+        assert_eq!(serde_json::from_str::<DrawingSheet>(&json).unwrap(), sheet);
+    }
+}
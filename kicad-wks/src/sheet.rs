@@ -0,0 +1,144 @@
+use std::fmt;
+
+use kicad_common::{
+    convert::{check_version, parse_document, FromSexp, List, Result},
+    Error, Size,
+};
+use kicad_sexp::{SexpBuilder, SexpOwned};
+
+use crate::Item;
+
+/// Defaults and page margins from the `(setup ...)` list.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Setup {
+    pub text_size: Size,
+    pub line_width: f64,
+    pub text_line_width: f64,
+    pub left_margin: f64,
+    pub right_margin: f64,
+    pub top_margin: f64,
+    pub bottom_margin: f64,
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Setup {
+            text_size: Size::new(1.5, 1.5),
+            line_width: 0.15,
+            text_line_width: 0.15,
+            left_margin: 10.0,
+            right_margin: 10.0,
+            top_margin: 10.0,
+            bottom_margin: 10.0,
+        }
+    }
+}
+
+impl FromSexp for Setup {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let default = Setup::default();
+        Ok(Setup {
+            text_size: list.parse("textsize")?.unwrap_or(default.text_size),
+            line_width: list.child_number("linewidth")?.unwrap_or(default.line_width),
+            text_line_width: list.child_number("textlinewidth")?.unwrap_or(default.text_line_width),
+            left_margin: list.child_number("left_margin")?.unwrap_or(default.left_margin),
+            right_margin: list.child_number("right_margin")?.unwrap_or(default.right_margin),
+            top_margin: list.child_number("top_margin")?.unwrap_or(default.top_margin),
+            bottom_margin: list.child_number("bottom_margin")?.unwrap_or(default.bottom_margin),
+        })
+    }
+}
+
+impl Setup {
+    fn to_sexp(self) -> SexpBuilder {
+        let number = |tag: &str, value: f64| SexpBuilder::new(tag).float(value);
+        SexpBuilder::new("setup")
+            .child(SexpBuilder::new("textsize").float(self.text_size.width).float(self.text_size.height))
+            .child(number("linewidth", self.line_width))
+            .child(number("textlinewidth", self.text_line_width))
+            .child(number("left_margin", self.left_margin))
+            .child(number("right_margin", self.right_margin))
+            .child(number("top_margin", self.top_margin))
+            .child(number("bottom_margin", self.bottom_margin))
+    }
+}
+
+/// A `.kicad_wks` drawing sheet: the frame and title block drawn around
+/// schematic and board pages.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DrawingSheet {
+    /// File format version, a date like `20231118`.
+    pub version: u32,
+    pub generator: String,
+    pub generator_version: Option<String>,
+    pub setup: Setup,
+    pub items: Vec<Item>,
+}
+
+impl Default for DrawingSheet {
+    fn default() -> Self {
+        DrawingSheet {
+            version: Self::NEWEST_VERSION,
+            generator: "pl_editor".to_string(),
+            generator_version: None,
+            setup: Setup::default(),
+            items: Vec::new(),
+        }
+    }
+}
+
+impl DrawingSheet {
+    /// Newest file format version the model is written against, KiCad 9.
+    pub const NEWEST_VERSION: u32 = 20231118;
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(items)))]
+    pub fn parse(src: &str) -> std::result::Result<Self, Error> {
+        let sheet: DrawingSheet = parse_document(src, "kicad_wks")?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("items", sheet.items.len());
+        Ok(sheet)
+    }
+
+    /// Fails with [`Error::Version`] for files from a newer KiCad.
+    pub fn check_version(&self) -> std::result::Result<(), Error> {
+        check_version("kicad_wks", self.version, Self::NEWEST_VERSION)
+    }
+
+    /// The sheet as KiCad writes it; [`Display`](fmt::Display) formats the
+    /// whole file.
+    pub fn to_sexp(&self) -> SexpOwned {
+        let header = SexpBuilder::new("kicad_wks")
+            .child(SexpBuilder::new("version").int(self.version.into()))
+            .child(SexpBuilder::new("generator").string(&self.generator))
+            .child_opt(self.generator_version.as_deref().map(|v| SexpBuilder::new("generator_version").string(v)))
+            .child(self.setup.to_sexp());
+        self.items.iter().fold(header, |sheet, item| sheet.child(item.to_sexp())).build()
+    }
+}
+
+impl fmt::Display for DrawingSheet {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(items = self.items.len())))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_sexp())
+    }
+}
+
+impl FromSexp for DrawingSheet {
+    fn from_sexp(list: &List) -> Result<Self> {
+        let mut sheet = DrawingSheet {
+            version: list.child_number("version")?.ok_or_else(|| list.error("missing (version ...)"))?,
+            generator: list.child_string("generator")?.unwrap_or_default(),
+            generator_version: list.child_string("generator_version")?,
+            setup: list.parse("setup")?.unwrap_or_default(),
+            items: Vec::new(),
+        };
+        for item in list.lists() {
+            if Item::is_item_tag(item.tag()) {
+                sheet.items.push(Item::from_sexp(&item).map_err(|e| e.within(list.tag()))?);
+            }
+        }
+        Ok(sheet)
+    }
+}